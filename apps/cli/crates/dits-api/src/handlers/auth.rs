@@ -1,8 +1,11 @@
 //! Authentication handlers.
 
 use axum::Json;
+use dits_core::User;
 use serde::{Deserialize, Serialize};
 
+use crate::middleware::AuthUser;
+
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub username: String,
@@ -41,8 +44,7 @@ pub async fn refresh() -> Json<TokenResponse> {
     })
 }
 
-/// Get current user info.
-pub async fn me() -> &'static str {
-    // TODO: Return current user
-    "TODO"
+/// Get the authenticated user.
+pub async fn me(AuthUser(user): AuthUser) -> Json<User> {
+    Json(user)
 }
@@ -1,23 +1,117 @@
 //! Branch handlers.
 
-use axum::{extract::Path, Json};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use dits_core::{Error, Hash};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::middleware::AuthUser;
+use crate::repo::{
+    branch_ref, find_readable_repo, find_writable_repo, resolve_rev_or_default, validate_name, HEADS_PREFIX,
+};
+use crate::state::AppState;
+
+#[derive(Serialize)]
+pub struct BranchResponse {
+    pub name: String,
+    pub head: Hash,
+    pub is_default: bool,
+}
+
+#[derive(Deserialize)]
+pub struct CreateBranchRequest {
+    pub name: String,
+    /// Branch, tag or commit to start from (default branch if omitted).
+    pub from: Option<String>,
+}
 
 /// List branches.
-pub async fn list(Path((_owner, _name)): Path<(String, String)>) -> Json<Vec<()>> {
-    Json(vec![])
+pub async fn list(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    user: Option<AuthUser>,
+) -> Result<Json<Vec<BranchResponse>>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let refs = state.db().list_refs(repo.id, HEADS_PREFIX).await?;
+    Ok(Json(
+        refs.into_iter()
+            .map(|(full, head)| {
+                let name = full.trim_start_matches(HEADS_PREFIX).to_string();
+                BranchResponse {
+                    is_default: name == repo.default_branch,
+                    name,
+                    head,
+                }
+            })
+            .collect(),
+    ))
 }
 
 /// Create branch.
-pub async fn create(Path((_owner, _name)): Path<(String, String)>) -> &'static str {
-    "TODO"
+pub async fn create(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    AuthUser(user): AuthUser,
+    Json(body): Json<CreateBranchRequest>,
+) -> Result<(StatusCode, Json<BranchResponse>), ApiError> {
+    let repo = find_writable_repo(&state, &owner, &name, &user).await?;
+    validate_name(&body.name)?;
+    let head = resolve_rev_or_default(&state, &repo, body.from.as_deref()).await?;
+
+    match state.db().update_ref(repo.id, &branch_ref(&body.name), None, Some(head)).await {
+        Err(Error::RefConflict(_)) => return Err(Error::BranchAlreadyExists(body.name).into()),
+        result => result?,
+    }
+
+    Ok((
+        StatusCode::CREATED,
+        Json(BranchResponse {
+            is_default: body.name == repo.default_branch,
+            name: body.name,
+            head,
+        }),
+    ))
 }
 
 /// Get branch.
-pub async fn get(Path((_owner, _name, _branch)): Path<(String, String, String)>) -> &'static str {
-    "TODO"
+pub async fn get(
+    State(state): State<AppState>,
+    Path((owner, name, branch)): Path<(String, String, String)>,
+    user: Option<AuthUser>,
+) -> Result<Json<BranchResponse>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let head = state
+        .db()
+        .get_ref(repo.id, &branch_ref(&branch))
+        .await?
+        .ok_or_else(|| Error::BranchNotFound(branch.clone()))?;
+    Ok(Json(BranchResponse {
+        is_default: branch == repo.default_branch,
+        name: branch,
+        head,
+    }))
 }
 
-/// Delete branch.
-pub async fn delete(Path((_owner, _name, _branch)): Path<(String, String, String)>) -> &'static str {
-    "TODO"
+/// Delete branch. The default branch cannot be deleted.
+pub async fn delete(
+    State(state): State<AppState>,
+    Path((owner, name, branch)): Path<(String, String, String)>,
+    AuthUser(user): AuthUser,
+) -> Result<StatusCode, ApiError> {
+    let repo = find_writable_repo(&state, &owner, &name, &user).await?;
+    if branch == repo.default_branch {
+        return Err(Error::CannotDeleteCurrentBranch.into());
+    }
+    let full = branch_ref(&branch);
+    let head = state
+        .db()
+        .get_ref(repo.id, &full)
+        .await?
+        .ok_or_else(|| Error::BranchNotFound(branch.clone()))?;
+    state.db().update_ref(repo.id, &full, Some(head), None).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -1,20 +1,122 @@
 //! Chunk handlers.
 
-use axum::extract::Path;
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use dits_core::{Error, Hash, Hasher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
-/// Upload chunk.
-pub async fn upload(Path((_owner, _name)): Path<(String, String)>) -> &'static str {
-    "TODO"
+use crate::error::ApiError;
+use crate::middleware::AuthUser;
+use crate::repo::{chunk_key, find_readable_repo, find_writable_repo, parse_hash};
+use crate::state::AppState;
+
+/// Largest chunk accepted by the upload endpoint.
+pub const MAX_CHUNK_SIZE: usize = 64 * 1024 * 1024;
+
+/// Most hashes accepted by one batch existence check.
+const MAX_BATCH: usize = 10_000;
+
+#[derive(Deserialize)]
+pub struct UploadQuery {
+    /// Expected hash; the upload is rejected if the content does not match.
+    pub hash: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct UploadResponse {
+    pub hash: Hash,
+    pub size: u64,
+}
+
+#[derive(Deserialize)]
+pub struct BatchCheckRequest {
+    pub hashes: Vec<Hash>,
+}
+
+#[derive(Serialize)]
+pub struct BatchCheckResponse {
+    pub present: Vec<Hash>,
+    pub missing: Vec<Hash>,
+}
+
+/// Upload chunk. The chunk is addressed by the BLAKE3 hash of the body.
+pub async fn upload(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    AuthUser(user): AuthUser,
+    Query(query): Query<UploadQuery>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<UploadResponse>), ApiError> {
+    let repo = find_writable_repo(&state, &owner, &name, &user).await?;
+    let hash = Hasher::hash(&body);
+    if let Some(expected) = &query.hash {
+        if parse_hash(expected)? != hash {
+            return Err(Error::ChunkCorrupted {
+                expected: expected.clone(),
+                actual: hash.to_hex(),
+            }
+            .into());
+        }
+    }
+
+    let key = chunk_key(&hash);
+    let size = body.len() as u64;
+    let stored = state.storage().exists(&key).await?;
+    if !stored {
+        state.storage().put(&key, body.clone()).await?;
+    }
+    state.db().insert_chunk(repo.id, hash, size).await?;
+    // Garbage collection may have deleted the stored object before the
+    // reference was recorded; once it is recorded, the object stays.
+    if stored && !state.storage().exists(&key).await? {
+        state.storage().put(&key, body).await?;
+    }
+
+    Ok((StatusCode::CREATED, Json(UploadResponse { hash, size })))
 }
 
 /// Download chunk.
 pub async fn download(
-    Path((_owner, _name, _hash)): Path<(String, String, String)>,
-) -> &'static str {
-    "TODO"
+    State(state): State<AppState>,
+    Path((owner, name, hash)): Path<(String, String, String)>,
+    user: Option<AuthUser>,
+) -> Result<impl IntoResponse, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let hash = parse_hash(&hash)?;
+    if !state.db().missing_chunks(repo.id, &[hash]).await?.is_empty() {
+        return Err(Error::ChunkNotFound(hash.to_hex()).into());
+    }
+    let data = state.storage().get(&chunk_key(&hash)).await?;
+    Ok(([(header::CONTENT_TYPE, "application/octet-stream")], data))
 }
 
 /// Batch check chunks existence.
-pub async fn batch_check(Path((_owner, _name)): Path<(String, String)>) -> &'static str {
-    "TODO"
+pub async fn batch_check(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    user: Option<AuthUser>,
+    Json(body): Json<BatchCheckRequest>,
+) -> Result<Json<BatchCheckResponse>, ApiError> {
+    if body.hashes.len() > MAX_BATCH {
+        return Err(ApiError::bad_request(format!(
+            "at most {} hashes per batch",
+            MAX_BATCH
+        )));
+    }
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let missing = state.db().missing_chunks(repo.id, &body.hashes).await?;
+    let missing_set: HashSet<&Hash> = missing.iter().collect();
+    let present = body
+        .hashes
+        .iter()
+        .filter(|h| !missing_set.contains(h))
+        .copied()
+        .collect();
+    Ok(Json(BatchCheckResponse { present, missing }))
 }
@@ -1,13 +1,131 @@
 //! Commit handlers.
 
-use axum::{extract::Path, Json};
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use dits_core::{Commit, Cursor, Error, Hash, Page};
+use serde::Deserialize;
+use std::collections::{BinaryHeap, HashSet};
 
-/// List commits.
-pub async fn list(Path((_owner, _name)): Path<(String, String)>) -> Json<Vec<()>> {
-    Json(vec![])
+use crate::error::ApiError;
+use crate::middleware::AuthUser;
+use crate::repo::{branch_ref, find_readable_repo, parse_hash, resolve_rev};
+use crate::state::AppState;
+
+/// Default and maximum page sizes for commit listings.
+const DEFAULT_LIMIT: usize = 30;
+const MAX_LIMIT: usize = 100;
+
+#[derive(Deserialize)]
+pub struct ListCommitsQuery {
+    /// Branch, tag or commit to start from (default branch if omitted).
+    #[serde(rename = "ref")]
+    pub rev: Option<String>,
+    pub limit: Option<usize>,
+    /// Opaque cursor from a previous page.
+    pub cursor: Option<String>,
+}
+
+/// Commit ordered by timestamp for history walks, newest first.
+struct ByDate(Commit);
+
+impl PartialEq for ByDate {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.hash == other.0.hash
+    }
+}
+
+impl Eq for ByDate {}
+
+impl PartialOrd for ByDate {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ByDate {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.0.created_at, self.0.hash).cmp(&(other.0.created_at, other.0.hash))
+    }
+}
+
+/// List commits reachable from a ref, newest first.
+pub async fn list(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    user: Option<AuthUser>,
+    Query(query): Query<ListCommitsQuery>,
+) -> Result<Json<Page<Commit>>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let offset = match &query.cursor {
+        Some(cursor) => cursor
+            .parse::<usize>()
+            .map_err(|_| ApiError::bad_request("invalid cursor"))?,
+        None => 0,
+    };
+
+    let start = match &query.rev {
+        Some(rev) => resolve_rev(&state, &repo, rev).await?,
+        None => match state.db().get_ref(repo.id, &branch_ref(&repo.default_branch)).await? {
+            Some(hash) => hash,
+            // A repository without commits has an empty history.
+            None => return Ok(Json(Page::empty())),
+        },
+    };
+
+    let mut heap = BinaryHeap::new();
+    let mut seen = HashSet::new();
+    enqueue(&state, repo.id, &start, &mut seen, &mut heap).await?;
+
+    let mut items = Vec::with_capacity(limit);
+    let mut position = 0;
+    while let Some(ByDate(commit)) = heap.pop() {
+        for parent in &commit.parents {
+            enqueue(&state, repo.id, parent, &mut seen, &mut heap).await?;
+        }
+        if position >= offset {
+            if items.len() == limit {
+                let mut page = Page::new(items, true);
+                page.next_cursor = Some(Cursor::new((offset + limit).to_string()));
+                return Ok(Json(page));
+            }
+            items.push(commit);
+        }
+        position += 1;
+    }
+
+    Ok(Json(Page::new(items, false)))
+}
+
+async fn enqueue(
+    state: &AppState,
+    repo: uuid::Uuid,
+    hash: &Hash,
+    seen: &mut HashSet<Hash>,
+    heap: &mut BinaryHeap<ByDate>,
+) -> Result<(), ApiError> {
+    if seen.insert(*hash) {
+        if let Some(commit) = state.db().get_commit(repo, hash).await? {
+            heap.push(ByDate(commit));
+        }
+    }
+    Ok(())
 }
 
 /// Get commit by SHA.
-pub async fn get(Path((_owner, _name, _sha)): Path<(String, String, String)>) -> &'static str {
-    "TODO"
+pub async fn get(
+    State(state): State<AppState>,
+    Path((owner, name, sha)): Path<(String, String, String)>,
+    user: Option<AuthUser>,
+) -> Result<Json<Commit>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let hash = parse_hash(&sha)?;
+    let commit = state
+        .db()
+        .get_commit(repo.id, &hash)
+        .await?
+        .ok_or(Error::CommitNotFound(sha))?;
+    Ok(Json(commit))
 }
@@ -1,13 +1,214 @@
 //! File and tree handlers.
 
-use axum::extract::Path;
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use dits_core::{commit::TreeEntry, Error, Hash};
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::error::ApiError;
+use crate::middleware::AuthUser;
+use crate::repo::{chunk_key, commit_manifest, find_readable_repo, resolve_rev_or_default};
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct RefQuery {
+    /// Branch, tag or commit to read from (default branch if omitted).
+    #[serde(rename = "ref")]
+    pub rev: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TreeResponse {
+    pub commit: Hash,
+    pub path: String,
+    pub entries: Vec<TreeEntry>,
+}
+
+/// Get the root directory tree.
+pub async fn root_tree(
+    state: State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    user: Option<AuthUser>,
+    query: Query<RefQuery>,
+) -> Result<Json<TreeResponse>, ApiError> {
+    tree(state, Path((owner, name, String::new())), user, query).await
+}
 
 /// Get directory tree.
-pub async fn tree(Path((_owner, _name, _path)): Path<(String, String, String)>) -> &'static str {
-    "TODO"
+pub async fn tree(
+    State(state): State<AppState>,
+    Path((owner, name, path)): Path<(String, String, String)>,
+    user: Option<AuthUser>,
+    Query(query): Query<RefQuery>,
+) -> Result<Json<TreeResponse>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let commit = resolve_rev_or_default(&state, &repo, query.rev.as_deref()).await?;
+    let manifest = commit_manifest(&state, &repo, &commit).await?;
+
+    let path = path.trim_matches('/').to_string();
+    if manifest.get(&path).is_some() {
+        return Err(ApiError::bad_request(format!("{} is a file", path)));
+    }
+    let entries = manifest
+        .list_dir(&path)
+        .ok_or_else(|| Error::FileNotFound(path.clone()))?;
+
+    Ok(Json(TreeResponse { commit, path, entries }))
 }
 
 /// Get file blob.
-pub async fn blob(Path((_owner, _name, _path)): Path<(String, String, String)>) -> &'static str {
-    "TODO"
+///
+/// The file is reassembled from its chunks as it is streamed. A single
+/// `Range: bytes=...` request is honoured; other range forms get the whole file.
+pub async fn blob(
+    State(state): State<AppState>,
+    Path((owner, name, path)): Path<(String, String, String)>,
+    user: Option<AuthUser>,
+    Query(query): Query<RefQuery>,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let commit = resolve_rev_or_default(&state, &repo, query.rev.as_deref()).await?;
+    let manifest = commit_manifest(&state, &repo, &commit).await?;
+
+    let path = path.trim_matches('/');
+    let entry = manifest
+        .get(path)
+        .ok_or_else(|| Error::FileNotFound(path.to_string()))?;
+
+    let size = entry.size;
+    let range = match headers.get(header::RANGE).and_then(|v| v.to_str().ok()) {
+        Some(value) => match parse_range(value, size) {
+            Ok(range) => range,
+            Err(()) => {
+                return Ok((
+                    StatusCode::RANGE_NOT_SATISFIABLE,
+                    [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+                )
+                    .into_response());
+            }
+        },
+        None => None,
+    };
+    let (start, end) = range.unwrap_or((0, size));
+
+    // Slices of each chunk overlapping [start, end).
+    let pieces: Vec<(Hash, usize, usize)> = entry
+        .chunks
+        .iter()
+        .filter(|c| c.offset < end && c.offset + c.size > start)
+        .map(|c| {
+            let lo = start.saturating_sub(c.offset);
+            let hi = (end - c.offset).min(c.size);
+            (c.hash, lo as usize, hi as usize)
+        })
+        .collect();
+
+    let hashes: Vec<Hash> = pieces.iter().map(|(hash, _, _)| *hash).collect();
+    if let Some(missing) = state.db().missing_chunks(repo.id, &hashes).await?.first() {
+        return Err(Error::ChunkNotFound(missing.to_hex()).into());
+    }
+
+    let storage = state.storage().clone();
+    let body = stream::iter(pieces).then(move |(hash, lo, hi)| {
+        let storage = storage.clone();
+        async move {
+            let data = storage
+                .get_range(&chunk_key(&hash), lo as u64..hi as u64)
+                .await?;
+            if data.len() != hi - lo {
+                return Err(Error::ChunkCorrupted {
+                    expected: format!("{} bytes", hi - lo),
+                    actual: format!("{} bytes", data.len()),
+                });
+            }
+            Ok(data)
+        }
+    });
+
+    let mut response = Response::new(Body::from_stream(body));
+    if range.is_some() {
+        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+    }
+    let response_headers = response.headers_mut();
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/octet-stream"));
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(end - start));
+    if let Ok(etag) = HeaderValue::from_str(&format!("\"{}\"", entry.content_hash.to_hex())) {
+        response_headers.insert(header::ETAG, etag);
+    }
+    if range.is_some() {
+        if let Ok(content_range) =
+            HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end - 1, size))
+        {
+            response_headers.insert(header::CONTENT_RANGE, content_range);
+        }
+    }
+    Ok(response)
+}
+
+/// Parse a `Range` header against a body of `size` bytes.
+///
+/// Returns the half-open span to serve, `Ok(None)` for forms that should be
+/// ignored (multiple ranges, other units), or `Err` if unsatisfiable.
+fn parse_range(value: &str, size: u64) -> Result<Option<(u64, u64)>, ()> {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let Some((first, last)) = spec.split_once('-') else {
+        return Ok(None);
+    };
+    let (first, last) = (first.trim(), last.trim());
+
+    let span = if first.is_empty() {
+        // Suffix range: the last N bytes.
+        let n: u64 = last.parse().map_err(|_| ())?;
+        if n == 0 {
+            return Err(());
+        }
+        (size.saturating_sub(n), size)
+    } else {
+        let start: u64 = first.parse().map_err(|_| ())?;
+        let end = if last.is_empty() {
+            size
+        } else {
+            let last: u64 = last.parse().map_err(|_| ())?;
+            if last < start {
+                return Err(());
+            }
+            last.saturating_add(1).min(size)
+        };
+        (start, end)
+    };
+
+    if span.0 >= size {
+        return Err(());
+    }
+    Ok(Some(span))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-9", 100), Ok(Some((0, 10))));
+        assert_eq!(parse_range("bytes=90-", 100), Ok(Some((90, 100))));
+        assert_eq!(parse_range("bytes=-10", 100), Ok(Some((90, 100))));
+        assert_eq!(parse_range("bytes=50-500", 100), Ok(Some((50, 100))));
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), Ok(None));
+        assert_eq!(parse_range("items=0-1", 100), Ok(None));
+        assert_eq!(parse_range("bytes=100-", 100), Err(()));
+        assert_eq!(parse_range("bytes=5-2", 100), Err(()));
+    }
 }
@@ -1,18 +1,108 @@
 //! Lock handlers.
 
-use axum::{extract::Path, Json};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use dits_core::{Error, Lock};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::middleware::AuthUser;
+use crate::repo::{find_readable_repo, find_writable_repo, notify};
+use crate::state::AppState;
+
+/// Lock lifetime when the request does not ask for one.
+const DEFAULT_LOCK_TTL_SECS: i64 = 24 * 60 * 60;
+
+#[derive(Serialize)]
+pub struct LockResponse {
+    pub id: Uuid,
+    pub path: String,
+    pub owner: String,
+    pub locked_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+impl From<Lock> for LockResponse {
+    fn from(lock: Lock) -> Self {
+        Self {
+            id: lock.id,
+            path: lock.path,
+            owner: lock.owner.name,
+            locked_at: lock.locked_at,
+            expires_at: lock.expires_at,
+            reason: lock.reason,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AcquireLockRequest {
+    pub path: String,
+    pub reason: Option<String>,
+    /// Lock lifetime in seconds.
+    pub ttl_secs: Option<i64>,
+}
+
+/// Webhook payload for lock events.
+fn lock_event(repository: &str, lock: &Lock) -> serde_json::Value {
+    json!({
+        "repository": repository,
+        "lock": { "id": lock.id, "path": lock.path, "owner": lock.owner.name },
+    })
+}
 
 /// List locks.
-pub async fn list(Path((_owner, _name)): Path<(String, String)>) -> Json<Vec<()>> {
-    Json(vec![])
+pub async fn list(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    user: Option<AuthUser>,
+) -> Result<Json<Vec<LockResponse>>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let locks = state.db().list_locks(repo.id).await?;
+    Ok(Json(locks.into_iter().map(LockResponse::from).collect()))
 }
 
-/// Acquire lock.
-pub async fn acquire(Path((_owner, _name)): Path<(String, String)>) -> &'static str {
-    "TODO"
+/// Acquire lock as the authenticated user.
+pub async fn acquire(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    AuthUser(user): AuthUser,
+    Json(body): Json<AcquireLockRequest>,
+) -> Result<(StatusCode, Json<LockResponse>), ApiError> {
+    let repo = find_writable_repo(&state, &owner, &name, &user).await?;
+    let path = body.path.trim_matches('/').to_string();
+    if path.is_empty() || path.split('/').any(|c| c.is_empty() || c == "." || c == "..") {
+        return Err(Error::InvalidPath(body.path).into());
+    }
+    let ttl = body.ttl_secs.unwrap_or(DEFAULT_LOCK_TTL_SECS);
+    if ttl <= 0 {
+        return Err(ApiError::bad_request("ttl_secs must be positive"));
+    }
+
+    let mut lock = Lock::with_duration(path, user, repo.id, Duration::seconds(ttl));
+    lock.reason = body.reason;
+
+    let lock = state.db().acquire_lock(lock).await?;
+    notify(&state, &repo, "lock", lock_event(&repo.name, &lock)).await;
+    Ok((StatusCode::CREATED, Json(LockResponse::from(lock))))
 }
 
-/// Release lock.
-pub async fn release(Path((_owner, _name, _id)): Path<(String, String, String)>) -> &'static str {
-    "TODO"
+/// Release lock. Only the lock owner may release it.
+pub async fn release(
+    State(state): State<AppState>,
+    Path((owner, name, id)): Path<(String, String, String)>,
+    AuthUser(user): AuthUser,
+) -> Result<StatusCode, ApiError> {
+    let repo = find_writable_repo(&state, &owner, &name, &user).await?;
+    let id = Uuid::parse_str(&id).map_err(|_| ApiError::bad_request("invalid lock id"))?;
+    let lock = state.db().release_lock(repo.id, id, user.id).await?;
+    notify(&state, &repo, "unlock", lock_event(&repo.name, &lock)).await;
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -1,60 +1,182 @@
 //! Repository handlers.
 
-use axum::{extract::Path, Json};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use dits_core::{repository::Visibility, Repository, Webhook};
 use serde::{Deserialize, Serialize};
 
+use crate::error::ApiError;
+use crate::middleware::AuthUser;
+use crate::repo::{branch_ref, can_read, find_readable_repo, find_repo, find_writable_repo, validate_name};
+use crate::state::AppState;
+
 #[derive(Serialize)]
 pub struct RepoResponse {
+    pub owner: String,
     pub name: String,
     pub description: Option<String>,
     pub default_branch: String,
+    pub private: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&Repository> for RepoResponse {
+    fn from(repo: &Repository) -> Self {
+        Self {
+            owner: repo.owner().to_string(),
+            name: repo.repo_name().to_string(),
+            description: repo.description.clone(),
+            default_branch: repo.default_branch.clone(),
+            private: !repo.is_public(),
+            created_at: repo.created_at,
+            updated_at: repo.updated_at,
+        }
+    }
 }
 
 #[derive(Deserialize)]
 pub struct CreateRepoRequest {
+    pub owner: String,
     pub name: String,
     pub description: Option<String>,
+    #[serde(default)]
     pub private: bool,
 }
 
-/// List repositories.
-pub async fn list() -> Json<Vec<RepoResponse>> {
-    // TODO: Implement
-    Json(vec![])
+#[derive(Deserialize)]
+pub struct UpdateRepoRequest {
+    pub description: Option<String>,
+    pub default_branch: Option<String>,
+    pub private: Option<bool>,
+    /// Replaces the repository's webhooks.
+    pub webhooks: Option<Vec<Webhook>>,
+    /// Replaces the repository's members. Only the owner may change them.
+    pub members: Option<Vec<String>>,
+}
+
+fn visibility(private: bool) -> Visibility {
+    if private {
+        Visibility::Private
+    } else {
+        Visibility::Public
+    }
 }
 
-/// Create a new repository.
-pub async fn create(Json(_body): Json<CreateRepoRequest>) -> Json<RepoResponse> {
-    // TODO: Implement
-    Json(RepoResponse {
-        name: "todo".to_string(),
-        description: None,
-        default_branch: "main".to_string(),
-    })
+/// List the repositories the caller may read.
+pub async fn list(
+    State(state): State<AppState>,
+    user: Option<AuthUser>,
+) -> Result<Json<Vec<RepoResponse>>, ApiError> {
+    let repos = state.db().list_repositories().await?;
+    Ok(Json(
+        repos
+            .iter()
+            .filter(|repo| can_read(repo, user.as_ref().map(|AuthUser(user)| user)))
+            .map(RepoResponse::from)
+            .collect(),
+    ))
+}
+
+/// Create a new repository owned by the caller.
+pub async fn create(
+    State(state): State<AppState>,
+    AuthUser(user): AuthUser,
+    Json(body): Json<CreateRepoRequest>,
+) -> Result<(StatusCode, Json<RepoResponse>), ApiError> {
+    validate_name(&body.owner)?;
+    validate_name(&body.name)?;
+    if body.owner != user.name {
+        return Err(ApiError::forbidden(format!(
+            "{} cannot create repositories for {}",
+            user.name, body.owner
+        )));
+    }
+
+    let mut repo = Repository::new(format!("{}/{}", body.owner, body.name));
+    repo.description = body.description;
+    repo.visibility = visibility(body.private);
+
+    let repo = state.db().create_repository(repo).await?;
+    Ok((StatusCode::CREATED, Json(RepoResponse::from(&repo))))
 }
 
 /// Get repository by owner and name.
-pub async fn get(Path((_owner, _name)): Path<(String, String)>) -> Json<RepoResponse> {
-    // TODO: Implement
-    Json(RepoResponse {
-        name: "todo".to_string(),
-        description: None,
-        default_branch: "main".to_string(),
-    })
+pub async fn get(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    user: Option<AuthUser>,
+) -> Result<Json<RepoResponse>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    Ok(Json(RepoResponse::from(&repo)))
 }
 
 /// Update repository.
-pub async fn update(Path((_owner, _name)): Path<(String, String)>) -> Json<RepoResponse> {
-    // TODO: Implement
-    Json(RepoResponse {
-        name: "todo".to_string(),
-        description: None,
-        default_branch: "main".to_string(),
-    })
+pub async fn update(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    AuthUser(user): AuthUser,
+    Json(body): Json<UpdateRepoRequest>,
+) -> Result<Json<RepoResponse>, ApiError> {
+    let mut repo = find_writable_repo(&state, &owner, &name, &user).await?;
+
+    if let Some(branch) = body.default_branch {
+        // Allow pointing at a branch that does not exist yet only while the
+        // repository is still empty.
+        let exists = state.db().get_ref(repo.id, &branch_ref(&branch)).await?.is_some();
+        let empty = state.db().list_refs(repo.id, "").await?.is_empty();
+        if !exists && !empty {
+            return Err(dits_core::Error::BranchNotFound(branch).into());
+        }
+        repo.default_branch = branch;
+    }
+    if let Some(description) = body.description {
+        repo.description = Some(description);
+    }
+    if let Some(private) = body.private {
+        repo.visibility = visibility(private);
+    }
+    if let Some(webhooks) = body.webhooks {
+        if let Some(hook) = webhooks
+            .iter()
+            .find(|h| !h.url.starts_with("http://") && !h.url.starts_with("https://"))
+        {
+            return Err(ApiError::bad_request(format!("invalid webhook URL: {}", hook.url)));
+        }
+        repo.settings.webhooks = webhooks;
+    }
+    if let Some(members) = body.members {
+        if user.name != repo.owner() {
+            return Err(ApiError::forbidden(format!(
+                "only {} can change the members of {}",
+                repo.owner(),
+                repo.name
+            )));
+        }
+        for member in &members {
+            validate_name(member)?;
+        }
+        repo.settings.members = members;
+    }
+
+    state.db().update_repository(&repo).await?;
+    let repo = find_repo(&state, &owner, &name).await?;
+    Ok(Json(RepoResponse::from(&repo)))
 }
 
 /// Delete repository.
-pub async fn delete(Path((_owner, _name)): Path<(String, String)>) -> &'static str {
-    // TODO: Implement
-    "OK"
+///
+/// Chunks stay in object storage until the cleanup job finds them unreferenced.
+pub async fn delete(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    AuthUser(user): AuthUser,
+) -> Result<StatusCode, ApiError> {
+    let repo = find_writable_repo(&state, &owner, &name, &user).await?;
+    state.db().delete_repository(repo.id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
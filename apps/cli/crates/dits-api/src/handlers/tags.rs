@@ -1,13 +1,57 @@
 //! Tag handlers.
 
-use axum::{extract::Path, Json};
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::Utc;
+use dits_core::{Author, Tag};
+use serde::Deserialize;
+
+use crate::error::ApiError;
+use crate::middleware::AuthUser;
+use crate::repo::{find_readable_repo, find_writable_repo, resolve_rev, validate_name};
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct CreateTagRequest {
+    pub name: String,
+    /// Branch or commit to tag.
+    pub target: String,
+    /// Message, making this an annotated tag.
+    pub message: Option<String>,
+    pub tagger: Option<Author>,
+}
 
 /// List tags.
-pub async fn list(Path((_owner, _name)): Path<(String, String)>) -> Json<Vec<()>> {
-    Json(vec![])
+pub async fn list(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    user: Option<AuthUser>,
+) -> Result<Json<Vec<Tag>>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    Ok(Json(state.db().list_tags(repo.id).await?))
 }
 
 /// Create tag.
-pub async fn create(Path((_owner, _name)): Path<(String, String)>) -> &'static str {
-    "TODO"
+pub async fn create(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    AuthUser(user): AuthUser,
+    Json(body): Json<CreateTagRequest>,
+) -> Result<(StatusCode, Json<Tag>), ApiError> {
+    let repo = find_writable_repo(&state, &owner, &name, &user).await?;
+    validate_name(&body.name)?;
+    let commit = resolve_rev(&state, &repo, &body.target).await?;
+
+    let tag = Tag {
+        name: body.name,
+        commit,
+        message: body.message,
+        tagger: body.tagger,
+        created_at: Utc::now(),
+    };
+    state.db().create_tag(repo.id, tag.clone()).await?;
+    Ok((StatusCode::CREATED, Json(tag)))
 }
@@ -1,18 +1,406 @@
 //! Push/Pull transfer handlers.
+//!
+//! Chunks are uploaded separately through the chunk endpoints. A push then
+//! sends the manifests and commits that reference them and the ref updates
+//! to apply; it is rejected if anything it references is missing, or if
+//! it changes a file locked by someone other than the authenticated pusher.
 
-use axum::extract::Path;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use dits_core::{Commit, Error, Hash, Job, Manifest, Repository};
+use dits_db::RefChange;
+use serde_json::json;
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::error::ApiError;
+use crate::middleware::AuthUser;
+use crate::repo::{
+    branch_ref, enqueue, find_readable_repo, find_writable_repo, is_ancestor, load_manifest, notify,
+    store_manifest, validate_name, HEADS_PREFIX,
+};
+use crate::state::AppState;
+
+/// Tag refs as reported by fetch.
+const TAGS_PREFIX: &str = "refs/tags/";
+
+/// Largest push request body (manifests and commits; chunks go separately).
+pub const MAX_PUSH_SIZE: usize = 256 * 1024 * 1024;
+
+#[derive(Deserialize)]
+pub struct PushRequest {
+    #[serde(default)]
+    pub commits: Vec<Commit>,
+    #[serde(default)]
+    pub manifests: Vec<Manifest>,
+    pub refs: Vec<RefUpdate>,
+}
+
+#[derive(Deserialize)]
+pub struct RefUpdate {
+    /// Branch name or full ref name.
+    pub name: String,
+    /// Value the client expects the ref to have (`None` if it should not exist).
+    pub old: Option<Hash>,
+    /// New value (`None` deletes the ref).
+    pub new: Option<Hash>,
+    /// Allow a non-fast-forward update.
+    #[serde(default)]
+    pub force: bool,
+}
+
+#[derive(Serialize)]
+pub struct PushResponse {
+    /// Refs after the push.
+    pub refs: BTreeMap<String, Option<Hash>>,
+}
+
+#[derive(Serialize)]
+pub struct FetchResponse {
+    pub default_branch: String,
+    pub branches: BTreeMap<String, Hash>,
+    pub tags: BTreeMap<String, Hash>,
+}
+
+#[derive(Deserialize)]
+pub struct PullRequest {
+    /// Commits the client wants.
+    pub want: Vec<Hash>,
+    /// Commits the client already has; history walks stop here.
+    #[serde(default)]
+    pub have: Vec<Hash>,
+}
+
+#[derive(Serialize)]
+pub struct PullResponse {
+    /// Commits the client is missing, children before parents.
+    pub commits: Vec<Commit>,
+    pub manifests: Vec<Manifest>,
+    /// Every chunk referenced by the returned manifests.
+    pub chunks: Vec<Hash>,
+}
+
+/// Full name of a pushed ref: a branch name, or a full branch or tag ref.
+fn full_ref_name(name: &str) -> Result<String, ApiError> {
+    match name.strip_prefix(HEADS_PREFIX).or_else(|| name.strip_prefix(TAGS_PREFIX)) {
+        Some(short) => {
+            validate_name(short)?;
+            Ok(name.to_string())
+        }
+        None if name.starts_with("refs/") => Err(ApiError::bad_request(format!("cannot push to {}", name))),
+        None => {
+            validate_name(name)?;
+            Ok(branch_ref(name))
+        }
+    }
+}
+
+/// Paths the ref updates of a push change.
+///
+/// A moved ref changes whatever differs between its old and new tree, so
+/// forced rewinds and moves to existing commits count too. A created ref
+/// changes what the pushed commits change relative to their parents.
+///
+/// `trees` holds the manifests sent with the push; others are loaded from
+/// storage as needed.
+async fn ref_changes(
+    state: &AppState,
+    repo: &Repository,
+    refs: &[RefUpdate],
+    commits: &[Commit],
+    mut trees: HashMap<Hash, Manifest>,
+) -> Result<BTreeSet<String>, ApiError> {
+    let pushed: HashMap<Hash, Hash> = commits.iter().map(|c| (c.hash, c.tree)).collect();
+    let mut changed = BTreeSet::new();
+    let mut created = false;
+
+    for update in refs {
+        match (update.old, update.new) {
+            (Some(old), Some(new)) => {
+                let old = commit_tree(state, repo, &pushed, &old).await?;
+                let new = commit_tree(state, repo, &pushed, &new).await?;
+                for hash in [old, new] {
+                    if let Entry::Vacant(entry) = trees.entry(hash) {
+                        entry.insert(load_manifest(state, &hash).await?);
+                    }
+                }
+                diff_paths(&trees[&old], &trees[&new], &mut changed);
+            }
+            (None, Some(_)) => created = true,
+            _ => {}
+        }
+    }
+
+    if created {
+        changed.extend(changed_paths(state, repo, commits, &pushed, trees).await?);
+    }
+    Ok(changed)
+}
+
+/// Paths the commits add, change or remove relative to their parents.
+async fn changed_paths(
+    state: &AppState,
+    repo: &Repository,
+    commits: &[Commit],
+    pushed: &HashMap<Hash, Hash>,
+    mut trees: HashMap<Hash, Manifest>,
+) -> Result<BTreeSet<String>, ApiError> {
+    let mut changed = BTreeSet::new();
+
+    for commit in commits {
+        let mut parent_trees = Vec::new();
+        for parent in &commit.parents {
+            parent_trees.push(commit_tree(state, repo, pushed, parent).await?);
+        }
+        for hash in parent_trees.iter().chain([&commit.tree]) {
+            if let Entry::Vacant(entry) = trees.entry(*hash) {
+                entry.insert(load_manifest(state, hash).await?);
+            }
+        }
+
+        let tree = &trees[&commit.tree];
+        if parent_trees.is_empty() {
+            changed.extend(tree.entries.keys().cloned());
+        }
+        for parent in &parent_trees {
+            diff_paths(&trees[parent], tree, &mut changed);
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Tree of a commit that is either being pushed (`pushed` maps those to
+/// their trees) or already stored.
+async fn commit_tree(
+    state: &AppState,
+    repo: &Repository,
+    pushed: &HashMap<Hash, Hash>,
+    commit: &Hash,
+) -> Result<Hash, ApiError> {
+    if let Some(tree) = pushed.get(commit) {
+        return Ok(*tree);
+    }
+    Ok(state
+        .db()
+        .get_commit(repo.id, commit)
+        .await?
+        .ok_or_else(|| Error::CommitNotFound(commit.to_hex()))?
+        .tree)
+}
+
+/// Add the paths added, changed or removed from `old` to `new` to `changed`.
+fn diff_paths(old: &Manifest, new: &Manifest, changed: &mut BTreeSet<String>) {
+    for (path, entry) in &new.entries {
+        if old.get(path).is_none_or(|e| e.content_hash != entry.content_hash) {
+            changed.insert(path.clone());
+        }
+    }
+    changed.extend(old.entries.keys().filter(|p| !new.entries.contains_key(*p)).cloned());
+}
 
 /// Push changes to remote.
-pub async fn push(Path((_owner, _name)): Path<(String, String)>) -> &'static str {
-    "TODO"
+pub async fn push(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    AuthUser(user): AuthUser,
+    Json(body): Json<PushRequest>,
+) -> Result<Json<PushResponse>, ApiError> {
+    let repo = find_writable_repo(&state, &owner, &name, &user).await?;
+    let names = body
+        .refs
+        .iter()
+        .map(|update| full_ref_name(&update.name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Manifests first: every chunk they reference must already be uploaded.
+    let mut trees = HashSet::new();
+    for manifest in &body.manifests {
+        manifest.validate()?;
+        let chunks: Vec<Hash> = manifest
+            .chunk_hashes()
+            .copied()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        if let Some(missing) = state.db().missing_chunks(repo.id, &chunks).await?.first() {
+            return Err(Error::ChunkNotFound(missing.to_hex()).into());
+        }
+        trees.insert(store_manifest(&state, manifest).await?);
+    }
+
+    // Commits must be intact and their trees and parents known.
+    let pushed: HashSet<Hash> = body.commits.iter().map(|c| c.hash).collect();
+    for commit in &body.commits {
+        if commit.compute_hash() != commit.hash {
+            return Err(Error::InvalidCommit(format!(
+                "{} does not match its content",
+                commit.hash.to_hex()
+            ))
+            .into());
+        }
+        if !trees.contains(&commit.tree) {
+            load_manifest(&state, &commit.tree).await.map_err(|_| {
+                Error::InvalidCommit(format!(
+                    "{}: missing tree {}",
+                    commit.hash.to_hex(),
+                    commit.tree.to_hex()
+                ))
+            })?;
+        }
+        for parent in &commit.parents {
+            if !pushed.contains(parent) && state.db().get_commit(repo.id, parent).await?.is_none() {
+                return Err(Error::InvalidCommit(format!(
+                    "{}: missing parent {}",
+                    commit.hash.to_hex(),
+                    parent.to_hex()
+                ))
+                .into());
+            }
+        }
+    }
+
+    // Files locked by someone else must not change, even in a forced push.
+    let locks = state.db().list_locks(repo.id).await?;
+    if !locks.is_empty() {
+        let pushed_trees = body.manifests.into_iter().map(|m| (m.hash(), m)).collect();
+        let changed = ref_changes(&state, &repo, &body.refs, &body.commits, pushed_trees).await?;
+        if let Some(lock) = locks
+            .iter()
+            .find(|l| changed.contains(&l.path) && l.owner.id != user.id)
+        {
+            return Err(Error::FileLocked {
+                path: lock.path.clone(),
+                owner: lock.owner.name.clone(),
+            }
+            .into());
+        }
+    }
+
+    for commit in &body.commits {
+        state.db().insert_commit(repo.id, commit).await?;
+    }
+
+    // Check every ref update before moving any of them.
+    let mut changes = Vec::new();
+    for (update, name) in body.refs.iter().zip(names) {
+        if let Some(new) = update.new {
+            if state.db().get_commit(repo.id, &new).await?.is_none() {
+                return Err(Error::CommitNotFound(new.to_hex()).into());
+            }
+            if let Some(old) = update.old {
+                if !is_ancestor(&state, &repo, &old, &new).await? {
+                    if !update.force {
+                        return Err(Error::NonFastForward(name).into());
+                    }
+                    if !repo.settings.allow_force_push {
+                        return Err(ApiError::forbidden(format!(
+                            "force push is disabled for {}",
+                            repo.name
+                        )));
+                    }
+                }
+            }
+        }
+        changes.push(RefChange { name, expected: update.old, new: update.new });
+    }
+    state.db().update_refs(repo.id, &changes).await?;
+
+    let updates: Vec<_> = changes
+        .iter()
+        .filter(|change| change.expected != change.new)
+        .map(|change| json!({ "ref": change.name, "before": change.expected, "after": change.new }))
+        .collect();
+    let refs = changes.into_iter().map(|change| (change.name, change.new)).collect();
+
+    // Verify the new content in the background.
+    for manifest in trees {
+        enqueue(&state, Job::VerifyManifest { repository_id: repo.id, manifest }).await;
+    }
+    if !updates.is_empty() {
+        let payload = json!({ "repository": repo.name, "refs": updates });
+        notify(&state, &repo, "push", payload).await;
+    }
+
+    Ok(Json(PushResponse { refs }))
 }
 
 /// Pull changes from remote.
-pub async fn pull(Path((_owner, _name)): Path<(String, String)>) -> &'static str {
-    "TODO"
+pub async fn pull(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    user: Option<AuthUser>,
+    Json(body): Json<PullRequest>,
+) -> Result<Json<PullResponse>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+
+    let mut seen: HashSet<Hash> = body.have.iter().copied().collect();
+    let mut queue: VecDeque<Hash> = body.want.iter().copied().collect();
+    let mut commits = Vec::new();
+    while let Some(hash) = queue.pop_front() {
+        if !seen.insert(hash) {
+            continue;
+        }
+        let commit = state
+            .db()
+            .get_commit(repo.id, &hash)
+            .await?
+            .ok_or_else(|| Error::CommitNotFound(hash.to_hex()))?;
+        queue.extend(commit.parents.iter().copied());
+        commits.push(commit);
+    }
+
+    let mut manifests: HashMap<Hash, Manifest> = HashMap::new();
+    for commit in &commits {
+        if let Entry::Vacant(slot) = manifests.entry(commit.tree) {
+            slot.insert(load_manifest(&state, &commit.tree).await?);
+        }
+    }
+    let mut chunks: Vec<Hash> = manifests
+        .values()
+        .flat_map(|m| m.chunk_hashes().copied())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    chunks.sort();
+
+    Ok(Json(PullResponse {
+        commits,
+        manifests: manifests.into_values().collect(),
+        chunks,
+    }))
 }
 
 /// Fetch metadata from remote.
-pub async fn fetch(Path((_owner, _name)): Path<(String, String)>) -> &'static str {
-    "TODO"
+pub async fn fetch(
+    State(state): State<AppState>,
+    Path((owner, name)): Path<(String, String)>,
+    user: Option<AuthUser>,
+) -> Result<Json<FetchResponse>, ApiError> {
+    let repo = find_readable_repo(&state, &owner, &name, user.as_ref()).await?;
+    let branches = state
+        .db()
+        .list_refs(repo.id, HEADS_PREFIX)
+        .await?
+        .into_iter()
+        .map(|(name, hash)| (name[HEADS_PREFIX.len()..].to_string(), hash))
+        .collect();
+    let mut tags: BTreeMap<String, Hash> = state
+        .db()
+        .list_refs(repo.id, TAGS_PREFIX)
+        .await?
+        .into_iter()
+        .map(|(name, hash)| (name[TAGS_PREFIX.len()..].to_string(), hash))
+        .collect();
+    for tag in state.db().list_tags(repo.id).await? {
+        tags.entry(tag.name).or_insert(tag.commit);
+    }
+
+    Ok(Json(FetchResponse {
+        default_branch: repo.default_branch,
+        branches,
+        tags,
+    }))
 }
@@ -1,13 +1,19 @@
 //! Dits API Server
 //!
 //! REST API server for the Dits version control system.
+//!
+//! `dits-api create-token <user> [email]` registers a user if needed and
+//! prints a new access token for them instead of starting the server.
 
+use anyhow::bail;
+use dits_core::User;
 use std::net::SocketAddr;
 use tracing::info;
 
 mod error;
 mod handlers;
 mod middleware;
+mod repo;
 mod routes;
 mod state;
 
@@ -21,11 +27,20 @@ async fn main() -> anyhow::Result<()> {
         )
         .init();
 
-    info!("Starting Dits API server");
-
     // Load configuration
     dotenvy::dotenv().ok();
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = args.first() {
+        return match (command.as_str(), &args[1..]) {
+            ("create-token", [name]) => create_token(name, "").await,
+            ("create-token", [name, email]) => create_token(name, email).await,
+            _ => bail!("usage: dits-api [create-token <user> [email]]"),
+        };
+    }
+
+    info!("Starting Dits API server");
+
     // Build application state
     let state = state::AppState::from_env().await?;
 
@@ -41,3 +56,20 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+/// Print a new access token for `name`, creating the user if needed.
+async fn create_token(name: &str, email: &str) -> anyhow::Result<()> {
+    if std::env::var_os("DATABASE_URL").is_none() {
+        bail!("DATABASE_URL must be set; tokens in an in-memory database are lost on exit");
+    }
+    let db = dits_db::database_from_env().await?;
+    let user = match db.get_user_by_name(name).await? {
+        Some(user) => user,
+        None => db.create_user(User::new(name, email)).await?,
+    };
+
+    let token = middleware::generate_token();
+    db.create_access_token(user.id, middleware::hash_token(&token)).await?;
+    println!("{}", token);
+    Ok(())
+}
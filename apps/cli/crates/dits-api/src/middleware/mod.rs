@@ -1,2 +1,47 @@
 //! Middleware components.
 
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts},
+};
+use dits_core::{Hash, Hasher, User};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::state::AppState;
+
+/// Generate a new random access token.
+pub fn generate_token() -> String {
+    format!("dits_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// Hash under which a token is stored. Tokens themselves are never stored.
+pub fn hash_token(token: &str) -> Hash {
+    Hasher::hash(token.as_bytes())
+}
+
+/// The user a request is authenticated as, from `Authorization: Bearer <token>`.
+pub struct AuthUser(pub User);
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| ApiError::unauthorized("missing bearer token"))?;
+        let user = state
+            .db()
+            .get_user_by_token(&hash_token(token))
+            .await?
+            .ok_or_else(|| ApiError::unauthorized("invalid access token"))?;
+        Ok(Self(user))
+    }
+}
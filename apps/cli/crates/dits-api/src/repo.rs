@@ -0,0 +1,210 @@
+//! Repository lookups shared by the handlers.
+//!
+//! Metadata (refs, commits, tags, locks) lives in the database. Content is
+//! content-addressed in object storage (see [`dits_storage::keys`]) and
+//! shared between repositories. A repository may only read chunks it has a
+//! database reference to, so knowing a hash is not enough to read another
+//! repository's content.
+//!
+//! Writes need the repository's owner or one of its members. Private
+//! repositories are reported as missing to everyone else, so their names do
+//! not leak.
+
+use bytes::Bytes;
+use dits_core::{Error, Hash, Job, Manifest, QueuedJob, Repository, User, WebhookDelivery};
+use std::collections::{HashSet, VecDeque};
+use tracing::warn;
+
+pub use dits_storage::keys::{chunk_key, manifest_key};
+
+use crate::error::ApiError;
+use crate::middleware::AuthUser;
+use crate::state::AppState;
+
+/// Prefix of branch refs.
+pub const HEADS_PREFIX: &str = "refs/heads/";
+
+/// Full ref name of a branch.
+pub fn branch_ref(branch: &str) -> String {
+    format!("{}{}", HEADS_PREFIX, branch)
+}
+
+/// Check a repository, owner or branch name component.
+pub fn validate_name(name: &str) -> Result<(), ApiError> {
+    let valid = !name.is_empty()
+        && name != "."
+        && name != ".."
+        && name.len() <= 100
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidRepositoryName(name.to_string()).into())
+    }
+}
+
+/// Parse a full hex hash from a path or query parameter.
+pub fn parse_hash(hex: &str) -> Result<Hash, ApiError> {
+    Hash::from_hex(hex).map_err(|_| ApiError::bad_request(format!("invalid hash: {}", hex)))
+}
+
+/// Look up a repository by owner and name.
+pub async fn find_repo(state: &AppState, owner: &str, name: &str) -> Result<Repository, ApiError> {
+    let full_name = format!("{}/{}", owner, name);
+    state
+        .db()
+        .get_repository(&full_name)
+        .await?
+        .ok_or_else(|| Error::RepositoryNotFound(full_name).into())
+}
+
+/// Whether `user` (`None` when anonymous) may read `repo`.
+pub fn can_read(repo: &Repository, user: Option<&User>) -> bool {
+    repo.is_public() || user.is_some_and(|u| repo.is_collaborator(&u.name))
+}
+
+/// Look up a repository the caller may read.
+pub async fn find_readable_repo(
+    state: &AppState,
+    owner: &str,
+    name: &str,
+    user: Option<&AuthUser>,
+) -> Result<Repository, ApiError> {
+    let repo = find_repo(state, owner, name).await?;
+    if !can_read(&repo, user.map(|AuthUser(user)| user)) {
+        return Err(Error::RepositoryNotFound(repo.name).into());
+    }
+    Ok(repo)
+}
+
+/// Look up a repository `user` may write to.
+pub async fn find_writable_repo(
+    state: &AppState,
+    owner: &str,
+    name: &str,
+    user: &User,
+) -> Result<Repository, ApiError> {
+    let repo = find_repo(state, owner, name).await?;
+    if !can_read(&repo, Some(user)) {
+        return Err(Error::RepositoryNotFound(repo.name).into());
+    }
+    if !repo.is_collaborator(&user.name) {
+        return Err(ApiError::forbidden(format!("{} cannot write to {}", user.name, repo.name)));
+    }
+    Ok(repo)
+}
+
+/// Resolve a branch name, tag name or full commit hash to a commit.
+pub async fn resolve_rev(state: &AppState, repo: &Repository, rev: &str) -> Result<Hash, ApiError> {
+    if let Some(hash) = state.db().get_ref(repo.id, &branch_ref(rev)).await? {
+        return Ok(hash);
+    }
+    if let Some(hash) = state.db().get_ref(repo.id, rev).await? {
+        return Ok(hash);
+    }
+    if let Some(tag) = state.db().list_tags(repo.id).await?.into_iter().find(|t| t.name == rev) {
+        return Ok(tag.commit);
+    }
+    if let Ok(hash) = Hash::from_hex(rev) {
+        if state.db().get_commit(repo.id, &hash).await?.is_some() {
+            return Ok(hash);
+        }
+    }
+    Err(Error::CommitNotFound(rev.to_string()).into())
+}
+
+/// Resolve an optional rev, defaulting to the repository's default branch.
+pub async fn resolve_rev_or_default(
+    state: &AppState,
+    repo: &Repository,
+    rev: Option<&str>,
+) -> Result<Hash, ApiError> {
+    match rev {
+        Some(rev) => resolve_rev(state, repo, rev).await,
+        None => state
+            .db()
+            .get_ref(repo.id, &branch_ref(&repo.default_branch))
+            .await?
+            .ok_or_else(|| Error::BranchNotFound(repo.default_branch.clone()).into()),
+    }
+}
+
+/// Load the manifest of a commit.
+pub async fn commit_manifest(
+    state: &AppState,
+    repo: &Repository,
+    commit: &Hash,
+) -> Result<Manifest, ApiError> {
+    let commit = state
+        .db()
+        .get_commit(repo.id, commit)
+        .await?
+        .ok_or_else(|| Error::CommitNotFound(commit.to_hex()))?;
+    load_manifest(state, &commit.tree).await
+}
+
+/// Load a manifest from object storage.
+pub async fn load_manifest(state: &AppState, hash: &Hash) -> Result<Manifest, ApiError> {
+    let bytes = state.storage().get(&manifest_key(hash)).await?;
+    Ok(Manifest::from_bytes(&bytes)?)
+}
+
+/// Store a manifest in object storage, returning its hash.
+pub async fn store_manifest(state: &AppState, manifest: &Manifest) -> Result<Hash, ApiError> {
+    let bytes = manifest.to_bytes();
+    let hash = dits_core::Hasher::hash(&bytes);
+    let key = manifest_key(&hash);
+    if !state.storage().exists(&key).await? {
+        state.storage().put(&key, Bytes::from(bytes)).await?;
+    }
+    Ok(hash)
+}
+
+/// Whether `ancestor` is reachable from `descendant` through parent links.
+pub async fn is_ancestor(
+    state: &AppState,
+    repo: &Repository,
+    ancestor: &Hash,
+    descendant: &Hash,
+) -> Result<bool, ApiError> {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([*descendant]);
+    while let Some(hash) = queue.pop_front() {
+        if hash == *ancestor {
+            return Ok(true);
+        }
+        if !seen.insert(hash) {
+            continue;
+        }
+        if let Some(commit) = state.db().get_commit(repo.id, &hash).await? {
+            queue.extend(commit.parents);
+        }
+    }
+    Ok(false)
+}
+
+/// Queue a background job.
+///
+/// The request that triggered the job has already succeeded, so a queue
+/// failure is logged rather than returned.
+pub async fn enqueue(state: &AppState, job: Job) {
+    let kind = job.kind();
+    if let Err(e) = state.queue().enqueue(QueuedJob::new(job)).await {
+        warn!("Failed to queue {} job: {}", kind, e);
+    }
+}
+
+/// Queue deliveries of `event` to the repository's webhooks.
+pub async fn notify(state: &AppState, repo: &Repository, event: &str, payload: serde_json::Value) {
+    for hook in repo.settings.webhooks.iter().filter(|h| h.wants(event)) {
+        let delivery = WebhookDelivery {
+            url: hook.url.clone(),
+            secret: hook.secret.clone(),
+            event: event.to_string(),
+            payload: payload.clone(),
+        };
+        enqueue(state, Job::DeliverWebhook(delivery)).await;
+    }
+}
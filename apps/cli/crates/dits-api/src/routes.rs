@@ -1,6 +1,7 @@
 //! API route definitions.
 
 use axum::{
+    extract::DefaultBodyLimit,
     routing::{get, post, put, delete},
     Router,
 };
@@ -62,10 +63,15 @@ fn api_v1_routes() -> Router<AppState> {
         .route("/repos/:owner/:name/tags", get(handlers::tags::list))
         .route("/repos/:owner/:name/tags", post(handlers::tags::create))
         // Files and trees
+        .route("/repos/:owner/:name/tree", get(handlers::files::root_tree))
         .route("/repos/:owner/:name/tree/*path", get(handlers::files::tree))
         .route("/repos/:owner/:name/blob/*path", get(handlers::files::blob))
         // Chunks
-        .route("/repos/:owner/:name/chunks", post(handlers::chunks::upload))
+        .route(
+            "/repos/:owner/:name/chunks",
+            post(handlers::chunks::upload)
+                .layer(DefaultBodyLimit::max(handlers::chunks::MAX_CHUNK_SIZE)),
+        )
         .route("/repos/:owner/:name/chunks/:hash", get(handlers::chunks::download))
         .route("/repos/:owner/:name/chunks/batch", post(handlers::chunks::batch_check))
         // Locks
@@ -73,7 +79,427 @@ fn api_v1_routes() -> Router<AppState> {
         .route("/repos/:owner/:name/locks", post(handlers::locks::acquire))
         .route("/repos/:owner/:name/locks/:id", delete(handlers::locks::release))
         // Push/Pull operations
-        .route("/repos/:owner/:name/push", post(handlers::transfer::push))
+        .route(
+            "/repos/:owner/:name/push",
+            post(handlers::transfer::push)
+                .layer(DefaultBodyLimit::max(handlers::transfer::MAX_PUSH_SIZE)),
+        )
         .route("/repos/:owner/:name/pull", post(handlers::transfer::pull))
         .route("/repos/:owner/:name/fetch", post(handlers::transfer::fetch))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{
+        body::{to_bytes, Body},
+        http::{Request, StatusCode},
+        response::Response,
+    };
+    use dits_core::{chunk::ChunkRef, commit::FileMode, Author, Commit, Hasher, Job, Manifest, ManifestEntry, User};
+    use dits_db::{Database, FileJobQueue, JobQueue, MemoryDatabase};
+    use dits_storage::LocalBackend;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+    use tower::ServiceExt;
+
+    async fn test_app(dir: &std::path::Path) -> (Router, Arc<FileJobQueue>, Arc<MemoryDatabase>) {
+        let queue = Arc::new(FileJobQueue::open(dir.join("queue")).await.unwrap());
+        let db = Arc::new(MemoryDatabase::new());
+        let state = AppState::new(
+            db.clone(),
+            Arc::new(LocalBackend::new(dir.join("storage"))),
+            queue.clone(),
+        );
+        (create_router(state), queue, db)
+    }
+
+    /// Create a user and return an access token for them.
+    async fn login(db: &MemoryDatabase, name: &str) -> String {
+        let user = db.create_user(User::new(name, format!("{}@example.com", name))).await.unwrap();
+        let token = crate::middleware::generate_token();
+        db.create_access_token(user.id, crate::middleware::hash_token(&token)).await.unwrap();
+        token
+    }
+
+    /// Create the public repository team/film with `members`, returning the owner's token.
+    async fn create_film(app: &Router, db: &MemoryDatabase, members: &[&str]) -> String {
+        let team = login(db, "team").await;
+        let request = json_request("POST", "/v1/repos", json!({"owner": "team", "name": "film"}));
+        assert_eq!(send(app, authed(request, &team)).await.status(), StatusCode::CREATED);
+        if !members.is_empty() {
+            let request = json_request("PUT", "/v1/repos/team/film", json!({"members": members}));
+            assert_eq!(send(app, authed(request, &team)).await.status(), StatusCode::OK);
+        }
+        team
+    }
+
+    fn upload_chunk(data: &'static [u8], token: &str) -> Request<Body> {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/repos/team/film/chunks")
+            .body(Body::from(data))
+            .unwrap();
+        authed(request, token)
+    }
+
+    async fn send(app: &Router, request: Request<Body>) -> Response {
+        app.clone().oneshot(request).await.unwrap()
+    }
+
+    fn json_request(method: &str, uri: &str, body: Value) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap()
+    }
+
+    fn authed(mut request: Request<Body>, token: &str) -> Request<Body> {
+        let value = format!("Bearer {}", token).parse().unwrap();
+        request.headers_mut().insert("authorization", value);
+        request
+    }
+
+    async fn body_json(response: Response) -> Value {
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_push_and_read_back() {
+        let dir = tempdir().unwrap();
+        let (app, queue, db) = test_app(dir.path()).await;
+        let team = create_film(&app, &db, &[]).await;
+
+        // Upload two chunks of one file.
+        let parts: [&[u8]; 2] = [b"hello ", b"world"];
+        let mut chunks = Vec::new();
+        let mut offset = 0;
+        for part in parts {
+            assert_eq!(send(&app, upload_chunk(part, &team)).await.status(), StatusCode::CREATED);
+            chunks.push(ChunkRef::new(Hasher::hash(part), offset, part.len() as u64));
+            offset += part.len() as u64;
+        }
+
+        let mut manifest = Manifest::new();
+        manifest.insert(ManifestEntry {
+            path: "docs/greeting.txt".to_string(),
+            mode: FileMode::Regular,
+            size: offset,
+            content_hash: Hasher::hash(b"hello world"),
+            chunks,
+        });
+        let commit = Commit::new(vec![], manifest.hash(), Author::new("alice", "a@example.com"), "init");
+        let push = json!({
+            "commits": [commit],
+            "manifests": [manifest],
+            "refs": [{"name": "main", "old": null, "new": commit.hash}],
+        });
+        let request = json_request("POST", "/v1/repos/team/film/push", push.clone());
+        let response = send(&app, authed(request, &team)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let job = queue.claim().await.unwrap().unwrap();
+        assert!(matches!(job.job, Job::VerifyManifest { .. }));
+
+        // Replaying the push is a ref conflict.
+        let request = json_request("POST", "/v1/repos/team/film/push", push);
+        let response = send(&app, authed(request, &team)).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let request = Request::get("/v1/repos/team/film/commits").body(Body::empty()).unwrap();
+        let page = body_json(send(&app, request).await).await;
+        assert_eq!(page["items"][0]["message"], "init");
+
+        let request = Request::get("/v1/repos/team/film/tree").body(Body::empty()).unwrap();
+        let tree = body_json(send(&app, request).await).await;
+        assert_eq!(tree["entries"][0]["name"], "docs");
+
+        let request = Request::get("/v1/repos/team/film/blob/docs/greeting.txt")
+            .body(Body::empty())
+            .unwrap();
+        let response = send(&app, request).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), "hello world");
+
+        let request = Request::get("/v1/repos/team/film/blob/docs/greeting.txt?ref=main")
+            .header("range", "bytes=4-7")
+            .body(Body::empty())
+            .unwrap();
+        let response = send(&app, request).await;
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()["content-range"], "bytes 4-7/11");
+        assert_eq!(to_bytes(response.into_body(), usize::MAX).await.unwrap(), "o wo");
+
+        let check = json!({"hashes": [Hasher::hash(b"hello "), Hasher::hash(b"nope")]});
+        let response = send(&app, json_request("POST", "/v1/repos/team/film/chunks/batch", check)).await;
+        let result = body_json(response).await;
+        assert_eq!(result["present"].as_array().map(Vec::len), Some(1));
+        assert_eq!(result["missing"].as_array().map(Vec::len), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_missing_chunks() {
+        let dir = tempdir().unwrap();
+        let (app, queue, db) = test_app(dir.path()).await;
+        let team = create_film(&app, &db, &[]).await;
+
+        let mut manifest = Manifest::new();
+        manifest.insert(ManifestEntry {
+            path: "a.bin".to_string(),
+            mode: FileMode::Regular,
+            size: 3,
+            content_hash: Hasher::hash(b"abc"),
+            chunks: vec![ChunkRef::new(Hasher::hash(b"abc"), 0, 3)],
+        });
+        let push = json!({"manifests": [manifest], "refs": []});
+        let request = json_request("POST", "/v1/repos/team/film/push", push);
+        let response = send(&app, authed(request, &team)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert!(queue.claim().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_locks_belong_to_the_authenticated_user() {
+        let dir = tempdir().unwrap();
+        let (app, _, db) = test_app(dir.path()).await;
+        let alice = login(&db, "alice").await;
+        let bob = login(&db, "bob").await;
+        create_film(&app, &db, &["alice", "bob"]).await;
+
+        let lock = json!({"path": "edit.prproj", "reason": "cutting"});
+        let response = send(&app, json_request("POST", "/v1/repos/team/film/locks", lock.clone())).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let forged = authed(json_request("POST", "/v1/repos/team/film/locks", lock.clone()), "dits_forged");
+        assert_eq!(send(&app, forged).await.status(), StatusCode::UNAUTHORIZED);
+
+        let response = send(&app, authed(json_request("POST", "/v1/repos/team/film/locks", lock.clone()), &alice)).await;
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let held = body_json(response).await;
+        assert_eq!(held["owner"], "alice");
+
+        let response = send(&app, authed(json_request("POST", "/v1/repos/team/film/locks", lock), &bob)).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let request = Request::get("/v1/repos/team/film/locks").body(Body::empty()).unwrap();
+        let locks = body_json(send(&app, request).await).await;
+        assert_eq!(locks[0]["owner"], "alice");
+
+        let uri = format!("/v1/repos/team/film/locks/{}", held["id"].as_str().unwrap());
+        let release = |token: &str| {
+            authed(Request::delete(uri.as_str()).body(Body::empty()).unwrap(), token)
+        };
+        assert_eq!(send(&app, release(&bob)).await.status(), StatusCode::FORBIDDEN);
+        assert_eq!(send(&app, release(&alice)).await.status(), StatusCode::NO_CONTENT);
+
+        let request = authed(Request::get("/v1/auth/me").body(Body::empty()).unwrap(), &bob);
+        assert_eq!(body_json(send(&app, request).await).await["name"], "bob");
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_changes_to_files_locked_by_others() {
+        let dir = tempdir().unwrap();
+        let (app, _, db) = test_app(dir.path()).await;
+        let alice = login(&db, "alice").await;
+        let bob = login(&db, "bob").await;
+        create_film(&app, &db, &["alice", "bob"]).await;
+
+        let request = json_request("POST", "/v1/repos/team/film/locks", json!({"path": "edit.prproj"}));
+        assert_eq!(send(&app, authed(request, &alice)).await.status(), StatusCode::CREATED);
+
+        send(&app, upload_chunk(b"cut", &bob)).await;
+        let mut manifest = Manifest::new();
+        manifest.insert(ManifestEntry {
+            path: "edit.prproj".to_string(),
+            mode: FileMode::Regular,
+            size: 3,
+            content_hash: Hasher::hash(b"cut"),
+            chunks: vec![ChunkRef::new(Hasher::hash(b"cut"), 0, 3)],
+        });
+        let commit = Commit::new(vec![], manifest.hash(), Author::new("bob", "b@example.com"), "recut");
+        let push = json!({
+            "commits": [commit],
+            "manifests": [manifest],
+            "refs": [{"name": "main", "old": null, "new": commit.hash}],
+        });
+
+        let anonymous = json_request("POST", "/v1/repos/team/film/push", push.clone());
+        assert_eq!(send(&app, anonymous).await.status(), StatusCode::UNAUTHORIZED);
+        let response = send(&app, authed(json_request("POST", "/v1/repos/team/film/push", push.clone()), &bob)).await;
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert_eq!(body_json(response).await["error"]["code"], "E5001");
+
+        let response = send(&app, authed(json_request("POST", "/v1/repos/team/film/push", push), &alice)).await;
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_moving_refs_over_locked_files() {
+        let dir = tempdir().unwrap();
+        let (app, _, db) = test_app(dir.path()).await;
+        let alice = login(&db, "alice").await;
+        let bob = login(&db, "bob").await;
+        let team = create_film(&app, &db, &["alice", "bob"]).await;
+
+        // main has the first cut; another branch already has the second.
+        let mut manifests = Vec::new();
+        for content in [&b"cut"[..], &b"recut"[..]] {
+            send(&app, upload_chunk(content, &team)).await;
+            let mut manifest = Manifest::new();
+            manifest.insert(ManifestEntry {
+                path: "edit.prproj".to_string(),
+                mode: FileMode::Regular,
+                size: content.len() as u64,
+                content_hash: Hasher::hash(content),
+                chunks: vec![ChunkRef::new(Hasher::hash(content), 0, content.len() as u64)],
+            });
+            manifests.push(manifest);
+        }
+        let author = Author::new("team", "t@example.com");
+        let first = Commit::new(vec![], manifests[0].hash(), author.clone(), "cut");
+        let second = Commit::new(vec![first.hash], manifests[1].hash(), author, "recut");
+        let push = json!({
+            "commits": [first, second],
+            "manifests": manifests,
+            "refs": [
+                {"name": "main", "old": null, "new": first.hash},
+                {"name": "other", "old": null, "new": second.hash},
+            ],
+        });
+        let request = json_request("POST", "/v1/repos/team/film/push", push);
+        assert_eq!(send(&app, authed(request, &team)).await.status(), StatusCode::OK);
+
+        let request = json_request("POST", "/v1/repos/team/film/locks", json!({"path": "edit.prproj"}));
+        assert_eq!(send(&app, authed(request, &alice)).await.status(), StatusCode::CREATED);
+
+        // Moving main to the existing second commit changes the locked file
+        // without pushing any commit.
+        let forward = json!({"refs": [{"name": "main", "old": first.hash, "new": second.hash}]});
+        let request = json_request("POST", "/v1/repos/team/film/push", forward.clone());
+        assert_eq!(send(&app, authed(request, &bob)).await.status(), StatusCode::CONFLICT);
+
+        // So does rewinding the other branch.
+        let rewind = json!({
+            "refs": [{"name": "other", "old": second.hash, "new": first.hash, "force": true}],
+        });
+        let request = json_request("POST", "/v1/repos/team/film/push", rewind);
+        assert_eq!(send(&app, authed(request, &bob)).await.status(), StatusCode::CONFLICT);
+
+        let request = json_request("POST", "/v1/repos/team/film/push", forward);
+        assert_eq!(send(&app, authed(request, &alice)).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_push_checks_every_ref_before_moving_any() {
+        let dir = tempdir().unwrap();
+        let (app, _, db) = test_app(dir.path()).await;
+        let team = create_film(&app, &db, &[]).await;
+        let repo = db.get_repository("team/film").await.unwrap().unwrap();
+
+        let manifest = Manifest::new();
+        let commit = Commit::new(vec![], manifest.hash(), Author::new("team", "t@example.com"), "init");
+        let push = |refs: Value| {
+            let body = json!({"commits": [commit], "manifests": [manifest], "refs": refs});
+            authed(json_request("POST", "/v1/repos/team/film/push", body), &team)
+        };
+
+        for name in ["refs/heads/../../escaped", "refs/meta/config", "feature/../main", ""] {
+            let response = send(&app, push(json!([{"name": name, "old": null, "new": commit.hash}]))).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", name);
+        }
+        assert!(db.list_refs(repo.id, "refs/").await.unwrap().is_empty());
+
+        db.update_ref(repo.id, "refs/tags/v1", None, Some(commit.hash)).await.unwrap();
+        let refs = json!([
+            {"name": "main", "old": null, "new": commit.hash},
+            {"name": "refs/tags/v1", "old": null, "new": commit.hash},
+        ]);
+        assert_eq!(send(&app, push(refs)).await.status(), StatusCode::CONFLICT);
+        assert_eq!(db.get_ref(repo.id, "refs/heads/main").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_writes_need_a_collaborator() {
+        let dir = tempdir().unwrap();
+        let (app, _, db) = test_app(dir.path()).await;
+        let alice = login(&db, "alice").await;
+        let mallory = login(&db, "mallory").await;
+        create_film(&app, &db, &["alice"]).await;
+
+        let create = json!({"owner": "team", "name": "other"});
+        let response = send(&app, json_request("POST", "/v1/repos", create.clone())).await;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = send(&app, authed(json_request("POST", "/v1/repos", create), &mallory)).await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+        let writes = || {
+            [
+                json_request("PUT", "/v1/repos/team/film", json!({"description": "mine"})),
+                Request::delete("/v1/repos/team/film").body(Body::empty()).unwrap(),
+                json_request("POST", "/v1/repos/team/film/branches", json!({"name": "b"})),
+                Request::delete("/v1/repos/team/film/branches/b").body(Body::empty()).unwrap(),
+                json_request("POST", "/v1/repos/team/film/tags", json!({"name": "v1", "target": "main"})),
+                Request::post("/v1/repos/team/film/chunks").body(Body::from(&b"x"[..])).unwrap(),
+                json_request("POST", "/v1/repos/team/film/push", json!({"refs": []})),
+            ]
+        };
+        for request in writes() {
+            assert_eq!(send(&app, request).await.status(), StatusCode::UNAUTHORIZED);
+        }
+        for request in writes() {
+            assert_eq!(send(&app, authed(request, &mallory)).await.status(), StatusCode::FORBIDDEN);
+        }
+
+        // Members can write, but only the owner manages members.
+        assert_eq!(send(&app, upload_chunk(b"x", &alice)).await.status(), StatusCode::CREATED);
+        let request = json_request("PUT", "/v1/repos/team/film", json!({"members": ["alice", "mallory"]}));
+        assert_eq!(send(&app, authed(request, &alice)).await.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_private_repositories_are_hidden() {
+        let dir = tempdir().unwrap();
+        let (app, _, db) = test_app(dir.path()).await;
+        let alice = login(&db, "alice").await;
+        let mallory = login(&db, "mallory").await;
+        let team = create_film(&app, &db, &["alice"]).await;
+        let request = json_request("PUT", "/v1/repos/team/film", json!({"private": true}));
+        assert_eq!(send(&app, authed(request, &team)).await.status(), StatusCode::OK);
+        send(&app, upload_chunk(b"secret", &team)).await;
+
+        let chunk = format!("/v1/repos/team/film/chunks/{}", Hasher::hash(b"secret").to_hex());
+        let reads = || {
+            [
+                Request::get("/v1/repos/team/film").body(Body::empty()).unwrap(),
+                Request::get("/v1/repos/team/film/commits").body(Body::empty()).unwrap(),
+                Request::get("/v1/repos/team/film/branches").body(Body::empty()).unwrap(),
+                Request::get("/v1/repos/team/film/tags").body(Body::empty()).unwrap(),
+                Request::get("/v1/repos/team/film/tree").body(Body::empty()).unwrap(),
+                Request::get("/v1/repos/team/film/blob/a.txt").body(Body::empty()).unwrap(),
+                Request::get(chunk.as_str()).body(Body::empty()).unwrap(),
+                json_request("POST", "/v1/repos/team/film/chunks/batch", json!({"hashes": []})),
+                json_request("POST", "/v1/repos/team/film/pull", json!({"want": []})),
+                json_request("POST", "/v1/repos/team/film/fetch", json!({"hashes": []})),
+                Request::get("/v1/repos/team/film/locks").body(Body::empty()).unwrap(),
+            ]
+        };
+        for request in reads() {
+            assert_eq!(send(&app, request).await.status(), StatusCode::NOT_FOUND);
+        }
+        for request in reads() {
+            assert_eq!(send(&app, authed(request, &mallory)).await.status(), StatusCode::NOT_FOUND);
+        }
+        // Writes by outsiders do not reveal the repository either.
+        let response = send(&app, upload_chunk(b"x", &mallory)).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let request = authed(Request::get(chunk.as_str()).body(Body::empty()).unwrap(), &alice);
+        assert_eq!(send(&app, request).await.status(), StatusCode::OK);
+        let listed = |token: &str| authed(Request::get("/v1/repos").body(Body::empty()).unwrap(), token);
+        assert_eq!(body_json(send(&app, listed(&alice)).await).await.as_array().map(Vec::len), Some(1));
+        assert_eq!(body_json(send(&app, listed(&mallory)).await).await.as_array().map(Vec::len), Some(0));
+    }
+}
@@ -1,5 +1,7 @@
 //! Application state.
 
+use dits_db::{Database, FileJobQueue, JobQueue};
+use dits_storage::StorageBackend;
 use std::sync::Arc;
 
 /// Shared application state.
@@ -9,22 +11,52 @@ pub struct AppState {
 }
 
 struct AppStateInner {
-    // Database connection pool
-    // db: dits_db::Pool,
-    // Cache client
-    // cache: dits_cache::Client,
-    // Storage client
-    // storage: dits_storage::Client,
-    // Configuration
-    // config: dits_core::Config,
+    /// Repository metadata: refs, commits, chunk references, locks.
+    db: Arc<dyn Database>,
+    /// Chunk and manifest contents.
+    storage: Arc<dyn StorageBackend>,
+    /// Background jobs for `dits-worker`.
+    queue: Arc<dyn JobQueue>,
 }
 
 impl AppState {
+    /// Create application state from explicit backends.
+    pub fn new(
+        db: Arc<dyn Database>,
+        storage: Arc<dyn StorageBackend>,
+        queue: Arc<dyn JobQueue>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(AppStateInner { db, storage, queue }),
+        }
+    }
+
     /// Create application state from environment variables.
+    ///
+    /// Metadata lives in the PostgreSQL database at `DATABASE_URL` (see
+    /// [`dits_db::database_from_env`]), object storage is chosen by
+    /// [`dits_storage::backend_from_env`] and the job queue lives in
+    /// `DITS_QUEUE_PATH` (default `./data/queue`).
     pub async fn from_env() -> anyhow::Result<Self> {
-        // TODO: Initialize database, cache, and storage connections
-        Ok(Self {
-            inner: Arc::new(AppStateInner {}),
-        })
+        let db = dits_db::database_from_env().await?;
+        let storage = dits_storage::backend_from_env().await;
+        let queue = FileJobQueue::from_env().await?;
+
+        Ok(Self::new(db, storage, Arc::new(queue)))
+    }
+
+    /// Metadata database.
+    pub fn db(&self) -> &dyn Database {
+        self.inner.db.as_ref()
+    }
+
+    /// Object storage.
+    pub fn storage(&self) -> &Arc<dyn StorageBackend> {
+        &self.inner.storage
+    }
+
+    /// Background job queue.
+    pub fn queue(&self) -> &dyn JobQueue {
+        self.inner.queue.as_ref()
     }
 }
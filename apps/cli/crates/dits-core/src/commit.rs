@@ -1,6 +1,6 @@
 //! Commit types and operations.
 
-use crate::hash::Hash;
+use crate::hash::{Hash, Hasher};
 use crate::user::Author;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -38,8 +38,8 @@ impl Commit {
         message: impl Into<String>,
     ) -> Self {
         let now = Utc::now();
-        Self {
-            hash: Hash::ZERO, // Will be computed
+        let mut commit = Self {
+            hash: Hash::ZERO,
             parents,
             tree,
             author: author.clone(),
@@ -47,7 +47,20 @@ impl Commit {
             message: message.into(),
             created_at: now,
             signature: None,
-        }
+        };
+        commit.hash = commit.compute_hash();
+        commit
+    }
+
+    /// Compute the content hash of this commit.
+    ///
+    /// Covers every field except `hash` itself, so a received commit can be
+    /// checked with `commit.compute_hash() == commit.hash`.
+    pub fn compute_hash(&self) -> CommitHash {
+        let mut unhashed = self.clone();
+        unhashed.hash = Hash::ZERO;
+        let bytes = serde_json::to_vec(&unhashed).expect("commit serialization should not fail");
+        Hasher::hash(&bytes)
     }
 
     /// Check if this is the initial commit.
@@ -236,6 +249,18 @@ mod tests {
         assert!(commit.is_merge());
     }
 
+    #[test]
+    fn test_commit_hash() {
+        let author = Author::new("Test", "test@example.com");
+        let mut commit = Commit::new(vec![], Hash::ZERO, author, "Initial commit");
+
+        assert!(!commit.hash.is_zero());
+        assert_eq!(commit.compute_hash(), commit.hash);
+
+        commit.message = "Tampered".to_string();
+        assert_ne!(commit.compute_hash(), commit.hash);
+    }
+
     #[test]
     fn test_commit_title() {
         let author = Author::new("Test", "test@example.com");
@@ -63,6 +63,18 @@ pub enum Error {
     #[error("Branch has unmerged changes")]
     UnmergedChanges,
 
+    #[error("Tag not found: {0}")]
+    TagNotFound(String),
+
+    #[error("Tag already exists: {0}")]
+    TagAlreadyExists(String),
+
+    #[error("Ref was updated concurrently: {0}")]
+    RefConflict(String),
+
+    #[error("Update to {0} is not a fast-forward")]
+    NonFastForward(String),
+
     // ==================== File/Chunk Errors ====================
     #[error("File not found: {0}")]
     FileNotFound(String),
@@ -179,6 +191,10 @@ impl Error {
             Error::BranchAlreadyExists(_) => "E4002",
             Error::CannotDeleteCurrentBranch => "E4003",
             Error::UnmergedChanges => "E4004",
+            Error::TagNotFound(_) => "E4005",
+            Error::TagAlreadyExists(_) => "E4006",
+            Error::RefConflict(_) => "E4007",
+            Error::NonFastForward(_) => "E4008",
 
             // E5xxx: Lock
             Error::FileLocked { .. } => "E5001",
@@ -232,6 +248,7 @@ impl Error {
             Error::RepositoryNotFound(_)
             | Error::CommitNotFound(_)
             | Error::BranchNotFound(_)
+            | Error::TagNotFound(_)
             | Error::FileNotFound(_)
             | Error::ChunkNotFound(_)
             | Error::LockNotFound(_)
@@ -241,8 +258,12 @@ impl Error {
             // 409 Conflict
             Error::RepositoryAlreadyExists(_)
             | Error::BranchAlreadyExists(_)
+            | Error::TagAlreadyExists(_)
+            | Error::RefConflict(_)
+            | Error::NonFastForward(_)
             | Error::MergeConflict(_)
             | Error::FileLocked { .. }
+            | Error::CannotDeleteCurrentBranch
             | Error::UnmergedChanges => 409,
 
             // 413 Payload Too Large
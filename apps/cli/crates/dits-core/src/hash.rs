@@ -1,13 +1,16 @@
 //! Hash types and utilities for content addressing.
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
 
 /// Size of a BLAKE3 hash in bytes.
 pub const HASH_SIZE: usize = 32;
 
 /// A content-addressable hash (BLAKE3).
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// Serialized as a hex string in human-readable formats (JSON) and as raw
+/// bytes otherwise (bincode).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Hash([u8; HASH_SIZE]);
 
 impl Hash {
@@ -89,6 +92,27 @@ impl From<[u8; HASH_SIZE]> for Hash {
     }
 }
 
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_hex())
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let hex = String::deserialize(deserializer)?;
+            Self::from_hex(&hex).map_err(serde::de::Error::custom)
+        } else {
+            <[u8; HASH_SIZE]>::deserialize(deserializer).map(Self)
+        }
+    }
+}
+
 impl TryFrom<&[u8]> for Hash {
     type Error = std::array::TryFromSliceError;
 
@@ -194,6 +218,20 @@ mod tests {
         assert_eq!(hash1, hash2);
     }
 
+    #[test]
+    fn test_hash_serde() {
+        let hash = Hasher::hash(b"test");
+
+        let json = serde_json::to_string(&hash).unwrap();
+        assert_eq!(json, format!("\"{}\"", hash.to_hex()));
+        assert_eq!(serde_json::from_str::<Hash>(&json).unwrap(), hash);
+        assert!(serde_json::from_str::<Hash>("\"abcd\"").is_err());
+
+        let bytes = bincode::serialize(&hash).unwrap();
+        assert_eq!(bytes.len(), HASH_SIZE);
+        assert_eq!(bincode::deserialize::<Hash>(&bytes).unwrap(), hash);
+    }
+
     #[test]
     fn test_zero_hash() {
         assert!(Hash::ZERO.is_zero());
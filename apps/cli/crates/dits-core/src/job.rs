@@ -0,0 +1,114 @@
+//! Background jobs.
+//!
+//! Jobs are queued by the API server and executed by `dits-worker`. They
+//! are serialized into the queue, so variants must stay backward compatible.
+
+use crate::hash::Hash;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Attempts a job gets before it is moved to the failed set.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 8;
+
+/// A unit of background work.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Job {
+    /// Re-read every file of a pushed manifest from its chunks and check
+    /// the chunk and content hashes.
+    VerifyManifest {
+        /// Repository the manifest was pushed to.
+        repository_id: Uuid,
+        /// Manifest hash.
+        manifest: Hash,
+    },
+    /// Remove chunk references no commit needs any more, then delete
+    /// objects no repository references.
+    CollectGarbage {
+        /// Only collect objects older than this, so in-flight pushes are safe.
+        grace_secs: u64,
+    },
+    /// POST an event to a webhook.
+    DeliverWebhook(WebhookDelivery),
+}
+
+impl Job {
+    /// Short name for logs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Job::VerifyManifest { .. } => "verify_manifest",
+            Job::CollectGarbage { .. } => "collect_garbage",
+            Job::DeliverWebhook(_) => "deliver_webhook",
+        }
+    }
+}
+
+/// A single webhook delivery.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    /// Target URL.
+    pub url: String,
+    /// Shared secret used to sign the body, if configured.
+    pub secret: Option<String>,
+    /// Event name, e.g. `push`.
+    pub event: String,
+    /// JSON body.
+    pub payload: serde_json::Value,
+}
+
+/// A job together with its queue bookkeeping.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct QueuedJob {
+    /// Unique job ID.
+    pub id: Uuid,
+    /// The work to do.
+    pub job: Job,
+    /// Attempts started so far.
+    pub attempts: u32,
+    /// Attempts allowed before the job is given up on.
+    pub max_attempts: u32,
+    /// Earliest time the job may run.
+    pub run_at: DateTime<Utc>,
+    /// When the current attempt was claimed by a worker.
+    pub claimed_at: Option<DateTime<Utc>>,
+    /// Error from the last failed attempt.
+    pub last_error: Option<String>,
+    /// When the job was queued.
+    pub created_at: DateTime<Utc>,
+}
+
+impl QueuedJob {
+    /// Wrap a job to run as soon as possible.
+    pub fn new(job: Job) -> Self {
+        let now = Utc::now();
+        Self {
+            id: Uuid::new_v4(),
+            job,
+            attempts: 0,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            run_at: now,
+            claimed_at: None,
+            last_error: None,
+            created_at: now,
+        }
+    }
+
+    /// Whether another attempt is allowed after the current one.
+    pub fn can_retry(&self) -> bool {
+        self.attempts < self.max_attempts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_job_serialization() {
+        let job = Job::CollectGarbage { grace_secs: 60 };
+        let json = serde_json::to_string(&job).unwrap();
+        assert_eq!(json, r#"{"type":"collect_garbage","grace_secs":60}"#);
+        assert_eq!(serde_json::from_str::<Job>(&json).unwrap(), job);
+    }
+}
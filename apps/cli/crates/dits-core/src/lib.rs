@@ -24,7 +24,9 @@ pub mod config;
 pub mod error;
 pub mod hash;
 pub mod hybrid;
+pub mod job;
 pub mod lock;
+pub mod manifest;
 pub mod repository;
 pub mod types;
 pub mod user;
@@ -36,7 +38,9 @@ pub use config::Config;
 pub use error::{Error, Result};
 pub use hash::{Hash, Hasher};
 pub use hybrid::{FileClassifier, GitStorage, HybridManifest, HybridManifestEntry, HybridStorage, StorageStrategy};
+pub use job::{Job, QueuedJob, WebhookDelivery};
 pub use lock::{Lock, LockInfo};
-pub use repository::{Branch, Repository, RepositoryInfo, Tag};
+pub use manifest::{Manifest, ManifestEntry};
+pub use repository::{Branch, Repository, RepositoryInfo, Tag, Webhook};
 pub use types::*;
 pub use user::{Author, User};
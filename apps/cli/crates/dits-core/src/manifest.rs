@@ -0,0 +1,198 @@
+//! Manifests: the file listing of a commit.
+//!
+//! A manifest is flat. Every tracked file appears under its full path with
+//! the ordered chunks that make up its content. Directories are implied by
+//! the paths and have no objects of their own.
+
+use crate::chunk::{ChunkHash, ChunkRef};
+use crate::commit::{FileMode, TreeEntry};
+use crate::error::{Error, Result};
+use crate::hash::{Hash, Hasher};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A file in a manifest.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Path relative to the repository root, `/`-separated.
+    pub path: String,
+    /// File mode.
+    pub mode: FileMode,
+    /// Total file size.
+    pub size: u64,
+    /// Hash of the whole file content.
+    pub content_hash: Hash,
+    /// Chunks in file order.
+    pub chunks: Vec<ChunkRef>,
+}
+
+impl ManifestEntry {
+    /// Check that the chunks tile the file exactly, in order and without gaps.
+    pub fn validate(&self) -> Result<()> {
+        let mut offset = 0;
+        for chunk in &self.chunks {
+            if chunk.offset != offset {
+                return Err(Error::InvalidCommit(format!(
+                    "{}: chunk at offset {} expected at {}",
+                    self.path, chunk.offset, offset
+                )));
+            }
+            offset += chunk.size;
+        }
+        if offset != self.size {
+            return Err(Error::InvalidCommit(format!(
+                "{}: chunks cover {} of {} bytes",
+                self.path, offset, self.size
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// The complete file listing of a commit.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Entries keyed by path.
+    pub entries: BTreeMap<String, ManifestEntry>,
+}
+
+impl Manifest {
+    /// Create an empty manifest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace an entry.
+    pub fn insert(&mut self, entry: ManifestEntry) {
+        self.entries.insert(entry.path.clone(), entry);
+    }
+
+    /// Look up an entry by path.
+    pub fn get(&self, path: &str) -> Option<&ManifestEntry> {
+        self.entries.get(path)
+    }
+
+    /// Serialize to the canonical byte form the manifest hash is computed over.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("manifest serialization should not fail")
+    }
+
+    /// Deserialize from bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        serde_json::from_slice(bytes).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    /// Content hash of the manifest.
+    pub fn hash(&self) -> Hash {
+        Hasher::hash(&self.to_bytes())
+    }
+
+    /// Validate every entry.
+    pub fn validate(&self) -> Result<()> {
+        for (path, entry) in &self.entries {
+            if *path != entry.path {
+                return Err(Error::InvalidCommit(format!("manifest key {} names {}", path, entry.path)));
+            }
+            entry.validate()?;
+        }
+        Ok(())
+    }
+
+    /// All chunk hashes referenced by the manifest.
+    pub fn chunk_hashes(&self) -> impl Iterator<Item = &ChunkHash> {
+        self.entries.values().flat_map(|e| e.chunks.iter().map(|c| &c.hash))
+    }
+
+    /// List the immediate children of a directory (`""` for the root).
+    ///
+    /// Directories are returned with the zero hash and no size. Returns
+    /// `None` if nothing lives under `dir`.
+    pub fn list_dir(&self, dir: &str) -> Option<Vec<TreeEntry>> {
+        let dir = dir.trim_matches('/');
+        let prefix = if dir.is_empty() { String::new() } else { format!("{}/", dir) };
+
+        let mut children: BTreeMap<&str, TreeEntry> = BTreeMap::new();
+        for (path, entry) in self.entries.range(prefix.clone()..) {
+            let Some(rest) = path.strip_prefix(&prefix) else {
+                break;
+            };
+            match rest.split_once('/') {
+                Some((name, _)) => {
+                    children.entry(name).or_insert_with(|| TreeEntry {
+                        name: name.to_string(),
+                        mode: FileMode::Directory,
+                        hash: Hash::ZERO,
+                        size: None,
+                    });
+                }
+                None => {
+                    children.insert(
+                        rest,
+                        TreeEntry {
+                            name: rest.to_string(),
+                            mode: entry.mode,
+                            hash: entry.content_hash,
+                            size: Some(entry.size),
+                        },
+                    );
+                }
+            }
+        }
+
+        if children.is_empty() && !dir.is_empty() {
+            return None;
+        }
+        Some(children.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, size: u64) -> ManifestEntry {
+        ManifestEntry {
+            path: path.to_string(),
+            mode: FileMode::Regular,
+            size,
+            content_hash: Hasher::hash(path.as_bytes()),
+            chunks: vec![ChunkRef::new(Hasher::hash(path.as_bytes()), 0, size)],
+        }
+    }
+
+    #[test]
+    fn test_list_dir() {
+        let mut manifest = Manifest::new();
+        manifest.insert(entry("README.md", 10));
+        manifest.insert(entry("footage/a.mov", 100));
+        manifest.insert(entry("footage/day2/b.mov", 200));
+        manifest.insert(entry("footage-old/c.mov", 300));
+
+        let root = manifest.list_dir("").unwrap();
+        let names: Vec<_> = root.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["README.md", "footage", "footage-old"]);
+        assert!(root[1].mode.is_directory());
+
+        let footage = manifest.list_dir("footage/").unwrap();
+        let names: Vec<_> = footage.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["a.mov", "day2"]);
+        assert_eq!(footage[0].size, Some(100));
+
+        assert!(manifest.list_dir("missing").is_none());
+    }
+
+    #[test]
+    fn test_validate_and_hash() {
+        let mut manifest = Manifest::new();
+        manifest.insert(entry("a.bin", 10));
+        assert!(manifest.validate().is_ok());
+
+        let parsed = Manifest::from_bytes(&manifest.to_bytes()).unwrap();
+        assert_eq!(parsed.hash(), manifest.hash());
+
+        let mut gap = entry("b.bin", 10);
+        gap.chunks[0].offset = 1;
+        manifest.insert(gap);
+        assert!(manifest.validate().is_err());
+    }
+}
@@ -57,6 +57,11 @@ impl Repository {
     pub fn is_public(&self) -> bool {
         self.visibility == Visibility::Public
     }
+
+    /// Check if a user may write to the repository: its owner or a member.
+    pub fn is_collaborator(&self, user_name: &str) -> bool {
+        self.owner() == user_name || self.settings.members.iter().any(|m| m == user_name)
+    }
 }
 
 /// Repository visibility.
@@ -94,6 +99,12 @@ pub struct RepositorySettings {
     pub chunker: ChunkerType,
     /// Default branch protection.
     pub branch_protection: Option<BranchProtection>,
+    /// Webhooks notified of repository events.
+    #[serde(default)]
+    pub webhooks: Vec<Webhook>,
+    /// Users other than the owner who may read and write the repository.
+    #[serde(default)]
+    pub members: Vec<String>,
 }
 
 impl Default for RepositorySettings {
@@ -114,6 +125,8 @@ impl Default for RepositorySettings {
             ],
             chunker: ChunkerType::VideoAware,
             branch_protection: None,
+            webhooks: Vec::new(),
+            members: Vec::new(),
         }
     }
 }
@@ -157,6 +170,25 @@ pub struct BranchProtection {
     pub allow_deletions: bool,
 }
 
+/// A webhook registered on a repository.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Webhook {
+    /// URL events are POSTed to.
+    pub url: String,
+    /// Secret for the `X-Dits-Signature-256` header.
+    pub secret: Option<String>,
+    /// Events to deliver (`push`, `lock`, `unlock`); empty means all.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl Webhook {
+    /// Whether this webhook wants `event`.
+    pub fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event)
+    }
+}
+
 /// Information about a repository.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RepositoryInfo {
@@ -279,6 +311,16 @@ mod tests {
         assert!(!repo.is_public());
     }
 
+    #[test]
+    fn test_repository_collaborators() {
+        let mut repo = Repository::new("myorg/myrepo");
+        repo.settings.members.push("editor".to_string());
+
+        assert!(repo.is_collaborator("myorg"));
+        assert!(repo.is_collaborator("editor"));
+        assert!(!repo.is_collaborator("myrepo"));
+    }
+
     #[test]
     fn test_status_clean() {
         let status = Status::default();
@@ -0,0 +1,134 @@
+-- Initial schema: users, organizations, repositories and their contents.
+--
+-- Hashes are stored as raw 32-byte BLAKE3 digests. Everything owned by a
+-- repository is removed with it.
+
+-- ==================== Users and organizations ====================
+
+CREATE TABLE users (
+    id          UUID PRIMARY KEY,
+    name        TEXT NOT NULL UNIQUE,
+    email       TEXT NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE organizations (
+    id            UUID PRIMARY KEY,
+    name          TEXT NOT NULL UNIQUE,
+    display_name  TEXT NOT NULL,
+    description   TEXT,
+    avatar_url    TEXT,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE TABLE organization_members (
+    organization_id  UUID NOT NULL REFERENCES organizations (id) ON DELETE CASCADE,
+    user_id          UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role             TEXT NOT NULL CHECK (role IN ('owner', 'admin', 'member', 'reader')),
+    joined_at        TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (organization_id, user_id)
+);
+
+CREATE INDEX organization_members_user_idx ON organization_members (user_id);
+
+-- ==================== Repositories ====================
+
+CREATE TABLE repositories (
+    id              UUID PRIMARY KEY,
+    -- Full `owner/name`.
+    name            TEXT NOT NULL UNIQUE,
+    description     TEXT,
+    default_branch  TEXT NOT NULL,
+    visibility      TEXT NOT NULL CHECK (visibility IN ('public', 'private', 'internal')),
+    settings        JSONB NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL,
+    updated_at      TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE refs (
+    repository_id  UUID NOT NULL REFERENCES repositories (id) ON DELETE CASCADE,
+    -- Full name, e.g. `refs/heads/main`.
+    name           TEXT NOT NULL,
+    commit_hash    BYTEA NOT NULL CHECK (length(commit_hash) = 32),
+    updated_at     TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (repository_id, name)
+);
+
+CREATE TABLE commits (
+    repository_id    UUID NOT NULL REFERENCES repositories (id) ON DELETE CASCADE,
+    hash             BYTEA NOT NULL CHECK (length(hash) = 32),
+    parents          BYTEA[] NOT NULL,
+    tree             BYTEA NOT NULL,
+    author_name      TEXT NOT NULL,
+    author_email     TEXT NOT NULL,
+    committer_name   TEXT NOT NULL,
+    committer_email  TEXT NOT NULL,
+    message          TEXT NOT NULL,
+    signature        TEXT,
+    created_at       TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (repository_id, hash)
+);
+
+CREATE TABLE tags (
+    repository_id  UUID NOT NULL REFERENCES repositories (id) ON DELETE CASCADE,
+    name           TEXT NOT NULL,
+    commit_hash    BYTEA NOT NULL CHECK (length(commit_hash) = 32),
+    message        TEXT,
+    tagger_name    TEXT,
+    tagger_email   TEXT,
+    created_at     TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (repository_id, name)
+);
+
+-- ==================== Chunks ====================
+
+-- Every chunk held in object storage, with the number of repositories
+-- referencing it. A chunk whose count drops to zero can be deleted.
+CREATE TABLE chunks (
+    hash        BYTEA PRIMARY KEY CHECK (length(hash) = 32),
+    size        BIGINT NOT NULL CHECK (size >= 0),
+    ref_count   BIGINT NOT NULL DEFAULT 0 CHECK (ref_count >= 0),
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX chunks_unreferenced_idx ON chunks (created_at) WHERE ref_count = 0;
+
+CREATE TABLE repository_chunks (
+    repository_id  UUID NOT NULL REFERENCES repositories (id) ON DELETE CASCADE,
+    hash           BYTEA NOT NULL REFERENCES chunks (hash),
+    added_at       TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (repository_id, hash)
+);
+
+CREATE INDEX repository_chunks_hash_idx ON repository_chunks (hash);
+
+-- Keep `chunks.ref_count` in step with `repository_chunks`, including rows
+-- removed by deleting a repository.
+CREATE FUNCTION update_chunk_ref_count() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        UPDATE chunks SET ref_count = ref_count + 1 WHERE hash = NEW.hash;
+        RETURN NEW;
+    ELSE
+        UPDATE chunks SET ref_count = ref_count - 1 WHERE hash = OLD.hash;
+        RETURN OLD;
+    END IF;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER repository_chunks_ref_count
+    AFTER INSERT OR DELETE ON repository_chunks
+    FOR EACH ROW EXECUTE FUNCTION update_chunk_ref_count();
+
+-- ==================== Locks ====================
+
+CREATE TABLE locks (
+    id             UUID PRIMARY KEY,
+    repository_id  UUID NOT NULL REFERENCES repositories (id) ON DELETE CASCADE,
+    path           TEXT NOT NULL,
+    owner_id       UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    locked_at      TIMESTAMPTZ NOT NULL,
+    expires_at     TIMESTAMPTZ NOT NULL,
+    reason         TEXT,
+    UNIQUE (repository_id, path)
+);
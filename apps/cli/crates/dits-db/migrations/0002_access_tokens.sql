@@ -0,0 +1,10 @@
+-- Bearer tokens identifying API users. Only a BLAKE3 hash of each token is
+-- kept, so a leaked database does not leak usable credentials.
+
+CREATE TABLE access_tokens (
+    token_hash  BYTEA PRIMARY KEY CHECK (length(token_hash) = 32),
+    user_id     UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX access_tokens_user_idx ON access_tokens (user_id);
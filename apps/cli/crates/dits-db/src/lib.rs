@@ -6,8 +6,174 @@
 //! - Commits and trees
 //! - Chunk references
 //! - Locks
+//!
+//! Servers talk to the [`Database`] trait. [`PgDatabase`] implements it on
+//! top of the typed functions in [`queries`]; [`MemoryDatabase`] implements
+//! it in process for tests and single-node development setups. Background
+//! jobs go through a [`JobQueue`].
 
+pub mod memory;
 pub mod models;
 pub mod pool;
+pub mod postgres;
+pub mod queries;
+pub mod queue;
+
+pub use memory::MemoryDatabase;
+pub use pool::{create_pool, migrate, Pool};
+pub use postgres::PgDatabase;
+pub use queue::{FileJobQueue, JobQueue};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dits_core::{Commit, Hash, Lock, Repository, Result, Tag, User};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Open the database named by `DATABASE_URL`, migrating it first.
+///
+/// Without `DATABASE_URL` an empty [`MemoryDatabase`] is returned, which
+/// loses everything on restart and is not shared between processes.
+pub async fn database_from_env() -> Result<Arc<dyn Database>> {
+    match std::env::var("DATABASE_URL") {
+        Ok(url) => Ok(Arc::new(PgDatabase::connect(&url).await?)),
+        Err(_) => {
+            tracing::warn!("DATABASE_URL is not set; using an in-memory database");
+            Ok(Arc::new(MemoryDatabase::new()))
+        }
+    }
+}
+
+/// One ref move in [`Database::update_refs`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RefChange {
+    /// Full ref name.
+    pub name: String,
+    /// Value the ref must have (`None` if it must not exist).
+    pub expected: Option<Hash>,
+    /// New value (`None` deletes the ref).
+    pub new: Option<Hash>,
+}
+
+/// Removal of a chunk's stored object, run by [`Database::delete_unreferenced_chunk`].
+pub type ObjectDeletion<'a> = Pin<Box<dyn Future<Output = Result<()>> + Send + 'a>>;
+
+/// Metadata storage used by the API server.
+///
+/// Repositories are addressed by their full `owner/name`. Refs use full
+/// names such as `refs/heads/main`.
+#[async_trait]
+pub trait Database: Send + Sync {
+    // ==================== Repositories ====================
+
+    /// Create a repository. Fails with `RepositoryAlreadyExists` if the name is taken.
+    async fn create_repository(&self, repo: Repository) -> Result<Repository>;
+
+    /// Look up a repository by `owner/name`.
+    async fn get_repository(&self, name: &str) -> Result<Option<Repository>>;
+
+    /// List all repositories, ordered by name.
+    async fn list_repositories(&self) -> Result<Vec<Repository>>;
+
+    /// Replace a repository's metadata.
+    async fn update_repository(&self, repo: &Repository) -> Result<()>;
+
+    /// Delete a repository with its refs, commits, tags, chunk references and locks.
+    async fn delete_repository(&self, id: Uuid) -> Result<()>;
+
+    // ==================== Refs ====================
+
+    /// List refs whose name starts with `prefix`, ordered by name.
+    async fn list_refs(&self, repo: Uuid, prefix: &str) -> Result<Vec<(String, Hash)>>;
+
+    /// Get the commit a ref points at.
+    async fn get_ref(&self, repo: Uuid, name: &str) -> Result<Option<Hash>>;
+
+    /// Atomically move a ref from `expected` to `new`.
+    ///
+    /// `expected: None` requires the ref not to exist; `new: None` deletes it.
+    /// Fails with `RefConflict` if the current value differs from `expected`.
+    async fn update_ref(
+        &self,
+        repo: Uuid,
+        name: &str,
+        expected: Option<Hash>,
+        new: Option<Hash>,
+    ) -> Result<()>;
+
+    /// Apply several ref moves as one, with the semantics of [`update_ref`](Self::update_ref).
+    ///
+    /// Either every ref moves or, if any current value differs from what
+    /// its change expects, none does and `RefConflict` names the first.
+    async fn update_refs(&self, repo: Uuid, changes: &[RefChange]) -> Result<()>;
+
+    // ==================== Commits ====================
+
+    /// Store a commit. Storing an existing commit again is a no-op.
+    async fn insert_commit(&self, repo: Uuid, commit: &Commit) -> Result<()>;
+
+    /// Look up a commit by hash.
+    async fn get_commit(&self, repo: Uuid, hash: &Hash) -> Result<Option<Commit>>;
+
+    // ==================== Chunks ====================
+
+    /// Record that a repository references a stored chunk.
+    ///
+    /// Recording an existing reference again refreshes its timestamp.
+    async fn insert_chunk(&self, repo: Uuid, hash: Hash, size: u64) -> Result<()>;
+
+    /// Return the subset of `hashes` the repository does not reference.
+    async fn missing_chunks(&self, repo: Uuid, hashes: &[Hash]) -> Result<Vec<Hash>>;
+
+    /// List the chunks a repository references, with when each was recorded.
+    async fn list_chunks(&self, repo: Uuid) -> Result<Vec<(Hash, DateTime<Utc>)>>;
+
+    /// Drop chunk references. Hashes the repository does not reference are ignored.
+    async fn remove_chunks(&self, repo: Uuid, hashes: &[Hash]) -> Result<()>;
+
+    /// Delete a chunk no repository references: run `delete` to remove its
+    /// stored object, then forget the chunk.
+    ///
+    /// No repository can start referencing the chunk until `delete` has
+    /// finished. Returns `false`, without running `delete`, if a repository
+    /// references the chunk.
+    async fn delete_unreferenced_chunk(&self, hash: Hash, delete: ObjectDeletion<'_>) -> Result<bool>;
+
+    // ==================== Tags ====================
+
+    /// Create a tag. Fails with `TagAlreadyExists` if the name is taken.
+    async fn create_tag(&self, repo: Uuid, tag: Tag) -> Result<()>;
+
+    /// List tags, ordered by name.
+    async fn list_tags(&self, repo: Uuid) -> Result<Vec<Tag>>;
+
+    // ==================== Locks ====================
+
+    /// List unexpired locks, ordered by path.
+    async fn list_locks(&self, repo: Uuid) -> Result<Vec<Lock>>;
+
+    /// Acquire a lock on `lock.path`.
+    ///
+    /// Re-acquiring a path the same user already holds refreshes the existing
+    /// lock. Fails with `FileLocked` if another user holds it.
+    async fn acquire_lock(&self, lock: Lock) -> Result<Lock>;
+
+    /// Release a lock. Fails with `LockOwnerMismatch` unless `user` owns it.
+    async fn release_lock(&self, repo: Uuid, id: Uuid, user: Uuid) -> Result<Lock>;
+
+    // ==================== Users ====================
+
+    /// Look up a user by name.
+    async fn get_user_by_name(&self, name: &str) -> Result<Option<User>>;
+
+    /// Create a user.
+    async fn create_user(&self, user: User) -> Result<User>;
+
+    /// Register an access token for a user, identified by the hash of the token.
+    async fn create_access_token(&self, user: Uuid, token_hash: Hash) -> Result<()>;
 
-pub use pool::Pool;
+    /// Look up the user an access token belongs to.
+    async fn get_user_by_token(&self, token_hash: &Hash) -> Result<Option<User>>;
+}
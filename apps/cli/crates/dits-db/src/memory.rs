@@ -0,0 +1,369 @@
+//! In-process database.
+//!
+//! Keeps all metadata in memory behind a single mutex, which makes every
+//! operation trivially atomic, except that the mutex is not held while
+//! [`Database::delete_unreferenced_chunk`] deletes the stored object. Intended for tests and single-node
+//! development; nothing survives a restart.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dits_core::{Commit, Error, Hash, Lock, Repository, Result, Tag, User};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+use crate::{Database, ObjectDeletion, RefChange};
+
+#[derive(Default)]
+struct RepoData {
+    refs: BTreeMap<String, Hash>,
+    commits: HashMap<Hash, Commit>,
+    /// Chunk size and when the reference was recorded.
+    chunks: HashMap<Hash, (u64, DateTime<Utc>)>,
+    tags: BTreeMap<String, Tag>,
+    locks: BTreeMap<String, Lock>,
+}
+
+#[derive(Default)]
+struct State {
+    repositories: BTreeMap<String, Repository>,
+    data: HashMap<Uuid, RepoData>,
+    users: BTreeMap<String, User>,
+    /// Access token hash to user ID.
+    tokens: HashMap<Hash, Uuid>,
+}
+
+impl State {
+    fn repo(&mut self, id: Uuid) -> Result<&mut RepoData> {
+        self.data
+            .get_mut(&id)
+            .ok_or_else(|| Error::RepositoryNotFound(id.to_string()))
+    }
+}
+
+/// In-memory implementation of [`Database`].
+#[derive(Default)]
+pub struct MemoryDatabase {
+    state: Mutex<State>,
+}
+
+impl MemoryDatabase {
+    /// Create an empty database.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // A panic mid-operation cannot leave a half-applied change behind,
+        // since every method validates before it mutates.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl Database for MemoryDatabase {
+    async fn create_repository(&self, repo: Repository) -> Result<Repository> {
+        let mut state = self.state();
+        if state.repositories.contains_key(&repo.name) {
+            return Err(Error::RepositoryAlreadyExists(repo.name));
+        }
+        state.data.insert(repo.id, RepoData::default());
+        state.repositories.insert(repo.name.clone(), repo.clone());
+        Ok(repo)
+    }
+
+    async fn get_repository(&self, name: &str) -> Result<Option<Repository>> {
+        Ok(self.state().repositories.get(name).cloned())
+    }
+
+    async fn list_repositories(&self) -> Result<Vec<Repository>> {
+        Ok(self.state().repositories.values().cloned().collect())
+    }
+
+    async fn update_repository(&self, repo: &Repository) -> Result<()> {
+        let mut state = self.state();
+        let existing = state
+            .repositories
+            .values_mut()
+            .find(|r| r.id == repo.id)
+            .ok_or_else(|| Error::RepositoryNotFound(repo.name.clone()))?;
+        *existing = repo.clone();
+        existing.updated_at = Utc::now();
+        Ok(())
+    }
+
+    async fn delete_repository(&self, id: Uuid) -> Result<()> {
+        let mut state = self.state();
+        state.repositories.retain(|_, r| r.id != id);
+        state.data.remove(&id);
+        Ok(())
+    }
+
+    async fn list_refs(&self, repo: Uuid, prefix: &str) -> Result<Vec<(String, Hash)>> {
+        let mut state = self.state();
+        Ok(state
+            .repo(repo)?
+            .refs
+            .range(prefix.to_string()..)
+            .take_while(|(name, _)| name.starts_with(prefix))
+            .map(|(name, hash)| (name.clone(), *hash))
+            .collect())
+    }
+
+    async fn get_ref(&self, repo: Uuid, name: &str) -> Result<Option<Hash>> {
+        Ok(self.state().repo(repo)?.refs.get(name).copied())
+    }
+
+    async fn update_ref(
+        &self,
+        repo: Uuid,
+        name: &str,
+        expected: Option<Hash>,
+        new: Option<Hash>,
+    ) -> Result<()> {
+        let mut state = self.state();
+        let refs = &mut state.repo(repo)?.refs;
+        if refs.get(name).copied() != expected {
+            return Err(Error::RefConflict(name.to_string()));
+        }
+        match new {
+            Some(hash) => refs.insert(name.to_string(), hash),
+            None => refs.remove(name),
+        };
+        Ok(())
+    }
+
+    async fn update_refs(&self, repo: Uuid, changes: &[RefChange]) -> Result<()> {
+        let mut state = self.state();
+        let refs = &mut state.repo(repo)?.refs;
+        let mut updated = refs.clone();
+        for change in changes {
+            if updated.get(&change.name).copied() != change.expected {
+                return Err(Error::RefConflict(change.name.clone()));
+            }
+            match change.new {
+                Some(hash) => updated.insert(change.name.clone(), hash),
+                None => updated.remove(&change.name),
+            };
+        }
+        *refs = updated;
+        Ok(())
+    }
+
+    async fn insert_commit(&self, repo: Uuid, commit: &Commit) -> Result<()> {
+        self.state()
+            .repo(repo)?
+            .commits
+            .entry(commit.hash)
+            .or_insert_with(|| commit.clone());
+        Ok(())
+    }
+
+    async fn get_commit(&self, repo: Uuid, hash: &Hash) -> Result<Option<Commit>> {
+        Ok(self.state().repo(repo)?.commits.get(hash).cloned())
+    }
+
+    async fn insert_chunk(&self, repo: Uuid, hash: Hash, size: u64) -> Result<()> {
+        self.state().repo(repo)?.chunks.insert(hash, (size, Utc::now()));
+        Ok(())
+    }
+
+    async fn missing_chunks(&self, repo: Uuid, hashes: &[Hash]) -> Result<Vec<Hash>> {
+        let mut state = self.state();
+        let chunks = &state.repo(repo)?.chunks;
+        Ok(hashes.iter().filter(|h| !chunks.contains_key(h)).copied().collect())
+    }
+
+    async fn list_chunks(&self, repo: Uuid) -> Result<Vec<(Hash, DateTime<Utc>)>> {
+        let mut state = self.state();
+        let mut chunks: Vec<_> = state
+            .repo(repo)?
+            .chunks
+            .iter()
+            .map(|(hash, (_, added_at))| (*hash, *added_at))
+            .collect();
+        chunks.sort();
+        Ok(chunks)
+    }
+
+    async fn remove_chunks(&self, repo: Uuid, hashes: &[Hash]) -> Result<()> {
+        let mut state = self.state();
+        let chunks = &mut state.repo(repo)?.chunks;
+        for hash in hashes {
+            chunks.remove(hash);
+        }
+        Ok(())
+    }
+
+    async fn delete_unreferenced_chunk(&self, hash: Hash, delete: ObjectDeletion<'_>) -> Result<bool> {
+        if self.state().data.values().any(|repo| repo.chunks.contains_key(&hash)) {
+            return Ok(false);
+        }
+        delete.await?;
+        Ok(true)
+    }
+
+    async fn create_tag(&self, repo: Uuid, tag: Tag) -> Result<()> {
+        let mut state = self.state();
+        let tags = &mut state.repo(repo)?.tags;
+        if tags.contains_key(&tag.name) {
+            return Err(Error::TagAlreadyExists(tag.name));
+        }
+        tags.insert(tag.name.clone(), tag);
+        Ok(())
+    }
+
+    async fn list_tags(&self, repo: Uuid) -> Result<Vec<Tag>> {
+        Ok(self.state().repo(repo)?.tags.values().cloned().collect())
+    }
+
+    async fn list_locks(&self, repo: Uuid) -> Result<Vec<Lock>> {
+        let mut state = self.state();
+        let locks = &mut state.repo(repo)?.locks;
+        locks.retain(|_, lock| !lock.is_expired());
+        Ok(locks.values().cloned().collect())
+    }
+
+    async fn acquire_lock(&self, lock: Lock) -> Result<Lock> {
+        let mut state = self.state();
+        let locks = &mut state.repo(lock.repository_id)?.locks;
+        if let Some(existing) = locks.get_mut(&lock.path) {
+            if !existing.is_expired() {
+                if !existing.is_owned_by(lock.owner.id) {
+                    return Err(Error::FileLocked {
+                        path: lock.path,
+                        owner: existing.owner.name.clone(),
+                    });
+                }
+                existing.expires_at = lock.expires_at;
+                if lock.reason.is_some() {
+                    existing.reason = lock.reason;
+                }
+                return Ok(existing.clone());
+            }
+        }
+        locks.insert(lock.path.clone(), lock.clone());
+        Ok(lock)
+    }
+
+    async fn release_lock(&self, repo: Uuid, id: Uuid, user: Uuid) -> Result<Lock> {
+        let mut state = self.state();
+        let locks = &mut state.repo(repo)?.locks;
+        let path = locks
+            .values()
+            .find(|l| l.id == id)
+            .map(|l| l.path.clone())
+            .ok_or_else(|| Error::LockNotFound(id.to_string()))?;
+        if !locks[&path].is_owned_by(user) {
+            return Err(Error::LockOwnerMismatch);
+        }
+        locks
+            .remove(&path)
+            .ok_or_else(|| Error::LockNotFound(id.to_string()))
+    }
+
+    async fn get_user_by_name(&self, name: &str) -> Result<Option<User>> {
+        Ok(self.state().users.get(name).cloned())
+    }
+
+    async fn create_user(&self, user: User) -> Result<User> {
+        let mut state = self.state();
+        if state.users.contains_key(&user.name) {
+            return Err(Error::Database(format!("user already exists: {}", user.name)));
+        }
+        state.users.insert(user.name.clone(), user.clone());
+        Ok(user)
+    }
+
+    async fn create_access_token(&self, user: Uuid, token_hash: Hash) -> Result<()> {
+        let mut state = self.state();
+        if !state.users.values().any(|u| u.id == user) {
+            return Err(Error::Database(format!("user not found: {}", user)));
+        }
+        state.tokens.insert(token_hash, user);
+        Ok(())
+    }
+
+    async fn get_user_by_token(&self, token_hash: &Hash) -> Result<Option<User>> {
+        let state = self.state();
+        Ok(state
+            .tokens
+            .get(token_hash)
+            .and_then(|id| state.users.values().find(|u| u.id == *id))
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dits_core::Hasher;
+
+    #[tokio::test]
+    async fn test_ref_compare_and_swap() {
+        let db = MemoryDatabase::new();
+        let repo = db.create_repository(Repository::new("team/film")).await.unwrap();
+        let (a, b) = (Hasher::hash(b"a"), Hasher::hash(b"b"));
+
+        db.update_ref(repo.id, "refs/heads/main", None, Some(a)).await.unwrap();
+        assert!(matches!(
+            db.update_ref(repo.id, "refs/heads/main", None, Some(b)).await,
+            Err(Error::RefConflict(_))
+        ));
+        db.update_ref(repo.id, "refs/heads/main", Some(a), Some(b)).await.unwrap();
+        assert_eq!(db.get_ref(repo.id, "refs/heads/main").await.unwrap(), Some(b));
+
+        db.update_ref(repo.id, "refs/heads/main", Some(b), None).await.unwrap();
+        assert!(db.list_refs(repo.id, "refs/heads/").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_refs_is_all_or_nothing() {
+        let db = MemoryDatabase::new();
+        let repo = db.create_repository(Repository::new("team/film")).await.unwrap();
+        let (a, b) = (Hasher::hash(b"a"), Hasher::hash(b"b"));
+        let change = |name: &str, expected, new| RefChange { name: name.to_string(), expected, new };
+
+        db.update_ref(repo.id, "refs/heads/main", None, Some(a)).await.unwrap();
+        let result = db
+            .update_refs(repo.id, &[change("refs/heads/edit", None, Some(a)), change("refs/heads/main", Some(b), None)])
+            .await;
+        assert!(matches!(result, Err(Error::RefConflict(name)) if name == "refs/heads/main"));
+        assert_eq!(db.list_refs(repo.id, "refs/").await.unwrap(), vec![("refs/heads/main".to_string(), a)]);
+
+        db.update_refs(repo.id, &[change("refs/heads/edit", None, Some(a)), change("refs/heads/main", Some(a), Some(b))])
+            .await
+            .unwrap();
+        assert_eq!(db.get_ref(repo.id, "refs/heads/edit").await.unwrap(), Some(a));
+        assert_eq!(db.get_ref(repo.id, "refs/heads/main").await.unwrap(), Some(b));
+    }
+
+    #[tokio::test]
+    async fn test_locks_are_exclusive() {
+        let db = MemoryDatabase::new();
+        let repo = db.create_repository(Repository::new("team/film")).await.unwrap();
+        let alice = User::new("alice", "alice@example.com");
+        let bob = User::new("bob", "bob@example.com");
+
+        let lock = db
+            .acquire_lock(Lock::new("edit.prproj", alice.clone(), repo.id))
+            .await
+            .unwrap();
+        assert!(matches!(
+            db.acquire_lock(Lock::new("edit.prproj", bob.clone(), repo.id)).await,
+            Err(Error::FileLocked { .. })
+        ));
+        let again = db
+            .acquire_lock(Lock::new("edit.prproj", alice.clone(), repo.id))
+            .await
+            .unwrap();
+        assert_eq!(again.id, lock.id);
+
+        assert!(matches!(
+            db.release_lock(repo.id, lock.id, bob.id).await,
+            Err(Error::LockOwnerMismatch)
+        ));
+        db.release_lock(repo.id, lock.id, alice.id).await.unwrap();
+        assert!(db.list_locks(repo.id).await.unwrap().is_empty());
+    }
+}
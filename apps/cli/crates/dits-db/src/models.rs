@@ -1,6 +1,219 @@
 //! Database models.
+//!
+//! Row types mirror the tables in `migrations/` and convert into the
+//! `dits_core` types the rest of the server works with.
+
+use chrono::{DateTime, Utc};
+use dits_core::repository::{RepositorySettings, Visibility};
+use dits_core::user::{OrgRole, Organization};
+use dits_core::{Author, Error, Hash, Result};
+use sqlx::types::Json;
+use uuid::Uuid;
 
 // Re-export core types as database models
 pub use dits_core::{
     Branch, Chunk, ChunkMeta, Commit, Lock, Repository, Tag, User,
 };
+
+/// Decode a hash column.
+pub(crate) fn hash_from_db(bytes: &[u8]) -> Result<Hash> {
+    let bytes = bytes
+        .try_into()
+        .map_err(|_| Error::Database(format!("invalid hash of {} bytes", bytes.len())))?;
+    Ok(Hash::from_bytes(bytes))
+}
+
+/// Encode hashes for an `= ANY($n)` or `BYTEA[]` parameter.
+pub(crate) fn hashes_to_db(hashes: &[Hash]) -> Vec<Vec<u8>> {
+    hashes.iter().map(|h| h.as_bytes().to_vec()).collect()
+}
+
+pub(crate) fn visibility_to_db(visibility: Visibility) -> &'static str {
+    match visibility {
+        Visibility::Public => "public",
+        Visibility::Private => "private",
+        Visibility::Internal => "internal",
+    }
+}
+
+fn visibility_from_db(value: &str) -> Result<Visibility> {
+    match value {
+        "public" => Ok(Visibility::Public),
+        "private" => Ok(Visibility::Private),
+        "internal" => Ok(Visibility::Internal),
+        other => Err(Error::Database(format!("unknown visibility: {}", other))),
+    }
+}
+
+pub(crate) fn role_to_db(role: OrgRole) -> &'static str {
+    match role {
+        OrgRole::Owner => "owner",
+        OrgRole::Admin => "admin",
+        OrgRole::Member => "member",
+        OrgRole::Reader => "reader",
+    }
+}
+
+pub(crate) fn role_from_db(value: &str) -> Result<OrgRole> {
+    match value {
+        "owner" => Ok(OrgRole::Owner),
+        "admin" => Ok(OrgRole::Admin),
+        "member" => Ok(OrgRole::Member),
+        "reader" => Ok(OrgRole::Reader),
+        other => Err(Error::Database(format!("unknown organization role: {}", other))),
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct RepositoryRow {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub default_branch: String,
+    pub visibility: String,
+    pub settings: Json<RepositorySettings>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl TryFrom<RepositoryRow> for Repository {
+    type Error = Error;
+
+    fn try_from(row: RepositoryRow) -> Result<Self> {
+        Ok(Self {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            default_branch: row.default_branch,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            visibility: visibility_from_db(&row.visibility)?,
+            settings: row.settings.0,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct UserRow {
+    pub id: Uuid,
+    pub name: String,
+    pub email: String,
+}
+
+impl From<UserRow> for User {
+    fn from(row: UserRow) -> Self {
+        User::with_id(row.id, row.name, row.email)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct OrganizationRow {
+    pub id: Uuid,
+    pub name: String,
+    pub display_name: String,
+    pub description: Option<String>,
+    pub avatar_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<OrganizationRow> for Organization {
+    fn from(row: OrganizationRow) -> Self {
+        Self {
+            id: row.id,
+            name: row.name,
+            display_name: row.display_name,
+            description: row.description,
+            avatar_url: row.avatar_url,
+            created_at: row.created_at,
+        }
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct CommitRow {
+    pub hash: Vec<u8>,
+    pub parents: Vec<Vec<u8>>,
+    pub tree: Vec<u8>,
+    pub author_name: String,
+    pub author_email: String,
+    pub committer_name: String,
+    pub committer_email: String,
+    pub message: String,
+    pub signature: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<CommitRow> for Commit {
+    type Error = Error;
+
+    fn try_from(row: CommitRow) -> Result<Self> {
+        Ok(Self {
+            hash: hash_from_db(&row.hash)?,
+            parents: row
+                .parents
+                .iter()
+                .map(|p| hash_from_db(p))
+                .collect::<Result<_>>()?,
+            tree: hash_from_db(&row.tree)?,
+            author: Author::new(row.author_name, row.author_email),
+            committer: Author::new(row.committer_name, row.committer_email),
+            message: row.message,
+            created_at: row.created_at,
+            signature: row.signature,
+        })
+    }
+}
+
+#[derive(sqlx::FromRow)]
+pub(crate) struct TagRow {
+    pub name: String,
+    pub commit_hash: Vec<u8>,
+    pub message: Option<String>,
+    pub tagger_name: Option<String>,
+    pub tagger_email: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<TagRow> for Tag {
+    type Error = Error;
+
+    fn try_from(row: TagRow) -> Result<Self> {
+        Ok(Self {
+            name: row.name,
+            commit: hash_from_db(&row.commit_hash)?,
+            message: row.message,
+            tagger: row
+                .tagger_name
+                .map(|name| Author::new(name, row.tagger_email.unwrap_or_default())),
+            created_at: row.created_at,
+        })
+    }
+}
+
+/// A lock joined with its owner.
+#[derive(sqlx::FromRow)]
+pub(crate) struct LockRow {
+    pub id: Uuid,
+    pub repository_id: Uuid,
+    pub path: String,
+    pub owner_id: Uuid,
+    pub owner_name: String,
+    pub owner_email: String,
+    pub locked_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+impl From<LockRow> for Lock {
+    fn from(row: LockRow) -> Self {
+        Self {
+            id: row.id,
+            path: row.path,
+            owner: User::with_id(row.owner_id, row.owner_name, row.owner_email),
+            repository_id: row.repository_id,
+            locked_at: row.locked_at,
+            expires_at: row.expires_at,
+            reason: row.reason,
+        }
+    }
+}
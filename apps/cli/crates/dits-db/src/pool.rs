@@ -1,11 +1,15 @@
 //! Database connection pool.
 
+use sqlx::migrate::{MigrateError, Migrator};
 use sqlx::postgres::{PgPool, PgPoolOptions};
 use std::time::Duration;
 
 /// Database connection pool.
 pub type Pool = PgPool;
 
+/// Schema migrations embedded from `migrations/`.
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 /// Create a new connection pool.
 pub async fn create_pool(database_url: &str) -> Result<Pool, sqlx::Error> {
     PgPoolOptions::new()
@@ -16,3 +20,8 @@ pub async fn create_pool(database_url: &str) -> Result<Pool, sqlx::Error> {
         .connect(database_url)
         .await
 }
+
+/// Apply any migrations the database has not seen yet.
+pub async fn migrate(pool: &Pool) -> Result<(), MigrateError> {
+    MIGRATOR.run(pool).await
+}
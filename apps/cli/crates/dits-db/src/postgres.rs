@@ -0,0 +1,368 @@
+//! PostgreSQL database.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dits_core::{Commit, Error, Hash, Lock, Repository, Result, Tag, User};
+use uuid::Uuid;
+
+use crate::pool::{create_pool, migrate, Pool};
+use crate::queries::{self, db_error};
+use crate::{Database, ObjectDeletion, RefChange};
+
+/// [`Database`] backed by PostgreSQL through [`queries`].
+pub struct PgDatabase {
+    pool: Pool,
+}
+
+impl PgDatabase {
+    /// Wrap an existing pool. The schema must already be migrated.
+    pub fn new(pool: Pool) -> Self {
+        Self { pool }
+    }
+
+    /// Connect to `database_url` and bring the schema up to date.
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let pool = create_pool(database_url).await.map_err(db_error)?;
+        migrate(&pool)
+            .await
+            .map_err(|e| Error::Database(format!("migration failed: {}", e)))?;
+        Ok(Self::new(pool))
+    }
+
+    /// The underlying pool, for queries outside the [`Database`] trait.
+    pub fn pool(&self) -> &Pool {
+        &self.pool
+    }
+}
+
+#[async_trait]
+impl Database for PgDatabase {
+    async fn create_repository(&self, repo: Repository) -> Result<Repository> {
+        queries::repositories::create(&self.pool, &repo).await?;
+        Ok(repo)
+    }
+
+    async fn get_repository(&self, name: &str) -> Result<Option<Repository>> {
+        queries::repositories::get_by_name(&self.pool, name).await
+    }
+
+    async fn list_repositories(&self) -> Result<Vec<Repository>> {
+        queries::repositories::list(&self.pool).await
+    }
+
+    async fn update_repository(&self, repo: &Repository) -> Result<()> {
+        queries::repositories::update(&self.pool, repo).await
+    }
+
+    async fn delete_repository(&self, id: Uuid) -> Result<()> {
+        queries::repositories::delete(&self.pool, id).await
+    }
+
+    async fn list_refs(&self, repo: Uuid, prefix: &str) -> Result<Vec<(String, Hash)>> {
+        queries::refs::list(&self.pool, repo, prefix).await
+    }
+
+    async fn get_ref(&self, repo: Uuid, name: &str) -> Result<Option<Hash>> {
+        queries::refs::get(&self.pool, repo, name).await
+    }
+
+    async fn update_ref(
+        &self,
+        repo: Uuid,
+        name: &str,
+        expected: Option<Hash>,
+        new: Option<Hash>,
+    ) -> Result<()> {
+        queries::refs::compare_and_swap(&self.pool, repo, name, expected, new).await
+    }
+
+    async fn update_refs(&self, repo: Uuid, changes: &[RefChange]) -> Result<()> {
+        // Dropping the transaction on a conflict rolls back the earlier moves
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        for change in changes {
+            queries::refs::compare_and_swap(&mut *tx, repo, &change.name, change.expected, change.new).await?;
+        }
+        tx.commit().await.map_err(db_error)
+    }
+
+    async fn insert_commit(&self, repo: Uuid, commit: &Commit) -> Result<()> {
+        queries::commits::insert(&self.pool, repo, commit).await
+    }
+
+    async fn get_commit(&self, repo: Uuid, hash: &Hash) -> Result<Option<Commit>> {
+        queries::commits::get(&self.pool, repo, hash).await
+    }
+
+    async fn insert_chunk(&self, repo: Uuid, hash: Hash, size: u64) -> Result<()> {
+        queries::chunks::insert(&self.pool, repo, hash, size).await
+    }
+
+    async fn missing_chunks(&self, repo: Uuid, hashes: &[Hash]) -> Result<Vec<Hash>> {
+        queries::chunks::missing(&self.pool, repo, hashes).await
+    }
+
+    async fn list_chunks(&self, repo: Uuid) -> Result<Vec<(Hash, DateTime<Utc>)>> {
+        queries::chunks::list(&self.pool, repo).await
+    }
+
+    async fn remove_chunks(&self, repo: Uuid, hashes: &[Hash]) -> Result<()> {
+        queries::chunks::remove(&self.pool, repo, hashes).await
+    }
+
+    async fn delete_unreferenced_chunk(&self, hash: Hash, delete: ObjectDeletion<'_>) -> Result<bool> {
+        // The row lock holds back new references until the object is gone;
+        // dropping the transaction early rolls back the lock's placeholder row
+        let mut tx = self.pool.begin().await.map_err(db_error)?;
+        if queries::chunks::lock(&mut tx, &hash).await? > 0 {
+            return Ok(false);
+        }
+        delete.await?;
+        queries::chunks::delete_unreferenced(&mut *tx, &[hash]).await?;
+        tx.commit().await.map_err(db_error)?;
+        Ok(true)
+    }
+
+    async fn create_tag(&self, repo: Uuid, tag: Tag) -> Result<()> {
+        queries::tags::create(&self.pool, repo, &tag).await
+    }
+
+    async fn list_tags(&self, repo: Uuid) -> Result<Vec<Tag>> {
+        queries::tags::list(&self.pool, repo).await
+    }
+
+    async fn list_locks(&self, repo: Uuid) -> Result<Vec<Lock>> {
+        queries::locks::list(&self.pool, repo).await
+    }
+
+    async fn acquire_lock(&self, lock: Lock) -> Result<Lock> {
+        let mut conn = self.pool.acquire().await.map_err(db_error)?;
+        queries::locks::acquire(&mut conn, &lock).await
+    }
+
+    async fn release_lock(&self, repo: Uuid, id: Uuid, user: Uuid) -> Result<Lock> {
+        let mut conn = self.pool.acquire().await.map_err(db_error)?;
+        queries::locks::release(&mut conn, repo, id, user).await
+    }
+
+    async fn get_user_by_name(&self, name: &str) -> Result<Option<User>> {
+        queries::users::get_by_name(&self.pool, name).await
+    }
+
+    async fn create_user(&self, user: User) -> Result<User> {
+        queries::users::create(&self.pool, &user).await?;
+        Ok(user)
+    }
+
+    async fn create_access_token(&self, user: Uuid, token_hash: Hash) -> Result<()> {
+        queries::users::create_token(&self.pool, user, &token_hash).await
+    }
+
+    async fn get_user_by_token(&self, token_hash: &Hash) -> Result<Option<User>> {
+        queries::users::get_by_token(&self.pool, token_hash).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! These run against the database in `DITS_TEST_DATABASE_URL`, so they
+    //! are ignored by default. Run them with `cargo test -- --ignored`.
+
+    use super::*;
+    use dits_core::Hasher;
+
+    async fn test_db() -> PgDatabase {
+        let url = std::env::var("DITS_TEST_DATABASE_URL").expect("DITS_TEST_DATABASE_URL must be set");
+        PgDatabase::connect(&url).await.expect("connect to test database")
+    }
+
+    fn unique(name: &str) -> String {
+        format!("{}-{}", name, Uuid::new_v4())
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DITS_TEST_DATABASE_URL"]
+    async fn test_ref_compare_and_swap() {
+        let db = test_db().await;
+        let repo = db.create_repository(Repository::new(unique("team/film"))).await.unwrap();
+        let (a, b) = (Hasher::hash(b"a"), Hasher::hash(b"b"));
+
+        db.update_ref(repo.id, "refs/heads/main", None, Some(a)).await.unwrap();
+        assert!(matches!(
+            db.update_ref(repo.id, "refs/heads/main", None, Some(b)).await,
+            Err(Error::RefConflict(_))
+        ));
+        assert!(matches!(
+            db.update_ref(repo.id, "refs/heads/main", Some(b), Some(a)).await,
+            Err(Error::RefConflict(_))
+        ));
+        db.update_ref(repo.id, "refs/heads/main", Some(a), Some(b)).await.unwrap();
+        assert_eq!(db.get_ref(repo.id, "refs/heads/main").await.unwrap(), Some(b));
+        assert_eq!(db.list_refs(repo.id, "refs/heads/").await.unwrap().len(), 1);
+
+        db.update_ref(repo.id, "refs/heads/main", Some(b), None).await.unwrap();
+        assert!(db.list_refs(repo.id, "refs/heads/").await.unwrap().is_empty());
+        db.delete_repository(repo.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DITS_TEST_DATABASE_URL"]
+    async fn test_update_refs_is_all_or_nothing() {
+        let db = test_db().await;
+        let repo = db.create_repository(Repository::new(unique("team/film"))).await.unwrap();
+        let (a, b) = (Hasher::hash(b"a"), Hasher::hash(b"b"));
+        let change = |name: &str, expected, new| RefChange { name: name.to_string(), expected, new };
+
+        db.update_ref(repo.id, "refs/heads/main", None, Some(a)).await.unwrap();
+        let result = db
+            .update_refs(repo.id, &[change("refs/heads/edit", None, Some(a)), change("refs/heads/main", Some(b), None)])
+            .await;
+        assert!(matches!(result, Err(Error::RefConflict(name)) if name == "refs/heads/main"));
+        assert_eq!(db.get_ref(repo.id, "refs/heads/edit").await.unwrap(), None);
+
+        db.update_refs(repo.id, &[change("refs/heads/edit", None, Some(a)), change("refs/heads/main", Some(a), Some(b))])
+            .await
+            .unwrap();
+        assert_eq!(db.get_ref(repo.id, "refs/heads/main").await.unwrap(), Some(b));
+        db.delete_repository(repo.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DITS_TEST_DATABASE_URL"]
+    async fn test_commits_and_tags_round_trip() {
+        let db = test_db().await;
+        let repo = db.create_repository(Repository::new(unique("team/film"))).await.unwrap();
+        let author = dits_core::Author::new("Alice", "alice@example.com");
+        let root = Commit::new(vec![], Hasher::hash(b"tree"), author.clone(), "init");
+        let child = Commit::new(vec![root.hash], Hasher::hash(b"tree2"), author, "edit");
+
+        db.insert_commit(repo.id, &root).await.unwrap();
+        db.insert_commit(repo.id, &child).await.unwrap();
+        db.insert_commit(repo.id, &child).await.unwrap();
+        let stored = db.get_commit(repo.id, &child.hash).await.unwrap().unwrap();
+        assert_eq!(stored.parents, vec![root.hash]);
+        assert_eq!(stored.tree, child.tree);
+        assert_eq!(stored.author.email, "alice@example.com");
+        assert!(db.get_commit(repo.id, &Hasher::hash(b"none")).await.unwrap().is_none());
+
+        let tag = Tag {
+            name: "v1".to_string(),
+            commit: child.hash,
+            message: None,
+            tagger: None,
+            created_at: Utc::now(),
+        };
+        db.create_tag(repo.id, tag.clone()).await.unwrap();
+        assert!(matches!(
+            db.create_tag(repo.id, tag).await,
+            Err(Error::TagAlreadyExists(_))
+        ));
+        assert_eq!(db.list_tags(repo.id).await.unwrap()[0].commit, child.hash);
+        db.delete_repository(repo.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DITS_TEST_DATABASE_URL"]
+    async fn test_chunk_ref_counts() {
+        let db = test_db().await;
+        let first = db.create_repository(Repository::new(unique("team/a"))).await.unwrap();
+        let second = db.create_repository(Repository::new(unique("team/b"))).await.unwrap();
+        let chunk = Hasher::hash(unique("chunk").as_bytes());
+        let pool = db.pool();
+
+        db.insert_chunk(first.id, chunk, 4).await.unwrap();
+        db.insert_chunk(first.id, chunk, 4).await.unwrap();
+        db.insert_chunk(second.id, chunk, 4).await.unwrap();
+        assert_eq!(queries::chunks::ref_count(pool, &chunk).await.unwrap(), Some(2));
+        assert!(db.missing_chunks(first.id, &[chunk]).await.unwrap().is_empty());
+
+        db.remove_chunks(first.id, &[chunk]).await.unwrap();
+        assert_eq!(db.missing_chunks(first.id, &[chunk]).await.unwrap(), vec![chunk]);
+        db.delete_repository(second.id).await.unwrap();
+        assert_eq!(queries::chunks::ref_count(pool, &chunk).await.unwrap(), Some(0));
+
+        let unreferenced = queries::chunks::unreferenced(pool, Utc::now(), i64::MAX).await.unwrap();
+        assert!(unreferenced.contains(&chunk));
+        let deleted = queries::chunks::delete_unreferenced(pool, &[chunk]).await.unwrap();
+        assert_eq!(deleted, vec![chunk]);
+        db.delete_repository(first.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DITS_TEST_DATABASE_URL"]
+    async fn test_locks_are_exclusive() {
+        let db = test_db().await;
+        let repo = db.create_repository(Repository::new(unique("team/film"))).await.unwrap();
+        let alice = db.create_user(User::new(unique("alice"), "alice@example.com")).await.unwrap();
+        let bob = db.create_user(User::new(unique("bob"), "bob@example.com")).await.unwrap();
+        let token = Hasher::hash(unique("token").as_bytes());
+        db.create_access_token(bob.id, token).await.unwrap();
+        assert_eq!(db.get_user_by_token(&token).await.unwrap().map(|u| u.id), Some(bob.id));
+
+        let lock = db
+            .acquire_lock(Lock::new("edit.prproj", alice.clone(), repo.id))
+            .await
+            .unwrap();
+        assert!(matches!(
+            db.acquire_lock(Lock::new("edit.prproj", bob.clone(), repo.id)).await,
+            Err(Error::FileLocked { owner, .. }) if owner == alice.name
+        ));
+        let again = db
+            .acquire_lock(Lock::new("edit.prproj", alice.clone(), repo.id))
+            .await
+            .unwrap();
+        assert_eq!(again.id, lock.id);
+
+        assert!(matches!(
+            db.release_lock(repo.id, lock.id, bob.id).await,
+            Err(Error::LockOwnerMismatch)
+        ));
+        let released = db.release_lock(repo.id, lock.id, alice.id).await.unwrap();
+        assert_eq!(released.owner.name, alice.name);
+        assert!(db.list_locks(repo.id).await.unwrap().is_empty());
+
+        // An expired lock can be taken over.
+        let mut stale = Lock::new("edit.prproj", alice.clone(), repo.id);
+        stale.expires_at = Utc::now() - chrono::Duration::minutes(1);
+        db.acquire_lock(stale).await.unwrap();
+        let taken = db
+            .acquire_lock(Lock::new("edit.prproj", bob.clone(), repo.id))
+            .await
+            .unwrap();
+        assert_eq!(db.list_locks(repo.id).await.unwrap()[0].id, taken.id);
+        db.delete_repository(repo.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "needs DITS_TEST_DATABASE_URL"]
+    async fn test_organization_members() {
+        use dits_core::user::{OrgRole, Organization};
+
+        let db = test_db().await;
+        let pool = db.pool();
+        let org = Organization {
+            id: Uuid::new_v4(),
+            name: unique("studio"),
+            display_name: "Studio".to_string(),
+            description: None,
+            avatar_url: None,
+            created_at: Utc::now(),
+        };
+        queries::organizations::create(pool, &org).await.unwrap();
+        let alice = db.create_user(User::new(unique("alice"), "alice@example.com")).await.unwrap();
+
+        queries::organizations::set_member(pool, org.id, alice.id, OrgRole::Member).await.unwrap();
+        queries::organizations::set_member(pool, org.id, alice.id, OrgRole::Admin).await.unwrap();
+        assert_eq!(
+            queries::organizations::member_role(pool, org.id, alice.id).await.unwrap(),
+            Some(OrgRole::Admin)
+        );
+        let members = queries::organizations::list_members(pool, org.id).await.unwrap();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].0.id, alice.id);
+
+        queries::organizations::remove_member(pool, org.id, alice.id).await.unwrap();
+        assert!(queries::organizations::member_role(pool, org.id, alice.id).await.unwrap().is_none());
+        let found = queries::organizations::get_by_name(pool, &org.name).await.unwrap();
+        assert_eq!(found.map(|o| o.id), Some(org.id));
+    }
+}
@@ -1,7 +1,32 @@
 //! Database queries.
+//!
+//! Each function takes any Postgres executor, so it can run against the pool
+//! directly or inside a transaction alongside other queries.
 
 pub mod chunks;
 pub mod commits;
 pub mod locks;
+pub mod organizations;
+pub mod refs;
 pub mod repositories;
+pub mod tags;
 pub mod users;
+
+use dits_core::Error;
+
+/// Map a driver error onto the Dits error type.
+///
+/// Connection problems become retryable errors; everything else is a
+/// `Database` error.
+pub(crate) fn db_error(err: sqlx::Error) -> Error {
+    match err {
+        sqlx::Error::PoolTimedOut => Error::Timeout,
+        sqlx::Error::Io(e) => Error::Network(e.to_string()),
+        other => Error::Database(other.to_string()),
+    }
+}
+
+/// Whether `err` is a violation of a unique or primary key constraint.
+pub(crate) fn is_unique_violation(err: &sqlx::Error) -> bool {
+    matches!(err, sqlx::Error::Database(e) if e.is_unique_violation())
+}
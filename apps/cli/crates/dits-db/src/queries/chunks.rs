@@ -0,0 +1,138 @@
+//! Chunk reference queries.
+//!
+//! `repository_chunks` records which repositories reference which chunk.
+//! A trigger keeps `chunks.ref_count` equal to the number of those rows,
+//! so a chunk whose count is zero is no longer needed by any repository.
+
+use chrono::{DateTime, Utc};
+use dits_core::{Hash, Result};
+use sqlx::{PgConnection, PgExecutor};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use super::db_error;
+use crate::models::{hash_from_db, hashes_to_db};
+
+/// Record that a repository references a stored chunk.
+///
+/// Recording an existing reference again refreshes its timestamp.
+pub async fn insert<'e>(db: impl PgExecutor<'e>, repo: Uuid, hash: Hash, size: u64) -> Result<()> {
+    let size = i64::try_from(size).unwrap_or(i64::MAX);
+    sqlx::query(
+        "WITH chunk AS ( \
+             INSERT INTO chunks (hash, size) VALUES ($2, $3) ON CONFLICT (hash) DO NOTHING \
+         ) \
+         INSERT INTO repository_chunks (repository_id, hash) VALUES ($1, $2) \
+         ON CONFLICT (repository_id, hash) DO UPDATE SET added_at = now()",
+    )
+    .bind(repo)
+    .bind(hash.as_bytes().as_slice())
+    .bind(size)
+    .execute(db)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Return the subset of `hashes` the repository does not reference, in order.
+pub async fn missing<'e>(db: impl PgExecutor<'e>, repo: Uuid, hashes: &[Hash]) -> Result<Vec<Hash>> {
+    let rows: Vec<(Vec<u8>,)> = sqlx::query_as(
+        "SELECT hash FROM repository_chunks WHERE repository_id = $1 AND hash = ANY($2)",
+    )
+    .bind(repo)
+    .bind(hashes_to_db(hashes))
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+    let present = rows
+        .iter()
+        .map(|(hash,)| hash_from_db(hash))
+        .collect::<Result<HashSet<_>>>()?;
+    Ok(hashes.iter().filter(|h| !present.contains(h)).copied().collect())
+}
+
+/// List the chunks a repository references, with when each was recorded.
+pub async fn list<'e>(db: impl PgExecutor<'e>, repo: Uuid) -> Result<Vec<(Hash, DateTime<Utc>)>> {
+    let rows: Vec<(Vec<u8>, DateTime<Utc>)> = sqlx::query_as(
+        "SELECT hash, added_at FROM repository_chunks WHERE repository_id = $1 ORDER BY hash",
+    )
+    .bind(repo)
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+    rows.into_iter()
+        .map(|(hash, added_at)| Ok((hash_from_db(&hash)?, added_at)))
+        .collect()
+}
+
+/// Drop a repository's references to `hashes`. Unreferenced hashes are ignored.
+pub async fn remove<'e>(db: impl PgExecutor<'e>, repo: Uuid, hashes: &[Hash]) -> Result<()> {
+    sqlx::query("DELETE FROM repository_chunks WHERE repository_id = $1 AND hash = ANY($2)")
+        .bind(repo)
+        .bind(hashes_to_db(hashes))
+        .execute(db)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Number of repositories referencing a chunk, or `None` if it is unknown.
+pub async fn ref_count<'e>(db: impl PgExecutor<'e>, hash: &Hash) -> Result<Option<u64>> {
+    let row: Option<(i64,)> = sqlx::query_as("SELECT ref_count FROM chunks WHERE hash = $1")
+        .bind(hash.as_bytes().as_slice())
+        .fetch_optional(db)
+        .await
+        .map_err(db_error)?;
+    Ok(row.map(|(count,)| count.max(0) as u64))
+}
+
+/// Lock a chunk's row until the transaction ends and return its reference count.
+///
+/// A row is created for a chunk the database does not know, so that an
+/// upload starting to reference the chunk waits for the lock as well.
+pub async fn lock(conn: &mut PgConnection, hash: &Hash) -> Result<u64> {
+    sqlx::query("INSERT INTO chunks (hash, size) VALUES ($1, 0) ON CONFLICT (hash) DO NOTHING")
+        .bind(hash.as_bytes().as_slice())
+        .execute(&mut *conn)
+        .await
+        .map_err(db_error)?;
+    let (count,): (i64,) = sqlx::query_as("SELECT ref_count FROM chunks WHERE hash = $1 FOR UPDATE")
+        .bind(hash.as_bytes().as_slice())
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(db_error)?;
+    Ok(count.max(0) as u64)
+}
+
+/// List up to `limit` chunks no repository references, created before `before`.
+pub async fn unreferenced<'e>(
+    db: impl PgExecutor<'e>,
+    before: DateTime<Utc>,
+    limit: i64,
+) -> Result<Vec<Hash>> {
+    let rows: Vec<(Vec<u8>,)> = sqlx::query_as(
+        "SELECT hash FROM chunks WHERE ref_count = 0 AND created_at < $1 \
+         ORDER BY created_at LIMIT $2",
+    )
+    .bind(before)
+    .bind(limit)
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+    rows.iter().map(|(hash,)| hash_from_db(hash)).collect()
+}
+
+/// Forget chunks that are still unreferenced. Returns the hashes removed.
+///
+/// A chunk referenced again since [`unreferenced`] listed it is kept, so
+/// only the returned chunks may be deleted from object storage.
+pub async fn delete_unreferenced<'e>(db: impl PgExecutor<'e>, hashes: &[Hash]) -> Result<Vec<Hash>> {
+    let rows: Vec<(Vec<u8>,)> = sqlx::query_as(
+        "DELETE FROM chunks WHERE hash = ANY($1) AND ref_count = 0 RETURNING hash",
+    )
+    .bind(hashes_to_db(hashes))
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+    rows.iter().map(|(hash,)| hash_from_db(hash)).collect()
+}
@@ -0,0 +1,49 @@
+//! Commit queries.
+
+use dits_core::{Commit, Hash, Result};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::db_error;
+use crate::models::{hashes_to_db, CommitRow};
+
+/// Store a commit. Storing an existing commit again is a no-op.
+pub async fn insert<'e>(db: impl PgExecutor<'e>, repo: Uuid, commit: &Commit) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO commits (repository_id, hash, parents, tree, author_name, author_email, \
+         committer_name, committer_email, message, signature, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+         ON CONFLICT (repository_id, hash) DO NOTHING",
+    )
+    .bind(repo)
+    .bind(commit.hash.as_bytes().as_slice())
+    .bind(hashes_to_db(&commit.parents))
+    .bind(commit.tree.as_bytes().as_slice())
+    .bind(&commit.author.name)
+    .bind(&commit.author.email)
+    .bind(&commit.committer.name)
+    .bind(&commit.committer.email)
+    .bind(&commit.message)
+    .bind(&commit.signature)
+    .bind(commit.created_at)
+    .execute(db)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Look up a commit by hash.
+pub async fn get<'e>(db: impl PgExecutor<'e>, repo: Uuid, hash: &Hash) -> Result<Option<Commit>> {
+    sqlx::query_as::<_, CommitRow>(
+        "SELECT hash, parents, tree, author_name, author_email, committer_name, \
+         committer_email, message, signature, created_at \
+         FROM commits WHERE repository_id = $1 AND hash = $2",
+    )
+    .bind(repo)
+    .bind(hash.as_bytes().as_slice())
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?
+    .map(Commit::try_from)
+    .transpose()
+}
@@ -0,0 +1,146 @@
+//! Lock queries.
+//!
+//! Each path has at most one row per repository. An expired row stays until
+//! it is replaced, so every read filters on `expires_at`.
+
+use chrono::{DateTime, Utc};
+use dits_core::{Error, Lock, Result};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use super::db_error;
+use crate::models::LockRow;
+
+const COLUMNS: &str = "l.id, l.repository_id, l.path, l.owner_id, \
+     u.name AS owner_name, u.email AS owner_email, l.locked_at, l.expires_at, l.reason";
+
+/// `id, locked_at, expires_at, reason` of the lock that ended up held.
+type AcquiredRow = (Uuid, DateTime<Utc>, DateTime<Utc>, Option<String>);
+
+/// List unexpired locks, ordered by path.
+pub async fn list<'e>(db: impl PgExecutor<'e>, repo: Uuid) -> Result<Vec<Lock>> {
+    let rows: Vec<LockRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM locks l JOIN users u ON u.id = l.owner_id \
+         WHERE l.repository_id = $1 AND l.expires_at >= $2 ORDER BY l.path",
+        COLUMNS
+    ))
+    .bind(repo)
+    .bind(Utc::now())
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+    Ok(rows.into_iter().map(Lock::from).collect())
+}
+
+/// Get the unexpired lock on a path.
+pub async fn get_by_path<'e>(db: impl PgExecutor<'e>, repo: Uuid, path: &str) -> Result<Option<Lock>> {
+    let row: Option<LockRow> = sqlx::query_as(&format!(
+        "SELECT {} FROM locks l JOIN users u ON u.id = l.owner_id \
+         WHERE l.repository_id = $1 AND l.path = $2 AND l.expires_at >= $3",
+        COLUMNS
+    ))
+    .bind(repo)
+    .bind(path)
+    .bind(Utc::now())
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?;
+    Ok(row.map(Lock::from))
+}
+
+/// Acquire a lock on `lock.path`. The owner must exist in `users`.
+///
+/// Re-acquiring a path the same user already holds keeps the existing lock
+/// and moves its expiry. Fails with `FileLocked` if another user holds it.
+pub async fn acquire(conn: &mut PgConnection, lock: &Lock) -> Result<Lock> {
+    let now = Utc::now();
+    // Insert, take over an expired lock, or refresh our own, all in one
+    // statement so two users racing for a path cannot both win.
+    let row: Option<AcquiredRow> = sqlx::query_as(
+        "INSERT INTO locks (id, repository_id, path, owner_id, locked_at, expires_at, reason) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7) \
+         ON CONFLICT (repository_id, path) DO UPDATE SET \
+             id = CASE WHEN locks.expires_at < $8 THEN excluded.id ELSE locks.id END, \
+             owner_id = excluded.owner_id, \
+             locked_at = CASE WHEN locks.expires_at < $8 \
+                 THEN excluded.locked_at ELSE locks.locked_at END, \
+             expires_at = excluded.expires_at, \
+             reason = CASE WHEN locks.expires_at < $8 \
+                 THEN excluded.reason ELSE coalesce(excluded.reason, locks.reason) END \
+         WHERE locks.expires_at < $8 OR locks.owner_id = excluded.owner_id \
+         RETURNING id, locked_at, expires_at, reason",
+    )
+    .bind(lock.id)
+    .bind(lock.repository_id)
+    .bind(&lock.path)
+    .bind(lock.owner.id)
+    .bind(lock.locked_at)
+    .bind(lock.expires_at)
+    .bind(&lock.reason)
+    .bind(now)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(db_error)?;
+
+    if let Some((id, locked_at, expires_at, reason)) = row {
+        return Ok(Lock {
+            id,
+            locked_at,
+            expires_at,
+            reason,
+            ..lock.clone()
+        });
+    }
+
+    let owner = get_by_path(&mut *conn, lock.repository_id, &lock.path)
+        .await?
+        .map(|held| held.owner.name)
+        .unwrap_or_default();
+    Err(Error::FileLocked {
+        path: lock.path.clone(),
+        owner,
+    })
+}
+
+/// Release a lock. Fails with `LockOwnerMismatch` unless `user` owns it.
+pub async fn release(conn: &mut PgConnection, repo: Uuid, id: Uuid, user: Uuid) -> Result<Lock> {
+    let row: Option<LockRow> = sqlx::query_as(&format!(
+        "WITH released AS ( \
+             DELETE FROM locks WHERE repository_id = $1 AND id = $2 AND owner_id = $3 \
+             RETURNING * \
+         ) \
+         SELECT {} FROM released l JOIN users u ON u.id = l.owner_id",
+        COLUMNS
+    ))
+    .bind(repo)
+    .bind(id)
+    .bind(user)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(db_error)?;
+    if let Some(row) = row {
+        return Ok(row.into());
+    }
+
+    let exists: Option<(Uuid,)> =
+        sqlx::query_as("SELECT owner_id FROM locks WHERE repository_id = $1 AND id = $2")
+            .bind(repo)
+            .bind(id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(db_error)?;
+    match exists {
+        Some(_) => Err(Error::LockOwnerMismatch),
+        None => Err(Error::LockNotFound(id.to_string())),
+    }
+}
+
+/// Delete locks that expired before `before`. Returns how many.
+pub async fn delete_expired<'e>(db: impl PgExecutor<'e>, before: DateTime<Utc>) -> Result<u64> {
+    let result = sqlx::query("DELETE FROM locks WHERE expires_at < $1")
+        .bind(before)
+        .execute(db)
+        .await
+        .map_err(db_error)?;
+    Ok(result.rows_affected())
+}
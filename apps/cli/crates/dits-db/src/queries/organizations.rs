@@ -0,0 +1,109 @@
+//! Organization and membership queries.
+
+use dits_core::user::{OrgRole, Organization};
+use dits_core::{Error, Result, User};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::{db_error, is_unique_violation};
+use crate::models::{role_from_db, role_to_db, OrganizationRow};
+
+/// Create an organization. Fails if the name is taken.
+pub async fn create<'e>(db: impl PgExecutor<'e>, org: &Organization) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO organizations (id, name, display_name, description, avatar_url, created_at) \
+         VALUES ($1, $2, $3, $4, $5, $6)",
+    )
+    .bind(org.id)
+    .bind(&org.name)
+    .bind(&org.display_name)
+    .bind(&org.description)
+    .bind(&org.avatar_url)
+    .bind(org.created_at)
+    .execute(db)
+    .await
+    .map_err(|e| {
+        if is_unique_violation(&e) {
+            Error::Database(format!("organization already exists: {}", org.name))
+        } else {
+            db_error(e)
+        }
+    })?;
+    Ok(())
+}
+
+/// Look up an organization by name.
+pub async fn get_by_name<'e>(db: impl PgExecutor<'e>, name: &str) -> Result<Option<Organization>> {
+    let row: Option<OrganizationRow> = sqlx::query_as(
+        "SELECT id, name, display_name, description, avatar_url, created_at \
+         FROM organizations WHERE name = $1",
+    )
+    .bind(name)
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?;
+    Ok(row.map(Organization::from))
+}
+
+/// Add a member, or change the role of an existing one.
+pub async fn set_member<'e>(
+    db: impl PgExecutor<'e>,
+    org: Uuid,
+    user: Uuid,
+    role: OrgRole,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO organization_members (organization_id, user_id, role) VALUES ($1, $2, $3) \
+         ON CONFLICT (organization_id, user_id) DO UPDATE SET role = excluded.role",
+    )
+    .bind(org)
+    .bind(user)
+    .bind(role_to_db(role))
+    .execute(db)
+    .await
+    .map_err(db_error)?;
+    Ok(())
+}
+
+/// Remove a member. Removing a non-member is a no-op.
+pub async fn remove_member<'e>(db: impl PgExecutor<'e>, org: Uuid, user: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM organization_members WHERE organization_id = $1 AND user_id = $2")
+        .bind(org)
+        .bind(user)
+        .execute(db)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Get a user's role in an organization, if they are a member.
+pub async fn member_role<'e>(
+    db: impl PgExecutor<'e>,
+    org: Uuid,
+    user: Uuid,
+) -> Result<Option<OrgRole>> {
+    let row: Option<(String,)> = sqlx::query_as(
+        "SELECT role FROM organization_members WHERE organization_id = $1 AND user_id = $2",
+    )
+    .bind(org)
+    .bind(user)
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?;
+    row.map(|(role,)| role_from_db(&role)).transpose()
+}
+
+/// List members with their roles, ordered by name.
+pub async fn list_members<'e>(db: impl PgExecutor<'e>, org: Uuid) -> Result<Vec<(User, OrgRole)>> {
+    let rows: Vec<(Uuid, String, String, String)> = sqlx::query_as(
+        "SELECT u.id, u.name, u.email, m.role FROM organization_members m \
+         JOIN users u ON u.id = m.user_id WHERE m.organization_id = $1 ORDER BY u.name",
+    )
+    .bind(org)
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+    rows.into_iter()
+        .map(|(id, name, email, role)| Ok((User::with_id(id, name, email), role_from_db(&role)?)))
+        .collect()
+}
@@ -0,0 +1,96 @@
+//! Ref queries.
+//!
+//! Refs only move through [`compare_and_swap`], so two concurrent pushes to
+//! the same branch cannot both succeed: whichever commits second sees a
+//! value other than the one it read and fails with `RefConflict`.
+
+use dits_core::{Error, Hash, Result};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::db_error;
+use crate::models::hash_from_db;
+
+/// List refs whose name starts with `prefix`, ordered by name.
+pub async fn list<'e>(
+    db: impl PgExecutor<'e>,
+    repo: Uuid,
+    prefix: &str,
+) -> Result<Vec<(String, Hash)>> {
+    let rows: Vec<(String, Vec<u8>)> = sqlx::query_as(
+        "SELECT name, commit_hash FROM refs \
+         WHERE repository_id = $1 AND starts_with(name, $2) ORDER BY name",
+    )
+    .bind(repo)
+    .bind(prefix)
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?;
+    rows.into_iter()
+        .map(|(name, hash)| Ok((name, hash_from_db(&hash)?)))
+        .collect()
+}
+
+/// Get the commit a ref points at.
+pub async fn get<'e>(db: impl PgExecutor<'e>, repo: Uuid, name: &str) -> Result<Option<Hash>> {
+    let row: Option<(Vec<u8>,)> = sqlx::query_as(
+        "SELECT commit_hash FROM refs WHERE repository_id = $1 AND name = $2",
+    )
+    .bind(repo)
+    .bind(name)
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?;
+    row.map(|(hash,)| hash_from_db(&hash)).transpose()
+}
+
+/// Move a ref from `expected` to `new` in a single statement.
+///
+/// `expected: None` requires the ref not to exist; `new: None` deletes it.
+/// Fails with `RefConflict` if the current value differs from `expected`.
+pub async fn compare_and_swap<'e>(
+    db: impl PgExecutor<'e>,
+    repo: Uuid,
+    name: &str,
+    expected: Option<Hash>,
+    new: Option<Hash>,
+) -> Result<()> {
+    let expected = expected.map(|h| h.as_bytes().to_vec());
+    let new = new.map(|h| h.as_bytes().to_vec());
+    let query = match (&expected, &new) {
+        (None, Some(new)) => sqlx::query(
+            "INSERT INTO refs (repository_id, name, commit_hash) VALUES ($1, $2, $3) \
+             ON CONFLICT (repository_id, name) DO NOTHING",
+        )
+        .bind(repo)
+        .bind(name)
+        .bind(new),
+        (Some(expected), Some(new)) => sqlx::query(
+            "UPDATE refs SET commit_hash = $4, updated_at = now() \
+             WHERE repository_id = $1 AND name = $2 AND commit_hash = $3",
+        )
+        .bind(repo)
+        .bind(name)
+        .bind(expected)
+        .bind(new),
+        (Some(expected), None) => sqlx::query(
+            "DELETE FROM refs WHERE repository_id = $1 AND name = $2 AND commit_hash = $3",
+        )
+        .bind(repo)
+        .bind(name)
+        .bind(expected),
+        // Deleting a ref that must not exist: succeeds only if it is absent.
+        (None, None) => sqlx::query(
+            "SELECT 1 WHERE NOT EXISTS \
+             (SELECT 1 FROM refs WHERE repository_id = $1 AND name = $2)",
+        )
+        .bind(repo)
+        .bind(name),
+    };
+
+    let result = query.execute(db).await.map_err(db_error)?;
+    if result.rows_affected() == 0 {
+        return Err(Error::RefConflict(name.to_string()));
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+//! Repository queries.
+
+use chrono::Utc;
+use dits_core::{Error, Repository, Result};
+use sqlx::types::Json;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::{db_error, is_unique_violation};
+use crate::models::{visibility_to_db, RepositoryRow};
+
+const COLUMNS: &str =
+    "id, name, description, default_branch, visibility, settings, created_at, updated_at";
+
+/// Insert a repository. Fails with `RepositoryAlreadyExists` if the name is taken.
+pub async fn create<'e>(db: impl PgExecutor<'e>, repo: &Repository) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO repositories \
+         (id, name, description, default_branch, visibility, settings, created_at, updated_at) \
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+    )
+    .bind(repo.id)
+    .bind(&repo.name)
+    .bind(&repo.description)
+    .bind(&repo.default_branch)
+    .bind(visibility_to_db(repo.visibility))
+    .bind(Json(&repo.settings))
+    .bind(repo.created_at)
+    .bind(repo.updated_at)
+    .execute(db)
+    .await
+    .map_err(|e| {
+        if is_unique_violation(&e) {
+            Error::RepositoryAlreadyExists(repo.name.clone())
+        } else {
+            db_error(e)
+        }
+    })?;
+    Ok(())
+}
+
+/// Look up a repository by `owner/name`.
+pub async fn get_by_name<'e>(db: impl PgExecutor<'e>, name: &str) -> Result<Option<Repository>> {
+    sqlx::query_as::<_, RepositoryRow>(&format!(
+        "SELECT {} FROM repositories WHERE name = $1",
+        COLUMNS
+    ))
+    .bind(name)
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?
+    .map(Repository::try_from)
+    .transpose()
+}
+
+/// Look up a repository by ID.
+pub async fn get_by_id<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Repository>> {
+    sqlx::query_as::<_, RepositoryRow>(&format!(
+        "SELECT {} FROM repositories WHERE id = $1",
+        COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?
+    .map(Repository::try_from)
+    .transpose()
+}
+
+/// List all repositories, ordered by name.
+pub async fn list<'e>(db: impl PgExecutor<'e>) -> Result<Vec<Repository>> {
+    sqlx::query_as::<_, RepositoryRow>(&format!(
+        "SELECT {} FROM repositories ORDER BY name",
+        COLUMNS
+    ))
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?
+    .into_iter()
+    .map(Repository::try_from)
+    .collect()
+}
+
+/// Replace a repository's metadata and bump `updated_at`.
+pub async fn update<'e>(db: impl PgExecutor<'e>, repo: &Repository) -> Result<()> {
+    let result = sqlx::query(
+        "UPDATE repositories SET name = $2, description = $3, default_branch = $4, \
+         visibility = $5, settings = $6, updated_at = $7 WHERE id = $1",
+    )
+    .bind(repo.id)
+    .bind(&repo.name)
+    .bind(&repo.description)
+    .bind(&repo.default_branch)
+    .bind(visibility_to_db(repo.visibility))
+    .bind(Json(&repo.settings))
+    .bind(Utc::now())
+    .execute(db)
+    .await
+    .map_err(|e| {
+        if is_unique_violation(&e) {
+            Error::RepositoryAlreadyExists(repo.name.clone())
+        } else {
+            db_error(e)
+        }
+    })?;
+    if result.rows_affected() == 0 {
+        return Err(Error::RepositoryNotFound(repo.name.clone()));
+    }
+    Ok(())
+}
+
+/// Delete a repository. Everything it owns is removed by cascade.
+pub async fn delete<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM repositories WHERE id = $1")
+        .bind(id)
+        .execute(db)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
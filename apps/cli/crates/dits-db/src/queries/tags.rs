@@ -0,0 +1,48 @@
+//! Tag queries.
+
+use dits_core::{Error, Result, Tag};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::{db_error, is_unique_violation};
+use crate::models::TagRow;
+
+/// Create a tag. Fails with `TagAlreadyExists` if the name is taken.
+pub async fn create<'e>(db: impl PgExecutor<'e>, repo: Uuid, tag: &Tag) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO tags (repository_id, name, commit_hash, message, tagger_name, \
+         tagger_email, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(repo)
+    .bind(&tag.name)
+    .bind(tag.commit.as_bytes().as_slice())
+    .bind(&tag.message)
+    .bind(tag.tagger.as_ref().map(|t| &t.name))
+    .bind(tag.tagger.as_ref().map(|t| &t.email))
+    .bind(tag.created_at)
+    .execute(db)
+    .await
+    .map_err(|e| {
+        if is_unique_violation(&e) {
+            Error::TagAlreadyExists(tag.name.clone())
+        } else {
+            db_error(e)
+        }
+    })?;
+    Ok(())
+}
+
+/// List tags, ordered by name.
+pub async fn list<'e>(db: impl PgExecutor<'e>, repo: Uuid) -> Result<Vec<Tag>> {
+    sqlx::query_as::<_, TagRow>(
+        "SELECT name, commit_hash, message, tagger_name, tagger_email, created_at \
+         FROM tags WHERE repository_id = $1 ORDER BY name",
+    )
+    .bind(repo)
+    .fetch_all(db)
+    .await
+    .map_err(db_error)?
+    .into_iter()
+    .map(Tag::try_from)
+    .collect()
+}
@@ -0,0 +1,70 @@
+//! User queries.
+
+use dits_core::{Error, Hash, Result, User};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::{db_error, is_unique_violation};
+use crate::models::UserRow;
+
+/// Create a user. Fails if the name is taken.
+pub async fn create<'e>(db: impl PgExecutor<'e>, user: &User) -> Result<()> {
+    sqlx::query("INSERT INTO users (id, name, email) VALUES ($1, $2, $3)")
+        .bind(user.id)
+        .bind(&user.name)
+        .bind(&user.email)
+        .execute(db)
+        .await
+        .map_err(|e| {
+            if is_unique_violation(&e) {
+                Error::Database(format!("user already exists: {}", user.name))
+            } else {
+                db_error(e)
+            }
+        })?;
+    Ok(())
+}
+
+/// Look up a user by name.
+pub async fn get_by_name<'e>(db: impl PgExecutor<'e>, name: &str) -> Result<Option<User>> {
+    let row: Option<UserRow> = sqlx::query_as("SELECT id, name, email FROM users WHERE name = $1")
+        .bind(name)
+        .fetch_optional(db)
+        .await
+        .map_err(db_error)?;
+    Ok(row.map(User::from))
+}
+
+/// Look up a user by ID.
+pub async fn get_by_id<'e>(db: impl PgExecutor<'e>, id: Uuid) -> Result<Option<User>> {
+    let row: Option<UserRow> = sqlx::query_as("SELECT id, name, email FROM users WHERE id = $1")
+        .bind(id)
+        .fetch_optional(db)
+        .await
+        .map_err(db_error)?;
+    Ok(row.map(User::from))
+}
+
+/// Register an access token, identified by its hash, for a user.
+pub async fn create_token<'e>(db: impl PgExecutor<'e>, user: Uuid, token_hash: &Hash) -> Result<()> {
+    sqlx::query("INSERT INTO access_tokens (token_hash, user_id) VALUES ($1, $2)")
+        .bind(token_hash.as_bytes().as_slice())
+        .bind(user)
+        .execute(db)
+        .await
+        .map_err(db_error)?;
+    Ok(())
+}
+
+/// Look up the user an access token belongs to.
+pub async fn get_by_token<'e>(db: impl PgExecutor<'e>, token_hash: &Hash) -> Result<Option<User>> {
+    let row: Option<UserRow> = sqlx::query_as(
+        "SELECT u.id, u.name, u.email FROM access_tokens t \
+         JOIN users u ON u.id = t.user_id WHERE t.token_hash = $1",
+    )
+    .bind(token_hash.as_bytes().as_slice())
+    .fetch_optional(db)
+    .await
+    .map_err(db_error)?;
+    Ok(row.map(User::from))
+}
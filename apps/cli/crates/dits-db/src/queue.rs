@@ -0,0 +1,266 @@
+//! Durable background job queue.
+//!
+//! Jobs move between three states: pending (waiting for `run_at`), running
+//! (claimed by a worker) and failed (given up on, kept for inspection). A
+//! finished job is removed. A worker that dies leaves its job running until
+//! [`JobQueue::requeue_stale`] returns it to pending, so every job runs at
+//! least once.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use dits_core::{Error, QueuedJob, Result};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+use uuid::Uuid;
+
+/// Queue of [`QueuedJob`]s shared by the API server and workers.
+#[async_trait]
+pub trait JobQueue: Send + Sync {
+    /// Add a job.
+    async fn enqueue(&self, job: QueuedJob) -> Result<()>;
+
+    /// Claim the job that has been due longest, counting the attempt.
+    ///
+    /// Returns `None` if no job is due. A job is only handed to one worker.
+    async fn claim(&self) -> Result<Option<QueuedJob>>;
+
+    /// Remove a claimed job after it succeeded.
+    async fn complete(&self, id: Uuid) -> Result<()>;
+
+    /// Return a claimed job to the queue after a failed attempt.
+    async fn retry(&self, id: Uuid, error: &str, run_at: DateTime<Utc>) -> Result<()>;
+
+    /// Give up on a claimed job.
+    async fn fail(&self, id: Uuid, error: &str) -> Result<()>;
+
+    /// Return jobs claimed more than `lease` ago to the queue. Returns how many.
+    async fn requeue_stale(&self, lease: Duration) -> Result<usize>;
+
+    /// List jobs that were given up on, oldest first.
+    async fn list_failed(&self) -> Result<Vec<QueuedJob>>;
+}
+
+/// File-backed queue: one JSON file per job in a directory per state.
+///
+/// ```text
+/// <root>/pending/<id>.json
+/// <root>/running/<id>.json
+/// <root>/failed/<id>.json
+/// ```
+///
+/// Claiming renames a file from `pending` to `running`, which succeeds for
+/// exactly one claimant, so several workers on one host can share a queue.
+/// Files are replaced by writing a temporary file and renaming it.
+pub struct FileJobQueue {
+    root: PathBuf,
+}
+
+const PENDING: &str = "pending";
+const RUNNING: &str = "running";
+const FAILED: &str = "failed";
+
+impl FileJobQueue {
+    /// Open a queue rooted at `root`, creating its directories.
+    pub async fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        for state in [PENDING, RUNNING, FAILED] {
+            tokio::fs::create_dir_all(root.join(state)).await?;
+        }
+        Ok(Self { root })
+    }
+
+    /// Open the queue in `DITS_QUEUE_PATH` (default `./data/queue`).
+    pub async fn from_env() -> Result<Self> {
+        let path = std::env::var("DITS_QUEUE_PATH").unwrap_or_else(|_| "./data/queue".to_string());
+        Self::open(path).await
+    }
+
+    fn path(&self, state: &str, id: Uuid) -> PathBuf {
+        self.root.join(state).join(format!("{}.json", id))
+    }
+
+    async fn read(&self, path: &Path) -> Result<QueuedJob> {
+        let bytes = tokio::fs::read(path).await?;
+        serde_json::from_slice(&bytes).map_err(|e| Error::Serialization(e.to_string()))
+    }
+
+    async fn write(&self, state: &str, job: &QueuedJob) -> Result<()> {
+        let bytes = serde_json::to_vec(job).map_err(|e| Error::Serialization(e.to_string()))?;
+        let temp = self.root.join(format!(".tmp-{}", Uuid::new_v4()));
+        tokio::fs::write(&temp, bytes).await?;
+        if let Err(e) = tokio::fs::rename(&temp, self.path(state, job.id)).await {
+            let _ = tokio::fs::remove_file(&temp).await;
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    async fn read_all(&self, state: &str) -> Result<Vec<QueuedJob>> {
+        let mut jobs = Vec::new();
+        let mut entries = tokio::fs::read_dir(self.root.join(state)).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            match self.read(&path).await {
+                Ok(job) => jobs.push(job),
+                // Claimed or completed by someone else since listing.
+                Err(Error::Io(e)) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => warn!("Skipping unreadable job {}: {}", path.display(), e),
+            }
+        }
+        Ok(jobs)
+    }
+
+    /// Move a running job to `state` with updated bookkeeping.
+    async fn finish_attempt(
+        &self,
+        id: Uuid,
+        state: &str,
+        update: impl FnOnce(&mut QueuedJob) + Send,
+    ) -> Result<()> {
+        let running = self.path(RUNNING, id);
+        let mut job = match self.read(&running).await {
+            Err(Error::Io(e)) if e.kind() == ErrorKind::NotFound => {
+                return Err(Error::Internal(format!("job {} is not running", id)));
+            }
+            result => result?,
+        };
+        update(&mut job);
+        job.claimed_at = None;
+        self.write(state, &job).await?;
+        tokio::fs::remove_file(&running).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl JobQueue for FileJobQueue {
+    async fn enqueue(&self, job: QueuedJob) -> Result<()> {
+        self.write(PENDING, &job).await
+    }
+
+    async fn claim(&self) -> Result<Option<QueuedJob>> {
+        let now = Utc::now();
+        let mut due: Vec<QueuedJob> = self
+            .read_all(PENDING)
+            .await?
+            .into_iter()
+            .filter(|job| job.run_at <= now)
+            .collect();
+        due.sort_by_key(|job| (job.run_at, job.created_at));
+
+        for candidate in due {
+            let running = self.path(RUNNING, candidate.id);
+            match tokio::fs::rename(self.path(PENDING, candidate.id), &running).await {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(e.into()),
+            }
+            // Re-read in case the job was rescheduled after it was listed.
+            let mut job = self.read(&running).await?;
+            job.attempts += 1;
+            job.claimed_at = Some(now);
+            self.write(RUNNING, &job).await?;
+            return Ok(Some(job));
+        }
+        Ok(None)
+    }
+
+    async fn complete(&self, id: Uuid) -> Result<()> {
+        match tokio::fs::remove_file(self.path(RUNNING, id)).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn retry(&self, id: Uuid, error: &str, run_at: DateTime<Utc>) -> Result<()> {
+        self.finish_attempt(id, PENDING, |job| {
+            job.last_error = Some(error.to_string());
+            job.run_at = run_at;
+        })
+        .await
+    }
+
+    async fn fail(&self, id: Uuid, error: &str) -> Result<()> {
+        self.finish_attempt(id, FAILED, |job| job.last_error = Some(error.to_string()))
+            .await
+    }
+
+    async fn requeue_stale(&self, lease: Duration) -> Result<usize> {
+        let cutoff = Utc::now() - lease;
+        let mut requeued = 0;
+        for job in self.read_all(RUNNING).await? {
+            if job.claimed_at.is_none_or(|at| at <= cutoff) {
+                match tokio::fs::rename(self.path(RUNNING, job.id), self.path(PENDING, job.id)).await {
+                    Ok(()) => requeued += 1,
+                    Err(e) if e.kind() == ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
+            }
+        }
+        Ok(requeued)
+    }
+
+    async fn list_failed(&self) -> Result<Vec<QueuedJob>> {
+        let mut jobs = self.read_all(FAILED).await?;
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dits_core::Job;
+    use tempfile::tempdir;
+
+    fn gc_job(grace_secs: u64) -> QueuedJob {
+        QueuedJob::new(Job::CollectGarbage { grace_secs })
+    }
+
+    #[tokio::test]
+    async fn test_claim_retry_complete() {
+        let dir = tempdir().unwrap();
+        let queue = FileJobQueue::open(dir.path()).await.unwrap();
+
+        let mut later = gc_job(1);
+        later.run_at = Utc::now() + Duration::hours(1);
+        queue.enqueue(later).await.unwrap();
+        let first = gc_job(2);
+        queue.enqueue(first.clone()).await.unwrap();
+
+        let claimed = queue.claim().await.unwrap().unwrap();
+        assert_eq!(claimed.id, first.id);
+        assert_eq!(claimed.attempts, 1);
+        assert!(queue.claim().await.unwrap().is_none());
+
+        queue.retry(claimed.id, "boom", Utc::now()).await.unwrap();
+        let again = queue.claim().await.unwrap().unwrap();
+        assert_eq!(again.attempts, 2);
+        assert_eq!(again.last_error.as_deref(), Some("boom"));
+
+        queue.complete(again.id).await.unwrap();
+        assert!(queue.claim().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_fail_and_requeue_stale() {
+        let dir = tempdir().unwrap();
+        let queue = FileJobQueue::open(dir.path()).await.unwrap();
+        queue.enqueue(gc_job(1)).await.unwrap();
+        queue.enqueue(gc_job(2)).await.unwrap();
+
+        let failed = queue.claim().await.unwrap().unwrap();
+        queue.fail(failed.id, "permanent").await.unwrap();
+        assert_eq!(queue.list_failed().await.unwrap()[0].id, failed.id);
+
+        // A worker that claimed a job and died.
+        let abandoned = queue.claim().await.unwrap().unwrap();
+        assert_eq!(queue.requeue_stale(Duration::hours(1)).await.unwrap(), 0);
+        assert_eq!(queue.requeue_stale(Duration::zero()).await.unwrap(), 1);
+        assert_eq!(queue.claim().await.unwrap().unwrap().id, abandoned.id);
+    }
+}
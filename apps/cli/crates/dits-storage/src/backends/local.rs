@@ -1,3 +1,268 @@
 //! Local filesystem storage backend.
 
-// TODO: Implement local filesystem backend
+use async_trait::async_trait;
+use bytes::Bytes;
+use dits_core::{Error, Result};
+use std::fs::{self, File};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{ObjectMeta, StorageBackend};
+
+/// Counter making temporary file names unique within the process.
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Stores each object as a file under a root directory.
+///
+/// `/`-separated keys map onto nested directories, with one extra level
+/// named after the first two characters of the final component so that
+/// no single directory grows too large:
+///
+/// ```text
+/// chunks/ab12cd...   ->   <root>/chunks/ab/ab12cd...
+/// ```
+///
+/// Writes go to a temporary file in the destination directory that is
+/// synced and then renamed over the target, so readers never observe a
+/// partially written object. Names starting with `.` are reserved for
+/// those temporary files and are not valid key components.
+pub struct LocalBackend {
+    root: PathBuf,
+}
+
+impl LocalBackend {
+    /// Create a backend rooted at `root`. The directory is created on first write.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Root directory of the backend.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf> {
+        let relative = Path::new(key);
+        let valid = !key.is_empty()
+            && relative.components().all(|c| match c {
+                Component::Normal(name) => !name.to_string_lossy().starts_with('.'),
+                _ => false,
+            });
+        let name = relative.file_name().map(|n| n.to_string_lossy());
+        let (true, Some(name)) = (valid, name) else {
+            return Err(Error::InvalidPath(key.to_string()));
+        };
+
+        let shard: String = name.chars().take(2).collect();
+        let mut path = self.root.clone();
+        if let Some(parent) = relative.parent() {
+            path.push(parent);
+        }
+        path.push(shard);
+        path.push(name.as_ref());
+        Ok(path)
+    }
+
+    /// Map a file below the root back to its key by dropping the shard directory.
+    fn key_for(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let mut components: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        if components.len() < 2 {
+            return None;
+        }
+        components.remove(components.len() - 2);
+        Some(components.join("/"))
+    }
+}
+
+fn not_found(key: &str, err: std::io::Error) -> Error {
+    if err.kind() == ErrorKind::NotFound {
+        Error::ObjectNotFound(key.to_string())
+    } else {
+        Error::Io(err)
+    }
+}
+
+/// Run blocking filesystem work off the async runtime.
+async fn blocking<T, F>(f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| Error::Internal(format!("storage task failed: {}", e)))?
+}
+
+/// Write `data` to `path` atomically: temp file, fsync, rename, fsync directory.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| Error::InvalidPath(path.display().to_string()))?;
+    fs::create_dir_all(dir)?;
+
+    let temp = dir.join(format!(
+        ".tmp-{}-{}",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let result = (|| {
+        let mut file = File::create(&temp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&temp, path)
+    })();
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(Error::Io(e));
+    }
+
+    // Persist the rename itself. Not supported on every platform.
+    #[cfg(unix)]
+    if let Ok(dir) = File::open(dir) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl StorageBackend for LocalBackend {
+    async fn put(&self, key: &str, data: Bytes) -> Result<()> {
+        let path = self.path_for(key)?;
+        blocking(move || write_atomic(&path, &data)).await
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes> {
+        let path = self.path_for(key)?;
+        let data = tokio::fs::read(&path).await.map_err(|e| not_found(key, e))?;
+        Ok(Bytes::from(data))
+    }
+
+    async fn get_range(&self, key: &str, range: Range<u64>) -> Result<Bytes> {
+        let path = self.path_for(key)?;
+        let key = key.to_string();
+        blocking(move || {
+            let mut file = File::open(&path).map_err(|e| not_found(&key, e))?;
+            let size = file.metadata()?.len();
+            let end = range.end.min(size);
+            let start = range.start.min(end);
+            let mut data = vec![0; (end - start) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut data)?;
+            Ok(Bytes::from(data))
+        })
+        .await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        let path = self.path_for(key)?;
+        Ok(tokio::fs::try_exists(&path).await?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let path = self.path_for(key)?;
+        match tokio::fs::remove_file(&path).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => Err(Error::Io(e)),
+            _ => Ok(()),
+        }
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        // Only directories above the final key component are unsharded, so
+        // the walk can start at the deepest one the prefix names in full.
+        let start = match prefix.rfind('/') {
+            Some(i) => self.root.join(&prefix[..i]),
+            None => self.root.clone(),
+        };
+
+        let mut keys = Vec::new();
+        let mut pending = vec![start];
+        while let Some(dir) = pending.pop() {
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == ErrorKind::NotFound => continue,
+                Err(e) => return Err(Error::Io(e)),
+            };
+            while let Some(entry) = entries.next_entry().await? {
+                if entry.file_name().to_string_lossy().starts_with('.') {
+                    continue;
+                }
+                let path = entry.path();
+                if entry.file_type().await?.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+                if let Some(key) = self.key_for(&path) {
+                    if key.starts_with(prefix) {
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMeta> {
+        let path = self.path_for(key)?;
+        let metadata = tokio::fs::metadata(&path).await.map_err(|e| not_found(key, e))?;
+        Ok(ObjectMeta {
+            size: metadata.len(),
+            content_type: None,
+            last_modified: metadata.modified().ok().map(chrono::DateTime::from),
+            etag: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_put_get_list_delete() {
+        let dir = tempdir().unwrap();
+        let backend = LocalBackend::new(dir.path());
+
+        backend.put("chunks/abcd", Bytes::from_static(b"data")).await.unwrap();
+        backend.put("manifests/ef", Bytes::from_static(b"m")).await.unwrap();
+        assert!(dir.path().join("chunks/ab/abcd").is_file());
+
+        assert_eq!(backend.get("chunks/abcd").await.unwrap(), Bytes::from_static(b"data"));
+        assert_eq!(backend.get_range("chunks/abcd", 1..3).await.unwrap(), Bytes::from_static(b"at"));
+        assert_eq!(backend.get_range("chunks/abcd", 2..99).await.unwrap(), Bytes::from_static(b"ta"));
+        assert!(backend.exists("chunks/abcd").await.unwrap());
+        assert_eq!(backend.head("chunks/abcd").await.unwrap().size, 4);
+        assert_eq!(backend.list("chunks/").await.unwrap(), vec!["chunks/abcd".to_string()]);
+        assert_eq!(backend.list("chunks/ab").await.unwrap(), vec!["chunks/abcd".to_string()]);
+        assert_eq!(backend.list("").await.unwrap().len(), 2);
+
+        backend.delete("chunks/abcd").await.unwrap();
+        backend.delete("chunks/abcd").await.unwrap();
+        assert!(matches!(backend.get("chunks/abcd").await, Err(Error::ObjectNotFound(_))));
+        assert!(matches!(backend.get("../escape").await, Err(Error::InvalidPath(_))));
+        assert!(matches!(backend.get("chunks/.tmp-1").await, Err(Error::InvalidPath(_))));
+    }
+
+    #[tokio::test]
+    async fn test_overwrite_leaves_no_temp_files() {
+        let dir = tempdir().unwrap();
+        let backend = LocalBackend::new(dir.path());
+
+        backend.put("objects/key", Bytes::from_static(b"first")).await.unwrap();
+        backend.put("objects/key", Bytes::from_static(b"second")).await.unwrap();
+
+        assert_eq!(backend.get("objects/key").await.unwrap(), Bytes::from_static(b"second"));
+        let names: Vec<_> = std::fs::read_dir(dir.path().join("objects/ke"))
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec!["key"]);
+    }
+}
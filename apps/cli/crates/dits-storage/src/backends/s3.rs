@@ -1,3 +1,521 @@
 //! S3-compatible storage backend.
 
-// TODO: Implement S3 backend
+use async_trait::async_trait;
+use aws_sdk_s3::{
+    config::{
+        http::HttpResponse, retry::RetryConfig, BehaviorVersion, Region,
+        RequestChecksumCalculation, ResponseChecksumValidation,
+    },
+    error::{DisplayErrorContext, ProvideErrorMetadata, SdkError},
+    primitives::ByteStream,
+    types::{CompletedMultipartUpload, CompletedPart},
+    Client,
+};
+use bytes::Bytes;
+use dits_core::{Error, Result};
+use futures::{stream, StreamExt, TryStreamExt};
+use std::future::Future;
+use std::ops::Range;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::{ObjectMeta, StorageBackend};
+
+/// S3 backend configuration.
+#[derive(Clone, Debug)]
+pub struct S3Config {
+    /// Bucket name.
+    pub bucket: String,
+    /// Prefix prepended to every key, e.g. `dits/`.
+    pub prefix: String,
+    /// Region (falls back to the AWS environment if unset).
+    pub region: Option<String>,
+    /// Custom endpoint for S3-compatible services such as MinIO.
+    pub endpoint: Option<String>,
+    /// Objects larger than this are uploaded in parts.
+    pub multipart_threshold: usize,
+    /// Size of each multipart part (S3 requires at least 5 MiB).
+    pub part_size: usize,
+    /// Parts uploaded concurrently.
+    pub upload_concurrency: usize,
+    /// Attempts after the first for retryable errors.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubles on each further attempt.
+    pub retry_base_delay: Duration,
+}
+
+impl S3Config {
+    /// Configuration for `bucket` with default tuning.
+    pub fn new(bucket: impl Into<String>) -> Self {
+        Self {
+            bucket: bucket.into(),
+            prefix: String::new(),
+            region: None,
+            endpoint: None,
+            multipart_threshold: 64 * 1024 * 1024,
+            part_size: 16 * 1024 * 1024,
+            upload_concurrency: 4,
+            max_retries: 5,
+            retry_base_delay: Duration::from_millis(100),
+        }
+    }
+
+    /// Read configuration from environment variables.
+    ///
+    /// Returns `None` unless `DITS_S3_BUCKET` is set. `DITS_S3_PREFIX`,
+    /// `DITS_S3_REGION` and `DITS_S3_ENDPOINT` are optional.
+    pub fn from_env() -> Option<Self> {
+        let mut config = Self::new(std::env::var("DITS_S3_BUCKET").ok()?);
+        config.prefix = std::env::var("DITS_S3_PREFIX").unwrap_or_default();
+        config.region = std::env::var("DITS_S3_REGION").ok();
+        config.endpoint = std::env::var("DITS_S3_ENDPOINT").ok();
+        Some(config)
+    }
+}
+
+/// Stores objects in an S3 bucket.
+///
+/// Retryable failures (timeouts, throttling, 5xx responses) are retried
+/// with exponential backoff according to [`S3Config::max_retries`]; the
+/// SDK's own retry layer is disabled so attempts are not multiplied.
+pub struct S3Backend {
+    client: Client,
+    config: S3Config,
+}
+
+impl S3Backend {
+    /// Create a backend using credentials from the AWS environment.
+    pub async fn new(config: S3Config) -> Self {
+        let shared = aws_config::defaults(BehaviorVersion::latest()).load().await;
+        let mut builder = aws_sdk_s3::config::Builder::from(&shared);
+        if let Some(region) = &config.region {
+            builder = builder.region(Region::new(region.clone()));
+        }
+        if let Some(endpoint) = &config.endpoint {
+            // Most S3-compatible services need path-style addressing and
+            // do not implement the newer default checksum headers.
+            builder = builder
+                .endpoint_url(endpoint)
+                .force_path_style(true)
+                .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+                .response_checksum_validation(ResponseChecksumValidation::WhenRequired);
+        }
+        let client = Client::from_conf(builder.retry_config(RetryConfig::disabled()).build());
+        Self::with_client(client, config)
+    }
+
+    /// Create a backend from an already configured client.
+    pub fn with_client(client: Client, config: S3Config) -> Self {
+        Self { client, config }
+    }
+
+    fn object_key(&self, key: &str) -> String {
+        format!("{}{}", self.config.prefix, key)
+    }
+
+    /// Run `op`, retrying while it fails with a retryable error.
+    async fn retry<T, F, Fut>(&self, what: &str, mut op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op().await {
+                Err(e) if e.is_retryable() && attempt < self.config.max_retries => {
+                    let delay = match e {
+                        Error::RateLimited { retry_after } if retry_after > 0 => {
+                            Duration::from_secs(retry_after)
+                        }
+                        _ => self.config.retry_base_delay * 2u32.pow(attempt.min(6)),
+                    };
+                    attempt += 1;
+                    warn!("S3 {} failed ({}), retry {} in {:?}", what, e, attempt, delay);
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn put_multipart(&self, key: &str, data: Bytes) -> Result<()> {
+        let created = self
+            .retry("create multipart upload", || async {
+                self.client
+                    .create_multipart_upload()
+                    .bucket(&self.config.bucket)
+                    .key(key)
+                    .send()
+                    .await
+                    .map_err(|e| sdk_error(key, e))
+            })
+            .await?;
+        let upload_id = created
+            .upload_id()
+            .ok_or_else(|| Error::Storage(format!("no upload id for {}", key)))?
+            .to_string();
+
+        let part_size = self.config.part_size.max(1);
+        let parts = (0..data.len()).step_by(part_size).enumerate().map(|(i, start)| {
+            let end = (start + part_size).min(data.len());
+            (i as i32 + 1, data.slice(start..end))
+        });
+        let uploaded: Result<Vec<CompletedPart>> = stream::iter(parts)
+            .map(|(number, body)| {
+                let upload_id = &upload_id;
+                async move {
+                    let output = self
+                        .retry("upload part", || async {
+                            self.client
+                                .upload_part()
+                                .bucket(&self.config.bucket)
+                                .key(key)
+                                .upload_id(upload_id)
+                                .part_number(number)
+                                .body(ByteStream::from(body.clone()))
+                                .send()
+                                .await
+                                .map_err(|e| sdk_error(key, e))
+                        })
+                        .await?;
+                    Ok(CompletedPart::builder()
+                        .part_number(number)
+                        .set_e_tag(output.e_tag().map(str::to_string))
+                        .build())
+                }
+            })
+            .buffered(self.config.upload_concurrency.max(1))
+            .try_collect()
+            .await;
+
+        let result = match uploaded {
+            Ok(parts) => {
+                let completed = CompletedMultipartUpload::builder().set_parts(Some(parts)).build();
+                self.retry("complete multipart upload", || async {
+                    self.client
+                        .complete_multipart_upload()
+                        .bucket(&self.config.bucket)
+                        .key(key)
+                        .upload_id(&upload_id)
+                        .multipart_upload(completed.clone())
+                        .send()
+                        .await
+                        .map_err(|e| sdk_error(key, e))
+                })
+                .await
+                .map(|_| ())
+            }
+            Err(e) => Err(e),
+        };
+
+        if result.is_err() {
+            // Best effort; lifecycle rules clean up anything left behind.
+            let _ = self
+                .client
+                .abort_multipart_upload()
+                .bucket(&self.config.bucket)
+                .key(key)
+                .upload_id(&upload_id)
+                .send()
+                .await;
+        }
+        result
+    }
+}
+
+/// Map an SDK failure onto the shared error type so callers can tell
+/// missing objects and retryable failures apart.
+fn sdk_error<E>(key: &str, err: SdkError<E, HttpResponse>) -> Error
+where
+    E: ProvideErrorMetadata + std::error::Error + Send + Sync + 'static,
+{
+    let message = DisplayErrorContext(&err).to_string();
+    match &err {
+        SdkError::TimeoutError(_) => Error::Timeout,
+        SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => Error::Network(message),
+        SdkError::ServiceError(service) => {
+            let status = service.raw().status().as_u16();
+            match (status, service.err().code()) {
+                (404, _) | (_, Some("NoSuchKey" | "NotFound")) => {
+                    Error::ObjectNotFound(key.to_string())
+                }
+                (429, _) | (_, Some("SlowDown" | "Throttling")) => {
+                    Error::RateLimited { retry_after: 0 }
+                }
+                (500..=599, _) | (_, Some("InternalError" | "RequestTimeout")) => {
+                    Error::Network(message)
+                }
+                _ => Error::Storage(message),
+            }
+        }
+        _ => Error::Storage(message),
+    }
+}
+
+#[async_trait]
+impl StorageBackend for S3Backend {
+    async fn put(&self, key: &str, data: Bytes) -> Result<()> {
+        let key = self.object_key(key);
+        if data.len() > self.config.multipart_threshold {
+            return self.put_multipart(&key, data).await;
+        }
+        self.retry("put", || async {
+            self.client
+                .put_object()
+                .bucket(&self.config.bucket)
+                .key(&key)
+                .body(ByteStream::from(data.clone()))
+                .send()
+                .await
+                .map_err(|e| sdk_error(&key, e))
+        })
+        .await
+        .map(|_| ())
+    }
+
+    async fn get(&self, key: &str) -> Result<Bytes> {
+        let key = self.object_key(key);
+        self.retry("get", || async {
+            let output = self
+                .client
+                .get_object()
+                .bucket(&self.config.bucket)
+                .key(&key)
+                .send()
+                .await
+                .map_err(|e| sdk_error(&key, e))?;
+            let body = output
+                .body
+                .collect()
+                .await
+                .map_err(|e| Error::Network(e.to_string()))?;
+            Ok(body.into_bytes())
+        })
+        .await
+    }
+
+    async fn get_range(&self, key: &str, range: Range<u64>) -> Result<Bytes> {
+        if range.start >= range.end {
+            return Ok(Bytes::new());
+        }
+        let key = self.object_key(key);
+        let header = format!("bytes={}-{}", range.start, range.end - 1);
+        self.retry("ranged get", || async {
+            let output = match self
+                .client
+                .get_object()
+                .bucket(&self.config.bucket)
+                .key(&key)
+                .range(&header)
+                .send()
+                .await
+            {
+                Ok(output) => output,
+                // The range starts past the end of the object.
+                Err(SdkError::ServiceError(e)) if e.raw().status().as_u16() == 416 => {
+                    return Ok(Bytes::new());
+                }
+                Err(e) => return Err(sdk_error(&key, e)),
+            };
+            let body = output
+                .body
+                .collect()
+                .await
+                .map_err(|e| Error::Network(e.to_string()))?;
+            Ok(body.into_bytes())
+        })
+        .await
+    }
+
+    async fn exists(&self, key: &str) -> Result<bool> {
+        match self.head(key).await {
+            Ok(_) => Ok(true),
+            Err(Error::ObjectNotFound(_)) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let key = self.object_key(key);
+        self.retry("delete", || async {
+            self.client
+                .delete_object()
+                .bucket(&self.config.bucket)
+                .key(&key)
+                .send()
+                .await
+                .map_err(|e| sdk_error(&key, e))
+        })
+        .await
+        .map(|_| ())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let full_prefix = self.object_key(prefix);
+        let mut keys = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let page = self
+                .retry("list", || async {
+                    self.client
+                        .list_objects_v2()
+                        .bucket(&self.config.bucket)
+                        .prefix(&full_prefix)
+                        .set_continuation_token(token.clone())
+                        .send()
+                        .await
+                        .map_err(|e| sdk_error(&full_prefix, e))
+                })
+                .await?;
+            keys.extend(
+                page.contents()
+                    .iter()
+                    .filter_map(|object| object.key())
+                    .filter_map(|key| key.strip_prefix(self.config.prefix.as_str()))
+                    .map(str::to_string),
+            );
+            match page.next_continuation_token() {
+                Some(next) if page.is_truncated() == Some(true) => token = Some(next.to_string()),
+                _ => break,
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+
+    async fn head(&self, key: &str) -> Result<ObjectMeta> {
+        let key = self.object_key(key);
+        let output = self
+            .retry("head", || async {
+                self.client
+                    .head_object()
+                    .bucket(&self.config.bucket)
+                    .key(&key)
+                    .send()
+                    .await
+                    .map_err(|e| sdk_error(&key, e))
+            })
+            .await?;
+        Ok(ObjectMeta {
+            size: output.content_length().unwrap_or(0).max(0) as u64,
+            content_type: output.content_type().map(str::to_string),
+            last_modified: output.last_modified().and_then(|t| {
+                chrono::DateTime::from_timestamp(t.secs(), t.subsec_nanos())
+            }),
+            etag: output.e_tag().map(str::to_string),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! Run against a mock HTTP server standing in for an S3-compatible service.
+
+    use super::*;
+    use aws_sdk_s3::config::Credentials;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn backend(server: &MockServer) -> S3Backend {
+        let mut config = S3Config::new("bucket");
+        config.retry_base_delay = Duration::from_millis(1);
+        config.multipart_threshold = 8;
+        config.part_size = 4;
+        let client_config = aws_sdk_s3::Config::builder()
+            .behavior_version(BehaviorVersion::latest())
+            .region(Region::new("us-east-1"))
+            .credentials_provider(Credentials::new("test", "test", None, None, "test"))
+            .endpoint_url(server.uri())
+            .force_path_style(true)
+            .request_checksum_calculation(RequestChecksumCalculation::WhenRequired)
+            .response_checksum_validation(ResponseChecksumValidation::WhenRequired)
+            .retry_config(RetryConfig::disabled())
+            .build();
+        S3Backend::with_client(Client::from_conf(client_config), config)
+    }
+
+    #[tokio::test]
+    async fn test_get_retries_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/chunks/abc"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/chunks/abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(b"data".to_vec()))
+            .mount(&server)
+            .await;
+
+        let backend = backend(&server).await;
+        assert_eq!(backend.get("chunks/abc").await.unwrap(), Bytes::from_static(b"data"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_object() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/bucket/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let backend = backend(&server).await;
+        assert!(!backend.exists("missing").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_ranged_get() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/chunks/abc"))
+            .and(header("range", "bytes=2-5"))
+            .respond_with(ResponseTemplate::new(206).set_body_bytes(b"ta12".to_vec()))
+            .mount(&server)
+            .await;
+
+        let backend = backend(&server).await;
+        assert_eq!(
+            backend.get_range("chunks/abc", 2..6).await.unwrap(),
+            Bytes::from_static(b"ta12")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_large_put_uses_multipart() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/bucket/big"))
+            .and(query_param("uploads", ""))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "<InitiateMultipartUploadResult><Bucket>bucket</Bucket>\
+                 <Key>big</Key><UploadId>up-1</UploadId></InitiateMultipartUploadResult>",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/bucket/big"))
+            .and(query_param("uploadId", "up-1"))
+            .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"etag\""))
+            .expect(3)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/bucket/big"))
+            .and(query_param("uploadId", "up-1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "<CompleteMultipartUploadResult><Bucket>bucket</Bucket>\
+                 <Key>big</Key><ETag>\"etag\"</ETag></CompleteMultipartUploadResult>",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let backend = backend(&server).await;
+        backend.put("big", Bytes::from_static(b"0123456789")).await.unwrap();
+    }
+}
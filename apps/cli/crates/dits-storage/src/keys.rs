@@ -0,0 +1,31 @@
+//! Object key layout shared by the server components.
+//!
+//! Content is addressed by hash and shared between repositories:
+//!
+//! ```text
+//! chunks/{hash}      raw chunk bytes
+//! manifests/{hash}   serialized Manifest
+//! ```
+
+use dits_core::Hash;
+
+/// Prefix of chunk keys.
+pub const CHUNKS_PREFIX: &str = "chunks/";
+
+/// Prefix of manifest keys.
+pub const MANIFESTS_PREFIX: &str = "manifests/";
+
+/// Storage key of a chunk.
+pub fn chunk_key(hash: &Hash) -> String {
+    format!("{}{}", CHUNKS_PREFIX, hash.to_hex())
+}
+
+/// Storage key of a manifest.
+pub fn manifest_key(hash: &Hash) -> String {
+    format!("{}{}", MANIFESTS_PREFIX, hash.to_hex())
+}
+
+/// Hash named by a key under `prefix`, if it is one.
+pub fn hash_from_key(prefix: &str, key: &str) -> Option<Hash> {
+    Hash::from_hex(key.strip_prefix(prefix)?).ok()
+}
@@ -8,10 +8,36 @@
 
 pub mod backends;
 pub mod client;
+pub mod keys;
+
+pub use backends::local::LocalBackend;
+pub use backends::s3::{S3Backend, S3Config};
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use dits_core::Result;
+use std::ops::Range;
+use std::sync::Arc;
+use tracing::info;
+
+/// Build the backend selected by the environment.
+///
+/// - `DITS_S3_BUCKET`: store objects in this S3 bucket (see [`S3Config::from_env`])
+/// - `DITS_STORAGE_PATH`: otherwise, directory for object storage (default `./data/storage`)
+pub async fn backend_from_env() -> Arc<dyn StorageBackend> {
+    match S3Config::from_env() {
+        Some(config) => {
+            info!("Using S3 object storage in bucket {}", config.bucket);
+            Arc::new(S3Backend::new(config).await)
+        }
+        None => {
+            let path = std::env::var("DITS_STORAGE_PATH")
+                .unwrap_or_else(|_| "./data/storage".to_string());
+            info!("Using local object storage in {}", path);
+            Arc::new(LocalBackend::new(path))
+        }
+    }
+}
 
 /// Storage backend trait.
 #[async_trait]
@@ -22,6 +48,17 @@ pub trait StorageBackend: Send + Sync {
     /// Download an object.
     async fn get(&self, key: &str) -> Result<Bytes>;
 
+    /// Download part of an object.
+    ///
+    /// The range is clamped to the object size, so reading past the end
+    /// returns fewer bytes rather than an error.
+    async fn get_range(&self, key: &str, range: Range<u64>) -> Result<Bytes> {
+        let data = self.get(key).await?;
+        let end = (range.end as usize).min(data.len());
+        let start = (range.start as usize).min(end);
+        Ok(data.slice(start..end))
+    }
+
     /// Check if object exists.
     async fn exists(&self, key: &str) -> Result<bool>;
 
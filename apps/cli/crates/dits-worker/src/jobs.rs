@@ -3,3 +3,23 @@
 pub mod chunking;
 pub mod cleanup;
 pub mod webhooks;
+
+use dits_core::{Job, QueuedJob, Result};
+use std::time::Duration;
+
+use crate::worker::JobContext;
+
+/// Run a job once.
+pub async fn run(ctx: &JobContext, queued: &QueuedJob) -> Result<()> {
+    match &queued.job {
+        Job::VerifyManifest { repository_id, manifest } => {
+            chunking::verify_manifest(ctx, *repository_id, manifest).await
+        }
+        Job::CollectGarbage { grace_secs } => {
+            cleanup::collect_garbage(ctx, Duration::from_secs(*grace_secs))
+                .await
+                .map(|_| ())
+        }
+        Job::DeliverWebhook(delivery) => webhooks::deliver(ctx, queued.id, delivery).await,
+    }
+}
@@ -1,3 +1,122 @@
-//! Chunking job.
+//! Verification of pushed content.
+//!
+//! Clients chunk files locally and upload the chunks before pushing the
+//! manifest that ties them together. The upload endpoint checks each chunk
+//! against its hash, but only reading every file back proves that the
+//! chunks really reassemble into the content the manifest claims.
 
-// TODO: Implement chunking job
+use dits_core::{Error, Hash, Hasher, Manifest, Result};
+use dits_storage::keys::{chunk_key, manifest_key};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::worker::JobContext;
+
+/// Re-read every file in a manifest from its chunks and check all hashes.
+///
+/// A chunk whose stored bytes do not match its hash is deleted and its
+/// reference dropped from the repository, so the next push that needs it
+/// uploads it again. A file whose chunks are intact but do not hash to the
+/// recorded content hash fails the job for an operator to look at.
+pub async fn verify_manifest(ctx: &JobContext, repository_id: Uuid, hash: &Hash) -> Result<()> {
+    let bytes = ctx.storage.get(&manifest_key(hash)).await?;
+    let actual = Hasher::hash(&bytes);
+    if actual != *hash {
+        return Err(Error::ChunkCorrupted {
+            expected: hash.to_hex(),
+            actual: actual.to_hex(),
+        });
+    }
+    let manifest = Manifest::from_bytes(&bytes)?;
+    manifest.validate()?;
+
+    let mut bytes_checked = 0;
+    for entry in manifest.entries.values() {
+        let mut content = Hasher::new();
+        for chunk in &entry.chunks {
+            let key = chunk_key(&chunk.hash);
+            let data = ctx.storage.get(&key).await?;
+            let actual = Hasher::hash(&data);
+            if actual != chunk.hash || data.len() as u64 != chunk.size {
+                error!("Chunk {} of {} is corrupted; removing it", chunk.hash, entry.path);
+                ctx.storage.delete(&key).await?;
+                ctx.db.remove_chunks(repository_id, &[chunk.hash]).await?;
+                return Err(Error::ChunkCorrupted {
+                    expected: chunk.hash.to_hex(),
+                    actual: actual.to_hex(),
+                });
+            }
+            content.update(&data);
+            bytes_checked += chunk.size;
+        }
+
+        let actual = content.finalize();
+        if actual != entry.content_hash {
+            return Err(Error::ChunkCorrupted {
+                expected: format!("{} for {}", entry.content_hash, entry.path),
+                actual: actual.to_hex(),
+            });
+        }
+    }
+
+    info!(
+        "Verified manifest {}: {} files, {} bytes",
+        hash.short(),
+        manifest.entries.len(),
+        bytes_checked
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use dits_core::{chunk::ChunkRef, commit::FileMode, ManifestEntry, Repository};
+    use dits_db::MemoryDatabase;
+    use dits_storage::LocalBackend;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    async fn store_file(ctx: &JobContext, repo: Uuid, stored: &[u8], claimed: &[u8]) -> Hash {
+        let chunk = Hasher::hash(claimed);
+        ctx.storage.put(&chunk_key(&chunk), Bytes::copy_from_slice(stored)).await.unwrap();
+        ctx.db.insert_chunk(repo, chunk, claimed.len() as u64).await.unwrap();
+
+        let mut manifest = Manifest::new();
+        manifest.insert(ManifestEntry {
+            path: "clip.mov".to_string(),
+            mode: FileMode::Regular,
+            size: claimed.len() as u64,
+            content_hash: Hasher::hash(claimed),
+            chunks: vec![ChunkRef::new(chunk, 0, claimed.len() as u64)],
+        });
+        let bytes = manifest.to_bytes();
+        let hash = Hasher::hash(&bytes);
+        ctx.storage.put(&manifest_key(&hash), Bytes::from(bytes)).await.unwrap();
+        hash
+    }
+
+    #[tokio::test]
+    async fn test_verify_manifest() {
+        let dir = tempdir().unwrap();
+        let ctx = JobContext {
+            db: Arc::new(MemoryDatabase::new()),
+            persistent_db: false,
+            storage: Arc::new(LocalBackend::new(dir.path())),
+            http: reqwest::Client::new(),
+        };
+        let repo = ctx.db.create_repository(Repository::new("team/film")).await.unwrap();
+
+        let good = store_file(&ctx, repo.id, b"frames", b"frames").await;
+        verify_manifest(&ctx, repo.id, &good).await.unwrap();
+
+        let bad = store_file(&ctx, repo.id, b"garbage", b"pixels").await;
+        let result = verify_manifest(&ctx, repo.id, &bad).await;
+        assert!(matches!(result, Err(Error::ChunkCorrupted { .. })));
+
+        let chunk = Hasher::hash(b"pixels");
+        assert!(!ctx.storage.exists(&chunk_key(&chunk)).await.unwrap());
+        assert_eq!(ctx.db.missing_chunks(repo.id, &[chunk]).await.unwrap(), vec![chunk]);
+    }
+}
@@ -1,3 +1,319 @@
-//! Cleanup job.
+//! Garbage collection of unreferenced content.
+//!
+//! Chunks and manifests are shared between repositories, so an object can
+//! only be deleted once no repository needs it. Collection runs in two
+//! passes:
+//!
+//! 1. For each repository, walk every commit reachable from its refs and
+//!    tags, and drop the repository's references to chunks none of those
+//!    commits use.
+//! 2. Delete chunk and manifest objects that no repository still needs.
+//!
+//! Pushes upload chunks and manifests before the refs that make them
+//! reachable, so anything younger than the grace period is left alone.
+//! An upload can also reference a chunk that is already stored without
+//! touching it, so each chunk's references are checked again, under the
+//! database's lock, right before it is deleted. A
+//! reachable commit or manifest that cannot be read aborts the collection,
+//! since the content it uses would otherwise look unreferenced.
+//!
+//! The live set comes from the database, so collection refuses to run
+//! against an in-memory one: it would find nothing live and delete
+//! everything.
 
-// TODO: Implement cleanup tasks
+use chrono::{DateTime, Utc};
+use dits_core::{Error, Hash, Manifest, Repository, Result};
+use dits_storage::keys::{hash_from_key, manifest_key, CHUNKS_PREFIX, MANIFESTS_PREFIX};
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+use tracing::info;
+
+use crate::worker::JobContext;
+
+/// What a collection removed.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct GcStats {
+    /// Chunk references dropped from repositories.
+    pub references_removed: usize,
+    /// Chunk objects deleted from storage.
+    pub chunks_deleted: usize,
+    /// Manifest objects deleted from storage.
+    pub manifests_deleted: usize,
+}
+
+/// Collect unreferenced chunks and manifests older than `grace`.
+pub async fn collect_garbage(ctx: &JobContext, grace: Duration) -> Result<GcStats> {
+    if !ctx.persistent_db {
+        return Err(Error::MissingConfig(
+            "DATABASE_URL (garbage collection needs the persistent database)".to_string(),
+        ));
+    }
+    let grace = chrono::Duration::from_std(grace)
+        .map_err(|e| Error::InvalidConfig(format!("grace period: {}", e)))?;
+    let cutoff = Utc::now() - grace;
+    let mut stats = GcStats::default();
+
+    let (live_chunks, live_manifests) = release_unreachable(ctx, cutoff, &mut stats).await?;
+    stats.chunks_deleted = delete_unreferenced(ctx, CHUNKS_PREFIX, &live_chunks, cutoff).await?;
+    stats.manifests_deleted =
+        delete_unreferenced(ctx, MANIFESTS_PREFIX, &live_manifests, cutoff).await?;
+
+    info!(
+        "Garbage collection removed {} chunk references, {} chunks and {} manifests",
+        stats.references_removed, stats.chunks_deleted, stats.manifests_deleted
+    );
+    Ok(stats)
+}
+
+/// First pass: drop each repository's references to chunks its history no
+/// longer uses, and return the chunks and manifests still needed.
+async fn release_unreachable(
+    ctx: &JobContext,
+    cutoff: DateTime<Utc>,
+    stats: &mut GcStats,
+) -> Result<(HashSet<Hash>, HashSet<Hash>)> {
+    let mut live_chunks = HashSet::new();
+    let mut live_manifests = HashSet::new();
+    for repo in ctx.db.list_repositories().await? {
+        let (chunks, manifests) = reachable_content(ctx, &repo).await?;
+
+        let stale: Vec<Hash> = ctx
+            .db
+            .list_chunks(repo.id)
+            .await?
+            .into_iter()
+            .filter(|(hash, added_at)| !chunks.contains(hash) && *added_at < cutoff)
+            .map(|(hash, _)| hash)
+            .collect();
+        if !stale.is_empty() {
+            ctx.db.remove_chunks(repo.id, &stale).await?;
+            stats.references_removed += stale.len();
+        }
+
+        // Whatever the repository still references keeps its object alive.
+        live_chunks.extend(ctx.db.list_chunks(repo.id).await?.into_iter().map(|(h, _)| h));
+        live_manifests.extend(manifests);
+    }
+    Ok((live_chunks, live_manifests))
+}
+
+/// Chunks and manifests used by commits reachable from a repository's refs and tags.
+///
+/// Fails if any reachable commit or manifest is missing.
+async fn reachable_content(
+    ctx: &JobContext,
+    repo: &Repository,
+) -> Result<(HashSet<Hash>, HashSet<Hash>)> {
+    let mut queue: VecDeque<Hash> = ctx
+        .db
+        .list_refs(repo.id, "")
+        .await?
+        .into_iter()
+        .map(|(_, hash)| hash)
+        .collect();
+    queue.extend(ctx.db.list_tags(repo.id).await?.into_iter().map(|t| t.commit));
+
+    let mut seen = HashSet::new();
+    let mut chunks = HashSet::new();
+    let mut manifests = HashSet::new();
+    while let Some(hash) = queue.pop_front() {
+        if !seen.insert(hash) {
+            continue;
+        }
+        let Some(commit) = ctx.db.get_commit(repo.id, &hash).await? else {
+            return Err(Error::CommitNotFound(format!("{} in {}", hash, repo.name)));
+        };
+        queue.extend(commit.parents.iter().copied());
+
+        if manifests.insert(commit.tree) {
+            let bytes = match ctx.storage.get(&manifest_key(&commit.tree)).await {
+                Ok(bytes) => bytes,
+                Err(Error::ObjectNotFound(_)) => {
+                    return Err(Error::ObjectNotFound(format!(
+                        "manifest {} of {} in {}",
+                        commit.tree, hash, repo.name
+                    )));
+                }
+                Err(e) => return Err(e),
+            };
+            chunks.extend(Manifest::from_bytes(&bytes)?.chunk_hashes().copied());
+        }
+    }
+    Ok((chunks, manifests))
+}
+
+/// Second pass: delete hash-named objects under `prefix` that are not in
+/// `live` and were last modified before `cutoff`.
+///
+/// Chunks referenced again since `live` was built are kept.
+async fn delete_unreferenced(
+    ctx: &JobContext,
+    prefix: &str,
+    live: &HashSet<Hash>,
+    cutoff: DateTime<Utc>,
+) -> Result<usize> {
+    let mut deleted = 0;
+    for key in ctx.storage.list(prefix).await? {
+        let Some(hash) = hash_from_key(prefix, &key) else {
+            continue;
+        };
+        if live.contains(&hash) {
+            continue;
+        }
+        let modified = match ctx.storage.head(&key).await {
+            Ok(meta) => meta.last_modified,
+            Err(Error::ObjectNotFound(_)) => continue,
+            Err(e) => return Err(e),
+        };
+        if modified.is_none_or(|at| at < cutoff) {
+            if prefix == CHUNKS_PREFIX {
+                if !ctx.db.delete_unreferenced_chunk(hash, ctx.storage.delete(&key)).await? {
+                    continue;
+                }
+            } else {
+                ctx.storage.delete(&key).await?;
+            }
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::Bytes;
+    use dits_core::{chunk::ChunkRef, commit::FileMode, Author, Commit, Hasher, ManifestEntry};
+    use dits_db::MemoryDatabase;
+    use dits_storage::keys::chunk_key;
+    use dits_storage::LocalBackend;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[tokio::test]
+    async fn test_collect_garbage() {
+        let dir = tempdir().unwrap();
+        let ctx = JobContext {
+            db: Arc::new(MemoryDatabase::new()),
+            persistent_db: true,
+            storage: Arc::new(LocalBackend::new(dir.path())),
+            http: reqwest::Client::new(),
+        };
+        let repo = ctx.db.create_repository(Repository::new("team/film")).await.unwrap();
+
+        // One chunk used by the branch head, one uploaded but never pushed.
+        let (used, orphan) = (Hasher::hash(b"used"), Hasher::hash(b"orphan"));
+        for (hash, data) in [(used, &b"used"[..]), (orphan, &b"orphan"[..])] {
+            ctx.storage.put(&chunk_key(&hash), Bytes::copy_from_slice(data)).await.unwrap();
+            ctx.db.insert_chunk(repo.id, hash, data.len() as u64).await.unwrap();
+        }
+        let mut manifest = Manifest::new();
+        manifest.insert(ManifestEntry {
+            path: "a.bin".to_string(),
+            mode: FileMode::Regular,
+            size: 4,
+            content_hash: used,
+            chunks: vec![ChunkRef::new(used, 0, 4)],
+        });
+        let bytes = manifest.to_bytes();
+        let tree = Hasher::hash(&bytes);
+        ctx.storage.put(&manifest_key(&tree), Bytes::from(bytes)).await.unwrap();
+        let commit = Commit::new(vec![], tree, Author::new("a", "a@example.com"), "init");
+        ctx.db.insert_commit(repo.id, &commit).await.unwrap();
+        ctx.db.update_ref(repo.id, "refs/heads/main", None, Some(commit.hash)).await.unwrap();
+
+        // Within the grace period nothing is touched.
+        let stats = collect_garbage(&ctx, Duration::from_secs(3600)).await.unwrap();
+        assert_eq!(stats, GcStats::default());
+
+        let stats = collect_garbage(&ctx, Duration::ZERO).await.unwrap();
+        assert_eq!(
+            stats,
+            GcStats { references_removed: 1, chunks_deleted: 1, manifests_deleted: 0 }
+        );
+        assert!(ctx.storage.exists(&chunk_key(&used)).await.unwrap());
+        assert!(!ctx.storage.exists(&chunk_key(&orphan)).await.unwrap());
+
+        // Deleting the repository releases everything it held.
+        ctx.db.delete_repository(repo.id).await.unwrap();
+        let stats = collect_garbage(&ctx, Duration::ZERO).await.unwrap();
+        assert_eq!(stats.chunks_deleted, 1);
+        assert_eq!(stats.manifests_deleted, 1);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_uploaded_again_between_passes_is_kept() {
+        let dir = tempdir().unwrap();
+        let ctx = JobContext {
+            db: Arc::new(MemoryDatabase::new()),
+            persistent_db: true,
+            storage: Arc::new(LocalBackend::new(dir.path())),
+            http: reqwest::Client::new(),
+        };
+        let film = ctx.db.create_repository(Repository::new("team/film")).await.unwrap();
+        let trailer = ctx.db.create_repository(Repository::new("team/trailer")).await.unwrap();
+        let chunk = Hasher::hash(b"frames");
+        ctx.storage.put(&chunk_key(&chunk), Bytes::from_static(b"frames")).await.unwrap();
+        ctx.db.insert_chunk(film.id, chunk, 6).await.unwrap();
+
+        let mut stats = GcStats::default();
+        let (live_chunks, _) = release_unreachable(&ctx, Utc::now(), &mut stats).await.unwrap();
+        assert_eq!(stats.references_removed, 1);
+        assert!(!live_chunks.contains(&chunk));
+
+        // The upload finds the object stored and only records the reference
+        ctx.db.insert_chunk(trailer.id, chunk, 6).await.unwrap();
+
+        let deleted = delete_unreferenced(&ctx, CHUNKS_PREFIX, &live_chunks, Utc::now()).await.unwrap();
+        assert_eq!(deleted, 0);
+        assert!(ctx.storage.exists(&chunk_key(&chunk)).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_missing_history_aborts_collection() {
+        let dir = tempdir().unwrap();
+        let ctx = JobContext {
+            db: Arc::new(MemoryDatabase::new()),
+            persistent_db: true,
+            storage: Arc::new(LocalBackend::new(dir.path())),
+            http: reqwest::Client::new(),
+        };
+        let repo = ctx.db.create_repository(Repository::new("team/film")).await.unwrap();
+        let chunk = Hasher::hash(b"frames");
+        ctx.storage.put(&chunk_key(&chunk), Bytes::from_static(b"frames")).await.unwrap();
+        ctx.db.insert_chunk(repo.id, chunk, 6).await.unwrap();
+
+        // The head commit's manifest is gone, so its chunks cannot be known
+        let commit = Commit::new(vec![], Hasher::hash(b"lost"), Author::new("a", "a@example.com"), "init");
+        ctx.db.insert_commit(repo.id, &commit).await.unwrap();
+        ctx.db.update_ref(repo.id, "refs/heads/main", None, Some(commit.hash)).await.unwrap();
+        let result = collect_garbage(&ctx, Duration::ZERO).await;
+        assert!(matches!(result, Err(Error::ObjectNotFound(_))));
+
+        // Neither is a ref pointing at an unknown commit
+        let unknown = Hasher::hash(b"unknown commit");
+        ctx.db.update_ref(repo.id, "refs/heads/main", Some(commit.hash), Some(unknown)).await.unwrap();
+        let result = collect_garbage(&ctx, Duration::ZERO).await;
+        assert!(matches!(result, Err(Error::CommitNotFound(_))));
+
+        assert!(ctx.storage.exists(&chunk_key(&chunk)).await.unwrap());
+        assert!(ctx.db.missing_chunks(repo.id, &[chunk]).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refuses_without_persistent_database() {
+        let dir = tempdir().unwrap();
+        let ctx = JobContext {
+            db: Arc::new(MemoryDatabase::new()),
+            persistent_db: false,
+            storage: Arc::new(LocalBackend::new(dir.path())),
+            http: reqwest::Client::new(),
+        };
+        let chunk = Hasher::hash(b"frames");
+        ctx.storage.put(&chunk_key(&chunk), Bytes::from_static(b"frames")).await.unwrap();
+
+        let result = collect_garbage(&ctx, Duration::ZERO).await;
+        assert!(matches!(result, Err(Error::MissingConfig(_))));
+        assert!(ctx.storage.exists(&chunk_key(&chunk)).await.unwrap());
+    }
+}
@@ -1,3 +1,162 @@
-//! Webhook delivery job.
+//! Webhook delivery.
+//!
+//! Each delivery is a JSON `POST` with these headers:
+//!
+//! - `X-Dits-Event`: event name (`push`, `lock`, `unlock`)
+//! - `X-Dits-Delivery`: job ID, stable across retries of the same delivery
+//! - `X-Dits-Signature-256`: `sha256=` followed by the hex HMAC-SHA256 of
+//!   the body keyed with the webhook secret, if one is configured
+//!
+//! Receivers should compare the signature in constant time.
 
-// TODO: Implement webhook delivery
+use dits_core::{Error, Result, WebhookDelivery};
+use hmac::{Hmac, Mac};
+use reqwest::{header, StatusCode};
+use sha2::Sha256;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::worker::JobContext;
+
+/// Header carrying the event name.
+pub const EVENT_HEADER: &str = "X-Dits-Event";
+/// Header carrying the delivery ID.
+pub const DELIVERY_HEADER: &str = "X-Dits-Delivery";
+/// Header carrying the body signature.
+pub const SIGNATURE_HEADER: &str = "X-Dits-Signature-256";
+
+/// How long a receiver gets to respond.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Signature header value for `body`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Deliver a webhook.
+///
+/// Network failures, timeouts, `408`, `429` and `5xx` responses are
+/// retryable. Any other non-success response means the receiver rejected
+/// the delivery and retrying will not help.
+pub async fn deliver(ctx: &JobContext, id: Uuid, delivery: &WebhookDelivery) -> Result<()> {
+    let body = serde_json::to_vec(&delivery.payload).map_err(|e| Error::Serialization(e.to_string()))?;
+
+    let mut request = ctx
+        .http
+        .post(&delivery.url)
+        .timeout(DELIVERY_TIMEOUT)
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &delivery.event)
+        .header(DELIVERY_HEADER, id.to_string());
+    if let Some(secret) = &delivery.secret {
+        request = request.header(SIGNATURE_HEADER, sign(secret, &body));
+    }
+
+    let response = request.body(body).send().await.map_err(|e| {
+        if e.is_timeout() {
+            Error::Timeout
+        } else {
+            Error::Network(format!("webhook {}: {}", delivery.url, e))
+        }
+    })?;
+
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => {
+            let retry_after = response
+                .headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(0);
+            Err(Error::RateLimited { retry_after })
+        }
+        StatusCode::REQUEST_TIMEOUT => Err(Error::Timeout),
+        s if s.is_server_error() => Err(Error::Network(format!(
+            "webhook {} responded {}",
+            delivery.url, s
+        ))),
+        s => Err(Error::InvalidConfig(format!(
+            "webhook {} rejected delivery with {}",
+            delivery.url, s
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dits_db::MemoryDatabase;
+    use dits_storage::LocalBackend;
+    use std::sync::Arc;
+    use wiremock::matchers::{header as header_is, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn context() -> JobContext {
+        JobContext {
+            db: Arc::new(MemoryDatabase::new()),
+            persistent_db: false,
+            storage: Arc::new(LocalBackend::new("unused")),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deliver_signed() {
+        let payload = serde_json::json!({ "repository": "team/film" });
+        let body = serde_json::to_vec(&payload).unwrap();
+        let id = Uuid::new_v4();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(header_is("x-dits-event", "push"))
+            .and(header_is("x-dits-delivery", id.to_string().as_str()))
+            .and(header_is("x-dits-signature-256", sign("s3cret", &body).as_str()))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let delivery = WebhookDelivery {
+            url: server.uri(),
+            secret: Some("s3cret".to_string()),
+            event: "push".to_string(),
+            payload,
+        };
+        deliver(&context(), id, &delivery).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rejections_are_not_retryable() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(410))
+            .mount(&server)
+            .await;
+
+        let delivery = WebhookDelivery {
+            url: server.uri(),
+            secret: None,
+            event: "lock".to_string(),
+            payload: serde_json::json!({}),
+        };
+        let err = deliver(&context(), Uuid::new_v4(), &delivery).await.unwrap_err();
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn test_sign() {
+        // RFC 4231 test case 2.
+        let signature = sign("Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            signature,
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
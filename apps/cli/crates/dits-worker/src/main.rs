@@ -1,14 +1,40 @@
 //! Dits Worker
 //!
 //! Background job processor for:
-//! - Chunking and deduplication
+//! - Chunk verification of pushed content
 //! - Webhook delivery
 //! - Cleanup tasks
-//! - Analytics aggregation
+//!
+//! Configuration comes from the environment:
+//!
+//! - `DITS_QUEUE_PATH`: job queue directory shared with the API server
+//! - `DITS_S3_BUCKET` / `DITS_STORAGE_PATH`: object storage, as for the API server
+//! - `DITS_WORKER_CONCURRENCY`: jobs run in parallel (default 4)
+//! - `DITS_GC_INTERVAL_SECS`: how often to queue garbage collection (default 86400, 0 disables);
+//!   never without `DATABASE_URL`
+//! - `DITS_GC_GRACE_SECS`: minimum age of collected objects (default 86400)
 
-use tracing::info;
+use chrono::Duration;
+use dits_core::{Job, QueuedJob};
+use dits_db::{FileJobQueue, JobQueue};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::{info, warn};
 
 mod jobs;
+mod worker;
+
+use worker::{JobContext, Worker};
+
+/// Jobs claimed longer ago than this are assumed to belong to a dead worker.
+const JOB_LEASE_MINUTES: i64 = 30;
+
+fn env_u64(name: &str, default: u64) -> u64 {
+    std::env::var(name)
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(default)
+}
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -18,7 +44,69 @@ async fn main() -> anyhow::Result<()> {
 
     info!("Starting Dits Worker");
 
-    // TODO: Implement job processing
+    dotenvy::dotenv().ok();
+
+    let queue: Arc<dyn JobQueue> = Arc::new(FileJobQueue::from_env().await?);
+    let persistent_db = std::env::var_os("DATABASE_URL").is_some();
+    if !persistent_db {
+        warn!("DATABASE_URL is not set; only webhook jobs can run meaningfully");
+    }
+    let ctx = Arc::new(JobContext {
+        db: dits_db::database_from_env().await?,
+        persistent_db,
+        storage: dits_storage::backend_from_env().await,
+        http: reqwest::Client::new(),
+    });
+
+    let requeued = queue.requeue_stale(Duration::minutes(JOB_LEASE_MINUTES)).await?;
+    if requeued > 0 {
+        info!("Requeued {} abandoned jobs", requeued);
+    }
+
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
+    let mut tasks = Vec::new();
+
+    let concurrency = env_u64("DITS_WORKER_CONCURRENCY", 4).max(1);
+    for _ in 0..concurrency {
+        let worker = Worker::new(ctx.clone(), queue.clone());
+        let shutdown = shutdown_rx.clone();
+        tasks.push(tokio::spawn(async move { worker.run(shutdown).await }));
+    }
+
+    let gc_interval = env_u64("DITS_GC_INTERVAL_SECS", 86_400);
+    let grace_secs = env_u64("DITS_GC_GRACE_SECS", 86_400);
+    if gc_interval > 0 && !persistent_db {
+        warn!("Garbage collection is disabled: it needs DATABASE_URL to know what is live");
+    } else if gc_interval > 0 {
+        let queue = queue.clone();
+        let mut shutdown = shutdown_rx.clone();
+        tasks.push(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(std::time::Duration::from_secs(gc_interval));
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = shutdown.changed() => break,
+                }
+                let job = QueuedJob::new(Job::CollectGarbage { grace_secs });
+                if let Err(e) = queue.enqueue(job).await {
+                    warn!("Failed to queue garbage collection: {}", e);
+                }
+                match queue.requeue_stale(Duration::minutes(JOB_LEASE_MINUTES)).await {
+                    Ok(0) => {}
+                    Ok(n) => info!("Requeued {} abandoned jobs", n),
+                    Err(e) => warn!("Failed to requeue abandoned jobs: {}", e),
+                }
+            }
+        }));
+    }
+
+    info!("Running {} workers", concurrency);
+    tokio::signal::ctrl_c().await?;
+    info!("Shutting down after current jobs");
+    let _ = shutdown_tx.send(true);
+    for task in tasks {
+        let _ = task.await;
+    }
 
     Ok(())
 }
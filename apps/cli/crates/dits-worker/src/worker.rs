@@ -0,0 +1,169 @@
+//! Job execution loop.
+
+use chrono::{Duration, Utc};
+use dits_core::QueuedJob;
+use dits_db::{Database, JobQueue};
+use dits_storage::StorageBackend;
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+use crate::jobs;
+
+/// Backends the jobs operate on.
+pub struct JobContext {
+    pub db: Arc<dyn Database>,
+    /// Whether `db` is the persistent database shared with the API server.
+    /// Garbage collection refuses to run without one.
+    pub persistent_db: bool,
+    pub storage: Arc<dyn StorageBackend>,
+    pub http: reqwest::Client,
+}
+
+/// Delay before retrying a job that has failed `attempts` times.
+///
+/// Doubles from 30 seconds up to an hour.
+pub fn backoff(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(7);
+    Duration::seconds(30 * (1 << exponent)).min(Duration::hours(1))
+}
+
+/// Claims jobs from a queue and runs them.
+pub struct Worker {
+    ctx: Arc<JobContext>,
+    queue: Arc<dyn JobQueue>,
+    poll_interval: std::time::Duration,
+}
+
+impl Worker {
+    /// Create a worker.
+    pub fn new(ctx: Arc<JobContext>, queue: Arc<dyn JobQueue>) -> Self {
+        Self {
+            ctx,
+            queue,
+            poll_interval: std::time::Duration::from_secs(1),
+        }
+    }
+
+    /// Run one due job, if any. Returns whether a job was run.
+    pub async fn run_once(&self) -> dits_core::Result<bool> {
+        let Some(job) = self.queue.claim().await? else {
+            return Ok(false);
+        };
+        self.execute(job).await?;
+        Ok(true)
+    }
+
+    /// Run jobs until `shutdown` becomes true. The current job is finished first.
+    pub async fn run(&self, mut shutdown: watch::Receiver<bool>) {
+        while !*shutdown.borrow() {
+            let idle = match self.run_once().await {
+                Ok(ran) => !ran,
+                Err(e) => {
+                    error!("Job queue error: {}", e);
+                    true
+                }
+            };
+            if idle {
+                tokio::select! {
+                    _ = tokio::time::sleep(self.poll_interval) => {}
+                    _ = shutdown.changed() => {}
+                }
+            }
+        }
+    }
+
+    async fn execute(&self, job: QueuedJob) -> dits_core::Result<()> {
+        let kind = job.job.kind();
+        match jobs::run(&self.ctx, &job).await {
+            Ok(()) => {
+                info!("Job {} ({}) completed", job.id, kind);
+                self.queue.complete(job.id).await
+            }
+            Err(e) if e.is_retryable() && job.can_retry() => {
+                let delay = backoff(job.attempts);
+                warn!(
+                    "Job {} ({}) failed on attempt {}/{}: {}; retrying in {}s",
+                    job.id,
+                    kind,
+                    job.attempts,
+                    job.max_attempts,
+                    e,
+                    delay.num_seconds()
+                );
+                self.queue.retry(job.id, &e.to_string(), Utc::now() + delay).await
+            }
+            Err(e) => {
+                error!("Job {} ({}) failed permanently: {}", job.id, kind, e);
+                self.queue.fail(job.id, &e.to_string()).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dits_core::{Job, WebhookDelivery};
+    use dits_db::{FileJobQueue, MemoryDatabase};
+    use dits_storage::LocalBackend;
+    use tempfile::tempdir;
+    use wiremock::matchers::method;
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1), Duration::seconds(30));
+        assert_eq!(backoff(2), Duration::seconds(60));
+        assert_eq!(backoff(20), Duration::hours(1));
+    }
+
+    #[tokio::test]
+    async fn test_failed_jobs_are_retried_then_given_up() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let dir = tempdir().unwrap();
+        let queue = Arc::new(FileJobQueue::open(dir.path().join("queue")).await.unwrap());
+        let ctx = Arc::new(JobContext {
+            db: Arc::new(MemoryDatabase::new()),
+            persistent_db: false,
+            storage: Arc::new(LocalBackend::new(dir.path().join("storage"))),
+            http: reqwest::Client::new(),
+        });
+        let worker = Worker::new(ctx, queue.clone());
+
+        let job = QueuedJob::new(Job::DeliverWebhook(WebhookDelivery {
+            url: server.uri(),
+            secret: None,
+            event: "push".to_string(),
+            payload: serde_json::json!({}),
+        }));
+        queue.enqueue(job.clone()).await.unwrap();
+
+        // 503: rescheduled with backoff, so nothing is due straight away.
+        assert!(worker.run_once().await.unwrap());
+        assert!(!worker.run_once().await.unwrap());
+
+        // Make it due again; the 404 is permanent.
+        let pending = dir.path().join(format!("queue/pending/{}.json", job.id));
+        let mut rescheduled: QueuedJob =
+            serde_json::from_slice(&std::fs::read(&pending).unwrap()).unwrap();
+        assert!(rescheduled.last_error.is_some());
+        rescheduled.run_at = Utc::now();
+        std::fs::write(&pending, serde_json::to_vec(&rescheduled).unwrap()).unwrap();
+
+        assert!(worker.run_once().await.unwrap());
+        let failed = queue.list_failed().await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].attempts, 2);
+    }
+}
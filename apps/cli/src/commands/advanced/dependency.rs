@@ -2,10 +2,10 @@
 //!
 //! Commands for checking and visualizing project file dependencies.
 
+use crate::store::{Container, RelinkMapping, Repository};
 use dits::dependency::{
-    DependencyValidator, is_project_file, ProjectType,
+    DependencyValidator, is_project_file, parse_project, plan_relink, ProjectType, RelinkPlan,
 };
-use dits::store::Repository;
 use anyhow::{Context, Result, bail};
 use console::style;
 use std::path::{Path, PathBuf};
@@ -273,6 +273,140 @@ pub fn dep_list() -> Result<()> {
     Ok(())
 }
 
+/// Relink media references of project files to the tracked copies in the
+/// working tree, or undo earlier relinks.
+pub fn dep_relink(files: &[String], undo: bool, dry_run: bool) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let repo = Repository::open(&cwd).context("Not a dits repository")?;
+
+    if undo {
+        let paths: Vec<String> = if files.is_empty() {
+            repo.relinks().list()?.into_iter().map(|r| r.path).collect()
+        } else {
+            files.iter().map(|f| f.replace('\\', "/")).collect()
+        };
+        if paths.is_empty() {
+            println!("{}", style("No relinked project files.").dim());
+            return Ok(());
+        }
+
+        let mut restored = 0;
+        for path in &paths {
+            if dry_run {
+                println!("  Would restore {}", style(path).cyan());
+            } else if repo.undo_relink(path)? {
+                println!("  {} {}", style("Restored").green(), style(path).cyan());
+                restored += 1;
+            } else {
+                println!("  {} {} (not relinked)", style("Skipped").dim(), path);
+            }
+        }
+        if !dry_run {
+            println!();
+            println!("Restored committed media paths in {} project file(s)", restored);
+        }
+        return Ok(());
+    }
+
+    let tracked = get_tracked_files(&repo)?;
+    let projects: Vec<String> = if files.is_empty() {
+        tracked.iter().filter(|p| Container::from_path(Path::new(p)).is_some()).cloned().collect()
+    } else {
+        files.iter().map(|f| f.replace('\\', "/")).collect()
+    };
+    if projects.is_empty() {
+        println!("{}", style("No project files to relink.").yellow());
+        return Ok(());
+    }
+
+    let mut total_relinked = 0;
+    let mut total_unresolved = 0;
+    for project in &projects {
+        if Container::from_path(Path::new(project)).is_none() {
+            println!(
+                "  {} {} (relinking supports .prproj, .fcpxml and .drp)",
+                style("Skipped").dim(),
+                project
+            );
+            continue;
+        }
+
+        print!("  {} ({})... ", style(project).cyan(), ProjectType::from_path(Path::new(project)).name());
+        let plan = match relink_project(&repo, project, &tracked, dry_run) {
+            Ok(plan) => plan,
+            Err(e) => {
+                println!("{} ({})", style("ERROR").red(), e);
+                continue;
+            }
+        };
+
+        if plan.targets.is_empty() && plan.unresolved.is_empty() {
+            println!("{} nothing to relink", style("✓").green());
+            continue;
+        }
+        println!(
+            "{} {} reference(s) {}",
+            style("✓").green(),
+            plan.targets.len(),
+            if dry_run { "to relink" } else { "relinked" }
+        );
+        for target in &plan.targets {
+            println!("      {} → {}", target.original, style(&target.tracked).green());
+        }
+        if !plan.unresolved.is_empty() {
+            println!("    {} {} reference(s) match no tracked file:", style("Unresolved:").yellow().bold(), plan.unresolved.len());
+            for path in &plan.unresolved {
+                println!("      - {}", style(path).yellow());
+            }
+        }
+        total_relinked += plan.targets.len();
+        total_unresolved += plan.unresolved.len();
+    }
+
+    println!();
+    println!("{}", style("Summary:").bold());
+    println!("  Relinked:    {}", style(total_relinked).green());
+    println!("  Unresolved:  {}", if total_unresolved > 0 { style(total_unresolved).yellow() } else { style(total_unresolved).green() });
+    if total_relinked > 0 && !dry_run {
+        println!();
+        println!(
+            "{} Commits keep the original paths. Restore them here with: dits dep-relink --undo",
+            style("Hint:").cyan()
+        );
+    }
+
+    Ok(())
+}
+
+/// Relink every tracked project file, e.g. after checkout. Returns the
+/// number of references relinked.
+pub fn relink_worktree(repo: &Repository) -> Result<usize> {
+    let tracked = get_tracked_files(repo)?;
+    let mut relinked = 0;
+    for project in tracked.iter().filter(|p| Container::from_path(Path::new(p)).is_some()) {
+        relinked += relink_project(repo, project, &tracked, false)?.targets.len();
+    }
+    Ok(relinked)
+}
+
+/// Match a project's external references to tracked files and, unless
+/// `dry_run`, rewrite them.
+fn relink_project(repo: &Repository, project: &str, tracked: &[String], dry_run: bool) -> Result<RelinkPlan> {
+    let root = std::fs::canonicalize(repo.work_dir())?;
+    let parsed = parse_project(&root.join(project))?;
+    let plan = plan_relink(&parsed, &root, tracked);
+
+    if !dry_run && !plan.targets.is_empty() {
+        let mappings = plan
+            .targets
+            .iter()
+            .map(|t| RelinkMapping::new(&t.original, root.join(&t.tracked).to_string_lossy()))
+            .collect();
+        repo.relink(project, mappings)?;
+    }
+    Ok(plan)
+}
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
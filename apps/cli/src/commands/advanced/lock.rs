@@ -1,68 +1,213 @@
 //! Lock management CLI commands.
 //!
 //! Provides commands for locking/unlocking files (primarily for binary files).
+//!
+//! When `origin` is set, locks are taken and released on the remote, which
+//! decides who holds what; the local lock store is only a cache of what the
+//! remote last reported. Without a remote, locks live in this clone alone.
 
 use anyhow::{Context, Result, bail};
-use crate::store::locks::{Lock, LockStore};
+use crate::store::locks::{current_user, Lock, LockStore};
+use crate::store::remote::{RemoteStore, RemoteType};
+use crate::store::remote_client::{token_for, HttpRemote, RemoteClientError, TOKEN_ENV};
+use crate::store::remote_server::{LockRequest, RemoteLock};
+use crate::store::Repository;
+use reqwest::StatusCode;
+use std::path::{Path, PathBuf};
+
+/// Remote whose locks are authoritative.
+const LOCK_REMOTE: &str = "origin";
+
+/// Where the locks of a repository are held.
+enum LockAuthority {
+    /// An HTTP remote; the lock owner is whoever the access token belongs to.
+    Http(HttpRemote),
+    /// A repository on this filesystem (its `.dits` directory).
+    Local(PathBuf),
+    /// No remote: locks are local to this clone.
+    None,
+}
+
+impl LockAuthority {
+    fn for_repo(dits_dir: &Path) -> Result<Self> {
+        let store = RemoteStore::new(dits_dir);
+        let Some(remote) = store.get(LOCK_REMOTE) else {
+            return Ok(Self::None);
+        };
+
+        match RemoteType::parse(&remote.url) {
+            RemoteType::Http(url) => {
+                Ok(Self::Http(HttpRemote::new(&url).with_token(token_for(dits_dir, LOCK_REMOTE))))
+            }
+            RemoteType::Local(path) => {
+                let work_dir = dits_dir.parent().unwrap_or(dits_dir);
+                let remote_dits = work_dir.join(path).join(".dits");
+                if !remote_dits.exists() {
+                    bail!("Remote '{}' is not a dits repository: {}", LOCK_REMOTE, remote.url);
+                }
+                Ok(Self::Local(remote_dits))
+            }
+            _ => {
+                eprintln!(
+                    "Warning: remote '{}' ({}) does not support locks; locking in this clone only",
+                    LOCK_REMOTE, remote.url
+                );
+                Ok(Self::None)
+            }
+        }
+    }
+}
+
+/// Explain a failed remote lock operation.
+fn remote_error(e: RemoteClientError) -> anyhow::Error {
+    match e {
+        RemoteClientError::Rejected(message) => anyhow::anyhow!("{}", message),
+        RemoteClientError::Status { status: StatusCode::UNAUTHORIZED, message } => anyhow::anyhow!(
+            "{} (set a token with `dits remote set-token {} <token>` or {})",
+            message,
+            LOCK_REMOTE,
+            TOKEN_ENV
+        ),
+        RemoteClientError::Status { message, .. } => anyhow::anyhow!("{}", message),
+        e => e.into(),
+    }
+}
 
 /// Lock a file.
-pub fn lock(
+pub async fn lock(
     path: &str,
     reason: Option<&str>,
     ttl_hours: Option<u64>,
     force: bool,
 ) -> Result<()> {
     let dits_dir = find_dits_dir()?;
-    let mut store = LockStore::new(&dits_dir);
-
-    let owner = get_current_user()?;
+    let mut cache = LockStore::new(&dits_dir);
     let ttl_secs = ttl_hours.map(|h| h * 3600);
 
-    match store.acquire(path, &owner, ttl_secs, reason, force) {
-        Ok(lock) => {
-            if force {
-                println!("Force-locked '{}'", path);
-            } else {
-                println!("Locked '{}'", path);
-            }
-            if let Some(r) = &lock.reason {
-                println!("  Reason: {}", r);
-            }
-            println!("  Expires in: {}", lock.expires_in_human());
-            Ok(())
+    let lock = match LockAuthority::for_repo(&dits_dir)? {
+        LockAuthority::Http(remote) => {
+            let request = LockRequest {
+                path: path.to_string(),
+                reason: reason.map(String::from),
+                ttl_secs,
+            };
+            let lock = match remote.lock(&request).await {
+                Err(RemoteClientError::Rejected(_)) if force => {
+                    // Break the other holder's lock, then take it
+                    let held = remote.locks().await.map_err(remote_error)?;
+                    if let Some(existing) = held.iter().find(|l| l.path == path) {
+                        remote.unlock(&existing.id, true).await.map_err(remote_error)?;
+                    }
+                    remote.lock(&request).await
+                }
+                result => result,
+            };
+            let mut lock = Lock::from(lock.map_err(remote_error)?);
+            lock.held = true;
+            cache.insert(lock.clone())?;
+            lock
+        }
+        LockAuthority::Local(remote_dits) => {
+            let owner = get_current_user()?;
+            let mut lock = LockStore::new(&remote_dits).acquire(path, &owner, ttl_secs, reason, force)?;
+            lock.held = true;
+            cache.insert(lock.clone())?;
+            lock
         }
-        Err(e) => {
-            bail!("{}", e)
+        LockAuthority::None => {
+            let owner = get_current_user()?;
+            cache.acquire(path, &owner, ttl_secs, reason, force)?
         }
+    };
+
+    if force {
+        println!("Force-locked '{}'", path);
+    } else {
+        println!("Locked '{}'", path);
+    }
+    println!("  Owner: {}", lock.owner);
+    if let Some(r) = &lock.reason {
+        println!("  Reason: {}", r);
     }
+    println!("  Expires in: {}", lock.expires_in_human());
+    if update_permissions(&dits_dir, Some(path))? > 0 {
+        println!("  Now writable");
+    }
+    Ok(())
 }
 
 /// Unlock a file.
-pub fn unlock(path: &str, force: bool) -> Result<()> {
+pub async fn unlock(path: &str, force: bool) -> Result<()> {
     let dits_dir = find_dits_dir()?;
-    let mut store = LockStore::new(&dits_dir);
+    let mut cache = LockStore::new(&dits_dir);
 
-    let owner = get_current_user()?;
-
-    match store.release(path, &owner, force) {
-        Ok(()) => {
-            if force {
-                println!("Force-unlocked '{}'", path);
-            } else {
-                println!("Unlocked '{}'", path);
+    match LockAuthority::for_repo(&dits_dir)? {
+        LockAuthority::Http(remote) => {
+            let cached = cache.get(path).map(|l| l.id.clone()).filter(|id| !id.is_empty());
+            let id = match cached {
+                Some(id) => id,
+                None => {
+                    let held = remote.locks().await.map_err(remote_error)?;
+                    match held.into_iter().find(|l| l.path == path) {
+                        Some(lock) => lock.id,
+                        None => {
+                            cache.remove(path)?;
+                            bail!("File '{}' is not locked", path);
+                        }
+                    }
+                }
+            };
+            match remote.unlock(&id, force).await {
+                // Already released or expired on the remote
+                Ok(()) | Err(RemoteClientError::Status { status: StatusCode::NOT_FOUND, .. }) => {}
+                Err(e) => return Err(remote_error(e)),
             }
-            Ok(())
+            cache.remove(path)?;
+        }
+        LockAuthority::Local(remote_dits) => {
+            let owner = get_current_user()?;
+            LockStore::new(&remote_dits).release(path, &owner, force)?;
+            cache.remove(path)?;
         }
-        Err(e) => {
-            bail!("{}", e)
+        LockAuthority::None => {
+            let owner = get_current_user()?;
+            cache.release(path, &owner, force)?;
         }
     }
+
+    if force {
+        println!("Force-unlocked '{}'", path);
+    } else {
+        println!("Unlocked '{}'", path);
+    }
+    if update_permissions(&dits_dir, Some(path))? > 0 {
+        println!("  Now read-only");
+    }
+    Ok(())
 }
 
 /// List all locks.
-pub fn locks(owner_filter: Option<&str>, verbose: bool) -> Result<()> {
+///
+/// With `remote`, the list comes from the remote and refreshes the cache;
+/// otherwise the cached locks are shown.
+pub async fn locks(owner_filter: Option<&str>, verbose: bool, remote: bool) -> Result<()> {
     let dits_dir = find_dits_dir()?;
-    let store = LockStore::new(&dits_dir);
+    let mut store = LockStore::new(&dits_dir);
+
+    if remote {
+        let held: Vec<Lock> = match LockAuthority::for_repo(&dits_dir)? {
+            LockAuthority::Http(remote) => {
+                let held: Vec<RemoteLock> = remote.locks().await.map_err(remote_error)?;
+                held.into_iter().map(Lock::from).collect()
+            }
+            LockAuthority::Local(remote_dits) => {
+                LockStore::new(&remote_dits).list().into_iter().cloned().collect()
+            }
+            LockAuthority::None => bail!("No remote '{}' configured", LOCK_REMOTE),
+        };
+        store.replace_all(held).context("Failed to update lock cache")?;
+        update_permissions(&dits_dir, None)?;
+    }
 
     let lock_list: Vec<&Lock> = if let Some(owner) = owner_filter {
         store.list_by_owner(owner)
@@ -106,24 +251,23 @@ pub fn locks(owner_filter: Option<&str>, verbose: bool) -> Result<()> {
     Ok(())
 }
 
-/// Get the current user identifier.
-fn get_current_user() -> Result<String> {
-    // Try git config first
-    if let Ok(output) = std::process::Command::new("git")
-        .args(["config", "user.email"])
-        .output()
-    {
-        if output.status.success() {
-            let email = String::from_utf8_lossy(&output.stdout).trim().to_string();
-            if !email.is_empty() {
-                return Ok(email);
-            }
-        }
+/// Re-apply `locks.read_only` permissions to `path`, or to every tracked
+/// file, after the locks we hold changed. Returns how many files changed.
+fn update_permissions(dits_dir: &Path, path: Option<&str>) -> Result<usize> {
+    let repo = Repository::open(dits_dir.parent().unwrap_or(dits_dir))?;
+    if !repo.read_only_until_locked() {
+        return Ok(0);
     }
+    let changed = match path {
+        Some(path) => repo.update_lock_permissions([path])?,
+        None => repo.refresh_lock_permissions()?,
+    };
+    Ok(changed)
+}
 
-    // Fall back to system username
-    std::env::var("USER")
-        .or_else(|_| std::env::var("USERNAME"))
+/// Get the current user identifier.
+fn get_current_user() -> Result<String> {
+    current_user()
         .context("Could not determine current user. Set USER environment variable or configure git user.email")
 }
 
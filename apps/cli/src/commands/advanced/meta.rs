@@ -2,10 +2,12 @@
 //!
 //! Commands for extracting and viewing file metadata.
 
-use dits::metadata::{MetadataRegistry, MetadataStore};
+use dits::core::Hash as DitsHash;
+use dits::metadata::{FileMetadata, MetaFilter, MetaQuery, MetadataIndex, MetadataRegistry, MetadataStore};
 use dits::store::Repository;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use console::style;
+use std::path::Path;
 
 /// Scan files in HEAD and extract metadata.
 pub fn meta_scan(verbose: bool) -> Result<()> {
@@ -32,6 +34,7 @@ pub fn meta_scan(verbose: bool) -> Result<()> {
 
     let mut scanned = 0;
     let mut extracted = 0;
+    let mut stored = Vec::new();
     let mut skipped = 0;
     let mut errors = 0;
 
@@ -77,6 +80,7 @@ pub fn meta_scan(verbose: bool) -> Result<()> {
                         );
                     }
                     extracted += 1;
+                    stored.push((entry.content_hash, metadata));
                 }
             }
             Err(e) => {
@@ -88,6 +92,9 @@ pub fn meta_scan(verbose: bool) -> Result<()> {
         }
     }
 
+    // Keep the query index in step with the store
+    MetadataIndex::new(repo.dits_dir()).update(stored.iter().map(|(h, m)| (h, m)))?;
+
     println!();
     println!("{}", style("Scan complete:").bold());
     println!("  Files scanned:   {}", scanned);
@@ -164,3 +171,84 @@ pub fn meta_list() -> Result<()> {
 
     Ok(())
 }
+
+/// Parse a metadata query and load the index columns it needs.
+pub fn meta_filter(dits_dir: &Path, query: &str) -> Result<MetaFilter> {
+    let query: MetaQuery = query.parse().map_err(|e| anyhow!("Invalid metadata query: {}", e))?;
+    Ok(MetaFilter::new(dits_dir, query)?)
+}
+
+/// Whether a file matches a metadata filter.
+pub fn meta_matches(filter: &MetaFilter, path: &str, content_hash: &crate::core::Hash) -> bool {
+    // Convert the hash to the library type via hex string
+    DitsHash::from_hex(&content_hash.to_hex())
+        .map(|hash| filter.matches(path, &hash))
+        .unwrap_or(false)
+}
+
+/// Find files in HEAD whose metadata matches a query.
+pub fn meta_find(query: &str, since: Option<&str>) -> Result<()> {
+    let cwd = std::env::current_dir()?;
+    let repo = Repository::open(&cwd).context("Not a dits repository")?;
+
+    let head = repo.head()?.context("No commits yet")?;
+    let manifest = repo.load_manifest(&repo.load_commit(&head)?.manifest)?;
+
+    // Only files added or changed after `since`
+    let baseline = match since {
+        Some(rev) => {
+            let hash = repo
+                .resolve_ref_or_prefix(rev)?
+                .with_context(|| format!("Could not resolve '{}' to a commit", rev))?;
+            Some(repo.load_manifest(&repo.load_commit(&hash)?.manifest)?)
+        }
+        None => None,
+    };
+
+    let filter = meta_filter(repo.dits_dir(), query)?;
+    let meta_store = MetadataStore::new(repo.dits_dir());
+
+    let mut found = 0;
+    for (path, entry) in manifest.iter() {
+        if let Some(base) = &baseline {
+            if base.get(path).map(|e| e.content_hash) == Some(entry.content_hash) {
+                continue;
+            }
+        }
+        if !filter.matches(path, &entry.content_hash) {
+            continue;
+        }
+
+        found += 1;
+        match meta_store.load(&entry.content_hash)? {
+            Some(metadata) => println!("  {} {}", path, style(summary(&metadata)).dim()),
+            None => println!("  {}", path),
+        }
+    }
+
+    println!();
+    println!(
+        "{} {} file(s) matching {}",
+        style("Found").bold(),
+        found,
+        style(filter.query()).cyan()
+    );
+
+    Ok(())
+}
+
+/// One-line summary of the common media fields.
+fn summary(metadata: &FileMetadata) -> String {
+    let field = |key: &str| metadata.extra.get(key);
+    let mut parts = vec![format!("[{}]", metadata.content_type)];
+    if let (Some(w), Some(h)) = (field("width"), field("height")) {
+        parts.push(format!("{}x{}", w, h));
+    }
+    if let Some(codec) = field("codec").and_then(|c| c.as_str()) {
+        parts.push(codec.to_string());
+    }
+    if let Some(duration) = field("duration").and_then(|d| d.as_f64()) {
+        parts.push(format!("{:.1}s", duration));
+    }
+    parts.join(" ")
+}
@@ -8,6 +8,10 @@
 //! # Share a repository for P2P access
 //! dits p2p share ./my-repo
 //!
+//! # Fetch from it on another machine, using the address it prints
+//! dits remote add editor dits://192.168.1.20:4433/<fingerprint>
+//! dits fetch editor
+//!
 //! # Connect to a shared repository using a join code
 //! dits p2p connect ABC-123 ./remote-repo
 //! ```
@@ -149,10 +153,10 @@ pub struct UnmountArgs {
 }
 
 /// Handle P2P commands
-pub fn handle_p2p_command(command: P2pCommands) -> Result<()> {
+pub async fn handle_p2p_command(command: P2pCommands) -> Result<()> {
     match command {
-        P2pCommands::Share(args) => share_repository(args),
-        P2pCommands::Connect(args) => connect_repository(args),
+        P2pCommands::Share(args) => share_repository(args).await,
+        P2pCommands::Connect(args) => connect_repository(args).await,
         P2pCommands::Status => show_p2p_status(),
         P2pCommands::List => list_p2p_shares(),
         P2pCommands::Cache(args) => handle_cache_command(args),
@@ -162,7 +166,7 @@ pub fn handle_p2p_command(command: P2pCommands) -> Result<()> {
 }
 
 /// Share a repository for P2P access
-pub fn share_repository(args: ShareArgs) -> Result<()> {
+pub async fn share_repository(args: ShareArgs) -> Result<()> {
     // Validate the repository path
     let repo_path = args.path.canonicalize()
         .with_context(|| format!("Failed to access repository path: {}", args.path.display()))?;
@@ -182,19 +186,12 @@ pub fn share_repository(args: ShareArgs) -> Result<()> {
     };
 
     // Start the P2P host (this will block until shutdown)
-    let rt = tokio::runtime::Runtime::new()
-        .with_context(|| "Failed to create tokio runtime")?;
-
-    rt.block_on(async {
-        let host = start_p2p_host(config).await?;
-        println!("✅ Repository shared successfully!");
-        println!("🎯 Join code: {}", host.join_code());
-        Ok(())
-    })
+    start_p2p_host(config).await?;
+    Ok(())
 }
 
 /// Connect to a shared repository using a join code
-pub fn connect_repository(args: ConnectArgs) -> Result<()> {
+pub async fn connect_repository(args: ConnectArgs) -> Result<()> {
     // Validate the target path
     if args.path.exists() {
         if !args.path.is_dir() {
@@ -221,15 +218,10 @@ pub fn connect_repository(args: ConnectArgs) -> Result<()> {
     };
 
     // Connect to the P2P repository
-    let rt = tokio::runtime::Runtime::new()
-        .with_context(|| "Failed to create tokio runtime")?;
-
-    rt.block_on(async {
-        let _client = connect_p2p_repository(config).await?;
-        println!("✅ Connected to P2P repository!");
-        println!("📁 Repository mounted at: {}", args.path.display());
-        Ok(())
-    })
+    let _client = connect_p2p_repository(config).await?;
+    println!("✅ Connected to P2P repository!");
+    println!("📁 Repository mounted at: {}", args.path.display());
+    Ok(())
 }
 
 /// Show status of active P2P connections
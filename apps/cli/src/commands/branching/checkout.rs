@@ -1,12 +1,27 @@
 //! Checkout commits or branches.
 
+use super::restore::restore_worktree_files;
+use crate::commands::advanced::dependency::relink_worktree;
+use crate::commands::advanced::meta::{meta_filter, meta_matches};
 use crate::core::Hash;
 use crate::store::Repository;
 use anyhow::{Context, Result};
 use console::style;
-use dits::proxy::{ProxyStore, VariantType};
+use dits::proxy::{ProxyStore, ProxyVariant, VariantType};
 use std::path::Path;
 
+/// Extensions of the video files proxies stand in for.
+pub const VIDEO_EXTENSIONS: [&str; 7] = ["mp4", "mov", "mkv", "avi", "mxf", "m4v", "webm"];
+
+/// Proxy resolutions in order of preference.
+pub const PROXY_PREFERENCE: [VariantType; 5] = [
+    VariantType::Proxy1080p,
+    VariantType::Proxy720p,
+    VariantType::Proxy540p,
+    VariantType::ProxyHalf,
+    VariantType::ProxyQuarter,
+];
+
 /// Checkout mode.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum CheckoutMode {
@@ -62,6 +77,7 @@ pub fn checkout(target: &str, mode: CheckoutMode) -> Result<()> {
                 format_bytes(proxy_result.1)
             );
         }
+        apply_relink_checkout(&repo);
         return Ok(());
     }
 
@@ -92,6 +108,7 @@ pub fn checkout(target: &str, mode: CheckoutMode) -> Result<()> {
                 format_bytes(proxy_result.1)
             );
         }
+        apply_relink_checkout(&repo);
         return Ok(());
     }
 
@@ -125,6 +142,7 @@ pub fn checkout(target: &str, mode: CheckoutMode) -> Result<()> {
                     format_bytes(proxy_result.1)
                 );
             }
+            apply_relink_checkout(&repo);
             return Ok(());
         }
     }
@@ -132,11 +150,34 @@ pub fn checkout(target: &str, mode: CheckoutMode) -> Result<()> {
     anyhow::bail!("pathspec '{}' did not match any branch or commit", target);
 }
 
+/// Restore the files of a commit or branch whose metadata matches a query,
+/// without moving HEAD.
+pub fn checkout_matching(target: &str, query: &str) -> Result<()> {
+    let repo = Repository::open(Path::new("."))
+        .context("Not a Dits repository (or any parent directory)")?;
+
+    let hash = repo
+        .resolve_ref_or_prefix(target)?
+        .with_context(|| format!("pathspec '{}' did not match any branch or commit", target))?;
+    let manifest = repo.load_manifest(&repo.load_commit(&hash)?.manifest)?;
+
+    let filter = meta_filter(repo.dits_dir(), query)?;
+    let paths: Vec<String> = manifest
+        .iter()
+        .filter(|(path, entry)| meta_matches(&filter, path, &entry.content_hash))
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    if paths.is_empty() {
+        println!("No files in '{}' match {}", target, style(filter.query()).cyan());
+        return Ok(());
+    }
+    restore_worktree_files(&repo, &paths, Some(&hash))
+}
+
 /// Apply proxy checkout - replace video files with their proxies where available.
 /// Returns (files_replaced, bytes_saved).
 fn apply_proxy_checkout(repo: &Repository) -> Result<(usize, u64)> {
-    use dits::core::Hash as DitsHash;
-
     let proxy_store = ProxyStore::new(repo.dits_dir());
     let cwd = std::env::current_dir()?;
 
@@ -148,53 +189,32 @@ fn apply_proxy_checkout(repo: &Repository) -> Result<(usize, u64)> {
     let commit = repo.load_commit(&head)?;
     let manifest = repo.load_manifest(&commit.manifest)?;
 
-    let video_extensions = ["mp4", "mov", "mkv", "avi", "mxf", "m4v", "webm"];
-
     let mut files_replaced = 0;
     let mut bytes_saved: u64 = 0;
 
     for (path, entry) in manifest.entries.iter() {
         let path_lower = path.to_lowercase();
-        if !video_extensions.iter().any(|ext| path_lower.ends_with(ext)) {
+        if !VIDEO_EXTENSIONS.iter().any(|ext| path_lower.ends_with(ext)) {
             continue;
         }
 
-        // Convert the hash to the library type via hex string
-        let content_hash_hex = entry.content_hash.to_hex();
-        let content_hash = match DitsHash::from_hex(&content_hash_hex) {
-            Ok(h) => h,
-            Err(_) => continue,
+        // Look for a proxy variant for this file
+        let Some(variant) = find_proxy(&proxy_store, &entry.content_hash, &PROXY_PREFERENCE) else {
+            continue;
         };
+        if let Ok(Some(proxy_data)) = proxy_store.load_data(&variant.content_hash) {
+            let file_path = cwd.join(path);
+            let original_size = entry.size;
 
-        // Look for a proxy variant for this file
-        // Try different resolutions in order of preference
-        let variant_types = [
-            VariantType::Proxy1080p,
-            VariantType::Proxy720p,
-            VariantType::Proxy540p,
-            VariantType::ProxyHalf,
-            VariantType::ProxyQuarter,
-        ];
-
-        for variant_type in &variant_types {
-            if let Ok(Some(variant)) = proxy_store.load(&content_hash, *variant_type) {
-                // Found a proxy - load and write it
-                if let Ok(Some(proxy_data)) = proxy_store.load_data(&variant.content_hash) {
-                    let file_path = cwd.join(path);
-                    let original_size = entry.size;
-
-                    // Write proxy in place of original
-                    if let Err(e) = std::fs::write(&file_path, &proxy_data) {
-                        eprintln!("  Warning: Failed to write proxy for {}: {}", path, e);
-                        continue;
-                    }
-
-                    files_replaced += 1;
-                    if original_size > proxy_data.len() as u64 {
-                        bytes_saved += original_size - proxy_data.len() as u64;
-                    }
-                }
-                break; // Found a proxy, don't try other resolutions
+            // Write proxy in place of original
+            if let Err(e) = std::fs::write(&file_path, &proxy_data) {
+                eprintln!("  Warning: Failed to write proxy for {}: {}", path, e);
+                continue;
+            }
+
+            files_replaced += 1;
+            if original_size > proxy_data.len() as u64 {
+                bytes_saved += original_size - proxy_data.len() as u64;
             }
         }
     }
@@ -202,6 +222,36 @@ fn apply_proxy_checkout(repo: &Repository) -> Result<(usize, u64)> {
     Ok((files_replaced, bytes_saved))
 }
 
+/// Find the first proxy of a video among `variant_types`.
+pub fn find_proxy(
+    proxy_store: &ProxyStore,
+    content_hash: &Hash,
+    variant_types: &[VariantType],
+) -> Option<ProxyVariant> {
+    // Proxies are keyed by the library's hash type
+    let content_hash = dits::core::Hash::from_hex(&content_hash.to_hex()).ok()?;
+    variant_types
+        .iter()
+        .find_map(|variant_type| proxy_store.load(&content_hash, *variant_type).ok().flatten())
+}
+
+/// Relink project media to the checked out tree if
+/// `dependencies.relink_on_checkout` is set. Failures only warn: the
+/// checkout itself has succeeded.
+fn apply_relink_checkout(repo: &Repository) {
+    if !repo.relink_on_checkout() {
+        return;
+    }
+    match relink_worktree(repo) {
+        Ok(0) => {}
+        Ok(relinked) => println!(
+            "  {} media reference(s) relinked to this working tree",
+            style(relinked).cyan()
+        ),
+        Err(e) => eprintln!("  Warning: Failed to relink project media: {}", e),
+    }
+}
+
 /// Format bytes as human-readable string.
 fn format_bytes(bytes: u64) -> String {
     const KB: u64 = 1024;
@@ -1,6 +1,6 @@
 //! Cherry-pick command - apply specific commits.
 
-use crate::core::{Author, Commit, Index, IndexEntry};
+use crate::core::{Author, Commit, Index, IndexEntry, IndexLock};
 use crate::store::Repository;
 use anyhow::{Context, Result, bail};
 use console::style;
@@ -60,8 +60,8 @@ fn cherry_pick_single(repo: &Repository, commit_ref: &str, no_commit: bool) -> R
 
     // Load current index
     let index_path = repo.dits_dir().join("index");
-    let index_json = fs::read_to_string(&index_path)?;
-    let mut index = Index::from_json(&index_json)?;
+    let lock = IndexLock::acquire(&index_path)?;
+    let mut index = Index::load(&index_path)?;
 
     // Apply changes from the commit
     for (path, entry) in commit_manifest.iter() {
@@ -146,7 +146,7 @@ fn cherry_pick_single(repo: &Repository, commit_ref: &str, no_commit: bool) -> R
     }
 
     // Save index
-    fs::write(&index_path, index.to_json())?;
+    lock.commit(&mut index)?;
 
     if !conflict_files.is_empty() {
         println!();
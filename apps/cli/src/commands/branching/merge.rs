@@ -1,10 +1,10 @@
 //! Merge command implementation.
 
-use crate::core::{Author, Commit, Hash, Manifest, ManifestEntry};
-use crate::store::Repository;
+use crate::core::{Author, Commit, Hash, Manifest, ManifestEntry, MergeDriver};
+use crate::store::{GitTextEngine, MergeResult as TextMergeResult, Repository};
 use anyhow::{Context, Result};
 use console::style;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 
 /// Merge result types.
@@ -90,7 +90,7 @@ pub fn merge(branch: &str, message: Option<&str>) -> Result<()> {
     }
 
     // Check for conflicts
-    let conflicts = detect_conflicts(&repo, merge_base.as_ref(), &our_hash, &their_hash)?;
+    let (conflicts, resolved) = detect_conflicts(&repo, merge_base.as_ref(), &our_hash, &their_hash)?;
 
     if !conflicts.is_empty() {
         // Report conflicts
@@ -122,6 +122,12 @@ pub fn merge(branch: &str, message: Option<&str>) -> Result<()> {
         return Ok(());
     }
 
+    let mut auto_merged: Vec<&String> = resolved.keys().collect();
+    auto_merged.sort();
+    for path in auto_merged {
+        println!("Auto-merging {}", path);
+    }
+
     // Perform three-way merge
     three_way_merge(
         &repo,
@@ -131,6 +137,7 @@ pub fn merge(branch: &str, message: Option<&str>) -> Result<()> {
         &their_hash,
         branch,
         message,
+        &resolved,
     )
 }
 
@@ -166,6 +173,10 @@ fn fast_forward_merge(
 }
 
 /// Three-way merge (when histories have diverged).
+///
+/// `resolved` holds the entries merge drivers produced for files changed
+/// on both sides.
+#[allow(clippy::too_many_arguments)]
 fn three_way_merge(
     repo: &Repository,
     current_branch: &str,
@@ -174,6 +185,7 @@ fn three_way_merge(
     theirs: &Hash,
     their_branch: &str,
     message: Option<&str>,
+    resolved: &HashMap<String, ManifestEntry>,
 ) -> Result<()> {
     // Load manifests
     let our_commit = repo.objects().load_commit(ours)?;
@@ -188,7 +200,7 @@ fn three_way_merge(
     };
 
     // Merge manifests
-    let merged_manifest = merge_manifests(base_manifest.as_ref(), &our_manifest, &their_manifest)?;
+    let merged_manifest = merge_manifests(base_manifest.as_ref(), &our_manifest, &their_manifest, resolved)?;
 
     // Store merged manifest
     let manifest_hash = repo.objects().store_manifest(&merged_manifest)?;
@@ -292,12 +304,16 @@ fn count_commits_between(repo: &Repository, from: &Hash, to: &Hash) -> Result<us
 }
 
 /// Detect merge conflicts between two branches.
+///
+/// Files changed on both sides are first offered to their `merge` driver
+/// from .ditsattributes; those it resolves are returned alongside the
+/// conflicts instead of being reported.
 fn detect_conflicts(
     repo: &Repository,
     base: Option<&Hash>,
     ours: &Hash,
     theirs: &Hash,
-) -> Result<Vec<MergeConflict>> {
+) -> Result<(Vec<MergeConflict>, HashMap<String, ManifestEntry>)> {
     let our_commit = repo.objects().load_commit(ours)?;
     let their_commit = repo.objects().load_commit(theirs)?;
     let our_manifest = repo.objects().load_manifest(&our_commit.manifest)?;
@@ -311,6 +327,7 @@ fn detect_conflicts(
     };
 
     let mut conflicts = Vec::new();
+    let mut resolved = HashMap::new();
 
     // Get all paths from both sides
     let mut all_paths: HashSet<String> = HashSet::new();
@@ -334,10 +351,15 @@ fn detect_conflicts(
                 let same_result = our_entry.content_hash == their_entry.content_hash;
 
                 if our_changed && their_changed && !same_result {
-                    conflicts.push(MergeConflict {
-                        path: path.clone(),
-                        conflict_type: ConflictType::BothModified,
-                    });
+                    match resolve_with_driver(repo, &path, Some(base_entry), our_entry, their_entry)? {
+                        Some(entry) => {
+                            resolved.insert(path.clone(), entry);
+                        }
+                        None => conflicts.push(MergeConflict {
+                            path: path.clone(),
+                            conflict_type: ConflictType::BothModified,
+                        }),
+                    }
                 }
             }
 
@@ -362,10 +384,15 @@ fn detect_conflicts(
             // Both added (no base)
             (None, Some(our_entry), Some(their_entry)) => {
                 if our_entry.content_hash != their_entry.content_hash {
-                    conflicts.push(MergeConflict {
-                        path: path.clone(),
-                        conflict_type: ConflictType::BothAdded,
-                    });
+                    match resolve_with_driver(repo, &path, None, our_entry, their_entry)? {
+                        Some(entry) => {
+                            resolved.insert(path.clone(), entry);
+                        }
+                        None => conflicts.push(MergeConflict {
+                            path: path.clone(),
+                            conflict_type: ConflictType::BothAdded,
+                        }),
+                    }
                 }
             }
 
@@ -374,14 +401,52 @@ fn detect_conflicts(
         }
     }
 
-    Ok(conflicts)
+    Ok((conflicts, resolved))
+}
+
+/// Resolve a file changed on both sides using its `merge` attribute.
+///
+/// Returns the entry to commit, or `None` if the change is a conflict.
+fn resolve_with_driver(
+    repo: &Repository,
+    path: &str,
+    base: Option<&ManifestEntry>,
+    ours: &ManifestEntry,
+    theirs: &ManifestEntry,
+) -> Result<Option<ManifestEntry>> {
+    match repo.attributes(path).merge {
+        Some(MergeDriver::Ours) => Ok(Some(ours.clone())),
+        Some(MergeDriver::Theirs) => Ok(Some(theirs.clone())),
+        Some(MergeDriver::Text) if !ours.is_mp4() && !theirs.is_mp4() => {
+            let base_content = match base {
+                Some(entry) => repo.read_entry(entry)?,
+                None => Vec::new(),
+            };
+            let texts = (
+                String::from_utf8(base_content),
+                String::from_utf8(repo.read_entry(ours)?),
+                String::from_utf8(repo.read_entry(theirs)?),
+            );
+            let (Ok(base_text), Ok(our_text), Ok(their_text)) = texts else {
+                return Ok(None);
+            };
+            match GitTextEngine::merge_text(&base_text, &our_text, &their_text, "ours", "theirs") {
+                TextMergeResult::Clean { content } => Ok(Some(repo.store_entry(ours, &content)?)),
+                TextMergeResult::Conflict { .. } => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
 }
 
 /// Merge two manifests using three-way merge strategy.
+///
+/// Entries in `resolved` replace whatever the three-way rules would pick.
 fn merge_manifests(
     base: Option<&Manifest>,
     ours: &Manifest,
     theirs: &Manifest,
+    resolved: &HashMap<String, ManifestEntry>,
 ) -> Result<Manifest> {
     let mut result = Manifest::new();
 
@@ -443,7 +508,7 @@ fn merge_manifests(
             (None, None, None) => None,
         };
 
-        if let Some(entry) = entry_to_use {
+        if let Some(entry) = resolved.get(&path).or(entry_to_use) {
             result.add(entry.clone());
         }
     }
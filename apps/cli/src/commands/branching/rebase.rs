@@ -1,6 +1,6 @@
 //! Rebase command - reapply commits on top of another base.
 
-use crate::core::{Author, Commit, Index, IndexEntry};
+use crate::core::{Author, Commit, Index, IndexEntry, IndexLock};
 use crate::store::Repository;
 use anyhow::{Context, Result, bail};
 use console::style;
@@ -171,8 +171,8 @@ fn apply_commits(
 
         // Build new index with applied changes
         let index_path = repo.dits_dir().join("index");
-        let index_json = fs::read_to_string(&index_path)?;
-        let mut index = Index::from_json(&index_json)?;
+        let lock = IndexLock::acquire(&index_path)?;
+        let mut index = Index::load(&index_path)?;
 
         let has_conflicts = false;
 
@@ -215,7 +215,7 @@ fn apply_commits(
         }
 
         // Save index
-        fs::write(&index_path, index.to_json())?;
+        lock.commit(&mut index)?;
 
         if has_conflicts {
             // Update state
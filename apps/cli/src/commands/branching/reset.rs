@@ -1,10 +1,9 @@
 //! Reset command implementation.
 
-use crate::core::{FileStatus, Index, IndexEntry};
+use crate::core::{FileStatus, Index, IndexEntry, IndexLock};
 use crate::store::Repository;
 use anyhow::{Context, Result};
 use console::style;
-use std::fs;
 use std::path::Path;
 
 /// Reset mode determines what gets reset.
@@ -37,8 +36,8 @@ pub fn reset(target: Option<&str>, mode: ResetMode, paths: &[String]) -> Result<
 /// Reset specific paths (unstage them).
 fn reset_paths(repo: &Repository, paths: &[String]) -> Result<()> {
     let index_path = repo.dits_dir().join("index");
-    let json = fs::read_to_string(&index_path)?;
-    let mut index = Index::from_json(&json)?;
+    let lock = IndexLock::acquire(&index_path)?;
+    let mut index = Index::load(&index_path)?;
 
     let mut unstaged = 0;
 
@@ -63,7 +62,7 @@ fn reset_paths(repo: &Repository, paths: &[String]) -> Result<()> {
     }
 
     // Save updated index
-    fs::write(&index_path, index.to_json())?;
+    lock.commit(&mut index)?;
 
     if unstaged > 0 {
         println!(
@@ -133,7 +132,7 @@ fn reset_to_commit(repo: &Repository, target: Option<&str>, mode: ResetMode) ->
             }
 
             let index_path = repo.dits_dir().join("index");
-            fs::write(&index_path, new_index.to_json())?;
+            new_index.write_file(&index_path)?;
 
             println!(
                 "{} HEAD is now at {} (index reset)",
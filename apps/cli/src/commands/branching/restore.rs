@@ -1,6 +1,6 @@
 //! Restore command implementation.
 
-use crate::core::{FileStatus, Index, IndexEntry};
+use crate::core::{FileStatus, Index, IndexEntry, IndexLock};
 use crate::store::Repository;
 use anyhow::{Context, Result};
 use console::style;
@@ -84,8 +84,8 @@ fn restore_conflict_version(_repo: &Repository, paths: &[String], use_ours: bool
 /// Restore staged files (unstage them).
 fn restore_staged_files(repo: &Repository, paths: &[String]) -> Result<()> {
     let index_path = repo.dits_dir().join("index");
-    let json = fs::read_to_string(&index_path)?;
-    let mut index = Index::from_json(&json)?;
+    let lock = IndexLock::acquire(&index_path)?;
+    let mut index = Index::load(&index_path)?;
 
     let mut unstaged = 0;
 
@@ -136,7 +136,7 @@ fn restore_staged_files(repo: &Repository, paths: &[String]) -> Result<()> {
     }
 
     // Save updated index
-    fs::write(&index_path, index.to_json())?;
+    lock.commit(&mut index)?;
 
     if unstaged > 0 {
         println!(
@@ -151,7 +151,7 @@ fn restore_staged_files(repo: &Repository, paths: &[String]) -> Result<()> {
 }
 
 /// Restore working tree files from a source commit.
-fn restore_worktree_files(
+pub(crate) fn restore_worktree_files(
     repo: &Repository,
     paths: &[String],
     source_hash: Option<&crate::core::Hash>,
@@ -1,6 +1,6 @@
 //! Stash command implementation.
 
-use crate::core::{chunk_data_with_refs, ChunkerConfig, FileStatus, Hash, Hasher, Index, IndexEntry, Manifest, ManifestEntry};
+use crate::core::{chunk_data_with_refs, ChunkerConfig, FileStatus, Hash, Hasher, Index, IndexEntry, IndexLock, Manifest, ManifestEntry};
 use crate::store::Repository;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
@@ -143,8 +143,7 @@ pub fn stash(
 /// Save current changes to stash.
 fn stash_push(repo: &Repository, stash_path: &Path, message: Option<&str>) -> Result<()> {
     let index_path = repo.dits_dir().join("index");
-    let json = fs::read_to_string(&index_path)?;
-    let index = Index::from_json(&json)?;
+    let index = Index::load(&index_path)?;
 
     // Check if there are any changes to stash
     let has_staged = index.entries.values().any(|e| e.status != FileStatus::Unchanged);
@@ -253,7 +252,7 @@ fn stash_push(repo: &Repository, stash_path: &Path, message: Option<&str>) -> Re
             new_index.stage(idx_entry);
         }
 
-        fs::write(&index_path, new_index.to_json())?;
+        new_index.write_file(&index_path)?;
 
         // Reset working tree files that had changes
         for path in &worktree_changes {
@@ -281,7 +280,7 @@ fn stash_push(repo: &Repository, stash_path: &Path, message: Option<&str>) -> Re
     } else {
         // No HEAD commit - clear the index entirely
         let new_index = Index::new();
-        fs::write(&index_path, new_index.to_json())?;
+        new_index.write_file(&index_path)?;
     }
 
     let msg = message.unwrap_or("WIP on stash");
@@ -338,8 +337,8 @@ fn stash_apply(repo: &Repository, stash_path: &Path, index: usize, remove: bool)
     // Load and apply index manifest changes
     let index_manifest = repo.objects().load_manifest(&entry.index_manifest)?;
     let index_path = repo.dits_dir().join("index");
-    let json = fs::read_to_string(&index_path)?;
-    let mut current_index = Index::from_json(&json)?;
+    let lock = IndexLock::acquire(&index_path)?;
+    let mut current_index = Index::load(&index_path)?;
 
     for (path, manifest_entry) in index_manifest.iter() {
         let idx_entry = IndexEntry::new(
@@ -355,7 +354,7 @@ fn stash_apply(repo: &Repository, stash_path: &Path, index: usize, remove: bool)
         current_index.stage(idx_entry);
     }
 
-    fs::write(&index_path, current_index.to_json())?;
+    lock.commit(&mut current_index)?;
 
     if remove {
         stash_list.save(stash_path)?;
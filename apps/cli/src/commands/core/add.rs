@@ -4,14 +4,24 @@ use crate::store::Repository;
 use crate::util::{format_bytes, safe_percentage};
 use anyhow::{Context, Result};
 use console::style;
+use dits::dependency::{is_project_file, DependencyValidator};
 use indicatif::{ProgressBar, ProgressStyle};
 use std::path::Path;
 
 /// Add files to the staging area.
-pub fn add(files: &[String]) -> Result<()> {
+///
+/// With `with_deps`, the files referenced by project files among `files`
+/// are staged too.
+pub fn add(files: &[String], with_deps: bool) -> Result<()> {
     let repo = Repository::open(Path::new("."))
         .context("Not a Dits repository (or any parent directory)")?;
 
+    let mut files = files.to_vec();
+    if with_deps {
+        files.extend(project_dependencies(&repo, &files)?);
+    }
+    let files = &files;
+
     let progress = ProgressBar::new(files.len() as u64);
     progress.set_style(
         ProgressStyle::default_bar()
@@ -112,3 +122,59 @@ pub fn add(files: &[String]) -> Result<()> {
     Ok(())
 }
 
+
+/// Files inside the repository that the project files in `files`
+/// reference, directly or through nested projects. References that cannot
+/// be staged are reported.
+fn project_dependencies(repo: &Repository, files: &[String]) -> Result<Vec<String>> {
+    let root = std::fs::canonicalize(repo.work_dir())?;
+    let projects: Vec<_> = files
+        .iter()
+        .map(|f| root.join(f))
+        .filter(|p| p.is_file() && is_project_file(p))
+        .collect();
+    if projects.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let closure = DependencyValidator::new(&root)
+        .dependency_closure(&projects)
+        .context("Failed to read project dependencies")?;
+
+    println!(
+        "{} {} project file(s) reference {} file(s) in the repository",
+        style("→").blue(),
+        projects.len(),
+        closure.files.len()
+    );
+    if !closure.missing.is_empty() {
+        println!(
+            "{} {} referenced file(s) do not exist:",
+            style("!").yellow().bold(),
+            closure.missing.len()
+        );
+        for path in &closure.missing {
+            println!("    - {}", style(path).yellow());
+        }
+    }
+    if !closure.external.is_empty() {
+        println!(
+            "{} {} referenced file(s) are outside the repository and were not staged:",
+            style("!").yellow().bold(),
+            closure.external.len()
+        );
+        for path in &closure.external {
+            println!("    - {}", style(path).yellow());
+        }
+        println!(
+            "  Move them into the repository, then run {}",
+            style("dits dep-relink").cyan()
+        );
+    }
+
+    Ok(closure
+        .files
+        .into_iter()
+        .filter(|f| !files.contains(f))
+        .collect())
+}
@@ -1,7 +1,12 @@
 //! Create a commit.
 
+use crate::commands::DISCARD_HINT;
+use crate::config::LockEnforcement;
+use crate::core::{Author, Commit};
+use crate::store::repository::RepoError;
 use crate::store::Repository;
-use anyhow::{Context, Result};
+use crate::vfs::{Overlay, OverlayBase};
+use anyhow::{bail, Context, Result};
 use console::style;
 use std::path::Path;
 
@@ -9,11 +14,29 @@ use std::path::Path;
 pub fn commit(message: &str) -> Result<()> {
     let repo = Repository::open(Path::new("."))
         .context("Not a Dits repository (or any parent directory)")?;
+    commit_repo(&repo, message)
+}
+
+/// Commit the changes made through a writable mount if there are any, or
+/// the staged changes otherwise.
+fn commit_repo(repo: &Repository, message: &str) -> Result<()> {
+    if let Some(overlay) = Overlay::open(repo.dits_dir())? {
+        let (_, base_manifest) = overlay.load_base(repo)?;
+        if !overlay.changes(&base_manifest).is_empty() {
+            return commit_overlay(repo, &overlay, message);
+        }
+    }
 
     // Get the number of staged files before committing
     let index = repo.load_index()?;
     let files_committed = index.len();
 
+    if repo.lock_enforcement() == LockEnforcement::Warn {
+        for violation in repo.lock_violations()? {
+            println!("{} {}", style("warning:").yellow().bold(), violation);
+        }
+    }
+
     match repo.commit(message) {
         Ok(commit) => {
             println!(
@@ -43,3 +66,193 @@ pub fn commit(message: &str) -> Result<()> {
         Err(e) => Err(e.into()),
     }
 }
+
+/// Commit the changes made through a writable mount to its branch.
+///
+/// The overlay's base advances to the new commit, so a live mount keeps
+/// working and only shows edits made after this commit as changes. Locks
+/// are enforced as for staged changes. If the branch is checked out, the
+/// working tree and index follow it for the changed paths, so later commits
+/// from either side build on the mount's changes.
+fn commit_overlay(repo: &Repository, overlay: &Overlay, message: &str) -> Result<()> {
+    let (base, base_manifest) = overlay.load_base(repo)?;
+    let changes = overlay.changes(&base_manifest);
+    if changes.is_empty() {
+        println!("{} Nothing to commit (mount clean)", style("!").yellow().bold());
+        return Ok(());
+    }
+
+    // Staged changes would silently be left out of this commit
+    let staged = repo.staged_paths()?;
+    if !staged.is_empty() {
+        bail!(
+            "Both the mount at {} and the index have changes; unstage the {} staged change(s) \
+             with \"dits restore --staged\", or drop the mount's with \"{}\", and commit again",
+            base.mount_point.display(),
+            staged.len(),
+            DISCARD_HINT
+        );
+    }
+
+    let changed: Vec<String> = changes.added.iter().chain(&changes.modified).chain(&changes.deleted).cloned().collect();
+    let enforcement = repo.lock_enforcement();
+    if enforcement != LockEnforcement::Off {
+        let violations = repo.path_lock_violations(changed.iter().map(String::as_str));
+        if enforcement == LockEnforcement::Require && !violations.is_empty() {
+            return Err(RepoError::LockViolations(violations).into());
+        }
+        for violation in &violations {
+            println!("{} {}", style("warning:").yellow().bold(), violation);
+        }
+    }
+
+    // The checked-out branch's working tree follows the commit below
+    let checked_out = repo.current_branch()?.as_deref() == Some(base.branch.as_str());
+    if checked_out {
+        let unstaged = repo.unstaged_paths(changed.iter().map(String::as_str))?;
+        if !unstaged.is_empty() {
+            bail!(
+                "The working tree has changes to {} that the mount also changed; \
+                 commit or discard them first",
+                unstaged.join(", ")
+            );
+        }
+    }
+
+    if repo.refs().get_branch(&base.branch)? != Some(base.commit) {
+        bail!(
+            "Branch '{}' moved since it was mounted at {}; the mount's changes apply to {}",
+            base.branch,
+            base.mount_point.display(),
+            &base.commit.to_hex()[..8]
+        );
+    }
+
+    let manifest = overlay.apply(&base_manifest);
+    let manifest_hash = repo.objects().store_manifest(&manifest)?;
+    let commit = Commit::new(Some(base.commit), manifest_hash, message, Author::from_env());
+    repo.objects().store_commit(&commit)?;
+    repo.refs().set_branch(&base.branch, &commit.hash)?;
+    overlay.set_base(&OverlayBase { commit: commit.hash, ..base })?;
+    if checked_out {
+        repo.update_paths(&commit.hash, &manifest, &changed)?;
+    }
+
+    println!(
+        "{} [{}] {}",
+        style("✓").green().bold(),
+        style(commit.short_hash()).yellow(),
+        message
+    );
+    println!("  {} file(s) changed in the mount", changes.len());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    /// Change `path` through `overlay` as a writable mount would.
+    fn mount_write(repo: &Repository, overlay: &mut Overlay, path: &str, data: &[u8]) {
+        let (_, view) = overlay.load_base(repo).unwrap();
+        let template = overlay
+            .apply(&view)
+            .get(path)
+            .cloned()
+            .unwrap_or_else(|| crate::core::ManifestEntry::new(path.to_string(), 0, crate::core::Hash::ZERO, Vec::new()));
+        overlay.put(repo.store_entry(&template, data).unwrap());
+        overlay.save().unwrap();
+    }
+
+    #[test]
+    fn test_mount_commits_keep_checked_out_branch_in_sync() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join("edit.txt"), "v1").unwrap();
+        repo.add("edit.txt").unwrap();
+        repo.commit("initial").unwrap();
+
+        let base = OverlayBase {
+            branch: "main".to_string(),
+            commit: repo.head().unwrap().unwrap(),
+            mount_point: temp.path().join("mnt"),
+        };
+        let mut overlay = Overlay::create(repo.dits_dir(), &base).unwrap();
+
+        mount_write(&repo, &mut overlay, "edit.txt", b"v2");
+        commit_overlay(&repo, &overlay, "first from mount").unwrap();
+        mount_write(&repo, &mut overlay, "notes.txt", b"notes");
+        commit_overlay(&repo, &overlay, "second from mount").unwrap();
+
+        let head = repo.head().unwrap().unwrap();
+        assert_eq!(overlay.base().unwrap().commit, head);
+        assert_eq!(fs::read_to_string(temp.path().join("edit.txt")).unwrap(), "v2");
+        assert_eq!(fs::read_to_string(temp.path().join("notes.txt")).unwrap(), "notes");
+        assert!(repo.staged_paths().unwrap().is_empty());
+        assert!(repo.status().unwrap().is_clean());
+
+        // A plain commit afterwards builds on the mount's changes
+        overlay.discard().unwrap();
+        fs::write(temp.path().join("other.txt"), "other").unwrap();
+        repo.add("other.txt").unwrap();
+        let commit = repo.commit("after unmount").unwrap();
+        let manifest = repo.load_manifest(&commit.manifest).unwrap();
+        assert_eq!(repo.read_entry(manifest.get("edit.txt").unwrap()).unwrap(), b"v2");
+        assert!(manifest.contains("notes.txt"));
+    }
+
+    #[test]
+    fn test_mount_commit_refuses_to_overwrite_unstaged_edits() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join("edit.txt"), "v1").unwrap();
+        repo.add("edit.txt").unwrap();
+        repo.commit("initial").unwrap();
+        let head = repo.head().unwrap().unwrap();
+
+        let base = OverlayBase { branch: "main".to_string(), commit: head, mount_point: temp.path().join("mnt") };
+        let mut overlay = Overlay::create(repo.dits_dir(), &base).unwrap();
+        mount_write(&repo, &mut overlay, "edit.txt", b"from mount");
+        fs::write(temp.path().join("edit.txt"), "local edit").unwrap();
+
+        assert!(commit_overlay(&repo, &overlay, "from mount").is_err());
+        assert_eq!(repo.head().unwrap(), Some(head));
+        assert_eq!(fs::read_to_string(temp.path().join("edit.txt")).unwrap(), "local edit");
+    }
+
+    #[test]
+    fn test_clean_mount_leaves_staged_changes_to_a_plain_commit() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join("edit.txt"), "v1").unwrap();
+        repo.add("edit.txt").unwrap();
+        repo.commit("initial").unwrap();
+
+        let base = OverlayBase {
+            branch: "main".to_string(),
+            commit: repo.head().unwrap().unwrap(),
+            mount_point: temp.path().join("mnt"),
+        };
+        let mut overlay = Overlay::create(repo.dits_dir(), &base).unwrap();
+        fs::write(temp.path().join("edit.txt"), "v2").unwrap();
+        repo.add("edit.txt").unwrap();
+
+        commit_repo(&repo, "staged").unwrap();
+        let commit = repo.load_commit(&repo.head().unwrap().unwrap()).unwrap();
+        assert_eq!(commit.message, "staged");
+        let manifest = repo.load_manifest(&commit.manifest).unwrap();
+        assert_eq!(repo.read_entry(manifest.get("edit.txt").unwrap()).unwrap(), b"v2");
+
+        // Once the mount has changes too, neither side is committed
+        let head = commit.hash;
+        overlay.set_base(&OverlayBase { commit: head, ..base }).unwrap();
+        mount_write(&repo, &mut overlay, "notes.txt", b"notes");
+        fs::write(temp.path().join("edit.txt"), "v3").unwrap();
+        repo.add("edit.txt").unwrap();
+        let err = commit_repo(&repo, "both").unwrap_err();
+        assert!(err.to_string().contains("Both the mount"), "{}", err);
+        assert_eq!(repo.head().unwrap(), Some(head));
+    }
+}
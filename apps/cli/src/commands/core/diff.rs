@@ -1,5 +1,6 @@
 //! Diff command implementation.
 
+use crate::core::DiffDriver;
 use crate::store::Repository;
 use crate::util::format_bytes;
 use anyhow::{Context, Result};
@@ -138,7 +139,7 @@ fn show_file_diff(repo: &Repository, path: &str, _source: DiffSource) -> Result<
     }
 
     // Check if binary
-    let is_binary = is_binary_file(&full_path)?;
+    let is_binary = is_binary_file(repo, path, &full_path)?;
 
     if is_binary {
         show_binary_diff(repo, path, &full_path)?;
@@ -199,7 +200,18 @@ fn show_mode_change_diff(_repo: &Repository, path: &str) -> Result<()> {
 }
 
 /// Check if a file is binary.
-fn is_binary_file(path: &Path) -> Result<bool> {
+///
+/// A `diff` or `storage` attribute in .ditsattributes takes precedence over
+/// detection.
+fn is_binary_file(repo: &Repository, rel_path: &str, path: &Path) -> Result<bool> {
+    let attrs = repo.attributes(rel_path);
+    if let Some(driver) = attrs.diff {
+        return Ok(driver == DiffDriver::Binary);
+    }
+    if let Some(strategy) = attrs.storage {
+        return Ok(!strategy.supports_line_diff());
+    }
+
     // Check by extension first
     if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
         let binary_extensions = [
@@ -341,14 +353,7 @@ fn get_original_content(repo: &Repository, path: &str) -> Option<String> {
     let manifest = repo.objects().load_manifest(&commit.manifest).ok()?;
 
     let entry = manifest.entries.get(path)?;
-
-    // Reconstruct content from chunks
-    let mut content = Vec::new();
-    for chunk_ref in &entry.chunks {
-        if let Ok(chunk) = repo.objects().load_chunk(&chunk_ref.hash) {
-            content.extend_from_slice(&chunk.data);
-        }
-    }
+    let content = repo.read_entry(entry).ok()?;
 
     String::from_utf8(content).ok()
 }
@@ -1,22 +1,28 @@
 //! Show commit history.
 
+use crate::commands::advanced::meta::{meta_filter, meta_matches};
+use crate::core::Commit;
 use crate::store::Repository;
 use anyhow::{Context, Result};
 use console::style;
+use dits::metadata::MetaFilter;
 use std::collections::HashSet;
 use std::path::Path;
 
 /// Show commit history.
-pub fn log(limit: usize, oneline: bool, graph: bool, all: bool) -> Result<()> {
+pub fn log(limit: usize, oneline: bool, graph: bool, all: bool, meta: Option<&str>) -> Result<()> {
     let repo = Repository::open(Path::new("."))
         .context("Not a Dits repository (or any parent directory)")?;
 
+    let filter = meta.map(|query| meta_filter(repo.dits_dir(), query)).transpose()?;
+    let filter = filter.as_ref();
+
     if graph {
-        log_with_graph(&repo, limit, oneline, all)?;
+        log_with_graph(&repo, limit, oneline, all, filter)?;
     } else if all {
-        log_all_branches(&repo, limit, oneline)?;
+        log_all_branches(&repo, limit, oneline, filter)?;
     } else {
-        let commits = repo.log(limit)?;
+        let commits = select_commits(&repo, limit, filter, |n| Ok(repo.log(n)?))?;
 
         if commits.is_empty() {
             println!("No commits yet");
@@ -66,6 +72,48 @@ pub fn log(limit: usize, oneline: bool, graph: bool, all: bool) -> Result<()> {
     Ok(())
 }
 
+/// The first `limit` commits, or with a metadata filter, the first `limit`
+/// commits that add or change a matching file.
+fn select_commits(
+    repo: &Repository,
+    limit: usize,
+    filter: Option<&MetaFilter>,
+    collect: impl Fn(usize) -> Result<Vec<Commit>>,
+) -> Result<Vec<Commit>> {
+    let Some(filter) = filter else {
+        return collect(limit);
+    };
+
+    let mut selected = Vec::new();
+    for commit in collect(usize::MAX)? {
+        if selected.len() >= limit {
+            break;
+        }
+        if touches_matching_file(repo, &commit, filter)? {
+            selected.push(commit);
+        }
+    }
+    Ok(selected)
+}
+
+/// Whether a commit adds or changes a file matching the filter.
+fn touches_matching_file(repo: &Repository, commit: &Commit, filter: &MetaFilter) -> Result<bool> {
+    let manifest = repo.load_manifest(&commit.manifest)?;
+    let parent = match commit.parent {
+        Some(parent) => Some(repo.load_manifest(&repo.load_commit(&parent)?.manifest)?),
+        None => None,
+    };
+
+    let touched = manifest.iter().any(|(path, entry)| {
+        let unchanged = parent
+            .as_ref()
+            .and_then(|p| p.get(path))
+            .is_some_and(|old| old.content_hash == entry.content_hash);
+        !unchanged && meta_matches(filter, path, &entry.content_hash)
+    });
+    Ok(touched)
+}
+
 /// Get decorations (branch names, tags) for a commit.
 fn get_decorations(repo: &Repository, hash: &crate::core::Hash) -> Result<Vec<String>> {
     let mut decorations = Vec::new();
@@ -103,12 +151,20 @@ fn get_decorations(repo: &Repository, hash: &crate::core::Hash) -> Result<Vec<St
 }
 
 /// Show log with ASCII graph.
-fn log_with_graph(repo: &Repository, limit: usize, oneline: bool, all: bool) -> Result<()> {
-    let commits = if all {
-        collect_all_commits(repo, limit)?
-    } else {
-        repo.log(limit)?
-    };
+fn log_with_graph(
+    repo: &Repository,
+    limit: usize,
+    oneline: bool,
+    all: bool,
+    filter: Option<&MetaFilter>,
+) -> Result<()> {
+    let commits = select_commits(repo, limit, filter, |n| {
+        if all {
+            collect_all_commits(repo, n)
+        } else {
+            Ok(repo.log(n)?)
+        }
+    })?;
 
     if commits.is_empty() {
         println!("No commits yet");
@@ -155,8 +211,8 @@ fn log_with_graph(repo: &Repository, limit: usize, oneline: bool, all: bool) ->
 }
 
 /// Log all branches.
-fn log_all_branches(repo: &Repository, limit: usize, oneline: bool) -> Result<()> {
-    let commits = collect_all_commits(repo, limit)?;
+fn log_all_branches(repo: &Repository, limit: usize, oneline: bool, filter: Option<&MetaFilter>) -> Result<()> {
+    let commits = select_commits(repo, limit, filter, |n| collect_all_commits(repo, n))?;
 
     if commits.is_empty() {
         println!("No commits yet");
@@ -1,16 +1,21 @@
 //! Show repository status.
 
+use crate::commands::DISCARD_HINT;
 use crate::store::Repository;
+use crate::vfs::Overlay;
 use anyhow::{Context, Result};
 use console::style;
 use std::path::Path;
 
 /// Show repository status.
-pub fn status() -> Result<()> {
+///
+/// With `refresh`, every tracked file is rehashed rather than trusting
+/// cached stat data.
+pub fn status(refresh: bool) -> Result<()> {
     let repo = Repository::open(Path::new("."))
         .context("Not a Dits repository (or any parent directory)")?;
 
-    let status = repo.status()?;
+    let status = repo.status_with_refresh(refresh)?;
 
     // Print branch
     if let Some(branch) = &status.branch {
@@ -21,6 +26,12 @@ pub fn status() -> Result<()> {
 
     println!();
 
+    // A writable mount keeps its changes apart from the working tree
+    let mount_changed = match Overlay::open(repo.dits_dir())? {
+        Some(overlay) => overlay_status(&repo, &overlay)?,
+        None => false,
+    };
+
     // Print staged changes
     if status.has_staged() {
         println!("Changes to be committed:");
@@ -60,6 +71,18 @@ pub fn status() -> Result<()> {
         println!();
     }
 
+    // Print lockable files changed without holding their lock
+    if !status.modified_without_lock.is_empty() {
+        println!("Modified without lock:");
+        println!("  (use \"dits lock <file>...\" before committing)");
+        println!();
+
+        for file in &status.modified_without_lock {
+            println!("        {}: {}", style("not locked").yellow(), file);
+        }
+        println!();
+    }
+
     // Print unstaged renames
     if !status.unstaged_renamed.is_empty() {
         println!("Unstaged renames:");
@@ -84,10 +107,11 @@ pub fn status() -> Result<()> {
         println!();
     }
 
-    // Clean status
-    if status.is_clean() && status.untracked.is_empty() && status.unstaged_renamed.is_empty() {
+    // Clean status; the mount's changes are still there to commit
+    let clean = status.is_clean() && !mount_changed;
+    if clean && status.untracked.is_empty() && status.unstaged_renamed.is_empty() {
         println!("nothing to commit, working tree clean");
-    } else if status.is_clean() && (!status.untracked.is_empty() || !status.unstaged_renamed.is_empty()) {
+    } else if clean && (!status.untracked.is_empty() || !status.unstaged_renamed.is_empty()) {
         println!(
             "nothing added to commit but untracked files present (use \"dits add\" to track)"
         );
@@ -95,3 +119,34 @@ pub fn status() -> Result<()> {
 
     Ok(())
 }
+
+/// Show the changes made through a writable mount, returning whether
+/// there are any.
+fn overlay_status(repo: &Repository, overlay: &Overlay) -> Result<bool> {
+    let (base, manifest) = overlay.load_base(repo)?;
+    let changes = overlay.changes(&manifest);
+    if changes.is_empty() {
+        return Ok(false);
+    }
+
+    println!(
+        "Changes in the writable mount of {} at {}:",
+        style(&base.branch).cyan().bold(),
+        base.mount_point.display()
+    );
+    println!("  (use \"dits commit -m <message>\" to commit them)");
+    println!("  (use \"{}\" to discard them)", DISCARD_HINT);
+    println!();
+    for file in &changes.added {
+        println!("        {}: {}", style("new file").green(), file);
+    }
+    for file in &changes.modified {
+        println!("        {}: {}", style("modified").green(), file);
+    }
+    for file in &changes.deleted {
+        println!("        {}: {}", style("deleted").green(), file);
+    }
+    println!();
+
+    Ok(true)
+}
@@ -5,6 +5,7 @@ use std::fs::{self, File};
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 
+use crate::commands::advanced::meta::{meta_filter, meta_matches};
 use crate::store::Repository;
 
 /// Archive format
@@ -46,6 +47,8 @@ pub struct ArchiveOptions {
     pub output: Option<PathBuf>,
     /// Specific paths to include
     pub paths: Vec<String>,
+    /// Metadata query files must match
+    pub meta: Option<String>,
 }
 
 /// Create an archive from repository content
@@ -54,11 +57,16 @@ pub fn archive(options: &ArchiveOptions) -> Result<PathBuf> {
         .context("Not in a dits repository")?;
     
     // Resolve the tree-ish to a commit
-    let commit_hash = repo.resolve_ref(&options.tree_ish)?
+    let commit_hash = repo.resolve_ref_or_prefix(&options.tree_ish)?
         .with_context(|| format!("Cannot resolve '{}' to a commit", options.tree_ish))?;
     
     let commit = repo.load_commit(&commit_hash)?;
-    let manifest = repo.load_manifest(&commit.manifest)?;
+    let mut manifest = repo.load_manifest(&commit.manifest)?;
+
+    if let Some(query) = &options.meta {
+        let filter = meta_filter(repo.dits_dir(), query)?;
+        manifest.entries.retain(|path, entry| meta_matches(&filter, path, &entry.content_hash));
+    }
     
     // Determine output path
     let output_path = match &options.output {
@@ -4,9 +4,11 @@ pub mod archive;
 pub mod clean;
 #[cfg(feature = "fuser")]
 pub mod mount;
+mod overlay;
 #[cfg(feature = "fuser")]
 pub mod unmount;
 pub mod sparse_checkout;
+pub mod webdav;
 pub mod worktree;
 
 #[allow(unused_imports)]
@@ -24,6 +26,8 @@ pub use {
         unlock as worktree_unlock,
     },
 };
+pub use overlay::{discard_overlay, DISCARD_HINT};
+pub use webdav::serve_webdav;
 #[cfg(feature = "fuser")]
 pub use mount::mount;
 #[cfg(feature = "fuser")]
@@ -1,15 +1,65 @@
 //! Mount command - mount repository as a virtual filesystem.
 
+use super::overlay::{close_overlay, open_overlay};
+use crate::commands::branching::checkout::{find_proxy, PROXY_PREFERENCE, VIDEO_EXTENSIONS};
+use crate::core::Manifest;
+use crate::store::reachability::ObjectId;
+use crate::store::remote::{RemoteStore, RemoteType};
+use crate::store::remote_client::token_for;
 use crate::store::Repository;
-use crate::vfs::{CacheConfig, mount as fuse_mount};
+use crate::vfs::{
+    mount as fuse_mount, mount_namespace as fuse_mount_namespace, mount_proxied as fuse_mount_proxied,
+    mount_writable as fuse_mount_writable, CacheConfig, HttpSource, ObjectSource, ProxyFile, ProxyView,
+};
 use anyhow::{bail, Result};
+use dits::p2p::net::QuicConnection;
+use dits::p2p::sync::{self, PeerAddress, SyncError};
+use dits::proxy::{ProxyStore, VariantType};
+use futures_util::future::BoxFuture;
 use std::path::Path;
 use std::sync::Arc;
 
+/// Chunks fetched ahead of each read in a lazy mount.
+const LAZY_READ_AHEAD: usize = 16;
+
 /// Mount a repository commit as a FUSE filesystem.
-pub fn mount(mount_point: &str, commit: Option<&str>, cache_mb: u64) -> Result<()> {
+///
+/// With `writable`, `commit` names the branch to edit (default: the current
+/// branch) and changes are kept in the repository's mount overlay. With
+/// `all_refs`, every branch, tag and commit is mounted side by side. With a
+/// `proxy` resolution, videos are served from their proxies where they have
+/// one. With a `lazy_remote`, chunks missing locally are downloaded from that
+/// remote.
+pub fn mount(
+    mount_point: &str,
+    commit: Option<&str>,
+    cache_mb: u64,
+    writable: bool,
+    all_refs: bool,
+    proxy: Option<&str>,
+    lazy_remote: Option<&str>,
+) -> Result<()> {
     let repo = Repository::open(Path::new("."))?;
 
+    // Configure cache
+    let mut cache_config = CacheConfig {
+        l1_max_bytes: cache_mb * 1024 * 1024,
+        l2_path: repo.dits_dir().join("cache"),
+        ..Default::default()
+    };
+    if let Some(remote_name) = lazy_remote {
+        cache_config.remote = Some(object_source(&repo, remote_name)?);
+        cache_config.prefetch_count = LAZY_READ_AHEAD;
+        println!("Fetching missing content from {}", remote_name);
+    }
+
+    if writable {
+        return mount_branch(repo, mount_point, commit, cache_config);
+    }
+    if all_refs {
+        return mount_refs(repo, mount_point, cache_config);
+    }
+
     // Resolve commit
     let commit_hash = match commit {
         Some(ref_str) => {
@@ -28,19 +78,185 @@ pub fn mount(mount_point: &str, commit: Option<&str>, cache_mb: u64) -> Result<(
 
     println!("Mounting commit {} ({} files)", &commit_hash.to_hex()[..8], manifest.len());
 
-    // Configure cache
-    let cache_config = CacheConfig {
-        l1_max_bytes: cache_mb * 1024 * 1024,
-        l2_path: repo.dits_dir().join("cache"),
-        ..Default::default()
+    let proxies = match proxy {
+        Some(resolution) => {
+            let view = proxy_view(&repo, &manifest, resolution)?;
+            println!(
+                "  {} video(s) served as proxies, {} as originals",
+                view.proxy_count(),
+                view.original_count()
+            );
+            Some(view)
+        }
+        None => None,
     };
 
     // Mount (blocks until unmounted)
     let mount_path = Path::new(mount_point);
     let object_store = Arc::new(repo.into_object_store());
+    if let Some(proxies) = proxies {
+        tokio::task::block_in_place(|| {
+            fuse_mount_proxied(&manifest, object_store, mount_path, cache_config, &proxies)
+        })?;
+        println!("Unmounted.");
+        return Ok(());
+    }
 
-    fuse_mount(&manifest, object_store, mount_path, cache_config)?;
+    // The chunk cache runs its own runtime, so serve outside the command's
+    tokio::task::block_in_place(|| fuse_mount(&manifest, object_store, mount_path, cache_config))?;
 
     println!("Unmounted.");
     Ok(())
 }
+
+/// Choose what to serve for each video in `manifest`.
+///
+/// `resolution` is one of 1080, 720, 540, half or quarter, or "any" for the
+/// best proxy each video has. Videos without such a proxy are served as
+/// originals.
+fn proxy_view(repo: &Repository, manifest: &Manifest, resolution: &str) -> Result<ProxyView> {
+    let variant_types = match resolution {
+        "any" => PROXY_PREFERENCE.to_vec(),
+        "1080" | "1080p" => vec![VariantType::Proxy1080p],
+        "720" | "720p" => vec![VariantType::Proxy720p],
+        "540" | "540p" => vec![VariantType::Proxy540p],
+        "half" => vec![VariantType::ProxyHalf],
+        "quarter" => vec![VariantType::ProxyQuarter],
+        _ => bail!("Unknown proxy resolution: {}. Use: 1080, 720, 540, half, quarter", resolution),
+    };
+
+    let proxy_store = ProxyStore::new(repo.dits_dir());
+    let mut view = ProxyView::new(resolution);
+    for (path, entry) in manifest.iter() {
+        let path_lower = path.to_lowercase();
+        if !VIDEO_EXTENSIONS.iter().any(|ext| path_lower.ends_with(ext)) {
+            continue;
+        }
+
+        // A variant whose data was deleted is no proxy at all
+        let proxy = find_proxy(&proxy_store, &entry.content_hash, &variant_types).and_then(|variant| {
+            let data = proxy_store.data_path(&variant.content_hash);
+            let size = std::fs::metadata(&data).ok()?.len();
+            Some(ProxyFile { data, size, variant: variant.variant_type.to_string() })
+        });
+        match proxy {
+            Some(proxy) => view.serve_proxy(path, entry.size, proxy),
+            None => view.serve_original(path),
+        }
+    }
+    Ok(view)
+}
+
+/// Mount every branch, tag and commit under `branches/`, `tags/` and `commits/`.
+fn mount_refs(repo: Repository, mount_point: &str, cache_config: CacheConfig) -> Result<()> {
+    let branches = repo.refs().list_branches()?;
+    let tags = repo.refs().list_tags()?;
+    println!("Mounting {} branch(es) and {} tag(s)", branches.len(), tags.len());
+
+    let root = repo.root().to_path_buf();
+    let object_store = Arc::new(Repository::open(&root)?.into_object_store());
+    let mount_path = Path::new(mount_point);
+    tokio::task::block_in_place(|| fuse_mount_namespace(repo, object_store, mount_path, cache_config))?;
+
+    println!("Unmounted.");
+    Ok(())
+}
+
+/// Mount a branch writable, resuming the overlay of an earlier mount of it.
+fn mount_branch(repo: Repository, mount_point: &str, branch: Option<&str>, cache_config: CacheConfig) -> Result<()> {
+    let mount_path = Path::new(mount_point);
+    std::fs::create_dir_all(mount_path)?;
+    let (overlay, manifest) = open_overlay(&repo, branch, mount_path.canonicalize()?)?;
+    let base = overlay.base()?;
+
+    println!(
+        "Mounting branch {} at {} ({} files, writable)",
+        base.branch,
+        &base.commit.to_hex()[..8],
+        manifest.len()
+    );
+
+    let root = repo.root().to_path_buf();
+    let object_store = Arc::new(Repository::open(&root)?.into_object_store());
+    tokio::task::block_in_place(|| {
+        fuse_mount_writable(repo, object_store, overlay, &manifest, mount_path, cache_config)
+    })?;
+
+    close_overlay(&root)?;
+    println!("Unmounted.");
+    Ok(())
+}
+
+/// Where a lazy mount downloads missing objects from.
+fn object_source(repo: &Repository, remote_name: &str) -> Result<Arc<dyn ObjectSource>> {
+    let remotes = RemoteStore::new(repo.dits_dir());
+    let Some(remote) = remotes.get(remote_name) else {
+        bail!("Remote '{}' not found; --lazy downloads content from a configured remote", remote_name);
+    };
+    match RemoteType::parse(&remote.url) {
+        RemoteType::Http(url) => Ok(Arc::new(HttpSource::new(&url, token_for(repo.dits_dir(), remote_name)))),
+        RemoteType::Dits(url) => Ok(Arc::new(PeerSource::new(PeerAddress::parse(&url)?))),
+        _ => bail!("Lazy mounts download from HTTP or P2P remotes, not {}", remote.url),
+    }
+}
+
+/// Objects served by a peer sharing its repository with `dits p2p share`.
+///
+/// The connection is opened on the first download and reopened after a
+/// failure, so a peer that comes back is picked up without remounting.
+struct PeerSource {
+    address: PeerAddress,
+    connection: tokio::sync::Mutex<Option<(quinn::Endpoint, QuicConnection)>>,
+}
+
+impl PeerSource {
+    fn new(address: PeerAddress) -> Self {
+        Self {
+            address,
+            connection: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// The open connection, connecting first if there is none.
+    async fn connection(&self) -> Result<QuicConnection, SyncError> {
+        let mut connection = self.connection.lock().await;
+        if let Some((_, quic)) = connection.as_ref() {
+            return Ok(quic.clone());
+        }
+        let (endpoint, quic) = self.address.connect().await?;
+        *connection = Some((endpoint, quic.clone()));
+        Ok(quic)
+    }
+}
+
+impl std::fmt::Debug for PeerSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PeerSource").field("address", &self.address).finish()
+    }
+}
+
+impl ObjectSource for PeerSource {
+    fn location(&self) -> String {
+        self.address.to_string()
+    }
+
+    fn download<'a>(&'a self, id: &'a ObjectId) -> BoxFuture<'a, Result<Option<Vec<u8>>, String>> {
+        Box::pin(async move {
+            let quic = self.connection().await.map_err(|e| e.to_string())?;
+
+            // P2P sync lives in the library, which has its own object ids
+            let kind = dits::store::reachability::ObjectKind::parse(id.kind.as_str())
+                .ok_or_else(|| format!("unknown object kind {}", id.kind.as_str()))?;
+            let wanted = dits::store::reachability::ObjectId { kind, id: id.id.clone() };
+            match sync::get_objects(&quic, &[wanted]).await {
+                Ok(mut objects) => Ok(objects.pop().map(|(_, data)| data)),
+                // The peer answered, it just does not have the object
+                Err(SyncError::Peer { .. }) => Ok(None),
+                Err(e) => {
+                    *self.connection.lock().await = None;
+                    Err(e.to_string())
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,93 @@
+//! Staging overlays shared by writable mounts and WebDAV servers.
+
+use crate::core::Manifest;
+use crate::store::Repository;
+use crate::vfs::{Overlay, OverlayBase};
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+/// How to throw away the uncommitted changes of a mount.
+#[cfg(feature = "fuser")]
+pub const DISCARD_HINT: &str = "dits mount --discard";
+#[cfg(not(feature = "fuser"))]
+pub const DISCARD_HINT: &str = "dits serve-webdav --discard";
+
+/// Open the overlay for editing `branch` (default: the current branch),
+/// resuming the uncommitted changes of an earlier session on it.
+///
+/// `location` is where the branch is served, e.g. a mount point or URL.
+/// Returns the overlay with the manifest of the branch's head.
+pub fn open_overlay(repo: &Repository, branch: Option<&str>, location: PathBuf) -> Result<(Overlay, Manifest)> {
+    let branch = match branch {
+        Some(branch) => branch.to_string(),
+        None => match repo.current_branch()? {
+            Some(branch) => branch,
+            None => bail!("HEAD is detached; use --commit <branch> to choose the branch to edit"),
+        },
+    };
+    let Some(head) = repo.refs().get_branch(&branch)? else {
+        bail!("Only branches can be edited, and '{}' is not one", branch);
+    };
+
+    // Uncommitted changes can only be resumed on top of the commit they were made against
+    if let Some(existing) = Overlay::open(repo.dits_dir())? {
+        let (old_base, old_manifest) = existing.load_base(repo)?;
+        let changes = existing.changes(&old_manifest);
+        if changes.is_empty() {
+            existing.discard()?;
+        } else if old_base.branch != branch || old_base.commit != head {
+            bail!(
+                "{} uncommitted change(s) from editing '{}' at {}; run \"dits commit\" or \"{}\" first",
+                changes.len(),
+                old_base.branch,
+                old_base.mount_point.display(),
+                DISCARD_HINT
+            );
+        } else {
+            println!("Resuming {} uncommitted change(s)", changes.len());
+        }
+    }
+
+    let base = OverlayBase {
+        branch,
+        commit: head,
+        mount_point: location,
+    };
+    let overlay = Overlay::create(repo.dits_dir(), &base)?;
+    let manifest = repo.load_manifest(&repo.load_commit(&head)?.manifest)?;
+    Ok((overlay, manifest))
+}
+
+/// Throw away the uncommitted changes made through a writable mount or
+/// WebDAV server, e.g. after the branch moved on without them.
+pub fn discard_overlay() -> Result<()> {
+    let repo = Repository::open(Path::new("."))?;
+    let Some(overlay) = Overlay::open(repo.dits_dir())? else {
+        println!("No mount changes to discard");
+        return Ok(());
+    };
+    let base = overlay.base()?;
+    overlay.discard()?;
+    println!(
+        "Discarded the uncommitted changes to '{}' made at {}",
+        base.branch,
+        base.mount_point.display()
+    );
+    Ok(())
+}
+
+/// Keep the overlay of the repository at `root` for `dits status` and
+/// `dits commit` if it has changes, or discard it.
+pub fn close_overlay(root: &Path) -> Result<()> {
+    let repo = Repository::open(root)?;
+    if let Some(overlay) = Overlay::open(repo.dits_dir())? {
+        let (_, base_manifest) = overlay.load_base(&repo)?;
+        let changes = overlay.changes(&base_manifest);
+        if changes.is_empty() {
+            overlay.discard()?;
+        } else {
+            println!("{} uncommitted change(s) kept; run \"dits commit\" to commit them", changes.len());
+        }
+    }
+    Ok(())
+}
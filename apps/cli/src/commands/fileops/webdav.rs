@@ -0,0 +1,69 @@
+//! Serve-webdav command - serve a repository over WebDAV, without FUSE.
+
+use super::overlay::{close_overlay, open_overlay};
+use crate::store::Repository;
+use crate::vfs::{CacheConfig, WebDav};
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Serve a commit over WebDAV on `bind`:`port` until interrupted.
+///
+/// With `writable`, `commit` names the branch to edit (default: the current
+/// branch) and uploads are kept in the repository's mount overlay, as with
+/// `dits mount --writable`.
+pub async fn serve_webdav(commit: Option<&str>, bind: &str, port: u16, cache_mb: u64, writable: bool) -> Result<()> {
+    let repo = Repository::open(Path::new("."))?;
+    let root = repo.root().to_path_buf();
+    let cache_config = CacheConfig {
+        l1_max_bytes: cache_mb * 1024 * 1024,
+        l2_path: repo.dits_dir().join("cache"),
+        ..Default::default()
+    };
+
+    let listener = tokio::net::TcpListener::bind((bind, port))
+        .await
+        .with_context(|| format!("Cannot listen on {}:{}", bind, port))?;
+    let url = format!("http://{}/", listener.local_addr()?);
+
+    let object_store = Arc::new(Repository::open(&root)?.into_object_store());
+    let dav = if writable {
+        let (overlay, manifest) = open_overlay(&repo, commit, url.clone().into())?;
+        let base = overlay.base()?;
+        println!(
+            "Serving branch {} at {} ({} files, writable)",
+            base.branch,
+            &base.commit.to_hex()[..8],
+            manifest.len()
+        );
+        // The chunk cache blocks on its own runtime, so build it off this one
+        tokio::task::spawn_blocking(move || WebDav::writable(repo, object_store, overlay, &manifest, cache_config))
+            .await??
+    } else {
+        let commit_hash = match commit {
+            Some(ref_str) => repo
+                .resolve_ref(ref_str)?
+                .ok_or_else(|| anyhow::anyhow!("Cannot resolve: {}", ref_str))?,
+            None => repo
+                .head()?
+                .ok_or_else(|| anyhow::anyhow!("No HEAD commit. Create a commit first."))?,
+        };
+        let manifest = repo.load_manifest(&repo.load_commit(&commit_hash)?.manifest)?;
+        println!("Serving commit {} ({} files)", &commit_hash.to_hex()[..8], manifest.len());
+        tokio::task::spawn_blocking(move || WebDav::new(&manifest, object_store, cache_config)).await??
+    };
+
+    println!("WebDAV server listening on {}", url);
+    println!("Press Ctrl-C to stop.");
+    axum::serve(listener, Arc::new(dav).router())
+        .with_graceful_shutdown(async {
+            let _ = tokio::signal::ctrl_c().await;
+        })
+        .await?;
+
+    if writable {
+        close_overlay(&root)?;
+    }
+    println!("Stopped.");
+    Ok(())
+}
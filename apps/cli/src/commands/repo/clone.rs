@@ -1,31 +1,128 @@
 //! Clone command - clone a repository from a source.
 
 use anyhow::{Context, Result, bail};
+use crate::core::Hash;
 use crate::store::{Repository, remote::{Remote, RemoteStore, RemoteType}};
+use super::fetch::{fetch_http, fetch_p2p};
+use dits::p2p::sync::PeerAddress;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-/// Clone a repository.
+/// Clone a repository from a local path, an HTTP remote, or a peer sharing
+/// it with `dits p2p share` (`dits://` URLs).
 ///
-/// Currently supports local clones. Network clones will be added in future.
-pub fn clone(source: &str, dest: Option<&str>, branch: Option<&str>) -> Result<()> {
+/// A `lazy` clone downloads history but no file content and leaves the
+/// working tree empty; its files are read through `dits mount --lazy`.
+pub async fn clone(source: &str, dest: Option<&str>, branch: Option<&str>, lazy: bool) -> Result<()> {
     let source_type = RemoteType::parse(source);
 
     match source_type {
+        RemoteType::Local(_) if lazy => {
+            bail!("Lazy clones need an HTTP remote; a local clone already has all content at hand")
+        }
         RemoteType::Local(source_path) => {
             clone_local(&source_path, dest, branch)
         }
-        RemoteType::Http(url) | RemoteType::Dits(url) | RemoteType::Ssh(url) => {
-            bail!(
-                "Network cloning not yet implemented.\n\
-                 URL: {}\n\n\
-                 For now, use local paths or copy the repository manually.",
-                url
-            )
+        RemoteType::Dits(_) if lazy => {
+            bail!("Lazy clones need an HTTP remote; peers always send file content")
+        }
+        RemoteType::Http(url) | RemoteType::Dits(url) => {
+            clone_remote(&url, dest, branch, lazy).await
+        }
+        RemoteType::Ssh(url) => {
+            bail!("Cloning over SSH is not supported: {}; use an http(s):// or dits:// URL", url)
         }
     }
 }
 
+/// Clone from an HTTP remote or a P2P peer by fetching into a fresh repository.
+async fn clone_remote(url: &str, dest: Option<&str>, branch: Option<&str>, lazy: bool) -> Result<()> {
+    let p2p = matches!(RemoteType::parse(url), RemoteType::Dits(_));
+
+    // Default destination is the peer's host name, or the last path segment of the URL
+    let dest_path = if let Some(d) = dest {
+        PathBuf::from(d)
+    } else if p2p {
+        PathBuf::from(PeerAddress::parse(url)?.host)
+    } else {
+        url.trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty() && !name.contains(':'))
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow::anyhow!("Cannot determine destination name from URL"))?
+    };
+
+    if dest_path.exists() {
+        bail!("Destination already exists: {}", dest_path.display());
+    }
+
+    println!("Cloning from {} into {}...", url, dest_path.display());
+
+    fs::create_dir_all(&dest_path)?;
+    let repo = Repository::init(&dest_path)
+        .context("Failed to initialize destination repository")?;
+
+    let mut remotes = RemoteStore::new(repo.dits_dir());
+    remotes.add(Remote::new("origin", url))?;
+
+    if p2p {
+        fetch_p2p(&repo, "origin", url, false).await?;
+    } else {
+        fetch_http(&repo, "origin", url, false, lazy).await?;
+    }
+
+    // Pick the requested branch, else main, else the first branch the remote has
+    let tracking_dir = repo.dits_dir().join("refs").join("remotes").join("origin");
+    let target_branch = match branch {
+        Some(b) => Some(b.to_string()),
+        None if tracking_dir.join("main").exists() => Some("main".to_string()),
+        None => fs::read_dir(&tracking_dir)
+            .ok()
+            .and_then(|mut entries| entries.find_map(|e| e.ok()))
+            .map(|e| e.file_name().to_string_lossy().to_string()),
+    };
+
+    let Some(target_branch) = target_branch else {
+        println!("Cloned into '{}' (empty repository)", dest_path.display());
+        return Ok(());
+    };
+
+    let tracking_ref = tracking_dir.join(&target_branch);
+    if !tracking_ref.exists() {
+        bail!("Remote branch '{}' not found", target_branch);
+    }
+    let commit = Hash::from_hex(fs::read_to_string(&tracking_ref)?.trim())?;
+    repo.refs().set_branch(&target_branch, &commit)?;
+    repo.refs().set_head_branch(&target_branch)?;
+
+    if lazy {
+        println!(
+            "Cloned '{}' into '{}' without file content; read it with \"dits mount --lazy\"",
+            target_branch,
+            dest_path.display()
+        );
+        return Ok(());
+    }
+
+    println!("Checking out branch '{}'...", target_branch);
+    match repo.checkout_branch(&target_branch) {
+        Ok(result) => {
+            println!(
+                "Cloned into '{}': {} files",
+                dest_path.display(),
+                result.files_restored
+            );
+        }
+        Err(e) => {
+            println!("Warning: Could not checkout files: {}", e);
+            println!("Repository cloned, but working tree is empty.");
+        }
+    }
+
+    Ok(())
+}
+
 /// Clone a local repository.
 fn clone_local(source: &Path, dest: Option<&str>, branch: Option<&str>) -> Result<()> {
     // Resolve source path
@@ -159,9 +256,18 @@ mod tests {
     use super::*;
     use tempfile::tempdir;
 
-    #[test]
-    fn test_clone_nonexistent_source() {
-        let result = clone("/nonexistent/path", Some("/tmp/dest"), None);
+    #[tokio::test]
+    async fn test_clone_nonexistent_source() {
+        let result = clone("/nonexistent/path", Some("/tmp/dest"), None, false).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_clone_over_ssh_is_refused() {
+        let temp = tempdir().unwrap();
+        let dest = temp.path().join("repo");
+        let err = clone("git@example.com:team/repo", dest.to_str(), None, false).await.unwrap_err();
+        assert!(err.to_string().contains("not supported"));
+        assert!(!dest.exists());
+    }
 }
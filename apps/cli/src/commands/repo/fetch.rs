@@ -2,6 +2,10 @@
 
 use anyhow::{Result, bail};
 use crate::store::remote::{RemoteStore, RemoteType};
+use crate::core::Hash;
+use crate::store::remote_client::{token_for, FetchOutcome, HttpRemote};
+use crate::store::{is_valid_ref_name, Repository};
+use dits::p2p::sync::{self, PeerAddress};
 use std::fs;
 use std::path::Path;
 
@@ -50,34 +54,111 @@ pub async fn fetch(
 async fn fetch_network(remote_name: &str, url: &str, prune: bool) -> Result<()> {
     println!("Fetching from {} ({}) ...", remote_name, url);
 
-    // For now, implement a basic HTTP-based fetch
+    let repo = Repository::open(Path::new("."))
+        .map_err(|_| anyhow::anyhow!("Not in a dits repository"))?;
+
     // TODO: Implement full QUIC protocol for efficiency
-    fetch_http(remote_name, url, prune).await
+    fetch_http(&repo, remote_name, url, prune, false).await
 }
 
-/// Fetch from an HTTP remote.
-async fn fetch_http(_remote_name: &str, url: &str, _prune: bool) -> Result<()> {
-    use reqwest::Client;
+/// Fetch from an HTTP remote into `repo`.
+///
+/// Branches are written as remote-tracking refs under `refs/remotes/<name>/`,
+/// new tags are created locally. A `lazy` fetch skips chunks and MP4 blobs.
+pub async fn fetch_http(repo: &Repository, remote_name: &str, url: &str, prune: bool, lazy: bool) -> Result<()> {
+    let mut remote = HttpRemote::new(url).with_token(token_for(repo.dits_dir(), remote_name));
+    if lazy {
+        remote = remote.without_content();
+    }
+    let outcome = remote.fetch(repo).await?;
+    update_remote_refs(repo, remote_name, &outcome, prune)
+}
+
+/// Fetch from a peer sharing its repository with `dits p2p share`.
+///
+/// Objects travel over QUIC; refs are written exactly as for an HTTP fetch.
+pub async fn fetch_p2p(repo: &Repository, remote_name: &str, url: &str, prune: bool) -> Result<()> {
+    let peer = PeerAddress::parse(url)?;
+    let (endpoint, connection) = peer.connect().await?;
+
+    // P2P sync lives in the library, which has its own repository type
+    let shared = dits::store::Repository::open(repo.root())?;
+    let result = sync::fetch(&connection, &shared).await;
+    connection.close(0, "fetch complete");
+    endpoint.wait_idle().await;
+    let fetched = result?;
+
+    let outcome = FetchOutcome {
+        refs: fetched
+            .refs
+            .iter()
+            .map(|(name, hash)| Ok((name.clone(), Hash::from_hex(&hash.to_hex())?)))
+            .collect::<Result<_>>()?,
+        objects_received: fetched.objects_received,
+        bytes_received: fetched.bytes_received,
+    };
+    update_remote_refs(repo, remote_name, &outcome, prune)
+}
 
-    let client = Client::new();
-    let base_url = url.trim_end_matches('/');
+/// Record fetched refs: branches as remote-tracking refs under
+/// `refs/remotes/<name>/`, tags only if they do not exist locally.
+///
+/// Ref names come from the remote and become file paths, so nothing is
+/// written if any of them could escape the refs directory.
+fn update_remote_refs(repo: &Repository, remote_name: &str, outcome: &FetchOutcome, prune: bool) -> Result<()> {
+    for name in outcome.refs.keys() {
+        let short = name.strip_prefix("refs/heads/").or_else(|| name.strip_prefix("refs/tags/"));
+        if short.is_some_and(|short| !is_valid_ref_name(short)) {
+            bail!("Remote '{}' sent an invalid ref name: {:?}", remote_name, name);
+        }
+    }
+
+    let local_remote_refs = repo.dits_dir().join("refs").join("remotes").join(remote_name);
+    let mut fetched_branches = 0;
 
-    // Get remote refs
-    let refs_url = format!("{}/refs", base_url);
-    println!("Fetching refs from {}", refs_url);
+    for (name, hash) in &outcome.refs {
+        if let Some(branch_name) = name.strip_prefix("refs/heads/") {
+            let local_ref = local_remote_refs.join(branch_name);
+            let current = fs::read_to_string(&local_ref).ok();
 
-    let refs_response = client.get(&refs_url).send().await?;
-    if !refs_response.status().is_success() {
-        bail!("Failed to fetch refs: HTTP {}", refs_response.status());
+            if current.as_deref().map(str::trim) != Some(hash.to_hex().as_str()) {
+                if let Some(parent) = local_ref.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&local_ref, format!("{}\n", hash.to_hex()))?;
+                fetched_branches += 1;
+                println!("  {} -> {}/{}", hash.short(), remote_name, branch_name);
+            }
+        } else if let Some(tag_name) = name.strip_prefix("refs/tags/") {
+            if repo.refs().get_tag(tag_name)?.is_none() {
+                repo.refs().set_tag(tag_name, hash)?;
+                println!("  * [new tag] {}", tag_name);
+            }
+        }
     }
 
-    let _refs_data: serde_json::Value = refs_response.json().await?;
-    println!("✓ Connected to remote server at {}", url);
+    // Prune stale remote-tracking refs
+    if prune && local_remote_refs.exists() {
+        for entry in fs::read_dir(&local_remote_refs)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                let branch_name = entry.file_name().to_string_lossy().to_string();
+                if !outcome.refs.contains_key(&format!("refs/heads/{}", branch_name)) {
+                    fs::remove_file(entry.path())?;
+                    println!("  - [deleted] {}/{}", remote_name, branch_name);
+                }
+            }
+        }
+    }
 
-    // For now, this is a basic implementation
-    // TODO: Implement full ref synchronization, object fetching, etc.
-    println!("Remote refs received (basic implementation)");
-    println!("Full remote sync will be implemented in Phase 4b");
+    if fetched_branches > 0 || outcome.objects_received > 0 {
+        println!(
+            "Fetched {} ref(s), {} object(s) ({} bytes) from {}",
+            fetched_branches, outcome.objects_received, outcome.bytes_received, remote_name
+        );
+    } else {
+        println!("Already up to date.");
+    }
 
     Ok(())
 }
@@ -90,9 +171,18 @@ pub async fn fetch_from_remote(remote_name: &str, url: &str, prune: bool) -> Res
         RemoteType::Local(remote_path) => {
             fetch_local(remote_name, &remote_path, prune).await
         }
-        RemoteType::Http(url) | RemoteType::Dits(url) | RemoteType::Ssh(url) => {
+        RemoteType::Dits(url) => {
+            println!("Fetching from {} ({}) over P2P ...", remote_name, url);
+            let repo = Repository::open(Path::new("."))
+                .map_err(|_| anyhow::anyhow!("Not in a dits repository"))?;
+            fetch_p2p(&repo, remote_name, &url, prune).await
+        }
+        RemoteType::Http(url) => {
             fetch_network(remote_name, &url, prune).await
         }
+        RemoteType::Ssh(url) => {
+            bail!("Fetching over SSH is not supported: {}; use an http(s):// or dits:// URL", url)
+        }
     }
 }
 
@@ -214,8 +304,8 @@ fn copy_missing_objects(remote_objects: &Path, local_objects: &Path) -> Result<u
 
     let mut count = 0;
 
-    // Handle the dits objects directory structure: blobs/, chunks/, commits/, manifests/
-    for category in &["blobs", "chunks", "commits", "manifests"] {
+    // Handle the dits objects directory structure: blobs/, chunks/, pack/, commits/, manifests/
+    for category in &["blobs", "chunks", "pack", "commits", "manifests"] {
         let remote_category = remote_objects.join(category);
         let local_category = local_objects.join(category);
 
@@ -264,4 +354,32 @@ fn copy_missing_objects(remote_objects: &Path, local_objects: &Path) -> Result<u
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BTreeMap;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_malicious_ref_names_are_rejected() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(&temp.path().join("repo")).unwrap();
+        let hash = Hash::from_bytes([7u8; 32]);
+
+        for name in ["refs/heads/../../../../escaped", "refs/tags/../../../../escaped", "refs/heads//x"] {
+            let outcome = FetchOutcome {
+                refs: BTreeMap::from([("refs/heads/main".to_string(), hash), (name.to_string(), hash)]),
+                objects_received: 0,
+                bytes_received: 0,
+            };
+            assert!(update_remote_refs(&repo, "origin", &outcome, false).is_err(), "{}", name);
+        }
+        assert!(!temp.path().join("escaped").exists());
+        assert!(!repo.dits_dir().join("refs/remotes/origin/main").exists());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_over_ssh_is_refused() {
+        let err = fetch_from_remote("origin", "git@example.com:team/project.dits", false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("SSH is not supported"), "{}", err);
+    }
 }
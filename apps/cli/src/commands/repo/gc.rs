@@ -1,9 +1,22 @@
 //! Garbage collection command - clean up unreferenced objects.
 
-use anyhow::{Result, bail};
+use crate::commands::branching::reflog::Reflog;
+use crate::commands::branching::stash::StashList;
+use crate::core::{Hash, Manifest};
+use crate::store::reachability::{self, ObjectId, ObjectKind};
+use crate::store::{ObjectStore, ObjectType, Repository};
+use crate::vfs::Overlay;
+use anyhow::{Context, Result, bail};
 use std::collections::HashSet;
 use std::fs;
 use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+/// How long unreachable loose objects are kept, like git's `gc.pruneExpire`.
+///
+/// An add or push writes its objects before the ref or index that reaches
+/// them, so younger objects may belong to one still in progress.
+const PRUNE_EXPIRE: Duration = Duration::from_secs(14 * 24 * 60 * 60);
 
 /// Run garbage collection.
 pub fn gc(
@@ -15,25 +28,25 @@ pub fn gc(
     if !dits_dir.exists() {
         bail!("Not a dits repository");
     }
+    let repo = Repository::open(Path::new("."))
+        .context("Not a Dits repository (or any parent directory)")?;
 
     println!("Running garbage collection...");
 
     let mut stats = GcStats::default();
 
     // Step 1: Collect all reachable objects
-    let reachable = collect_reachable_objects(dits_dir)?;
+    let reachable = collect_reachable_objects(&repo)?;
     stats.reachable_objects = reachable.len();
 
-    // Step 2: Find unreferenced objects
-    let objects_dir = dits_dir.join("objects");
-    let unreferenced = find_unreferenced_objects(&objects_dir, &reachable)?;
+    // Step 2: Find unreferenced loose objects old enough to be pruned
+    let expire = SystemTime::now() - PRUNE_EXPIRE;
+    let unreferenced = find_unreferenced_objects(repo.objects(), &reachable, expire)?;
     stats.unreferenced_objects = unreferenced.len();
 
     // Step 3: Calculate space that would be freed
-    for obj_path in &unreferenced {
-        if let Ok(meta) = fs::metadata(obj_path) {
-            stats.bytes_to_free += meta.len();
-        }
+    for (_, _, size) in &unreferenced {
+        stats.bytes_to_free += size;
     }
 
     // Step 4: Prune expired locks
@@ -46,25 +59,35 @@ pub fn gc(
 
     // Step 5: Remove unreferenced objects (if not dry run)
     if !dry_run && !unreferenced.is_empty() {
-        for obj_path in &unreferenced {
-            if let Err(e) = fs::remove_file(obj_path) {
-                eprintln!("Warning: Could not remove {}: {}", obj_path.display(), e);
+        for (obj_type, hash, _) in &unreferenced {
+            match repo.objects().remove_loose(*obj_type, hash) {
+                Ok(_) => stats.objects_removed += 1,
+                Err(e) => eprintln!("Warning: Could not remove {} {}: {}", obj_type.dir_name(), hash.short(), e),
             }
         }
-        stats.objects_removed = unreferenced.len();
 
         // Clean up empty fan-out directories
-        cleanup_empty_directories(&objects_dir)?;
+        cleanup_empty_directories(repo.objects())?;
     }
 
-    // Step 6: Aggressive mode - repack objects
+    // Step 6: Aggressive mode - repack chunks, dropping unreachable ones from packs
     if aggressive && !dry_run {
-        println!("Aggressive mode: repacking objects...");
-        // In a full implementation, this would:
-        // - Re-delta compress objects
-        // - Repack into fewer files
-        // - Optimize storage layout
-        println!("  (Repacking not yet implemented)");
+        println!("Aggressive mode: repacking chunks...");
+        let keep: HashSet<Hash> = reachable
+            .iter()
+            .filter(|id| id.kind == ObjectKind::Chunk)
+            .filter_map(|id| id.hash().ok())
+            .collect();
+        let pack = repo.objects().repack_chunks(&keep, expire)?;
+        println!(
+            "  Packed {} chunks ({} unreachable dropped, {} old packs removed)",
+            pack.chunks_packed, pack.chunks_dropped, pack.packs_removed
+        );
+        println!(
+            "  Chunk storage: {} -> {}",
+            format_size(pack.bytes_before),
+            format_size(pack.bytes_after)
+        );
     }
 
     // Print summary
@@ -108,47 +131,92 @@ struct GcStats {
     locks_pruned: usize,
 }
 
-/// Collect all reachable object hashes.
-fn collect_reachable_objects(dits_dir: &Path) -> Result<HashSet<String>> {
-    let mut reachable = HashSet::new();
+/// Collect every object reachable from refs, HEAD, reflogs, stashes, the
+/// index and the overlay of a writable mount or WebDAV server.
+///
+/// Fails rather than returning a partial set: a missing manifest would
+/// otherwise make all of its chunks look like garbage.
+fn collect_reachable_objects(repo: &Repository) -> Result<HashSet<ObjectId>> {
+    let dits_dir = repo.dits_dir();
+    let store = repo.objects();
+    let mut tips = Vec::new();
 
     // Walk all refs (heads, tags, remotes)
     let refs_dir = dits_dir.join("refs");
     if refs_dir.exists() {
-        collect_refs_objects(&refs_dir, &mut reachable)?;
+        collect_ref_tips(&refs_dir, &mut tips)?;
+    }
+
+    // Check HEAD (may be detached)
+    if let Some(head) = repo.refs().resolve_head()? {
+        tips.push(head);
     }
 
-    // Check HEAD
-    let head = dits_dir.join("HEAD");
-    if head.exists() {
-        let content = fs::read_to_string(&head)?;
-        if let Some(commit_hash) = content.strip_prefix("ref: ") {
-            // Symbolic ref - resolve it
-            let ref_path = dits_dir.join(commit_hash.trim());
-            if ref_path.exists() {
-                let hash = fs::read_to_string(&ref_path)?.trim().to_string();
-                collect_commit_objects(dits_dir, &hash, &mut reachable)?;
+    // Commits recorded in reflogs can still be checked out
+    let logs_dir = dits_dir.join("logs");
+    if logs_dir.exists() {
+        for entry in walkdir::WalkDir::new(&logs_dir) {
+            let entry = entry?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            if let Ok(reflog) = Reflog::load(entry.path()) {
+                for log in reflog.entries {
+                    tips.push(log.hash);
+                    tips.extend(log.previous);
+                }
             }
-        } else {
-            // Direct commit hash
-            let hash = content.trim();
-            collect_commit_objects(dits_dir, hash, &mut reachable)?;
         }
     }
 
-    // Check stash
-    let stash_dir = dits_dir.join("stash");
-    if stash_dir.exists() {
-        for entry in fs::read_dir(&stash_dir)? {
-            let entry = entry?;
-            if entry.file_type()?.is_file() {
-                // Stash entries contain commit hashes
-                if let Ok(content) = fs::read_to_string(entry.path()) {
-                    for line in content.lines() {
-                        if line.len() >= 64 {
-                            collect_commit_objects(dits_dir, line.trim(), &mut reachable)?;
-                        }
-                    }
+    // Stashes reference manifests directly
+    let stashes = StashList::load(&dits_dir.join("stash.json"))?;
+    let mut manifests = Vec::new();
+    for entry in &stashes.entries {
+        tips.extend(entry.base_commit);
+        manifests.push(entry.index_manifest);
+        manifests.push(entry.worktree_manifest);
+    }
+
+    // Content written through a writable mount is stored before it is committed
+    let overlay = match Overlay::open(dits_dir)? {
+        Some(overlay) => {
+            tips.push(overlay.base()?.commit);
+            overlay.apply(&Manifest::new())
+        }
+        None => Manifest::new(),
+    };
+
+    let commits = reachability::commits_between(store, &tips, &HashSet::new())?;
+    let mut reachable: HashSet<ObjectId> = reachability::objects_for_commits(store, &commits)
+        .context("History is incomplete; refusing to collect garbage")?
+        .into_iter()
+        .collect();
+
+    for hash in manifests {
+        if !store.has_object(ObjectType::Manifest, &hash) {
+            continue;
+        }
+        let manifest = store.load_manifest(&hash)?;
+        reachable.extend(reachability::manifest_objects(&manifest));
+        reachable.insert(ObjectId::new(ObjectKind::Manifest, &hash));
+    }
+
+    reachable.extend(reachability::manifest_objects(&overlay));
+
+    // Staged but uncommitted files
+    let index = repo.load_index()?;
+    for entry in index.entries.values() {
+        for chunk in &entry.chunks {
+            reachable.insert(ObjectId::new(ObjectKind::Chunk, &chunk.hash));
+        }
+        if let Some(mp4) = &entry.mp4_metadata {
+            for hash in mp4.ftyp_hash.iter().chain(mp4.moov_hash.iter()) {
+                reachable.insert(ObjectId::new(ObjectKind::Blob, hash));
+            }
+            for atom in &mp4.other_atoms {
+                if let Some(hash) = &atom.hash {
+                    reachable.insert(ObjectId::new(ObjectKind::Blob, hash));
                 }
             }
         }
@@ -157,94 +225,49 @@ fn collect_reachable_objects(dits_dir: &Path) -> Result<HashSet<String>> {
     Ok(reachable)
 }
 
-/// Collect objects referenced by refs in a directory.
-fn collect_refs_objects(refs_dir: &Path, reachable: &mut HashSet<String>) -> Result<()> {
+/// Collect commit hashes stored in ref files under a directory.
+fn collect_ref_tips(refs_dir: &Path, tips: &mut Vec<Hash>) -> Result<()> {
     for entry in fs::read_dir(refs_dir)? {
         let entry = entry?;
         let path = entry.path();
 
         if path.is_dir() {
-            collect_refs_objects(&path, reachable)?;
+            collect_ref_tips(&path, tips)?;
         } else if path.is_file() {
             let content = fs::read_to_string(&path)?;
-            let hash = content.trim();
-            if !hash.is_empty() {
-                // This is a dits directory, not a git one
-                let dits_dir = refs_dir.parent().unwrap();
-                collect_commit_objects(dits_dir, hash, reachable)?;
+            // Skips lock files and anything else that is not a commit hash
+            if let Ok(hash) = Hash::from_hex(content.trim()) {
+                tips.push(hash);
             }
         }
     }
     Ok(())
 }
 
-/// Recursively collect objects reachable from a commit.
-fn collect_commit_objects(dits_dir: &Path, hash: &str, reachable: &mut HashSet<String>) -> Result<()> {
-    if hash.is_empty() || reachable.contains(hash) {
-        return Ok(());
-    }
-
-    reachable.insert(hash.to_string());
-
-    // Try to read the commit object
-    let obj_path = object_path(dits_dir, hash);
-    if !obj_path.exists() {
-        return Ok(()); // Object doesn't exist, skip
-    }
-
-    // In a full implementation, we would parse the commit to find:
-    // - Parent commits
-    // - Tree/manifest objects
-    // - Blob objects
-    // For now, we just mark the object as reachable
-
-    Ok(())
-}
-
-/// Get the path to an object given its hash.
-fn object_path(dits_dir: &Path, hash: &str) -> std::path::PathBuf {
-    let objects = dits_dir.join("objects");
-    if hash.len() >= 2 {
-        // Fan-out layout
-        objects.join(&hash[..2]).join(&hash[2..])
-    } else {
-        objects.join(hash)
-    }
-}
-
-/// Find unreferenced objects.
-fn find_unreferenced_objects(objects_dir: &Path, reachable: &HashSet<String>) -> Result<Vec<std::path::PathBuf>> {
+/// Find loose objects that are not reachable and were last modified at or
+/// before `expire`. Git blobs live in their own repository and are left alone.
+fn find_unreferenced_objects(
+    store: &ObjectStore,
+    reachable: &HashSet<ObjectId>,
+    expire: SystemTime,
+) -> Result<Vec<(ObjectType, Hash, u64)>> {
     let mut unreferenced = Vec::new();
 
-    if !objects_dir.exists() {
-        return Ok(unreferenced);
-    }
-
-    for entry in fs::read_dir(objects_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            // Fan-out directory
-            let prefix = entry.file_name().to_string_lossy().to_string();
-
-            for sub_entry in fs::read_dir(&path)? {
-                let sub_entry = sub_entry?;
-                if sub_entry.file_type()?.is_file() {
-                    let suffix = sub_entry.file_name().to_string_lossy().to_string();
-                    let hash = format!("{}{}", prefix, suffix);
-
-                    if !reachable.contains(&hash) {
-                        unreferenced.push(sub_entry.path());
-                    }
-                }
+    for kind in [ObjectKind::Chunk, ObjectKind::Blob, ObjectKind::Manifest, ObjectKind::Commit] {
+        let Some(obj_type) = kind.object_type() else {
+            continue;
+        };
+        for hash in store.list_loose(obj_type)? {
+            if reachable.contains(&ObjectId::new(kind, &hash)) {
+                continue;
             }
-        } else if path.is_file() {
-            // Direct object file
-            let hash = entry.file_name().to_string_lossy().to_string();
-            if !reachable.contains(&hash) {
-                unreferenced.push(path);
+            // Removed concurrently, or too young to be sure nothing is about to reference it
+            match store.loose_modified(obj_type, &hash) {
+                Ok(modified) if modified <= expire => {}
+                _ => continue,
             }
+            let size = store.loose_size(obj_type, &hash).unwrap_or(0);
+            unreferenced.push((obj_type, hash, size));
         }
     }
 
@@ -264,24 +287,10 @@ fn prune_expired_locks(dits_dir: &Path) -> Result<usize> {
 }
 
 /// Clean up empty fan-out directories.
-fn cleanup_empty_directories(objects_dir: &Path) -> Result<()> {
-    if !objects_dir.exists() {
-        return Ok(());
+fn cleanup_empty_directories(store: &ObjectStore) -> Result<()> {
+    for obj_type in [ObjectType::Chunk, ObjectType::Blob, ObjectType::Manifest, ObjectType::Commit] {
+        store.remove_empty_fanout_dirs(obj_type)?;
     }
-
-    for entry in fs::read_dir(objects_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.is_dir() {
-            // Check if directory is empty
-            let is_empty = fs::read_dir(&path)?.next().is_none();
-            if is_empty {
-                let _ = fs::remove_dir(&path);
-            }
-        }
-    }
-
     Ok(())
 }
 
@@ -305,4 +314,77 @@ fn format_size(bytes: u64) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::Chunk;
+    use crate::vfs::OverlayBase;
+    use tempfile::tempdir;
+
+    fn binary(seed: u8) -> Vec<u8> {
+        (0..64 * 1024u32).map(|i| (i as u8).wrapping_mul(31) ^ seed).collect()
+    }
+
+    #[test]
+    fn test_only_unreachable_objects_are_collected() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        fs::write(temp.path().join("committed.bin"), binary(1)).unwrap();
+        repo.add("committed.bin").unwrap();
+        repo.commit("first").unwrap();
+
+        // Staged but not committed
+        fs::write(temp.path().join("staged.bin"), binary(2)).unwrap();
+        repo.add("staged.bin").unwrap();
+
+        let garbage = Chunk::new(b"nobody references this".to_vec());
+        repo.objects().store_chunk(&garbage).unwrap();
+
+        let reachable = collect_reachable_objects(&repo).unwrap();
+        let unreferenced = find_unreferenced_objects(repo.objects(), &reachable, SystemTime::now()).unwrap();
+        let hashes: Vec<Hash> = unreferenced.iter().map(|(_, hash, _)| *hash).collect();
+        assert_eq!(hashes, vec![garbage.hash]);
+
+        // Objects younger than the expiry are never pruned
+        let expire = SystemTime::now() - PRUNE_EXPIRE;
+        assert!(find_unreferenced_objects(repo.objects(), &reachable, expire).unwrap().is_empty());
+        assert_eq!(repo.objects().repack_chunks(&HashSet::new(), expire).unwrap().chunks_dropped, 0);
+        assert!(repo.objects().has_chunk(&garbage.hash));
+
+        // Repacking keeps everything that is still reachable
+        let keep: HashSet<Hash> = reachable
+            .iter()
+            .filter(|id| id.kind == ObjectKind::Chunk)
+            .filter_map(|id| id.hash().ok())
+            .collect();
+        let stats = repo.objects().repack_chunks(&keep, SystemTime::now()).unwrap();
+        assert_eq!(stats.chunks_dropped, 1);
+        assert!(!repo.objects().has_chunk(&garbage.hash));
+        for hash in &keep {
+            assert!(repo.objects().load_chunk(hash).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_mount_overlay_content_is_reachable() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join("clip.bin"), binary(1)).unwrap();
+        repo.add("clip.bin").unwrap();
+        repo.commit("first").unwrap();
+
+        // A mount wrote new content but has not committed it
+        let head = repo.head().unwrap().unwrap();
+        let base = OverlayBase { branch: "main".to_string(), commit: head, mount_point: temp.path().join("mnt") };
+        let mut overlay = Overlay::create(repo.dits_dir(), &base).unwrap();
+        let (_, manifest) = overlay.load_base(&repo).unwrap();
+        let edited = repo.store_entry(manifest.get("clip.bin").unwrap(), &binary(2)).unwrap();
+        let chunks: Vec<Hash> = edited.chunks.iter().map(|c| c.hash).collect();
+        overlay.put(edited);
+        overlay.save().unwrap();
+
+        let reachable = collect_reachable_objects(&repo).unwrap();
+        for hash in &chunks {
+            assert!(reachable.contains(&ObjectId::new(ObjectKind::Chunk, hash)));
+        }
+        assert!(find_unreferenced_objects(repo.objects(), &reachable, SystemTime::now()).unwrap().is_empty());
+    }
 }
@@ -176,25 +176,18 @@ fn update_commit_graph(repo: &Repository) -> Result<()> {
 }
 
 fn pack_loose_objects(repo: &Repository) -> Result<()> {
-    // Count loose objects
-    let chunks_dir = repo.dits_dir().join("objects").join("chunks");
-    if !chunks_dir.exists() {
+    let stats = repo.objects().pack_loose_chunks()?;
+
+    if stats.chunks_packed == 0 {
+        println!("    No loose chunks to pack");
         return Ok(());
     }
-    
-    let mut loose_count = 0;
-    for entry in fs::read_dir(&chunks_dir)? {
-        let entry = entry?;
-        if entry.file_type()?.is_file() {
-            loose_count += 1;
-        }
-    }
-    
-    println!("    Found {} loose objects", loose_count);
-    
-    // In a full implementation, we would pack these into packfiles
-    // For now, just report the count
-    
+
+    println!(
+        "    Packed {} loose chunks ({} -> {} bytes)",
+        stats.chunks_packed, stats.bytes_before, stats.bytes_after
+    );
+
     Ok(())
 }
 
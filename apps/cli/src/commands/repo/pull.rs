@@ -30,9 +30,15 @@ pub async fn pull(
         RemoteType::Local(remote_path) => {
             pull_local(&current_dir, remote_name, &remote_path, branch, rebase)
         }
-        RemoteType::Http(url) | RemoteType::Dits(url) | RemoteType::Ssh(url) => {
+        RemoteType::Http(url) => {
             pull_network(remote_name, &url, branch, rebase).await
         }
+        RemoteType::Dits(url) => {
+            bail!("Pulling from a peer is not supported: {}; use \"dits fetch {}\" to get its branches", url, remote_name)
+        }
+        RemoteType::Ssh(url) => {
+            bail!("Pulling over SSH is not supported: {}; use an http(s):// or dits:// URL", url)
+        }
     }
 }
 
@@ -189,8 +195,8 @@ fn copy_missing_objects(remote_objects: &Path, local_objects: &Path) -> Result<u
 
     let mut count = 0;
 
-    // Handle the dits objects directory structure: blobs/, chunks/, commits/, manifests/
-    for category in &["blobs", "chunks", "commits", "manifests"] {
+    // Handle the dits objects directory structure: blobs/, chunks/, pack/, commits/, manifests/
+    for category in &["blobs", "chunks", "pack", "commits", "manifests"] {
         let remote_category = remote_objects.join(category);
         let local_category = local_objects.join(category);
 
@@ -1,7 +1,12 @@
 //! Push command - push changes to a remote repository.
 
 use anyhow::{Context, Result, bail};
+use crate::core::Hash;
+use crate::store::locks::{current_user, Lock, LockStore};
 use crate::store::remote::{RemoteStore, RemoteType};
+use crate::store::remote_client::{token_for, HttpRemote, RemoteClientError};
+use crate::store::remote_server::push_lock_violations;
+use crate::store::Repository;
 use std::fs;
 use std::path::Path;
 
@@ -30,9 +35,15 @@ pub async fn push(
         RemoteType::Local(remote_path) => {
             push_local(&remote_path, branch, force, all)
         }
-        RemoteType::Http(url) | RemoteType::Dits(url) | RemoteType::Ssh(url) => {
+        RemoteType::Http(url) => {
             push_network(remote_name, &url, branch, force, all).await
         }
+        RemoteType::Dits(url) => {
+            bail!("Pushing to a peer is not supported: {}; peers only serve fetches, push to an http(s):// remote", url)
+        }
+        RemoteType::Ssh(url) => {
+            bail!("Pushing over SSH is not supported: {}; use an http(s):// URL", url)
+        }
     }
 }
 
@@ -46,22 +57,96 @@ async fn push_network(
 ) -> Result<()> {
     println!("Pushing to {} ({}) ...", remote_name, url);
 
-    // For now, implement a basic HTTP-based push
     // TODO: Implement full QUIC protocol for efficiency
     push_http(remote_name, url, branch, force, all).await
 }
 
 /// Push to an HTTP remote.
 async fn push_http(
-    _remote_name: &str,
+    remote_name: &str,
     url: &str,
-    _branch: Option<&str>,
-    _force: bool,
-    _all: bool,
+    branch: Option<&str>,
+    force: bool,
+    all: bool,
 ) -> Result<()> {
-    // TODO: Implement push logic - send refs, objects, etc.
-    println!("Network push to {} - basic implementation", url);
-    println!("Full remote push will be implemented in Phase 4b");
+    let repo = Repository::open(Path::new("."))
+        .map_err(|_| anyhow::anyhow!("Not in a dits repository"))?;
+
+    let branches: Vec<String> = if all {
+        repo.list_branches()?
+    } else if let Some(b) = branch {
+        vec![b.to_string()]
+    } else {
+        match repo.current_branch()? {
+            Some(b) => vec![b],
+            None => bail!("Cannot push detached HEAD. Specify a branch name."),
+        }
+    };
+
+    if branches.is_empty() {
+        println!("Nothing to push.");
+        return Ok(());
+    }
+
+    let remote = HttpRemote::new(url).with_token(token_for(repo.dits_dir(), remote_name));
+    let mut pushed_count = 0;
+    let mut objects_sent = 0;
+    let mut bytes_sent = 0;
+    let mut rejected = Vec::new();
+
+    for branch_name in &branches {
+        match remote.push_branch(&repo, branch_name, force).await {
+            Ok(outcome) if outcome.up_to_date => {
+                println!("  = {} is up to date", branch_name);
+            }
+            Ok(outcome) => {
+                let range = match outcome.old {
+                    Some(old) if force => format!("{}...{} (forced update)", old.short(), outcome.new.short()),
+                    Some(old) => format!("{}..{}", old.short(), outcome.new.short()),
+                    None => format!("[new branch] {}", outcome.new.short()),
+                };
+                println!(
+                    "  + {} -> {} ({} objects sent, {} already on remote)",
+                    branch_name, range, outcome.objects_sent, outcome.objects_skipped
+                );
+
+                // Update the remote-tracking ref
+                let tracking = repo.dits_dir().join("refs").join("remotes").join(remote_name).join(branch_name);
+                if let Some(parent) = tracking.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&tracking, format!("{}\n", outcome.new.to_hex()))?;
+
+                pushed_count += 1;
+                objects_sent += outcome.objects_sent;
+                bytes_sent += outcome.bytes_sent;
+            }
+            Err(RemoteClientError::Rejected(reason)) => {
+                println!("  ! [rejected] {} ({})", branch_name, reason);
+                rejected.push(branch_name.clone());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    if pushed_count > 0 {
+        println!(
+            "\nPushed {} branch(es), {} objects ({} bytes) sent.",
+            pushed_count, objects_sent, bytes_sent
+        );
+    } else if rejected.is_empty() {
+        println!("\nNothing to push (everything up-to-date).");
+    }
+
+    if !rejected.is_empty() {
+        bail!("Failed to push some refs to {}: {}", url, rejected.join(", "));
+    }
+
+    // Pick up locks released on the remote, then re-apply read-only files
+    if let Ok(held) = remote.locks().await {
+        LockStore::new(repo.dits_dir()).replace_all(held.into_iter().map(Lock::from).collect())?;
+    }
+    repo.refresh_lock_permissions()?;
 
     Ok(())
 }
@@ -131,10 +216,12 @@ pub fn push_local(
         }
 
         let local_commit = fs::read_to_string(&local_ref)?.trim().to_string();
+        let mut old = None;
 
         // Check if remote already has this commit
         if remote_ref.exists() {
             let remote_commit = fs::read_to_string(&remote_ref)?.trim().to_string();
+            old = Some(Hash::from_hex(&remote_commit)?);
             if remote_commit == local_commit && !force {
                 println!("  = {} is up to date", branch_name);
                 continue;
@@ -154,6 +241,17 @@ pub fn push_local(
         let copied = copy_missing_objects(&local_objects, &remote_objects)?;
         objects_copied += copied;
 
+        // Files locked in the remote by someone else must not change
+        let tip = Hash::from_hex(&local_commit)?;
+        let user = current_user();
+        let violations = push_lock_violations(&remote_dits, old, tip, user.as_deref())?;
+        if !violations.is_empty() {
+            for violation in &violations {
+                println!("  ! {}", violation);
+            }
+            bail!("Push of {} rejected by file locks", branch_name);
+        }
+
         // Update remote ref
         fs::create_dir_all(remote_ref.parent().unwrap())?;
         fs::write(&remote_ref, format!("{}\n", local_commit))?;
@@ -171,6 +269,13 @@ pub fn push_local(
         println!("\nNothing to push (everything up-to-date).");
     }
 
+    // Pick up locks released on the remote, then re-apply read-only files
+    let held = LockStore::new(&remote_dits).list().into_iter().cloned().collect();
+    LockStore::new(local_dits).replace_all(held)?;
+    if let Ok(repo) = Repository::open(Path::new(".")) {
+        repo.refresh_lock_permissions()?;
+    }
+
     Ok(())
 }
 
@@ -183,8 +288,8 @@ fn copy_missing_objects(local_objects: &Path, remote_objects: &Path) -> Result<u
 
     let mut count = 0;
 
-    // Handle the dits objects directory structure: blobs/, chunks/, commits/, manifests/
-    for category in &["blobs", "chunks", "commits", "manifests"] {
+    // Handle the dits objects directory structure: blobs/, chunks/, pack/, commits/, manifests/
+    for category in &["blobs", "chunks", "pack", "commits", "manifests"] {
         let local_category = local_objects.join(category);
         let remote_category = remote_objects.join(category);
 
@@ -110,6 +110,22 @@ pub fn remote_set_url(name: &str, url: &str, push: bool) -> Result<()> {
     Ok(())
 }
 
+/// Set (or, with no token, clear) the access token for a remote.
+pub fn remote_set_token(name: &str, token: Option<&str>) -> Result<()> {
+    let dits_dir = find_dits_dir()?;
+    let mut store = RemoteStore::new(&dits_dir);
+
+    store.set_token(name, token)
+        .context(format!("Failed to set token for remote '{}'", name))?;
+
+    if token.is_some() {
+        println!("Updated access token for '{}'", name);
+    } else {
+        println!("Cleared access token for '{}'", name);
+    }
+    Ok(())
+}
+
 /// Handle remote subcommand.
 pub fn remote(
     action: Option<&str>,
@@ -143,7 +159,11 @@ pub fn remote(
             let url_val = url.ok_or_else(|| anyhow::anyhow!("URL required"))?;
             remote_set_url(name, url_val, push)
         }
-        Some(other) => bail!("Unknown remote action: {}. Use add, remove, rename, get-url, set-url, or list.", other),
+        Some("set-token") => {
+            let name = name.ok_or_else(|| anyhow::anyhow!("Remote name required"))?;
+            remote_set_token(name, url)
+        }
+        Some(other) => bail!("Unknown remote action: {}. Use add, remove, rename, get-url, set-url, set-token, or list.", other),
     }
 }
 
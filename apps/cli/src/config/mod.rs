@@ -20,6 +20,12 @@ pub struct Config {
     /// Chunking settings.
     #[serde(default)]
     pub chunking: ChunkingConfig,
+    /// File lock settings.
+    #[serde(default)]
+    pub locks: LocksConfig,
+    /// Project dependency settings.
+    #[serde(default)]
+    pub dependencies: DependenciesConfig,
     /// Additional settings (for extensibility).
     #[serde(default, flatten)]
     pub extra: BTreeMap<String, toml::Value>,
@@ -105,6 +111,62 @@ impl ChunkingConfig {
     }
 }
 
+/// What `commit` does about changes that break the locking rules.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LockEnforcement {
+    /// Don't check locks.
+    Off,
+    /// Commit, but print each violation.
+    #[default]
+    Warn,
+    /// Refuse to commit.
+    Require,
+}
+
+impl LockEnforcement {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LockEnforcement::Off => "off",
+            LockEnforcement::Warn => "warn",
+            LockEnforcement::Require => "require",
+        }
+    }
+}
+
+impl std::str::FromStr for LockEnforcement {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(LockEnforcement::Off),
+            "warn" => Ok(LockEnforcement::Warn),
+            "require" => Ok(LockEnforcement::Require),
+            _ => Err(()),
+        }
+    }
+}
+
+/// File lock configuration.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct LocksConfig {
+    /// Whether commits touching paths locked by others, or lockable file
+    /// types the committer has not locked, are allowed.
+    #[serde(default)]
+    pub enforce: LockEnforcement,
+    /// Keep lockable files read-only in the working tree until locked.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// Project dependency configuration.
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct DependenciesConfig {
+    /// Relink project media to the working tree after checkout.
+    #[serde(default)]
+    pub relink_on_checkout: bool,
+}
+
 impl Config {
     /// Load configuration from file.
     pub fn load(path: &Path) -> Result<Self, ConfigError> {
@@ -134,6 +196,9 @@ impl Config {
             ["chunking", "target_size"] => Some(self.chunking.target_size.to_string()),
             ["chunking", "min_size"] => Some(self.chunking.min_size.to_string()),
             ["chunking", "max_size"] => Some(self.chunking.max_size.to_string()),
+            ["locks", "enforce"] => Some(self.locks.enforce.as_str().to_string()),
+            ["locks", "read_only"] => Some(self.locks.read_only.to_string()),
+            ["dependencies", "relink_on_checkout"] => Some(self.dependencies.relink_on_checkout.to_string()),
             _ => None,
         }
     }
@@ -161,6 +226,27 @@ impl Config {
             ["chunking", "max_size"] => {
                 self.chunking.max_size = parse_size(value)?
             }
+            ["locks", "enforce"] => {
+                self.locks.enforce = value.parse().map_err(|_| ConfigError::InvalidValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    reason: "expected off, warn or require".to_string(),
+                })?
+            }
+            ["locks", "read_only"] => {
+                self.locks.read_only = value.parse().map_err(|_| ConfigError::InvalidValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    reason: "expected true or false".to_string(),
+                })?
+            }
+            ["dependencies", "relink_on_checkout"] => {
+                self.dependencies.relink_on_checkout = value.parse().map_err(|_| ConfigError::InvalidValue {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                    reason: "expected true or false".to_string(),
+                })?
+            }
             _ => return Err(ConfigError::UnknownKey(key.to_string())),
         }
         Ok(())
@@ -199,6 +285,12 @@ impl Config {
         items.push(("chunking.target_size".to_string(), format_size(self.chunking.target_size)));
         items.push(("chunking.min_size".to_string(), format_size(self.chunking.min_size)));
         items.push(("chunking.max_size".to_string(), format_size(self.chunking.max_size)));
+        items.push(("locks.enforce".to_string(), self.locks.enforce.as_str().to_string()));
+        items.push(("locks.read_only".to_string(), self.locks.read_only.to_string()));
+        items.push((
+            "dependencies.relink_on_checkout".to_string(),
+            self.dependencies.relink_on_checkout.to_string(),
+        ));
 
         items
     }
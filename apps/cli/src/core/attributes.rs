@@ -0,0 +1,292 @@
+//! .ditsattributes file parsing and per-path attribute lookup.
+//!
+//! A gitattributes-style file at the repository root overrides how paths are
+//! stored, chunked, locked, diffed and merged:
+//!
+//! ```text
+//! # pattern        attributes...
+//! *.xml            storage=chunk chunker=media diff=binary
+//! *.cache          binary
+//! scenes/**        lockable
+//! notes/*.xml      -lockable merge=text
+//! ```
+//!
+//! Patterns without a `/` match the file name at any depth; others are
+//! anchored at the repository root. When several lines match, later lines
+//! win attribute by attribute. Unknown attributes are ignored.
+
+use super::{ChunkerConfig, StorageStrategy};
+use globset::{GlobBuilder, GlobMatcher};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Name of the attributes file at the repository root.
+const ATTRIBUTES_FILE: &str = ".ditsattributes";
+
+/// Named chunker configuration (`chunker=<profile>`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChunkerProfile {
+    /// [`ChunkerConfig::default`]
+    Default,
+    /// [`ChunkerConfig::media`]: large chunks for video, audio and images.
+    Media,
+    /// [`ChunkerConfig::project`]: small chunks for project files.
+    Project,
+    /// [`ChunkerConfig::fast`]: very large chunks, least overhead.
+    Fast,
+}
+
+impl ChunkerProfile {
+    /// The chunker configuration for this profile.
+    pub fn config(&self) -> ChunkerConfig {
+        match self {
+            ChunkerProfile::Default => ChunkerConfig::default(),
+            ChunkerProfile::Media => ChunkerConfig::media(),
+            ChunkerProfile::Project => ChunkerConfig::project(),
+            ChunkerProfile::Fast => ChunkerConfig::fast(),
+        }
+    }
+}
+
+impl FromStr for ChunkerProfile {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => Ok(ChunkerProfile::Default),
+            "media" => Ok(ChunkerProfile::Media),
+            "project" => Ok(ChunkerProfile::Project),
+            "fast" => Ok(ChunkerProfile::Fast),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How `dits diff` shows changes (`diff=<driver>`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffDriver {
+    /// Line-based text diff.
+    Text,
+    /// Size summary only.
+    Binary,
+}
+
+impl FromStr for DiffDriver {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(DiffDriver::Text),
+            "binary" => Ok(DiffDriver::Binary),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How `dits merge` resolves a file changed on both sides (`merge=<driver>`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MergeDriver {
+    /// Report a conflict (the default).
+    Binary,
+    /// Three-way line merge; only overlapping edits conflict.
+    Text,
+    /// Keep the current branch's version.
+    Ours,
+    /// Take the merged branch's version.
+    Theirs,
+}
+
+impl FromStr for MergeDriver {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(MergeDriver::Binary),
+            "text" => Ok(MergeDriver::Text),
+            "ours" => Ok(MergeDriver::Ours),
+            "theirs" => Ok(MergeDriver::Theirs),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Parse a `storage=` value.
+fn parse_storage(s: &str) -> Option<StorageStrategy> {
+    match s {
+        "git" | "text" | "git-text" => Some(StorageStrategy::GitText),
+        "dits" | "chunk" => Some(StorageStrategy::DitsChunk),
+        "hybrid" => Some(StorageStrategy::Hybrid),
+        _ => None,
+    }
+}
+
+/// Attributes that apply to a path. `None` means built-in detection decides.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathAttributes {
+    /// Storage engine override.
+    pub storage: Option<StorageStrategy>,
+    /// Chunker profile override.
+    pub chunker: Option<ChunkerProfile>,
+    /// Whether the file must be locked before it is changed.
+    pub lockable: Option<bool>,
+    /// Diff driver override.
+    pub diff: Option<DiffDriver>,
+    /// Merge driver override.
+    pub merge: Option<MergeDriver>,
+}
+
+impl PathAttributes {
+    /// Parse the attributes of one line. Returns `None` if nothing was recognized.
+    fn parse<'a>(attrs: impl Iterator<Item = &'a str>) -> Option<Self> {
+        let mut out = Self::default();
+        for attr in attrs {
+            match attr.split_once('=') {
+                Some(("storage", v)) => out.storage = parse_storage(v).or(out.storage),
+                Some(("chunker", v)) => out.chunker = v.parse().ok().or(out.chunker),
+                Some(("diff", v)) => out.diff = v.parse().ok().or(out.diff),
+                Some(("merge", v)) => out.merge = v.parse().ok().or(out.merge),
+                Some(_) => {}
+                None => match attr {
+                    "lockable" => out.lockable = Some(true),
+                    "-lockable" => out.lockable = Some(false),
+                    "diff" => out.diff = Some(DiffDriver::Text),
+                    "-diff" => out.diff = Some(DiffDriver::Binary),
+                    "-merge" => out.merge = Some(MergeDriver::Binary),
+                    "text" => {
+                        out.storage = Some(StorageStrategy::GitText);
+                        out.diff = Some(DiffDriver::Text);
+                    }
+                    "binary" => {
+                        out.storage = Some(StorageStrategy::DitsChunk);
+                        out.diff = Some(DiffDriver::Binary);
+                        out.merge = Some(MergeDriver::Binary);
+                    }
+                    _ => {}
+                },
+            }
+        }
+        (out != Self::default()).then_some(out)
+    }
+
+    /// Overlay the attributes set in `other`.
+    fn apply(&mut self, other: &Self) {
+        self.storage = other.storage.or(self.storage);
+        self.chunker = other.chunker.or(self.chunker);
+        self.lockable = other.lockable.or(self.lockable);
+        self.diff = other.diff.or(self.diff);
+        self.merge = other.merge.or(self.merge);
+    }
+}
+
+/// Attribute rules loaded from `.ditsattributes`.
+#[derive(Clone, Debug)]
+pub struct AttributeMatcher {
+    /// Rules in file order.
+    rules: Vec<(GlobMatcher, PathAttributes)>,
+    /// Root directory for relative pattern matching.
+    root: PathBuf,
+}
+
+impl AttributeMatcher {
+    /// Load `.ditsattributes` from the given repository root, if present.
+    pub fn new(root: &Path) -> Self {
+        let content = fs::read_to_string(root.join(ATTRIBUTES_FILE)).unwrap_or_default();
+        Self::parse(root, &content)
+    }
+
+    /// Matcher with no rules.
+    pub fn empty() -> Self {
+        Self::parse(Path::new(""), "")
+    }
+
+    /// Parse attribute file content.
+    pub fn parse(root: &Path, content: &str) -> Self {
+        let mut rules = Vec::new();
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let Some(pattern) = fields.next() else { continue };
+            let Some(attrs) = PathAttributes::parse(fields) else { continue };
+
+            let glob = match pattern.strip_prefix('/') {
+                Some(anchored) => anchored.to_string(),
+                None if pattern.contains('/') => pattern.to_string(),
+                None => format!("**/{}", pattern),
+            };
+            if let Ok(glob) = GlobBuilder::new(&glob).literal_separator(true).build() {
+                rules.push((glob.compile_matcher(), attrs));
+            }
+        }
+
+        Self {
+            rules,
+            root: root.to_path_buf(),
+        }
+    }
+
+    /// Attributes for a path (absolute, or relative to the root).
+    pub fn get(&self, path: &Path) -> PathAttributes {
+        let relative = path.strip_prefix(&self.root).unwrap_or(path);
+        let mut attrs = PathAttributes::default();
+        for (glob, rule) in &self.rules {
+            if glob.is_match(relative) {
+                attrs.apply(rule);
+            }
+        }
+        attrs
+    }
+
+    /// Attributes for a repository-relative path.
+    pub fn get_str(&self, path: &str) -> PathAttributes {
+        self.get(Path::new(path))
+    }
+}
+
+impl Default for AttributeMatcher {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_patterns_and_precedence() {
+        let attrs = AttributeMatcher::parse(
+            Path::new("/repo"),
+            "# studio overrides\n\
+             *.xml storage=chunk chunker=media diff=binary\n\
+             notes/*.xml storage=git -diff merge=text\n\
+             /cache/** binary lockable\n\
+             *.mp4 lfs merge=nle-recursive\n",
+        );
+
+        let edl = attrs.get(Path::new("/repo/exports/cut.xml"));
+        assert_eq!(edl.storage, Some(StorageStrategy::DitsChunk));
+        assert_eq!(edl.chunker, Some(ChunkerProfile::Media));
+        assert_eq!(edl.diff, Some(DiffDriver::Binary));
+        assert_eq!(edl.merge, None);
+
+        // Later lines override earlier ones, attribute by attribute
+        let notes = attrs.get_str("notes/todo.xml");
+        assert_eq!(notes.storage, Some(StorageStrategy::GitText));
+        assert_eq!(notes.chunker, Some(ChunkerProfile::Media));
+        assert_eq!(notes.merge, Some(MergeDriver::Text));
+        assert_eq!(attrs.get_str("a/notes/todo.xml").storage, Some(StorageStrategy::DitsChunk));
+
+        let cache = attrs.get_str("cache/sim/frame.bin");
+        assert_eq!(cache.lockable, Some(true));
+        assert_eq!(cache.merge, Some(MergeDriver::Binary));
+        assert_eq!(attrs.get_str("src/cache/x.bin"), PathAttributes::default());
+
+        // Unknown attributes and values are ignored
+        assert_eq!(attrs.get_str("clip.mp4"), PathAttributes::default());
+    }
+}
@@ -11,6 +11,13 @@
 use crate::core::hash::{Hash, Hasher};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use std::io::{self, Read};
+
+/// Number of chunks hashed together by [`chunk_reader_with_refs`].
+///
+/// Bounds streaming memory to roughly `STREAM_BATCH * max_size` while still
+/// giving rayon enough work to hash in parallel.
+const STREAM_BATCH: usize = 32;
 
 /// Configuration for the FastCDC chunker.
 ///
@@ -143,7 +150,7 @@ impl Chunk {
 }
 
 /// Reference to a chunk within a file.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct ChunkRef {
     /// The chunk hash.
     pub hash: Hash,
@@ -297,6 +304,56 @@ pub fn chunk_data_with_refs_parallel(data: &[u8], config: &ChunkerConfig) -> (Ve
     results.into_iter().unzip()
 }
 
+/// Chunk a stream in bounded memory, handing each chunk to `store` in order.
+///
+/// Produces exactly the boundaries [`chunk_data_with_refs`] would for the
+/// same bytes, but never holds more than one batch of chunks, so files larger
+/// than RAM can be added. Hashing within a batch runs in parallel.
+pub fn chunk_reader_with_refs<R, F, E>(
+    reader: R,
+    config: &ChunkerConfig,
+    mut store: F,
+) -> Result<Vec<ChunkRef>, E>
+where
+    R: Read,
+    F: FnMut(&Chunk) -> Result<(), E>,
+    E: From<io::Error>,
+{
+    let chunker = fastcdc::v2020::StreamCDC::new(
+        reader,
+        config.min_size,
+        config.avg_size,
+        config.max_size,
+    );
+
+    let mut refs = Vec::new();
+    let mut pending: Vec<(u64, Vec<u8>)> = Vec::with_capacity(STREAM_BATCH);
+
+    let mut flush = |pending: &mut Vec<(u64, Vec<u8>)>, refs: &mut Vec<ChunkRef>| -> Result<(), E> {
+        let chunks: Vec<(u64, Chunk)> = std::mem::take(pending)
+            .into_par_iter()
+            .map(|(offset, data)| (offset, Chunk::new(data)))
+            .collect();
+
+        for (offset, chunk) in &chunks {
+            store(chunk)?;
+            refs.push(ChunkRef::new(chunk.hash, *offset, chunk.size() as u64));
+        }
+        Ok(())
+    };
+
+    for result in chunker {
+        let chunk_info = result.map_err(io::Error::from)?;
+        pending.push((chunk_info.offset, chunk_info.data));
+        if pending.len() == STREAM_BATCH {
+            flush(&mut pending, &mut refs)?;
+        }
+    }
+    flush(&mut pending, &mut refs)?;
+
+    Ok(refs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -446,4 +503,31 @@ mod tests {
 
         assert_eq!(data, reconstructed);
     }
+
+    #[test]
+    fn test_stream_matches_in_memory() {
+        let data: Vec<u8> = (0..3_000_000u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        let config = ChunkerConfig::small();
+
+        let (chunks, expected) = chunk_data_with_refs(&data, &config);
+
+        let mut streamed = Vec::new();
+        let refs = chunk_reader_with_refs(&data[..], &config, |chunk: &Chunk| -> io::Result<()> {
+            streamed.push(chunk.clone());
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(refs.len(), expected.len());
+        for (a, b) in refs.iter().zip(expected.iter()) {
+            assert_eq!((a.hash, a.offset, a.size), (b.hash, b.offset, b.size));
+        }
+        assert_eq!(streamed.len(), chunks.len());
+
+        // Tiny and empty inputs behave like the in-memory chunker
+        let small = chunk_reader_with_refs(&b"tiny"[..], &config, |_: &Chunk| -> io::Result<()> { Ok(()) }).unwrap();
+        assert_eq!(small.len(), 1);
+        let empty = chunk_reader_with_refs(&b""[..], &config, |_: &Chunk| -> io::Result<()> { Ok(()) }).unwrap();
+        assert!(empty.is_empty());
+    }
 }
@@ -140,8 +140,11 @@ impl Commit {
         all
     }
 
-    /// Compute the hash of this commit.
-    fn compute_hash(&self) -> Hash {
+    /// Compute the hash of this commit from its contents.
+    ///
+    /// A commit received from elsewhere is only genuine if this matches its
+    /// `hash` field.
+    pub fn compute_hash(&self) -> Hash {
         let mut hasher = Hasher::new();
 
         if let Some(parent) = &self.parent {
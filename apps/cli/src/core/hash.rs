@@ -2,6 +2,9 @@
 
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
 
 /// Size of a BLAKE3 hash in bytes (32 bytes = 256 bits).
 pub const HASH_SIZE: usize = 32;
@@ -111,6 +114,54 @@ impl Hasher {
         let hash = blake3::hash(data);
         Hash::from_bytes(*hash.as_bytes())
     }
+
+    /// Hash everything a reader yields without buffering it.
+    /// Returns the hash and the number of bytes read.
+    pub fn hash_reader<R: Read>(reader: R) -> io::Result<(Hash, u64)> {
+        let mut reader = HashingReader::new(reader);
+        io::copy(&mut reader, &mut io::sink())?;
+        Ok(reader.finish())
+    }
+
+    /// Hash a file in bounded memory.
+    pub fn hash_file(path: &Path) -> io::Result<Hash> {
+        Ok(Self::hash_reader(File::open(path)?)?.0)
+    }
+}
+
+/// Reader adapter that hashes every byte read through it.
+///
+/// Lets a single pass over a file feed both the chunker and the
+/// whole-file content hash.
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Hasher,
+    bytes_read: u64,
+}
+
+impl<R: Read> HashingReader<R> {
+    /// Wrap a reader.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            hasher: Hasher::new(),
+            bytes_read: 0,
+        }
+    }
+
+    /// Hash and length of everything read so far.
+    pub fn finish(&self) -> (Hash, u64) {
+        (self.hasher.finalize(), self.bytes_read)
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.bytes_read += n as u64;
+        Ok(n)
+    }
 }
 
 impl Default for Hasher {
@@ -131,6 +182,14 @@ mod tests {
         assert_eq!(hash1, hash2);
     }
 
+    #[test]
+    fn test_hash_reader_matches_hash() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let (hash, len) = Hasher::hash_reader(&data[..]).unwrap();
+        assert_eq!(hash, Hasher::hash(&data));
+        assert_eq!(len, data.len() as u64);
+    }
+
     #[test]
     fn test_hash_hex_roundtrip() {
         let hash = Hasher::hash(b"test data");
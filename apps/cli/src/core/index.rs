@@ -9,9 +9,14 @@
 
 use crate::core::chunk::ChunkRef;
 use crate::core::hash::Hash;
+use crate::core::index_file::SharedIndex;
 use crate::core::storage_strategy::StorageStrategy;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
+use std::fs::Metadata;
+use std::os::unix::fs::MetadataExt;
+use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Status of a file in the working directory.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -35,7 +40,7 @@ pub enum FileStatus {
 }
 
 /// Represents an atom in the MP4 file structure.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct StoredAtom {
     /// 4-character atom type (e.g., "ftyp", "uuid", "free").
     pub atom_type: String,
@@ -47,7 +52,7 @@ pub struct StoredAtom {
 }
 
 /// MP4-specific metadata for structure-aware versioning.
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
 pub struct Mp4Metadata {
     /// Hash of the ftyp atom data.
     pub ftyp_hash: Option<Hash>,
@@ -86,8 +91,57 @@ pub enum FileType {
     Other,
 }
 
+/// A filesystem timestamp with nanosecond precision.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct StatTime {
+    pub secs: i64,
+    pub nanos: u32,
+}
+
+impl From<SystemTime> for StatTime {
+    fn from(time: SystemTime) -> Self {
+        match time.duration_since(SystemTime::UNIX_EPOCH) {
+            Ok(d) => Self { secs: d.as_secs() as i64, nanos: d.subsec_nanos() },
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// Filesystem metadata recorded when an entry was last known to match the
+/// working tree. If it is unchanged the file need not be rehashed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatData {
+    pub mtime: StatTime,
+    pub ctime: StatTime,
+    pub size: u64,
+    pub ino: u64,
+    pub dev: u64,
+}
+
+impl StatData {
+    /// Capture stat data from file metadata.
+    pub fn from_metadata(metadata: &Metadata) -> Self {
+        Self {
+            mtime: StatTime { secs: metadata.mtime(), nanos: metadata.mtime_nsec() as u32 },
+            ctime: StatTime { secs: metadata.ctime(), nanos: metadata.ctime_nsec() as u32 },
+            size: metadata.len(),
+            ino: metadata.ino(),
+            dev: metadata.dev(),
+        }
+    }
+
+    /// Whether stat data has been recorded.
+    pub fn is_set(&self) -> bool {
+        *self != Self::default()
+    }
+
+    fn is_unset(&self) -> bool {
+        !self.is_set()
+    }
+}
+
 /// An entry in the index representing a staged file.
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct IndexEntry {
     /// File path.
     pub path: String,
@@ -124,6 +178,11 @@ pub struct IndexEntry {
     /// Set when storage is GitText or Hybrid.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub git_oid: Option<String>,
+
+    /// Stat data of the working tree file when it last matched `content_hash`.
+    /// Unset for entries that have never been compared against the working tree.
+    #[serde(default, skip_serializing_if = "StatData::is_unset")]
+    pub stat: StatData,
 }
 
 impl IndexEntry {
@@ -151,6 +210,7 @@ impl IndexEntry {
             mp4_metadata: None,
             storage: StorageStrategy::DitsChunk,
             git_oid: None,
+            stat: StatData::default(),
         }
     }
 
@@ -179,6 +239,7 @@ impl IndexEntry {
             mp4_metadata: Some(mp4_metadata),
             storage: StorageStrategy::DitsChunk,
             git_oid: None,
+            stat: StatData::default(),
         }
     }
 
@@ -208,6 +269,7 @@ impl IndexEntry {
             mp4_metadata: None,
             storage: StorageStrategy::GitText,
             git_oid: Some(git_oid),
+            stat: StatData::default(),
         }
     }
 
@@ -237,6 +299,7 @@ impl IndexEntry {
             mp4_metadata: None,
             storage,
             git_oid,
+            stat: StatData::default(),
         }
     }
 
@@ -273,6 +336,13 @@ pub struct Index {
     pub entries: BTreeMap<String, IndexEntry>,
     /// The commit this index is based on (HEAD).
     pub base_commit: Option<Hash>,
+    /// When the index file was last written. Entries whose file was modified
+    /// at or after this moment are "racily clean" and must be rehashed.
+    #[serde(skip)]
+    written_at: Option<StatTime>,
+    /// Shared index the file was split against, if it was.
+    #[serde(skip)]
+    pub(super) shared: Option<Arc<SharedIndex>>,
 }
 
 impl Index {
@@ -281,6 +351,8 @@ impl Index {
         Self {
             entries: BTreeMap::new(),
             base_commit: None,
+            written_at: None,
+            shared: None,
         }
     }
 
@@ -289,6 +361,8 @@ impl Index {
         Self {
             entries: BTreeMap::new(),
             base_commit: Some(commit_hash),
+            written_at: None,
+            shared: None,
         }
     }
 
@@ -332,6 +406,50 @@ impl Index {
         self.entries.clear();
     }
 
+    /// Record when the index file was written (its mtime on disk).
+    pub fn set_written_at(&mut self, time: SystemTime) {
+        self.written_at = Some(time.into());
+    }
+
+    /// Check whether the file at `path` is known to match its entry from stat data alone.
+    ///
+    /// Like git, an entry whose mtime is not strictly older than the index
+    /// write is treated as racy: the file could have changed again within the
+    /// same timestamp tick, so it must be rehashed.
+    pub fn is_stat_clean(&self, path: &str, current: &StatData) -> bool {
+        let (Some(entry), Some(written_at)) = (self.entries.get(path), self.written_at) else {
+            return false;
+        };
+        entry.stat.is_set() && entry.stat == *current && entry.stat.mtime < written_at
+    }
+
+    /// Forget the stat data of entries whose file was modified at or after
+    /// `written_at`, the time the index is being written.
+    ///
+    /// Like git's smudging of racily clean entries: such a file could change
+    /// again within the same timestamp tick without its stat data changing, so
+    /// it must be rehashed rather than trusted. Returns whether any were
+    /// forgotten.
+    pub fn smudge_racy_entries(&mut self, written_at: SystemTime) -> bool {
+        let written_at = StatTime::from(written_at);
+        let mut smudged = false;
+        for entry in self.entries.values_mut() {
+            if entry.stat.is_set() && entry.stat.mtime >= written_at {
+                entry.stat = StatData::default();
+                smudged = true;
+            }
+        }
+        smudged
+    }
+
+    /// Whether any entry has stat data that is not yet older than the index
+    /// write, and so cannot be trusted until the index is written again.
+    pub fn has_racy_entries(&self) -> bool {
+        self.entries.values().any(|e| {
+            e.stat.is_set() && self.written_at.is_none_or(|written_at| e.stat.mtime >= written_at)
+        })
+    }
+
     /// Get entries by status.
     pub fn entries_by_status(&self, status: FileStatus) -> Vec<&IndexEntry> {
         self.entries
@@ -385,6 +503,53 @@ mod tests {
         assert_eq!(index.len(), 0);
     }
 
+    #[test]
+    fn test_stat_clean_detects_racy_entries() {
+        let stat = StatData {
+            mtime: StatTime { secs: 1_000, nanos: 500 },
+            ctime: StatTime { secs: 1_000, nanos: 500 },
+            size: 100,
+            ino: 42,
+            dev: 1,
+        };
+        let mut entry = IndexEntry::new(
+            "clip.bin".to_string(),
+            Hash::ZERO,
+            100,
+            1_000,
+            0o644,
+            FileType::Regular,
+            String::new(),
+            vec![],
+        );
+        entry.stat = stat;
+
+        let mut index = Index::new();
+        index.stage(entry);
+
+        // Unknown write time: never trust the cache
+        assert!(!index.is_stat_clean("clip.bin", &stat));
+
+        // Index written in the same tick as the file: racy
+        index.written_at = Some(StatTime { secs: 1_000, nanos: 500 });
+        assert!(!index.is_stat_clean("clip.bin", &stat));
+
+        index.written_at = Some(StatTime { secs: 1_001, nanos: 0 });
+        assert!(index.is_stat_clean("clip.bin", &stat));
+
+        let resized = StatData { size: 101, ..stat };
+        assert!(!index.is_stat_clean("clip.bin", &resized));
+        assert!(!index.is_stat_clean("other.bin", &stat));
+
+        // Writing the index in the file's tick forgets its stat data
+        let tick = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000);
+        assert!(!index.smudge_racy_entries(tick + std::time::Duration::from_secs(1)));
+        assert!(index.entries["clip.bin"].stat.is_set());
+        assert!(index.smudge_racy_entries(tick));
+        assert!(!index.entries["clip.bin"].stat.is_set());
+        assert!(!index.has_racy_entries());
+    }
+
     #[test]
     fn test_index_json_roundtrip() {
         let mut index = Index::new();
@@ -0,0 +1,946 @@
+//! Binary on-disk encoding of the index.
+//!
+//! The JSON index had to be parsed and pretty-printed in full on every
+//! command, which dominates latency once a repository tracks hundreds of
+//! thousands of image-sequence frames. The binary form is a flat sequence of
+//! length-prefixed records that is decoded straight out of an mmap.
+//!
+//! Layout:
+//! ```text
+//! "DINX" | version u32 | entry count u32 | base commit flag u8 | base commit [32]
+//! entries [count]                  sorted by path
+//! extensions...                    signature [4] | length u32 | data [length]
+//! checksum [32]                    BLAKE3 of all preceding bytes
+//! ```
+//!
+//! Entry:
+//! ```text
+//! path len u32 | path | content hash [32] | size u64 | mtime i64 | mode u32
+//! file type u8 | status u8 | storage u8 | flags u8
+//! stat: mtime secs i64 | mtime nanos u32 | ctime secs i64 | ctime nanos u32
+//!       size u64 | ino u64 | dev u64
+//! symlink target len u32 | symlink target
+//! [git oid len u32 | git oid]      present if flags & FLAG_GIT_OID
+//! chunk count u32 | chunks [count x (hash [32] | offset u64 | size u64)]
+//! ```
+//!
+//! Extensions carry data that most entries do not need. As in git, a
+//! signature starting with an upper-case letter is optional and may be
+//! skipped by readers that do not understand it; any other unknown signature
+//! is a hard error, because dropping it would lose data on the next write.
+//!
+//! Defined extensions:
+//! - `mp4m`: MP4 structure metadata, as records of
+//!   `entry ordinal u32 | JSON length u32 | JSON`.
+//! - `link`: the index is split, as with git's split index:
+//!   `shared checksum [32] | deleted count u32 | deleted paths [count x (len u32 | path)]`.
+//!
+//! Large indexes are written split so that updates are incremental. The
+//! bulk of the entries lives in `sharedindex.<checksum>` next to the index,
+//! an ordinary unsplit index file that is never modified. The index itself
+//! only holds the entries that differ from the shared index and the paths
+//! deleted from it, so staging a few files re-encodes and rewrites only
+//! those entries. Once more than a fifth of the shared entries changed, a
+//! new shared index is written and the old one removed.
+//!
+//! All integers are little-endian. As with git's `index.lock`, commands that
+//! modify the index hold an [`IndexLock`] from loading it until the new file
+//! is renamed into place, so readers never observe a partial index and a
+//! concurrent writer fails instead of losing another writer's update.
+
+use crate::core::chunk::ChunkRef;
+use crate::core::hash::{Hash, Hasher, HASH_SIZE};
+use crate::core::index::{FileStatus, FileType, Index, IndexEntry, Mp4Metadata, StatData, StatTime};
+use crate::core::storage_strategy::StorageStrategy;
+use memmap2::Mmap;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+const INDEX_MAGIC: &[u8; 4] = b"DINX";
+const INDEX_VERSION: u32 = 1;
+
+const EXT_MP4: &[u8; 4] = b"mp4m";
+const EXT_LINK: &[u8; 4] = b"link";
+
+/// Indexes with fewer entries are always written unsplit.
+const SPLIT_MIN_ENTRIES: usize = 1024;
+/// A new shared index is written once more than 1/N of its entries changed.
+const SPLIT_REWRITE_RATIO: usize = 5;
+
+const SHARED_INDEX_PREFIX: &str = "sharedindex.";
+
+const FLAG_GIT_OID: u8 = 1;
+
+/// Encoding an index file was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexFormat {
+    /// Current binary format, with the checksum from its trailer.
+    Binary { checksum: Hash },
+    /// Pre-binary JSON index; rewrite it to migrate.
+    LegacyJson,
+}
+
+/// Entries of the shared index a split index was read from or written against.
+#[derive(Debug)]
+pub struct SharedIndex {
+    checksum: Hash,
+    entries: BTreeMap<String, IndexEntry>,
+}
+
+/// Contents of a `link` extension.
+struct Link {
+    shared: Hash,
+    deleted: Vec<String>,
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn file_type_code(file_type: FileType) -> u8 {
+    match file_type {
+        FileType::Regular => 0,
+        FileType::Symlink => 1,
+        FileType::Directory => 2,
+        FileType::Other => 3,
+    }
+}
+
+fn file_type_from_code(code: u8) -> io::Result<FileType> {
+    Ok(match code {
+        0 => FileType::Regular,
+        1 => FileType::Symlink,
+        2 => FileType::Directory,
+        3 => FileType::Other,
+        _ => return Err(invalid(format!("unknown file type {}", code))),
+    })
+}
+
+fn status_code(status: FileStatus) -> u8 {
+    match status {
+        FileStatus::Added => 0,
+        FileStatus::Modified => 1,
+        FileStatus::Deleted => 2,
+        FileStatus::Renamed => 3,
+        FileStatus::TypeChanged => 4,
+        FileStatus::ModeChanged => 5,
+        FileStatus::Unchanged => 6,
+        FileStatus::Untracked => 7,
+    }
+}
+
+fn status_from_code(code: u8) -> io::Result<FileStatus> {
+    Ok(match code {
+        0 => FileStatus::Added,
+        1 => FileStatus::Modified,
+        2 => FileStatus::Deleted,
+        3 => FileStatus::Renamed,
+        4 => FileStatus::TypeChanged,
+        5 => FileStatus::ModeChanged,
+        6 => FileStatus::Unchanged,
+        7 => FileStatus::Untracked,
+        _ => return Err(invalid(format!("unknown file status {}", code))),
+    })
+}
+
+fn storage_code(storage: StorageStrategy) -> u8 {
+    match storage {
+        StorageStrategy::GitText => 0,
+        StorageStrategy::DitsChunk => 1,
+        StorageStrategy::Hybrid => 2,
+    }
+}
+
+fn storage_from_code(code: u8) -> io::Result<StorageStrategy> {
+    Ok(match code {
+        0 => StorageStrategy::GitText,
+        1 => StorageStrategy::DitsChunk,
+        2 => StorageStrategy::Hybrid,
+        _ => return Err(invalid(format!("unknown storage strategy {}", code))),
+    })
+}
+
+struct Encoder {
+    buf: Vec<u8>,
+}
+
+impl Encoder {
+    fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn i64(&mut self, v: i64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn hash(&mut self, hash: &Hash) {
+        self.buf.extend_from_slice(hash.as_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.u32(bytes.len() as u32);
+        self.buf.extend_from_slice(bytes);
+    }
+
+    fn time(&mut self, time: StatTime) {
+        self.i64(time.secs);
+        self.u32(time.nanos);
+    }
+
+    fn entry(&mut self, entry: &IndexEntry) {
+        self.bytes(entry.path.as_bytes());
+        self.hash(&entry.content_hash);
+        self.u64(entry.size);
+        self.i64(entry.mtime);
+        self.u32(entry.mode);
+        self.u8(file_type_code(entry.file_type));
+        self.u8(status_code(entry.status));
+        self.u8(storage_code(entry.storage));
+        self.u8(if entry.git_oid.is_some() { FLAG_GIT_OID } else { 0 });
+
+        self.time(entry.stat.mtime);
+        self.time(entry.stat.ctime);
+        self.u64(entry.stat.size);
+        self.u64(entry.stat.ino);
+        self.u64(entry.stat.dev);
+
+        self.bytes(entry.symlink_target.as_bytes());
+        if let Some(oid) = &entry.git_oid {
+            self.bytes(oid.as_bytes());
+        }
+
+        self.u32(entry.chunks.len() as u32);
+        for chunk in &entry.chunks {
+            self.hash(&chunk.hash);
+            self.u64(chunk.offset);
+            self.u64(chunk.size);
+        }
+    }
+
+    fn extension(&mut self, signature: &[u8; 4], data: &[u8]) {
+        self.buf.extend_from_slice(signature);
+        self.bytes(data);
+    }
+}
+
+/// Bounds-checked reader over an index buffer.
+struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, n: usize) -> io::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.buf.len())
+            .ok_or_else(|| invalid("truncated index"))?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn array<const N: usize>(&mut self) -> io::Result<[u8; N]> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N)?);
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    fn i64(&mut self) -> io::Result<i64> {
+        Ok(i64::from_le_bytes(self.array()?))
+    }
+
+    fn hash(&mut self) -> io::Result<Hash> {
+        Ok(Hash::from_bytes(self.array::<HASH_SIZE>()?))
+    }
+
+    fn bytes(&mut self) -> io::Result<&'a [u8]> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("index path is not UTF-8"))
+    }
+
+    fn time(&mut self) -> io::Result<StatTime> {
+        Ok(StatTime { secs: self.i64()?, nanos: self.u32()? })
+    }
+
+    fn entry(&mut self) -> io::Result<IndexEntry> {
+        let path = self.string()?;
+        let content_hash = self.hash()?;
+        let size = self.u64()?;
+        let mtime = self.i64()?;
+        let mode = self.u32()?;
+        let file_type = file_type_from_code(self.u8()?)?;
+        let status = status_from_code(self.u8()?)?;
+        let storage = storage_from_code(self.u8()?)?;
+        let flags = self.u8()?;
+
+        let stat = StatData {
+            mtime: self.time()?,
+            ctime: self.time()?,
+            size: self.u64()?,
+            ino: self.u64()?,
+            dev: self.u64()?,
+        };
+
+        let symlink_target = self.string()?;
+        let git_oid = if flags & FLAG_GIT_OID != 0 { Some(self.string()?) } else { None };
+
+        let count = self.u32()? as usize;
+        let mut chunks = Vec::with_capacity(count.min(self.buf.len() / (HASH_SIZE + 16)));
+        for _ in 0..count {
+            chunks.push(ChunkRef::new(self.hash()?, self.u64()?, self.u64()?));
+        }
+
+        Ok(IndexEntry {
+            path,
+            content_hash,
+            size,
+            mtime,
+            mode,
+            file_type,
+            symlink_target,
+            chunks,
+            status,
+            mp4_metadata: None,
+            storage,
+            git_oid,
+            stat,
+        })
+    }
+}
+
+/// Encode an index file holding `entries`, split against a shared index if `link` is set.
+fn encode(base_commit: Option<&Hash>, entries: &[&IndexEntry], link: Option<(&Hash, &[&str])>) -> Vec<u8> {
+    let mut enc = Encoder { buf: Vec::with_capacity(64 + entries.len() * 160) };
+    enc.buf.extend_from_slice(INDEX_MAGIC);
+    enc.u32(INDEX_VERSION);
+    enc.u32(entries.len() as u32);
+    match base_commit {
+        Some(hash) => {
+            enc.u8(1);
+            enc.hash(hash);
+        }
+        None => {
+            enc.u8(0);
+            enc.buf.extend_from_slice(&[0u8; HASH_SIZE]);
+        }
+    }
+
+    let mut mp4 = Encoder { buf: Vec::new() };
+    for (ordinal, entry) in entries.iter().enumerate() {
+        enc.entry(entry);
+        if let Some(meta) = &entry.mp4_metadata {
+            mp4.u32(ordinal as u32);
+            let json = serde_json::to_vec(meta).expect("mp4 metadata serialization should not fail");
+            mp4.bytes(&json);
+        }
+    }
+    if !mp4.buf.is_empty() {
+        enc.extension(EXT_MP4, &mp4.buf);
+    }
+    if let Some((shared, deleted)) = link {
+        let mut ext = Encoder { buf: Vec::new() };
+        ext.hash(shared);
+        ext.u32(deleted.len() as u32);
+        for path in deleted {
+            ext.bytes(path.as_bytes());
+        }
+        enc.extension(EXT_LINK, &ext.buf);
+    }
+
+    let checksum = Hasher::hash(&enc.buf);
+    enc.buf.extend_from_slice(checksum.as_bytes());
+    enc.buf
+}
+
+/// Decode an index file, returning its `link` extension if it is split.
+fn decode(data: &[u8]) -> io::Result<(Index, Option<Link>)> {
+    if data.len() < 4 + 4 + 4 + 1 + HASH_SIZE + HASH_SIZE || &data[0..4] != INDEX_MAGIC {
+        return Err(invalid("not a binary index"));
+    }
+    let (body, trailer) = data.split_at(data.len() - HASH_SIZE);
+    if Hasher::hash(body).as_bytes()[..] != trailer[..] {
+        return Err(invalid("index checksum mismatch"));
+    }
+
+    let mut dec = Decoder { buf: body, pos: 4 };
+    let version = dec.u32()?;
+    if version != INDEX_VERSION {
+        return Err(invalid(format!("unsupported index version {}", version)));
+    }
+    let count = dec.u32()? as usize;
+    let has_base = dec.u8()? != 0;
+    let base = dec.hash()?;
+
+    let mut index = Index::new();
+    index.base_commit = has_base.then_some(base);
+
+    // Entries are written in path order; keep the ordinal order for extensions.
+    let mut paths = Vec::with_capacity(count.min(body.len()));
+    for _ in 0..count {
+        let entry = dec.entry()?;
+        paths.push(entry.path.clone());
+        index.entries.insert(entry.path.clone(), entry);
+    }
+
+    let mut link = None;
+    while dec.pos < body.len() {
+        let signature: [u8; 4] = dec.array()?;
+        let data = dec.bytes()?;
+        match &signature {
+            EXT_MP4 => {
+                let mut ext = Decoder { buf: data, pos: 0 };
+                while ext.pos < data.len() {
+                    let ordinal = ext.u32()? as usize;
+                    let meta: Mp4Metadata = serde_json::from_slice(ext.bytes()?)
+                        .map_err(|e| invalid(format!("bad mp4 metadata: {}", e)))?;
+                    let entry = paths
+                        .get(ordinal)
+                        .and_then(|path| index.entries.get_mut(path))
+                        .ok_or_else(|| invalid("mp4 metadata for unknown entry"))?;
+                    entry.mp4_metadata = Some(meta);
+                }
+            }
+            EXT_LINK => {
+                let mut ext = Decoder { buf: data, pos: 0 };
+                let shared = ext.hash()?;
+                let count = ext.u32()? as usize;
+                let mut deleted = Vec::with_capacity(count.min(data.len()));
+                for _ in 0..count {
+                    deleted.push(ext.string()?);
+                }
+                link = Some(Link { shared, deleted });
+            }
+            sig if sig[0].is_ascii_uppercase() => {}
+            sig => {
+                return Err(invalid(format!(
+                    "unsupported required index extension '{}'",
+                    String::from_utf8_lossy(sig)
+                )));
+            }
+        }
+    }
+
+    Ok((index, link))
+}
+
+fn map_file(path: &Path) -> io::Result<Mmap> {
+    let file = File::open(path)?;
+    if file.metadata()?.len() == 0 {
+        return Err(invalid("empty index file"));
+    }
+    // SAFETY: index files are only ever replaced by rename, never
+    // modified in place, so the mapping cannot change under us.
+    unsafe { Mmap::map(&file) }
+}
+
+fn shared_index_path(path: &Path, checksum: &Hash) -> PathBuf {
+    path.with_file_name(format!("{}{}", SHARED_INDEX_PREFIX, checksum.to_hex()))
+}
+
+impl Index {
+    /// Encode the whole index in the binary on-disk format, unsplit.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let entries: Vec<_> = self.entries.values().collect();
+        encode(self.base_commit.as_ref(), &entries, None)
+    }
+
+    /// Decode an unsplit index in the binary on-disk format, verifying its checksum.
+    pub fn from_bytes(data: &[u8]) -> io::Result<Self> {
+        match decode(data)? {
+            (index, None) => Ok(index),
+            (_, Some(_)) => Err(invalid("split index must be read with its shared index")),
+        }
+    }
+
+    /// Read an index file, accepting both the binary format and legacy JSON.
+    pub fn read_file(path: &Path) -> io::Result<(Self, IndexFormat)> {
+        match Self::read_file_once(path) {
+            // A writer replaced the index and removed the shared index it
+            // was split against; the new index names the one that replaced it.
+            Err(e) if e.kind() == io::ErrorKind::NotFound && path.exists() => Self::read_file_once(path),
+            result => result,
+        }
+    }
+
+    fn read_file_once(path: &Path) -> io::Result<(Self, IndexFormat)> {
+        let data = map_file(path)?;
+        if data.starts_with(INDEX_MAGIC) {
+            let (mut index, link) = decode(&data)?;
+            if let Some(link) = link {
+                index.apply_shared(path, link)?;
+            }
+            return Ok((index, IndexFormat::Binary { checksum: index_checksum(&data) }));
+        }
+        let json = std::str::from_utf8(&data).map_err(|_| invalid("index is neither binary nor JSON"))?;
+        let index = Self::from_json(json).map_err(|e| invalid(format!("invalid JSON index: {}", e)))?;
+        Ok((index, IndexFormat::LegacyJson))
+    }
+
+    /// Fill in the entries a split index file leaves to its shared index.
+    fn apply_shared(&mut self, path: &Path, link: Link) -> io::Result<()> {
+        let data = map_file(&shared_index_path(path, &link.shared))?;
+        if index_checksum(&data) != link.shared {
+            return Err(invalid("shared index does not match its name"));
+        }
+        let shared = Self::from_bytes(&data)?.entries;
+
+        let mut entries = shared.clone();
+        for path in &link.deleted {
+            entries.remove(path);
+        }
+        entries.append(&mut self.entries);
+        self.entries = entries;
+        self.shared = Some(Arc::new(SharedIndex { checksum: link.shared, entries: shared }));
+        Ok(())
+    }
+
+    /// Encode the index for writing.
+    ///
+    /// Returns the index file and, if a new shared index has to be written
+    /// first, its contents.
+    fn split_into_bytes(&mut self) -> (Vec<u8>, Option<Vec<u8>>) {
+        if let Some(shared) = &self.shared {
+            let changed: Vec<_> = self
+                .entries
+                .values()
+                .filter(|entry| shared.entries.get(&entry.path) != Some(*entry))
+                .collect();
+            let deleted: Vec<_> = shared
+                .entries
+                .keys()
+                .filter(|path| !self.entries.contains_key(*path))
+                .map(String::as_str)
+                .collect();
+            if (changed.len() + deleted.len()) * SPLIT_REWRITE_RATIO <= shared.entries.len() {
+                let bytes = encode(self.base_commit.as_ref(), &changed, Some((&shared.checksum, &deleted)));
+                return (bytes, None);
+            }
+        }
+
+        if self.entries.len() < SPLIT_MIN_ENTRIES {
+            self.shared = None;
+            return (self.to_bytes(), None);
+        }
+        let entries: Vec<_> = self.entries.values().collect();
+        let shared_bytes = encode(None, &entries, None);
+        let checksum = index_checksum(&shared_bytes);
+        let bytes = encode(self.base_commit.as_ref(), &[], Some((&checksum, &[])));
+        self.shared = Some(Arc::new(SharedIndex { checksum, entries: self.entries.clone() }));
+        (bytes, Some(shared_bytes))
+    }
+
+    /// Read an index file in either format.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Self::read_file(path).map(|(index, _)| index)
+    }
+
+    /// Atomically write the index in the binary format.
+    ///
+    /// Returns the checksum of the written file.
+    pub fn write_file(&self, path: &Path) -> io::Result<Hash> {
+        let (checksum, _) = IndexLock::acquire(path)?.commit(&mut self.clone())?;
+        Ok(checksum)
+    }
+}
+
+/// Exclusive lock on an index file, like git's `index.lock`.
+///
+/// Take it before loading an index that is going to be modified and commit
+/// the modified index through it. Dropping the lock without committing
+/// leaves the index untouched.
+pub struct IndexLock {
+    path: PathBuf,
+    lock: PathBuf,
+    file: Option<File>,
+}
+
+impl IndexLock {
+    /// Lock the index file at `path`.
+    ///
+    /// Fails with [`io::ErrorKind::AlreadyExists`] if the lock file is present,
+    /// i.e. another process is updating the index or one crashed while doing so.
+    pub fn acquire(path: &Path) -> io::Result<Self> {
+        let lock = path.with_extension("lock");
+        match OpenOptions::new().read(true).write(true).create_new(true).open(&lock) {
+            Ok(file) => Ok(Self { path: path.to_path_buf(), lock, file: Some(file) }),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(io::Error::new(
+                e.kind(),
+                format!(
+                    "{} exists: another dits process is updating the index; remove it if none is running",
+                    lock.display()
+                ),
+            )),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write `index` and rename it into place, releasing the lock.
+    ///
+    /// Entries modified in the same tick as the write are racily clean and
+    /// lose their stat data. `index` is left as written, with its write time
+    /// set. If the file already holds exactly this index it is not rewritten.
+    /// Returns the checksum and mtime of the index file.
+    pub fn commit(mut self, index: &mut Index) -> io::Result<(Hash, SystemTime)> {
+        let had_shared = index.shared.is_some();
+        let (mut bytes, mut shared) = index.split_into_bytes();
+        if shared.is_none() && !index.has_racy_entries() && on_disk_checksum(&self.path) == Some(index_checksum(&bytes))
+        {
+            let mtime = fs::metadata(&self.path)?.modified()?;
+            index.set_written_at(mtime);
+            return Ok((index_checksum(&bytes), mtime));
+        }
+
+        let file = self.file.as_mut().expect("index lock is committed once");
+        let mut new_shared = false;
+        let mut mtime;
+        loop {
+            if let Some(shared_bytes) = shared.take() {
+                write_shared_index(&self.path, &shared_bytes)?;
+                new_shared = true;
+            }
+            file.set_len(0)?;
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&bytes)?;
+            file.sync_all()?;
+            mtime = file.metadata()?.modified()?;
+
+            // Entries modified in the same tick as the write are racily
+            // clean: write them again without stat data so they get rehashed
+            if !index.smudge_racy_entries(mtime) {
+                break;
+            }
+            (bytes, shared) = index.split_into_bytes();
+        }
+        fs::rename(&self.lock, &self.path)?;
+        self.file = None;
+
+        if new_shared || (had_shared && index.shared.is_none()) {
+            remove_stale_shared_indexes(&self.path, index.shared.as_ref().map(|shared| &shared.checksum));
+        }
+        index.set_written_at(mtime);
+        Ok((index_checksum(&bytes), mtime))
+    }
+}
+
+impl Drop for IndexLock {
+    fn drop(&mut self) {
+        // Once renamed into place, the lock file may belong to another process
+        if self.file.is_some() {
+            let _ = fs::remove_file(&self.lock);
+        }
+    }
+}
+
+/// Trailer checksum of the binary index file at `path`, if there is one.
+fn on_disk_checksum(path: &Path) -> Option<Hash> {
+    let mut file = File::open(path).ok()?;
+    let mut magic = [0u8; 4];
+    file.read_exact(&mut magic).ok()?;
+    if &magic != INDEX_MAGIC {
+        return None;
+    }
+    let mut trailer = [0u8; HASH_SIZE];
+    file.seek(SeekFrom::End(-(HASH_SIZE as i64))).ok()?;
+    file.read_exact(&mut trailer).ok()?;
+    Some(Hash::from_bytes(trailer))
+}
+
+/// Write a shared index next to the index at `path`, unless it already exists.
+fn write_shared_index(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let shared_path = shared_index_path(path, &index_checksum(bytes));
+    if shared_path.exists() {
+        return Ok(());
+    }
+    let mut tmp = shared_path.clone().into_os_string();
+    tmp.push(".tmp");
+    let mut file = File::create(&tmp)?;
+    file.write_all(bytes)?;
+    file.sync_all()?;
+    fs::rename(&tmp, &shared_path)
+}
+
+/// Remove shared indexes other than `keep` from beside the index at `path`.
+fn remove_stale_shared_indexes(path: &Path, keep: Option<&Hash>) {
+    let keep = keep.map(|checksum| format!("{}{}", SHARED_INDEX_PREFIX, checksum.to_hex()));
+    let Some(Ok(dir)) = path.parent().map(fs::read_dir) else {
+        return;
+    };
+    for entry in dir.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with(SHARED_INDEX_PREFIX) && keep.as_deref() != Some(&*name) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// Checksum from the trailer of an encoded index.
+pub fn index_checksum(data: &[u8]) -> Hash {
+    let mut checksum = [0u8; HASH_SIZE];
+    checksum.copy_from_slice(&data[data.len() - HASH_SIZE..]);
+    Hash::from_bytes(checksum)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::index::StoredAtom;
+    use tempfile::tempdir;
+
+    fn sample_index() -> Index {
+        let mut index = Index::from_commit(Hasher::hash(b"base"));
+
+        let mut video = IndexEntry::new_mp4(
+            "clip.mp4".to_string(),
+            Hasher::hash(b"clip"),
+            4096,
+            1_700_000_000,
+            0o644,
+            FileType::Regular,
+            String::new(),
+            vec![ChunkRef::new(Hasher::hash(b"c1"), 0, 2048), ChunkRef::new(Hasher::hash(b"c2"), 2048, 2048)],
+            Mp4Metadata {
+                moov_hash: Some(Hasher::hash(b"moov")),
+                moov_size: 100,
+                mdat_size: 4096,
+                atom_order: vec!["ftyp".into(), "moov".into(), "mdat".into()],
+                other_atoms: vec![StoredAtom { atom_type: "free".into(), hash: None, inline_data: Some(vec![0; 8]) }],
+                ..Default::default()
+            },
+        );
+        video.stat = StatData {
+            mtime: StatTime { secs: 10, nanos: 5 },
+            ctime: StatTime { secs: 11, nanos: 6 },
+            size: 4096,
+            ino: 42,
+            dev: 7,
+        };
+        index.stage(video);
+
+        let mut text = IndexEntry::new_text(
+            "notes.txt".to_string(),
+            Hasher::hash(b"notes"),
+            5,
+            1_700_000_001,
+            0o755,
+            FileType::Regular,
+            String::new(),
+            "0123456789abcdef0123456789abcdef01234567".to_string(),
+        );
+        text.status = FileStatus::Unchanged;
+        index.stage(text);
+
+        index.stage(IndexEntry::new(
+            "link".to_string(),
+            Hasher::hash(b"target"),
+            6,
+            0,
+            0o777,
+            FileType::Symlink,
+            "target".to_string(),
+            vec![],
+        ));
+
+        index
+    }
+
+    #[test]
+    fn test_binary_roundtrip() {
+        let index = sample_index();
+        let bytes = index.to_bytes();
+        let parsed = Index::from_bytes(&bytes).unwrap();
+
+        assert_eq!(parsed.base_commit, index.base_commit);
+        assert_eq!(parsed.len(), 3);
+        // Compare through JSON, which covers every field.
+        assert_eq!(parsed.to_json(), index.to_json());
+        assert_eq!(index_checksum(&bytes), Hasher::hash(&bytes[..bytes.len() - HASH_SIZE]));
+    }
+
+    #[test]
+    fn test_write_refuses_existing_lock() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("index");
+        let index = sample_index();
+        index.write_file(&path).unwrap();
+        assert!(!temp.path().join("index.lock").exists());
+
+        fs::write(temp.path().join("index.lock"), b"other writer").unwrap();
+        let err = Index::new().write_file(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(Index::load(&path).unwrap().len(), index.len());
+        assert_eq!(fs::read(temp.path().join("index.lock")).unwrap(), b"other writer");
+    }
+
+    #[test]
+    fn test_lock_is_held_until_commit() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("index");
+        sample_index().write_file(&path).unwrap();
+
+        // A second writer cannot load and save while the first holds the lock
+        let lock = IndexLock::acquire(&path).unwrap();
+        let mut index = Index::load(&path).unwrap();
+        assert_eq!(IndexLock::acquire(&path).err().unwrap().kind(), io::ErrorKind::AlreadyExists);
+        index.unstage("link");
+        lock.commit(&mut index).unwrap();
+        assert!(!temp.path().join("index.lock").exists());
+        assert!(!Index::load(&path).unwrap().is_staged("link"));
+
+        // Dropping the lock leaves the index as it was
+        let lock = IndexLock::acquire(&path).unwrap();
+        drop(lock);
+        assert!(!temp.path().join("index.lock").exists());
+        assert_eq!(Index::load(&path).unwrap().len(), 2);
+    }
+
+    fn shared_indexes(dir: &Path) -> Vec<String> {
+        let mut names: Vec<_> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with(SHARED_INDEX_PREFIX))
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_large_index_updates_are_incremental() {
+        let temp = tempdir().unwrap();
+        let path = temp.path().join("index");
+        let frame = |n: usize| {
+            IndexEntry::new(
+                format!("shots/frame_{:05}.exr", n),
+                Hasher::hash(&n.to_le_bytes()),
+                1 << 20,
+                0,
+                0o644,
+                FileType::Regular,
+                String::new(),
+                vec![ChunkRef::new(Hasher::hash(&n.to_le_bytes()), 0, 1 << 20)],
+            )
+        };
+        let mut index = Index::from_commit(Hasher::hash(b"base"));
+        for n in 0..2000 {
+            index.stage(frame(n));
+        }
+        index.write_file(&path).unwrap();
+        let shared = shared_indexes(temp.path());
+        assert_eq!(shared.len(), 1);
+        let full_size = fs::metadata(temp.path().join(&shared[0])).unwrap().len();
+
+        // Staging a few files rewrites only those entries
+        let lock = IndexLock::acquire(&path).unwrap();
+        let mut index = Index::load(&path).unwrap();
+        let mut changed = frame(7);
+        changed.content_hash = Hasher::hash(b"retouched");
+        index.stage(changed.clone());
+        index.unstage("shots/frame_00008.exr");
+        index.stage(frame(5000));
+        lock.commit(&mut index).unwrap();
+        assert_eq!(shared_indexes(temp.path()), shared);
+        assert!(fs::metadata(&path).unwrap().len() * 100 < full_size);
+
+        let loaded = Index::load(&path).unwrap();
+        assert_eq!(loaded.to_json(), index.to_json());
+        assert_eq!(loaded.get("shots/frame_00007.exr").unwrap().content_hash, changed.content_hash);
+        assert!(!loaded.is_staged("shots/frame_00008.exr"));
+        assert!(loaded.is_staged("shots/frame_05000.exr"));
+        assert!(Index::from_bytes(&fs::read(&path).unwrap()).is_err());
+
+        // Once much of the shared index is out of date it is replaced
+        let lock = IndexLock::acquire(&path).unwrap();
+        let mut index = Index::load(&path).unwrap();
+        for n in 0..600 {
+            index.unstage(&format!("shots/frame_{:05}.exr", n));
+        }
+        lock.commit(&mut index).unwrap();
+        let replaced = shared_indexes(temp.path());
+        assert_eq!(replaced.len(), 1);
+        assert_ne!(replaced, shared);
+        assert_eq!(Index::load(&path).unwrap().to_json(), index.to_json());
+
+        // Small indexes are written whole again
+        let lock = IndexLock::acquire(&path).unwrap();
+        let mut index = Index::load(&path).unwrap();
+        index.entries.retain(|path, _| path.as_str() < "shots/frame_01100.exr");
+        lock.commit(&mut index).unwrap();
+        assert!(shared_indexes(temp.path()).is_empty());
+        assert_eq!(Index::from_bytes(&fs::read(&path).unwrap()).unwrap().len(), 500);
+    }
+
+    #[test]
+    fn test_corruption_is_detected() {
+        let mut bytes = sample_index().to_bytes();
+        let mid = bytes.len() / 2;
+        bytes[mid] ^= 0xff;
+        assert!(Index::from_bytes(&bytes).is_err());
+        assert!(Index::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_unknown_extensions() {
+        let index = Index::new();
+        let with_ext = |sig: &[u8; 4]| {
+            let bytes = index.to_bytes();
+            let mut enc = Encoder { buf: bytes[..bytes.len() - HASH_SIZE].to_vec() };
+            enc.extension(sig, b"payload");
+            let checksum = Hasher::hash(&enc.buf);
+            enc.buf.extend_from_slice(checksum.as_bytes());
+            enc.buf
+        };
+
+        // Optional extensions are skipped, required ones are refused.
+        assert!(Index::from_bytes(&with_ext(b"TREE")).is_ok());
+        assert!(Index::from_bytes(&with_ext(b"link")).is_err());
+        assert!(Index::from_bytes(&with_ext(b"zzzz")).is_err());
+    }
+
+    #[test]
+    fn test_read_legacy_json() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("index");
+        let index = sample_index();
+
+        fs::write(&path, index.to_json()).unwrap();
+        let (parsed, format) = Index::read_file(&path).unwrap();
+        assert_eq!(format, IndexFormat::LegacyJson);
+        assert_eq!(parsed.to_json(), index.to_json());
+
+        let checksum = parsed.write_file(&path).unwrap();
+        let (parsed, format) = Index::read_file(&path).unwrap();
+        assert_eq!(format, IndexFormat::Binary { checksum });
+        assert_eq!(parsed.to_json(), index.to_json());
+    }
+}
@@ -161,6 +161,23 @@ impl ManifestEntry {
     }
 }
 
+/// Check that a manifest path names a file inside the work tree.
+///
+/// Manifests also arrive from remotes and peers, and checkout joins their
+/// paths onto the work tree, so empty, absolute, `.`, `..` and `.dits`
+/// components are rejected.
+pub fn is_valid_manifest_path(path: &str) -> bool {
+    !path.is_empty()
+        && !std::path::Path::new(path).is_absolute()
+        && path.split(['/', '\\']).all(|part| {
+            !part.is_empty()
+                && part != "."
+                && part != ".."
+                && !part.eq_ignore_ascii_case(".dits")
+                && !part.contains('\0')
+        })
+}
+
 /// A manifest (tree) object containing all files in a commit.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Manifest {
@@ -206,6 +223,14 @@ impl Manifest {
         self.entries.is_empty()
     }
 
+    /// First entry path that could escape the work tree, if any.
+    pub fn invalid_path(&self) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(key, entry)| !is_valid_manifest_path(key) || entry.path != **key)
+            .map(|(key, _)| key.as_str())
+    }
+
     /// Get all file paths.
     pub fn paths(&self) -> impl Iterator<Item = &str> {
         self.entries.keys().map(|s| s.as_str())
@@ -285,6 +310,22 @@ mod tests {
         assert_eq!(manifest.len(), 0);
     }
 
+    #[test]
+    fn test_manifest_paths_stay_in_work_tree() {
+        for path in ["", "/etc/passwd", "../x", "a/../../x", "a//b", "./a", "a/.", ".dits/HEAD", "a/.DITS/x", "..\\x"] {
+            assert!(!is_valid_manifest_path(path), "{:?}", path);
+        }
+        for path in ["a.txt", "footage/clip.mov", ".ditsattributes", "a..b/c"] {
+            assert!(is_valid_manifest_path(path), "{:?}", path);
+        }
+
+        let mut manifest = Manifest::new();
+        manifest.add(ManifestEntry::new("ok.txt".to_string(), 1, Hash::ZERO, vec![]));
+        assert_eq!(manifest.invalid_path(), None);
+        manifest.add(ManifestEntry::new("../../.bashrc".to_string(), 1, Hash::ZERO, vec![]));
+        assert_eq!(manifest.invalid_path(), Some("../../.bashrc"));
+    }
+
     #[test]
     fn test_manifest_hash_deterministic() {
         let mut manifest = Manifest::new();
@@ -20,20 +20,24 @@ mod filetype;
 mod manifest;
 mod commit;
 mod index;
+mod index_file;
 mod ignore;
+mod attributes;
 mod storage_strategy;
 
 // Storage Strategy Layer (Phase 3.6)
 pub use storage_strategy::{StorageStrategy, FileClassifier};
 
 // Universal Layer exports
-pub use hash::{Hash, Hasher};
+pub use hash::{Hash, Hasher, HashingReader, HASH_SIZE};
 #[allow(unused_imports)]
-pub use chunk::{Chunk, ChunkRef, ChunkerConfig, chunk_data, chunk_data_with_refs, chunk_data_parallel, chunk_data_with_refs_parallel};
+pub use chunk::{Chunk, ChunkRef, ChunkerConfig, chunk_data, chunk_data_with_refs, chunk_data_parallel, chunk_data_with_refs_parallel, chunk_reader_with_refs};
 pub use manifest::{Manifest, ManifestEntry, FileMode};
 pub use commit::{Commit, Author};
-pub use index::{Index, IndexEntry, FileStatus, FileType, Mp4Metadata, StoredAtom};
+pub use index::{Index, IndexEntry, FileStatus, FileType, Mp4Metadata, StatData, StoredAtom};
+pub use index_file::{IndexFormat, IndexLock};
 pub use ignore::IgnoreMatcher;
+pub use attributes::{AttributeMatcher, DiffDriver, MergeDriver, PathAttributes};
 
 // Smart Layer exports
 #[allow(unused_imports)]
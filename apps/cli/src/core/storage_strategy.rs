@@ -5,6 +5,7 @@
 //! - **DitsChunk**: FastCDC chunking for binary/media files (deduplication, keyframe alignment)
 //! - **Hybrid**: Both engines for NLE projects (Git for metadata, Dits for payload)
 
+use super::AttributeMatcher;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
 
@@ -80,7 +81,8 @@ impl StorageStrategy {
 /// 2. Extension-based classification
 /// 3. Content analysis (if content available)
 pub struct FileClassifier {
-    // Future: attribute cache from .ditsattributes
+    /// Rules from .ditsattributes.
+    attributes: AttributeMatcher,
 }
 
 impl Default for FileClassifier {
@@ -92,7 +94,17 @@ impl Default for FileClassifier {
 impl FileClassifier {
     /// Create a new file classifier.
     pub fn new() -> Self {
-        Self {}
+        Self::with_attributes(AttributeMatcher::empty())
+    }
+
+    /// Create a classifier that honours `storage=` attributes.
+    pub fn with_attributes(attributes: AttributeMatcher) -> Self {
+        Self { attributes }
+    }
+
+    /// The attribute rules this classifier was created with.
+    pub fn attributes(&self) -> &AttributeMatcher {
+        &self.attributes
     }
 
     /// Classify a file and determine its storage strategy.
@@ -101,6 +113,11 @@ impl FileClassifier {
     /// * `path` - File path (for extension-based detection)
     /// * `content` - Optional file content (for content-based detection)
     pub fn classify(&self, path: &Path, content: Option<&[u8]>) -> StorageStrategy {
+        // 0. Explicit .ditsattributes override
+        if let Some(strategy) = self.attributes.get(path).storage {
+            return strategy;
+        }
+
         // 1. Check extension first (most common case)
         if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
             if let Some(strategy) = Self::classify_by_extension(ext) {
@@ -356,8 +373,14 @@ impl FileClassifier {
             return StorageStrategy::DitsChunk;
         }
 
-        // Try to parse as UTF-8
-        if let Ok(text) = std::str::from_utf8(content) {
+        // Try to parse as UTF-8. Content may be a leading sample of a larger
+        // file, so a multi-byte character cut off at the end is not an error.
+        let text = match std::str::from_utf8(content) {
+            Ok(text) => Some(text),
+            Err(e) if e.error_len().is_none() => std::str::from_utf8(&content[..e.valid_up_to()]).ok(),
+            Err(_) => None,
+        };
+        if let Some(text) = text {
             // Check for reasonable line lengths (text heuristic)
             let lines: Vec<&str> = text.lines().collect();
             if !lines.is_empty() {
@@ -491,6 +514,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_attribute_override() {
+        let attributes = AttributeMatcher::parse(
+            Path::new("/repo"),
+            "*.xml storage=chunk\n*.studiocache storage=git\n",
+        );
+        let classifier = FileClassifier::with_attributes(attributes);
+
+        assert_eq!(
+            classifier.classify(Path::new("/repo/exports/cut.xml"), Some(b"<xml/>")),
+            StorageStrategy::DitsChunk
+        );
+        assert_eq!(
+            classifier.classify(Path::new("sim.studiocache"), Some(&[0, 1, 2])),
+            StorageStrategy::GitText
+        );
+        assert_eq!(
+            classifier.classify(Path::new("config.json"), None),
+            StorageStrategy::GitText
+        );
+    }
+
     #[test]
     fn test_strategy_properties() {
         assert!(StorageStrategy::GitText.supports_line_diff());
@@ -0,0 +1,308 @@
+//! Minimal Compound File Binary (structured storage) reader.
+//!
+//! AAF files are stored in Microsoft's Compound File Binary format: a FAT
+//! filesystem inside a single file. This reader only lists streams and reads
+//! their contents; it never writes.
+
+use super::parser::ParseError;
+use byteorder::{ByteOrder, LittleEndian};
+use std::collections::HashSet;
+
+/// File signature.
+const SIGNATURE: [u8; 8] = [0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+/// Last sector of a chain.
+const END_OF_CHAIN: u32 = 0xFFFF_FFFE;
+/// Number of DIFAT entries stored in the header.
+const HEADER_DIFAT_ENTRIES: usize = 109;
+/// Size of a directory entry.
+const DIR_ENTRY_SIZE: usize = 128;
+
+/// Directory entry object types.
+const STREAM_OBJECT: u8 = 2;
+const ROOT_OBJECT: u8 = 5;
+
+/// A compound file loaded into memory.
+pub struct CompoundFile {
+    data: Vec<u8>,
+    sector_size: usize,
+    mini_sector_size: usize,
+    mini_cutoff: u64,
+    fat: Vec<u32>,
+    mini_fat: Vec<u32>,
+    mini_stream: Vec<u8>,
+    entries: Vec<DirEntry>,
+}
+
+/// A directory entry.
+struct DirEntry {
+    name: String,
+    object_type: u8,
+    start: u32,
+    size: u64,
+}
+
+fn invalid(msg: &str) -> ParseError {
+    ParseError::InvalidProject(format!("compound file: {}", msg))
+}
+
+impl CompoundFile {
+    /// Whether data starts with the compound file signature.
+    pub fn is_compound_file(data: &[u8]) -> bool {
+        data.starts_with(&SIGNATURE)
+    }
+
+    /// Parse a compound file.
+    pub fn parse(data: Vec<u8>) -> Result<Self, ParseError> {
+        if data.len() < 512 || !Self::is_compound_file(&data) {
+            return Err(invalid("bad signature"));
+        }
+
+        let sector_shift = LittleEndian::read_u16(&data[0x1E..]);
+        let mini_shift = LittleEndian::read_u16(&data[0x20..]);
+        if !(7..=16).contains(&sector_shift) || mini_shift >= sector_shift {
+            return Err(invalid("bad sector size"));
+        }
+
+        let mut file = Self {
+            sector_size: 1 << sector_shift,
+            mini_sector_size: 1 << mini_shift,
+            mini_cutoff: LittleEndian::read_u32(&data[0x38..]).into(),
+            fat: Vec::new(),
+            mini_fat: Vec::new(),
+            mini_stream: Vec::new(),
+            entries: Vec::new(),
+            data,
+        };
+
+        // The FAT sectors are listed in the header, then in a chain of DIFAT sectors
+        let mut fat_sectors: Vec<u32> = (0..HEADER_DIFAT_ENTRIES)
+            .map(|i| LittleEndian::read_u32(&file.data[0x4C + i * 4..]))
+            .collect();
+        let mut difat = LittleEndian::read_u32(&file.data[0x44..]);
+        let per_sector = file.sector_size / 4;
+        let mut guard = 0;
+        while difat < END_OF_CHAIN && guard < file.sector_count() {
+            let sector = file.sector(difat)?;
+            fat_sectors.extend((0..per_sector - 1).map(|i| LittleEndian::read_u32(&sector[i * 4..])));
+            difat = LittleEndian::read_u32(&sector[(per_sector - 1) * 4..]);
+            guard += 1;
+        }
+        // The header's FAT sector count is untrusted; it can only name sectors
+        // that are listed and exist
+        let fat_count = (LittleEndian::read_u32(&file.data[0x2C..]) as usize)
+            .min(fat_sectors.len())
+            .min(file.sector_count());
+        let mut fat = Vec::with_capacity(fat_count * per_sector);
+        for &sector in fat_sectors.iter().filter(|&&s| s < END_OF_CHAIN).take(fat_count) {
+            let sector = file.sector(sector)?;
+            fat.extend((0..per_sector).map(|i| LittleEndian::read_u32(&sector[i * 4..])));
+        }
+        file.fat = fat;
+
+        let first_mini_fat = LittleEndian::read_u32(&file.data[0x3C..]);
+        let mini_fat = file.read_chain(first_mini_fat, None)?;
+        file.mini_fat = (0..mini_fat.len() / 4).map(|i| LittleEndian::read_u32(&mini_fat[i * 4..])).collect();
+
+        let directory = file.read_chain(LittleEndian::read_u32(&file.data[0x30..]), None)?;
+        file.entries = directory.chunks_exact(DIR_ENTRY_SIZE).map(DirEntry::parse).collect();
+
+        // The root entry owns the mini stream holding all small streams
+        let root = file.entries.iter().find(|e| e.object_type == ROOT_OBJECT).ok_or_else(|| invalid("no root"))?;
+        let (start, size) = (root.start, root.size);
+        file.mini_stream = file.read_chain(start, Some(size))?;
+
+        Ok(file)
+    }
+
+    /// Names and contents of every stream.
+    pub fn streams(&self) -> Vec<(String, Vec<u8>)> {
+        self.entries
+            .iter()
+            .filter(|e| e.object_type == STREAM_OBJECT)
+            .filter_map(|e| self.read_stream(e).ok().map(|data| (e.name.clone(), data)))
+            .collect()
+    }
+
+    fn read_stream(&self, entry: &DirEntry) -> Result<Vec<u8>, ParseError> {
+        if entry.size < self.mini_cutoff {
+            self.read_mini_chain(entry.start, entry.size)
+        } else {
+            self.read_chain(entry.start, Some(entry.size))
+        }
+    }
+
+    fn sector_count(&self) -> usize {
+        self.data.len() / self.sector_size
+    }
+
+    fn sector(&self, index: u32) -> Result<&[u8], ParseError> {
+        let start = (index as usize + 1) * self.sector_size;
+        self.data.get(start..start + self.sector_size).ok_or_else(|| invalid("sector out of range"))
+    }
+
+    /// Follow a FAT chain, truncating to `size` if given.
+    fn read_chain(&self, mut sector: u32, size: Option<u64>) -> Result<Vec<u8>, ParseError> {
+        let mut out = Vec::new();
+        let mut visited = HashSet::new();
+        while sector < END_OF_CHAIN {
+            if !visited.insert(sector) {
+                return Err(invalid("FAT chain loop"));
+            }
+            out.extend_from_slice(self.sector(sector)?);
+            sector = *self.fat.get(sector as usize).ok_or_else(|| invalid("FAT index out of range"))?;
+        }
+        if let Some(size) = size {
+            out.truncate(size as usize);
+        }
+        Ok(out)
+    }
+
+    /// Follow a mini FAT chain within the mini stream.
+    fn read_mini_chain(&self, mut sector: u32, size: u64) -> Result<Vec<u8>, ParseError> {
+        let mut out = Vec::new();
+        let mut visited = HashSet::new();
+        while sector < END_OF_CHAIN && (out.len() as u64) < size {
+            if !visited.insert(sector) {
+                return Err(invalid("mini FAT chain loop"));
+            }
+            let start = sector as usize * self.mini_sector_size;
+            let chunk = self
+                .mini_stream
+                .get(start..start + self.mini_sector_size)
+                .ok_or_else(|| invalid("mini sector out of range"))?;
+            out.extend_from_slice(chunk);
+            sector = *self.mini_fat.get(sector as usize).ok_or_else(|| invalid("mini FAT index out of range"))?;
+        }
+        out.truncate(size as usize);
+        Ok(out)
+    }
+}
+
+impl DirEntry {
+    fn parse(raw: &[u8]) -> Self {
+        let name_len = (LittleEndian::read_u16(&raw[64..]) as usize).min(64);
+        let units: Vec<u16> = (0..name_len / 2).map(|i| LittleEndian::read_u16(&raw[i * 2..])).collect();
+        Self {
+            name: String::from_utf16_lossy(&units).trim_end_matches('\0').to_string(),
+            object_type: raw[66],
+            start: LittleEndian::read_u32(&raw[116..]),
+            // Version 3 files only define the low 32 bits
+            size: LittleEndian::read_u32(&raw[120..]).into(),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    const FREE: u32 = 0xFFFF_FFFF;
+    const FAT_SECTOR: u32 = 0xFFFF_FFFD;
+
+    fn dir_entry(name: &str, object_type: u8, start: u32, size: u32) -> Vec<u8> {
+        let mut entry = vec![0u8; DIR_ENTRY_SIZE];
+        let units: Vec<u16> = name.encode_utf16().chain([0]).collect();
+        for (i, unit) in units.iter().enumerate() {
+            LittleEndian::write_u16(&mut entry[i * 2..], *unit);
+        }
+        LittleEndian::write_u16(&mut entry[64..], (units.len() * 2) as u16);
+        entry[66] = object_type;
+        LittleEndian::write_u32(&mut entry[116..], start);
+        LittleEndian::write_u32(&mut entry[120..], size);
+        entry
+    }
+
+    fn u32s(values: &[u32], sector_size: usize) -> Vec<u8> {
+        let mut out = vec![0xFF; sector_size];
+        for (i, v) in values.iter().enumerate() {
+            LittleEndian::write_u32(&mut out[i * 4..], *v);
+        }
+        out
+    }
+
+    /// Build a version 3 compound file holding a small stream (in the mini
+    /// stream) and a large one (in regular sectors).
+    pub(crate) fn build(small: &[u8], large: &[u8]) -> Vec<u8> {
+        assert!(small.len() <= 512 && large.len() >= 4096);
+        let large_sectors = large.len().div_ceil(512) as u32;
+
+        // Sectors: 0 FAT, 1 directory, 2 mini FAT, 3 mini stream, 4.. large stream
+        let mut fat = vec![FAT_SECTOR, END_OF_CHAIN, END_OF_CHAIN, END_OF_CHAIN];
+        fat.extend((5..4 + large_sectors).chain([END_OF_CHAIN]));
+        let small_sectors = small.len().div_ceil(64) as u32;
+        let mut mini_fat: Vec<u32> = (1..small_sectors).collect();
+        mini_fat.push(END_OF_CHAIN);
+
+        let mut header = vec![0u8; 512];
+        header[..8].copy_from_slice(&SIGNATURE);
+        LittleEndian::write_u16(&mut header[0x1A..], 3);
+        LittleEndian::write_u16(&mut header[0x1C..], 0xFFFE);
+        LittleEndian::write_u16(&mut header[0x1E..], 9);
+        LittleEndian::write_u16(&mut header[0x20..], 6);
+        LittleEndian::write_u32(&mut header[0x2C..], 1);
+        LittleEndian::write_u32(&mut header[0x30..], 1);
+        LittleEndian::write_u32(&mut header[0x38..], 4096);
+        LittleEndian::write_u32(&mut header[0x3C..], 2);
+        LittleEndian::write_u32(&mut header[0x40..], 1);
+        LittleEndian::write_u32(&mut header[0x44..], END_OF_CHAIN);
+        let mut difat = vec![0];
+        difat.resize(HEADER_DIFAT_ENTRIES, FREE);
+        for (i, v) in difat.iter().enumerate() {
+            LittleEndian::write_u32(&mut header[0x4C + i * 4..], *v);
+        }
+
+        let directory = [
+            dir_entry("Root Entry", ROOT_OBJECT, 3, 512),
+            dir_entry("small", STREAM_OBJECT, 0, small.len() as u32),
+            dir_entry("large", STREAM_OBJECT, 4, large.len() as u32),
+            vec![0u8; DIR_ENTRY_SIZE],
+        ]
+        .concat();
+        let mut mini_stream = small.to_vec();
+        mini_stream.resize(512, 0);
+        let mut large = large.to_vec();
+        large.resize(large_sectors as usize * 512, 0);
+
+        [header, u32s(&fat, 512), directory, u32s(&mini_fat, 512), mini_stream, large].concat()
+    }
+
+    #[test]
+    fn test_read_streams() {
+        let large: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let file = CompoundFile::parse(build(b"hello mini stream", &large)).unwrap();
+        let streams = file.streams();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[0], ("small".to_string(), b"hello mini stream".to_vec()));
+        assert_eq!(streams[1].0, "large");
+        assert_eq!(streams[1].1, large);
+
+        assert!(CompoundFile::parse(vec![0u8; 1024]).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_fat_count() {
+        let large: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let mut data = build(b"hello mini stream", &large);
+        LittleEndian::write_u32(&mut data[0x2C..], u32::MAX);
+
+        let file = CompoundFile::parse(data).unwrap();
+        assert_eq!(file.fat.len(), 128);
+        assert_eq!(file.streams().len(), 2);
+    }
+
+    #[test]
+    fn test_cyclic_chains() {
+        let large: Vec<u8> = (0..5000u32).map(|i| (i % 251) as u8).collect();
+        let mut data = build(&[7u8; 200], &large);
+        // The large stream's last sector links back to its first
+        let last = 4 + large.len().div_ceil(512);
+        LittleEndian::write_u32(&mut data[512 + (last - 1) * 4..], 4);
+        // The small stream's first mini sector links to itself
+        LittleEndian::write_u32(&mut data[512 * 3..], 0);
+
+        let file = CompoundFile::parse(data).unwrap();
+        assert!(file.read_chain(4, None).is_err());
+        assert!(file.read_mini_chain(0, 200).is_err());
+        assert!(file.streams().is_empty());
+    }
+}
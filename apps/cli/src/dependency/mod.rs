@@ -1,18 +1,24 @@
 //! Dependency graph and NLE project file parsing (Phase 7).
 //!
 //! This module provides:
-//! - Project file parsers for Premiere Pro, DaVinci Resolve, Final Cut Pro
+//! - Project file parsers for Premiere Pro, DaVinci Resolve, Final Cut Pro,
+//!   After Effects, OpenTimelineIO, CMX3600 EDL and AAF
 //! - Dependency tracking to prevent "Media Offline" errors
 //! - Validation that all referenced assets are tracked before commit
+//! - Matching of external media paths to tracked files for relinking
 
+mod cfb;
 mod parser;
 mod graph;
+mod relink;
 mod validator;
 
 pub use parser::{
     ProjectParser, ProjectType, ParsedProject, MediaReference, MediaType,
     PremiereParser, ResolveParser, FcpParser, AfterEffectsParser,
+    OtioParser, EdlParser, AafParser,
     parse_project, ParseError,
 };
+pub use relink::{plan_relink, RelinkPlan, RelinkTarget};
 pub use graph::{DependencyGraph, DependencyNode, DependencyEdge, EdgeType, GraphStats};
-pub use validator::{DependencyValidator, ValidationResult, ValidationError, is_project_file, filter_project_files};
+pub use validator::{DependencyClosure, DependencyValidator, ValidationResult, ValidationError, is_project_file, filter_project_files};
@@ -5,11 +5,15 @@
 //! - DaVinci Resolve (.drp) - Zip containing XML
 //! - Final Cut Pro (.fcpxml) - Plain XML
 //! - After Effects (.aep) - Binary with embedded paths
+//! - OpenTimelineIO (.otio) - JSON
+//! - CMX3600 EDL (.edl) - Plain text event list
+//! - AAF (.aaf) - Structured storage container
 
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::{Path, PathBuf};
+use super::cfb::CompoundFile;
 use flate2::read::GzDecoder;
 use quick_xml::events::Event;
 use quick_xml::Reader;
@@ -47,6 +51,12 @@ pub enum ProjectType {
     FinalCutPro,
     /// After Effects (.aep)
     AfterEffects,
+    /// OpenTimelineIO (.otio)
+    OpenTimelineIo,
+    /// CMX3600 edit decision list (.edl)
+    Edl,
+    /// Advanced Authoring Format (.aaf)
+    Aaf,
     /// Unknown project type
     Unknown,
 }
@@ -59,6 +69,9 @@ impl ProjectType {
             Some("drp") => Self::DaVinciResolve,
             Some("fcpxml") => Self::FinalCutPro,
             Some("aep") => Self::AfterEffects,
+            Some("otio") => Self::OpenTimelineIo,
+            Some("edl") => Self::Edl,
+            Some("aaf") => Self::Aaf,
             _ => Self::Unknown,
         }
     }
@@ -70,6 +83,9 @@ impl ProjectType {
             Self::DaVinciResolve => "DaVinci Resolve",
             Self::FinalCutPro => "Final Cut Pro",
             Self::AfterEffects => "After Effects",
+            Self::OpenTimelineIo => "OpenTimelineIO",
+            Self::Edl => "EDL",
+            Self::Aaf => "AAF",
             Self::Unknown => "Unknown",
         }
     }
@@ -107,7 +123,7 @@ impl MediaType {
             Some("mp4" | "mov" | "avi" | "mkv" | "mxf" | "m4v" | "webm" | "r3d" | "braw" | "arw") => Self::Video,
             Some("wav" | "mp3" | "aac" | "aiff" | "flac" | "ogg" | "m4a") => Self::Audio,
             Some("jpg" | "jpeg" | "png" | "tiff" | "tif" | "psd" | "exr" | "dpx" | "gif" | "bmp") => Self::Image,
-            Some("prproj" | "drp" | "fcpxml" | "aep") => Self::Project,
+            Some("prproj" | "drp" | "fcpxml" | "aep" | "otio" | "edl" | "aaf") => Self::Project,
             _ => Self::Other,
         }
     }
@@ -163,6 +179,9 @@ pub fn parse_project(path: &Path) -> Result<ParsedProject, ParseError> {
         ProjectType::DaVinciResolve => ResolveParser.parse(path),
         ProjectType::FinalCutPro => FcpParser.parse(path),
         ProjectType::AfterEffects => AfterEffectsParser.parse(path),
+        ProjectType::OpenTimelineIo => OtioParser.parse(path),
+        ProjectType::Edl => EdlParser.parse(path),
+        ProjectType::Aaf => AafParser.parse(path),
         ProjectType::Unknown => Err(ParseError::UnsupportedType(
             path.extension()
                 .and_then(|e| e.to_str())
@@ -534,6 +553,242 @@ fn parse_aep_binary(data: &[u8], project_dir: &Path) -> Result<Vec<MediaReferenc
         .collect())
 }
 
+// ============================================================================
+// OpenTimelineIO Parser (.otio - JSON)
+// ============================================================================
+
+/// Parser for OpenTimelineIO timelines.
+pub struct OtioParser;
+
+impl ProjectParser for OtioParser {
+    fn parse(&self, path: &Path) -> Result<ParsedProject, ParseError> {
+        let file = File::open(path)?;
+        let json: serde_json::Value = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| ParseError::InvalidProject(format!("OTIO JSON: {}", e)))?;
+
+        let mut urls = HashSet::new();
+        collect_otio_references(&json, &mut urls);
+
+        let project_dir = path.parent().unwrap_or(Path::new("."));
+        let (nested, media): (Vec<_>, Vec<_>) = urls
+            .into_iter()
+            .map(|p| create_media_reference(&p, project_dir))
+            .partition(|r| r.media_type == MediaType::Project);
+
+        Ok(ParsedProject {
+            project_path: path.to_path_buf(),
+            project_type: ProjectType::OpenTimelineIo,
+            references: media,
+            nested_projects: nested,
+        })
+    }
+
+    fn can_parse(&self, path: &Path) -> bool {
+        ProjectType::from_path(path) == ProjectType::OpenTimelineIo
+    }
+}
+
+/// Collect media reference targets from an OTIO object tree.
+fn collect_otio_references(value: &serde_json::Value, urls: &mut HashSet<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let schema = map.get("OTIO_SCHEMA").and_then(|s| s.as_str()).unwrap_or("");
+            let field = |key: &str| map.get(key).and_then(|v| v.as_str()).unwrap_or("");
+
+            if schema.starts_with("ExternalReference.") {
+                push_url(field("target_url"), urls);
+            } else if schema.starts_with("ImageSequenceReference.") {
+                // Reference the first frame of the sequence
+                let padding = map.get("frame_zero_padding").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+                let start = map.get("start_frame").and_then(|v| v.as_i64()).unwrap_or(0);
+                let base = field("target_url_base");
+                let separator = if base.is_empty() || base.ends_with('/') { "" } else { "/" };
+                push_url(
+                    &format!(
+                        "{}{}{}{:0width$}{}",
+                        base,
+                        separator,
+                        field("name_prefix"),
+                        start,
+                        field("name_suffix"),
+                        width = padding
+                    ),
+                    urls,
+                );
+            }
+
+            for child in map.values() {
+                collect_otio_references(child, urls);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for item in items {
+                collect_otio_references(item, urls);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Add a reference URL, skipping empty and non-file URLs.
+fn push_url(url: &str, urls: &mut HashSet<String>) {
+    let url = url.trim();
+    let remote = url.contains("://") && !url.starts_with("file://");
+    if !url.is_empty() && !remote {
+        urls.insert(url.to_string());
+    }
+}
+
+// ============================================================================
+// CMX3600 EDL Parser (.edl - Plain text)
+// ============================================================================
+
+/// Parser for CMX3600 edit decision lists.
+pub struct EdlParser;
+
+impl ProjectParser for EdlParser {
+    fn parse(&self, path: &Path) -> Result<ParsedProject, ParseError> {
+        let data = std::fs::read(path)?;
+        let text = String::from_utf8_lossy(&data);
+
+        let project_dir = path.parent().unwrap_or(Path::new("."));
+        let references = parse_edl(&text)
+            .into_iter()
+            .map(|p| create_media_reference(&p, project_dir))
+            .collect();
+
+        Ok(ParsedProject {
+            project_path: path.to_path_buf(),
+            project_type: ProjectType::Edl,
+            references,
+            nested_projects: Vec::new(),
+        })
+    }
+
+    fn can_parse(&self, path: &Path) -> bool {
+        ProjectType::from_path(path) == ProjectType::Edl
+    }
+}
+
+/// Extract source media from EDL comments.
+///
+/// Events reference reels, not files, so the media comes from the comment
+/// lines NLEs add below each event: `* SOURCE FILE:` carries a full path,
+/// otherwise `* FROM CLIP NAME:` / `* TO CLIP NAME:` name the clip file.
+fn parse_edl(text: &str) -> HashSet<String> {
+    let mut references = HashSet::new();
+    let mut source_files = Vec::new();
+    let mut clip_names = Vec::new();
+
+    let mut flush = |source_files: &mut Vec<String>, clip_names: &mut Vec<String>| {
+        if source_files.is_empty() {
+            references.extend(clip_names.drain(..));
+        } else {
+            references.extend(source_files.drain(..));
+            clip_names.clear();
+        }
+    };
+
+    for line in text.lines() {
+        let line = line.trim();
+
+        // A new event starts with its event number
+        if line.split_whitespace().next().is_some_and(|n| n.len() >= 3 && n.chars().all(|c| c.is_ascii_digit())) {
+            flush(&mut source_files, &mut clip_names);
+            continue;
+        }
+
+        let Some(comment) = line.strip_prefix('*') else { continue };
+        let Some((key, value)) = comment.split_once(':') else { continue };
+        let value = value.trim();
+        match key.trim().to_uppercase().as_str() {
+            "SOURCE FILE" | "FROM FILE" if !value.is_empty() => source_files.push(value.to_string()),
+            "FROM CLIP NAME" | "TO CLIP NAME"
+                if MediaType::from_path(Path::new(value)) != MediaType::Other =>
+            {
+                clip_names.push(value.to_string())
+            }
+            _ => {}
+        }
+    }
+    flush(&mut source_files, &mut clip_names);
+
+    references
+}
+
+// ============================================================================
+// AAF Parser (.aaf - Structured storage)
+// ============================================================================
+
+/// Parser for AAF compositions.
+pub struct AafParser;
+
+impl ProjectParser for AafParser {
+    fn parse(&self, path: &Path) -> Result<ParsedProject, ParseError> {
+        let data = std::fs::read(path)?;
+        let container = CompoundFile::parse(data)?;
+
+        // Locators are UTF-16 URL strings in the objects' property streams
+        let mut references = HashSet::new();
+        for (_, stream) in container.streams() {
+            for candidate in utf16_strings(&stream) {
+                // Drop length or tag bytes that decoded as part of the string
+                let candidate = match candidate.find("file://") {
+                    Some(start) => candidate[start..].to_string(),
+                    None => candidate,
+                };
+                if candidate.starts_with("file://") || looks_like_path(&candidate) {
+                    let media_type = MediaType::from_path(Path::new(&candidate));
+                    if media_type != MediaType::Other {
+                        references.insert(candidate);
+                    }
+                }
+            }
+        }
+
+        let project_dir = path.parent().unwrap_or(Path::new("."));
+        let (nested, media): (Vec<_>, Vec<_>) = references
+            .into_iter()
+            .map(|p| create_media_reference(&p, project_dir))
+            .partition(|r| r.media_type == MediaType::Project);
+
+        Ok(ParsedProject {
+            project_path: path.to_path_buf(),
+            project_type: ProjectType::Aaf,
+            references: media,
+            nested_projects: nested,
+        })
+    }
+
+    fn can_parse(&self, path: &Path) -> bool {
+        ProjectType::from_path(path) == ProjectType::Aaf
+    }
+}
+
+/// Printable UTF-16LE strings of at least 4 characters, at either byte alignment.
+fn utf16_strings(data: &[u8]) -> Vec<String> {
+    let mut strings = Vec::new();
+    for offset in 0..2 {
+        let mut current = Vec::new();
+        for pair in data[offset.min(data.len())..].chunks_exact(2) {
+            let unit = u16::from_le_bytes([pair[0], pair[1]]);
+            let printable = char::from_u32(unit.into()).is_some_and(|c| !c.is_control());
+            if printable {
+                current.push(unit);
+                continue;
+            }
+            if current.len() >= 4 {
+                strings.push(String::from_utf16_lossy(&current));
+            }
+            current.clear();
+        }
+        if current.len() >= 4 {
+            strings.push(String::from_utf16_lossy(&current));
+        }
+    }
+    strings
+}
+
 // ============================================================================
 // Helper Functions
 // ============================================================================
@@ -543,7 +798,7 @@ fn create_media_reference(path_str: &str, project_dir: &Path) -> MediaReference
     let normalized = normalize_path(path_str, project_dir);
     let is_absolute = Path::new(path_str).is_absolute() ||
                       path_str.starts_with('/') ||
-                      (path_str.len() > 2 && &path_str[1..2] == ":");
+                      (path_str.len() > 2 && path_str.as_bytes()[1] == b':');
     let exists = normalized.exists();
     let media_type = MediaType::from_path(&normalized);
 
@@ -584,20 +839,27 @@ fn decode_file_url(url: &str) -> String {
         .or_else(|| url.strip_prefix("file://"))
         .unwrap_or(url);
 
-    // URL decode
-    let mut result = String::new();
-    let mut chars = path.chars().peekable();
-    while let Some(c) = chars.next() {
-        if c == '%' {
-            let hex: String = chars.by_ref().take(2).collect();
-            if let Ok(byte) = u8::from_str_radix(&hex, 16) {
-                result.push(byte as char);
+    // URL decode; escapes are bytes of UTF-8 sequences
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
             }
-        } else {
-            result.push(c);
         }
     }
-    result
+    String::from_utf8_lossy(&decoded).into_owned()
 }
 
 /// Check if a string looks like a file path.
@@ -613,7 +875,7 @@ fn looks_like_path(s: &str) -> bool {
     let starts_like_path = s.starts_with('/') ||
                            s.starts_with("./") ||
                            s.starts_with("../") ||
-                           (s.len() > 2 && &s[1..2] == ":") ||
+                           (s.len() > 2 && s.as_bytes()[1] == b':') ||
                            s.starts_with("file://") ||
                            s.starts_with("Volumes/") ||
                            s.starts_with("Users/");
@@ -638,6 +900,9 @@ mod tests {
         assert_eq!(ProjectType::from_path(Path::new("project.drp")), ProjectType::DaVinciResolve);
         assert_eq!(ProjectType::from_path(Path::new("project.fcpxml")), ProjectType::FinalCutPro);
         assert_eq!(ProjectType::from_path(Path::new("project.aep")), ProjectType::AfterEffects);
+        assert_eq!(ProjectType::from_path(Path::new("conform.otio")), ProjectType::OpenTimelineIo);
+        assert_eq!(ProjectType::from_path(Path::new("reel1.EDL")), ProjectType::Edl);
+        assert_eq!(ProjectType::from_path(Path::new("turnover.aaf")), ProjectType::Aaf);
         assert_eq!(ProjectType::from_path(Path::new("project.txt")), ProjectType::Unknown);
     }
 
@@ -666,4 +931,84 @@ mod tests {
             "/Users/test/My Video.mp4"
         );
     }
+
+    #[test]
+    fn test_otio_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let otio = dir.path().join("conform.otio");
+        std::fs::write(&otio, r#"{
+            "OTIO_SCHEMA": "Timeline.1",
+            "tracks": {"OTIO_SCHEMA": "Stack.1", "children": [{
+                "OTIO_SCHEMA": "Track.1",
+                "children": [
+                    {"OTIO_SCHEMA": "Clip.2", "media_references": {"DEFAULT_MEDIA": {
+                        "OTIO_SCHEMA": "ExternalReference.1", "target_url": "media/A001C003.mov"}}},
+                    {"OTIO_SCHEMA": "Clip.1", "media_reference": {
+                        "OTIO_SCHEMA": "ExternalReference.1", "target_url": "file:///Volumes/RAID/A002%20C001.mxf"}},
+                    {"OTIO_SCHEMA": "Clip.2", "media_references": {"DEFAULT_MEDIA": {
+                        "OTIO_SCHEMA": "ImageSequenceReference.1", "target_url_base": "vfx/sh010/",
+                        "name_prefix": "sh010.", "name_suffix": ".exr", "start_frame": 1001, "frame_zero_padding": 4}}},
+                    {"OTIO_SCHEMA": "Clip.1", "media_reference": {
+                        "OTIO_SCHEMA": "ExternalReference.1", "target_url": "https://example.com/stock.mp4"}},
+                    {"OTIO_SCHEMA": "Clip.1", "media_reference": {"OTIO_SCHEMA": "MissingReference.1"}}
+                ]}]}
+        }"#).unwrap();
+
+        let parsed = parse_project(&otio).unwrap();
+        assert_eq!(parsed.project_type, ProjectType::OpenTimelineIo);
+        let mut paths: Vec<_> = parsed.references.iter().map(|r| r.normalized_path.clone()).collect();
+        paths.sort();
+        assert_eq!(paths, vec![
+            PathBuf::from("/Volumes/RAID/A002 C001.mxf"),
+            dir.path().join("media/A001C003.mov"),
+            dir.path().join("vfx/sh010/sh010.1001.exr"),
+        ]);
+    }
+
+    #[test]
+    fn test_edl_references() {
+        let edl = "TITLE: REEL 1 CONFORM\n\
+FCM: NON-DROP FRAME\n\
+\n\
+001  A001C003 V     C        01:00:00:00 01:00:04:00 00:00:00:00 00:00:04:00\n\
+* FROM CLIP NAME: A001C003_220101_R1AB.mov\n\
+* SOURCE FILE: /Volumes/RAID/Day1/A001C003_220101_R1AB.mov\n\
+\n\
+002  B002C010 V     D    024 01:00:10:00 01:00:12:00 00:00:04:00 00:00:06:00\n\
+* FROM CLIP NAME: A001C003_220101_R1AB.mov\n\
+* TO CLIP NAME: B002C010.mxf\n\
+003  AX       A     C        00:00:00:00 00:00:06:00 00:00:00:00 00:00:06:00\n\
+* FROM CLIP NAME: Temp Music Cue 3\n";
+        let mut refs: Vec<_> = parse_edl(edl).into_iter().collect();
+        refs.sort();
+        assert_eq!(refs, vec![
+            "/Volumes/RAID/Day1/A001C003_220101_R1AB.mov",
+            "A001C003_220101_R1AB.mov",
+            "B002C010.mxf",
+        ]);
+    }
+
+    #[test]
+    fn test_aaf_references() {
+        let utf16 = |s: &str| -> Vec<u8> { s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect() };
+        let small = [vec![0x02, 0x00, 0x11], utf16("file:///Volumes/Avid%20MediaFiles/MXF/1/A001V.mxf"), vec![0, 0]].concat();
+        let mut large = vec![0u8; 4096];
+        large.extend(utf16("Sequence 1"));
+        large.extend([0, 0]);
+        large.extend(utf16("audio/dialog_01.wav"));
+        large.extend([0, 0, 0]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let aaf = dir.path().join("turnover.aaf");
+        std::fs::write(&aaf, crate::dependency::cfb::tests::build(&small, &large)).unwrap();
+
+        let parsed = parse_project(&aaf).unwrap();
+        assert_eq!(parsed.project_type, ProjectType::Aaf);
+        let mut paths: Vec<_> = parsed.references.iter().map(|r| r.normalized_path.clone()).collect();
+        paths.sort();
+        assert_eq!(paths, vec![
+            PathBuf::from("/Volumes/Avid MediaFiles/MXF/1/A001V.mxf"),
+            dir.path().join("audio/dialog_01.wav"),
+        ]);
+    }
 }
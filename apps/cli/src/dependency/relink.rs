@@ -0,0 +1,138 @@
+//! Matching project media references to tracked files.
+//!
+//! A project opened on another machine still points at the media paths of
+//! the machine that saved it. Each reference that does not resolve inside
+//! the repository is matched to the tracked file sharing the longest path
+//! suffix with it, so `/Volumes/RAID/Shoot/Day1/A001.mov` finds
+//! `footage/Day1/A001.mov`.
+
+use super::parser::ParsedProject;
+use std::path::Path;
+
+/// A reference that can be relinked to a tracked file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelinkTarget {
+    /// Path as stored in the project.
+    pub original: String,
+    /// Tracked file it resolves to (relative to repo root).
+    pub tracked: String,
+}
+
+/// Relink targets of a project.
+#[derive(Debug, Default)]
+pub struct RelinkPlan {
+    /// References matched to a tracked file.
+    pub targets: Vec<RelinkTarget>,
+    /// References outside the repository with no single matching tracked file.
+    pub unresolved: Vec<String>,
+}
+
+/// Match the references of a project that point outside `root`, or at
+/// files that do not exist, to tracked files.
+pub fn plan_relink(project: &ParsedProject, root: &Path, tracked: &[String]) -> RelinkPlan {
+    let mut plan = RelinkPlan::default();
+    let tracked: Vec<(&String, Vec<&str>)> = tracked.iter().map(|t| (t, components(t))).collect();
+
+    for reference in project.references.iter().chain(&project.nested_projects) {
+        if reference.exists && reference.normalized_path.starts_with(root) {
+            continue;
+        }
+        if plan.targets.iter().any(|t| t.original == reference.original_path) {
+            continue;
+        }
+
+        let path = reference.normalized_path.to_string_lossy();
+        let wanted = components(&path);
+        let mut best: Vec<&String> = Vec::new();
+        let mut best_len = 0;
+        for (candidate, parts) in &tracked {
+            let len = common_suffix(&wanted, parts);
+            if len == 0 || len < best_len {
+                continue;
+            }
+            if len > best_len {
+                best.clear();
+                best_len = len;
+            }
+            best.push(candidate);
+        }
+
+        match best.as_slice() {
+            [tracked] => plan.targets.push(RelinkTarget {
+                original: reference.original_path.clone(),
+                tracked: (*tracked).clone(),
+            }),
+            _ => plan.unresolved.push(reference.original_path.clone()),
+        }
+    }
+    plan
+}
+
+/// Path components, splitting on both separators.
+fn components(path: &str) -> Vec<&str> {
+    path.split(['/', '\\']).filter(|c| !c.is_empty()).collect()
+}
+
+/// Number of trailing components two paths share. Comparison ignores ASCII
+/// case, as media often comes from case-insensitive volumes.
+fn common_suffix(a: &[&str], b: &[&str]) -> usize {
+    a.iter()
+        .rev()
+        .zip(b.iter().rev())
+        .take_while(|(x, y)| x.eq_ignore_ascii_case(y))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dependency::parser::{MediaReference, MediaType, ProjectType};
+    use std::path::PathBuf;
+
+    fn reference(path: &str, exists: bool) -> MediaReference {
+        MediaReference {
+            original_path: path.to_string(),
+            normalized_path: PathBuf::from(path.replace('\\', "/")),
+            is_absolute: true,
+            exists,
+            media_type: MediaType::Video,
+        }
+    }
+
+    #[test]
+    fn test_plan_relink() {
+        let project = ParsedProject {
+            project_path: PathBuf::from("/work/proj/edit.prproj"),
+            project_type: ProjectType::PremierePro,
+            references: vec![
+                reference("/Volumes/RAID/Shoot/Day1/A001.mov", false),
+                reference("D:\\Footage\\Day2\\a001.MOV", false),
+                reference("/Volumes/RAID/Music/score.wav", false),
+                reference("/Volumes/RAID/Shoot/B001.mov", false),
+                reference("/work/proj/footage/Day1/A001.mov", true),
+            ],
+            nested_projects: Vec::new(),
+        };
+        let tracked = [
+            "footage/Day1/A001.mov".to_string(),
+            "footage/Day2/A001.mov".to_string(),
+            "edit.prproj".to_string(),
+        ];
+
+        let plan = plan_relink(&project, Path::new("/work/proj"), &tracked);
+        assert_eq!(
+            plan.targets,
+            [
+                RelinkTarget {
+                    original: "/Volumes/RAID/Shoot/Day1/A001.mov".to_string(),
+                    tracked: "footage/Day1/A001.mov".to_string(),
+                },
+                RelinkTarget {
+                    original: "D:\\Footage\\Day2\\a001.MOV".to_string(),
+                    tracked: "footage/Day2/A001.mov".to_string(),
+                },
+            ]
+        );
+        assert_eq!(plan.unresolved, ["/Volumes/RAID/Music/score.wav", "/Volumes/RAID/Shoot/B001.mov"]);
+    }
+}
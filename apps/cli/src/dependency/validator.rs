@@ -6,7 +6,7 @@
 use super::graph::{DependencyGraph, DependencyNode, EdgeType};
 use super::parser::{parse_project, ParsedProject, ProjectType};
 use std::collections::HashSet;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use thiserror::Error;
 
 /// Validation errors.
@@ -69,6 +69,18 @@ impl ValidationResult {
     }
 }
 
+/// Everything a set of projects references, nested projects included.
+#[derive(Debug, Default)]
+pub struct DependencyClosure {
+    /// Existing files inside the repository (relative to repo root),
+    /// including nested projects.
+    pub files: Vec<String>,
+    /// Referenced files inside the repository that do not exist.
+    pub missing: Vec<String>,
+    /// References outside the repository root, as stored in the project.
+    pub external: Vec<String>,
+}
+
 /// Validates dependencies for project files.
 pub struct DependencyValidator {
     /// Repository root path.
@@ -223,6 +235,52 @@ impl DependencyValidator {
         }
     }
 
+    /// Collect the files the given projects reference, recursing into
+    /// nested projects. Each project is parsed once, so projects that
+    /// reference each other terminate.
+    ///
+    /// Projects given here must parse; a nested project that does not is
+    /// still listed but not recursed into.
+    pub fn dependency_closure(&self, project_paths: &[PathBuf]) -> Result<DependencyClosure, ValidationError> {
+        let mut closure = DependencyClosure::default();
+        let mut visited = HashSet::new();
+        let mut pending: Vec<(PathBuf, bool)> = project_paths.iter().rev().map(|p| (p.clone(), false)).collect();
+
+        while let Some((project, nested)) = pending.pop() {
+            if !visited.insert(normalize_lexically(&project)) {
+                continue;
+            }
+            let parsed = match parse_project(&project) {
+                Ok(parsed) => parsed,
+                Err(_) if nested => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            for reference in parsed.references.iter().chain(&parsed.nested_projects) {
+                let path = normalize_lexically(&reference.normalized_path);
+                if !path.starts_with(&self.repo_root) {
+                    closure.external.push(reference.original_path.clone());
+                    continue;
+                }
+                let rel_path = self.relative_path(&path);
+                if !reference.exists {
+                    closure.missing.push(rel_path);
+                    continue;
+                }
+                if is_project_file(&path) {
+                    pending.push((path, true));
+                }
+                closure.files.push(rel_path);
+            }
+        }
+
+        for list in [&mut closure.files, &mut closure.missing, &mut closure.external] {
+            list.sort();
+            list.dedup();
+        }
+        Ok(closure)
+    }
+
     /// Get relative path from repo root.
     fn relative_path(&self, path: &Path) -> String {
         path.strip_prefix(&self.repo_root)
@@ -232,13 +290,31 @@ impl DependencyValidator {
     }
 }
 
+/// Resolve `.` and `..` components without touching the filesystem.
+fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
 /// Check if a file is a supported project file.
 pub fn is_project_file(path: &Path) -> bool {
     matches!(ProjectType::from_path(path),
         ProjectType::PremierePro |
         ProjectType::DaVinciResolve |
         ProjectType::FinalCutPro |
-        ProjectType::AfterEffects
+        ProjectType::AfterEffects |
+        ProjectType::OpenTimelineIo |
+        ProjectType::Edl |
+        ProjectType::Aaf
     )
 }
 
@@ -271,7 +347,40 @@ mod tests {
         assert!(is_project_file(Path::new("project.drp")));
         assert!(is_project_file(Path::new("project.fcpxml")));
         assert!(is_project_file(Path::new("project.aep")));
+        assert!(is_project_file(Path::new("conform.otio")));
+        assert!(is_project_file(Path::new("reel1.edl")));
+        assert!(is_project_file(Path::new("turnover.aaf")));
         assert!(!is_project_file(Path::new("video.mp4")));
         assert!(!is_project_file(Path::new("README.md")));
     }
+
+    #[test]
+    fn test_dependency_closure() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path();
+        std::fs::create_dir_all(root.join("edit")).unwrap();
+        std::fs::create_dir_all(root.join("footage")).unwrap();
+        std::fs::write(root.join("footage/a001.mov"), b"clip").unwrap();
+        std::fs::write(root.join("footage/music.wav"), b"music").unwrap();
+
+        // Two projects referencing each other, plus media inside, missing and outside
+        std::fs::write(
+            root.join("edit/main.fcpxml"),
+            r#"<fcpxml><asset src="../footage/a001.mov"/><asset src="./sub.fcpxml"/><asset src="/Volumes/RAID/b001.mov"/></fcpxml>"#,
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("edit/sub.fcpxml"),
+            r#"<fcpxml><asset src="../footage/music.wav"/><asset src="../footage/gone.mov"/><asset src="./main.fcpxml"/></fcpxml>"#,
+        )
+        .unwrap();
+
+        let validator = DependencyValidator::new(root);
+        let closure = validator.dependency_closure(&[root.join("edit/main.fcpxml")]).unwrap();
+        assert_eq!(closure.files, ["edit/main.fcpxml", "edit/sub.fcpxml", "footage/a001.mov", "footage/music.wav"]);
+        assert_eq!(closure.missing, ["footage/gone.mov"]);
+        assert_eq!(closure.external, ["/Volumes/RAID/b001.mov"]);
+
+        assert!(validator.dependency_closure(&[root.join("edit/none.fcpxml")]).is_err());
+    }
 }
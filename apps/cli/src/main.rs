@@ -54,10 +54,17 @@ enum Commands {
         /// Files or directories to add
         #[arg(required = true)]
         files: Vec<String>,
+        /// Also stage the media and nested projects that project files reference
+        #[arg(long)]
+        with_deps: bool,
     },
 
     /// Show repository status
-    Status,
+    Status {
+        /// Rehash every tracked file instead of trusting cached stat data
+        #[arg(long)]
+        refresh: bool,
+    },
 
     /// Create a commit from staged changes
     Commit {
@@ -80,6 +87,9 @@ enum Commands {
         /// Show commits from all branches
         #[arg(long)]
         all: bool,
+        /// Only show commits that add or change files matching a metadata query
+        #[arg(long, value_name = "QUERY")]
+        meta: Option<String>,
     },
 
     /// Checkout a commit or branch
@@ -89,6 +99,9 @@ enum Commands {
         /// Checkout mode: full (default) or proxy
         #[arg(short, long, default_value = "full")]
         mode: String,
+        /// Only restore files matching a metadata query, keeping HEAD
+        #[arg(long, value_name = "QUERY")]
+        meta: Option<String>,
     },
 
     /// List, create, or delete branches
@@ -319,13 +332,33 @@ enum Commands {
     #[cfg(feature = "fuser")]
     Mount {
         /// Mount point path
-        mount_point: String,
-        /// Commit to mount (default: HEAD)
+        #[arg(required_unless_present = "discard")]
+        mount_point: Option<String>,
+        /// Commit to mount (default: HEAD); with --writable, the branch to edit
         #[arg(short, long)]
         commit: Option<String>,
         /// L1 (RAM) cache size in MB
         #[arg(long, default_value = "256")]
         cache_mb: u64,
+        /// Allow edits, kept in a copy-on-write overlay until `dits commit`
+        #[arg(long)]
+        writable: bool,
+        /// Show every branch, tag and commit under branches/, tags/ and commits/
+        #[arg(long, conflicts_with_all = ["commit", "writable"])]
+        refs: bool,
+        /// Serve videos from their proxies (1080, 720, 540, half, quarter; default: best available)
+        #[arg(long, value_name = "RESOLUTION", num_args = 0..=1, default_missing_value = "any",
+              conflicts_with_all = ["writable", "refs"])]
+        proxy: Option<String>,
+        /// Download chunks missing locally from a remote as they are read
+        #[arg(long)]
+        lazy: bool,
+        /// Remote to download from with --lazy (default: origin)
+        #[arg(long, requires = "lazy")]
+        remote: Option<String>,
+        /// Throw away the uncommitted changes of a writable mount instead of mounting
+        #[arg(long, exclusive = true)]
+        discard: bool,
     },
 
     /// Unmount a virtual filesystem
@@ -335,6 +368,28 @@ enum Commands {
         mount_point: String,
     },
 
+    /// Serve the repository over WebDAV, mountable without FUSE
+    ServeWebdav {
+        /// Commit to serve (default: HEAD); with --writable, the branch to edit
+        #[arg(short, long)]
+        commit: Option<String>,
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        bind: String,
+        /// Port to listen on
+        #[arg(short, long, default_value = "8090")]
+        port: u16,
+        /// L1 (RAM) cache size in MB
+        #[arg(long, default_value = "256")]
+        cache_mb: u64,
+        /// Accept uploads, kept in a copy-on-write overlay until `dits commit`
+        #[arg(long)]
+        writable: bool,
+        /// Throw away the uncommitted changes of a writable server instead of serving
+        #[arg(long, exclusive = true)]
+        discard: bool,
+    },
+
     /// Show cache statistics
     CacheStats,
 
@@ -380,6 +435,16 @@ enum Commands {
     #[command(name = "meta-list")]
     MetaList,
 
+    /// Find files whose metadata matches a query (e.g. "codec=prores width>=3840")
+    #[command(name = "meta-find")]
+    MetaFind {
+        /// Query terms: field=value, field>value, field~text, ...
+        query: String,
+        /// Only files added or changed since this commit, branch or tag
+        #[arg(long)]
+        since: Option<String>,
+    },
+
     /// Initialize a new video timeline project (Phase 5)
     #[command(name = "video-init")]
     VideoInit {
@@ -488,6 +553,19 @@ enum Commands {
     #[command(name = "dep-list")]
     DepList,
 
+    /// Point project media references at the tracked copies in this working tree
+    #[command(name = "dep-relink")]
+    DepRelink {
+        /// Project files to relink (default: all tracked .prproj, .fcpxml and .drp files)
+        files: Vec<String>,
+        /// Restore the committed media paths
+        #[arg(long)]
+        undo: bool,
+        /// Show what would be relinked without changing files
+        #[arg(long)]
+        dry_run: bool,
+    },
+
     /// Initialize lifecycle tracking for chunks (Phase 8)
     #[command(name = "freeze-init")]
     FreezeInit,
@@ -601,15 +679,18 @@ enum Commands {
         /// Branch to checkout after clone
         #[arg(short, long)]
         branch: Option<String>,
+        /// Download history only; file content is fetched by `dits mount --lazy`
+        #[arg(long)]
+        lazy: bool,
     },
 
     /// Manage remote repositories
     Remote {
-        /// Action: add, remove, rm, rename, get-url, set-url, list
+        /// Action: add, remove, rm, rename, get-url, set-url, set-token, list
         action: Option<String>,
         /// Remote name
         name: Option<String>,
-        /// Remote URL (for add/set-url)
+        /// Remote URL (for add/set-url), or access token (for set-token)
         url: Option<String>,
         /// Show verbose output
         #[arg(short, long)]
@@ -688,6 +769,9 @@ enum Commands {
         /// Show verbose output
         #[arg(short, long)]
         verbose: bool,
+        /// Ask the remote instead of showing cached locks
+        #[arg(long)]
+        remote: bool,
     },
 
     /// Run garbage collection
@@ -807,6 +891,9 @@ enum Commands {
         /// Output file
         #[arg(short, long)]
         output: Option<String>,
+        /// Only include files matching a metadata query
+        #[arg(long, value_name = "QUERY")]
+        meta: Option<String>,
         /// Specific paths to include
         #[arg(last = true)]
         paths: Vec<String>,
@@ -877,6 +964,12 @@ enum Commands {
         /// Base directory containing repositories
         #[arg(short, long)]
         base_dir: Option<String>,
+        /// File of `<user> <token>` lines allowed to push and to take and release locks
+        #[arg(long)]
+        tokens: Option<String>,
+        /// User allowed to force-release other users' locks (repeatable)
+        #[arg(long = "admin", value_name = "USER")]
+        admins: Vec<String>,
     },
 
     /// Synchronize with remote repository (bi-directional)
@@ -918,7 +1011,7 @@ async fn main() {
     let command_name = match &cli.command {
         Commands::Init { .. } => "init",
         Commands::Add { .. } => "add",
-        Commands::Status => "status",
+        Commands::Status { .. } => "status",
         Commands::Commit { .. } => "commit",
         Commands::Log { .. } => "log",
         Commands::Checkout { .. } => "checkout",
@@ -939,6 +1032,7 @@ async fn main() {
         Commands::Mount { .. } => "mount",
         #[cfg(feature = "fuser")]
         Commands::Unmount { .. } => "unmount",
+        Commands::ServeWebdav { .. } => "serve-webdav",
         Commands::Fsck { .. } => "fsck",
         Commands::Gc { .. } => "gc",
         Commands::Clean { .. } => "clean",
@@ -971,6 +1065,7 @@ async fn main() {
         Commands::MetaScan { .. } => "meta-scan",
         Commands::MetaShow { .. } => "meta-show",
         Commands::MetaList => "meta-list",
+        Commands::MetaFind { .. } => "meta-find",
         Commands::VideoInit { .. } => "video-init",
         Commands::VideoAddClip { .. } => "video-add-clip",
         Commands::VideoShow { .. } => "video-show",
@@ -982,6 +1077,7 @@ async fn main() {
         Commands::DepCheck { .. } => "dep-check",
         Commands::DepGraph { .. } => "dep-graph",
         Commands::DepList => "dep-list",
+        Commands::DepRelink { .. } => "dep-relink",
         Commands::FreezeInit => "freeze-init",
         Commands::FreezeStatus => "freeze-status",
         Commands::Freeze { .. } => "freeze",
@@ -1006,14 +1102,19 @@ async fn main() {
 
     let result: anyhow::Result<()> = match cli.command {
         Commands::Init { path } => commands::init(&path),
-        Commands::Add { files } => commands::add(&files),
-        Commands::Status => commands::status(),
+        Commands::Add { files, with_deps } => commands::add(&files, with_deps),
+        Commands::Status { refresh } => commands::status(refresh),
         Commands::Commit { message } => commands::commit(&message),
-        Commands::Log { limit, oneline, graph, all } => commands::log(limit, oneline, graph, all),
-        Commands::Checkout { target, mode } => {
+        Commands::Log { limit, oneline, graph, all, meta } => {
+            commands::log(limit, oneline, graph, all, meta.as_deref())
+        }
+        Commands::Checkout { target, mode, meta } => {
             let checkout_mode = commands::CheckoutMode::from_str(&mode)
                 .unwrap_or(commands::CheckoutMode::Full);
-            commands::checkout(&target, checkout_mode)
+            match meta {
+                Some(query) => commands::checkout::checkout_matching(&target, &query),
+                None => commands::checkout(&target, checkout_mode),
+            }
         }
         Commands::Branch { name, delete } => commands::branch(name.as_deref(), delete),
         Commands::Switch { branch } => commands::switch(&branch),
@@ -1073,9 +1174,19 @@ async fn main() {
         Commands::Segment { file, output, duration } => commands::segment(&file, output.as_deref(), duration),
         Commands::Assemble { segments_dir, output } => commands::assemble(&segments_dir, &output),
         #[cfg(feature = "fuser")]
-        Commands::Mount { mount_point, commit, cache_mb } => commands::mount(&mount_point, commit.as_deref(), cache_mb),
+        Commands::Mount { discard: true, .. } => commands::discard_overlay(),
+        #[cfg(feature = "fuser")]
+        Commands::Mount { mount_point, commit, cache_mb, writable, refs, proxy, lazy, remote, .. } => {
+            let mount_point = mount_point.expect("mount point is required without --discard");
+            let remote = lazy.then(|| remote.as_deref().unwrap_or("origin"));
+            commands::mount(&mount_point, commit.as_deref(), cache_mb, writable, refs, proxy.as_deref(), remote)
+        }
         #[cfg(feature = "fuser")]
         Commands::Unmount { mount_point } => commands::unmount(&mount_point),
+        Commands::ServeWebdav { discard: true, .. } => commands::discard_overlay(),
+        Commands::ServeWebdav { commit, bind, port, cache_mb, writable, .. } => {
+            commands::serve_webdav(commit.as_deref(), &bind, port, cache_mb, writable).await
+        }
         Commands::CacheStats => commands::cache_stats(),
         Commands::InspectFile { path, chunks } => commands::inspect_file(&path, chunks),
         Commands::RepoStats { verbose } => commands::repo_stats(verbose),
@@ -1083,6 +1194,7 @@ async fn main() {
         Commands::MetaScan { verbose } => commands::meta_scan(verbose),
         Commands::MetaShow { path } => commands::meta_show(&path),
         Commands::MetaList => commands::meta_list(),
+        Commands::MetaFind { query, since } => commands::meta_find(&query, since.as_deref()),
         Commands::VideoInit { name } => commands::video_init(&name),
         Commands::VideoAddClip { project, file, in_point, out, start, track } => {
             commands::video_add_clip(&project, &file, in_point, out, start, track.as_deref())
@@ -1098,6 +1210,7 @@ async fn main() {
         Commands::DepCheck { files, all, strict } => commands::dep_check(&files, all, strict),
         Commands::DepGraph { file, format } => commands::dep_graph(&file, format.as_deref()),
         Commands::DepList => commands::dep_list(),
+        Commands::DepRelink { files, undo, dry_run } => commands::dep_relink(&files, undo, dry_run),
         Commands::FreezeInit => commands::freeze_init(),
         Commands::FreezeStatus => commands::freeze_status(),
         Commands::Freeze { files, tier, apply_policy, all } => {
@@ -1113,9 +1226,9 @@ async fn main() {
         Commands::Audit { last, event_type } => commands::audit_show(last, event_type.as_deref()),
         Commands::AuditStats => commands::audit_stats(),
         Commands::AuditExport { output } => commands::audit_export(output.as_deref()),
-        Commands::P2p { command } => commands::handle_p2p_command(command),
-        Commands::Clone { source, dest, branch } => {
-            commands::clone(&source, dest.as_deref(), branch.as_deref())
+        Commands::P2p { command } => commands::handle_p2p_command(command).await,
+        Commands::Clone { source, dest, branch, lazy } => {
+            commands::clone(&source, dest.as_deref(), branch.as_deref(), lazy).await
         }
         Commands::Remote { action, name, url, verbose, push } => {
             commands::remote(action.as_deref(), name.as_deref(), url.as_deref(), verbose, push)
@@ -1130,10 +1243,10 @@ async fn main() {
             commands::fetch(remote.as_deref(), all, prune).await
         }
         Commands::Lock { path, reason, ttl, force } => {
-            commands::lock_file(&path, reason.as_deref(), ttl, force)
+            commands::lock_file(&path, reason.as_deref(), ttl, force).await
         }
-        Commands::Unlock { path, force } => commands::unlock(&path, force),
-        Commands::Locks { owner, verbose } => commands::locks(owner.as_deref(), verbose),
+        Commands::Unlock { path, force } => commands::unlock(&path, force).await,
+        Commands::Locks { owner, verbose, remote } => commands::locks(owner.as_deref(), verbose, remote).await,
         Commands::Gc { dry_run, prune, aggressive } => commands::gc(dry_run, prune, aggressive),
         Commands::Clean { dry_run, force, directories, remove_ignored, only_ignored, exclude, paths } => {
             let options = commands::clean::CleanOptions {
@@ -1243,7 +1356,7 @@ async fn main() {
                 _ => Err(anyhow::anyhow!("Unknown hooks action: {}. Use: list, install, uninstall, run, show", action)),
             }
         }
-        Commands::Archive { tree_ish, format, prefix, output, paths } => {
+        Commands::Archive { tree_ish, format, prefix, output, meta, paths } => {
             match commands::archive::ArchiveFormat::from_str(&format) {
                 Some(fmt) => {
                     let options = commands::archive::ArchiveOptions {
@@ -1252,6 +1365,7 @@ async fn main() {
                         prefix,
                         output: output.map(std::path::PathBuf::from),
                         paths,
+                        meta,
                     };
                     commands::archive::archive(&options).map(|_| ())
                 }
@@ -1352,10 +1466,10 @@ async fn main() {
                 }
             }
         }
-        Commands::Serve { port, base_dir } => {
+        Commands::Serve { port, base_dir, tokens, admins } => {
             use std::path::PathBuf;
             let base = base_dir.map(PathBuf::from).unwrap_or_else(|| std::env::current_dir().unwrap());
-            match crate::store::remote_server::start_server(base, port).await {
+            match crate::store::remote_server::start_server(base, port, tokens.map(PathBuf::from), admins).await {
                 Ok(()) => Ok(()),
                 Err(e) => {
                     eprintln!("Failed to start server: {}", e);
@@ -0,0 +1,385 @@
+//! Native EXIF reader for JPEG and TIFF-based (DNG, NEF, ARW, CR2) files.
+//!
+//! Only IFD0, the Exif sub-IFD and the GPS sub-IFD are read, which covers
+//! camera, exposure, capture time, location and pixel dimensions.
+
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Largest number of entries read from a single IFD.
+const MAX_IFD_ENTRIES: u16 = 512;
+
+/// EXIF fields of a photo.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExifData {
+    /// Pixel width.
+    pub width: Option<u32>,
+    /// Pixel height.
+    pub height: Option<u32>,
+    /// Camera manufacturer.
+    pub make: Option<String>,
+    /// Camera model.
+    pub model: Option<String>,
+    /// ISO speed.
+    pub iso: Option<u32>,
+    /// Exposure time, formatted like `1/250` or `2`.
+    pub exposure: Option<String>,
+    /// Aperture f-number.
+    pub f_number: Option<f64>,
+    /// Focal length in millimetres.
+    pub focal_length: Option<f64>,
+    /// `DateTimeOriginal`, as stored (`YYYY:MM:DD HH:MM:SS`).
+    pub date_taken: Option<String>,
+    /// Signed decimal latitude.
+    pub gps_latitude: Option<f64>,
+    /// Signed decimal longitude.
+    pub gps_longitude: Option<f64>,
+}
+
+impl ExifData {
+    /// Read EXIF data from a JPEG or TIFF-based file.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Read EXIF data from a JPEG or TIFF stream.
+    pub fn from_reader<R: Read + Seek>(mut r: R) -> io::Result<Self> {
+        let mut magic = [0u8; 2];
+        r.read_exact(&mut magic)?;
+        match &magic {
+            [0xFF, 0xD8] => Self::read_jpeg(&mut r),
+            b"II" | b"MM" => {
+                let mut exif = Self::default();
+                exif.read_tiff(&mut r, 0)?;
+                Ok(exif)
+            }
+            _ => Err(invalid("not a JPEG or TIFF file")),
+        }
+    }
+
+    /// Walk JPEG segments for the APP1 Exif block and the frame header.
+    fn read_jpeg<R: Read + Seek>(r: &mut R) -> io::Result<Self> {
+        let mut exif = Self::default();
+        let mut frame_size = None;
+        loop {
+            let mut marker = [0u8; 2];
+            if r.read_exact(&mut marker).is_err() || marker[0] != 0xFF {
+                break;
+            }
+            // Standalone markers carry no length
+            if marker[1] == 0xD8 || marker[1] == 0x01 || (0xD0..=0xD7).contains(&marker[1]) {
+                continue;
+            }
+            if marker[1] == 0xD9 || marker[1] == 0xDA {
+                break;
+            }
+
+            let len = u64::from(r.read_u16::<BigEndian>()?);
+            let start = r.stream_position()?;
+            match marker[1] {
+                0xE1 => {
+                    let mut header = [0u8; 6];
+                    r.read_exact(&mut header)?;
+                    if &header == b"Exif\0\0" {
+                        exif.read_tiff(r, start + 6)?;
+                    }
+                }
+                0xC0..=0xCF if !matches!(marker[1], 0xC4 | 0xC8 | 0xCC) => {
+                    let _precision = r.read_u8()?;
+                    let height = u32::from(r.read_u16::<BigEndian>()?);
+                    let width = u32::from(r.read_u16::<BigEndian>()?);
+                    frame_size = Some((width, height));
+                }
+                _ => {}
+            }
+            r.seek(SeekFrom::Start(start + len.saturating_sub(2)))?;
+        }
+
+        // The frame header is authoritative for JPEG dimensions
+        if let Some((width, height)) = frame_size {
+            exif.width = Some(width);
+            exif.height = Some(height);
+        }
+        Ok(exif)
+    }
+
+    /// Parse a TIFF structure starting at `base`.
+    fn read_tiff<R: Read + Seek>(&mut self, r: &mut R, base: u64) -> io::Result<()> {
+        r.seek(SeekFrom::Start(base))?;
+        let mut order = [0u8; 2];
+        r.read_exact(&mut order)?;
+        match &order {
+            b"II" => Tiff::<_, LittleEndian>::new(r, base).read_into(self),
+            b"MM" => Tiff::<_, BigEndian>::new(r, base).read_into(self),
+            _ => Err(invalid("bad TIFF byte order")),
+        }
+    }
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// One IFD entry. `value` holds the raw 4-byte value/offset field.
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    value: [u8; 4],
+}
+
+/// TIFF reader with fixed byte order.
+struct Tiff<'a, R, B> {
+    r: &'a mut R,
+    base: u64,
+    _order: std::marker::PhantomData<B>,
+}
+
+impl<'a, R: Read + Seek, B: ByteOrder> Tiff<'a, R, B> {
+    fn new(r: &'a mut R, base: u64) -> Self {
+        Self { r, base, _order: std::marker::PhantomData }
+    }
+
+    fn read_into(mut self, exif: &mut ExifData) -> io::Result<()> {
+        self.r.seek(SeekFrom::Start(self.base + 2))?;
+        if self.r.read_u16::<B>()? != 42 {
+            return Err(invalid("bad TIFF magic"));
+        }
+        let ifd0 = self.r.read_u32::<B>()?;
+
+        let mut exif_ifd = None;
+        let mut gps_ifd = None;
+        for entry in self.entries(ifd0)? {
+            match entry.tag {
+                0x0100 => exif.width = self.uint(&entry),
+                0x0101 => exif.height = self.uint(&entry),
+                0x010F => exif.make = self.ascii(&entry)?,
+                0x0110 => exif.model = self.ascii(&entry)?,
+                0x8769 => exif_ifd = self.uint(&entry),
+                0x8825 => gps_ifd = self.uint(&entry),
+                _ => {}
+            }
+        }
+
+        if let Some(offset) = exif_ifd {
+            for entry in self.entries(offset)? {
+                match entry.tag {
+                    0x829A => exif.exposure = self.rational(&entry)?.map(format_exposure),
+                    0x829D => exif.f_number = self.rational(&entry)?.map(|(n, d)| n as f64 / d as f64),
+                    0x8827 => exif.iso = self.uint(&entry),
+                    0x9003 => exif.date_taken = self.ascii(&entry)?,
+                    0x920A => exif.focal_length = self.rational(&entry)?.map(|(n, d)| n as f64 / d as f64),
+                    0xA002 => exif.width = self.uint(&entry).or(exif.width),
+                    0xA003 => exif.height = self.uint(&entry).or(exif.height),
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(offset) = gps_ifd {
+            let (mut lat_ref, mut lat, mut lon_ref, mut lon) = (None, None, None, None);
+            for entry in self.entries(offset)? {
+                match entry.tag {
+                    1 => lat_ref = Some(entry.value[0]),
+                    2 => lat = self.degrees(&entry)?,
+                    3 => lon_ref = Some(entry.value[0]),
+                    4 => lon = self.degrees(&entry)?,
+                    _ => {}
+                }
+            }
+            exif.gps_latitude = lat.map(|v| if lat_ref == Some(b'S') { -v } else { v });
+            exif.gps_longitude = lon.map(|v| if lon_ref == Some(b'W') { -v } else { v });
+        }
+        Ok(())
+    }
+
+    fn entries(&mut self, offset: u32) -> io::Result<Vec<Entry>> {
+        self.r.seek(SeekFrom::Start(self.base + u64::from(offset)))?;
+        let count = self.r.read_u16::<B>()?.min(MAX_IFD_ENTRIES);
+        let mut entries = Vec::with_capacity(count.into());
+        for _ in 0..count {
+            let tag = self.r.read_u16::<B>()?;
+            let kind = self.r.read_u16::<B>()?;
+            let count = self.r.read_u32::<B>()?;
+            let mut value = [0u8; 4];
+            self.r.read_exact(&mut value)?;
+            entries.push(Entry { tag, kind, count, value });
+        }
+        Ok(entries)
+    }
+
+    /// A SHORT or LONG value.
+    fn uint(&self, entry: &Entry) -> Option<u32> {
+        match entry.kind {
+            3 => Some(B::read_u16(&entry.value).into()),
+            4 => Some(B::read_u32(&entry.value)),
+            _ => None,
+        }
+    }
+
+    /// An ASCII value, trimmed of NULs and whitespace.
+    fn ascii(&mut self, entry: &Entry) -> io::Result<Option<String>> {
+        if entry.kind != 2 {
+            return Ok(None);
+        }
+        let len = entry.count.min(4096) as usize;
+        let bytes = if len <= 4 {
+            entry.value[..len].to_vec()
+        } else {
+            self.r.seek(SeekFrom::Start(self.base + u64::from(B::read_u32(&entry.value))))?;
+            let mut buf = vec![0u8; len];
+            self.r.read_exact(&mut buf)?;
+            buf
+        };
+        let text = String::from_utf8_lossy(&bytes);
+        let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        Ok((!text.is_empty()).then(|| text.to_string()))
+    }
+
+    /// RATIONAL values (numerator, denominator) with non-zero denominators.
+    fn rationals(&mut self, entry: &Entry, n: u32) -> io::Result<Option<Vec<(u32, u32)>>> {
+        if entry.kind != 5 || entry.count < n {
+            return Ok(None);
+        }
+        self.r.seek(SeekFrom::Start(self.base + u64::from(B::read_u32(&entry.value))))?;
+        let mut out = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            let (num, den) = (self.r.read_u32::<B>()?, self.r.read_u32::<B>()?);
+            if den == 0 {
+                return Ok(None);
+            }
+            out.push((num, den));
+        }
+        Ok(Some(out))
+    }
+
+    fn rational(&mut self, entry: &Entry) -> io::Result<Option<(u32, u32)>> {
+        Ok(self.rationals(entry, 1)?.map(|v| v[0]))
+    }
+
+    /// Degrees/minutes/seconds as decimal degrees.
+    fn degrees(&mut self, entry: &Entry) -> io::Result<Option<f64>> {
+        Ok(self.rationals(entry, 3)?.map(|v| {
+            let [d, m, s] = [v[0], v[1], v[2]].map(|(n, d)| n as f64 / d as f64);
+            d + m / 60.0 + s / 3600.0
+        }))
+    }
+}
+
+/// Format an exposure time like exiftool: `1/250` below one second.
+fn format_exposure((num, den): (u32, u32)) -> String {
+    if num == 0 || num >= den {
+        return format!("{}", num as f64 / den as f64);
+    }
+    format!("1/{}", (den as f64 / num as f64).round())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Little-endian TIFF with IFD0 (make, model, Exif and GPS pointers),
+    /// an Exif IFD and a GPS IFD.
+    fn tiff() -> Vec<u8> {
+        struct Ifd(Vec<(u16, u16, u32, Vec<u8>)>);
+        let mut out = b"II*\0".to_vec();
+        out.extend(8u32.to_le_bytes());
+
+        let rational = |n: u32, d: u32| [n.to_le_bytes(), d.to_le_bytes()].concat();
+        let ifd0 = Ifd(vec![
+            (0x010F, 2, 6, b"Canon\0".to_vec()),
+            (0x0110, 2, 9, b"EOS R5 \0\0".to_vec()),
+            (0x8769, 4, 1, Vec::new()),
+            (0x8825, 4, 1, Vec::new()),
+        ]);
+        let exif_ifd = Ifd(vec![
+            (0x829A, 5, 1, rational(1, 250)),
+            (0x829D, 5, 1, rational(28, 10)),
+            (0x8827, 3, 1, 400u16.to_le_bytes().to_vec()),
+            (0x9003, 2, 20, b"2024:05:01 12:30:00\0".to_vec()),
+            (0xA002, 4, 1, 8192u32.to_le_bytes().to_vec()),
+            (0xA003, 4, 1, 5464u32.to_le_bytes().to_vec()),
+        ]);
+        let gps_ifd = Ifd(vec![
+            (1, 2, 2, b"N\0".to_vec()),
+            (2, 5, 3, [rational(40, 1), rational(30, 1), rational(0, 1)].concat()),
+            (3, 2, 2, b"W\0".to_vec()),
+            (4, 5, 3, [rational(73, 1), rational(15, 1), rational(0, 1)].concat()),
+        ]);
+
+        // Lay out each IFD followed by its out-of-line values
+        let mut ifd_offsets = Vec::new();
+        let mut blobs = Vec::new();
+        let mut pos = 8u32;
+        for ifd in [&ifd0, &exif_ifd, &gps_ifd] {
+            ifd_offsets.push(pos);
+            pos += 2 + ifd.0.len() as u32 * 12 + 4;
+            let mut values = Vec::new();
+            for (_, _, _, data) in &ifd.0 {
+                if data.len() > 4 {
+                    values.push(Some(pos));
+                    pos += data.len() as u32;
+                } else {
+                    values.push(None);
+                }
+            }
+            blobs.push(values);
+        }
+
+        for (i, ifd) in [&ifd0, &exif_ifd, &gps_ifd].into_iter().enumerate() {
+            out.extend((ifd.0.len() as u16).to_le_bytes());
+            for (j, (tag, kind, count, data)) in ifd.0.iter().enumerate() {
+                out.extend(tag.to_le_bytes());
+                out.extend(kind.to_le_bytes());
+                out.extend(count.to_le_bytes());
+                let value = match (tag, blobs[i][j]) {
+                    (0x8769, _) => ifd_offsets[1].to_le_bytes().to_vec(),
+                    (0x8825, _) => ifd_offsets[2].to_le_bytes().to_vec(),
+                    (_, Some(offset)) => offset.to_le_bytes().to_vec(),
+                    (_, None) => data.clone(),
+                };
+                out.extend(&value);
+                out.extend(vec![0; 4 - value.len()]);
+            }
+            out.extend(0u32.to_le_bytes());
+            for (_, _, _, data) in ifd.0.iter().filter(|e| e.3.len() > 4) {
+                out.extend(data);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_read_tiff() {
+        let exif = ExifData::from_reader(Cursor::new(tiff())).unwrap();
+        assert_eq!(exif.make.as_deref(), Some("Canon"));
+        assert_eq!(exif.model.as_deref(), Some("EOS R5"));
+        assert_eq!(exif.exposure.as_deref(), Some("1/250"));
+        assert_eq!(exif.f_number, Some(2.8));
+        assert_eq!(exif.iso, Some(400));
+        assert_eq!(exif.date_taken.as_deref(), Some("2024:05:01 12:30:00"));
+        assert_eq!((exif.width, exif.height), (Some(8192), Some(5464)));
+        assert_eq!(exif.gps_latitude, Some(40.5));
+        assert_eq!(exif.gps_longitude, Some(-73.25));
+    }
+
+    #[test]
+    fn test_read_jpeg() {
+        let tiff = tiff();
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        jpeg.extend(((tiff.len() + 8) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(&tiff);
+        // SOF0: precision, height, width, components
+        jpeg.extend([0xFF, 0xC0, 0x00, 0x08, 0x08, 0x02, 0xD0, 0x05, 0x00, 0x00]);
+        jpeg.extend([0xFF, 0xDA, 0x00, 0x02, 0xFF, 0xD9]);
+
+        let exif = ExifData::from_reader(Cursor::new(jpeg)).unwrap();
+        assert_eq!(exif.model.as_deref(), Some("EOS R5"));
+        assert_eq!((exif.width, exif.height), (Some(1280), Some(720)));
+        assert!(ExifData::from_reader(Cursor::new(b"GIF89a".to_vec())).is_err());
+    }
+}
@@ -1,5 +1,7 @@
 //! Metadata extractors for different file types.
 
+use super::exif::ExifData;
+use super::mp4_info::Mp4MediaInfo;
 use super::FileMetadata;
 use serde_json::Value;
 use std::path::Path;
@@ -147,6 +149,116 @@ impl MetadataExtractor for BasicFileExtractor {
     }
 }
 
+/// JSON number from a float, 0 if not finite.
+fn float(value: f64) -> Value {
+    Value::Number(serde_json::Number::from_f64(value).unwrap_or(serde_json::Number::from(0)))
+}
+
+/// Native MP4/MOV extractor reading the `moov` atom tree directly.
+pub struct NativeMp4Extractor;
+
+impl MetadataExtractor for NativeMp4Extractor {
+    fn name(&self) -> &'static str {
+        "mp4"
+    }
+
+    fn supports(&self, path: &Path, _mime: Option<&str>) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(|ext| matches!(ext.to_lowercase().as_str(), "mp4" | "m4v" | "mov" | "m4a" | "3gp" | "3g2"))
+            .unwrap_or(false)
+    }
+
+    fn extract(&self, path: &Path) -> Result<FileMetadata, String> {
+        let info = Mp4MediaInfo::read(path).map_err(|e| format!("Failed to parse MP4: {}", e))?;
+        let mime = BasicFileExtractor::guess_mime(path);
+
+        let mut metadata = match &info.video_codec {
+            Some(codec) => FileMetadata::video(&mime, info.duration, info.width, info.height, codec),
+            None if !info.audio.is_empty() => {
+                FileMetadata::new("audio", &mime).with_extra("duration", float(info.duration))
+            }
+            None => return Err("No video or audio tracks".to_string()),
+        };
+
+        if let Some(fps) = info.frame_rate {
+            metadata = metadata.with_extra("frame_rate", float(fps));
+        }
+        metadata = metadata.with_extra("has_audio", Value::Bool(!info.audio.is_empty()));
+        if let Some(audio) = info.audio.first() {
+            let channels: u32 = info.audio.iter().map(|t| t.channels).sum();
+            metadata = metadata
+                .with_extra("audio_codec", Value::String(audio.codec.clone()))
+                .with_extra("audio_channels", Value::Number(channels.into()))
+                .with_extra("audio_sample_rate", Value::Number(audio.sample_rate.into()));
+        }
+        if let Some(layout) = info.channel_layout() {
+            metadata = metadata.with_extra("channel_layout", Value::String(layout));
+        }
+        if let Some(tc) = info.timecode {
+            metadata = metadata.with_extra("timecode", Value::String(tc));
+        }
+
+        Ok(metadata)
+    }
+}
+
+/// Native EXIF extractor for JPEG and TIFF-based RAW files.
+pub struct NativeExifExtractor;
+
+impl MetadataExtractor for NativeExifExtractor {
+    fn name(&self) -> &'static str {
+        "exif-native"
+    }
+
+    fn supports(&self, path: &Path, _mime: Option<&str>) -> bool {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .map(|ext| {
+                matches!(
+                    ext.to_lowercase().as_str(),
+                    "jpg" | "jpeg" | "tiff" | "tif" | "dng" | "nef" | "arw" | "cr2"
+                )
+            })
+            .unwrap_or(false)
+    }
+
+    fn extract(&self, path: &Path) -> Result<FileMetadata, String> {
+        let exif = ExifData::read(path).map_err(|e| format!("Failed to read EXIF: {}", e))?;
+        let mime = BasicFileExtractor::guess_mime(path);
+        let mut metadata = FileMetadata::photo(&mime, exif.width.unwrap_or(0), exif.height.unwrap_or(0));
+
+        if let Some(model) = exif.model {
+            metadata = metadata.with_extra("camera_model", Value::String(model));
+        }
+        if let Some(make) = exif.make {
+            metadata = metadata.with_extra("camera_make", Value::String(make));
+        }
+        if let Some(iso) = exif.iso {
+            metadata = metadata.with_extra("iso", Value::Number(iso.into()));
+        }
+        if let Some(exposure) = exif.exposure {
+            metadata = metadata.with_extra("exposure", Value::String(exposure));
+        }
+        if let Some(f) = exif.f_number {
+            metadata = metadata.with_extra("f_number", float(f));
+        }
+        if let Some(fl) = exif.focal_length {
+            metadata = metadata.with_extra("focal_length", float(fl));
+        }
+        if let Some(date) = exif.date_taken {
+            metadata = metadata.with_extra("date_taken", Value::String(date));
+        }
+        if let (Some(lat), Some(lon)) = (exif.gps_latitude, exif.gps_longitude) {
+            metadata = metadata
+                .with_extra("gps_latitude", float(lat))
+                .with_extra("gps_longitude", float(lon));
+        }
+
+        Ok(metadata)
+    }
+}
+
 /// Video metadata extractor using ffprobe.
 pub struct VideoFFprobeExtractor;
 
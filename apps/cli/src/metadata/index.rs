@@ -0,0 +1,262 @@
+//! Secondary index over stored metadata.
+//!
+//! Scalar metadata fields are kept column-wise under `.dits/meta/index/`, one
+//! `<field>.json` file mapping content hash to value, so a query only reads
+//! the columns it references instead of every metadata JSON. The index
+//! records the generation of the metadata store it was built from and is
+//! rebuilt once the store has changed since.
+
+use super::query::MetaQuery;
+use super::{FileMetadata, MetadataStore};
+use crate::core::Hash;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Column every indexed entry has; its length is the index entry count.
+const TYPE_COLUMN: &str = "type";
+
+/// One column: content hash (hex) to field value.
+type Column = BTreeMap<String, Value>;
+
+/// Column-wise metadata index under `.dits/meta/index/`.
+pub struct MetadataIndex {
+    /// Directory holding the column files.
+    dir: PathBuf,
+}
+
+impl MetadataIndex {
+    /// Create an index handle for a repository.
+    pub fn new(dits_dir: &Path) -> Self {
+        Self {
+            dir: dits_dir.join("meta").join("index"),
+        }
+    }
+
+    fn column_path(&self, field: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", field))
+    }
+
+    /// Fields that have a column.
+    fn fields(&self) -> io::Result<Vec<String>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut fields = Vec::new();
+        for file in fs::read_dir(&self.dir)? {
+            let name = file?.file_name().to_string_lossy().to_string();
+            if let Some(field) = name.strip_suffix(".json") {
+                fields.push(field.to_string());
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Generation of the metadata store the index was built from.
+    fn generation(&self) -> io::Result<Option<u64>> {
+        match fs::read_to_string(self.dir.join("generation")) {
+            Ok(text) => Ok(text.trim().parse().ok()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Load one column. A missing column is empty.
+    pub fn column(&self, field: &str) -> io::Result<Column> {
+        let path = self.column_path(field);
+        if !path.exists() {
+            return Ok(Column::new());
+        }
+        let json = fs::read_to_string(&path)?;
+        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn write_column(&self, field: &str, column: &Column) -> io::Result<()> {
+        let json = serde_json::to_string(column).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = self.dir.join(format!("{}.json.tmp", field));
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, self.column_path(field))
+    }
+
+    /// Add or replace entries, writing each affected column once.
+    ///
+    /// A replaced entry loses the values of fields it no longer has.
+    pub fn update<'a>(&self, entries: impl IntoIterator<Item = (&'a Hash, &'a FileMetadata)>) -> io::Result<()> {
+        let mut changes: HashMap<String, Vec<(String, Value)>> = HashMap::new();
+        let mut updated = HashSet::new();
+        for (hash, metadata) in entries {
+            let hex = hash.to_hex();
+            for (field, value) in indexed_fields(metadata) {
+                changes.entry(field).or_default().push((hex.clone(), value));
+            }
+            updated.insert(hex);
+        }
+        if updated.is_empty() {
+            return Ok(());
+        }
+        for field in self.fields()? {
+            changes.entry(field).or_default();
+        }
+
+        fs::create_dir_all(&self.dir)?;
+        for (field, values) in changes {
+            let mut column = self.column(&field)?;
+            let before = column.len();
+            column.retain(|hex, _| !updated.contains(hex));
+            if values.is_empty() && column.len() == before {
+                continue;
+            }
+            column.extend(values);
+            if column.is_empty() {
+                fs::remove_file(self.column_path(&field))?;
+            } else {
+                self.write_column(&field, &column)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rebuild the whole index from the metadata store.
+    pub fn rebuild(&self, store: &MetadataStore) -> io::Result<usize> {
+        // Read before the entries, so changes made meanwhile make it stale
+        let generation = store.generation()?;
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir)?;
+        }
+
+        let mut loaded = Vec::new();
+        for hash in store.list()? {
+            if let Some(metadata) = store.load(&hash)? {
+                loaded.push((hash, metadata));
+            }
+        }
+        self.update(loaded.iter().map(|(h, m)| (h, m)))?;
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join("generation"), generation.to_string())?;
+        Ok(loaded.len())
+    }
+
+    /// Whether the index reflects the store as it is now.
+    ///
+    /// Besides the generation, the entry count is compared, which catches
+    /// entries written by versions that did not keep a generation.
+    pub fn is_current(&self, store: &MetadataStore) -> io::Result<bool> {
+        Ok(self.generation()? == Some(store.generation()?) && self.column(TYPE_COLUMN)?.len() == store.count()?)
+    }
+}
+
+/// Scalar fields of a metadata record, flattened as stored.
+fn indexed_fields(metadata: &FileMetadata) -> Vec<(String, Value)> {
+    let Ok(Value::Object(map)) = serde_json::to_value(metadata) else {
+        return Vec::new();
+    };
+    map.into_iter()
+        .filter(|(field, value)| {
+            !matches!(value, Value::Object(_) | Value::Array(_) | Value::Null)
+                && field.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+        })
+        .collect()
+}
+
+/// A query bound to the index columns it references.
+pub struct MetaFilter {
+    query: MetaQuery,
+    columns: HashMap<String, Column>,
+}
+
+impl MetaFilter {
+    /// Load the columns a query needs, rebuilding a stale index first.
+    pub fn new(dits_dir: &Path, query: MetaQuery) -> io::Result<Self> {
+        let store = MetadataStore::new(dits_dir);
+        let index = MetadataIndex::new(dits_dir);
+        if !index.is_current(&store)? {
+            index.rebuild(&store)?;
+        }
+
+        let mut columns = HashMap::new();
+        for field in query.fields() {
+            if field != "path" {
+                columns.insert(field.to_string(), index.column(field)?);
+            }
+        }
+        Ok(Self { query, columns })
+    }
+
+    /// The query being evaluated.
+    pub fn query(&self) -> &MetaQuery {
+        &self.query
+    }
+
+    /// Whether a file at `path` with the given content matches.
+    pub fn matches(&self, path: &str, content_hash: &Hash) -> bool {
+        let hex = content_hash.to_hex();
+        let path = Value::String(path.to_string());
+        self.query.matches(|field| {
+            if field == "path" {
+                return Some(&path);
+            }
+            self.columns.get(field).and_then(|c| c.get(&hex))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_filter_uses_index() {
+        let temp = tempdir().unwrap();
+        let store = MetadataStore::new(temp.path());
+        store.init().unwrap();
+
+        let uhd = Hash::from_hex("aaaa1234567890abcdef1234567890abcdef1234567890abcdef1234567890ab").unwrap();
+        let hd = Hash::from_hex("bbbb1234567890abcdef1234567890abcdef1234567890abcdef1234567890ab").unwrap();
+        let photo = Hash::from_hex("cccc1234567890abcdef1234567890abcdef1234567890abcdef1234567890ab").unwrap();
+        store.store(&uhd, &FileMetadata::video("video/quicktime", 42.0, 3840, 2160, "apch")).unwrap();
+        store.store(&hd, &FileMetadata::video("video/mp4", 90.0, 1920, 1080, "avc1")).unwrap();
+
+        let query: MetaQuery = "codec=prores width>=3840 duration>30".parse().unwrap();
+        let filter = MetaFilter::new(temp.path(), query.clone()).unwrap();
+        assert!(filter.matches("a/uhd.mov", &uhd));
+        assert!(!filter.matches("b/hd.mp4", &hd));
+        assert!(temp.path().join("meta/index/codec.json").exists());
+
+        // New metadata makes the index stale; it is rebuilt on next use
+        store.store(&photo, &FileMetadata::photo("image/jpeg", 6000, 4000)).unwrap();
+        let filter = MetaFilter::new(temp.path(), "type=photo path~.jpg".parse().unwrap()).unwrap();
+        assert!(filter.matches("stills/001.jpg", &photo));
+        assert!(!filter.matches("stills/001.png", &photo));
+        assert!(!filter.matches("a/uhd.jpg", &uhd));
+        assert_eq!(MetadataIndex::new(temp.path()).column("type").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_replaced_metadata_is_reindexed() {
+        let temp = tempdir().unwrap();
+        let store = MetadataStore::new(temp.path());
+        store.init().unwrap();
+
+        let clip = Hash::from_hex("aaaa1234567890abcdef1234567890abcdef1234567890abcdef1234567890ab").unwrap();
+        store.store(&clip, &FileMetadata::video("video/quicktime", 42.0, 3840, 2160, "apch")).unwrap();
+        assert!(MetaFilter::new(temp.path(), "codec=prores".parse().unwrap()).unwrap().matches("a.mov", &clip));
+
+        // Same entry count, different content: the index must still notice
+        store.store(&clip, &FileMetadata::photo("image/jpeg", 6000, 4000)).unwrap();
+        let index = MetadataIndex::new(temp.path());
+        assert!(!index.is_current(&store).unwrap());
+        let filter = MetaFilter::new(temp.path(), "type=photo".parse().unwrap()).unwrap();
+        assert!(filter.matches("a.jpg", &clip));
+        assert!(index.is_current(&store).unwrap());
+        assert!(!index.column("codec").unwrap().contains_key(&clip.to_hex()));
+
+        // Updating in place drops fields the entry no longer has
+        index.update([(&clip, &FileMetadata::video("video/mp4", 10.0, 1920, 1080, "avc1"))]).unwrap();
+        index.update([(&clip, &FileMetadata::photo("image/jpeg", 6000, 4000))]).unwrap();
+        assert!(index.column("codec").unwrap().is_empty());
+        assert_eq!(index.column("type").unwrap().len(), 1);
+    }
+}
@@ -4,11 +4,22 @@
 //! Metadata is stored separately from the content-addressed objects
 //! to avoid changing object identity.
 
+mod exif;
 mod extractor;
+mod index;
+mod mp4_info;
+mod query;
 mod registry;
 mod store;
 
-pub use extractor::{MetadataExtractor, BasicFileExtractor, VideoFFprobeExtractor, PhotoExifExtractor};
+pub use exif::ExifData;
+pub use extractor::{
+    MetadataExtractor, BasicFileExtractor, NativeExifExtractor, NativeMp4Extractor, PhotoExifExtractor,
+    VideoFFprobeExtractor,
+};
+pub use index::{MetaFilter, MetadataIndex};
+pub use mp4_info::{AudioTrack, Mp4MediaInfo};
+pub use query::{MetaQuery, QueryOp, QueryTerm};
 pub use registry::MetadataRegistry;
 pub use store::MetadataStore;
 
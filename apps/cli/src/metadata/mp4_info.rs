@@ -0,0 +1,388 @@
+//! Native MP4/MOV media info, read from the `moov` atom tree.
+//!
+//! Reads `mvhd` (duration), `tkhd` (display size), `mdhd`/`hdlr` (track
+//! timing and kind), `stsd` (codec fourcc, audio channels), `stts`/`stsz`
+//! (frame rate) and the first `tmcd` sample (start timecode). Media data is
+//! never read beyond that one timecode sample.
+
+use crate::mp4::{Atom, AtomType, Mp4Parser, ParseError};
+use byteorder::{BigEndian, ReadBytesExt};
+use std::fs::File;
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Largest atom payload read into memory. Only table headers are needed.
+const MAX_PAYLOAD: u64 = 64 * 1024;
+
+/// An audio track.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioTrack {
+    /// Sample entry fourcc (e.g. `mp4a`, `lpcm`, `sowt`).
+    pub codec: String,
+    /// Channel count.
+    pub channels: u32,
+    /// Sample rate in Hz.
+    pub sample_rate: u32,
+}
+
+/// Media properties of an MP4/MOV file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mp4MediaInfo {
+    /// Movie duration in seconds.
+    pub duration: f64,
+    /// Display width of the first video track.
+    pub width: u32,
+    /// Display height of the first video track.
+    pub height: u32,
+    /// Sample entry fourcc of the first video track (e.g. `avc1`, `apch`).
+    pub video_codec: Option<String>,
+    /// Frames per second of the first video track.
+    pub frame_rate: Option<f64>,
+    /// Audio tracks in file order.
+    pub audio: Vec<AudioTrack>,
+    /// Start timecode from the first timecode track, `HH:MM:SS:FF`
+    /// (`;` before the frames for drop-frame).
+    pub timecode: Option<String>,
+}
+
+impl Mp4MediaInfo {
+    /// Read media info from a file.
+    pub fn read(path: &Path) -> Result<Self, ParseError> {
+        let atoms = Mp4Parser::parse_tree(path)?;
+        let moov = atoms
+            .iter()
+            .find(|a| a.atom_type == AtomType::Moov)
+            .ok_or_else(|| ParseError::MissingAtom("moov".to_string()))?;
+        let mut file = File::open(path)?;
+
+        let mut info = Self::default();
+        if let Some(mvhd) = child(moov, b"mvhd") {
+            let mvhd = read_payload(&mut file, mvhd)?;
+            let (timescale, duration) = parse_media_header(&mvhd)?;
+            if timescale > 0 {
+                info.duration = duration as f64 / timescale as f64;
+            }
+        }
+        for trak in moov.children.iter().filter(|a| a.atom_type == AtomType::Trak) {
+            info.read_track(&mut file, trak)?;
+        }
+        Ok(info)
+    }
+
+    /// Channel layout summary: `stereo`, `5.1`, or e.g. `8x mono` for one
+    /// track per channel as is common in production MOVs.
+    pub fn channel_layout(&self) -> Option<String> {
+        let first = self.audio.first()?;
+        if self.audio.iter().all(|t| t.channels == first.channels) && self.audio.len() > 1 {
+            return Some(format!("{}x {}", self.audio.len(), layout_name(first.channels)));
+        }
+        let names: Vec<String> = self.audio.iter().map(|t| layout_name(t.channels)).collect();
+        Some(names.join("+"))
+    }
+
+    fn read_track(&mut self, file: &mut File, trak: &Atom) -> Result<(), ParseError> {
+        let Some(mdia) = child(trak, b"mdia") else { return Ok(()) };
+        let Some(stbl) = child(mdia, b"minf").and_then(|minf| child(minf, b"stbl")) else {
+            return Ok(());
+        };
+        let (Some(hdlr), Some(mdhd), Some(stsd)) = (child(mdia, b"hdlr"), child(mdia, b"mdhd"), child(stbl, b"stsd"))
+        else {
+            return Ok(());
+        };
+
+        // hdlr: version/flags(4) pre_defined(4) handler_type(4)
+        let hdlr = read_payload(file, hdlr)?;
+        let handler = hdlr.get(8..12).unwrap_or_default();
+        let (timescale, media_duration) = parse_media_header(&read_payload(file, mdhd)?)?;
+
+        // stsd: version/flags(4) entry_count(4), then size(4) format(4) and
+        // the first sample entry, which starts with reserved(6) data_ref_index(2)
+        let stsd = read_payload(file, stsd)?;
+        let format = fourcc(stsd.get(12..16).unwrap_or_default());
+        let entry = stsd.get(16..).unwrap_or_default();
+
+        match handler {
+            b"vide" if self.video_codec.is_none() => {
+                self.video_codec = Some(format);
+                let mut r = Cursor::new(entry);
+                r.seek(SeekFrom::Start(24))?;
+                let (coded_width, coded_height) = (r.read_u16::<BigEndian>()?, r.read_u16::<BigEndian>()?);
+                let (width, height) = match child(trak, b"tkhd") {
+                    Some(tkhd) => parse_tkhd_size(&read_payload(file, tkhd)?)?,
+                    None => (0, 0),
+                };
+                self.width = if width > 0 { width } else { coded_width.into() };
+                self.height = if height > 0 { height } else { coded_height.into() };
+                self.frame_rate = frame_rate(file, stbl, timescale, media_duration)?;
+            }
+            b"soun" => {
+                let mut r = Cursor::new(entry);
+                r.seek(SeekFrom::Start(8))?;
+                let version = r.read_u16::<BigEndian>()?;
+                r.seek(SeekFrom::Start(16))?;
+                let mut channels = u32::from(r.read_u16::<BigEndian>()?);
+                r.seek(SeekFrom::Start(24))?;
+                let mut sample_rate = r.read_u32::<BigEndian>()? >> 16;
+                if version == 2 {
+                    // QuickTime v2: sizeOfStructOnly(4) sampleRate(f64) channels(4)
+                    r.seek(SeekFrom::Start(32))?;
+                    sample_rate = r.read_f64::<BigEndian>()? as u32;
+                    channels = r.read_u32::<BigEndian>()?;
+                }
+                if sample_rate == 0 {
+                    sample_rate = timescale;
+                }
+                self.audio.push(AudioTrack { codec: format, channels, sample_rate });
+            }
+            b"tmcd" if self.timecode.is_none() && format == "tmcd" => {
+                // reserved(4) flags(4) timescale(4) frame_duration(4) number_of_frames(1)
+                let mut r = Cursor::new(entry);
+                r.seek(SeekFrom::Start(12))?;
+                let flags = r.read_u32::<BigEndian>()?;
+                r.seek(SeekFrom::Start(24))?;
+                let fps = u32::from(r.read_u8()?);
+                if let Some(frame) = first_sample_u32(file, stbl)? {
+                    self.timecode = Some(frames_to_timecode(frame.into(), fps, flags & 1 != 0));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Direct child atom by tag.
+fn child<'a>(atom: &'a Atom, tag: &[u8; 4]) -> Option<&'a Atom> {
+    let atom_type = AtomType::from_tag(tag);
+    atom.children.iter().find(|c| c.atom_type == atom_type)
+}
+
+/// Read an atom's payload, up to `MAX_PAYLOAD` bytes.
+fn read_payload(file: &mut File, atom: &Atom) -> Result<Vec<u8>, ParseError> {
+    file.seek(SeekFrom::Start(atom.data_start))?;
+    let mut data = Vec::new();
+    file.take(atom.data_length.min(MAX_PAYLOAD)).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+fn fourcc(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).trim_end().to_string()
+}
+
+/// Timescale and duration from an `mvhd` or `mdhd` payload.
+fn parse_media_header(data: &[u8]) -> Result<(u32, u64), ParseError> {
+    let mut r = Cursor::new(data);
+    let version = r.read_u8()?;
+    if version == 1 {
+        // flags(3) creation(8) modification(8)
+        r.seek(SeekFrom::Start(20))?;
+        Ok((r.read_u32::<BigEndian>()?, r.read_u64::<BigEndian>()?))
+    } else {
+        r.seek(SeekFrom::Start(12))?;
+        Ok((r.read_u32::<BigEndian>()?, r.read_u32::<BigEndian>()?.into()))
+    }
+}
+
+/// Display width and height (16.16 fixed point) from a `tkhd` payload.
+fn parse_tkhd_size(data: &[u8]) -> Result<(u32, u32), ParseError> {
+    let mut r = Cursor::new(data);
+    let offset = if r.read_u8()? == 1 { 88 } else { 76 };
+    r.seek(SeekFrom::Start(offset))?;
+    Ok((r.read_u32::<BigEndian>()? >> 16, r.read_u32::<BigEndian>()? >> 16))
+}
+
+/// Frame rate from `stts` when constant, else the average over `stsz`.
+fn frame_rate(file: &mut File, stbl: &Atom, timescale: u32, duration: u64) -> Result<Option<f64>, ParseError> {
+    if timescale == 0 {
+        return Ok(None);
+    }
+    if let Some(stts) = child(stbl, b"stts") {
+        let mut r = Cursor::new(read_payload(file, stts)?);
+        r.seek(SeekFrom::Start(4))?;
+        if r.read_u32::<BigEndian>()? == 1 {
+            let _count = r.read_u32::<BigEndian>()?;
+            let delta = r.read_u32::<BigEndian>()?;
+            if delta > 0 {
+                return Ok(Some(round_rate(timescale as f64 / delta as f64)));
+            }
+        }
+    }
+    if let (Some(stsz), true) = (child(stbl, b"stsz"), duration > 0) {
+        let mut r = Cursor::new(read_payload(file, stsz)?);
+        r.seek(SeekFrom::Start(8))?;
+        let samples = r.read_u32::<BigEndian>()?;
+        return Ok(Some(round_rate(samples as f64 * timescale as f64 / duration as f64)));
+    }
+    Ok(None)
+}
+
+fn round_rate(fps: f64) -> f64 {
+    (fps * 1000.0).round() / 1000.0
+}
+
+/// The first 32-bit sample of a track, located through `stco`/`co64`.
+fn first_sample_u32(file: &mut File, stbl: &Atom) -> Result<Option<u32>, ParseError> {
+    let offset = if let Some(stco) = child(stbl, b"stco") {
+        let mut r = Cursor::new(read_payload(file, stco)?);
+        r.seek(SeekFrom::Start(4))?;
+        if r.read_u32::<BigEndian>()? == 0 {
+            return Ok(None);
+        }
+        u64::from(r.read_u32::<BigEndian>()?)
+    } else if let Some(co64) = child(stbl, b"co64") {
+        let mut r = Cursor::new(read_payload(file, co64)?);
+        r.seek(SeekFrom::Start(4))?;
+        if r.read_u32::<BigEndian>()? == 0 {
+            return Ok(None);
+        }
+        r.read_u64::<BigEndian>()?
+    } else {
+        return Ok(None);
+    };
+
+    file.seek(SeekFrom::Start(offset))?;
+    Ok(Some(file.read_u32::<BigEndian>()?))
+}
+
+/// Format a frame count as SMPTE timecode.
+fn frames_to_timecode(mut frame: u64, fps: u32, drop_frame: bool) -> String {
+    let fps = u64::from(fps.max(1));
+    if drop_frame && fps % 30 == 0 {
+        // Frame numbers 0 and 1 (0-3 at 60) are skipped each minute, except every tenth
+        let dropped = fps / 15;
+        let per_ten_minutes = fps * 600 - dropped * 9;
+        let per_minute = fps * 60 - dropped;
+        let (tens, rem) = (frame / per_ten_minutes, frame % per_ten_minutes);
+        frame += dropped * 9 * tens;
+        if rem > dropped {
+            frame += dropped * ((rem - dropped) / per_minute);
+        }
+    }
+
+    let ff = frame % fps;
+    let total_secs = frame / fps;
+    let sep = if drop_frame { ';' } else { ':' };
+    format!(
+        "{:02}:{:02}:{:02}{}{:02}",
+        (total_secs / 3600) % 24,
+        (total_secs / 60) % 60,
+        total_secs % 60,
+        sep,
+        ff
+    )
+}
+
+fn layout_name(channels: u32) -> String {
+    match channels {
+        1 => "mono".to_string(),
+        2 => "stereo".to_string(),
+        6 => "5.1".to_string(),
+        8 => "7.1".to_string(),
+        n => format!("{}ch", n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn atom(tag: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+        let mut out = ((payload.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(tag);
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn full(version_flags: u32, body: &[u8]) -> Vec<u8> {
+        let mut out = version_flags.to_be_bytes().to_vec();
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn be(values: &[u32]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    fn track(handler: &[u8; 4], tkhd: Option<Vec<u8>>, timescale: u32, duration: u32, entry: Vec<u8>, stbl_extra: Vec<u8>) -> Vec<u8> {
+        let mdhd = atom(b"mdhd", &full(0, &be(&[0, 0, timescale, duration, 0])));
+        let mut hdlr_body = be(&[0]);
+        hdlr_body.extend_from_slice(handler);
+        hdlr_body.extend_from_slice(&[0; 12]);
+        let hdlr = atom(b"hdlr", &full(0, &hdlr_body));
+        let mut stsd_body = be(&[1]);
+        stsd_body.extend(entry);
+        let mut stbl = atom(b"stsd", &full(0, &stsd_body));
+        stbl.extend(stbl_extra);
+        let minf = atom(b"minf", &atom(b"stbl", &stbl));
+        let mdia = atom(b"mdia", &[mdhd, hdlr, minf].concat());
+        atom(b"trak", &[tkhd.unwrap_or_default(), mdia].concat())
+    }
+
+    fn sample_entry(format: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut entry = vec![0; 6];
+        entry.extend_from_slice(&1u16.to_be_bytes());
+        entry.extend_from_slice(body);
+        atom(format, &entry)
+    }
+
+    #[test]
+    fn test_read_mov_media_info() {
+        // Video: 1920x1080 ProRes at 24000/1001
+        let mut tkhd = vec![0u8; 72];
+        tkhd.extend(be(&[1920 << 16, 1080 << 16]));
+        let tkhd = atom(b"tkhd", &full(0, &tkhd));
+        let mut visual = vec![0u8; 16];
+        visual.extend_from_slice(&1920u16.to_be_bytes());
+        visual.extend_from_slice(&1080u16.to_be_bytes());
+        visual.extend_from_slice(&[0; 50]);
+        let stts = atom(b"stts", &full(0, &be(&[1, 240, 1001])));
+        let video = track(b"vide", Some(tkhd), 24000, 240_240, sample_entry(b"apch", &visual), stts);
+
+        // Two mono PCM tracks at 48 kHz
+        let mut sound = vec![0u8; 8];
+        sound.extend_from_slice(&1u16.to_be_bytes());
+        sound.extend_from_slice(&24u16.to_be_bytes());
+        sound.extend_from_slice(&[0; 4]);
+        sound.extend(be(&[48000 << 16]));
+        let audio = track(b"soun", None, 48000, 480_000, sample_entry(b"lpcm", &sound), Vec::new());
+
+        // Drop-frame timecode track whose sample is appended after moov
+        let mut tmcd = be(&[0, 1, 30000, 1001]);
+        tmcd.extend_from_slice(&[30, 0]);
+        let timecode_entry = sample_entry(b"tmcd", &tmcd);
+
+        let mvhd = atom(b"mvhd", &full(0, &[be(&[0, 0, 600, 6006]), vec![0; 80]].concat()));
+        let build = |sample_offset: u32| {
+            let stco = atom(b"stco", &full(0, &be(&[1, sample_offset])));
+            let timecode = track(b"tmcd", None, 30000, 300_300, timecode_entry.clone(), stco);
+            let moov = atom(b"moov", &[mvhd.clone(), video.clone(), audio.clone(), audio.clone(), timecode].concat());
+            [atom(b"ftyp", b"qt  \0\0\0\0"), moov].concat()
+        };
+        let header_len = build(0).len() as u32;
+        // 01:00:00;00 at 29.97 DF is frame 107892
+        let file = [build(header_len + 8), atom(b"mdat", &107_892u32.to_be_bytes())].concat();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("clip.mov");
+        File::create(&path).unwrap().write_all(&file).unwrap();
+
+        let info = Mp4MediaInfo::read(&path).unwrap();
+        assert_eq!(info.duration, 10.01);
+        assert_eq!((info.width, info.height), (1920, 1080));
+        assert_eq!(info.video_codec.as_deref(), Some("apch"));
+        assert_eq!(info.frame_rate, Some(23.976));
+        assert_eq!(info.audio.len(), 2);
+        assert_eq!(info.audio[0], AudioTrack { codec: "lpcm".into(), channels: 1, sample_rate: 48000 });
+        assert_eq!(info.channel_layout().as_deref(), Some("2x mono"));
+        assert_eq!(info.timecode.as_deref(), Some("01:00:00;00"));
+    }
+
+    #[test]
+    fn test_frames_to_timecode() {
+        assert_eq!(frames_to_timecode(86_400, 24, false), "01:00:00:00");
+        assert_eq!(frames_to_timecode(1799, 30, true), "00:00:59;29");
+        assert_eq!(frames_to_timecode(1800, 30, true), "00:01:00;02");
+        assert_eq!(frames_to_timecode(17_982, 30, true), "00:10:00;00");
+    }
+}
@@ -0,0 +1,264 @@
+//! Metadata query language.
+//!
+//! A query is a list of terms that must all match:
+//!
+//! ```text
+//! codec=prores width>=3840 duration>30
+//! type=photo camera_model~"eos r" iso<=800
+//! ```
+//!
+//! Each term is `field OP value` with `=`, `!=`, `>`, `>=`, `<`, `<=` or `~`
+//! (case-insensitive substring). Fields are `path`, `type`, `mime` or any key
+//! an extractor stored. Numbers compare numerically, everything else
+//! case-insensitively as text. A term on a field the file does not have
+//! never matches. `codec` and `audio_codec` also match codec families, so
+//! `codec=prores` matches `apch` and `codec=h264` matches `avc1`.
+
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// Comparison operator of a query term.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Contains,
+}
+
+impl QueryOp {
+    /// Operators in match order (two-character operators first).
+    const ALL: [(&'static str, QueryOp); 7] = [
+        (">=", QueryOp::Ge),
+        ("<=", QueryOp::Le),
+        ("!=", QueryOp::Ne),
+        ("=", QueryOp::Eq),
+        (">", QueryOp::Gt),
+        ("<", QueryOp::Lt),
+        ("~", QueryOp::Contains),
+    ];
+}
+
+/// One `field OP value` term.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTerm {
+    /// Field name.
+    pub field: String,
+    /// Comparison operator.
+    pub op: QueryOp,
+    /// Value to compare against, as written.
+    pub value: String,
+}
+
+impl QueryTerm {
+    /// Whether a field value satisfies this term.
+    pub fn matches(&self, value: Option<&Value>) -> bool {
+        let Some(value) = value else { return false };
+
+        let text = match value {
+            Value::String(s) => s.clone(),
+            Value::Number(n) => n.to_string(),
+            Value::Bool(b) => b.to_string(),
+            _ => return false,
+        };
+
+        if self.op == QueryOp::Contains {
+            return text.to_lowercase().contains(&self.value.to_lowercase());
+        }
+        if self.op == QueryOp::Eq && self.is_codec_field() {
+            if let (Some(a), Some(b)) = (codec_family(&text), codec_family(&self.value)) {
+                return a == b;
+            }
+        }
+
+        let ordering = match (value.as_f64(), self.value.parse::<f64>()) {
+            (Some(a), Ok(b)) => a.partial_cmp(&b),
+            _ => Some(text.to_lowercase().cmp(&self.value.to_lowercase())),
+        };
+        let Some(ordering) = ordering else { return false };
+
+        match self.op {
+            QueryOp::Eq => ordering == Ordering::Equal,
+            QueryOp::Ne => ordering != Ordering::Equal,
+            QueryOp::Gt => ordering == Ordering::Greater,
+            QueryOp::Ge => ordering != Ordering::Less,
+            QueryOp::Lt => ordering == Ordering::Less,
+            QueryOp::Le => ordering != Ordering::Greater,
+            QueryOp::Contains => unreachable!("handled above"),
+        }
+    }
+
+    fn is_codec_field(&self) -> bool {
+        self.field == "codec" || self.field == "audio_codec"
+    }
+}
+
+impl FromStr for QueryTerm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (pos, token, op) = QueryOp::ALL
+            .iter()
+            .filter_map(|(token, op)| s.find(token).map(|pos| (pos, *token, *op)))
+            .min_by_key(|(pos, token, _)| (*pos, std::cmp::Reverse(token.len())))
+            .ok_or_else(|| format!("expected field=value, field>value, ... in '{}'", s))?;
+
+        let field = s[..pos].trim();
+        if field.is_empty() || !field.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.') {
+            return Err(format!("invalid field name in '{}'", s));
+        }
+        let value = s[pos + token.len()..].trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+
+        Ok(Self {
+            field: field.to_string(),
+            op,
+            value: value.to_string(),
+        })
+    }
+}
+
+/// A parsed metadata query: all terms must match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetaQuery {
+    /// Terms in query order.
+    pub terms: Vec<QueryTerm>,
+}
+
+impl MetaQuery {
+    /// Fields referenced by the query, without duplicates.
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields: Vec<&str> = self.terms.iter().map(|t| t.field.as_str()).collect();
+        fields.sort_unstable();
+        fields.dedup();
+        fields
+    }
+
+    /// Evaluate the query, looking up field values with `get`.
+    pub fn matches<'a>(&self, get: impl Fn(&str) -> Option<&'a Value>) -> bool {
+        self.terms.iter().all(|term| term.matches(get(&term.field)))
+    }
+}
+
+impl FromStr for MetaQuery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let terms = split_terms(s)
+            .iter()
+            .map(|t| t.parse())
+            .collect::<Result<Vec<QueryTerm>, _>>()?;
+        if terms.is_empty() {
+            return Err("empty metadata query".to_string());
+        }
+        Ok(Self { terms })
+    }
+}
+
+impl fmt::Display for MetaQuery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let terms: Vec<String> = self
+            .terms
+            .iter()
+            .map(|t| {
+                let op = QueryOp::ALL.iter().find(|(_, op)| *op == t.op).map(|(s, _)| *s).unwrap_or("=");
+                if t.value.contains(char::is_whitespace) {
+                    format!("{}{}\"{}\"", t.field, op, t.value)
+                } else {
+                    format!("{}{}{}", t.field, op, t.value)
+                }
+            })
+            .collect();
+        write!(f, "{}", terms.join(" "))
+    }
+}
+
+/// Split on whitespace outside double quotes.
+fn split_terms(s: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in s.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    terms
+}
+
+/// Codec family for fourccs and ffprobe codec names.
+fn codec_family(codec: &str) -> Option<&'static str> {
+    Some(match codec.to_lowercase().as_str() {
+        "prores" | "apch" | "apcn" | "apcs" | "apco" | "ap4h" | "ap4x" => "prores",
+        "h264" | "avc" | "avc1" | "avc3" => "h264",
+        "hevc" | "h265" | "hvc1" | "hev1" => "hevc",
+        "dnxhd" | "dnxhr" | "avdn" | "avdh" => "dnxhd",
+        "av1" | "av01" => "av1",
+        "vp9" | "vp09" => "vp9",
+        "aac" | "mp4a" => "aac",
+        "pcm" | "lpcm" | "sowt" | "twos" | "in24" | "in32" | "fl32" => "pcm",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_and_match() {
+        let query: MetaQuery = "codec=prores width>=3840 duration>30 camera~\"fx 6\"".parse().unwrap();
+        assert_eq!(query.terms.len(), 4);
+        assert_eq!(query.terms[1].op, QueryOp::Ge);
+        assert_eq!(query.terms[3].value, "fx 6");
+        assert_eq!(query.fields(), ["camera", "codec", "duration", "width"]);
+        assert_eq!(query.to_string(), "codec=prores width>=3840 duration>30 camera~\"fx 6\"");
+
+        let clip = json!({"codec": "apch", "width": 3840, "duration": 42.5, "camera": "Sony FX 6"});
+        assert!(query.matches(|f| clip.get(f)));
+
+        let short = json!({"codec": "apch", "width": 3840, "duration": 12.0, "camera": "Sony FX 6"});
+        assert!(!query.matches(|f| short.get(f)));
+
+        // Missing fields never match
+        let photo = json!({"width": 6000});
+        assert!(!"codec!=prores".parse::<MetaQuery>().unwrap().matches(|f| photo.get(f)));
+
+        assert!("".parse::<MetaQuery>().is_err());
+        assert!("prores".parse::<MetaQuery>().is_err());
+        assert!(">5".parse::<MetaQuery>().is_err());
+    }
+
+    #[test]
+    fn test_term_comparisons() {
+        let term = |s: &str| s.parse::<QueryTerm>().unwrap();
+        assert!(term("codec=h264").matches(Some(&json!("avc1"))));
+        assert!(!term("codec=hevc").matches(Some(&json!("avc1"))));
+        assert!(term("type=VIDEO").matches(Some(&json!("video"))));
+        assert!(term("date_taken>=2024:01:01").matches(Some(&json!("2024:05:01 12:30:00"))));
+        assert!(term("frame_rate<24.5").matches(Some(&json!(23.976))));
+        assert!(term("has_audio=true").matches(Some(&json!(true))));
+        assert!(term("width!=1920").matches(Some(&json!(3840))));
+    }
+}
@@ -1,6 +1,9 @@
 //! Metadata extractor registry.
 
-use super::extractor::{MetadataExtractor, BasicFileExtractor, VideoFFprobeExtractor, PhotoExifExtractor};
+use super::extractor::{
+    MetadataExtractor, BasicFileExtractor, NativeExifExtractor, NativeMp4Extractor, PhotoExifExtractor,
+    VideoFFprobeExtractor,
+};
 use super::FileMetadata;
 use std::path::Path;
 
@@ -22,7 +25,11 @@ impl MetadataRegistry {
             extractors: Vec::new(),
         };
 
-        // Add specialized extractors first (higher priority)
+        // Native extractors need no external tools, so they go first
+        registry.register(Box::new(NativeMp4Extractor));
+        registry.register(Box::new(NativeExifExtractor));
+
+        // External-tool extractors cover formats the native ones can't parse
         registry.register(Box::new(VideoFFprobeExtractor));
         registry.register(Box::new(PhotoExifExtractor));
 
@@ -47,15 +54,19 @@ impl MetadataRegistry {
         None
     }
 
-    /// Extract metadata using the best available extractor.
+    /// Extract metadata using the best available extractor, falling back to
+    /// the next supporting one when an extractor fails.
     pub fn extract(&self, path: &Path) -> Result<FileMetadata, String> {
         let mime = self.guess_mime(path);
 
-        if let Some(extractor) = self.find_extractor(path, mime.as_deref()) {
-            extractor.extract(path)
-        } else {
-            Err("No suitable extractor found".to_string())
+        let mut last_error = "No suitable extractor found".to_string();
+        for extractor in self.extractors.iter().filter(|e| e.supports(path, mime.as_deref())) {
+            match extractor.extract(path) {
+                Ok(metadata) => return Ok(metadata),
+                Err(e) => last_error = e,
+            }
         }
+        Err(last_error)
     }
 
     /// Guess MIME type from path.
@@ -97,4 +108,17 @@ mod tests {
         assert!(extractor.is_some());
         assert_eq!(extractor.unwrap().name(), "basic");
     }
+
+    #[test]
+    fn test_extract_falls_back() {
+        let registry = MetadataRegistry::new();
+        assert_eq!(registry.list_extractors()[..2], ["mp4", "exif-native"]);
+
+        // A truncated MP4 fails the native parser and falls through to basic
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.mp4");
+        std::fs::write(&path, b"not an mp4").unwrap();
+        let metadata = registry.extract(&path).unwrap();
+        assert_eq!(metadata.extra["size"], 10);
+    }
 }
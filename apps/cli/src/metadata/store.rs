@@ -10,6 +10,8 @@ use std::path::{Path, PathBuf};
 pub struct MetadataStore {
     /// Base path for metadata storage.
     base_path: PathBuf,
+    /// File holding the store's generation.
+    generation_path: PathBuf,
 }
 
 impl MetadataStore {
@@ -17,6 +19,7 @@ impl MetadataStore {
     pub fn new(dits_dir: &Path) -> Self {
         Self {
             base_path: dits_dir.join("meta").join("manifest"),
+            generation_path: dits_dir.join("meta").join("generation"),
         }
     }
 
@@ -48,7 +51,7 @@ impl MetadataStore {
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
         fs::write(&path, json)?;
-        Ok(())
+        self.bump_generation()
     }
 
     /// Load metadata for a manifest.
@@ -76,6 +79,7 @@ impl MetadataStore {
         let path = self.metadata_path(manifest_hash);
         if path.exists() {
             fs::remove_file(&path)?;
+            self.bump_generation()?;
             Ok(true)
         } else {
             Ok(false)
@@ -120,6 +124,27 @@ impl MetadataStore {
     pub fn count(&self) -> io::Result<usize> {
         Ok(self.list()?.len())
     }
+
+    /// Number of changes made to the store; every store and delete bumps it.
+    pub fn generation(&self) -> io::Result<u64> {
+        match fs::read_to_string(&self.generation_path) {
+            Ok(text) => text
+                .trim()
+                .parse()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Record a change. Called after the change is on disk, so an index
+    /// built from a generation already includes everything before it.
+    fn bump_generation(&self) -> io::Result<()> {
+        let next = self.generation().unwrap_or(0) + 1;
+        let tmp = self.generation_path.with_extension("tmp");
+        fs::write(&tmp, next.to_string())?;
+        fs::rename(&tmp, &self.generation_path)
+    }
 }
 
 #[cfg(test)]
@@ -130,6 +130,17 @@ impl Mp4Parser {
         })
     }
 
+    /// Parse the atom tree of any ISOBMFF file, without requiring `mdat`
+    /// or rejecting fragmented files. Returns the top-level atoms.
+    pub fn parse_tree<P: AsRef<Path>>(path: P) -> Result<Vec<Atom>, ParseError> {
+        let mut file = File::open(path.as_ref())?;
+        let file_size = file.metadata()?.len();
+        if file_size < 8 {
+            return Err(ParseError::FileTooSmall);
+        }
+        Self::parse_atoms(&mut file, 0, file_size)
+    }
+
     /// Parse atoms within a range of the file.
     fn parse_atoms(
         file: &mut File,
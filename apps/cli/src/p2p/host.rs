@@ -3,6 +3,7 @@
 //! This module implements the server side of DITS P2P functionality,
 //! allowing repositories to be shared over the network using QUIC.
 
+use std::net::{IpAddr, SocketAddr, UdpSocket};
 use std::path::PathBuf;
 use std::sync::Arc;
 use anyhow::{Context, Result};
@@ -11,6 +12,8 @@ use tracing::info;
 
 use crate::p2p::types::{ShareId, ShareInfo};
 use crate::p2p::crypto::generate_join_code;
+use crate::p2p::net::create_server_endpoint;
+use crate::p2p::sync::{serve, PeerAddress};
 use crate::Repository;
 
 /// Configuration for the P2P host server
@@ -62,18 +65,31 @@ impl P2pHost {
     }
 
     /// Start the P2P host server
+    ///
+    /// Serves repository sync requests until the process is stopped.
     pub async fn start(&self) -> Result<()> {
-        let addr = format!("{}:{}", self.config.bind_addr, self.config.port);
+        let addr: SocketAddr = format!("{}:{}", self.config.bind_addr, self.config.port)
+            .parse()
+            .with_context(|| format!("Invalid bind address: {}", self.config.bind_addr))?;
         info!("Starting P2P host server on {}", addr);
 
-        // TODO: Initialize QUIC endpoint
+        let (endpoint, fingerprint) = create_server_endpoint(addr)?;
+        let address = PeerAddress {
+            host: advertised_host(&addr),
+            port: endpoint.local_addr()?.port(),
+            fingerprint: Some(fingerprint),
+        };
+
         // TODO: Register with signaling server
-        // TODO: Start accepting connections
 
         println!("🚀 P2P repository share active!");
         println!("📋 Join code: {}", self.join_code);
-        println!("🌐 Address: {}", addr);
+        println!("🌐 Address: {}", address);
         println!("📁 Repository: {}", self.config.repo_path.display());
+        println!();
+        println!("On the same network, fetch with:");
+        println!("   dits remote add <name> {}", address);
+        println!("   dits fetch <name>");
 
         if self.config.daemon {
             println!("🔄 Running in daemon mode...");
@@ -81,9 +97,10 @@ impl P2pHost {
             println!("   (Would run in background)");
         } else {
             println!("Press Ctrl+C to stop sharing");
-            // TODO: Wait for shutdown signal
         }
 
+        let dits_dir = self.repository.read().await.dits_dir().to_path_buf();
+        serve(endpoint, dits_dir).await;
         Ok(())
     }
 
@@ -114,6 +131,26 @@ impl P2pHost {
     }
 }
 
+/// Host name peers should use to reach a server bound to `addr`.
+///
+/// For a wildcard bind this is the address of the interface that routes to
+/// other machines; connecting a UDP socket sends no packets.
+fn advertised_host(addr: &SocketAddr) -> String {
+    if !addr.ip().is_unspecified() {
+        return addr.ip().to_string();
+    }
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("192.0.2.1:9")?;
+            socket.local_addr()
+        })
+        .map(|local| local.ip())
+        .ok()
+        .filter(|ip| !ip.is_unspecified())
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]))
+        .to_string()
+}
+
 /// Start a P2P host server with the given configuration
 pub async fn start_p2p_host(config: HostConfig) -> Result<P2pHost> {
     let host = P2pHost::new(config).await?;
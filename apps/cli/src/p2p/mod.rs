@@ -10,6 +10,7 @@
 //! - **Connect**: Join a shared repository using a join code
 //! - **Send**: Transfer files directly to a peer
 //! - **Receive**: Accept file transfers from peers
+//! - **Sync**: Fetch a shared repository's commits and missing chunks
 //! - **Signal Server**: Optional rendezvous server for NAT traversal
 
 pub mod client;
@@ -18,6 +19,7 @@ pub mod host;
 pub mod net;
 pub mod protocol;
 pub mod rendezvous;
+pub mod sync;
 pub mod transfer;
 pub mod types;
 
@@ -181,6 +181,14 @@ pub fn generate_self_signed_cert_with_fingerprint() -> (
     (certs, key, fingerprint)
 }
 
+/// Select the `ring` crypto provider for rustls.
+///
+/// More than one provider is compiled in, so rustls cannot pick one itself.
+fn install_crypto_provider() {
+    // Fails only when a provider is already installed
+    let _ = rustls::crypto::ring::default_provider().install_default();
+}
+
 /// Create high-throughput transport configuration optimized for P2P file transfers
 pub fn create_high_throughput_transport_config() -> TransportConfig {
     let mut transport = TransportConfig::default();
@@ -223,6 +231,7 @@ pub fn create_client_endpoint_with_pinned_cert(
         "Creating client endpoint with pinned cert: {}",
         hex::encode(expected_fingerprint)
     );
+    install_crypto_provider();
     let bind_addr: SocketAddr = format!("0.0.0.0:{}", port).parse().unwrap();
     let mut endpoint =
         Endpoint::client(bind_addr).map_err(|e| ConnectionError::Connect(e.to_string()))?;
@@ -244,6 +253,7 @@ pub fn create_client_endpoint_with_pinned_cert(
 
 /// Create a QUIC client endpoint (development mode - insecure)
 pub fn create_client_endpoint() -> Result<Endpoint, ConnectionError> {
+    install_crypto_provider();
     warn!("SECURITY: Creating client endpoint WITHOUT certificate pinning");
     let bind_addr: SocketAddr = "0.0.0.0:0".parse().unwrap();
     let mut endpoint =
@@ -268,6 +278,7 @@ pub fn create_client_endpoint() -> Result<Endpoint, ConnectionError> {
 pub fn create_server_endpoint(
     bind_addr: SocketAddr,
 ) -> Result<(Endpoint, CertFingerprint), ConnectionError> {
+    install_crypto_provider();
     let (certs, key, fingerprint) = generate_self_signed_cert_with_fingerprint();
 
     let crypto = rustls::ServerConfig::builder()
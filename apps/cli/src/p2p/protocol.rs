@@ -3,10 +3,13 @@
 //! All network messages are defined here. Messages are serialized with bincode
 //! and prefixed with a 4-byte little-endian length.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 
 use crate::p2p::types::{ChunkId, ContentHash, DirEntry, FileAttr, ShareId, ShareInfo};
 use crate::p2p::MAX_MESSAGE_SIZE;
+use crate::store::reachability::ObjectId;
 
 /// Protocol error types
 #[derive(Debug, Clone)]
@@ -66,6 +69,14 @@ pub enum NetMessage {
     // Multi-share
     ListShares(ListSharesRequest),
     ListSharesResponse(ListSharesResponse),
+
+    // Repository sync
+    ListRefs(ListRefsRequest),
+    ListRefsResponse(ListRefsResponse),
+    WantHave(WantHaveRequest),
+    ObjectList(ObjectListMessage),
+    GetObjects(GetObjectsRequest),
+    ObjectData(ObjectDataMessage),
 }
 
 // === Handshake Messages ===
@@ -217,6 +228,44 @@ pub struct ListSharesResponse {
     pub shares: Vec<ShareInfo>,
 }
 
+// === Repository Sync Messages ===
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListRefsRequest {}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ListRefsResponse {
+    /// Ref name (`refs/heads/main`, `refs/tags/v1`) to hex commit hash.
+    pub refs: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WantHaveRequest {
+    /// Hex commit hashes the client wants.
+    pub wants: Vec<String>,
+    /// Hex commit hashes the client already has.
+    pub haves: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObjectListMessage {
+    pub objects: Vec<ObjectId>,
+    pub is_final: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GetObjectsRequest {
+    pub objects: Vec<ObjectId>,
+}
+
+/// One piece of an object; objects larger than a piece span several messages.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ObjectDataMessage {
+    pub id: ObjectId,
+    pub data: Vec<u8>,
+    pub is_final: bool,
+}
+
 // === Serialization ===
 
 /// Serialize a message with length prefix
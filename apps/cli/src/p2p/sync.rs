@@ -0,0 +1,653 @@
+//! Repository sync over a P2P connection.
+//!
+//! Peers exchange the content-addressed objects already in their stores
+//! rather than fixed slices of files, so a fetch moves only the chunks the
+//! receiver lacks:
+//!
+//! 1. The client asks for the host's refs.
+//! 2. It sends the ref tips it wants and the commits it already has; the
+//!    host answers with every object reachable from the wants but not from
+//!    the haves, content first, then manifests, then commits.
+//! 3. The client drops the objects already in its store and requests the
+//!    rest in batches, each on its own QUIC stream.
+//!
+//! Every request is one bidirectional stream. Objects are verified against
+//! their hash and written in the same order as an HTTP fetch, so an
+//! interrupted sync never leaves a commit whose content is incomplete.
+//!
+//! Hosts are addressed as `dits://HOST:PORT/FINGERPRINT`, where the
+//! fingerprint pins the host's self-signed certificate.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use quinn::{Endpoint, RecvStream, SendStream};
+use thiserror::Error;
+use tracing::{debug, info, warn};
+
+use crate::core::{Commit, Hash};
+use crate::p2p::crypto::validate_join_code;
+use crate::p2p::net::{
+    connect, create_client_endpoint, create_client_endpoint_with_pinned_cert, recv_message,
+    send_message, ConnectionError, QuicConnection,
+};
+use crate::p2p::protocol::{
+    ErrorMessage, GetObjectsRequest, ListRefsRequest, ListRefsResponse, NetMessage,
+    ObjectDataMessage, ObjectListMessage, WantHaveRequest,
+};
+use crate::p2p::types::CertFingerprint;
+use crate::p2p::{DEFAULT_P2P_PORT, P2P_CHUNK_SIZE};
+use crate::store::reachability::{self, ObjectId, ObjectKind, TransferError};
+use crate::store::remote_client::{FetchOutcome, TRANSFER_CONCURRENCY};
+use crate::store::{GitTextEngine, ObjectError, ObjectStore, ObjectType, RefStore, Repository};
+
+/// Object ids per `ObjectList` message.
+const LIST_BATCH: usize = 4096;
+
+/// Objects requested per stream.
+const GET_BATCH: usize = 64;
+
+/// Server name presented to the host; the certificate is pinned, not named.
+const SERVER_NAME: &str = "dits-p2p";
+
+/// Error code for malformed or unexpected requests.
+pub const ERROR_BAD_REQUEST: u32 = 400;
+
+/// Error code for failures while serving a request.
+pub const ERROR_INTERNAL: u32 = 500;
+
+/// Errors during a P2P sync.
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("{0}")]
+    Connection(#[from] ConnectionError),
+
+    #[error("Peer error {code}: {message}")]
+    Peer { code: u32, message: String },
+
+    #[error("Unexpected message from peer: {0}")]
+    Unexpected(String),
+
+    #[error("Peer did not send {kind} {id}", kind = .0.kind.as_str(), id = .0.id)]
+    Incomplete(ObjectId),
+
+    #[error("Invalid peer address: {0}")]
+    InvalidAddress(String),
+
+    #[error("Transfer error: {0}")]
+    Transfer(#[from] TransferError),
+
+    #[error("Object error: {0}")]
+    Object(#[from] ObjectError),
+
+    #[error("Invalid hash: {0}")]
+    InvalidHash(#[from] hex::FromHexError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Turn a reply that is not the expected one into an error.
+fn unexpected(msg: NetMessage) -> SyncError {
+    match msg {
+        NetMessage::Error(e) => SyncError::Peer { code: e.code, message: e.message },
+        other => {
+            let debug = format!("{:?}", other);
+            SyncError::Unexpected(debug.split('(').next().unwrap_or_default().to_string())
+        }
+    }
+}
+
+fn parse_hashes(hexes: &[String]) -> Result<Vec<Hash>, SyncError> {
+    Ok(hexes.iter().map(|h| Hash::from_hex(h)).collect::<Result<_, _>>()?)
+}
+
+// ========== Addressing ==========
+
+/// Address of a shared repository: `dits://HOST:PORT[/FINGERPRINT]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerAddress {
+    pub host: String,
+    pub port: u16,
+    /// BLAKE3 hash of the host certificate. Without it the certificate is
+    /// not verified.
+    pub fingerprint: Option<CertFingerprint>,
+}
+
+impl PeerAddress {
+    /// Parse a `dits://` URL. The scheme and port are optional.
+    pub fn parse(url: &str) -> Result<Self, SyncError> {
+        let invalid = || SyncError::InvalidAddress(url.to_string());
+        let rest = url.strip_prefix("dits://").unwrap_or(url);
+        let (authority, fingerprint) = match rest.split_once('/') {
+            Some((authority, fp)) => (authority, Some(fp.trim_end_matches('/'))),
+            None => (rest, None),
+        };
+
+        if let Some(code) = fingerprint.filter(|fp| validate_join_code(fp)) {
+            return Err(SyncError::InvalidAddress(format!(
+                "{} (join code {} needs the signal server; use the address printed by `dits p2p share`)",
+                url, code
+            )));
+        }
+
+        // A bracketed IPv6 address without a port also contains ':'
+        let (host, port) = match authority.rsplit_once(':').filter(|_| !authority.ends_with(']')) {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid())?),
+            None => (authority, DEFAULT_P2P_PORT),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+
+        let fingerprint = match fingerprint.filter(|fp| !fp.is_empty()) {
+            Some(fp) => {
+                let mut bytes = [0u8; 32];
+                hex::decode_to_slice(fp, &mut bytes).map_err(|_| invalid())?;
+                Some(bytes)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            host: host.to_string(),
+            port,
+            fingerprint,
+        })
+    }
+
+    /// Connect to the host, pinning its certificate if a fingerprint is known.
+    ///
+    /// The endpoint must outlive the connection.
+    pub async fn connect(&self) -> Result<(Endpoint, QuicConnection), SyncError> {
+        let addr = tokio::net::lookup_host(format!("{}:{}", self.host, self.port))
+            .await?
+            .next()
+            .ok_or_else(|| SyncError::InvalidAddress(self.to_string()))?;
+
+        let endpoint = match self.fingerprint {
+            Some(fingerprint) => create_client_endpoint_with_pinned_cert(0, fingerprint)?,
+            None => create_client_endpoint()?,
+        };
+        let connection = connect(&endpoint, addr, SERVER_NAME).await?;
+        Ok((endpoint, connection))
+    }
+}
+
+impl fmt::Display for PeerAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "dits://{}:{}", self.host, self.port)?;
+        if let Some(fingerprint) = &self.fingerprint {
+            write!(f, "/{}", hex::encode(fingerprint))?;
+        }
+        Ok(())
+    }
+}
+
+// ========== Host ==========
+
+/// Refs a host advertises: branches under `refs/heads/`, tags under `refs/tags/`.
+pub fn advertised_refs(refs: &RefStore) -> std::io::Result<BTreeMap<String, Hash>> {
+    let mut advertised = BTreeMap::new();
+    for name in refs.list_branches()? {
+        if let Some(hash) = refs.get_branch(&name)? {
+            advertised.insert(format!("refs/heads/{}", name), hash);
+        }
+    }
+    for name in refs.list_tags()? {
+        if let Some(hash) = refs.get_tag(&name)? {
+            advertised.insert(format!("refs/tags/{}", name), hash);
+        }
+    }
+    Ok(advertised)
+}
+
+/// Serve sync requests on every connection accepted by `endpoint`.
+pub async fn serve(endpoint: Endpoint, dits_dir: PathBuf) {
+    while let Some(incoming) = endpoint.accept().await {
+        let dits_dir = dits_dir.clone();
+        tokio::spawn(async move {
+            match incoming.await {
+                Ok(connection) => serve_connection(QuicConnection::new(connection), dits_dir).await,
+                Err(e) => warn!("P2P handshake failed: {}", e),
+            }
+        });
+    }
+}
+
+/// Serve sync requests on one connection until the peer closes it.
+pub async fn serve_connection(connection: QuicConnection, dits_dir: PathBuf) {
+    let peer = connection.remote_address();
+    info!("Peer connected: {}", peer);
+
+    while let Ok((send, recv)) = connection.accept_stream().await {
+        let dits_dir = dits_dir.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_stream(send, recv, &dits_dir).await {
+                debug!("Request from {} failed: {}", peer, e);
+            }
+        });
+    }
+
+    info!("Peer disconnected: {}", peer);
+}
+
+/// Answer the single request made on a stream.
+async fn serve_stream(mut send: SendStream, mut recv: RecvStream, dits_dir: &Path) -> Result<(), SyncError> {
+    let result = match recv_message(&mut recv).await? {
+        NetMessage::ListRefs(_) => send_refs(&mut send, dits_dir).await,
+        NetMessage::WantHave(request) => send_object_list(&mut send, dits_dir, &request).await,
+        NetMessage::GetObjects(request) => send_objects(&mut send, dits_dir, &request).await,
+        other => Err(unexpected(other)),
+    };
+
+    if let Err(e) = &result {
+        let code = match e {
+            SyncError::Unexpected(_) | SyncError::InvalidHash(_) => ERROR_BAD_REQUEST,
+            _ => ERROR_INTERNAL,
+        };
+        let reply = NetMessage::Error(ErrorMessage { code, message: e.to_string() });
+        // The peer may already be gone; the original error is what matters
+        let _ = send_message(&mut send, &reply).await;
+    }
+    let _ = send.finish();
+    result
+}
+
+async fn send_refs(send: &mut SendStream, dits_dir: &Path) -> Result<(), SyncError> {
+    let refs = advertised_refs(&RefStore::new(dits_dir))?
+        .into_iter()
+        .map(|(name, hash)| (name, hash.to_hex()))
+        .collect();
+    send_message(send, &NetMessage::ListRefsResponse(ListRefsResponse { refs })).await?;
+    Ok(())
+}
+
+/// List every object reachable from the wants but not from the haves.
+async fn send_object_list(send: &mut SendStream, dits_dir: &Path, request: &WantHaveRequest) -> Result<(), SyncError> {
+    let objects = {
+        let store = ObjectStore::new(dits_dir);
+        let wants = parse_hashes(&request.wants)?;
+        let haves = parse_hashes(&request.haves)?;
+
+        let exclude = reachability::ancestors(&store, &haves)?;
+        let commits = reachability::commits_between(&store, &wants, &exclude)?;
+        reachability::objects_for_commits(&store, &commits)?
+    };
+
+    // Always send one message, even for an empty list
+    let mut batches: Vec<&[ObjectId]> = objects.chunks(LIST_BATCH).collect();
+    if batches.is_empty() {
+        batches.push(&[]);
+    }
+    let last = batches.len() - 1;
+    for (i, batch) in batches.into_iter().enumerate() {
+        let msg = NetMessage::ObjectList(ObjectListMessage {
+            objects: batch.to_vec(),
+            is_final: i == last,
+        });
+        send_message(send, &msg).await?;
+    }
+    Ok(())
+}
+
+/// Send the requested objects in order, split into pieces.
+async fn send_objects(send: &mut SendStream, dits_dir: &Path, request: &GetObjectsRequest) -> Result<(), SyncError> {
+    let store = ObjectStore::new(dits_dir);
+    let git = GitTextEngine::open(dits_dir).ok();
+
+    for id in &request.objects {
+        let data = reachability::read_object(&store, git.as_ref(), id)?;
+        let mut offset = 0;
+        loop {
+            let end = (offset + P2P_CHUNK_SIZE).min(data.len());
+            let msg = NetMessage::ObjectData(ObjectDataMessage {
+                id: id.clone(),
+                data: data[offset..end].to_vec(),
+                is_final: end == data.len(),
+            });
+            send_message(send, &msg).await?;
+            if end == data.len() {
+                break;
+            }
+            offset = end;
+        }
+    }
+    Ok(())
+}
+
+// ========== Client ==========
+
+/// Ask the host for its refs.
+pub async fn list_refs(connection: &QuicConnection) -> Result<BTreeMap<String, Hash>, SyncError> {
+    let (mut send, mut recv) = connection.open_stream().await?;
+    send_message(&mut send, &NetMessage::ListRefs(ListRefsRequest {})).await?;
+    let _ = send.finish();
+
+    match recv_message(&mut recv).await? {
+        NetMessage::ListRefsResponse(response) => response
+            .refs
+            .into_iter()
+            .map(|(name, hex)| Ok((name, Hash::from_hex(&hex)?)))
+            .collect(),
+        other => Err(unexpected(other)),
+    }
+}
+
+/// Ask the host which objects reach `wants` from `haves`.
+async fn want_have(connection: &QuicConnection, wants: &[Hash], haves: &[Hash]) -> Result<Vec<ObjectId>, SyncError> {
+    let (mut send, mut recv) = connection.open_stream().await?;
+    let request = WantHaveRequest {
+        wants: wants.iter().map(Hash::to_hex).collect(),
+        haves: haves.iter().map(Hash::to_hex).collect(),
+    };
+    send_message(&mut send, &NetMessage::WantHave(request)).await?;
+    let _ = send.finish();
+
+    let mut objects = Vec::new();
+    loop {
+        match recv_message(&mut recv).await? {
+            NetMessage::ObjectList(list) => {
+                objects.extend(list.objects);
+                if list.is_final {
+                    return Ok(objects);
+                }
+            }
+            other => return Err(unexpected(other)),
+        }
+    }
+}
+
+/// Download a batch of objects on a new stream.
+pub async fn get_objects(connection: &QuicConnection, batch: &[ObjectId]) -> Result<Vec<(ObjectId, Vec<u8>)>, SyncError> {
+    let (mut send, mut recv) = connection.open_stream().await?;
+    let request = GetObjectsRequest { objects: batch.to_vec() };
+    send_message(&mut send, &NetMessage::GetObjects(request)).await?;
+    let _ = send.finish();
+
+    let mut objects = Vec::with_capacity(batch.len());
+    for id in batch {
+        let mut data = Vec::new();
+        loop {
+            match recv_message(&mut recv).await? {
+                NetMessage::ObjectData(piece) if piece.id == *id => {
+                    data.extend_from_slice(&piece.data);
+                    if piece.is_final {
+                        break;
+                    }
+                }
+                other => return Err(unexpected(other)),
+            }
+        }
+        objects.push((id.clone(), data));
+    }
+    Ok(objects)
+}
+
+/// Commits at the tip of every local ref, including remote-tracking refs.
+fn local_tips(repo: &Repository) -> std::io::Result<Vec<Hash>> {
+    let mut tips = HashSet::new();
+    let mut dirs = vec![repo.dits_dir().join("refs")];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                dirs.push(entry.path());
+            } else if let Ok(hash) = Hash::from_hex(fs::read_to_string(entry.path())?.trim()) {
+                if repo.objects().has_object(ObjectType::Commit, &hash) {
+                    tips.insert(hash);
+                }
+            }
+        }
+    }
+    Ok(tips.into_iter().collect())
+}
+
+/// Fetch every object reachable from the host's refs that is missing locally.
+///
+/// Content is stored as it arrives; manifests and commits are held back
+/// until everything they reference, including parent commits, is present. Refs are not touched; the
+/// caller decides where the advertised refs go.
+pub async fn fetch(connection: &QuicConnection, repo: &Repository) -> Result<FetchOutcome, SyncError> {
+    let refs = list_refs(connection).await?;
+    let store = repo.objects();
+    let git = repo.git_engine();
+
+    let mut outcome = FetchOutcome {
+        refs: refs.clone(),
+        objects_received: 0,
+        bytes_received: 0,
+    };
+
+    let mut wants: Vec<Hash> = refs
+        .values()
+        .filter(|h| !store.has_object(ObjectType::Commit, h))
+        .copied()
+        .collect();
+    wants.sort_by_key(|h| h.to_hex());
+    wants.dedup();
+    if wants.is_empty() {
+        return Ok(outcome);
+    }
+
+    let haves = local_tips(repo)?;
+    let missing: Vec<ObjectId> = want_have(connection, &wants, &haves)
+        .await?
+        .into_iter()
+        .filter(|id| !reachability::has_object(store, git, id))
+        .collect();
+    let (graph, content): (Vec<ObjectId>, Vec<ObjectId>) = missing
+        .into_iter()
+        .partition(|id| matches!(id.kind, ObjectKind::Manifest | ObjectKind::Commit));
+
+    let sizes: Vec<u64> = stream::iter(content.chunks(GET_BATCH))
+        .map(|batch| async move {
+            let mut bytes = 0;
+            for (id, data) in get_objects(connection, batch).await? {
+                reachability::write_object(store, git, &id, &data)?;
+                bytes += data.len() as u64;
+            }
+            Ok::<u64, SyncError>(bytes)
+        })
+        .buffer_unordered(TRANSFER_CONCURRENCY)
+        .try_collect()
+        .await?;
+    outcome.objects_received += content.len();
+    outcome.bytes_received += sizes.iter().sum::<u64>();
+
+    // Batches stay in list order: manifests, then commits oldest first
+    let held: Vec<Vec<(ObjectId, Vec<u8>)>> = stream::iter(graph.chunks(GET_BATCH))
+        .map(|batch| get_objects(connection, batch))
+        .buffered(TRANSFER_CONCURRENCY)
+        .try_collect()
+        .await?;
+
+    for (id, data) in held.iter().flatten() {
+        let hash = id.hash()?;
+        match id.kind {
+            ObjectKind::Manifest => {
+                ObjectStore::verify_object(ObjectType::Manifest, &hash, data)?;
+                let manifest = ObjectStore::decode_manifest(data)?;
+                ObjectStore::check_manifest_paths(&manifest)?;
+                if let Some(absent) = reachability::manifest_objects(&manifest)
+                    .into_iter()
+                    .find(|c| !reachability::has_object(store, git, c))
+                {
+                    return Err(SyncError::Incomplete(absent));
+                }
+            }
+            _ => {
+                ObjectStore::verify_object(ObjectType::Commit, &hash, data)?;
+                let commit: Commit = serde_json::from_slice(data).map_err(ObjectError::from)?;
+                if !store.has_object(ObjectType::Manifest, &commit.manifest) {
+                    return Err(SyncError::Incomplete(ObjectId::new(ObjectKind::Manifest, &commit.manifest)));
+                }
+                // Parents come earlier in the list and are already written
+                if let Some(parent) = commit
+                    .all_parents()
+                    .into_iter()
+                    .find(|p| !store.has_object(ObjectType::Commit, p))
+                {
+                    return Err(SyncError::Incomplete(ObjectId::new(ObjectKind::Commit, &parent)));
+                }
+            }
+        }
+        reachability::write_object(store, git, id, data)?;
+        outcome.objects_received += 1;
+        outcome.bytes_received += data.len() as u64;
+    }
+
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::p2p::net::create_server_endpoint;
+    use tempfile::tempdir;
+
+    fn commit_file(repo: &Repository, name: &str, content: &[u8]) {
+        fs::write(repo.work_dir().join(name), content).unwrap();
+        repo.add(name).unwrap();
+        repo.commit(&format!("update {}", name)).unwrap();
+    }
+
+    fn binary(size: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..size)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect()
+    }
+
+    /// Share `repo` on a loopback endpoint and return its pinned address.
+    fn share(repo: &Repository) -> PeerAddress {
+        let (endpoint, fingerprint) = create_server_endpoint("127.0.0.1:0".parse().unwrap()).unwrap();
+        let port = endpoint.local_addr().unwrap().port();
+        tokio::spawn(serve(endpoint, repo.dits_dir().to_path_buf()));
+        PeerAddress {
+            host: "127.0.0.1".to_string(),
+            port,
+            fingerprint: Some(fingerprint),
+        }
+    }
+
+    #[test]
+    fn test_parse_peer_address() {
+        let fp = "ab".repeat(32);
+        let addr = PeerAddress::parse(&format!("dits://192.168.1.20:5000/{}", fp)).unwrap();
+        assert_eq!(addr.host, "192.168.1.20");
+        assert_eq!(addr.port, 5000);
+        assert_eq!(addr.fingerprint, Some([0xab; 32]));
+        assert_eq!(addr.to_string(), format!("dits://192.168.1.20:5000/{}", fp));
+
+        let addr = PeerAddress::parse("dits://editor-mac.local").unwrap();
+        assert_eq!((addr.port, addr.fingerprint), (DEFAULT_P2P_PORT, None));
+
+        assert!(PeerAddress::parse("dits://peer/DEF-456").is_err());
+        assert!(PeerAddress::parse("dits://host:port").is_err());
+        assert!(PeerAddress::parse("dits://host:4433/nothex").is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fetch_transfers_only_missing_chunks() {
+        let host_dir = tempdir().unwrap();
+        let host = Repository::init(host_dir.path()).unwrap();
+        let mut footage = binary(2 * 1024 * 1024, 11);
+        commit_file(&host, "clip.bin", &footage);
+        commit_file(&host, "notes.txt", b"scene 1 notes");
+        host.refs().set_tag("v1", &host.head().unwrap().unwrap()).unwrap();
+
+        let (endpoint, connection) = share(&host).connect().await.unwrap();
+
+        let reader_dir = tempdir().unwrap();
+        let reader = Repository::init(reader_dir.path()).unwrap();
+        let first = fetch(&connection, &reader).await.unwrap();
+        let tip = host.head().unwrap().unwrap();
+        assert_eq!(first.refs.get("refs/heads/main"), Some(&tip));
+        assert_eq!(first.refs.get("refs/tags/v1"), Some(&tip));
+
+        // Every object reachable from the tip is now local
+        let commits = reachability::commits_between(reader.objects(), &[tip], &HashSet::new()).unwrap();
+        assert_eq!(commits.len(), 2);
+        for id in reachability::objects_for_commits(reader.objects(), &commits).unwrap() {
+            assert!(reachability::has_object(reader.objects(), reader.git_engine(), &id));
+        }
+
+        let again = fetch(&connection, &reader).await.unwrap();
+        assert_eq!(again.objects_received, 0);
+
+        // One changed byte only re-sends the chunk around it
+        footage[100] ^= 0xff;
+        commit_file(&host, "clip.bin", &footage);
+        fs::write(reader.dits_dir().join("refs/heads/main"), format!("{}\n", tip.to_hex())).unwrap();
+        let second = fetch(&connection, &reader).await.unwrap();
+        assert_eq!(second.refs.get("refs/heads/main"), host.head().unwrap().as_ref());
+        assert!(second.bytes_received < first.bytes_received / 4);
+
+        connection.close(0, "done");
+        endpoint.wait_idle().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fetch_refuses_manifest_paths_outside_work_tree() {
+        use crate::core::Author;
+
+        let host_dir = tempdir().unwrap();
+        let host = Repository::init(host_dir.path()).unwrap();
+        commit_file(&host, "notes.txt", b"harmless");
+
+        // A malicious peer rewrites a manifest entry to climb out of the work tree
+        let head = host.head().unwrap().unwrap();
+        let mut manifest = host.load_manifest(&host.load_commit(&head).unwrap().manifest).unwrap();
+        let mut entry = manifest.remove("notes.txt").unwrap();
+        entry.path = "../escaped.txt".to_string();
+        manifest.add(entry);
+        let manifest_hash = host.objects().store_manifest(&manifest).unwrap();
+        let crafted = Commit::new(Some(head), manifest_hash, "crafted", Author::new("mallory", "m@example.com"));
+        host.objects().store_commit(&crafted).unwrap();
+        host.refs().set_branch("main", &crafted.hash).unwrap();
+
+        let (endpoint, connection) = share(&host).connect().await.unwrap();
+
+        let reader_dir = tempdir().unwrap();
+        let reader = Repository::init(reader_dir.path()).unwrap();
+        let result = fetch(&connection, &reader).await;
+        assert!(matches!(result, Err(SyncError::Object(ObjectError::InvalidPath(_)))));
+        assert!(!reader.objects().has_object(ObjectType::Manifest, &manifest_hash));
+        assert!(!reader.objects().has_object(ObjectType::Commit, &crafted.hash));
+
+        connection.close(0, "done");
+        endpoint.wait_idle().await;
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fetch_refuses_commits_with_missing_parents() {
+        let host_dir = tempdir().unwrap();
+        let host = Repository::init(host_dir.path()).unwrap();
+        commit_file(&host, "notes.txt", b"take 1");
+        let first = host.head().unwrap().unwrap();
+        commit_file(&host, "notes.txt", b"take 2");
+        let second = host.head().unwrap().unwrap();
+
+        // The host lost the first commit, so its object list stops short
+        let hex = first.to_hex();
+        fs::remove_file(host.dits_dir().join("objects/commits").join(&hex[..2]).join(&hex[2..])).unwrap();
+
+        let (endpoint, connection) = share(&host).connect().await.unwrap();
+
+        let reader_dir = tempdir().unwrap();
+        let reader = Repository::init(reader_dir.path()).unwrap();
+        let result = fetch(&connection, &reader).await;
+        assert!(matches!(result, Err(SyncError::Incomplete(id)) if id == ObjectId::new(ObjectKind::Commit, &first)));
+        assert!(!reader.objects().has_object(ObjectType::Commit, &second));
+
+        connection.close(0, "done");
+        endpoint.wait_idle().await;
+    }
+}
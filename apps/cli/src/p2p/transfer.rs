@@ -1,6 +1,8 @@
 //! P2P file transfer for DITS
 //!
-//! Handles sending and receiving files between peers.
+//! Handles sending and receiving files between peers. Files are split into
+//! fixed `P2P_CHUNK_SIZE` pieces, which suits loose files outside a
+//! repository; repositories sync their stored chunks through [`super::sync`].
 
 use std::collections::HashMap;
 use std::io::{Read, Write};
@@ -93,7 +95,7 @@ pub mod zero_copy {
             file: &File,
             offset: u64,
             size: usize,
-            stream: &mut crate::p2p::net::SendStream,
+            stream: &mut quinn::SendStream,
         ) -> std::io::Result<()> {
             use tokio::io::AsyncWriteExt;
 
@@ -113,8 +115,7 @@ pub mod zero_copy {
 
         impl FileExt for File {
             fn read_exact_at(&self, buf: &mut [u8], offset: u64) -> std::io::Result<()> {
-                use std::os::unix::fs::FileExt;
-                self.read_exact_at(buf, offset)
+                std::os::unix::fs::FileExt::read_exact_at(self, buf, offset)
             }
         }
     }
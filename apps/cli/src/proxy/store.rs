@@ -39,7 +39,7 @@ impl ProxyStore {
     }
 
     /// Get the path to proxy data storage.
-    fn data_path(&self, content_hash: &Hash) -> PathBuf {
+    pub fn data_path(&self, content_hash: &Hash) -> PathBuf {
         let hash_hex = content_hash.to_hex();
         self.base_dir
             .join("data")
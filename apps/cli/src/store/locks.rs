@@ -1,6 +1,10 @@
 //! File locking for exclusive editing.
 //!
 //! Provides file-level locks to prevent concurrent edits to binary files.
+//!
+//! A server keeps the authoritative [`LockStore`] for each hosted
+//! repository. In a clone with a remote, the local store is a cache of the
+//! locks last seen on that remote.
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -15,6 +19,9 @@ const DEFAULT_TTL_SECS: u64 = 8 * 60 * 60;
 /// A file lock.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Lock {
+    /// Unique lock ID, used to release the lock on a remote.
+    #[serde(default)]
+    pub id: String,
     /// Path being locked (relative to repo root).
     pub path: String,
     /// Owner (user identifier).
@@ -25,6 +32,9 @@ pub struct Lock {
     pub expires_at: u64,
     /// Optional reason for locking.
     pub reason: Option<String>,
+    /// Taken from this clone (set on cached copies of remote locks).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub held: bool,
 }
 
 impl Lock {
@@ -32,11 +42,13 @@ impl Lock {
     pub fn new(path: impl Into<String>, owner: impl Into<String>, ttl_secs: u64) -> Self {
         let now = current_timestamp();
         Self {
+            id: uuid::Uuid::new_v4().to_string(),
             path: path.into(),
             owner: owner.into(),
             acquired_at: now,
             expires_at: now + ttl_secs,
             reason: None,
+            held: false,
         }
     }
 
@@ -46,6 +58,12 @@ impl Lock {
         self
     }
 
+    /// Whether this clone holds the lock, either because it took it or
+    /// because it is owned by `user`.
+    pub fn is_mine(&self, user: Option<&str>) -> bool {
+        self.held || Some(self.owner.as_str()) == user
+    }
+
     /// Check if the lock is expired.
     pub fn is_expired(&self) -> bool {
         current_timestamp() > self.expires_at
@@ -102,7 +120,12 @@ impl LockStore {
         }
         let file = File::open(path).ok()?;
         let reader = BufReader::new(file);
-        serde_json::from_reader(reader).ok()
+        let mut locks: HashMap<String, Lock> = serde_json::from_reader(reader).ok()?;
+        // Locks written before IDs existed
+        for lock in locks.values_mut().filter(|l| l.id.is_empty()) {
+            lock.id = uuid::Uuid::new_v4().to_string();
+        }
+        Some(locks)
     }
 
     /// Save locks to disk.
@@ -167,6 +190,43 @@ impl LockStore {
         Ok(())
     }
 
+    /// Cache a lock held on a remote, replacing any lock on the same path.
+    pub fn insert(&mut self, lock: Lock) -> Result<(), LockError> {
+        self.locks.insert(lock.path.clone(), lock);
+        self.save()?;
+        Ok(())
+    }
+
+    /// Drop a cached lock. Returns the lock if there was one.
+    pub fn remove(&mut self, path: &str) -> Result<Option<Lock>, LockError> {
+        let removed = self.locks.remove(path);
+        if removed.is_some() {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    /// Replace every lock with `locks`, e.g. the full list from a remote.
+    ///
+    /// Locks this clone took are still marked as held afterwards.
+    pub fn replace_all(&mut self, locks: Vec<Lock>) -> Result<(), LockError> {
+        let held: Vec<String> = self.locks.values().filter(|l| l.held).map(|l| l.id.clone()).collect();
+        self.locks = locks
+            .into_iter()
+            .map(|mut l| {
+                l.held |= held.contains(&l.id);
+                (l.path.clone(), l)
+            })
+            .collect();
+        self.save()?;
+        Ok(())
+    }
+
+    /// Find a lock by ID.
+    pub fn get_by_id(&self, id: &str) -> Option<&Lock> {
+        self.locks.values().find(|l| l.id == id)
+    }
+
     /// Get lock for a path.
     pub fn get(&self, path: &str) -> Option<&Lock> {
         self.locks.get(path).filter(|l| !l.is_expired())
@@ -191,6 +251,33 @@ impl LockStore {
         self.get(path).is_some()
     }
 
+    /// Check changed `paths` against the locks.
+    ///
+    /// A path locked by someone other than `is_mine` says is a violation, as
+    /// is an unlocked path for which `requires_lock` is true.
+    pub fn violations<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a str>,
+        is_mine: impl Fn(&Lock) -> bool,
+        requires_lock: impl Fn(&str) -> bool,
+    ) -> Vec<LockViolation> {
+        let mut violations = Vec::new();
+        for path in paths {
+            match self.get(path) {
+                Some(lock) if is_mine(lock) => {}
+                Some(lock) => violations.push(LockViolation::LockedByOther {
+                    path: path.to_string(),
+                    owner: lock.owner.clone(),
+                }),
+                None if requires_lock(path) => {
+                    violations.push(LockViolation::NotLocked(path.to_string()))
+                }
+                None => {}
+            }
+        }
+        violations
+    }
+
     /// Clean up expired locks.
     pub fn cleanup_expired(&mut self) {
         let expired: Vec<String> = self.locks.iter()
@@ -224,6 +311,24 @@ impl LockStore {
     }
 }
 
+/// A change that breaks the locking rules.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LockViolation {
+    /// The path is locked by someone else.
+    LockedByOther { path: String, owner: String },
+    /// The path's file type must be locked before editing, and is not.
+    NotLocked(String),
+}
+
+impl std::fmt::Display for LockViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockViolation::LockedByOther { path, owner } => write!(f, "'{}' is locked by {}", path, owner),
+            LockViolation::NotLocked(path) => write!(f, "'{}' must be locked before it is changed (dits lock {})", path, path),
+        }
+    }
+}
+
 /// Lock errors.
 #[derive(Debug, thiserror::Error)]
 pub enum LockError {
@@ -244,6 +349,17 @@ pub enum LockError {
     Io(#[from] std::io::Error),
 }
 
+/// Name local locks are taken under: git `user.email`, else the login name.
+pub fn current_user() -> Option<String> {
+    if let Ok(output) = std::process::Command::new("git").args(["config", "user.email"]).output() {
+        let email = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !email.is_empty() {
+            return Some(email);
+        }
+    }
+    std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok()
+}
+
 /// Get current Unix timestamp.
 fn current_timestamp() -> u64 {
     SystemTime::now()
@@ -255,6 +371,7 @@ fn current_timestamp() -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::FileCategory;
     use tempfile::tempdir;
 
     #[test]
@@ -309,6 +426,48 @@ mod tests {
         assert_eq!(user1_locks.len(), 2);
     }
 
+    #[test]
+    fn test_cache_replace_all() {
+        let dir = tempdir().unwrap();
+        let mut store = LockStore::new(dir.path());
+        store.acquire("old.mov", "user1@example.com", None, None, false).unwrap();
+
+        let remote = Lock::new("scene.prproj", "alice", 3600);
+        store.replace_all(vec![remote.clone()]).unwrap();
+        assert!(!store.is_locked("old.mov"));
+        assert_eq!(store.get_by_id(&remote.id).unwrap().owner, "alice");
+
+        store.remove("scene.prproj").unwrap();
+        assert!(LockStore::new(dir.path()).list().is_empty());
+    }
+
+    #[test]
+    fn test_violations() {
+        let dir = tempdir().unwrap();
+        let mut store = LockStore::new(dir.path());
+        store.acquire("shot.blend", "alice", None, None, false).unwrap();
+        let mut mine = Lock::new("edit.prproj", "bob", 3600);
+        mine.held = true;
+        store.insert(mine).unwrap();
+
+        let paths = ["shot.blend", "edit.prproj", "level.uasset", "notes.txt"];
+        let needs_locking = |p: &str| FileCategory::from_path(Path::new(p)).needs_locking();
+        let violations = store.violations(paths, |l| l.held, needs_locking);
+        assert_eq!(
+            violations,
+            vec![
+                LockViolation::LockedByOther { path: "shot.blend".into(), owner: "alice".into() },
+                LockViolation::NotLocked("level.uasset".into()),
+            ]
+        );
+
+        // Refreshing from the remote keeps our own lock marked as held
+        let remote = store.list().into_iter().cloned().map(|mut l| { l.held = false; l }).collect();
+        store.replace_all(remote).unwrap();
+        assert!(store.violations(paths, |l| l.owner == "alice", |_| false).len() == 1);
+        assert!(store.get("edit.prproj").unwrap().held);
+    }
+
     #[test]
     fn test_persistence() {
         let dir = tempdir().unwrap();
@@ -9,18 +9,23 @@
 //! Files are routed to the appropriate engine based on `StorageStrategy`.
 
 mod objects;
+mod pack;
 mod refs;
 mod git_engine;
 pub mod locks;
+pub mod reachability;
+pub mod relink;
 pub mod remote;
+pub mod remote_client;
 pub mod remote_server;
 pub mod repository;
 
 #[allow(unused_imports)]
 pub use {
     locks::{Lock, LockError, LockStore},
-    objects::ObjectStore,
-    refs::RefStore,
+    objects::{ObjectError, ObjectStore, ObjectType, PackStats},
+    refs::{is_valid_ref_name, RefStore},
+    relink::{Container, RelinkMapping, RelinkRecord, RelinkStore},
     remote::{Remote, RemoteError, RemoteStore, RemoteType},
     repository::{
         AddResult, CheckoutResult, FileDedupStats, FileStats, RepoDedupStats, RepoError, RepoStats,
@@ -10,6 +10,8 @@
 //! │   ├── chunks/
 //! │   │   ├── a7/b9c3d4...  (chunk data by hash)
 //! │   │   └── ...
+//! │   ├── pack/
+//! │   │   └── pack-{id}.pack/.idx  (packed chunks, see pack.rs)
 //! │   ├── manifests/
 //! │   │   └── {hash}.json
 //! │   └── commits/
@@ -19,12 +21,17 @@
 //!     └── branches/
 //! ```
 
+use super::pack::{self, PackReader, PackWriter};
 use crate::core::{Chunk, Commit, Hash, Hasher, Manifest};
 use crate::security::{encrypt_chunk, decrypt_chunk, EncryptedChunk, UserSecret};
 use bincode;
+use std::collections::HashSet;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
 use thiserror::Error;
 
 /// Errors from the object store.
@@ -44,6 +51,9 @@ pub enum ObjectError {
 
     #[error("Serialization error: {0}")]
     SerializationError(String),
+
+    #[error("Manifest path escapes the work tree: {0:?}")]
+    InvalidPath(String),
 }
 
 /// Type of object in the store.
@@ -77,6 +87,58 @@ pub struct ObjectStore {
     root: PathBuf,
     /// Encryption configuration (if enabled).
     encryption: Option<EncryptionConfig>,
+    /// Chunk packs, opened on first use and dropped after a repack.
+    packs: RwLock<Option<LoadedPacks>>,
+}
+
+/// Open chunk packs and the pack directory's mtime when they were listed.
+#[derive(Clone)]
+struct LoadedPacks {
+    packs: Arc<Vec<PackReader>>,
+    dir_mtime: Option<SystemTime>,
+}
+
+/// Distinguishes temporary files of objects written concurrently by one process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Write a loose object through a temporary file named uniquely per writer,
+/// then rename it into place. Returns `false` if a concurrent writer of the
+/// same object put it in place first.
+fn write_through_tmp(path: &Path, data: &[u8]) -> io::Result<bool> {
+    let tmp_path = path.with_extension(format!(
+        "tmp-{}-{}",
+        std::process::id(),
+        TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&tmp_path, data)?;
+    match fs::rename(&tmp_path, path) {
+        Ok(()) => Ok(true),
+        Err(e) => {
+            let _ = fs::remove_file(&tmp_path);
+            if path.exists() {
+                Ok(false)
+            } else {
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Result of packing or repacking chunks.
+#[derive(Debug, Clone, Default)]
+pub struct PackStats {
+    /// Chunks written into the new pack.
+    pub chunks_packed: usize,
+    /// Unreachable chunks that were not carried over.
+    pub chunks_dropped: usize,
+    /// Loose chunk files removed.
+    pub loose_removed: usize,
+    /// Old packs removed.
+    pub packs_removed: usize,
+    /// Bytes used by chunk storage before the operation.
+    pub bytes_before: u64,
+    /// Bytes used by chunk storage after the operation.
+    pub bytes_after: u64,
 }
 
 /// Encryption configuration for the object store.
@@ -92,6 +154,7 @@ impl ObjectStore {
         Self {
             root: dits_dir.join("objects"),
             encryption: None,
+            packs: RwLock::new(None),
         }
     }
 
@@ -100,6 +163,7 @@ impl ObjectStore {
         Self {
             root: dits_dir.join("objects"),
             encryption: Some(EncryptionConfig { user_secret }),
+            packs: RwLock::new(None),
         }
     }
 
@@ -132,6 +196,90 @@ impl ObjectStore {
             .join(&hex[2..])
     }
 
+    /// Directory holding chunk packs.
+    fn pack_dir(&self) -> PathBuf {
+        self.root.join("pack")
+    }
+
+    /// Modification time of the pack directory, which changes when packs are added or removed.
+    fn pack_dir_mtime(&self) -> Option<SystemTime> {
+        fs::metadata(self.pack_dir()).and_then(|m| m.modified()).ok()
+    }
+
+    /// Get the open packs, loading them on first use.
+    fn packs(&self) -> Arc<Vec<PackReader>> {
+        if let Some(loaded) = self.packs.read().ok().and_then(|guard| guard.clone()) {
+            return loaded.packs;
+        }
+
+        // Read the mtime first: a pack added while listing triggers a reload later
+        let dir_mtime = self.pack_dir_mtime();
+        let packs = Arc::new(pack::load_packs(&self.pack_dir()).unwrap_or_else(|e| {
+            eprintln!("Warning: failed to read packs: {}", e);
+            Vec::new()
+        }));
+        if let Ok(mut guard) = self.packs.write() {
+            *guard = Some(LoadedPacks { packs: packs.clone(), dir_mtime });
+        }
+        packs
+    }
+
+    /// Forget the open packs so the next lookup sees the current pack directory.
+    fn reload_packs(&self) {
+        if let Ok(mut guard) = self.packs.write() {
+            *guard = None;
+        }
+    }
+
+    /// Reload the packs if another process (e.g. `dits gc`) changed the pack
+    /// directory since they were opened. Returns whether they were reloaded.
+    ///
+    /// Called after a lookup misses, so long-lived readers such as servers
+    /// and mounts find chunks that were moved from loose storage into a pack.
+    fn refresh_packs(&self) -> bool {
+        let loaded = self.packs.read().ok().and_then(|guard| guard.as_ref().map(|l| l.dir_mtime));
+        match loaded {
+            Some(dir_mtime) if dir_mtime != self.pack_dir_mtime() => {
+                self.reload_packs();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Find a chunk in the packs, retrying once after a refresh on a miss.
+    fn find_packed<T>(
+        &self,
+        mut find: impl FnMut(&PackReader) -> Result<Option<T>, ObjectError>,
+    ) -> Result<Option<T>, ObjectError> {
+        for pack in self.packs().iter() {
+            if let Some(found) = find(pack)? {
+                return Ok(Some(found));
+            }
+        }
+        if self.refresh_packs() {
+            for pack in self.packs().iter() {
+                if let Some(found) = find(pack)? {
+                    return Ok(Some(found));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Read the stored (possibly encrypted) bytes of a chunk from loose storage or a pack.
+    fn read_stored_chunk(&self, hash: &Hash) -> Result<Vec<u8>, ObjectError> {
+        let path = self.object_path(ObjectType::Chunk, hash);
+        match fs::read(&path) {
+            Ok(data) => return Ok(data),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        self.find_packed(|pack| Ok(pack.read(hash)?))?
+            .ok_or_else(|| ObjectError::NotFound(hash.to_hex()))
+    }
+
     // ========== Chunk Operations ==========
 
     /// Store a chunk. Returns true if it was newly stored, false if it already existed.
@@ -139,7 +287,7 @@ impl ObjectStore {
     pub fn store_chunk(&self, chunk: &Chunk) -> Result<bool, ObjectError> {
         let path = self.object_path(ObjectType::Chunk, &chunk.hash);
 
-        if path.exists() {
+        if self.has_chunk(&chunk.hash) {
             // Already stored (dedup!)
             return Ok(false);
         }
@@ -162,21 +310,14 @@ impl ObjectStore {
             chunk.data.clone()
         };
 
-        // Write data
-        fs::write(&path, &data_to_store)?;
-        Ok(true)
+        // Write to a temporary file first so neither readers nor packing see a partial chunk
+        Ok(write_through_tmp(&path, &data_to_store)?)
     }
 
     /// Load a chunk by hash.
     /// If encryption is enabled, the chunk data will be decrypted after loading.
     pub fn load_chunk(&self, hash: &Hash) -> Result<Chunk, ObjectError> {
-        let path = self.object_path(ObjectType::Chunk, hash);
-
-        if !path.exists() {
-            return Err(ObjectError::NotFound(hash.to_hex()));
-        }
-
-        let stored_data = fs::read(&path)?;
+        let stored_data = self.read_stored_chunk(hash)?;
 
         // Decrypt chunk if encryption is enabled
         let plaintext_data = if let Some(config) = &self.encryption {
@@ -212,13 +353,222 @@ impl ObjectStore {
     /// Check if a chunk exists.
     pub fn has_chunk(&self, hash: &Hash) -> bool {
         self.object_path(ObjectType::Chunk, hash).exists()
+            || matches!(self.find_packed(|pack| Ok(pack.contains(hash).then_some(()))), Ok(Some(())))
     }
 
     /// Get the size of a stored chunk.
     pub fn chunk_size(&self, hash: &Hash) -> Result<u64, ObjectError> {
         let path = self.object_path(ObjectType::Chunk, hash);
-        let metadata = fs::metadata(&path)?;
-        Ok(metadata.len())
+        match fs::metadata(&path) {
+            Ok(metadata) => return Ok(metadata.len()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+
+        self.find_packed(|pack| Ok(pack.find(hash).map(|entry| entry.length)))?
+            .ok_or_else(|| ObjectError::NotFound(hash.to_hex()))
+    }
+
+    // ========== Pack Operations ==========
+
+    /// List hashes of loose objects of the given type.
+    pub fn list_loose(&self, obj_type: ObjectType) -> io::Result<Vec<Hash>> {
+        let dir = self.root.join(obj_type.dir_name());
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut hashes = Vec::new();
+        for fanout in fs::read_dir(&dir)? {
+            let fanout = fanout?;
+            let prefix = fanout.file_name().to_string_lossy().to_string();
+            if prefix.len() != 2 || !fanout.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(fanout.path())? {
+                let name = entry?.file_name().to_string_lossy().to_string();
+                // Skips temporary files left by interrupted writes
+                if let Ok(hash) = Hash::from_hex(&format!("{}{}", prefix, name)) {
+                    hashes.push(hash);
+                }
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// List hashes of all packed chunks.
+    pub fn list_packed_chunks(&self) -> Vec<Hash> {
+        let mut hashes: Vec<Hash> = self
+            .packs()
+            .iter()
+            .flat_map(|pack| pack.entries().map(|entry| entry.hash).collect::<Vec<_>>())
+            .collect();
+        hashes.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+        hashes.dedup();
+        hashes
+    }
+
+    /// Verify the structure of every pack. Returns the paths of damaged packs with the error.
+    pub fn verify_packs(&self) -> Vec<(PathBuf, io::Error)> {
+        self.packs()
+            .iter()
+            .filter_map(|pack| pack.verify().err().map(|e| (pack.pack_path().to_path_buf(), e)))
+            .collect()
+    }
+
+    /// Size on disk of a loose object.
+    pub fn loose_size(&self, obj_type: ObjectType, hash: &Hash) -> io::Result<u64> {
+        Ok(fs::metadata(self.object_path(obj_type, hash))?.len())
+    }
+
+    /// Last modification time of a loose object.
+    pub fn loose_modified(&self, obj_type: ObjectType, hash: &Hash) -> io::Result<SystemTime> {
+        fs::metadata(self.object_path(obj_type, hash))?.modified()
+    }
+
+    /// Remove a loose object. Returns the number of bytes freed.
+    pub fn remove_loose(&self, obj_type: ObjectType, hash: &Hash) -> io::Result<u64> {
+        let size = self.loose_size(obj_type, hash)?;
+        fs::remove_file(self.object_path(obj_type, hash))?;
+        Ok(size)
+    }
+
+    /// Bytes used by loose chunks and packs.
+    fn chunk_storage_size(&self) -> io::Result<u64> {
+        let mut total = 0;
+        for dir in [self.root.join(ObjectType::Chunk.dir_name()), self.pack_dir()] {
+            if !dir.exists() {
+                continue;
+            }
+            for entry in walkdir::WalkDir::new(&dir) {
+                let entry = entry?;
+                if entry.file_type().is_file() {
+                    total += entry.metadata()?.len();
+                }
+            }
+        }
+        Ok(total)
+    }
+
+    /// Move all loose chunks into a new pack. Existing packs are left alone.
+    pub fn pack_loose_chunks(&self) -> Result<PackStats, ObjectError> {
+        let mut stats = PackStats {
+            bytes_before: self.chunk_storage_size()?,
+            ..Default::default()
+        };
+
+        let loose = self.list_loose(ObjectType::Chunk)?;
+        let mut writer = PackWriter::create(&self.pack_dir())?;
+        for hash in &loose {
+            let data = fs::read(self.object_path(ObjectType::Chunk, hash))?;
+            writer.add(hash, &data)?;
+        }
+        stats.chunks_packed = writer.len();
+        writer.finish()?;
+        self.reload_packs();
+
+        for hash in &loose {
+            self.remove_loose(ObjectType::Chunk, hash)?;
+            stats.loose_removed += 1;
+        }
+        self.remove_empty_fanout_dirs(ObjectType::Chunk)?;
+
+        stats.bytes_after = self.chunk_storage_size()?;
+        Ok(stats)
+    }
+
+    /// Rewrite all chunk storage into a single pack holding only `keep`.
+    ///
+    /// Loose chunks and old packs are removed once the new pack is in place.
+    /// Chunks written concurrently (after the loose listing) are left untouched,
+    /// as are loose chunks outside `keep` modified after `expire`: like git's
+    /// `gc.pruneExpire`, they may belong to an add or push still in progress.
+    pub fn repack_chunks(&self, keep: &HashSet<Hash>, expire: SystemTime) -> Result<PackStats, ObjectError> {
+        let mut stats = PackStats {
+            bytes_before: self.chunk_storage_size()?,
+            ..Default::default()
+        };
+
+        let mut loose = self.list_loose(ObjectType::Chunk)?;
+        loose.retain(|hash| {
+            keep.contains(hash)
+                || self.loose_modified(ObjectType::Chunk, hash).is_ok_and(|modified| modified <= expire)
+        });
+        let old_packs = self.packs();
+
+        let mut writer = PackWriter::create(&self.pack_dir())?;
+        let mut seen = HashSet::new();
+        for hash in &loose {
+            if !seen.insert(*hash) {
+                continue;
+            }
+            if keep.contains(hash) {
+                let data = fs::read(self.object_path(ObjectType::Chunk, hash))?;
+                writer.add(hash, &data)?;
+            } else {
+                stats.chunks_dropped += 1;
+            }
+        }
+        for pack in old_packs.iter() {
+            for entry in pack.entries() {
+                if !seen.insert(entry.hash) {
+                    continue;
+                }
+                if keep.contains(&entry.hash) {
+                    writer.add(&entry.hash, &pack.read_entry(&entry)?)?;
+                } else {
+                    stats.chunks_dropped += 1;
+                }
+            }
+        }
+        stats.chunks_packed = writer.len();
+        let new_idx = writer.finish()?;
+
+        // Release our maps before unlinking the files they point at
+        let old_paths: Vec<(PathBuf, PathBuf)> = old_packs
+            .iter()
+            .map(|pack| (pack.pack_path().to_path_buf(), pack.idx_path().to_path_buf()))
+            .collect();
+        drop(old_packs);
+        self.reload_packs();
+
+        for (pack_path, idx_path) in old_paths {
+            // A repack with identical contents produces the same pack name
+            if Some(&idx_path) == new_idx.as_ref() {
+                continue;
+            }
+            // Index first: a pack without an index is ignored by readers
+            fs::remove_file(&idx_path)?;
+            fs::remove_file(&pack_path)?;
+            stats.packs_removed += 1;
+        }
+
+        for hash in &loose {
+            self.remove_loose(ObjectType::Chunk, hash)?;
+            stats.loose_removed += 1;
+        }
+        self.remove_empty_fanout_dirs(ObjectType::Chunk)?;
+
+        stats.bytes_after = self.chunk_storage_size()?;
+        Ok(stats)
+    }
+
+    /// Remove empty `xx/` fanout directories for an object type.
+    pub fn remove_empty_fanout_dirs(&self, obj_type: ObjectType) -> io::Result<usize> {
+        let dir = self.root.join(obj_type.dir_name());
+        if !dir.exists() {
+            return Ok(0);
+        }
+
+        let mut removed = 0;
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() && fs::read_dir(&path)?.next().is_none() {
+                fs::remove_dir(&path)?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
     }
 
     // ========== Blob Operations ==========
@@ -330,12 +680,18 @@ impl ObjectStore {
             });
         }
 
+        Self::decode_manifest(&data)
+    }
+
+    /// Decode manifest bytes.
+    /// Supports both binary format (Phase 6+) and legacy JSON format for backwards compatibility.
+    pub fn decode_manifest(data: &[u8]) -> Result<Manifest, ObjectError> {
         // Try binary format first (Phase 6+), fall back to JSON for backwards compatibility
-        match bincode::deserialize::<Manifest>(&data) {
+        match bincode::deserialize::<Manifest>(data) {
             Ok(manifest) => Ok(manifest),
             Err(bincode_err) => {
                 // Fall back to JSON deserialization for legacy manifests
-                match String::from_utf8(data) {
+                match String::from_utf8(data.to_vec()) {
                     Ok(json) => match Manifest::from_json(&json) {
                         Ok(manifest) => Ok(manifest),
                         Err(json_err) => {
@@ -393,6 +749,96 @@ impl ObjectStore {
         Ok(commit)
     }
 
+    // ========== Transfer Operations ==========
+    // Type-generic access used by push/fetch. Chunks travel as plaintext so the
+    // receiver can verify them against their BLAKE3 address.
+
+    /// Check if an object of the given type exists.
+    pub fn has_object(&self, obj_type: ObjectType, hash: &Hash) -> bool {
+        match obj_type {
+            ObjectType::Chunk => self.has_chunk(hash),
+            _ => self.object_path(obj_type, hash).exists(),
+        }
+    }
+
+    /// Read the canonical bytes of an object for transfer.
+    pub fn read_object(&self, obj_type: ObjectType, hash: &Hash) -> Result<Vec<u8>, ObjectError> {
+        match obj_type {
+            ObjectType::Chunk => Ok(self.load_chunk(hash)?.data),
+            ObjectType::Blob => self.load_blob(hash),
+            ObjectType::Manifest | ObjectType::Commit => {
+                let path = self.object_path(obj_type, hash);
+                if !path.exists() {
+                    return Err(ObjectError::NotFound(hash.to_hex()));
+                }
+                let data = fs::read(&path)?;
+                Self::verify_object(obj_type, hash, &data)?;
+                Ok(data)
+            }
+        }
+    }
+
+    /// Verify and store an object received from another repository.
+    /// Returns true if it was newly stored, false if it already existed.
+    pub fn write_object(&self, obj_type: ObjectType, hash: &Hash, data: &[u8]) -> Result<bool, ObjectError> {
+        Self::verify_object(obj_type, hash, data)?;
+
+        if obj_type == ObjectType::Chunk {
+            return self.store_chunk(&Chunk::with_hash(*hash, data.to_vec()));
+        }
+
+        let path = self.object_path(obj_type, hash);
+        if path.exists() {
+            return Ok(false);
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Write to a temporary file first so a concurrent reader never sees a partial object
+        Ok(write_through_tmp(&path, data)?)
+    }
+
+    /// Check that object bytes match the hash they are addressed by.
+    pub fn verify_object(obj_type: ObjectType, hash: &Hash, data: &[u8]) -> Result<(), ObjectError> {
+        let actual = match obj_type {
+            // Commits are addressed by the hash of their contents, which is
+            // also recorded inside them (see load_commit)
+            ObjectType::Commit => {
+                let commit = serde_json::from_slice::<Commit>(data)?;
+                let actual = commit.compute_hash();
+                if commit.hash != actual {
+                    return Err(ObjectError::ChecksumMismatch {
+                        expected: commit.hash.to_hex(),
+                        actual: actual.to_hex(),
+                    });
+                }
+                actual
+            }
+            _ => Hasher::hash(data),
+        };
+
+        if actual != *hash {
+            return Err(ObjectError::ChecksumMismatch {
+                expected: hash.to_hex(),
+                actual: actual.to_hex(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check that every path in a manifest stays inside the work tree.
+    ///
+    /// Run on manifests received from remotes and peers before they are
+    /// stored, and again at checkout.
+    pub fn check_manifest_paths(manifest: &Manifest) -> Result<(), ObjectError> {
+        match manifest.invalid_path() {
+            Some(path) => Err(ObjectError::InvalidPath(path.to_string())),
+            None => Ok(()),
+        }
+    }
+
     // ========== Stats ==========
 
     /// Count objects of each type.
@@ -413,8 +859,10 @@ impl ObjectStore {
             Ok(count)
         };
 
+        let packed: usize = self.packs().iter().map(|pack| pack.len()).sum();
+
         Ok((
-            count_dir(ObjectType::Chunk)?,
+            count_dir(ObjectType::Chunk)? + packed,
             count_dir(ObjectType::Manifest)?,
             count_dir(ObjectType::Commit)?,
         ))
@@ -519,4 +967,143 @@ mod tests {
         let result = store.load_chunk(&chunk.hash);
         assert!(matches!(result, Err(ObjectError::ChecksumMismatch { .. })));
     }
+
+    #[test]
+    fn test_write_object_verifies_hash() {
+        let temp = tempdir().unwrap();
+        let store = ObjectStore::new(temp.path());
+        store.init().unwrap();
+
+        let data = b"transferred chunk".to_vec();
+        let hash = Hasher::hash(&data);
+
+        // Wrong address is rejected
+        let result = store.write_object(ObjectType::Chunk, &Hash::ZERO, &data);
+        assert!(matches!(result, Err(ObjectError::ChecksumMismatch { .. })));
+
+        assert!(store.write_object(ObjectType::Chunk, &hash, &data).unwrap());
+        assert!(!store.write_object(ObjectType::Chunk, &hash, &data).unwrap());
+        assert_eq!(store.read_object(ObjectType::Chunk, &hash).unwrap(), data);
+
+        use crate::core::Author;
+        let commit = Commit::new(None, Hash::ZERO, "Remote commit", Author::new("Test", "test@test.com"));
+        let json = commit.to_json();
+        assert!(store.write_object(ObjectType::Commit, &commit.hash, json.as_bytes()).unwrap());
+        assert_eq!(store.load_commit(&commit.hash).unwrap().message, "Remote commit");
+
+        // A commit whose contents were altered under its recorded hash is rejected
+        let mut forged = Commit::new(None, Hash::ZERO, "Original", Author::new("Test", "test@test.com"));
+        forged.message = "Forged".to_string();
+        let result = store.write_object(ObjectType::Commit, &forged.hash, forged.to_json().as_bytes());
+        assert!(matches!(result, Err(ObjectError::ChecksumMismatch { .. })));
+        assert!(!store.has_object(ObjectType::Commit, &forged.hash));
+    }
+
+    #[test]
+    fn test_concurrent_writes_of_one_object() {
+        use crate::core::Author;
+        let temp = tempdir().unwrap();
+        let store = ObjectStore::new(temp.path());
+        store.init().unwrap();
+
+        // Like two pushes uploading the same commits at once
+        let commits: Vec<Commit> = (0..20)
+            .map(|i| Commit::new(None, Hash::ZERO, &format!("commit {}", i), Author::new("Test", "test@test.com")))
+            .collect();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for commit in &commits {
+                        store.write_object(ObjectType::Commit, &commit.hash, commit.to_json().as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+        for commit in &commits {
+            assert_eq!(store.load_commit(&commit.hash).unwrap().message, commit.message);
+        }
+    }
+
+    #[test]
+    fn test_pack_loose_chunks() {
+        let temp = tempdir().unwrap();
+        let store = ObjectStore::new(temp.path());
+        store.init().unwrap();
+
+        let chunks: Vec<Chunk> = (0..50u32).map(|i| Chunk::new(format!("chunk {}", i).into_bytes())).collect();
+        for chunk in &chunks {
+            store.store_chunk(chunk).unwrap();
+        }
+
+        let stats = store.pack_loose_chunks().unwrap();
+        assert_eq!(stats.chunks_packed, 50);
+        assert_eq!(stats.loose_removed, 50);
+        assert!(store.list_loose(ObjectType::Chunk).unwrap().is_empty());
+        assert!(store.verify_packs().is_empty());
+
+        for chunk in &chunks {
+            assert!(store.has_chunk(&chunk.hash));
+            assert_eq!(store.load_chunk(&chunk.hash).unwrap().data, chunk.data);
+            assert_eq!(store.chunk_size(&chunk.hash).unwrap(), chunk.data.len() as u64);
+            // Packed chunks still dedup
+            assert!(!store.store_chunk(chunk).unwrap());
+        }
+        assert_eq!(store.count_objects().unwrap().0, 50);
+
+        // A fresh store sees the pack on disk
+        let reopened = ObjectStore::new(temp.path());
+        assert_eq!(reopened.load_chunk(&chunks[7].hash).unwrap().data, chunks[7].data);
+    }
+
+    #[test]
+    fn test_repack_drops_unreachable_chunks() {
+        let temp = tempdir().unwrap();
+        let store = ObjectStore::new(temp.path());
+        store.init().unwrap();
+
+        let packed = Chunk::new(b"packed and kept".to_vec());
+        let packed_garbage = Chunk::new(b"packed garbage".to_vec());
+        store.store_chunk(&packed).unwrap();
+        store.store_chunk(&packed_garbage).unwrap();
+        store.pack_loose_chunks().unwrap();
+
+        let loose = Chunk::new(b"loose and kept".to_vec());
+        let loose_garbage = Chunk::new(b"loose garbage".to_vec());
+        store.store_chunk(&loose).unwrap();
+        store.store_chunk(&loose_garbage).unwrap();
+
+        let keep: HashSet<Hash> = [packed.hash, loose.hash].into_iter().collect();
+        let stats = store.repack_chunks(&keep, SystemTime::now()).unwrap();
+        assert_eq!(stats.chunks_packed, 2);
+        assert_eq!(stats.chunks_dropped, 2);
+        assert_eq!(stats.packs_removed, 1);
+
+        assert_eq!(store.load_chunk(&packed.hash).unwrap().data, packed.data);
+        assert_eq!(store.load_chunk(&loose.hash).unwrap().data, loose.data);
+        assert!(!store.has_chunk(&packed_garbage.hash));
+        assert!(!store.has_chunk(&loose_garbage.hash));
+        assert_eq!(fs::read_dir(temp.path().join("objects/pack")).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_chunks_packed_by_another_process_stay_readable() {
+        let temp = tempdir().unwrap();
+        let server = ObjectStore::new(temp.path());
+        server.init().unwrap();
+
+        let chunk = Chunk::new(b"written before an external gc".to_vec());
+        server.store_chunk(&chunk).unwrap();
+        // Opens the (empty) pack list, as a long-lived server would
+        assert!(!server.has_chunk(&Hash::ZERO));
+        assert!(server.has_chunk(&chunk.hash));
+
+        // `dits gc` in another process moves the chunk into a pack
+        let gc = ObjectStore::new(temp.path());
+        gc.pack_loose_chunks().unwrap();
+        assert!(gc.list_loose(ObjectType::Chunk).unwrap().is_empty());
+
+        assert!(server.has_chunk(&chunk.hash));
+        assert_eq!(server.load_chunk(&chunk.hash).unwrap().data, chunk.data);
+        assert_eq!(server.chunk_size(&chunk.hash).unwrap(), chunk.data.len() as u64);
+    }
 }
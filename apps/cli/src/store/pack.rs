@@ -0,0 +1,477 @@
+//! Packfiles for chunk storage.
+//!
+//! Loose storage keeps one file per chunk, which runs out of inodes and makes
+//! directory walks slow once a repository holds millions of small chunks.
+//! A pack concatenates many chunks into a single file and a sorted index maps
+//! each hash to its offset, so a lookup is one binary search over an mmap.
+//!
+//! Layout:
+//! ```text
+//! .dits/objects/pack/
+//! ├── pack-{id}.pack   header + chunk records
+//! └── pack-{id}.idx    fanout + sorted entries + checksum
+//! ```
+//!
+//! Pack file:
+//! ```text
+//! "DPCK" | version u32 | records...
+//! record = hash [32] | length u64 | stored bytes [length]
+//! ```
+//! Stored bytes are exactly what the loose file held, so encrypted chunks
+//! stay encrypted inside a pack. The per-record header makes the pack
+//! self-describing: an index can be rebuilt from the pack alone.
+//!
+//! Index file:
+//! ```text
+//! "DIDX" | version u32 | count u64 | fanout [256 x u32]
+//! entries [count x (hash [32] | offset u64 | length u64)]   sorted by hash
+//! checksum [32]                                             BLAKE3 of all preceding bytes
+//! ```
+//! `fanout[b]` is the number of entries whose first hash byte is <= `b`.
+//! All integers are little-endian. The index is renamed into place after the
+//! pack, so a visible index always refers to a complete pack.
+
+use crate::core::{Hash, Hasher, HASH_SIZE};
+use memmap2::Mmap;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const PACK_MAGIC: &[u8; 4] = b"DPCK";
+const IDX_MAGIC: &[u8; 4] = b"DIDX";
+const PACK_VERSION: u32 = 1;
+
+const PACK_HEADER_SIZE: u64 = 8;
+const RECORD_HEADER_SIZE: u64 = HASH_SIZE as u64 + 8;
+const IDX_HEADER_SIZE: usize = 16;
+const FANOUT_SIZE: usize = 256 * 4;
+const IDX_ENTRY_SIZE: usize = HASH_SIZE + 16;
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn read_u32(buf: &[u8], at: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&buf[at..at + 4]);
+    u32::from_le_bytes(bytes)
+}
+
+fn read_u64(buf: &[u8], at: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&buf[at..at + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// Location of a chunk inside a pack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackEntry {
+    pub hash: Hash,
+    /// Offset of the stored bytes (past the record header).
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// A memory-mapped pack and its index.
+pub struct PackReader {
+    pack_path: PathBuf,
+    idx_path: PathBuf,
+    pack: Mmap,
+    idx: Mmap,
+    count: usize,
+}
+
+impl PackReader {
+    /// Open a pack by its index path. The matching `.pack` must sit next to it.
+    pub fn open(idx_path: &Path) -> io::Result<Self> {
+        let pack_path = idx_path.with_extension("pack");
+
+        let idx_file = File::open(idx_path)?;
+        // SAFETY: pack and index files are immutable once renamed into place;
+        // repacking writes new files and unlinks old ones rather than truncating.
+        let idx = unsafe { Mmap::map(&idx_file)? };
+        let pack_file = File::open(&pack_path)?;
+        let pack = unsafe { Mmap::map(&pack_file)? };
+
+        if idx.len() < IDX_HEADER_SIZE + FANOUT_SIZE + HASH_SIZE || &idx[0..4] != IDX_MAGIC {
+            return Err(invalid(format!("{}: not a pack index", idx_path.display())));
+        }
+        let version = read_u32(&idx, 4);
+        if version != PACK_VERSION {
+            return Err(invalid(format!(
+                "{}: unsupported pack index version {}",
+                idx_path.display(),
+                version
+            )));
+        }
+        let count = read_u64(&idx, 8) as usize;
+        let expected_len = count
+            .checked_mul(IDX_ENTRY_SIZE)
+            .and_then(|entries| entries.checked_add(IDX_HEADER_SIZE + FANOUT_SIZE + HASH_SIZE));
+        if expected_len != Some(idx.len()) {
+            return Err(invalid(format!("{}: truncated pack index", idx_path.display())));
+        }
+        // Lookups bound their binary search by the fanout, so it must never
+        // decrease and must end at the entry count
+        let fanout: Vec<u32> = (0..256).map(|i| read_u32(&idx, IDX_HEADER_SIZE + i * 4)).collect();
+        if fanout.windows(2).any(|w| w[0] > w[1]) || fanout[255] as usize != count {
+            return Err(invalid(format!("{}: corrupt fanout table", idx_path.display())));
+        }
+
+        if pack.len() < PACK_HEADER_SIZE as usize || &pack[0..4] != PACK_MAGIC {
+            return Err(invalid(format!("{}: not a pack file", pack_path.display())));
+        }
+
+        Ok(Self {
+            pack_path,
+            idx_path: idx_path.to_path_buf(),
+            pack,
+            idx,
+            count,
+        })
+    }
+
+    /// Path of the `.pack` file.
+    pub fn pack_path(&self) -> &Path {
+        &self.pack_path
+    }
+
+    /// Path of the `.idx` file.
+    pub fn idx_path(&self) -> &Path {
+        &self.idx_path
+    }
+
+    /// Number of chunks in this pack.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    fn fanout(&self, byte: usize) -> usize {
+        read_u32(&self.idx, IDX_HEADER_SIZE + byte * 4) as usize
+    }
+
+    fn entry_at(&self, i: usize) -> PackEntry {
+        let at = IDX_HEADER_SIZE + FANOUT_SIZE + i * IDX_ENTRY_SIZE;
+        PackEntry {
+            hash: Hash::from_slice(&self.idx[at..at + HASH_SIZE]),
+            offset: read_u64(&self.idx, at + HASH_SIZE),
+            length: read_u64(&self.idx, at + HASH_SIZE + 8),
+        }
+    }
+
+    fn hash_at(&self, i: usize) -> &[u8] {
+        let at = IDX_HEADER_SIZE + FANOUT_SIZE + i * IDX_ENTRY_SIZE;
+        &self.idx[at..at + HASH_SIZE]
+    }
+
+    /// Look up a chunk in the index.
+    pub fn find(&self, hash: &Hash) -> Option<PackEntry> {
+        let first = hash.as_bytes()[0] as usize;
+        let mut lo = if first == 0 { 0 } else { self.fanout(first - 1) };
+        let mut hi = self.fanout(first);
+        let target = &hash.as_bytes()[..];
+
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match self.hash_at(mid).cmp(target) {
+                std::cmp::Ordering::Less => lo = mid + 1,
+                std::cmp::Ordering::Greater => hi = mid,
+                std::cmp::Ordering::Equal => return Some(self.entry_at(mid)),
+            }
+        }
+        None
+    }
+
+    /// Check whether the pack contains a chunk.
+    pub fn contains(&self, hash: &Hash) -> bool {
+        self.find(hash).is_some()
+    }
+
+    /// Read the stored bytes of a chunk, if present.
+    pub fn read(&self, hash: &Hash) -> io::Result<Option<Vec<u8>>> {
+        match self.find(hash) {
+            Some(entry) => self.read_entry(&entry).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the stored bytes for an index entry.
+    pub fn read_entry(&self, entry: &PackEntry) -> io::Result<Vec<u8>> {
+        let start = entry.offset as usize;
+        let end = start
+            .checked_add(entry.length as usize)
+            .filter(|end| *end <= self.pack.len())
+            .ok_or_else(|| invalid(format!("{}: entry out of bounds", self.pack_path.display())))?;
+        Ok(self.pack[start..end].to_vec())
+    }
+
+    /// Iterate over all entries in hash order.
+    pub fn entries(&self) -> impl Iterator<Item = PackEntry> + '_ {
+        (0..self.count).map(move |i| self.entry_at(i))
+    }
+
+    /// Verify the index checksum and that every record header agrees with the index.
+    pub fn verify(&self) -> io::Result<()> {
+        let body = self.idx.len() - HASH_SIZE;
+        let computed = Hasher::hash(&self.idx[..body]);
+        if computed.as_bytes()[..] != self.idx[body..] {
+            return Err(invalid(format!("{}: index checksum mismatch", self.idx_path.display())));
+        }
+
+        for entry in self.entries() {
+            let header = entry
+                .offset
+                .checked_sub(RECORD_HEADER_SIZE)
+                .ok_or_else(|| invalid(format!("{}: bad entry offset", self.idx_path.display())))?
+                as usize;
+            if header + RECORD_HEADER_SIZE as usize > self.pack.len() {
+                return Err(invalid(format!("{}: entry out of bounds", self.pack_path.display())));
+            }
+            let record_hash = &self.pack[header..header + HASH_SIZE];
+            let record_len = read_u64(&self.pack, header + HASH_SIZE);
+            if record_hash != &entry.hash.as_bytes()[..] || record_len != entry.length {
+                return Err(invalid(format!(
+                    "{}: record for {} does not match index",
+                    self.pack_path.display(),
+                    entry.hash.short()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes a new pack and its index.
+///
+/// Records are streamed to a temporary file; nothing becomes visible to
+/// readers until [`PackWriter::finish`] renames the index into place.
+pub struct PackWriter {
+    pack_dir: PathBuf,
+    tmp_path: PathBuf,
+    out: BufWriter<File>,
+    offset: u64,
+    entries: Vec<PackEntry>,
+    seen: HashSet<Hash>,
+}
+
+impl PackWriter {
+    /// Start a new pack in `pack_dir`.
+    pub fn create(pack_dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(pack_dir)?;
+        let tmp_path = pack_dir.join(format!("tmp-pack-{}-{}", std::process::id(), unique_suffix()));
+        let mut out = BufWriter::new(File::create(&tmp_path)?);
+        out.write_all(PACK_MAGIC)?;
+        out.write_all(&PACK_VERSION.to_le_bytes())?;
+
+        Ok(Self {
+            pack_dir: pack_dir.to_path_buf(),
+            tmp_path,
+            out,
+            offset: PACK_HEADER_SIZE,
+            entries: Vec::new(),
+            seen: HashSet::new(),
+        })
+    }
+
+    /// Append the stored bytes of a chunk. Duplicates are ignored.
+    pub fn add(&mut self, hash: &Hash, stored: &[u8]) -> io::Result<bool> {
+        if !self.seen.insert(*hash) {
+            return Ok(false);
+        }
+
+        self.out.write_all(hash.as_bytes())?;
+        self.out.write_all(&(stored.len() as u64).to_le_bytes())?;
+        self.out.write_all(stored)?;
+
+        self.entries.push(PackEntry {
+            hash: *hash,
+            offset: self.offset + RECORD_HEADER_SIZE,
+            length: stored.len() as u64,
+        });
+        self.offset += RECORD_HEADER_SIZE + stored.len() as u64;
+        Ok(true)
+    }
+
+    /// Number of chunks written so far.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Flush the pack, write its index and move both into place.
+    /// Returns the index path, or `None` if the pack would be empty.
+    pub fn finish(mut self) -> io::Result<Option<PathBuf>> {
+        if self.entries.is_empty() {
+            drop(self.out);
+            fs::remove_file(&self.tmp_path)?;
+            return Ok(None);
+        }
+
+        self.entries.sort_by(|a, b| a.hash.as_bytes().cmp(b.hash.as_bytes()));
+
+        // Name the pack after its contents so identical repacks converge
+        let mut hasher = Hasher::new();
+        for entry in &self.entries {
+            hasher.update(entry.hash.as_bytes());
+        }
+        let name = format!("pack-{}", hasher.finalize().to_hex());
+        let pack_path = self.pack_dir.join(format!("{}.pack", name));
+        let idx_path = self.pack_dir.join(format!("{}.idx", name));
+
+        self.out.flush()?;
+        let file = self.out.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()?;
+        drop(file);
+
+        let mut idx = Vec::with_capacity(
+            IDX_HEADER_SIZE + FANOUT_SIZE + self.entries.len() * IDX_ENTRY_SIZE + HASH_SIZE,
+        );
+        idx.extend_from_slice(IDX_MAGIC);
+        idx.extend_from_slice(&PACK_VERSION.to_le_bytes());
+        idx.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+
+        let mut fanout = [0u32; 256];
+        for entry in &self.entries {
+            fanout[entry.hash.as_bytes()[0] as usize] += 1;
+        }
+        let mut running = 0u32;
+        for count in fanout.iter_mut() {
+            running += *count;
+            *count = running;
+        }
+        for count in fanout {
+            idx.extend_from_slice(&count.to_le_bytes());
+        }
+
+        for entry in &self.entries {
+            idx.extend_from_slice(entry.hash.as_bytes());
+            idx.extend_from_slice(&entry.offset.to_le_bytes());
+            idx.extend_from_slice(&entry.length.to_le_bytes());
+        }
+        let checksum = Hasher::hash(&idx);
+        idx.extend_from_slice(checksum.as_bytes());
+
+        let tmp_idx = self.tmp_path.with_extension("idx");
+        {
+            let mut f = File::create(&tmp_idx)?;
+            f.write_all(&idx)?;
+            f.sync_all()?;
+        }
+
+        fs::rename(&self.tmp_path, &pack_path)?;
+        fs::rename(&tmp_idx, &idx_path)?;
+        Ok(Some(idx_path))
+    }
+}
+
+fn unique_suffix() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Open every pack in `pack_dir`. Unreadable packs are skipped with a warning
+/// so one damaged pack does not hide the rest of the store.
+pub fn load_packs(pack_dir: &Path) -> io::Result<Vec<PackReader>> {
+    if !pack_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut packs = Vec::new();
+    for entry in fs::read_dir(pack_dir)? {
+        let path = entry?.path();
+        let is_idx = path.extension().map(|e| e == "idx").unwrap_or(false);
+        let is_pack_name = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .map(|n| n.starts_with("pack-"))
+            .unwrap_or(false);
+        if !is_idx || !is_pack_name {
+            continue;
+        }
+
+        match PackReader::open(&path) {
+            Ok(pack) => packs.push(pack),
+            Err(e) => eprintln!("Warning: skipping pack {}: {}", path.display(), e),
+        }
+    }
+
+    // Larger packs first: they are the most likely to hold a given chunk
+    packs.sort_by_key(|pack| std::cmp::Reverse(pack.len()));
+    Ok(packs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_and_read_pack() {
+        let temp = tempdir().unwrap();
+        let mut writer = PackWriter::create(temp.path()).unwrap();
+
+        let chunks: Vec<Vec<u8>> = (0..300u32).map(|i| format!("chunk {}", i).into_bytes()).collect();
+        for data in &chunks {
+            assert!(writer.add(&Hasher::hash(data), data).unwrap());
+        }
+        // Duplicates are skipped
+        assert!(!writer.add(&Hasher::hash(&chunks[0]), &chunks[0]).unwrap());
+
+        let idx_path = writer.finish().unwrap().unwrap();
+        let pack = PackReader::open(&idx_path).unwrap();
+        pack.verify().unwrap();
+        assert_eq!(pack.len(), chunks.len());
+
+        for data in &chunks {
+            assert_eq!(pack.read(&Hasher::hash(data)).unwrap().unwrap(), *data);
+        }
+        assert!(!pack.contains(&Hasher::hash(b"missing")));
+        assert!(pack.read(&Hash::ZERO).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_corrupt_index_detected() {
+        let temp = tempdir().unwrap();
+        let mut writer = PackWriter::create(temp.path()).unwrap();
+        writer.add(&Hasher::hash(b"a"), b"a").unwrap();
+        let idx_path = writer.finish().unwrap().unwrap();
+
+        let mut bytes = fs::read(&idx_path).unwrap();
+        let at = IDX_HEADER_SIZE + FANOUT_SIZE + HASH_SIZE;
+        bytes[at] ^= 0xff;
+        fs::write(&idx_path, bytes).unwrap();
+
+        let pack = PackReader::open(&idx_path).unwrap();
+        assert!(pack.verify().is_err());
+    }
+
+    #[test]
+    fn test_corrupt_fanout_detected() {
+        let temp = tempdir().unwrap();
+        let mut writer = PackWriter::create(temp.path()).unwrap();
+        writer.add(&Hasher::hash(b"a"), b"a").unwrap();
+        let idx_path = writer.finish().unwrap().unwrap();
+        let original = fs::read(&idx_path).unwrap();
+
+        // A bucket past the entry count, and counts that go back down
+        for buckets in [&[2u32][..], &[1, 0]] {
+            let mut bytes = original.clone();
+            for (i, value) in buckets.iter().enumerate() {
+                let at = IDX_HEADER_SIZE + i * 4;
+                bytes[at..at + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            fs::write(&idx_path, bytes).unwrap();
+            assert!(PackReader::open(&idx_path).is_err());
+        }
+    }
+
+    #[test]
+    fn test_empty_writer_leaves_nothing() {
+        let temp = tempdir().unwrap();
+        let writer = PackWriter::create(temp.path()).unwrap();
+        assert!(writer.finish().unwrap().is_none());
+        assert!(load_packs(temp.path()).unwrap().is_empty());
+        assert_eq!(fs::read_dir(temp.path()).unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,462 @@
+//! Object reachability for push/fetch negotiation.
+//!
+//! A commit reaches its parents and its manifest; a manifest reaches the
+//! chunks of every entry, the MP4 `ftyp`/`moov`/extra atom blobs, and the
+//! Git blobs of text files. Transfers walk this graph to find the objects
+//! one side is missing.
+
+use crate::core::{Commit, Hash, Manifest};
+use super::git_engine::{GitEngineError, GitTextEngine};
+use super::objects::{ObjectError, ObjectStore, ObjectType};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use thiserror::Error;
+
+/// Errors while reading or writing transferable objects.
+#[derive(Debug, Error)]
+pub enum TransferError {
+    #[error("Object error: {0}")]
+    Object(#[from] ObjectError),
+
+    #[error("Git engine error: {0}")]
+    Git(#[from] GitEngineError),
+
+    #[error("Invalid object id: {0}")]
+    InvalidId(String),
+
+    #[error("Git blob checksum mismatch: expected {expected}, got {actual}")]
+    GitChecksumMismatch { expected: String, actual: String },
+
+    #[error("Git text engine not available")]
+    NoGitEngine,
+}
+
+/// Kind of a transferable object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectKind {
+    /// Raw chunk data.
+    Chunk,
+    /// File-type-specific blob (MP4 atoms).
+    Blob,
+    /// Text file content stored in the Git engine.
+    GitBlob,
+    /// Manifest (tree).
+    Manifest,
+    /// Commit.
+    Commit,
+}
+
+impl ObjectKind {
+    /// Name used in URLs and on the wire.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ObjectKind::Chunk => "chunk",
+            ObjectKind::Blob => "blob",
+            ObjectKind::GitBlob => "git_blob",
+            ObjectKind::Manifest => "manifest",
+            ObjectKind::Commit => "commit",
+        }
+    }
+
+    /// Parse a kind from its wire name.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "chunk" => Some(ObjectKind::Chunk),
+            "blob" => Some(ObjectKind::Blob),
+            "git_blob" => Some(ObjectKind::GitBlob),
+            "manifest" => Some(ObjectKind::Manifest),
+            "commit" => Some(ObjectKind::Commit),
+            _ => None,
+        }
+    }
+
+    /// The object store type, or None for objects kept in the Git engine.
+    pub fn object_type(&self) -> Option<ObjectType> {
+        match self {
+            ObjectKind::Chunk => Some(ObjectType::Chunk),
+            ObjectKind::Blob => Some(ObjectType::Blob),
+            ObjectKind::Manifest => Some(ObjectType::Manifest),
+            ObjectKind::Commit => Some(ObjectType::Commit),
+            ObjectKind::GitBlob => None,
+        }
+    }
+}
+
+/// Identifier of a transferable object.
+///
+/// `id` is the hex BLAKE3 hash, or the hex Git OID for `GitBlob`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct ObjectId {
+    pub kind: ObjectKind,
+    pub id: String,
+}
+
+impl ObjectId {
+    /// Create an id for an object addressed by a BLAKE3 hash.
+    pub fn new(kind: ObjectKind, hash: &Hash) -> Self {
+        Self {
+            kind,
+            id: hash.to_hex(),
+        }
+    }
+
+    /// Create an id for a Git blob.
+    pub fn git_blob(oid: impl Into<String>) -> Self {
+        Self {
+            kind: ObjectKind::GitBlob,
+            id: oid.into(),
+        }
+    }
+
+    /// Parse the id as a BLAKE3 hash.
+    pub fn hash(&self) -> Result<Hash, hex::FromHexError> {
+        Hash::from_hex(&self.id)
+    }
+}
+
+// ========== Object Access ==========
+
+/// Check whether an object is present locally.
+pub fn has_object(store: &ObjectStore, git: Option<&GitTextEngine>, id: &ObjectId) -> bool {
+    match id.kind.object_type() {
+        Some(obj_type) => id.hash().map(|h| store.has_object(obj_type, &h)).unwrap_or(false),
+        None => git
+            .zip(GitTextEngine::parse_oid(&id.id).ok())
+            .map(|(engine, oid)| engine.has_blob(oid))
+            .unwrap_or(false),
+    }
+}
+
+/// Read an object's canonical bytes for transfer.
+pub fn read_object(
+    store: &ObjectStore,
+    git: Option<&GitTextEngine>,
+    id: &ObjectId,
+) -> Result<Vec<u8>, TransferError> {
+    match id.kind.object_type() {
+        Some(obj_type) => {
+            let hash = id.hash().map_err(|_| TransferError::InvalidId(id.id.clone()))?;
+            Ok(store.read_object(obj_type, &hash)?)
+        }
+        None => {
+            let engine = git.ok_or(TransferError::NoGitEngine)?;
+            Ok(engine.read_blob(GitTextEngine::parse_oid(&id.id)?)?)
+        }
+    }
+}
+
+/// Verify and store an object. Returns true if it was newly stored.
+pub fn write_object(
+    store: &ObjectStore,
+    git: Option<&GitTextEngine>,
+    id: &ObjectId,
+    data: &[u8],
+) -> Result<bool, TransferError> {
+    match id.kind.object_type() {
+        Some(obj_type) => {
+            let hash = id.hash().map_err(|_| TransferError::InvalidId(id.id.clone()))?;
+            Ok(store.write_object(obj_type, &hash, data)?)
+        }
+        None => {
+            let engine = git.ok_or(TransferError::NoGitEngine)?;
+            let expected = GitTextEngine::parse_oid(&id.id)?;
+            let actual = git2::Oid::hash_object(git2::ObjectType::Blob, data)
+                .map_err(GitEngineError::from)?;
+            if actual != expected {
+                return Err(TransferError::GitChecksumMismatch {
+                    expected: expected.to_string(),
+                    actual: actual.to_string(),
+                });
+            }
+            if engine.has_blob(expected) {
+                return Ok(false);
+            }
+            engine.store_blob(data)?;
+            Ok(true)
+        }
+    }
+}
+
+// ========== Graph Walking ==========
+
+/// All content objects referenced by a manifest (chunks, blobs, Git blobs).
+pub fn manifest_objects(manifest: &Manifest) -> Vec<ObjectId> {
+    let mut objects = Vec::new();
+
+    for entry in manifest.entries.values() {
+        for chunk in &entry.chunks {
+            objects.push(ObjectId::new(ObjectKind::Chunk, &chunk.hash));
+        }
+
+        if let Some(mp4) = &entry.mp4_metadata {
+            for hash in mp4.ftyp_hash.iter().chain(mp4.moov_hash.iter()) {
+                objects.push(ObjectId::new(ObjectKind::Blob, hash));
+            }
+            for atom in &mp4.other_atoms {
+                if let Some(hash) = &atom.hash {
+                    objects.push(ObjectId::new(ObjectKind::Blob, hash));
+                }
+            }
+        }
+
+        if let Some(oid) = &entry.git_oid {
+            objects.push(ObjectId::git_blob(oid.clone()));
+        }
+    }
+
+    objects
+}
+
+/// Parent hashes of a commit (first parent followed by merge parents).
+pub fn commit_parents(commit: &Commit) -> impl Iterator<Item = &Hash> {
+    commit.parent.iter().chain(commit.parents.iter())
+}
+
+/// All commits reachable from `tips` that exist in the store.
+pub fn ancestors(store: &ObjectStore, tips: &[Hash]) -> Result<HashSet<Hash>, ObjectError> {
+    let commits = commits_between(store, tips, &HashSet::new())?;
+    Ok(commits.into_iter().map(|c| c.hash).collect())
+}
+
+/// Commits reachable from `tips` but not from `exclude`, newest first.
+///
+/// The walk stops at excluded commits and at commits missing from the store
+/// (e.g. the boundary of a shallow clone).
+pub fn commits_between(
+    store: &ObjectStore,
+    tips: &[Hash],
+    exclude: &HashSet<Hash>,
+) -> Result<Vec<Commit>, ObjectError> {
+    let mut seen: HashSet<Hash> = HashSet::new();
+    let mut queue: VecDeque<Hash> = tips.iter().copied().collect();
+    let mut commits = Vec::new();
+
+    while let Some(hash) = queue.pop_front() {
+        if exclude.contains(&hash) || !seen.insert(hash) {
+            continue;
+        }
+        if !store.has_object(ObjectType::Commit, &hash) {
+            continue;
+        }
+
+        let commit = store.load_commit(&hash)?;
+        queue.extend(commit_parents(&commit).copied());
+        commits.push(commit);
+    }
+
+    Ok(commits)
+}
+
+/// First object reachable from `tips` that is missing from the store, if any.
+///
+/// The walk stops at commits in `exclude`, whose history is already known to
+/// be complete. Unlike [`commits_between`], a missing parent commit counts as
+/// missing rather than as a shallow boundary.
+pub fn first_missing_object(
+    store: &ObjectStore,
+    git: Option<&GitTextEngine>,
+    tips: &[Hash],
+    exclude: &HashSet<Hash>,
+) -> Result<Option<ObjectId>, ObjectError> {
+    let mut seen: HashSet<Hash> = HashSet::new();
+    let mut checked: HashSet<ObjectId> = HashSet::new();
+    let mut queue: VecDeque<Hash> = tips.iter().copied().collect();
+
+    while let Some(hash) = queue.pop_front() {
+        if exclude.contains(&hash) || !seen.insert(hash) {
+            continue;
+        }
+        if !store.has_object(ObjectType::Commit, &hash) {
+            return Ok(Some(ObjectId::new(ObjectKind::Commit, &hash)));
+        }
+
+        let commit = store.load_commit(&hash)?;
+        let manifest_id = ObjectId::new(ObjectKind::Manifest, &commit.manifest);
+        if checked.insert(manifest_id.clone()) {
+            if !store.has_object(ObjectType::Manifest, &commit.manifest) {
+                return Ok(Some(manifest_id));
+            }
+            let manifest = store.load_manifest(&commit.manifest)?;
+            for id in manifest_objects(&manifest) {
+                if checked.insert(id.clone()) && !has_object(store, git, &id) {
+                    return Ok(Some(id));
+                }
+            }
+        }
+        queue.extend(commit_parents(&commit).copied());
+    }
+
+    Ok(None)
+}
+
+/// Check whether `ancestor` is reachable from `descendant`.
+pub fn is_ancestor(store: &ObjectStore, ancestor: &Hash, descendant: &Hash) -> Result<bool, ObjectError> {
+    Ok(ancestors(store, &[*descendant])?.contains(ancestor))
+}
+
+/// Paths added, modified or removed by any of `commits` relative to their
+/// parents. A root commit changes every path it contains.
+pub fn changed_paths(store: &ObjectStore, commits: &[Commit]) -> Result<BTreeSet<String>, ObjectError> {
+    let mut changed = BTreeSet::new();
+
+    for commit in commits {
+        let manifest = store.load_manifest(&commit.manifest)?;
+        let parents: Vec<Manifest> = commit_parents(commit)
+            .map(|p| store.load_manifest(&store.load_commit(p)?.manifest))
+            .collect::<Result<_, _>>()?;
+        if parents.is_empty() {
+            changed.extend(manifest.paths().map(String::from));
+        }
+
+        for parent in &parents {
+            diff_paths(parent, &manifest, &mut changed);
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Paths that differ between the trees of commits `old` and `new`, whether or
+/// not one descends from the other.
+pub fn tree_changes(store: &ObjectStore, old: &Hash, new: &Hash) -> Result<BTreeSet<String>, ObjectError> {
+    let old = store.load_manifest(&store.load_commit(old)?.manifest)?;
+    let new = store.load_manifest(&store.load_commit(new)?.manifest)?;
+    let mut changed = BTreeSet::new();
+    diff_paths(&old, &new, &mut changed);
+    Ok(changed)
+}
+
+/// Add the paths added, modified or removed from `old` to `new` to `changed`.
+fn diff_paths(old: &Manifest, new: &Manifest, changed: &mut BTreeSet<String>) {
+    for (path, entry) in new.iter() {
+        if old.get(path).is_none_or(|e| e.content_hash != entry.content_hash) {
+            changed.insert(path.clone());
+        }
+    }
+    changed.extend(old.paths().filter(|p| !new.contains(p)).map(String::from));
+}
+
+/// Every object needed to reconstruct `commits`, deduplicated.
+///
+/// Objects are ordered so that content precedes the manifests that reference
+/// it, and manifests precede their commits. Uploading in this order means a
+/// receiver never holds a commit whose content is incomplete.
+pub fn objects_for_commits(store: &ObjectStore, commits: &[Commit]) -> Result<Vec<ObjectId>, ObjectError> {
+    let mut seen: HashSet<ObjectId> = HashSet::new();
+    let mut objects = Vec::new();
+
+    for commit in commits {
+        let manifest = store.load_manifest(&commit.manifest)?;
+        for id in manifest_objects(&manifest) {
+            if seen.insert(id.clone()) {
+                objects.push(id);
+            }
+        }
+        let manifest_id = ObjectId::new(ObjectKind::Manifest, &commit.manifest);
+        if seen.insert(manifest_id.clone()) {
+            objects.push(manifest_id);
+        }
+    }
+
+    // Stable sort keeps chunk order within a file, which helps sequential reads
+    objects.sort_by_key(|id| id.kind);
+    objects.extend(commits.iter().rev().map(|c| ObjectId::new(ObjectKind::Commit, &c.hash)));
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Author, Chunk, ChunkRef, ManifestEntry};
+    use tempfile::tempdir;
+
+    fn commit_file(store: &ObjectStore, parent: Option<Hash>, content: &[u8]) -> Commit {
+        let chunk = Chunk::new(content.to_vec());
+        store.store_chunk(&chunk).unwrap();
+
+        let mut manifest = Manifest::new();
+        manifest.add(ManifestEntry::new(
+            "file.bin".to_string(),
+            content.len() as u64,
+            chunk.hash,
+            vec![ChunkRef::new(chunk.hash, 0, content.len() as u64)],
+        ));
+        let manifest_hash = store.store_manifest(&manifest).unwrap();
+
+        let commit = Commit::new(parent, manifest_hash, "test", Author::new("Test", "test@test.com"));
+        store.store_commit(&commit).unwrap();
+        commit
+    }
+
+    #[test]
+    fn test_commits_between_stops_at_exclude() {
+        let temp = tempdir().unwrap();
+        let store = ObjectStore::new(temp.path());
+        store.init().unwrap();
+
+        let c1 = commit_file(&store, None, b"one");
+        let c2 = commit_file(&store, Some(c1.hash), b"two");
+        let c3 = commit_file(&store, Some(c2.hash), b"three");
+
+        let all = commits_between(&store, &[c3.hash], &HashSet::new()).unwrap();
+        assert_eq!(all.len(), 3);
+
+        let exclude = ancestors(&store, &[c1.hash]).unwrap();
+        let new: Vec<Hash> = commits_between(&store, &[c3.hash], &exclude)
+            .unwrap()
+            .iter()
+            .map(|c| c.hash)
+            .collect();
+        assert_eq!(new, vec![c3.hash, c2.hash]);
+
+        assert!(is_ancestor(&store, &c1.hash, &c3.hash).unwrap());
+        assert!(!is_ancestor(&store, &c3.hash, &c1.hash).unwrap());
+    }
+
+    #[test]
+    fn test_objects_for_commits_ordering() {
+        let temp = tempdir().unwrap();
+        let store = ObjectStore::new(temp.path());
+        store.init().unwrap();
+
+        let c1 = commit_file(&store, None, b"one");
+        let c2 = commit_file(&store, Some(c1.hash), b"two");
+        let commits = commits_between(&store, &[c2.hash], &HashSet::new()).unwrap();
+
+        let objects = objects_for_commits(&store, &commits).unwrap();
+        let kinds: Vec<ObjectKind> = objects.iter().map(|o| o.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ObjectKind::Chunk,
+                ObjectKind::Chunk,
+                ObjectKind::Manifest,
+                ObjectKind::Manifest,
+                ObjectKind::Commit,
+                ObjectKind::Commit,
+            ]
+        );
+        // Oldest commit goes first
+        assert_eq!(objects[4].id, c1.hash.to_hex());
+    }
+
+    #[test]
+    fn test_changed_paths() {
+        let temp = tempdir().unwrap();
+        let store = ObjectStore::new(temp.path());
+        store.init().unwrap();
+
+        let c1 = commit_file(&store, None, b"one");
+        let c2 = commit_file(&store, Some(c1.hash), b"one");
+        let c3 = commit_file(&store, Some(c2.hash), b"two");
+
+        assert_eq!(changed_paths(&store, std::slice::from_ref(&c1)).unwrap().len(), 1);
+        assert!(changed_paths(&store, std::slice::from_ref(&c2)).unwrap().is_empty());
+        assert!(changed_paths(&store, &[c3.clone(), c2.clone()]).unwrap().contains("file.bin"));
+
+        // Moving back from c3 to c1 changes the file even though no commit is new
+        assert!(tree_changes(&store, &c3.hash, &c1.hash).unwrap().contains("file.bin"));
+        assert!(tree_changes(&store, &c1.hash, &c2.hash).unwrap().is_empty());
+    }
+}
@@ -5,6 +5,24 @@ use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
+/// Check that a branch or tag name stays inside the refs directory.
+///
+/// Names also arrive from remotes and peers, so anything that could climb
+/// out of `refs/` is rejected: empty names and segments, absolute names and
+/// `..` anywhere.
+pub fn is_valid_ref_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains("..") && name.split(['/', '\\']).all(|part| !part.is_empty() && part != ".")
+}
+
+/// Fail with `InvalidInput` unless `name` is a valid ref name.
+fn check_ref_name(name: &str) -> io::Result<()> {
+    if is_valid_ref_name(name) {
+        Ok(())
+    } else {
+        Err(io::Error::new(io::ErrorKind::InvalidInput, format!("invalid ref name: {:?}", name)))
+    }
+}
+
 /// Reference store for branches and HEAD.
 pub struct RefStore {
     /// Root path of the refs directory.
@@ -99,6 +117,7 @@ impl RefStore {
 
     /// Update a branch to point to a commit.
     pub fn set_branch(&self, name: &str, hash: &Hash) -> io::Result<()> {
+        check_ref_name(name)?;
         let path = self.branch_path(name);
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
@@ -162,6 +181,7 @@ impl RefStore {
 
     /// Create a tag pointing to a commit.
     pub fn set_tag(&self, name: &str, hash: &Hash) -> io::Result<()> {
+        check_ref_name(name)?;
         let path = self.tag_path(name);
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
@@ -276,4 +296,22 @@ mod tests {
 
         assert!(refs.current_branch().unwrap().is_none());
     }
+
+    #[test]
+    fn test_ref_names_cannot_leave_refs_dir() {
+        let temp = tempdir().unwrap();
+        let refs = RefStore::new(&temp.path().join(".dits"));
+        refs.init().unwrap();
+        let hash = Hash::from_bytes([1u8; 32]);
+
+        for name in ["", "../../../x", "a/../../x", "/etc/x", "\\x", "a//b", "a/./b", "a/"] {
+            assert!(!is_valid_ref_name(name), "{:?}", name);
+            assert!(refs.set_branch(name, &hash).is_err(), "{:?}", name);
+            assert!(refs.set_tag(name, &hash).is_err(), "{:?}", name);
+        }
+        assert!(!temp.path().join("x").exists());
+
+        refs.set_branch("feature/color-grade", &hash).unwrap();
+        assert_eq!(refs.get_branch("feature/color-grade").unwrap(), Some(hash));
+    }
 }
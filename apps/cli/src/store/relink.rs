@@ -0,0 +1,424 @@
+//! Reversible media path rewrites for NLE project files.
+//!
+//! Project files store the absolute media paths of the machine that saved
+//! them, such as `/Volumes/RAID/...` or `D:\Footage\...`. Relinking rewrites
+//! those paths to the tracked copies in this working tree and records the
+//! rewrite in `.dits/relinks.json`. Staging a relinked file applies the
+//! rewrite in reverse, so commits keep the project's original paths.
+
+use crate::core::Hash;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use quick_xml::events::{BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+/// Gzip magic bytes.
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+/// How a project file packages its XML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    /// Plain XML (`.fcpxml`).
+    Xml,
+    /// Gzip-compressed XML (`.prproj`).
+    Gzip,
+    /// Zip archive of XML documents (`.drp`).
+    Zip,
+}
+
+impl Container {
+    /// Container of a project file that can be relinked, by extension.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+        match ext.as_str() {
+            "prproj" => Some(Self::Gzip),
+            "fcpxml" => Some(Self::Xml),
+            "drp" => Some(Self::Zip),
+            _ => None,
+        }
+    }
+}
+
+/// One rewritten media reference.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelinkMapping {
+    /// Path as stored in the committed project.
+    pub original: String,
+    /// Path of the tracked copy in the working tree.
+    pub relinked: String,
+}
+
+impl RelinkMapping {
+    /// Create a mapping.
+    pub fn new(original: impl Into<String>, relinked: impl Into<String>) -> Self {
+        Self {
+            original: original.into(),
+            relinked: relinked.into(),
+        }
+    }
+}
+
+/// A relinked project file in the working tree.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RelinkRecord {
+    /// Path of the project (relative to repo root).
+    pub path: String,
+    /// Hash of the project before relinking.
+    pub canonical: Hash,
+    /// Hash of the project as relinked.
+    pub relinked: Hash,
+    /// Rewritten references.
+    pub mappings: Vec<RelinkMapping>,
+}
+
+impl RelinkRecord {
+    /// The mappings that undo this rewrite.
+    pub fn reversed(&self) -> Vec<RelinkMapping> {
+        self.mappings
+            .iter()
+            .map(|m| RelinkMapping::new(&m.relinked, &m.original))
+            .collect()
+    }
+}
+
+/// Relink records of a working tree, stored in `.dits/relinks.json`.
+pub struct RelinkStore {
+    path: PathBuf,
+}
+
+impl RelinkStore {
+    /// Create a store for a repository.
+    pub fn new(dits_dir: &Path) -> Self {
+        Self {
+            path: dits_dir.join("relinks.json"),
+        }
+    }
+
+    fn load_all(&self) -> io::Result<BTreeMap<String, RelinkRecord>> {
+        if !self.path.exists() {
+            return Ok(BTreeMap::new());
+        }
+        let json = fs::read_to_string(&self.path)?;
+        serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    fn save_all(&self, records: &BTreeMap<String, RelinkRecord>) -> io::Result<()> {
+        if records.is_empty() {
+            return match fs::remove_file(&self.path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            };
+        }
+        let json = serde_json::to_string_pretty(records).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    /// Record for a project path, if it is relinked.
+    pub fn load(&self, path: &str) -> io::Result<Option<RelinkRecord>> {
+        Ok(self.load_all()?.remove(path))
+    }
+
+    /// All relinked projects, by path.
+    pub fn list(&self) -> io::Result<Vec<RelinkRecord>> {
+        Ok(self.load_all()?.into_values().collect())
+    }
+
+    /// Add or replace a record.
+    pub fn save(&self, record: &RelinkRecord) -> io::Result<()> {
+        let mut records = self.load_all()?;
+        records.insert(record.path.clone(), record.clone());
+        self.save_all(&records)
+    }
+
+    /// Remove a record, returning whether it existed.
+    pub fn remove(&self, path: &str) -> io::Result<bool> {
+        let mut records = self.load_all()?;
+        let existed = records.remove(path).is_some();
+        if existed {
+            self.save_all(&records)?;
+        }
+        Ok(existed)
+    }
+}
+
+/// Rewrite the media references of a project file.
+///
+/// Path text and path attributes equal to a mapping's `original` are
+/// replaced with its `relinked` path; `file://` URLs are compared decoded
+/// and rewritten as URLs. Returns `None` if the file type is not supported
+/// or no reference matched.
+pub fn rewrite_project(path: &Path, data: &[u8], mappings: &[RelinkMapping]) -> io::Result<Option<Vec<u8>>> {
+    let Some(container) = Container::from_path(path) else {
+        return Ok(None);
+    };
+    let lookup: HashMap<&str, &str> = mappings
+        .iter()
+        .map(|m| (m.original.as_str(), m.relinked.as_str()))
+        .collect();
+
+    let (rewritten, count) = match container {
+        // Premiere can also save uncompressed projects
+        Container::Gzip if data.starts_with(&GZIP_MAGIC) => {
+            let mut xml = Vec::new();
+            GzDecoder::new(data).read_to_end(&mut xml)?;
+            let (xml, count) = rewrite_xml(&xml, &lookup)?;
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&xml)?;
+            (encoder.finish()?, count)
+        }
+        Container::Xml | Container::Gzip => rewrite_xml(data, &lookup)?,
+        Container::Zip => rewrite_zip(data, &lookup)?,
+    };
+    Ok((count > 0).then_some(rewritten))
+}
+
+fn invalid_data(e: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Rewrite matching paths in an XML document, leaving everything else as is.
+fn rewrite_xml(xml: &[u8], lookup: &HashMap<&str, &str>) -> io::Result<(Vec<u8>, usize)> {
+    let mut reader = Reader::from_reader(xml);
+    let mut writer = Writer::new(Vec::with_capacity(xml.len()));
+    let mut buf = Vec::new();
+    let mut count = 0;
+
+    loop {
+        let event = match reader.read_event_into(&mut buf).map_err(invalid_data)? {
+            Event::Eof => break,
+            Event::Start(e) => Event::Start(rewrite_attributes(e, lookup, &mut count)?),
+            Event::Empty(e) => Event::Empty(rewrite_attributes(e, lookup, &mut count)?),
+            Event::Text(text) => {
+                let value = text.unescape().map_err(invalid_data)?;
+                match lookup.get(value.trim()) {
+                    Some(relinked) => {
+                        count += 1;
+                        let value = value.replacen(value.trim(), relinked, 1);
+                        Event::Text(BytesText::new(&value).into_owned())
+                    }
+                    None => Event::Text(text),
+                }
+            }
+            event => event,
+        };
+        writer.write_event(event).map_err(invalid_data)?;
+        buf.clear();
+    }
+
+    Ok((writer.into_inner(), count))
+}
+
+/// Rewrite matching path attributes of an element.
+fn rewrite_attributes<'a>(
+    start: BytesStart<'a>,
+    lookup: &HashMap<&str, &str>,
+    count: &mut usize,
+) -> io::Result<BytesStart<'a>> {
+    let mut attributes = Vec::new();
+    let mut changed = false;
+    for attr in start.attributes() {
+        let attr = attr.map_err(invalid_data)?;
+        let key = String::from_utf8_lossy(attr.key.as_ref()).into_owned();
+        let value = attr.unescape_value().map_err(invalid_data)?.into_owned();
+
+        let relinked = match lookup.get(value.trim()) {
+            Some(relinked) => Some(relinked.to_string()),
+            None if value.starts_with("file://") => lookup.get(decode_file_url(&value).as_str()).map(|p| file_url(p)),
+            None => None,
+        };
+        match relinked {
+            Some(relinked) => {
+                changed = true;
+                *count += 1;
+                attributes.push((key, relinked));
+            }
+            None => attributes.push((key, value)),
+        }
+    }
+    if !changed {
+        return Ok(start);
+    }
+
+    let mut element = BytesStart::new(String::from_utf8_lossy(start.name().as_ref()).into_owned());
+    for (key, value) in &attributes {
+        element.push_attribute((key.as_str(), value.as_str()));
+    }
+    Ok(element)
+}
+
+/// Rewrite the XML documents of a zip archive, copying other entries as is.
+fn rewrite_zip(data: &[u8], lookup: &HashMap<&str, &str>) -> io::Result<(Vec<u8>, usize)> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut count = 0;
+
+    for i in 0..archive.len() {
+        let rewritten = {
+            let mut file = archive.by_index(i)?;
+            if file.name().to_lowercase().ends_with(".xml") {
+                let mut xml = Vec::new();
+                file.read_to_end(&mut xml)?;
+                let (xml, n) = rewrite_xml(&xml, lookup)?;
+                (n > 0).then(|| (file.name().to_string(), file.compression(), xml, n))
+            } else {
+                None
+            }
+        };
+
+        match rewritten {
+            Some((name, compression, xml, n)) => {
+                writer.start_file(name, SimpleFileOptions::default().compression_method(compression))?;
+                writer.write_all(&xml)?;
+                count += n;
+            }
+            None => writer.raw_copy_file(archive.by_index_raw(i)?)?,
+        }
+    }
+
+    Ok((writer.finish()?.into_inner(), count))
+}
+
+/// Decode a `file://` URL to a path.
+fn decode_file_url(url: &str) -> String {
+    let path = url
+        .strip_prefix("file://localhost")
+        .or_else(|| url.strip_prefix("file://"))
+        .unwrap_or(url);
+
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| path.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Encode a path as a `file://` URL.
+fn file_url(path: &str) -> String {
+    let mut url = String::from("file://");
+    for byte in path.bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            url.push(byte as char);
+        } else {
+            url.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mappings() -> Vec<RelinkMapping> {
+        vec![
+            RelinkMapping::new("/Volumes/RAID/Shoot/A001 C002.mov", "/work/proj/footage/A001 C002.mov"),
+            RelinkMapping::new("D:\\Footage\\music.wav", "/work/proj/audio/music.wav"),
+        ]
+    }
+
+    #[test]
+    fn test_rewrite_gzip_xml_and_reverse() {
+        let xml = "<Project>\n  <Media>\n    <FilePath>/Volumes/RAID/Shoot/A001 C002.mov</FilePath>\n    \
+                   <FilePath>D:\\Footage\\music.wav</FilePath>\n    <FilePath>/elsewhere.mov</FilePath>\n  </Media>\n</Project>\n";
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(xml.as_bytes()).unwrap();
+        let data = encoder.finish().unwrap();
+
+        let path = Path::new("edit.prproj");
+        let relinked = rewrite_project(path, &data, &mappings()).unwrap().unwrap();
+        let mut text = String::new();
+        GzDecoder::new(&relinked[..]).read_to_string(&mut text).unwrap();
+        assert!(text.contains("<FilePath>/work/proj/footage/A001 C002.mov</FilePath>"));
+        assert!(text.contains("<FilePath>/work/proj/audio/music.wav</FilePath>"));
+        assert!(text.contains("<FilePath>/elsewhere.mov</FilePath>"));
+
+        let record = RelinkRecord {
+            path: "edit.prproj".to_string(),
+            canonical: Hash::ZERO,
+            relinked: Hash::ZERO,
+            mappings: mappings(),
+        };
+        let restored = rewrite_project(path, &relinked, &record.reversed()).unwrap().unwrap();
+        let mut text = String::new();
+        GzDecoder::new(&restored[..]).read_to_string(&mut text).unwrap();
+        assert_eq!(text, xml);
+
+        // Nothing to rewrite
+        assert!(rewrite_project(path, &restored, &[]).unwrap().is_none());
+        assert!(rewrite_project(Path::new("edit.aep"), &data, &mappings()).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_rewrite_fcpxml_file_urls() {
+        let xml = r#"<fcpxml><resources><asset id="r1" src="file:///Volumes/RAID/Shoot/A001%20C002.mov" name="A&amp;B"/></resources></fcpxml>"#;
+        let relinked = rewrite_project(Path::new("cut.fcpxml"), xml.as_bytes(), &mappings()).unwrap().unwrap();
+        let relinked = String::from_utf8(relinked).unwrap();
+        assert!(relinked.contains(r#"src="file:///work/proj/footage/A001%20C002.mov""#));
+        assert!(relinked.contains(r#"name="A&amp;B""#));
+    }
+
+    #[test]
+    fn test_rewrite_zip_keeps_other_entries() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("project.xml", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"<Project><Clip><SysPath>D:\\Footage\\music.wav</SysPath></Clip></Project>").unwrap();
+        writer.start_file("thumb.png", SimpleFileOptions::default()).unwrap();
+        writer.write_all(&[0x89, b'P', b'N', b'G']).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let relinked = rewrite_project(Path::new("grade.drp"), &data, &mappings()).unwrap().unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(relinked)).unwrap();
+        let mut xml = String::new();
+        archive.by_name("project.xml").unwrap().read_to_string(&mut xml).unwrap();
+        assert!(xml.contains("<SysPath>/work/proj/audio/music.wav</SysPath>"));
+        let mut png = Vec::new();
+        archive.by_name("thumb.png").unwrap().read_to_end(&mut png).unwrap();
+        assert_eq!(png, [0x89, b'P', b'N', b'G']);
+    }
+
+    #[test]
+    fn test_store_records() {
+        let temp = tempfile::tempdir().unwrap();
+        let store = RelinkStore::new(temp.path());
+        assert!(store.load("edit.prproj").unwrap().is_none());
+
+        let record = RelinkRecord {
+            path: "edit.prproj".to_string(),
+            canonical: Hash::ZERO,
+            relinked: Hash::ZERO,
+            mappings: mappings(),
+        };
+        store.save(&record).unwrap();
+        assert_eq!(store.load("edit.prproj").unwrap().unwrap().mappings, mappings());
+        assert_eq!(store.list().unwrap().len(), 1);
+
+        assert!(store.remove("edit.prproj").unwrap());
+        assert!(!store.remove("edit.prproj").unwrap());
+        assert!(!temp.path().join("relinks.json").exists());
+    }
+}
@@ -17,6 +17,9 @@ pub struct Remote {
     pub url: String,
     /// Push URL (defaults to fetch URL if not set).
     pub push_url: Option<String>,
+    /// Access token sent to HTTP remotes (overridden by `DITS_TOKEN`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
 }
 
 impl Remote {
@@ -26,6 +29,7 @@ impl Remote {
             name: name.into(),
             url: url.into(),
             push_url: None,
+            token: None,
         }
     }
 
@@ -142,6 +146,15 @@ impl RemoteStore {
         Ok(())
     }
 
+    /// Set or clear the access token for a remote.
+    pub fn set_token(&mut self, name: &str, token: Option<&str>) -> Result<(), RemoteError> {
+        let remote = self.remotes.get_mut(name)
+            .ok_or_else(|| RemoteError::NotFound(name.to_string()))?;
+        remote.token = token.map(String::from);
+        self.save()?;
+        Ok(())
+    }
+
     /// Get the number of remotes.
     pub fn len(&self) -> usize {
         self.remotes.len()
@@ -0,0 +1,802 @@
+//! HTTP client for repositories served by `RepoServer`.
+//!
+//! A remote URL points at one hosted repository, e.g.
+//! `http://server:8080/repos/project`. Push works in three steps:
+//!
+//! 1. Walk the local commits the remote does not have (stopping at any
+//!    remote ref we already know about).
+//! 2. Offer every object those commits reach and upload only the ones the
+//!    server reports missing, so unchanged chunks are never re-sent.
+//! 3. Compare-and-swap the remote ref; the server rejects non-fast-forward
+//!    updates unless forced.
+//!
+//! Fetch walks the other way: it downloads commits from the remote refs until
+//! it reaches commits already present locally, then the manifests of the new
+//! commits, then whichever chunks and blobs those manifests reference that
+//! the local store lacks. Every object is verified against its hash, and
+//! manifests whose paths would escape the work tree are refused. A
+//! client built [`HttpRemote::without_content`] skips the chunks and blobs,
+//! leaving a partial clone whose files are read through `dits mount --lazy`.
+//!
+//! Locks are held by the remote; [`HttpRemote::lock`] and
+//! [`HttpRemote::unlock`] act as whoever owns the client's access token.
+
+use crate::core::{Commit, Hash};
+use super::objects::{ObjectError, ObjectStore, ObjectType};
+use super::reachability::{self, ObjectId, ObjectKind, TransferError};
+use super::remote::RemoteStore;
+use super::remote_server::{
+    LockRequest, NegotiateRequest, NegotiateResponse, RefUpdateRequest, RefUpdateResponse, RemoteLock,
+};
+use super::repository::Repository;
+use futures_util::stream::{self, StreamExt, TryStreamExt};
+use reqwest::StatusCode;
+use std::collections::{BTreeMap, HashSet};
+use thiserror::Error;
+
+/// Number of object ids offered per negotiation request.
+const NEGOTIATE_BATCH: usize = 1024;
+
+/// Number of concurrent object uploads/downloads.
+pub const TRANSFER_CONCURRENCY: usize = 8;
+
+/// Errors from talking to an HTTP remote.
+#[derive(Debug, Error)]
+pub enum RemoteClientError {
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Remote returned {status}: {message}")]
+    Status { status: StatusCode, message: String },
+
+    #[error("Rejected: {0}")]
+    Rejected(String),
+
+    #[error("Transfer error: {0}")]
+    Transfer(#[from] TransferError),
+
+    #[error("Object error: {0}")]
+    Object(#[from] ObjectError),
+
+    #[error("Invalid hash: {0}")]
+    InvalidHash(#[from] hex::FromHexError),
+}
+
+/// Result of pushing one branch.
+#[derive(Debug, Clone)]
+pub struct PushOutcome {
+    /// Remote value before the push.
+    pub old: Option<Hash>,
+    /// Remote value after the push.
+    pub new: Hash,
+    /// Objects uploaded.
+    pub objects_sent: usize,
+    /// Bytes uploaded.
+    pub bytes_sent: u64,
+    /// Objects the remote already had.
+    pub objects_skipped: usize,
+    /// True if the remote was already at `new`.
+    pub up_to_date: bool,
+}
+
+/// Result of fetching from a remote.
+#[derive(Debug, Clone)]
+pub struct FetchOutcome {
+    /// Refs advertised by the remote.
+    pub refs: BTreeMap<String, Hash>,
+    /// Objects downloaded.
+    pub objects_received: usize,
+    /// Bytes downloaded.
+    pub bytes_received: u64,
+}
+
+/// Environment variable that overrides a remote's configured access token.
+pub const TOKEN_ENV: &str = "DITS_TOKEN";
+
+/// Access token for the remote named `remote_name` in `dits_dir`:
+/// `DITS_TOKEN` if set, else the token configured on the remote.
+pub fn token_for(dits_dir: &std::path::Path, remote_name: &str) -> Option<String> {
+    std::env::var(TOKEN_ENV)
+        .ok()
+        .filter(|t| !t.is_empty())
+        .or_else(|| RemoteStore::new(dits_dir).get(remote_name)?.token.clone())
+}
+
+/// Client for a single remote repository.
+pub struct HttpRemote {
+    client: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    content: bool,
+}
+
+impl HttpRemote {
+    /// Create a client for the repository at `url`.
+    pub fn new(url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: url.trim_end_matches('/').to_string(),
+            token: None,
+            content: true,
+        }
+    }
+
+    /// Authenticate every request with a bearer token.
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// Fetch only commits, manifests and text content, not chunks or MP4 blobs.
+    pub fn without_content(mut self) -> Self {
+        self.content = false;
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, url: String) -> reqwest::RequestBuilder {
+        let builder = self.client.request(method, url);
+        match &self.token {
+            Some(token) => builder.bearer_auth(token),
+            None => builder,
+        }
+    }
+
+    fn object_url(&self, id: &ObjectId) -> String {
+        format!("{}/objects/{}/{}", self.base_url, id.kind.as_str(), id.id)
+    }
+
+    /// Turn a non-success response into an error carrying the server's message.
+    async fn check(response: reqwest::Response) -> Result<reqwest::Response, RemoteClientError> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+
+        let body = response.text().await.unwrap_or_default();
+        let message = serde_json::from_str::<serde_json::Value>(&body)
+            .ok()
+            .and_then(|v| {
+                // `RepoServer` sends `{"error": "..."}`, dits-api `{"error": {"message": "..."}}`
+                let error = v.get("error")?;
+                error
+                    .as_str()
+                    .or_else(|| error.get("message").and_then(|m| m.as_str()))
+                    .map(String::from)
+            })
+            .unwrap_or(body);
+
+        if status == StatusCode::CONFLICT {
+            return Err(RemoteClientError::Rejected(message));
+        }
+        Err(RemoteClientError::Status { status, message })
+    }
+
+    /// Fetch all refs (`refs/heads/*`, `refs/tags/*`) from the remote.
+    pub async fn refs(&self) -> Result<BTreeMap<String, Hash>, RemoteClientError> {
+        let response = self.request(reqwest::Method::GET, format!("{}/refs", self.base_url)).send().await?;
+        let raw: BTreeMap<String, String> = Self::check(response).await?.json().await?;
+
+        raw.into_iter()
+            .map(|(name, hex)| Ok((name, Hash::from_hex(&hex)?)))
+            .collect()
+    }
+
+    /// Ask the remote which of `objects` it does not have.
+    pub async fn missing(&self, objects: &[ObjectId]) -> Result<Vec<ObjectId>, RemoteClientError> {
+        let mut missing = Vec::new();
+
+        for batch in objects.chunks(NEGOTIATE_BATCH) {
+            let request = NegotiateRequest {
+                objects: batch.to_vec(),
+            };
+            let response = self
+                .request(reqwest::Method::POST, format!("{}/negotiate", self.base_url))
+                .json(&request)
+                .send()
+                .await?;
+            let response: NegotiateResponse = Self::check(response).await?.json().await?;
+            missing.extend(response.missing);
+        }
+
+        Ok(missing)
+    }
+
+    /// Upload one object.
+    pub async fn upload(&self, id: &ObjectId, data: Vec<u8>) -> Result<(), RemoteClientError> {
+        let response = self.request(reqwest::Method::PUT, self.object_url(id)).body(data).send().await?;
+        Self::check(response).await?;
+        Ok(())
+    }
+
+    /// Download one object. The caller is responsible for verifying it.
+    pub async fn download(&self, id: &ObjectId) -> Result<Vec<u8>, RemoteClientError> {
+        let response = self.request(reqwest::Method::GET, self.object_url(id)).send().await?;
+        Ok(Self::check(response).await?.bytes().await?.to_vec())
+    }
+
+    /// Compare-and-swap a remote ref.
+    pub async fn update_ref(&self, request: &RefUpdateRequest) -> Result<RefUpdateResponse, RemoteClientError> {
+        let response = self
+            .request(reqwest::Method::POST, format!("{}/refs", self.base_url))
+            .json(request)
+            .send()
+            .await?;
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    /// List the locks currently held on the remote.
+    pub async fn locks(&self) -> Result<Vec<RemoteLock>, RemoteClientError> {
+        let response = self
+            .request(reqwest::Method::GET, format!("{}/locks", self.base_url))
+            .send()
+            .await?;
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    /// Lock a file on the remote. Fails with `Rejected` if someone else holds it.
+    pub async fn lock(&self, request: &LockRequest) -> Result<RemoteLock, RemoteClientError> {
+        let response = self
+            .request(reqwest::Method::POST, format!("{}/locks", self.base_url))
+            .json(request)
+            .send()
+            .await?;
+        Ok(Self::check(response).await?.json().await?)
+    }
+
+    /// Release a lock on the remote by ID.
+    pub async fn unlock(&self, id: &str, force: bool) -> Result<(), RemoteClientError> {
+        let mut url = format!("{}/locks/{}", self.base_url, id);
+        if force {
+            url.push_str("?force=true");
+        }
+        let response = self.request(reqwest::Method::DELETE, url).send().await?;
+        Self::check(response).await?;
+        Ok(())
+    }
+
+    /// Push a local branch to the remote branch of the same name.
+    pub async fn push_branch(
+        &self,
+        repo: &Repository,
+        branch: &str,
+        force: bool,
+    ) -> Result<PushOutcome, RemoteClientError> {
+        let ref_name = format!("refs/heads/{}", branch);
+        let local = repo
+            .refs()
+            .get_branch(branch)
+            .map_err(ObjectError::from)?
+            .ok_or_else(|| RemoteClientError::Rejected(format!("Branch '{}' does not exist locally", branch)))?;
+
+        let remote_refs = self.refs().await?;
+        let old = remote_refs.get(&ref_name).copied();
+
+        let mut outcome = PushOutcome {
+            old,
+            new: local,
+            objects_sent: 0,
+            bytes_sent: 0,
+            objects_skipped: 0,
+            up_to_date: old == Some(local),
+        };
+        if outcome.up_to_date {
+            return Ok(outcome);
+        }
+
+        let store = repo.objects();
+
+        // Refuse early rather than uploading objects for a push the server will reject
+        if let (Some(old), false) = (old, force) {
+            let known = store.has_object(ObjectType::Commit, &old);
+            if !known || !reachability::is_ancestor(store, &old, &local)? {
+                return Err(RemoteClientError::Rejected(format!(
+                    "Non-fast-forward push to {} (fetch first, or use --force)",
+                    ref_name
+                )));
+            }
+        }
+
+        // Everything reachable from a remote ref we know about is already on the server
+        let remote_tips: Vec<Hash> = remote_refs.values().copied().collect();
+        let exclude: HashSet<Hash> = reachability::ancestors(store, &remote_tips)?;
+        let commits = reachability::commits_between(store, &[local], &exclude)?;
+        let candidates = reachability::objects_for_commits(store, &commits)?;
+
+        let missing = self.missing(&candidates).await?;
+        outcome.objects_skipped = candidates.len() - missing.len();
+
+        // Upload content before manifests and manifests before commits
+        let git = repo.git_engine();
+        for kind in [ObjectKind::Chunk, ObjectKind::Blob, ObjectKind::GitBlob, ObjectKind::Manifest, ObjectKind::Commit] {
+            let batch: Vec<&ObjectId> = missing.iter().filter(|id| id.kind == kind).collect();
+
+            let sizes: Vec<u64> = stream::iter(batch)
+                .map(|id| async move {
+                    let data = reachability::read_object(store, git, id)?;
+                    let size = data.len() as u64;
+                    self.upload(id, data).await?;
+                    Ok::<u64, RemoteClientError>(size)
+                })
+                .buffer_unordered(TRANSFER_CONCURRENCY)
+                .try_collect()
+                .await?;
+
+            outcome.objects_sent += sizes.len();
+            outcome.bytes_sent += sizes.iter().sum::<u64>();
+        }
+
+        self.update_ref(&RefUpdateRequest {
+            name: ref_name,
+            old: old.map(|h| h.to_hex()),
+            new: local.to_hex(),
+            force,
+        })
+        .await?;
+
+        Ok(outcome)
+    }
+
+    /// Download a commit and check it is addressed by `hash`.
+    async fn download_commit(&self, hash: Hash) -> Result<(Vec<u8>, Commit), RemoteClientError> {
+        let data = self.download(&ObjectId::new(ObjectKind::Commit, &hash)).await?;
+        ObjectStore::verify_object(ObjectType::Commit, &hash, &data)?;
+        let commit: Commit = serde_json::from_slice(&data).map_err(ObjectError::from)?;
+        Ok((data, commit))
+    }
+
+    /// Download every object reachable from the remote refs that is missing locally.
+    ///
+    /// Commits and manifests are held in memory and written only after the
+    /// content they reference, so an interrupted fetch never leaves a local
+    /// commit whose objects are incomplete. Refs are not touched; the caller
+    /// decides where the advertised refs go.
+    pub async fn fetch(&self, repo: &Repository) -> Result<FetchOutcome, RemoteClientError> {
+        let refs = self.refs().await?;
+        let store = repo.objects();
+        let git = repo.git_engine();
+
+        let mut outcome = FetchOutcome {
+            refs: refs.clone(),
+            objects_received: 0,
+            bytes_received: 0,
+        };
+
+        // Walk the commit graph one frontier at a time until we reach known history
+        let mut seen: HashSet<Hash> = HashSet::new();
+        let mut frontier: Vec<Hash> = refs.values().copied().collect();
+        let mut commits: Vec<(Hash, Vec<u8>)> = Vec::new();
+        let mut manifest_hashes: Vec<Hash> = Vec::new();
+
+        while !frontier.is_empty() {
+            frontier.retain(|h| !store.has_object(ObjectType::Commit, h) && seen.insert(*h));
+
+            let downloaded: Vec<(Hash, Vec<u8>, Commit)> = stream::iter(std::mem::take(&mut frontier))
+                .map(|hash| async move {
+                    let (data, commit) = self.download_commit(hash).await?;
+                    Ok::<_, RemoteClientError>((hash, data, commit))
+                })
+                .buffer_unordered(TRANSFER_CONCURRENCY)
+                .try_collect()
+                .await?;
+
+            for (hash, data, commit) in downloaded {
+                frontier.extend(reachability::commit_parents(&commit).copied());
+                if !store.has_object(ObjectType::Manifest, &commit.manifest) {
+                    manifest_hashes.push(commit.manifest);
+                }
+                outcome.bytes_received += data.len() as u64;
+                commits.push((hash, data));
+            }
+        }
+
+        manifest_hashes.sort_by_key(|h| h.to_hex());
+        manifest_hashes.dedup();
+
+        let manifests: Vec<(Hash, Vec<u8>)> = stream::iter(manifest_hashes)
+            .map(|hash| async move {
+                let data = self.download(&ObjectId::new(ObjectKind::Manifest, &hash)).await?;
+                ObjectStore::verify_object(ObjectType::Manifest, &hash, &data)?;
+                Ok::<_, RemoteClientError>((hash, data))
+            })
+            .buffer_unordered(TRANSFER_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        // Content referenced by the new manifests (chunks, MP4 ftyp/moov blobs, text blobs)
+        let mut wanted: HashSet<ObjectId> = HashSet::new();
+        let mut content: Vec<ObjectId> = Vec::new();
+        for (_, data) in &manifests {
+            let manifest = ObjectStore::decode_manifest(data)?;
+            ObjectStore::check_manifest_paths(&manifest)?;
+            for id in reachability::manifest_objects(&manifest) {
+                if !self.content && matches!(id.kind, ObjectKind::Chunk | ObjectKind::Blob) {
+                    continue;
+                }
+                if !reachability::has_object(store, git, &id) && wanted.insert(id.clone()) {
+                    content.push(id);
+                }
+            }
+        }
+
+        let sizes: Vec<u64> = stream::iter(content)
+            .map(|id| async move {
+                let data = self.download(&id).await?;
+                reachability::write_object(store, git, &id, &data)?;
+                Ok::<u64, RemoteClientError>(data.len() as u64)
+            })
+            .buffer_unordered(TRANSFER_CONCURRENCY)
+            .try_collect()
+            .await?;
+        outcome.objects_received += sizes.len();
+        outcome.bytes_received += sizes.iter().sum::<u64>();
+
+        for (hash, data) in &manifests {
+            store.write_object(ObjectType::Manifest, hash, data)?;
+            outcome.bytes_received += data.len() as u64;
+        }
+        outcome.objects_received += manifests.len();
+
+        // Oldest first, so a commit's parents are always present before it
+        for (hash, data) in commits.iter().rev() {
+            store.write_object(ObjectType::Commit, hash, data)?;
+        }
+        outcome.objects_received += commits.len();
+
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::remote_server::RepoServer;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    /// Serve `base` on an ephemeral port and return the URL of `base/<repo>`.
+    async fn serve(base: &Path, repo: &str) -> String {
+        serve_with(RepoServer::new(base.to_path_buf()), repo).await
+    }
+
+    async fn serve_with(server: RepoServer, repo: &str) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = Arc::new(server).router();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}/repos/{}", addr, repo)
+    }
+
+    fn commit_file(repo: &Repository, name: &str, content: &[u8]) {
+        fs::write(repo.work_dir().join(name), content).unwrap();
+        repo.add(name).unwrap();
+        repo.commit(&format!("update {}", name)).unwrap();
+    }
+
+    fn binary(size: usize, seed: u64) -> Vec<u8> {
+        let mut state = seed;
+        (0..size)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+                (state >> 33) as u8
+            })
+            .collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_push_uploads_only_missing_objects() {
+        let server_dir = tempdir().unwrap();
+        let hosted = Repository::init(&server_dir.path().join("project")).unwrap();
+        let url = serve(server_dir.path(), "project").await;
+
+        let local_dir = tempdir().unwrap();
+        let local = Repository::init(local_dir.path()).unwrap();
+        let mut footage = binary(2 * 1024 * 1024, 1);
+        commit_file(&local, "clip.bin", &footage);
+
+        let remote = HttpRemote::new(&url);
+        let first = remote.push_branch(&local, "main", false).await.unwrap();
+        assert!(first.objects_sent > 0);
+        assert_eq!(hosted.refs().get_branch("main").unwrap(), local.head().unwrap());
+
+        let again = remote.push_branch(&local, "main", false).await.unwrap();
+        assert!(again.up_to_date);
+
+        // Change a small region: only the affected chunk(s) travel
+        footage[100] ^= 0xff;
+        commit_file(&local, "clip.bin", &footage);
+        let second = remote.push_branch(&local, "main", false).await.unwrap();
+        assert!(second.objects_skipped > 0);
+        assert!(second.bytes_sent < first.bytes_sent);
+
+        let head = local.head().unwrap().unwrap();
+        let commit = hosted.load_commit(&head).unwrap();
+        assert!(hosted.load_manifest(&commit.manifest).is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_push_rejects_non_fast_forward() {
+        let server_dir = tempdir().unwrap();
+        Repository::init(&server_dir.path().join("project")).unwrap();
+        let url = serve(server_dir.path(), "project").await;
+        let remote = HttpRemote::new(&url);
+
+        let alice_dir = tempdir().unwrap();
+        let alice = Repository::init(alice_dir.path()).unwrap();
+        commit_file(&alice, "edit.txt", b"alice");
+        remote.push_branch(&alice, "main", false).await.unwrap();
+
+        let bob_dir = tempdir().unwrap();
+        let bob = Repository::init(bob_dir.path()).unwrap();
+        commit_file(&bob, "edit.txt", b"bob");
+
+        let result = remote.push_branch(&bob, "main", false).await;
+        assert!(matches!(result, Err(RemoteClientError::Rejected(_))));
+
+        let forced = remote.push_branch(&bob, "main", true).await.unwrap();
+        assert_eq!(Some(forced.new), bob.head().unwrap());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fetch_downloads_only_missing_objects() {
+        let server_dir = tempdir().unwrap();
+        Repository::init(&server_dir.path().join("project")).unwrap();
+        let url = serve(server_dir.path(), "project").await;
+        let remote = HttpRemote::new(&url);
+
+        let author_dir = tempdir().unwrap();
+        let author = Repository::init(author_dir.path()).unwrap();
+        let mut footage = binary(2 * 1024 * 1024, 7);
+        commit_file(&author, "clip.bin", &footage);
+        commit_file(&author, "notes.txt", b"scene 1 notes");
+        remote.push_branch(&author, "main", false).await.unwrap();
+
+        let reader_dir = tempdir().unwrap();
+        let reader = Repository::init(reader_dir.path()).unwrap();
+        let first = remote.fetch(&reader).await.unwrap();
+        let tip = author.head().unwrap().unwrap();
+        assert_eq!(first.refs.get("refs/heads/main"), Some(&tip));
+
+        // Every object reachable from the tip is now local
+        let commits = reachability::commits_between(reader.objects(), &[tip], &HashSet::new()).unwrap();
+        assert_eq!(commits.len(), 2);
+        for id in reachability::objects_for_commits(reader.objects(), &commits).unwrap() {
+            assert!(reachability::has_object(reader.objects(), reader.git_engine(), &id));
+        }
+
+        let again = remote.fetch(&reader).await.unwrap();
+        assert_eq!(again.objects_received, 0);
+
+        footage[100] ^= 0xff;
+        commit_file(&author, "clip.bin", &footage);
+        remote.push_branch(&author, "main", false).await.unwrap();
+        let second = remote.fetch(&reader).await.unwrap();
+        assert!(second.bytes_received < first.bytes_received);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_push_requires_token_when_tokens_are_configured() {
+        let server_dir = tempdir().unwrap();
+        let hosted = Repository::init(&server_dir.path().join("project")).unwrap();
+        let tokens = [("alice-token".to_string(), "alice".to_string())].into_iter().collect();
+        let server = RepoServer::new(server_dir.path().to_path_buf()).with_tokens(tokens);
+        let url = serve_with(server, "project").await;
+
+        let local_dir = tempdir().unwrap();
+        let local = Repository::init(local_dir.path()).unwrap();
+        commit_file(&local, "notes.txt", b"scene 1 notes");
+        let head = local.head().unwrap().unwrap();
+
+        for remote in [HttpRemote::new(&url), HttpRemote::new(&url).with_token(Some("stolen".into()))] {
+            assert!(matches!(
+                remote.push_branch(&local, "main", true).await,
+                Err(RemoteClientError::Status { status: StatusCode::UNAUTHORIZED, .. })
+            ));
+            let commit = ObjectId::new(ObjectKind::Commit, &head);
+            let data = local.objects().read_object(ObjectType::Commit, &head).unwrap();
+            assert!(matches!(
+                remote.upload(&commit, data).await,
+                Err(RemoteClientError::Status { status: StatusCode::UNAUTHORIZED, .. })
+            ));
+            let request = RefUpdateRequest { name: "refs/heads/main".into(), old: None, new: head.to_hex(), force: true };
+            assert!(matches!(
+                remote.update_ref(&request).await,
+                Err(RemoteClientError::Status { status: StatusCode::UNAUTHORIZED, .. })
+            ));
+        }
+        assert_eq!(hosted.refs().get_branch("main").unwrap(), None);
+
+        let alice = HttpRemote::new(&url).with_token(Some("alice-token".into()));
+        alice.push_branch(&local, "main", false).await.unwrap();
+        assert_eq!(hosted.refs().get_branch("main").unwrap(), Some(head));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_ref_update_requires_complete_history() {
+        let server_dir = tempdir().unwrap();
+        let hosted = Repository::init(&server_dir.path().join("project")).unwrap();
+        let url = serve(server_dir.path(), "project").await;
+        let remote = HttpRemote::new(&url);
+
+        let local_dir = tempdir().unwrap();
+        let local = Repository::init(local_dir.path()).unwrap();
+        commit_file(&local, "clip.bin", &binary(256 * 1024, 3));
+        let head = local.head().unwrap().unwrap();
+        let manifest = local.load_commit(&head).unwrap().manifest;
+
+        // Only the commit and its manifest: the chunks never arrive
+        for (kind, obj_type, hash) in [
+            (ObjectKind::Manifest, ObjectType::Manifest, manifest),
+            (ObjectKind::Commit, ObjectType::Commit, head),
+        ] {
+            let data = local.objects().read_object(obj_type, &hash).unwrap();
+            remote.upload(&ObjectId::new(kind, &hash), data).await.unwrap();
+        }
+
+        let request = RefUpdateRequest { name: "refs/heads/main".into(), old: None, new: head.to_hex(), force: true };
+        match remote.update_ref(&request).await {
+            Err(RemoteClientError::Status { status, message }) => {
+                assert_eq!(status, StatusCode::BAD_REQUEST);
+                assert!(message.starts_with("chunk "), "{}", message);
+            }
+            other => panic!("expected rejection, got {:?}", other.map(|r| r.new)),
+        }
+        assert_eq!(hosted.refs().get_branch("main").unwrap(), None);
+
+        remote.push_branch(&local, "main", false).await.unwrap();
+        assert_eq!(hosted.refs().get_branch("main").unwrap(), Some(head));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_fetch_refuses_manifest_paths_outside_work_tree() {
+        use crate::core::Author;
+        use crate::store::repository::RepoError;
+
+        let server_dir = tempdir().unwrap();
+        let hosted = Repository::init(&server_dir.path().join("project").join("work")).unwrap();
+        commit_file(&hosted, "notes.txt", b"harmless");
+
+        // A compromised server rewrites a manifest entry to climb out of the work tree
+        let head = hosted.head().unwrap().unwrap();
+        let mut manifest = hosted.load_manifest(&hosted.load_commit(&head).unwrap().manifest).unwrap();
+        let mut entry = manifest.remove("notes.txt").unwrap();
+        entry.path = "../../escaped.txt".to_string();
+        manifest.add(entry);
+        let manifest_hash = hosted.objects().store_manifest(&manifest).unwrap();
+        let crafted = Commit::new(Some(head), manifest_hash, "crafted", Author::new("mallory", "m@example.com"));
+        hosted.objects().store_commit(&crafted).unwrap();
+        hosted.refs().set_branch("main", &crafted.hash).unwrap();
+
+        // Checkout refuses it too, before writing anything
+        assert!(matches!(hosted.checkout(&crafted.hash), Err(RepoError::Object(ObjectError::InvalidPath(_)))));
+        assert!(!server_dir.path().join("escaped.txt").exists());
+
+        let url = serve(&server_dir.path().join("project"), "work").await;
+        let reader_dir = tempdir().unwrap();
+        let reader = Repository::init(&reader_dir.path().join("clone")).unwrap();
+        let result = HttpRemote::new(&url).fetch(&reader).await;
+        assert!(matches!(result, Err(RemoteClientError::Object(ObjectError::InvalidPath(_)))));
+        assert!(!reader.objects().has_object(ObjectType::Manifest, &manifest_hash));
+        assert!(!reader.objects().has_object(ObjectType::Commit, &crafted.hash));
+        assert!(!reader_dir.path().join("escaped.txt").exists());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_locks_are_owned_by_the_token_holder() {
+        let server_dir = tempdir().unwrap();
+        Repository::init(&server_dir.path().join("project")).unwrap();
+        let tokens = [("alice-token", "alice"), ("bob-token", "bob")]
+            .into_iter()
+            .map(|(t, u)| (t.to_string(), u.to_string()))
+            .collect();
+        let server = RepoServer::new(server_dir.path().to_path_buf()).with_tokens(tokens);
+        let url = serve_with(server, "project").await;
+
+        let alice = HttpRemote::new(&url).with_token(Some("alice-token".into()));
+        let bob = HttpRemote::new(&url).with_token(Some("bob-token".into()));
+        let request = LockRequest {
+            path: "edit/scene.prproj".into(),
+            reason: Some("cutting".into()),
+            ttl_secs: None,
+        };
+
+        let anonymous = HttpRemote::new(&url).lock(&request).await;
+        assert!(matches!(
+            anonymous,
+            Err(RemoteClientError::Status { status: StatusCode::UNAUTHORIZED, .. })
+        ));
+
+        let lock = alice.lock(&request).await.unwrap();
+        assert_eq!(lock.owner, "alice");
+        assert!(matches!(bob.lock(&request).await, Err(RemoteClientError::Rejected(_))));
+
+        let listed = bob.locks().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].owner, "alice");
+        assert_eq!(listed[0].reason.as_deref(), Some("cutting"));
+
+        assert!(matches!(
+            bob.unlock(&lock.id, false).await,
+            Err(RemoteClientError::Status { status: StatusCode::FORBIDDEN, .. })
+        ));
+        alice.unlock(&lock.id, false).await.unwrap();
+        assert!(bob.locks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_only_admins_can_break_locks() {
+        let server_dir = tempdir().unwrap();
+        Repository::init(&server_dir.path().join("project")).unwrap();
+        let tokens = [("alice-token", "alice"), ("bob-token", "bob"), ("carol-token", "carol")]
+            .into_iter()
+            .map(|(t, u)| (t.to_string(), u.to_string()))
+            .collect();
+        let server = RepoServer::new(server_dir.path().to_path_buf())
+            .with_tokens(tokens)
+            .with_admins(["carol".to_string()].into_iter().collect());
+        let url = serve_with(server, "project").await;
+        let alice = HttpRemote::new(&url).with_token(Some("alice-token".into()));
+        let bob = HttpRemote::new(&url).with_token(Some("bob-token".into()));
+        let carol = HttpRemote::new(&url).with_token(Some("carol-token".into()));
+
+        let request = LockRequest { path: "scene.blend".into(), reason: None, ttl_secs: None };
+        let lock = alice.lock(&request).await.unwrap();
+        assert!(matches!(
+            bob.unlock(&lock.id, true).await,
+            Err(RemoteClientError::Status { status: StatusCode::FORBIDDEN, .. })
+        ));
+        assert_eq!(bob.locks().await.unwrap().len(), 1);
+
+        carol.unlock(&lock.id, true).await.unwrap();
+        assert!(bob.locks().await.unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_push_rejects_changes_to_files_locked_by_others() {
+        let server_dir = tempdir().unwrap();
+        Repository::init(&server_dir.path().join("project")).unwrap();
+        let tokens = [("alice-token", "alice"), ("bob-token", "bob")]
+            .into_iter()
+            .map(|(t, u)| (t.to_string(), u.to_string()))
+            .collect();
+        let server = RepoServer::new(server_dir.path().to_path_buf()).with_tokens(tokens);
+        let url = serve_with(server, "project").await;
+        let alice = HttpRemote::new(&url).with_token(Some("alice-token".into()));
+        let bob = HttpRemote::new(&url).with_token(Some("bob-token".into()));
+
+        alice
+            .lock(&LockRequest { path: "scene.blend".into(), reason: None, ttl_secs: None })
+            .await
+            .unwrap();
+
+        let bob_dir = tempdir().unwrap();
+        let bob_repo = Repository::init(bob_dir.path()).unwrap();
+        commit_file(&bob_repo, "scene.blend", b"bob's scene");
+        match bob.push_branch(&bob_repo, "main", true).await {
+            Err(RemoteClientError::Rejected(message)) => assert!(message.contains("locked by alice")),
+            other => panic!("expected rejection, got {:?}", other.map(|o| o.new)),
+        }
+
+        let alice_dir = tempdir().unwrap();
+        let alice_repo = Repository::init(alice_dir.path()).unwrap();
+        commit_file(&alice_repo, "scene.blend", b"alice's scene");
+        alice.push_branch(&alice_repo, "main", false).await.unwrap();
+
+        // Replacing main with history that lacks the file changes it too
+        let notes_dir = tempdir().unwrap();
+        let notes = Repository::init(notes_dir.path()).unwrap();
+        commit_file(&notes, "notes.txt", b"notes");
+        assert!(matches!(
+            bob.push_branch(&notes, "main", true).await,
+            Err(RemoteClientError::Rejected(_))
+        ));
+
+        // Files nobody has locked are unaffected
+        let head = notes.refs().get_branch("main").unwrap().unwrap();
+        notes.refs().set_branch("notes", &head).unwrap();
+        bob.push_branch(&notes, "notes", false).await.unwrap();
+    }
+}
@@ -1,42 +1,313 @@
 //! HTTP server for serving Dits repositories over the network.
 //!
-//! This implements basic HTTP endpoints for remote repository access.
+//! This implements basic HTTP endpoints for remote repository access:
+//!
+//! - `GET  /repos/:repo/refs` - list branches and tags
+//! - `POST /repos/:repo/refs` - compare-and-swap a ref (push)
+//! - `POST /repos/:repo/negotiate` - report which of a list of objects are missing
+//! - `GET  /repos/:repo/objects/:kind/:id` - download an object
+//! - `PUT  /repos/:repo/objects/:kind/:id` - upload an object (verified by hash)
+//! - `GET  /repos/:repo/locks` - list file locks
+//! - `POST /repos/:repo/locks` - lock a file as the authenticated user
+//! - `DELETE /repos/:repo/locks/:id` - release a lock held by the authenticated user
+//!   (`?force=true` releases anyone's lock, for server admins only)
+//!
+//! Lock writes require an `Authorization: Bearer <token>` header naming a
+//! token from the server's tokens file; the lock owner is the user the token
+//! belongs to, never a name supplied by the client. Once a tokens file is
+//! configured, negotiation, uploads and ref updates require a token too, and
+//! a ref only moves once every object its new commit reaches is present.
+//! Browsers on other origins may read but not write.
+//!
 //! Full QUIC protocol implementation will come in Phase 4b.
 
+use crate::core::Hash;
+use super::git_engine::GitTextEngine;
+use super::locks::{Lock, LockError, LockStore, LockViolation};
+use super::objects::{ObjectError, ObjectStore, ObjectType};
+use super::reachability::{self, ObjectId, ObjectKind, TransferError};
 use axum::{
-    extract::Path,
-    http::StatusCode,
+    body::Bytes,
+    extract::{DefaultBodyLimit, Path, Query},
+    http::{header, HeaderMap, Method, StatusCode},
     response::Json,
-    routing::get,
+    routing::{delete, get, post},
     Router,
 };
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
-use tower_http::cors::CorsLayer;
+use tokio::sync::Mutex;
+use tower_http::cors::{Any, CorsLayer};
+
+/// Largest object accepted by the upload endpoint (large MP4 `moov` blobs included).
+const MAX_OBJECT_SIZE: usize = 512 * 1024 * 1024;
+
+/// Request body for `POST /repos/:repo/negotiate`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegotiateRequest {
+    /// Objects the client is able to send.
+    pub objects: Vec<ObjectId>,
+}
+
+/// Response body for `POST /repos/:repo/negotiate`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NegotiateResponse {
+    /// Subset of the requested objects the server does not have.
+    pub missing: Vec<ObjectId>,
+}
+
+/// Request body for `POST /repos/:repo/refs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefUpdateRequest {
+    /// Full ref name (e.g. `refs/heads/main`).
+    pub name: String,
+    /// Value the client expects the ref to have (None if it should not exist).
+    pub old: Option<String>,
+    /// New commit hash.
+    pub new: String,
+    /// Skip the expected-value and fast-forward checks.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Response body for a successful ref update.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RefUpdateResponse {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: String,
+}
+
+/// Request body for `POST /repos/:repo/locks`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockRequest {
+    /// Path to lock (relative to the repository root).
+    pub path: String,
+    /// Why the file is being locked.
+    pub reason: Option<String>,
+    /// Lock lifetime in seconds (server default if omitted).
+    pub ttl_secs: Option<u64>,
+}
+
+/// A lock as reported by a remote.
+///
+/// Shared with `dits-api`, whose lock routes use the same shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemoteLock {
+    pub id: String,
+    pub path: String,
+    /// Name of the authenticated user holding the lock.
+    pub owner: String,
+    pub locked_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub reason: Option<String>,
+}
+
+impl From<&Lock> for RemoteLock {
+    fn from(lock: &Lock) -> Self {
+        let time = |secs: u64| DateTime::from_timestamp(secs as i64, 0).unwrap_or_default();
+        Self {
+            id: lock.id.clone(),
+            path: lock.path.clone(),
+            owner: lock.owner.clone(),
+            locked_at: time(lock.acquired_at),
+            expires_at: time(lock.expires_at),
+            reason: lock.reason.clone(),
+        }
+    }
+}
+
+impl From<RemoteLock> for Lock {
+    fn from(lock: RemoteLock) -> Self {
+        Self {
+            id: lock.id,
+            path: lock.path,
+            owner: lock.owner,
+            acquired_at: lock.locked_at.timestamp().max(0) as u64,
+            expires_at: lock.expires_at.timestamp().max(0) as u64,
+            reason: lock.reason,
+            held: false,
+        }
+    }
+}
+
+/// Query string for `DELETE /repos/:repo/locks/:id`.
+#[derive(Debug, Default, Deserialize)]
+struct UnlockQuery {
+    /// Release a lock held by someone else.
+    #[serde(default)]
+    force: bool,
+}
+
+type ApiError = (StatusCode, Json<serde_json::Value>);
+
+fn api_error(status: StatusCode, message: impl Into<String>) -> ApiError {
+    (status, Json(json!({ "error": message.into() })))
+}
+
+fn lock_error(e: LockError) -> ApiError {
+    match e {
+        LockError::AlreadyLocked { .. } => api_error(StatusCode::CONFLICT, e.to_string()),
+        LockError::NotOwner { .. } => api_error(StatusCode::FORBIDDEN, e.to_string()),
+        LockError::NotLocked(_) => api_error(StatusCode::NOT_FOUND, e.to_string()),
+        LockError::Io(_) => api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+fn transfer_error(e: TransferError) -> ApiError {
+    match e {
+        TransferError::Object(ObjectError::NotFound(id)) => api_error(StatusCode::NOT_FOUND, id),
+        TransferError::Object(ObjectError::ChecksumMismatch { .. })
+        | TransferError::Object(ObjectError::Json(_))
+        | TransferError::GitChecksumMismatch { .. }
+        | TransferError::InvalidId(_) => api_error(StatusCode::BAD_REQUEST, e.to_string()),
+        _ => api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim)
+}
+
+/// Commits every branch and tag of a hosted repository point at.
+fn ref_tips(dits_dir: &std::path::Path) -> Vec<Hash> {
+    ["refs/heads", "refs/tags"]
+        .iter()
+        .filter_map(|dir| fs::read_dir(dits_dir.join(dir)).ok())
+        .flatten()
+        .filter_map(|entry| fs::read_to_string(entry.ok()?.path()).ok())
+        .filter_map(|content| Hash::from_hex(content.trim()).ok())
+        .collect()
+}
+
+/// Changes that moving a ref of a hosted repository from `old` to `new`
+/// would make to files locked by someone other than `pusher`.
+///
+/// A moved ref changes whatever differs between its old and new tree, so
+/// forced rewinds and moves to existing commits are checked too. A new ref
+/// only brings in the commits no other ref reaches, so history that was there
+/// before the lock is not held against it.
+pub fn push_lock_violations(
+    dits_dir: &std::path::Path,
+    old: Option<Hash>,
+    new: Hash,
+    pusher: Option<&str>,
+) -> Result<Vec<LockViolation>, ObjectError> {
+    let locks = LockStore::new(dits_dir);
+    if locks.list().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let store = ObjectStore::new(dits_dir);
+    let changed = match old {
+        Some(old) => reachability::tree_changes(&store, &old, &new)?,
+        None => {
+            let exclude = reachability::ancestors(&store, &ref_tips(dits_dir))?;
+            let commits = reachability::commits_between(&store, &[new], &exclude)?;
+            reachability::changed_paths(&store, &commits)?
+        }
+    };
+    Ok(locks.violations(changed.iter().map(String::as_str), |l| Some(l.owner.as_str()) == pusher, |_| false))
+}
 
 /// Repository server state
 pub struct RepoServer {
     /// Base directory containing repositories
     base_dir: PathBuf,
+    /// Serializes ref updates so concurrent pushes cannot both win
+    ref_lock: Mutex<()>,
+    /// Serializes lock store updates
+    lock_store_lock: Mutex<()>,
+    /// Access token -> user name
+    tokens: HashMap<String, String>,
+    /// Users allowed to break other users' locks
+    admins: HashSet<String>,
 }
 
 impl RepoServer {
     /// Create a new repository server
     pub fn new(base_dir: PathBuf) -> Self {
-        Self { base_dir }
+        Self {
+            base_dir,
+            ref_lock: Mutex::new(()),
+            lock_store_lock: Mutex::new(()),
+            tokens: HashMap::new(),
+            admins: HashSet::new(),
+        }
+    }
+
+    /// Accept the given access tokens (token -> user name) for locks and pushes.
+    pub fn with_tokens(mut self, tokens: HashMap<String, String>) -> Self {
+        self.tokens = tokens;
+        self
+    }
+
+    /// Allow the given users to force-release locks held by others.
+    pub fn with_admins(mut self, admins: HashSet<String>) -> Self {
+        self.admins = admins;
+        self
     }
 
     /// Create the Axum router with all routes
     pub fn router(self: Arc<Self>) -> Router {
         Router::new()
-            .route("/repos/:repo/refs", get(Self::get_refs))
+            .route("/repos/:repo/refs", get(Self::get_refs).post(Self::update_ref))
+            .route("/repos/:repo/negotiate", post(Self::negotiate))
             .route("/repos/:repo/objects/:hash", get(Self::get_object))
-            .layer(CorsLayer::permissive())
+            .route(
+                "/repos/:repo/objects/:kind/:id",
+                get(Self::download_object).put(Self::upload_object),
+            )
+            .route("/repos/:repo/locks", get(Self::list_locks).post(Self::acquire_lock))
+            .route("/repos/:repo/locks/:id", delete(Self::release_lock))
+            .layer(DefaultBodyLimit::max(MAX_OBJECT_SIZE))
+            .layer(CorsLayer::new().allow_origin(Any).allow_methods([Method::GET]))
             .with_state(self)
     }
 
+    /// Resolve the `.dits` directory of a hosted repository.
+    fn dits_dir(&self, repo: &str) -> Result<PathBuf, ApiError> {
+        if repo.is_empty() || repo.contains("..") || repo.contains('/') || repo.contains('\\') {
+            return Err(api_error(StatusCode::BAD_REQUEST, "Invalid repository name"));
+        }
+
+        let dits_dir = self.base_dir.join(repo).join(".dits");
+        if !dits_dir.exists() {
+            return Err(api_error(StatusCode::NOT_FOUND, format!("Repository not found: {}", repo)));
+        }
+        Ok(dits_dir)
+    }
+
+    /// Resolve the user a request's bearer token belongs to.
+    fn authenticate(&self, headers: &HeaderMap) -> Result<String, ApiError> {
+        let token = bearer_token(headers)
+            .ok_or_else(|| api_error(StatusCode::UNAUTHORIZED, "missing bearer token"))?;
+        self.tokens
+            .get(token)
+            .cloned()
+            .ok_or_else(|| api_error(StatusCode::UNAUTHORIZED, "invalid access token"))
+    }
+
+    /// Resolve the user allowed to write to a repository.
+    ///
+    /// A server without tokens is open to anonymous writes; once tokens are
+    /// configured every write must carry a valid one.
+    fn authorize_write(&self, headers: &HeaderMap) -> Result<Option<String>, ApiError> {
+        if self.tokens.is_empty() {
+            return Ok(None);
+        }
+        self.authenticate(headers).map(Some)
+    }
+
     /// Get repository refs
     async fn get_refs(
         Path(repo): Path<String>,
@@ -85,38 +356,311 @@ impl RepoServer {
         Path((repo, hash)): Path<(String, String)>,
         state: axum::extract::State<Arc<RepoServer>>,
     ) -> Result<Vec<u8>, StatusCode> {
-        let repo_path = state.base_dir.join(repo);
+        let dits_dir = state.dits_dir(&repo).map_err(|(status, _)| status)?;
+        let hash = Hash::from_hex(&hash).map_err(|_| StatusCode::BAD_REQUEST)?;
+        let store = ObjectStore::new(&dits_dir);
 
-        if !repo_path.join(".dits").exists() {
-            return Err(StatusCode::NOT_FOUND);
+        // Try different object types
+        let object_types = [ObjectType::Chunk, ObjectType::Manifest, ObjectType::Commit, ObjectType::Blob];
+
+        for obj_type in object_types {
+            if store.has_object(obj_type, &hash) {
+                return store
+                    .read_object(obj_type, &hash)
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+            }
         }
 
-        // Parse hash to determine object path
-        if hash.len() < 4 {
-            return Err(StatusCode::BAD_REQUEST);
+        Err(StatusCode::NOT_FOUND)
+    }
+
+    /// Report which of the offered objects the repository is missing
+    async fn negotiate(
+        Path(repo): Path<String>,
+        state: axum::extract::State<Arc<RepoServer>>,
+        headers: HeaderMap,
+        Json(request): Json<NegotiateRequest>,
+    ) -> Result<Json<NegotiateResponse>, ApiError> {
+        state.authorize_write(&headers)?;
+        let dits_dir = state.dits_dir(&repo)?;
+        let store = ObjectStore::new(&dits_dir);
+        let git = GitTextEngine::open(&dits_dir).ok();
+
+        let missing = request
+            .objects
+            .into_iter()
+            .filter(|id| !reachability::has_object(&store, git.as_ref(), id))
+            .collect();
+
+        Ok(Json(NegotiateResponse { missing }))
+    }
+
+    /// Download a typed object
+    async fn download_object(
+        Path((repo, kind, id)): Path<(String, String, String)>,
+        state: axum::extract::State<Arc<RepoServer>>,
+    ) -> Result<Vec<u8>, ApiError> {
+        let dits_dir = state.dits_dir(&repo)?;
+        let kind = ObjectKind::parse(&kind)
+            .ok_or_else(|| api_error(StatusCode::BAD_REQUEST, format!("Unknown object kind: {}", kind)))?;
+        let store = ObjectStore::new(&dits_dir);
+        let git = GitTextEngine::open(&dits_dir).ok();
+
+        let id = ObjectId { kind, id };
+        if !reachability::has_object(&store, git.as_ref(), &id) {
+            return Err(api_error(StatusCode::NOT_FOUND, id.id));
         }
+        reachability::read_object(&store, git.as_ref(), &id).map_err(transfer_error)
+    }
+
+    /// Upload a typed object. The body must hash to the id in the path.
+    async fn upload_object(
+        Path((repo, kind, id)): Path<(String, String, String)>,
+        state: axum::extract::State<Arc<RepoServer>>,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> Result<StatusCode, ApiError> {
+        state.authorize_write(&headers)?;
+        let dits_dir = state.dits_dir(&repo)?;
+        let kind = ObjectKind::parse(&kind)
+            .ok_or_else(|| api_error(StatusCode::BAD_REQUEST, format!("Unknown object kind: {}", kind)))?;
+        let store = ObjectStore::new(&dits_dir);
+        let git = if kind == ObjectKind::GitBlob {
+            Some(
+                GitTextEngine::open(&dits_dir)
+                    .or_else(|_| GitTextEngine::init(&dits_dir))
+                    .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+            )
+        } else {
+            None
+        };
 
-        let prefix1 = &hash[0..2];
-        let prefix2 = &hash[2..4];
-        let objects_dir = repo_path.join(".dits/objects");
+        let id = ObjectId { kind, id };
+        let created = reachability::write_object(&store, git.as_ref(), &id, &body).map_err(transfer_error)?;
 
-        // Try different object types
-        let object_types = ["chunks", "manifests", "commits"];
+        Ok(if created { StatusCode::CREATED } else { StatusCode::OK })
+    }
+
+    /// Atomically move a ref, rejecting stale or non-fast-forward updates
+    /// and updates that change files locked by someone other than the pusher
+    async fn update_ref(
+        Path(repo): Path<String>,
+        state: axum::extract::State<Arc<RepoServer>>,
+        headers: HeaderMap,
+        Json(request): Json<RefUpdateRequest>,
+    ) -> Result<Json<RefUpdateResponse>, ApiError> {
+        let pusher = state.authorize_write(&headers)?;
+        let dits_dir = state.dits_dir(&repo)?;
 
-        for obj_type in &object_types {
-            let object_path = objects_dir.join(obj_type).join(prefix1).join(prefix2).join(&hash);
-            if object_path.exists() {
-                return std::fs::read(&object_path).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR);
+        let valid_name = (request.name.starts_with("refs/heads/") || request.name.starts_with("refs/tags/"))
+            && !request.name.contains("..")
+            && !request.name.ends_with('/');
+        if !valid_name {
+            return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid ref name: {}", request.name)));
+        }
+
+        let new = Hash::from_hex(&request.new)
+            .map_err(|_| api_error(StatusCode::BAD_REQUEST, format!("Invalid hash: {}", request.new)))?;
+
+        let store = ObjectStore::new(&dits_dir);
+        if !store.has_object(ObjectType::Commit, &new) {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                format!("Commit {} has not been uploaded", new.short()),
+            ));
+        }
+
+        // History already reachable from a ref is complete, so only the new part is walked
+        let git = GitTextEngine::open(&dits_dir).ok();
+        let missing = reachability::ancestors(&store, &ref_tips(&dits_dir))
+            .and_then(|known| reachability::first_missing_object(&store, git.as_ref(), &[new], &known))
+            .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if let Some(id) = missing {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                format!("{} {} has not been uploaded", id.kind.as_str(), id.id),
+            ));
+        }
+
+        let ref_path = dits_dir.join(&request.name);
+
+        let _guard = state.ref_lock.lock().await;
+
+        let current = match fs::read_to_string(&ref_path) {
+            Ok(content) => Some(content.trim().to_string()),
+            Err(_) => None,
+        };
+        let current_hash = current
+            .as_deref()
+            .map(Hash::from_hex)
+            .transpose()
+            .map_err(|_| api_error(StatusCode::INTERNAL_SERVER_ERROR, "Corrupt ref on server"))?;
+
+        if !request.force {
+            if current != request.old {
+                return Err(api_error(
+                    StatusCode::CONFLICT,
+                    format!("Stale ref {}: remote has moved since it was fetched", request.name),
+                ));
+            }
+
+            if let Some(current_hash) = &current_hash {
+                let fast_forward = reachability::is_ancestor(&store, current_hash, &new)
+                    .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+                if !fast_forward {
+                    return Err(api_error(
+                        StatusCode::CONFLICT,
+                        format!("Non-fast-forward update of {} rejected", request.name),
+                    ));
+                }
             }
         }
 
-        Err(StatusCode::NOT_FOUND)
+        // Locks hold even for forced pushes. The lock store stays locked until
+        // the ref is written, so no lock can be taken in between.
+        let _locks_guard = state.lock_store_lock.lock().await;
+        let violations = push_lock_violations(&dits_dir, current_hash, new, pusher.as_deref())
+            .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if !violations.is_empty() {
+            let reasons: Vec<String> = violations.iter().map(|v| v.to_string()).collect();
+            return Err(api_error(StatusCode::CONFLICT, format!("Push rejected: {}", reasons.join("; "))));
+        }
+
+        // Write to a temporary file and rename so readers never see a torn ref
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = ref_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let mut tmp_name = ref_path.file_name().unwrap_or_default().to_os_string();
+            tmp_name.push(".lock");
+            let tmp_path = ref_path.with_file_name(tmp_name);
+            fs::write(&tmp_path, format!("{}\n", new.to_hex()))?;
+            fs::rename(&tmp_path, &ref_path)
+        };
+        write().map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+        Ok(Json(RefUpdateResponse {
+            name: request.name,
+            old: current,
+            new: new.to_hex(),
+        }))
+    }
+
+    /// List unexpired locks
+    async fn list_locks(
+        Path(repo): Path<String>,
+        state: axum::extract::State<Arc<RepoServer>>,
+    ) -> Result<Json<Vec<RemoteLock>>, ApiError> {
+        let dits_dir = state.dits_dir(&repo)?;
+        let store = LockStore::new(&dits_dir);
+        let mut locks: Vec<RemoteLock> = store
+            .list()
+            .into_iter()
+            .filter(|l| !l.is_expired())
+            .map(RemoteLock::from)
+            .collect();
+        locks.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Json(locks))
+    }
+
+    /// Lock a file for the authenticated user. Re-locking a file the user
+    /// already holds renews it.
+    async fn acquire_lock(
+        Path(repo): Path<String>,
+        state: axum::extract::State<Arc<RepoServer>>,
+        headers: HeaderMap,
+        Json(request): Json<LockRequest>,
+    ) -> Result<(StatusCode, Json<RemoteLock>), ApiError> {
+        let user = state.authenticate(&headers)?;
+        let dits_dir = state.dits_dir(&repo)?;
+
+        let path = request.path.trim_matches('/');
+        if path.is_empty() || path.split('/').any(|c| c.is_empty() || c == "." || c == "..") {
+            return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid path: {}", request.path)));
+        }
+        if request.ttl_secs == Some(0) {
+            return Err(api_error(StatusCode::BAD_REQUEST, "ttl_secs must be positive"));
+        }
+
+        let _guard = state.lock_store_lock.lock().await;
+        let mut store = LockStore::new(&dits_dir);
+        let renew = store.get(path).is_some_and(|l| l.owner == user);
+        let lock = store
+            .acquire(path, &user, request.ttl_secs, request.reason.as_deref(), renew)
+            .map_err(lock_error)?;
+
+        Ok((StatusCode::CREATED, Json(RemoteLock::from(&lock))))
+    }
+
+    /// Release a lock. Only its owner may release it, unless an admin passes
+    /// `?force=true`.
+    async fn release_lock(
+        Path((repo, id)): Path<(String, String)>,
+        Query(query): Query<UnlockQuery>,
+        state: axum::extract::State<Arc<RepoServer>>,
+        headers: HeaderMap,
+    ) -> Result<StatusCode, ApiError> {
+        let user = state.authenticate(&headers)?;
+        let dits_dir = state.dits_dir(&repo)?;
+
+        if query.force && !state.admins.contains(&user) {
+            return Err(api_error(
+                StatusCode::FORBIDDEN,
+                format!("{} may not force-release locks", user),
+            ));
+        }
+
+        let _guard = state.lock_store_lock.lock().await;
+        let mut store = LockStore::new(&dits_dir);
+        let path = store
+            .get_by_id(&id)
+            .map(|l| l.path.clone())
+            .ok_or_else(|| api_error(StatusCode::NOT_FOUND, format!("Lock not found: {}", id)))?;
+        store.release(&path, &user, query.force).map_err(lock_error)?;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Read a tokens file: one `<user> <token>` pair per line, `#` for comments.
+pub fn load_tokens(path: &std::path::Path) -> std::io::Result<HashMap<String, String>> {
+    let content = fs::read_to_string(path)?;
+    let mut tokens = HashMap::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [user, token] => {
+                tokens.insert(token.to_string(), user.to_string());
+            }
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("{}:{}: expected `<user> <token>`", path.display(), number + 1),
+                ))
+            }
+        }
     }
+    Ok(tokens)
 }
 
 /// Start the repository server
-pub async fn start_server(base_dir: PathBuf, port: u16) -> anyhow::Result<()> {
-    let server = Arc::new(RepoServer::new(base_dir));
+///
+/// Without a tokens file, locks can be listed but not taken or released, and
+/// anyone who can reach the server may push.
+pub async fn start_server(
+    base_dir: PathBuf,
+    port: u16,
+    tokens: Option<PathBuf>,
+    admins: Vec<String>,
+) -> anyhow::Result<()> {
+    let mut server = RepoServer::new(base_dir).with_admins(admins.into_iter().collect());
+    if let Some(path) = tokens {
+        server = server.with_tokens(load_tokens(&path)?);
+    }
+    let server = Arc::new(server);
     let app = server.router();
 
     let addr = format!("0.0.0.0:{}", port);
@@ -127,4 +671,3 @@ pub async fn start_server(base_dir: PathBuf, port: u16) -> anyhow::Result<()> {
 
     Ok(())
 }
-
@@ -9,18 +9,20 @@
 //!
 //! Files are automatically classified by the `FileClassifier`.
 
-use crate::config::Config;
+use crate::config::{Config, LockEnforcement};
 use crate::core::{
-    chunk_data_with_refs, chunk_data_with_refs_parallel, Author, ChunkerConfig, Commit,
-    FileClassifier, FileMode, FileStatus, FileType, Hash, Hasher, Index, IndexEntry, IgnoreMatcher, Manifest,
-    ManifestEntry, Mp4Metadata, StorageStrategy, StoredAtom,
+    chunk_data_with_refs, chunk_data_with_refs_parallel, chunk_reader_with_refs,
+    AttributeMatcher, Author, Chunk, ChunkRef, ChunkerConfig, Commit, FileClassifier, FileMode,
+    FileCategory, FileStatus, FileType, Hash, Hasher, HashingReader, Index, IndexEntry, IndexFormat, IndexLock,
+    IgnoreMatcher, Manifest, ManifestEntry, Mp4Metadata, PathAttributes, StatData, StorageStrategy, StoredAtom,
 };
 use crate::mp4::{Deconstructor, Mp4Parser};
 use crate::security::KeyStore;
+use crate::store::locks::{self, LockStore, LockViolation};
+use crate::store::relink::{self, RelinkMapping, RelinkRecord, RelinkStore};
 use crate::store::{GitTextEngine, ObjectStore, RefStore};
-use bincode;
 use std::fs::{self, File};
-use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{self, BufReader, BufWriter, Read, Write};
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::sync::Mutex;
@@ -31,6 +33,13 @@ use walkdir::WalkDir;
 /// Below this threshold, sequential chunking is faster due to lower overhead.
 const PARALLEL_CHUNK_THRESHOLD: usize = 1024 * 1024;
 
+/// Bytes read from the start of a file to classify its storage strategy.
+const CLASSIFY_SAMPLE_SIZE: u64 = 8 * 1024;
+
+/// Text files larger than this are chunked like binaries (8 MB): the Git
+/// engine takes blobs whole, so they would have to fit in memory.
+const MAX_TEXT_SIZE: u64 = 8 * 1024 * 1024;
+
 /// Repository errors.
 #[derive(Debug, Error)]
 pub enum RepoError {
@@ -66,6 +75,9 @@ pub enum RepoError {
 
     #[error("Git engine error: {0}")]
     GitEngine(#[from] super::git_engine::GitEngineError),
+
+    #[error("Commit blocked by file locks: {}", .0.iter().map(|v| v.to_string()).collect::<Vec<_>>().join("; "))]
+    LockViolations(Vec<LockViolation>),
 }
 
 /// Cached index with metadata for performance optimization.
@@ -122,8 +134,7 @@ impl Repository {
 
         // Create empty index
         let index = Index::new();
-        let index_path = dits_dir.join("index");
-        fs::write(&index_path, index.to_json())?;
+        index.write_file(&dits_dir.join("index"))?;
 
         // Initialize ignore matcher
         let ignore = IgnoreMatcher::new(&work_dir);
@@ -140,8 +151,8 @@ impl Repository {
         // Phase 3.6: Initialize Git text engine
         let git_engine = GitTextEngine::init(&dits_dir).ok();
 
-        // Phase 3.6: Initialize file classifier
-        let file_classifier = FileClassifier::new();
+        // Phase 3.6: Initialize file classifier with .ditsattributes overrides
+        let file_classifier = FileClassifier::with_attributes(AttributeMatcher::new(&work_dir));
 
         Ok(Self {
             work_dir,
@@ -186,8 +197,8 @@ impl Repository {
             GitTextEngine::init(&dits_dir).ok()
         };
 
-        // Phase 3.6: Initialize file classifier
-        let file_classifier = FileClassifier::new();
+        // Phase 3.6: Initialize file classifier with .ditsattributes overrides
+        let file_classifier = FileClassifier::with_attributes(AttributeMatcher::new(&work_dir));
 
         // Check for encryption and create object store accordingly
         let mut objects = ObjectStore::new(&dits_dir);
@@ -394,7 +405,7 @@ impl Repository {
     }
 
     /// Find a commit by hash prefix.
-    fn find_commit_by_prefix(&self, prefix: &str) -> Result<Option<Hash>, RepoError> {
+    pub fn find_commit_by_prefix(&self, prefix: &str) -> Result<Option<Hash>, RepoError> {
         // Walk commit directory looking for matches
         let commits_dir = self.dits_dir.join("objects").join("commits");
         if !commits_dir.exists() {
@@ -442,7 +453,8 @@ impl Repository {
     // ========== Index Operations ==========
 
     /// Load the index with caching for performance optimization.
-    /// Supports both binary format (Phase 6+) and legacy JSON format for backwards compatibility.
+    /// Reads the binary index format; a legacy JSON index is migrated to the
+    /// binary format the first time it is opened.
     pub fn load_index(&self) -> Result<Index, RepoError> {
         let index_path = self.dits_dir.join("index");
 
@@ -475,44 +487,34 @@ impl Repository {
             return Ok(index);
         }
 
-        let data = fs::read(&index_path)?;
         let mtime = index_path.metadata()?.modified()?;
 
-        // Try binary format first (Phase 6+), fall back to JSON for backwards compatibility
-        let index = match bincode::deserialize::<Index>(&data) {
-            Ok(index) => index,
-            Err(bincode_err) => {
-                // Fall back to JSON deserialization for legacy indexes
-                match String::from_utf8(data.clone()) {
-                    Ok(json) => match Index::from_json(&json) {
-                        Ok(index) => index,
-                        Err(json_err) => {
-                            eprintln!("Warning: Index file appears to be corrupted (bincode error: {}, JSON error: {}). Creating new empty index.", bincode_err, json_err);
-                            // Backup the corrupted index file
-                            let backup_path = index_path.with_extension("index.corrupted");
-                            if let Err(e) = fs::rename(&index_path, &backup_path) {
-                                eprintln!("Warning: Could not backup corrupted index file: {}", e);
-                            } else {
-                                eprintln!("Corrupted index file backed up to: {}", backup_path.display());
-                            }
-                            Index::new()
-                        }
-                    },
-                    Err(utf8_err) => {
-                        eprintln!("Warning: Index file contains invalid UTF-8 data (bincode error: {}, UTF-8 error: {}). Creating new empty index.", bincode_err, utf8_err);
-                        // Backup the corrupted index file
-                        let backup_path = index_path.with_extension("index.corrupted");
-                        if let Err(e) = fs::rename(&index_path, &backup_path) {
-                            eprintln!("Warning: Could not backup corrupted index file: {}", e);
-                        } else {
-                            eprintln!("Corrupted index file backed up to: {}", backup_path.display());
-                        }
-                        Index::new()
-                    }
+        let mut index = match Index::read_file(&index_path) {
+            Ok((index, IndexFormat::Binary { .. })) => index,
+            Ok((mut index, IndexFormat::LegacyJson)) => {
+                // Rewrite in the binary format. The cache is filled by save_index.
+                // If the index is locked, its holder's save migrates it.
+                if let Ok(lock) = self.lock_index() {
+                    self.save_index(lock, &mut index)?;
+                    return self.load_index();
                 }
+                return Ok(index);
+            }
+            Err(e) => {
+                eprintln!("Warning: Index file appears to be corrupted ({}). Creating new empty index.", e);
+                // Backup the corrupted index file
+                let backup_path = index_path.with_extension("index.corrupted");
+                if let Err(e) = fs::rename(&index_path, &backup_path) {
+                    eprintln!("Warning: Could not backup corrupted index file: {}", e);
+                } else {
+                    eprintln!("Corrupted index file backed up to: {}", backup_path.display());
+                }
+                Index::new()
             }
         };
 
+        index.set_written_at(mtime);
+
         // Cache the loaded index
         if let Ok(mut cache_guard) = self.index_cache.lock() {
             *cache_guard = Some(CachedIndex {
@@ -524,20 +526,24 @@ impl Repository {
         Ok(index)
     }
 
-    fn save_index(&self, index: &Index) -> Result<(), RepoError> {
-        let index_path = self.dits_dir.join("index");
-        fs::write(&index_path, index.to_json())
-            .map_err(|e| RepoError::IndexError(e.to_string()))?;
+    /// Lock the index for an update, as git does with `index.lock`.
+    ///
+    /// Take the lock before [`Self::load_index`] and hand it to
+    /// [`Self::save_index`], so that a concurrent command fails instead of
+    /// one of the two updates being lost.
+    fn lock_index(&self) -> Result<IndexLock, RepoError> {
+        IndexLock::acquire(&self.dits_dir.join("index")).map_err(|e| RepoError::IndexError(e.to_string()))
+    }
 
-        // Update cache with new mtime
-        let mtime = index_path.metadata()?.modified()?;
+    /// Write the index through the lock taken before loading it.
+    fn save_index(&self, lock: IndexLock, index: &mut Index) -> Result<(), RepoError> {
+        let (_, mtime) = lock.commit(index).map_err(|e| RepoError::IndexError(e.to_string()))?;
         if let Ok(mut cache_guard) = self.index_cache.lock() {
             *cache_guard = Some(CachedIndex {
                 index: index.clone(),
                 mtime,
             });
         }
-
         Ok(())
     }
 
@@ -556,6 +562,7 @@ impl Repository {
             return Err(RepoError::FileIgnored(path.to_string()));
         }
 
+        let lock = self.lock_index()?;
         let mut index = self.load_index()?;
         let mut result = AddResult::default();
 
@@ -585,54 +592,127 @@ impl Repository {
             }
         }
 
-        self.save_index(&index)?;
+        self.save_index(lock, &mut index)?;
         Ok(result)
     }
 
     /// Add a single file to the index.
     ///
+    /// Files whose stat data matches their index entry are skipped without
+    /// being read.
+    fn add_file(
+        &self,
+        index: &mut Index,
+        rel_path: &str,
+        full_path: &Path,
+        result: &mut AddResult,
+    ) -> Result<(), RepoError> {
+        // Stat before reading: if the file changes while we hash it, the
+        // recorded stat will be stale and the next check rehashes.
+        let stat = StatData::from_metadata(&fs::metadata(full_path)?);
+        if index.is_stat_clean(rel_path, &stat) {
+            return Ok(());
+        }
+
+        self.add_file_contents(index, rel_path, full_path, result)?;
+
+        if let Some(entry) = index.entries.get_mut(rel_path) {
+            entry.stat = stat;
+        }
+        Ok(())
+    }
+
+    /// Hash and store a file's contents, staging it if they changed.
+    ///
     /// Phase 3.6: Routes files based on storage strategy:
     /// - GitText: Store via libgit2, line-based operations
     /// - DitsChunk: Store via FastCDC chunking
     /// - Hybrid: Both (for NLE projects)
-    fn add_file(
+    fn add_file_contents(
         &self,
         index: &mut Index,
         rel_path: &str,
         full_path: &Path,
         result: &mut AddResult,
     ) -> Result<(), RepoError> {
-        // Check if this is an MP4 file - use specialized handler
-        if Self::is_mp4_file(full_path) {
-            return self.add_mp4_file(index, rel_path, full_path, result);
+        // Relinked projects are staged with their original media paths
+        if let Some(record) = self.relinks().load(rel_path)? {
+            return self.add_relinked_file(index, rel_path, full_path, &record, result);
         }
 
-        let data = fs::read(full_path)?;
-        let content_hash = Hasher::hash(&data);
-
-        // Check if file has changed
-        if let Some(existing) = index.get(rel_path) {
-            if existing.content_hash == content_hash {
-                // File hasn't changed
-                return Ok(());
-            }
+        // Check if this is an MP4 file - use specialized handler, unless
+        // .ditsattributes picks the storage engine explicitly
+        let explicit_storage = self.attributes(rel_path).storage.is_some();
+        if !explicit_storage && Self::is_mp4_file(full_path) {
+            return self.add_mp4_file(index, rel_path, full_path, result);
         }
 
-        // Phase 3.6: Classify file to determine storage strategy
-        let strategy = self.file_classifier.classify(full_path, Some(&data));
+        // Phase 3.6: Classify file to determine storage strategy.
+        // Only a leading sample is read so large binaries never have to fit in memory.
+        let sample = read_sample(full_path)?;
+        let strategy = self.file_classifier.classify(full_path, Some(&sample));
 
         // Route to appropriate storage engine
         match strategy {
             StorageStrategy::GitText => {
+                let file = File::open(full_path)?;
+                if !explicit_storage && file.metadata()?.len() > MAX_TEXT_SIZE {
+                    return self.add_binary_stream(index, rel_path, full_path, file, result);
+                }
+                let mut reader = HashingReader::new(file);
+                let mut data = Vec::new();
+                reader.read_to_end(&mut data)?;
+                let (content_hash, _) = reader.finish();
+
+                // Check if file has changed
+                if let Some(existing) = index.get(rel_path) {
+                    if existing.content_hash == content_hash {
+                        // File hasn't changed
+                        return Ok(());
+                    }
+                }
+
                 self.add_text_file(index, rel_path, full_path, &data, content_hash, result)
             }
             StorageStrategy::DitsChunk => {
-                self.add_binary_file(index, rel_path, full_path, &data, content_hash, result)
+                self.add_binary_file(index, rel_path, full_path, result)
             }
             StorageStrategy::Hybrid => {
                 // For now, treat hybrid files as binary
                 // Full hybrid support will parse metadata vs payload
-                self.add_binary_file(index, rel_path, full_path, &data, content_hash, result)
+                self.add_binary_file(index, rel_path, full_path, result)
+            }
+        }
+    }
+
+    /// Stage a relinked project file with its relink undone.
+    fn add_relinked_file(
+        &self,
+        index: &mut Index,
+        rel_path: &str,
+        full_path: &Path,
+        record: &RelinkRecord,
+        result: &mut AddResult,
+    ) -> Result<(), RepoError> {
+        let data = fs::read(full_path)?;
+        let unchanged = Hasher::hash(&data) == record.relinked
+            && index.get(rel_path).is_some_and(|e| e.content_hash == record.canonical);
+        if unchanged {
+            return Ok(());
+        }
+
+        let data = relink::rewrite_project(full_path, &data, &record.reversed())?.unwrap_or(data);
+        let sample = &data[..data.len().min(CLASSIFY_SAMPLE_SIZE as usize)];
+        match self.file_classifier.classify(full_path, Some(sample)) {
+            StorageStrategy::GitText => {
+                let content_hash = Hasher::hash(&data);
+                if index.get(rel_path).is_some_and(|e| e.content_hash == content_hash) {
+                    return Ok(());
+                }
+                self.add_text_file(index, rel_path, full_path, &data, content_hash, result)
+            }
+            StorageStrategy::DitsChunk | StorageStrategy::Hybrid => {
+                self.add_binary_stream(index, rel_path, full_path, &data[..], result)
             }
         }
     }
@@ -688,10 +768,11 @@ impl Repository {
             Some(oid.to_string())
         } else {
             // Fallback: store as Dits chunk
+            let chunker = self.chunker_for(rel_path);
             let (chunks, chunk_refs) = if data.len() >= PARALLEL_CHUNK_THRESHOLD {
-                chunk_data_with_refs_parallel(data, &self.chunker_config)
+                chunk_data_with_refs_parallel(data, &chunker)
             } else {
-                chunk_data_with_refs(data, &self.chunker_config)
+                chunk_data_with_refs(data, &chunker)
             };
 
             for chunk in &chunks {
@@ -750,33 +831,42 @@ impl Repository {
     }
 
     /// Add a binary file using Dits CDC storage.
+    ///
+    /// The file is streamed through the chunker, so only a bounded window is
+    /// held in memory regardless of file size. The content hash is computed
+    /// in the same pass.
     fn add_binary_file(
         &self,
         index: &mut Index,
         rel_path: &str,
         full_path: &Path,
-        data: &[u8],
-        content_hash: Hash,
         result: &mut AddResult,
     ) -> Result<(), RepoError> {
-        // Chunk the file - use parallel chunking for large files
-        let (chunks, chunk_refs) = if data.len() >= PARALLEL_CHUNK_THRESHOLD {
-            chunk_data_with_refs_parallel(data, &self.chunker_config)
-        } else {
-            chunk_data_with_refs(data, &self.chunker_config)
-        };
+        self.add_binary_stream(index, rel_path, full_path, File::open(full_path)?, result)
+    }
 
-        // Store chunks (dedup happens here)
-        for chunk in &chunks {
-            let was_new = self.objects.store_chunk(chunk)?;
-            if was_new {
-                result.new_chunks += 1;
-                result.new_bytes += chunk.size() as u64;
-            } else {
-                result.dedup_chunks += 1;
-                result.dedup_bytes += chunk.size() as u64;
+    /// Add binary content read from `content`, taking file metadata from
+    /// `full_path`.
+    fn add_binary_stream<R: Read>(
+        &self,
+        index: &mut Index,
+        rel_path: &str,
+        full_path: &Path,
+        content: R,
+        result: &mut AddResult,
+    ) -> Result<(), RepoError> {
+        let mut reader = HashingReader::new(content);
+        let mut stats = AddResult::default();
+        let chunk_refs = self.store_stream(&mut reader, &self.chunker_for(rel_path), &mut stats)?;
+        let (content_hash, file_size) = reader.finish();
+
+        // Check if file has changed (its chunks were already present, so nothing new was written)
+        if let Some(existing) = index.get(rel_path) {
+            if existing.content_hash == content_hash {
+                return Ok(());
             }
         }
+        result.merge_chunk_stats(&stats);
 
         // Get file metadata
         let metadata = fs::metadata(full_path)?;
@@ -808,7 +898,7 @@ impl Repository {
         let mut entry = IndexEntry::new(
             rel_path.to_string(),
             content_hash,
-            data.len() as u64,
+            file_size,
             mtime,
             mode,
             file_type,
@@ -827,6 +917,26 @@ impl Repository {
         Ok(())
     }
 
+    /// Chunk a stream and store its chunks, recording dedup stats in `result`.
+    fn store_stream<R: Read>(
+        &self,
+        reader: R,
+        chunker: &ChunkerConfig,
+        result: &mut AddResult,
+    ) -> Result<Vec<ChunkRef>, RepoError> {
+        chunk_reader_with_refs(reader, chunker, |chunk: &Chunk| -> Result<(), RepoError> {
+            let was_new = self.objects.store_chunk(chunk)?;
+            if was_new {
+                result.new_chunks += 1;
+                result.new_bytes += chunk.size() as u64;
+            } else {
+                result.dedup_chunks += 1;
+                result.dedup_bytes += chunk.size() as u64;
+            }
+            Ok(())
+        })
+    }
+
     /// Check if a file is an ISO Base Media File Format (MP4/MOV family).
     /// These formats share the same atom-based structure and can use MP4-aware versioning.
     fn is_mp4_file(path: &Path) -> bool {
@@ -854,7 +964,7 @@ impl Repository {
             Ok(s) => s,
             Err(_) => {
                 // If parsing fails, fall back to regular file handling
-                return self.add_regular_file(index, rel_path, full_path, result);
+                return self.add_binary_file(index, rel_path, full_path, result);
             }
         };
 
@@ -863,14 +973,20 @@ impl Repository {
             Ok(d) => d,
             Err(_) => {
                 // Fall back to regular file handling
-                return self.add_regular_file(index, rel_path, full_path, result);
+                return self.add_binary_file(index, rel_path, full_path, result);
             }
         };
 
-        // Compute content hash of the full file for change detection
-        let data = fs::read(full_path)?;
-        let content_hash = Hasher::hash(&data);
-        let actual_file_size = data.len() as u64;
+        // Single streaming pass: hash the whole file for change detection while
+        // chunking only the mdat payload. Chunk offsets are relative to mdat data.
+        let mut reader = HashingReader::new(BufReader::new(File::open(full_path)?));
+        io::copy(&mut (&mut reader).take(deconstructed.mdat_data_offset), &mut io::sink())?;
+        let mut mdat_stats = AddResult::default();
+        let chunker = self.chunker_for(rel_path);
+        let mdat = (&mut reader).take(deconstructed.mdat_data_size);
+        let chunk_refs = self.store_stream(mdat, &chunker, &mut mdat_stats)?;
+        io::copy(&mut reader, &mut io::sink())?;
+        let (content_hash, actual_file_size) = reader.finish();
 
         // Check if file has changed
         if let Some(existing) = index.get(rel_path) {
@@ -878,6 +994,7 @@ impl Repository {
                 return Ok(());
             }
         }
+        result.merge_chunk_stats(&mdat_stats);
 
         // Store ftyp atom
         let (ftyp_hash, ftyp_new) = self.objects.store_blob(&deconstructed.ftyp_data)?;
@@ -926,31 +1043,6 @@ impl Repository {
             .map(|a| a.atom_type.as_fourcc().to_string())
             .collect();
 
-        // Read and chunk only the mdat data
-        let mut file = File::open(full_path)?;
-        file.seek(SeekFrom::Start(deconstructed.mdat_data_offset))?;
-        let mut mdat_data = vec![0u8; deconstructed.mdat_data_size as usize];
-        file.read_exact(&mut mdat_data)?;
-
-        // Chunk the mdat data - use parallel chunking for large files
-        let (chunks, chunk_refs) = if mdat_data.len() >= PARALLEL_CHUNK_THRESHOLD {
-            chunk_data_with_refs_parallel(&mdat_data, &self.chunker_config)
-        } else {
-            chunk_data_with_refs(&mdat_data, &self.chunker_config)
-        };
-
-        // Store mdat chunks
-        for chunk in &chunks {
-            let was_new = self.objects.store_chunk(chunk)?;
-            if was_new {
-                result.new_chunks += 1;
-                result.new_bytes += chunk.size() as u64;
-            } else {
-                result.dedup_chunks += 1;
-                result.dedup_bytes += chunk.size() as u64;
-            }
-        }
-
         // Calculate the reconstructed file size for MP4
         // Structure: all atoms in original order
         let other_atoms_size: u64 = deconstructed.other_atoms.iter()
@@ -1036,100 +1128,26 @@ impl Repository {
         Ok(())
     }
 
-    /// Add a regular (non-MP4) file to the index.
-    fn add_regular_file(
-        &self,
-        index: &mut Index,
-        rel_path: &str,
-        full_path: &Path,
-        result: &mut AddResult,
-    ) -> Result<(), RepoError> {
-        let data = fs::read(full_path)?;
-        let content_hash = Hasher::hash(&data);
-
-        // Check if file has changed
-        if let Some(existing) = index.get(rel_path) {
-            if existing.content_hash == content_hash {
-                return Ok(());
-            }
-        }
-
-        // Chunk the file - use parallel chunking for large files
-        let (chunks, chunk_refs) = if data.len() >= PARALLEL_CHUNK_THRESHOLD {
-            chunk_data_with_refs_parallel(&data, &self.chunker_config)
-        } else {
-            chunk_data_with_refs(&data, &self.chunker_config)
-        };
-
-        // Store chunks
-        for chunk in &chunks {
-            let was_new = self.objects.store_chunk(chunk)?;
-            if was_new {
-                result.new_chunks += 1;
-                result.new_bytes += chunk.size() as u64;
-            } else {
-                result.dedup_chunks += 1;
-                result.dedup_bytes += chunk.size() as u64;
-            }
-        }
-
-        // Get file metadata
-        let metadata = fs::metadata(full_path)?;
-        let mtime = metadata
-            .modified()
-            .map(|t| {
-                t.duration_since(std::time::UNIX_EPOCH)
-                    .unwrap_or_default()
-                    .as_secs() as i64
-            })
-            .unwrap_or(0);
-
-        // Get file mode and type
-        let mode = metadata.permissions().mode();
-        let file_type = if metadata.is_dir() {
-            FileType::Directory
-        } else if metadata.file_type().is_symlink() {
-            FileType::Symlink
-        } else {
-            FileType::Regular
-        };
-        let symlink_target = if file_type == FileType::Symlink {
-            fs::read_link(full_path)?
-                .to_string_lossy()
-                .to_string()
-        } else {
-            String::new()
-        };
-
-        // Create index entry
-        let mut entry = IndexEntry::new(
-            rel_path.to_string(),
-            content_hash,
-            data.len() as u64,
-            mtime,
-            mode,
-            file_type,
-            symlink_target,
-            chunk_refs,
-        );
-        entry.status = if index.is_staged(rel_path) {
-            FileStatus::Modified
-        } else {
-            FileStatus::Added
-        };
-
-        index.stage(entry);
-        result.files_staged += 1;
-
-        Ok(())
-    }
-
     // ========== Status Operations ==========
 
     /// Get repository status.
     pub fn status(&self) -> Result<Status, RepoError> {
-        let index = self.load_index()?;
+        self.status_with_refresh(false)
+    }
+
+    /// Get repository status.
+    ///
+    /// Tracked files whose stat data matches the index are not rehashed
+    /// unless `refresh` is set. Files found clean by hashing have their stat
+    /// data recorded so later calls can skip them.
+    pub fn status_with_refresh(&self, refresh: bool) -> Result<Status, RepoError> {
+        // Best effort: status must still work in a read-only repository or
+        // while another command holds the lock; it then records nothing
+        let lock = self.lock_index().ok();
+        let mut index = self.load_index()?;
         let head_manifest = self.get_head_manifest()?;
+        let mut refreshed: Vec<(String, StatData)> = Vec::new();
+        let relinks = self.relinks().list()?;
 
         let mut status = Status::default();
         status.branch = self.refs.current_branch()?;
@@ -1251,11 +1269,27 @@ impl Repository {
                     };
 
                     if let Some(manifest_entry) = manifest.get(rel_path) {
-                        let data = fs::read(full_path)?;
-                        let hash = Hasher::hash(&data);
-
-                        // Check for content changes
-                        let content_changed = manifest_entry.content_hash != hash;
+                        // Check for content changes, trusting stat data when it is not racy
+                        let stat = StatData::from_metadata(&metadata);
+                        let index_entry = index.get(rel_path);
+                        let stat_clean = !refresh
+                            && index_entry.map(|e| e.content_hash == manifest_entry.content_hash).unwrap_or(false)
+                            && index.is_stat_clean(rel_path, &stat);
+
+                        let content_changed = if stat_clean {
+                            false
+                        } else {
+                            let hash = Hasher::hash_file(full_path)?;
+                            // A relinked project is unchanged if only its relink differs
+                            let hash = relinks
+                                .iter()
+                                .find(|r| r.path == *rel_path && r.relinked == hash)
+                                .map_or(hash, |r| r.canonical);
+                            if index_entry.map(|e| e.content_hash == hash && e.stat != stat).unwrap_or(false) {
+                                refreshed.push((rel_path.clone(), stat));
+                            }
+                            manifest_entry.content_hash != hash
+                        };
 
                         // Check for type changes (only for files that exist in both)
                         let type_changed = !matches!(manifest_entry.mp4_metadata, Some(_)) &&
@@ -1268,7 +1302,8 @@ impl Repository {
                             FileMode::Symlink => 0o777, // symlinks typically have this mode
                             FileMode::Regular => 0o644,
                         };
-                        let mode_changed = manifest_mode_u32 != (current_mode & 0o777);
+                        // Write bits are ignored: read-only lockable files are not changes
+                        let mode_changed = (manifest_mode_u32 & 0o555) != (current_mode & 0o555);
 
                         // Prioritize change types: content > type > mode
                         if content_changed {
@@ -1306,8 +1341,7 @@ impl Repository {
             for (old_path, old_hash) in &missing_from_working {
                 // Look for an untracked file with the same content hash
                 for (new_path, new_full_path) in &potential_renames {
-                    if let Ok(data) = fs::read(new_full_path) {
-                        let new_hash = Hasher::hash(&data);
+                    if let Ok(new_hash) = Hasher::hash_file(new_full_path) {
                         if new_hash == *old_hash {
                             // Found an unstaged rename!
                             status.unstaged_renamed.push((old_path.clone(), new_path.clone()));
@@ -1327,6 +1361,33 @@ impl Repository {
             }
         }
 
+        if !refreshed.is_empty() {
+            for (path, stat) in refreshed {
+                if let Some(entry) = index.entries.get_mut(&path) {
+                    entry.stat = stat;
+                }
+            }
+            if let Some(lock) = lock {
+                let _ = self.save_index(lock, &mut index);
+            }
+        }
+
+        if self.config.locks.read_only {
+            let store = LockStore::new(&self.dits_dir);
+            let user = locks::current_user();
+            let mut unlocked: Vec<String> = status
+                .modified
+                .iter()
+                .chain(&status.staged_modified)
+                .filter(|p| self.is_lockable(p))
+                .filter(|p| !store.get(p).is_some_and(|l| l.is_mine(user.as_deref())))
+                .cloned()
+                .collect();
+            unlocked.sort();
+            unlocked.dedup();
+            status.modified_without_lock = unlocked;
+        }
+
         Ok(status)
     }
 
@@ -1341,16 +1402,257 @@ impl Repository {
         }
     }
 
+    // ========== Lock Checks ==========
+
+    /// How commits treat changes that break the locking rules.
+    pub fn lock_enforcement(&self) -> LockEnforcement {
+        self.config.locks.enforce
+    }
+
+    /// Staged changes that break the locking rules: paths locked by someone
+    /// else, and lockable file types nobody here has locked.
+    ///
+    /// Checked against the local lock cache; run `dits locks --remote` to
+    /// refresh it. The remote re-checks on push.
+    pub fn lock_violations(&self) -> Result<Vec<LockViolation>, RepoError> {
+        let index = self.load_index()?;
+        self.index_lock_violations(&index)
+    }
+
+    fn index_lock_violations(&self, index: &Index) -> Result<Vec<LockViolation>, RepoError> {
+        let changed = self.index_changes(index)?;
+        Ok(self.path_lock_violations(changed.iter().map(String::as_str)))
+    }
+
+    /// Locking rules that changing `paths` would break, as for
+    /// [`Self::lock_violations`].
+    pub fn path_lock_violations<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Vec<LockViolation> {
+        let store = LockStore::new(&self.dits_dir);
+        let user = locks::current_user();
+        let is_mine = |lock: &locks::Lock| lock.is_mine(user.as_deref());
+        store.violations(paths, is_mine, |path| self.is_lockable(path))
+    }
+
+    /// Paths whose staged content differs from HEAD, sorted. Empty if nothing
+    /// has been staged.
+    pub fn staged_paths(&self) -> Result<Vec<String>, RepoError> {
+        let index = self.load_index()?;
+        if index.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.index_changes(&index)
+    }
+
+    /// Paths `index` adds, changes or removes relative to HEAD, sorted.
+    fn index_changes(&self, index: &Index) -> Result<Vec<String>, RepoError> {
+        let head = self.get_head_manifest()?;
+        let mut changed: Vec<String> = index
+            .entries
+            .iter()
+            .filter(|(path, entry)| {
+                head.as_ref()
+                    .and_then(|m| m.get(path))
+                    .is_none_or(|e| e.content_hash != entry.content_hash)
+            })
+            .map(|(path, _)| path.clone())
+            .collect();
+        if let Some(head) = &head {
+            changed.extend(head.paths().filter(|p| !index.entries.contains_key(*p)).map(String::from));
+        }
+        changed.sort_unstable();
+        Ok(changed)
+    }
+
+    /// Attributes from `.ditsattributes` for a repository-relative path.
+    pub fn attributes(&self, path: &str) -> PathAttributes {
+        self.file_classifier.attributes().get_str(path)
+    }
+
+    /// Whether a path must be locked before it is changed: the `lockable`
+    /// attribute if set, otherwise its file category.
+    pub fn is_lockable(&self, path: &str) -> bool {
+        self.attributes(path)
+            .lockable
+            .unwrap_or_else(|| FileCategory::from_path(Path::new(path)).needs_locking())
+    }
+
+    /// Chunker for a path: its `chunker` attribute, else the configured one.
+    fn chunker_for(&self, path: &str) -> ChunkerConfig {
+        match self.attributes(path).chunker {
+            Some(profile) => profile.config(),
+            None => self.chunker_config.clone(),
+        }
+    }
+
+    /// Whether lockable files stay read-only until locked (`locks.read_only`).
+    pub fn read_only_until_locked(&self) -> bool {
+        self.config.locks.read_only
+    }
+
+    /// Make the given lockable files writable if we hold their lock and
+    /// read-only otherwise. Other paths are left alone, as is everything
+    /// unless `locks.read_only` is set.
+    ///
+    /// Returns the number of files whose permissions changed.
+    pub fn update_lock_permissions<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a str>,
+    ) -> Result<usize, RepoError> {
+        if !self.config.locks.read_only {
+            return Ok(0);
+        }
+
+        let store = LockStore::new(&self.dits_dir);
+        let user = locks::current_user();
+        let mut changed = 0;
+        for path in paths {
+            if !self.is_lockable(path) {
+                continue;
+            }
+            let writable = store.get(path).is_some_and(|l| l.is_mine(user.as_deref()));
+            if set_writable(&self.work_dir.join(path), writable)? {
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// [`Self::update_lock_permissions`] for every tracked file.
+    pub fn refresh_lock_permissions(&self) -> Result<usize, RepoError> {
+        let index = self.load_index()?;
+        self.update_lock_permissions(index.entries.keys().map(String::as_str))
+    }
+
+    // ========== Relink Operations ==========
+
+    /// Relink records of this working tree.
+    pub fn relinks(&self) -> RelinkStore {
+        RelinkStore::new(&self.dits_dir)
+    }
+
+    /// Whether project media is relinked after checkout
+    /// (`dependencies.relink_on_checkout`).
+    pub fn relink_on_checkout(&self) -> bool {
+        self.config.dependencies.relink_on_checkout
+    }
+
+    /// Rewrite media paths in a project file and record the rewrite, so
+    /// that staging the file undoes it.
+    ///
+    /// Returns the record, or `None` if no mapping matched a reference.
+    pub fn relink(&self, path: &str, mappings: Vec<RelinkMapping>) -> Result<Option<RelinkRecord>, RepoError> {
+        let full_path = self.work_dir.join(path);
+        let data = fs::read(&full_path)?;
+        let Some(relinked) = relink::rewrite_project(&full_path, &data, &mappings)? else {
+            return Ok(None);
+        };
+
+        let hash = Hasher::hash(&data);
+        let mut record = match self.relinks().load(path)? {
+            // Relinked again: the canonical version is still the one before the first relink
+            Some(mut record) => {
+                if record.relinked != hash {
+                    let canonical = relink::rewrite_project(&full_path, &data, &record.reversed())?;
+                    record.canonical = canonical.map_or(hash, |c| Hasher::hash(&c));
+                }
+                record
+            }
+            None => RelinkRecord {
+                path: path.to_string(),
+                canonical: hash,
+                relinked: hash,
+                mappings: Vec::new(),
+            },
+        };
+        for mapping in mappings {
+            if !record.mappings.iter().any(|m| m.original == mapping.original) {
+                record.mappings.push(mapping);
+            }
+        }
+        record.relinked = Hasher::hash(&relinked);
+
+        self.write_project(path, &relinked)?;
+        self.relinks().save(&record)?;
+        self.refresh_project_stat(path, &record.canonical)?;
+        Ok(Some(record))
+    }
+
+    /// Undo the relink of a project file, restoring its committed media
+    /// paths. Returns whether the file was relinked.
+    pub fn undo_relink(&self, path: &str) -> Result<bool, RepoError> {
+        let Some(record) = self.relinks().load(path)? else {
+            return Ok(false);
+        };
+        let full_path = self.work_dir.join(path);
+        let data = fs::read(&full_path)?;
+
+        // Unchanged since relinking: restore the committed bytes exactly
+        let committed = match self.get_head_manifest()? {
+            Some(manifest) if Hasher::hash(&data) == record.relinked => manifest
+                .get(path)
+                .filter(|e| e.content_hash == record.canonical && e.mp4_metadata.is_none())
+                .map(|e| self.read_entry(e))
+                .transpose()?,
+            _ => None,
+        };
+        let restored = match committed {
+            Some(committed) => committed,
+            None => relink::rewrite_project(&full_path, &data, &record.reversed())?.unwrap_or(data),
+        };
+
+        self.write_project(path, &restored)?;
+        self.relinks().remove(path)?;
+        self.refresh_project_stat(path, &Hasher::hash(&restored))?;
+        Ok(true)
+    }
+
+    /// Replace a project file's content, keeping it read-only if it was.
+    fn write_project(&self, path: &str, data: &[u8]) -> Result<(), RepoError> {
+        let full_path = self.work_dir.join(path);
+        let made_writable = set_writable(&full_path, true)?;
+        fs::write(&full_path, data)?;
+        if made_writable {
+            set_writable(&full_path, false)?;
+        }
+        Ok(())
+    }
+
+    /// Record the stat data of a rewritten project whose index entry has
+    /// `content_hash`, so status does not rehash it.
+    fn refresh_project_stat(&self, path: &str, content_hash: &Hash) -> Result<(), RepoError> {
+        let lock = self.lock_index()?;
+        let mut index = self.load_index()?;
+        let Some(entry) = index.entries.get_mut(path) else {
+            return Ok(());
+        };
+        if entry.content_hash != *content_hash {
+            return Ok(());
+        }
+        entry.stat = StatData::from_metadata(&fs::metadata(self.work_dir.join(path))?);
+        self.save_index(lock, &mut index)
+    }
+
     // ========== Commit Operations ==========
 
     /// Create a commit from staged changes.
+    ///
+    /// With `locks.enforce = "require"`, fails with
+    /// [`RepoError::LockViolations`] if [`Self::lock_violations`] finds any.
     pub fn commit(&self, message: &str) -> Result<Commit, RepoError> {
-        let index = self.load_index()?;
+        let lock = self.lock_index()?;
+        let mut index = self.load_index()?;
 
         if index.is_empty() {
             return Err(RepoError::NothingToCommit);
         }
 
+        if self.config.locks.enforce == LockEnforcement::Require {
+            let violations = self.index_lock_violations(&index)?;
+            if !violations.is_empty() {
+                return Err(RepoError::LockViolations(violations));
+            }
+        }
+
         // Build manifest from index
         let mut manifest = Manifest::new();
         for (path, entry) in &index.entries {
@@ -1436,13 +1738,11 @@ impl Repository {
         }
 
         // Update index base commit
-        let mut new_index = Index::from_commit(commit.hash);
-        for (_path, entry) in index.entries {
-            let mut new_entry = entry;
-            new_entry.status = FileStatus::Unchanged;
-            new_index.stage(new_entry);
+        index.base_commit = Some(commit.hash);
+        for entry in index.entries.values_mut() {
+            entry.status = FileStatus::Unchanged;
         }
-        self.save_index(&new_index)?;
+        self.save_index(lock, &mut index)?;
 
         Ok(commit)
     }
@@ -1451,6 +1751,7 @@ impl Repository {
 
     /// Checkout a commit, restoring all files.
     pub fn checkout(&self, hash: &Hash) -> Result<CheckoutResult, RepoError> {
+        let lock = self.lock_index()?;
         // Capture the current HEAD manifest (if any) so we can remove files that no longer exist
         // in the target commit (branch switches should not leave tracked leftovers behind).
         let previous_manifest = match self.head()? {
@@ -1463,8 +1764,12 @@ impl Repository {
 
         let commit = self.objects.load_commit(hash)?;
         let manifest = self.objects.load_manifest(&commit.manifest)?;
+        // Refuse before touching the work tree if any path could escape it
+        ObjectStore::check_manifest_paths(&manifest)?;
 
         let mut result = CheckoutResult::default();
+        let relinks = self.relinks().list()?;
+        let mut removed = Vec::new();
 
         // Remove files that were tracked in the previous commit but do not exist in the target.
         if let Some(prev_manifest) = previous_manifest {
@@ -1488,13 +1793,20 @@ impl Repository {
                         .unwrap_or(false),
                     _ => fs::read(&full_old_path)
                         .ok()
-                        .map(|data| Hasher::hash(&data) == old_entry.content_hash)
+                        .map(|data| {
+                            // A relinked project only differs from its commit by the relink
+                            let hash = Hasher::hash(&data);
+                            hash == old_entry.content_hash
+                                || relinks.iter().any(|r| r.path == *old_path && r.relinked == hash)
+                        })
                         .unwrap_or(false),
                 };
 
                 if should_remove {
                     // If this was a file or symlink, remove it. (We don't expect directories in manifests.)
-                    let _ = fs::remove_file(&full_old_path);
+                    if fs::remove_file(&full_old_path).is_ok() {
+                        removed.push(old_path.clone());
+                    }
                 }
             }
         }
@@ -1507,6 +1819,11 @@ impl Repository {
                 fs::create_dir_all(parent)?;
             }
 
+            // A lockable file left read-only must be writable to be replaced
+            if self.config.locks.read_only {
+                set_writable(&full_path, true)?;
+            }
+
             // Check if this is an MP4 file
             if let Some(ref mp4_meta) = entry.mp4_metadata {
                 self.checkout_mp4_file(&full_path, entry, mp4_meta, &mut result)?;
@@ -1515,74 +1832,166 @@ impl Repository {
             }
         }
 
+        // Before the index records file stats, as chmod touches ctime
+        self.update_lock_permissions(manifest.paths())?;
+
+        // Relinked projects that were replaced or removed are no longer
+        // relinked; ones left in place keep their record
+        for record in &relinks {
+            if manifest.contains(&record.path) || removed.contains(&record.path) {
+                self.relinks().remove(&record.path)?;
+            }
+        }
+
         // Update HEAD
         self.refs.set_head_detached(hash)?;
 
-        // Update index
-        let mut index = Index::from_commit(*hash);
+        // Update index. Rebuilt from the loaded one, so that entries the
+        // checkout left alone are not rewritten.
+        let mut index = self.load_index()?;
+        index.clear();
+        index.base_commit = Some(*hash);
         for (path, entry) in manifest.iter() {
-            let full_path = self.work_dir.join(path);
+            index.stage(self.checked_out_entry(path, entry));
+        }
+        self.save_index(lock, &mut index)?;
 
-            // Get file metadata if possible
-            let (mode, file_type, symlink_target) = if full_path.exists() {
-                if let Ok(metadata) = fs::metadata(&full_path) {
-                    let mode = metadata.permissions().mode();
-                    let file_type = if metadata.is_dir() {
-                        FileType::Directory
-                    } else if metadata.is_symlink() {
-                        FileType::Symlink
-                    } else {
-                        FileType::Regular
-                    };
-                    let symlink_target = if file_type == FileType::Symlink {
-                        fs::read_link(&full_path)
-                            .map(|p| p.to_string_lossy().to_string())
-                            .unwrap_or_default()
-                    } else {
-                        String::new()
-                    };
-                    (mode, file_type, symlink_target)
+        Ok(result)
+    }
+
+    /// Index entry for a file just written from a manifest entry: tracked,
+    /// unchanged, and with stat data that can be trusted.
+    fn checked_out_entry(&self, path: &str, entry: &ManifestEntry) -> IndexEntry {
+        let full_path = self.work_dir.join(path);
+
+        // Get file metadata if possible
+        let (mode, file_type, symlink_target) = if full_path.exists() {
+            if let Ok(metadata) = fs::metadata(&full_path) {
+                let mode = metadata.permissions().mode();
+                let file_type = if metadata.is_dir() {
+                    FileType::Directory
+                } else if metadata.is_symlink() {
+                    FileType::Symlink
                 } else {
-                    (0o644, FileType::Regular, String::new())
-                }
+                    FileType::Regular
+                };
+                let symlink_target = if file_type == FileType::Symlink {
+                    fs::read_link(&full_path)
+                        .map(|p| p.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                } else {
+                    String::new()
+                };
+                (mode, file_type, symlink_target)
             } else {
                 (0o644, FileType::Regular, String::new())
-            };
+            }
+        } else {
+            (0o644, FileType::Regular, String::new())
+        };
 
-            // Recreate index entries from the manifest. These are tracked files, so mark them
-            // unchanged (not staged), and preserve storage strategy metadata.
-            let mut idx_entry = if let Some(ref mp4_meta) = entry.mp4_metadata {
-                IndexEntry::new_mp4(
-                    path.clone(),
-                    entry.content_hash,
-                    entry.size,
-                    0,
-                    mode,
-                    file_type,
-                    symlink_target,
-                    entry.chunks.clone(),
-                    mp4_meta.clone(),
-                )
-            } else {
-                IndexEntry::new_with_strategy(
-                    path.clone(),
-                    entry.content_hash,
-                    entry.size,
-                    0,
-                    mode,
-                    file_type,
-                    symlink_target,
-                    entry.chunks.clone(),
-                    entry.storage,
-                    entry.git_oid.clone(),
-                )
-            };
-            idx_entry.status = FileStatus::Unchanged;
-            index.stage(idx_entry);
+        // Recreate index entries from the manifest. These are tracked files, so mark them
+        // unchanged (not staged), and preserve storage strategy metadata.
+        let mut idx_entry = if let Some(ref mp4_meta) = entry.mp4_metadata {
+            IndexEntry::new_mp4(
+                path.to_string(),
+                entry.content_hash,
+                entry.size,
+                0,
+                mode,
+                file_type,
+                symlink_target,
+                entry.chunks.clone(),
+                mp4_meta.clone(),
+            )
+        } else {
+            IndexEntry::new_with_strategy(
+                path.to_string(),
+                entry.content_hash,
+                entry.size,
+                0,
+                mode,
+                file_type,
+                symlink_target,
+                entry.chunks.clone(),
+                entry.storage,
+                entry.git_oid.clone(),
+            )
+        };
+        idx_entry.status = FileStatus::Unchanged;
+        // The file was just written from this entry, so its stat can be trusted
+        if let Ok(metadata) = fs::metadata(&full_path) {
+            idx_entry.stat = StatData::from_metadata(&metadata);
+        }
+        idx_entry
+    }
+
+    /// Bring the working tree and index up to `commit` for `paths` only.
+    ///
+    /// For commits made outside the index, such as from a writable mount,
+    /// that advance the checked-out branch. They change nothing but `paths`,
+    /// so every other index entry still matches the new HEAD.
+    pub fn update_paths(&self, commit: &Hash, manifest: &Manifest, paths: &[String]) -> Result<(), RepoError> {
+        let lock = self.lock_index()?;
+        let mut index = self.load_index()?;
+        let mut result = CheckoutResult::default();
+
+        for path in paths {
+            let full_path = self.work_dir.join(path);
+            match manifest.get(path) {
+                Some(entry) => {
+                    if let Some(parent) = full_path.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    if self.config.locks.read_only {
+                        set_writable(&full_path, true)?;
+                    }
+                    match entry.mp4_metadata {
+                        Some(ref mp4_meta) => self.checkout_mp4_file(&full_path, entry, mp4_meta, &mut result)?,
+                        None => self.checkout_regular_file(&full_path, entry, &mut result)?,
+                    }
+                }
+                None => {
+                    if full_path.exists() {
+                        fs::remove_file(&full_path)?;
+                    }
+                    index.unstage(path);
+                }
+            }
         }
-        self.save_index(&index)?;
 
-        Ok(result)
+        // Before the index records file stats, as chmod touches ctime
+        self.update_lock_permissions(paths.iter().map(String::as_str).filter(|p| manifest.contains(p)))?;
+
+        for path in paths {
+            if let Some(entry) = manifest.get(path) {
+                index.stage(self.checked_out_entry(path, entry));
+            }
+        }
+        index.base_commit = Some(*commit);
+        self.save_index(lock, &mut index)
+    }
+
+    /// Paths among `paths` whose working-tree file holds changes the index
+    /// does not know about: untracked files and edits that were not staged.
+    pub fn unstaged_paths<'a>(&self, paths: impl IntoIterator<Item = &'a str>) -> Result<Vec<String>, RepoError> {
+        let index = self.load_index()?;
+        let mut unstaged = Vec::new();
+        for path in paths {
+            let full_path = self.work_dir.join(path);
+            let Ok(metadata) = fs::symlink_metadata(&full_path) else {
+                continue;
+            };
+            let changed = match index.get(path) {
+                None => true,
+                Some(_) if index.is_stat_clean(path, &StatData::from_metadata(&metadata)) => false,
+                Some(entry) => Hasher::hash_file(&full_path)? != entry.content_hash,
+            };
+            if changed {
+                unstaged.push(path.to_string());
+            }
+        }
+        Ok(unstaged)
     }
 
     /// Checkout a branch.
@@ -1762,16 +2171,22 @@ impl Repository {
         entry: &ManifestEntry,
         result: &mut CheckoutResult,
     ) -> Result<(), RepoError> {
+        let data = self.read_entry(entry)?;
+        fs::write(full_path, &data)?;
+        result.files_restored += 1;
+        result.bytes_restored += entry.size;
+
+        Ok(())
+    }
+
+    /// Read the content of a (non-MP4) manifest entry.
+    pub fn read_entry(&self, entry: &ManifestEntry) -> Result<Vec<u8>, RepoError> {
         // Phase 3.6: Check storage strategy
         if entry.is_git_text() {
             // Load from Git object store
             if let (Some(ref git_oid), Some(ref engine)) = (&entry.git_oid, &self.git_engine) {
                 let oid = GitTextEngine::parse_oid(git_oid)?;
-                let data = engine.read_blob(oid)?;
-                fs::write(full_path, &data)?;
-                result.files_restored += 1;
-                result.bytes_restored += entry.size;
-                return Ok(());
+                return Ok(engine.read_blob(oid)?);
             }
             // Fall through to chunk-based restore if Git engine not available
         }
@@ -1782,12 +2197,30 @@ impl Repository {
             let chunk = self.objects.load_chunk(&chunk_ref.hash)?;
             data.extend_from_slice(&chunk.data);
         }
+        Ok(data)
+    }
 
-        fs::write(full_path, &data)?;
-        result.files_restored += 1;
-        result.bytes_restored += entry.size;
-
-        Ok(())
+    /// Store new content for a tracked path, e.g. the result of a merge.
+    ///
+    /// The returned entry keeps `template`'s path, mode and storage strategy.
+    pub fn store_entry(&self, template: &ManifestEntry, data: &[u8]) -> Result<ManifestEntry, RepoError> {
+        let mut entry = template.clone();
+        entry.size = data.len() as u64;
+        entry.content_hash = Hasher::hash(data);
+        entry.mp4_metadata = None;
+        entry.chunks = Vec::new();
+        entry.git_oid = None;
+
+        match &self.git_engine {
+            Some(engine) if template.is_git_text() => {
+                entry.git_oid = Some(engine.store_blob(data)?.to_string());
+            }
+            _ => {
+                let mut stats = AddResult::default();
+                entry.chunks = self.store_stream(data, &self.chunker_for(&template.path), &mut stats)?;
+            }
+        }
+        Ok(entry)
     }
 
     // ========== Branch Operations ==========
@@ -2020,6 +2453,31 @@ impl Repository {
     }
 }
 
+/// Read up to `CLASSIFY_SAMPLE_SIZE` bytes from the start of a file.
+fn read_sample(path: &Path) -> io::Result<Vec<u8>> {
+    let mut sample = Vec::new();
+    File::open(path)?.take(CLASSIFY_SAMPLE_SIZE).read_to_end(&mut sample)?;
+    Ok(sample)
+}
+
+/// Add or clear the write bits of a file, returning whether they changed.
+/// Missing files and symlinks are skipped.
+fn set_writable(path: &Path, writable: bool) -> io::Result<bool> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(m) if m.is_file() => m,
+        Ok(_) => return Ok(false),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    let mode = metadata.permissions().mode();
+    let new_mode = if writable { mode | 0o200 } else { mode & !0o222 };
+    if new_mode == mode {
+        return Ok(false);
+    }
+    fs::set_permissions(path, fs::Permissions::from_mode(new_mode))?;
+    Ok(true)
+}
+
 /// Result of an add operation.
 #[derive(Debug, Default)]
 pub struct AddResult {
@@ -2032,6 +2490,14 @@ pub struct AddResult {
 }
 
 impl AddResult {
+    /// Add chunk counters from another result.
+    fn merge_chunk_stats(&mut self, other: &AddResult) {
+        self.new_chunks += other.new_chunks;
+        self.new_bytes += other.new_bytes;
+        self.dedup_chunks += other.dedup_chunks;
+        self.dedup_bytes += other.dedup_bytes;
+    }
+
     /// Calculate dedup ratio.
     pub fn dedup_ratio(&self) -> f64 {
         let total = self.new_bytes + self.dedup_bytes;
@@ -2056,6 +2522,8 @@ pub struct Status {
     pub modified: Vec<String>,
     pub untracked: Vec<String>,
     pub unstaged_renamed: Vec<(String, String)>, // (old_path, new_path)
+    /// Changed lockable files we hold no lock on (`locks.read_only` only).
+    pub modified_without_lock: Vec<String>,
 }
 
 impl Status {
@@ -2247,6 +2715,115 @@ mod tests {
         assert_eq!(content, "original content");
     }
 
+    #[test]
+    fn test_add_streams_large_binary() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let content: Vec<u8> = (0..5_000_000u32).map(|i| (i.wrapping_mul(2654435761) >> 11) as u8).collect();
+        fs::write(temp.path().join("footage.bin"), &content).unwrap();
+
+        let result = repo.add("footage.bin").unwrap();
+        assert_eq!(result.files_staged, 1);
+
+        // Streaming produces the same entry as hashing and chunking in memory
+        let index = repo.load_index().unwrap();
+        let entry = index.get("footage.bin").unwrap();
+        assert_eq!(entry.content_hash, Hasher::hash(&content));
+        assert_eq!(entry.size, content.len() as u64);
+        let (_, expected) = chunk_data_with_refs(&content, &repo.chunker_config);
+        let actual: Vec<(Hash, u64)> = entry.chunks.iter().map(|c| (c.hash, c.offset)).collect();
+        let expected: Vec<(Hash, u64)> = expected.iter().map(|c| (c.hash, c.offset)).collect();
+        assert_eq!(actual, expected);
+
+        // Re-adding an unchanged file stages nothing
+        let again = repo.add("footage.bin").unwrap();
+        assert_eq!(again.files_staged, 0);
+        assert_eq!(again.dedup_chunks, 0);
+
+        let commit = repo.commit("Add footage").unwrap();
+        fs::remove_file(temp.path().join("footage.bin")).unwrap();
+        repo.checkout(&commit.hash).unwrap();
+        assert_eq!(fs::read(temp.path().join("footage.bin")).unwrap(), content);
+    }
+
+    #[test]
+    fn test_add_chunks_large_text() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let content: String = (0..600_000).map(|i| format!("frame {:06} ok\n", i)).collect();
+        assert!(content.len() as u64 > MAX_TEXT_SIZE);
+        fs::write(temp.path().join("render.txt"), &content).unwrap();
+        fs::write(temp.path().join("notes.txt"), "small\n").unwrap();
+        repo.add("render.txt").unwrap();
+        repo.add("notes.txt").unwrap();
+
+        let index = repo.load_index().unwrap();
+        let entry = index.get("render.txt").unwrap();
+        assert_eq!(entry.storage, StorageStrategy::DitsChunk);
+        assert_eq!(entry.content_hash, Hasher::hash(content.as_bytes()));
+        assert!(entry.git_oid.is_none());
+        assert!(!entry.chunks.is_empty());
+        assert_eq!(index.get("notes.txt").unwrap().storage, StorageStrategy::GitText);
+        assert_eq!(index.get("notes.txt").unwrap().content_hash, Hasher::hash(b"small\n"));
+
+        let commit = repo.commit("Add log").unwrap();
+        fs::remove_file(temp.path().join("render.txt")).unwrap();
+        repo.checkout(&commit.hash).unwrap();
+        assert_eq!(fs::read_to_string(temp.path().join("render.txt")).unwrap(), content);
+    }
+
+    #[test]
+    fn test_add_keeps_large_text_in_git_when_attributes_say_so() {
+        let temp = tempdir().unwrap();
+        Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join(".ditsattributes"), "*.log storage=git\n").unwrap();
+        let repo = Repository::open(temp.path()).unwrap();
+
+        let content: String = (0..600_000).map(|i| format!("frame {:06} ok\n", i)).collect();
+        assert!(content.len() as u64 > MAX_TEXT_SIZE);
+        fs::write(temp.path().join("render.log"), &content).unwrap();
+        repo.add("render.log").unwrap();
+
+        let index = repo.load_index().unwrap();
+        let entry = index.get("render.log").unwrap();
+        assert_eq!(entry.storage, StorageStrategy::GitText);
+        assert!(entry.git_oid.is_some());
+        assert!(entry.chunks.is_empty());
+    }
+
+    #[test]
+    fn test_add_mp4_chunks_mdat_only() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+
+        let payload: Vec<u8> = (0..300_000u32).map(|i| (i.wrapping_mul(40503) >> 7) as u8).collect();
+        let mut file = Vec::new();
+        file.extend_from_slice(&16u32.to_be_bytes());
+        file.extend_from_slice(b"ftypisom");
+        file.extend_from_slice(&0u32.to_be_bytes());
+        file.extend_from_slice(&8u32.to_be_bytes());
+        file.extend_from_slice(b"moov");
+        file.extend_from_slice(&(payload.len() as u32 + 8).to_be_bytes());
+        file.extend_from_slice(b"mdat");
+        file.extend_from_slice(&payload);
+        fs::write(temp.path().join("clip.mp4"), &file).unwrap();
+
+        repo.add("clip.mp4").unwrap();
+        let index = repo.load_index().unwrap();
+        let entry = index.get("clip.mp4").unwrap();
+        assert!(entry.mp4_metadata.is_some());
+        assert_eq!(entry.content_hash, Hasher::hash(&file));
+        assert_eq!(entry.size, file.len() as u64);
+
+        // Chunks cover the mdat payload, with offsets relative to it
+        let (_, expected) = chunk_data_with_refs(&payload, &repo.chunker_config);
+        let actual: Vec<(Hash, u64)> = entry.chunks.iter().map(|c| (c.hash, c.offset)).collect();
+        let expected: Vec<(Hash, u64)> = expected.iter().map(|c| (c.hash, c.offset)).collect();
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn test_deduplication() {
         let temp = tempdir().unwrap();
@@ -2286,6 +2863,96 @@ mod tests {
         assert!(!status.untracked.contains(&"untracked.txt".to_string()));
     }
 
+    #[test]
+    fn test_status_trusts_stat_cache_unless_refreshed() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let path = temp.path().join("clip.bin");
+
+        fs::write(&path, vec![1u8; 4096]).unwrap();
+        repo.add("clip.bin").unwrap();
+        repo.commit("Add clip").unwrap();
+        assert!(repo.status().unwrap().modified.is_empty());
+
+        // Change the content but make the cached stat claim it is unchanged.
+        // Backdate the file so the entry is not racily clean.
+        fs::write(&path, vec![2u8; 4096]).unwrap();
+        let past = fs::metadata(&path).unwrap().modified().unwrap() - std::time::Duration::from_secs(60);
+        File::options().write(true).open(&path).unwrap().set_modified(past).unwrap();
+        let lock = repo.lock_index().unwrap();
+        let mut index = repo.load_index().unwrap();
+        index.entries.get_mut("clip.bin").unwrap().stat =
+            StatData::from_metadata(&fs::metadata(&path).unwrap());
+        repo.save_index(lock, &mut index).unwrap();
+
+        assert!(repo.status().unwrap().modified.is_empty());
+        let refreshed = repo.status_with_refresh(true).unwrap();
+        assert_eq!(refreshed.modified, vec!["clip.bin".to_string()]);
+    }
+
+    #[test]
+    fn test_status_rehashes_racy_entries() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let path = temp.path().join("clip.bin");
+
+        fs::write(&path, vec![1u8; 4096]).unwrap();
+        repo.add("clip.bin").unwrap();
+        repo.commit("Add clip").unwrap();
+
+        // A file modified at or after the index write cannot be trusted from stat alone
+        fs::write(&path, vec![2u8; 4096]).unwrap();
+        let future = fs::metadata(&path).unwrap().modified().unwrap() + std::time::Duration::from_secs(60);
+        File::options().write(true).open(&path).unwrap().set_modified(future).unwrap();
+        let lock = repo.lock_index().unwrap();
+        let mut index = repo.load_index().unwrap();
+        index.entries.get_mut("clip.bin").unwrap().stat =
+            StatData::from_metadata(&fs::metadata(&path).unwrap());
+        repo.save_index(lock, &mut index).unwrap();
+
+        assert_eq!(repo.status().unwrap().modified, vec!["clip.bin".to_string()]);
+    }
+
+    #[test]
+    fn test_concurrent_index_updates_are_not_lost() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join("a.bin"), vec![1u8; 4096]).unwrap();
+        fs::write(temp.path().join("b.bin"), vec![2u8; 4096]).unwrap();
+
+        // While one command holds the index between load and save, another fails
+        let other = Repository::open(temp.path()).unwrap();
+        let lock = repo.lock_index().unwrap();
+        let mut index = repo.load_index().unwrap();
+        assert!(matches!(other.add("b.bin"), Err(RepoError::IndexError(_))));
+        repo.add_file(&mut index, "a.bin", &temp.path().join("a.bin"), &mut AddResult::default())
+            .unwrap();
+        repo.save_index(lock, &mut index).unwrap();
+
+        other.add("b.bin").unwrap();
+        let index = Repository::open(temp.path()).unwrap().load_index().unwrap();
+        assert!(index.is_staged("a.bin"));
+        assert!(index.is_staged("b.bin"));
+        assert!(!temp.path().join(".dits/index.lock").exists());
+    }
+
+    #[test]
+    fn test_legacy_json_index_is_migrated() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join("clip.bin"), vec![1u8; 4096]).unwrap();
+        repo.add("clip.bin").unwrap();
+
+        let index_path = temp.path().join(".dits/index");
+        let index = Index::load(&index_path).unwrap();
+        fs::write(&index_path, index.to_json()).unwrap();
+
+        let repo = Repository::open(temp.path()).unwrap();
+        assert!(repo.load_index().unwrap().is_staged("clip.bin"));
+        let (_, format) = Index::read_file(&index_path).unwrap();
+        assert!(matches!(format, IndexFormat::Binary { .. }));
+    }
+
     // ========== Phase 4 Tests ==========
 
     #[test]
@@ -2410,4 +3077,197 @@ mod tests {
         assert_eq!(stats.physical_size, stats.logical_size);
         assert_eq!(stats.saved_bytes, 0);
     }
+
+    #[test]
+    fn test_commit_enforces_locks() {
+        let temp = tempdir().unwrap();
+        Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join(".dits/config.toml"), "[locks]\nenforce = \"require\"\n").unwrap();
+        let repo = Repository::open(temp.path()).unwrap();
+        assert_eq!(repo.lock_enforcement(), LockEnforcement::Require);
+
+        // Scene files must be locked before they are committed
+        fs::write(temp.path().join("shot.blend"), b"scene v1").unwrap();
+        fs::write(temp.path().join("notes.txt"), b"notes").unwrap();
+        repo.add("shot.blend").unwrap();
+        repo.add("notes.txt").unwrap();
+        match repo.commit("unlocked") {
+            Err(RepoError::LockViolations(v)) => {
+                assert_eq!(v, vec![LockViolation::NotLocked("shot.blend".into())])
+            }
+            other => panic!("expected lock violation, got {:?}", other.map(|c| c.hash)),
+        }
+
+        let mut cache = LockStore::new(repo.dits_dir());
+        let mut lock = locks::Lock::new("shot.blend", "me", 3600);
+        lock.held = true;
+        cache.insert(lock).unwrap();
+        repo.commit("locked").unwrap();
+
+        // Someone else now holds it; an unchanged file is still fine to commit around
+        cache.replace_all(vec![locks::Lock::new("shot.blend", "someone-else", 3600)]).unwrap();
+        fs::write(temp.path().join("notes.txt"), b"more notes").unwrap();
+        repo.add("notes.txt").unwrap();
+        assert!(repo.lock_violations().unwrap().is_empty());
+
+        fs::write(temp.path().join("shot.blend"), b"scene v2").unwrap();
+        repo.add("shot.blend").unwrap();
+        assert!(matches!(repo.commit("overwrite"), Err(RepoError::LockViolations(_))));
+    }
+
+    #[test]
+    fn test_staged_paths_and_path_lock_violations() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        assert!(repo.staged_paths().unwrap().is_empty());
+
+        fs::write(temp.path().join("notes.txt"), b"notes").unwrap();
+        repo.add("notes.txt").unwrap();
+        assert_eq!(repo.staged_paths().unwrap(), vec!["notes.txt"]);
+        repo.commit("notes").unwrap();
+        assert!(repo.staged_paths().unwrap().is_empty());
+
+        // Paths changed some other way, e.g. through a mount, are checked the same
+        let violations = repo.path_lock_violations(["shot.blend", "notes.txt"]);
+        assert_eq!(violations, vec![LockViolation::NotLocked("shot.blend".into())]);
+    }
+
+    #[test]
+    fn test_read_only_until_locked() {
+        let temp = tempdir().unwrap();
+        Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join(".dits/config.toml"), "[locks]\nread_only = true\n").unwrap();
+        let repo = Repository::open(temp.path()).unwrap();
+        let scene = temp.path().join("shot.blend");
+        let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        fs::write(&scene, b"scene v1").unwrap();
+        fs::write(temp.path().join("notes.txt"), b"notes").unwrap();
+        repo.add("shot.blend").unwrap();
+        repo.add("notes.txt").unwrap();
+        let commit = repo.commit("initial").unwrap();
+
+        // Checkout leaves lockable files read-only, and that is not a change
+        repo.checkout(&commit.hash).unwrap();
+        assert_eq!(mode(&scene) & 0o222, 0);
+        assert_ne!(mode(&temp.path().join("notes.txt")) & 0o200, 0);
+        assert!(repo.status().unwrap().is_clean());
+
+        let mut cache = LockStore::new(repo.dits_dir());
+        let mut lock = locks::Lock::new("shot.blend", "me", 3600);
+        lock.held = true;
+        cache.insert(lock).unwrap();
+        assert_eq!(repo.update_lock_permissions(["shot.blend"]).unwrap(), 1);
+        assert_ne!(mode(&scene) & 0o200, 0);
+
+        fs::write(&scene, b"scene v2").unwrap();
+        assert!(repo.status().unwrap().modified_without_lock.is_empty());
+
+        cache.remove("shot.blend").unwrap();
+        assert_eq!(repo.refresh_lock_permissions().unwrap(), 1);
+        assert_eq!(mode(&scene) & 0o222, 0);
+        let status = repo.status().unwrap();
+        assert_eq!(status.modified, vec!["shot.blend".to_string()]);
+        assert_eq!(status.modified_without_lock, vec!["shot.blend".to_string()]);
+
+        // Checking out over a read-only file still works
+        repo.checkout(&commit.hash).unwrap();
+        assert_eq!(fs::read(&scene).unwrap(), b"scene v1");
+        assert_eq!(mode(&scene) & 0o222, 0);
+    }
+
+    #[test]
+    fn test_attributes_override_routing() {
+        let temp = tempdir().unwrap();
+        Repository::init(temp.path()).unwrap();
+        fs::write(
+            temp.path().join(".ditsattributes"),
+            "*.xml storage=chunk chunker=fast\n*.simcache lockable\n*.blend -lockable\n",
+        )
+        .unwrap();
+        let repo = Repository::open(temp.path()).unwrap();
+
+        fs::write(temp.path().join("cut.xml"), b"<edl>huge binary export</edl>").unwrap();
+        fs::write(temp.path().join("notes.json"), b"{}").unwrap();
+        repo.add("cut.xml").unwrap();
+        repo.add("notes.json").unwrap();
+        let index = repo.load_index().unwrap();
+        assert_eq!(index.get("cut.xml").unwrap().storage, StorageStrategy::DitsChunk);
+        assert_eq!(index.get("notes.json").unwrap().storage, StorageStrategy::GitText);
+
+        assert!(repo.is_lockable("cache/sim.simcache"));
+        assert!(!repo.is_lockable("shot.blend"));
+        assert!(repo.is_lockable("edit.prproj"));
+        assert!(!repo.is_lockable("cut.xml.txt"));
+
+        // Content survives a round trip through the overridden engine
+        let commit = repo.commit("edl").unwrap();
+        let manifest = repo.load_manifest(&commit.manifest).unwrap();
+        let entry = manifest.get("cut.xml").unwrap();
+        assert_eq!(repo.read_entry(entry).unwrap(), b"<edl>huge binary export</edl>");
+    }
+
+    #[test]
+    fn test_relink_is_reversed_on_add() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        let project = temp.path().join("cut.fcpxml");
+        let original = r#"<fcpxml><asset src="file:///Volumes/RAID/A001.mov"/></fcpxml>"#;
+        fs::write(&project, original).unwrap();
+        repo.add("cut.fcpxml").unwrap();
+        let commit = repo.commit("cut").unwrap();
+
+        let mapping = RelinkMapping::new("/Volumes/RAID/A001.mov", "/work/footage/A001.mov");
+        assert!(repo.relink("cut.fcpxml", vec![mapping.clone()]).unwrap().is_some());
+        assert!(fs::read_to_string(&project).unwrap().contains("file:///work/footage/A001.mov"));
+        assert!(repo.status_with_refresh(true).unwrap().is_clean());
+
+        // Edits made while relinked are staged with the original paths
+        let edited = r#"<fcpxml><asset src="file:///work/footage/A001.mov"/><title/></fcpxml>"#;
+        fs::write(&project, edited).unwrap();
+        repo.add("cut.fcpxml").unwrap();
+        let commit2 = repo.commit("title").unwrap();
+        let manifest = repo.load_manifest(&commit2.manifest).unwrap();
+        let staged = repo.read_entry(manifest.get("cut.fcpxml").unwrap()).unwrap();
+        assert_eq!(staged, br#"<fcpxml><asset src="file:///Volumes/RAID/A001.mov"/><title/></fcpxml>"#);
+
+        assert!(repo.undo_relink("cut.fcpxml").unwrap());
+        assert_eq!(fs::read(&project).unwrap(), staged);
+        assert!(repo.status_with_refresh(true).unwrap().is_clean());
+        assert!(!repo.undo_relink("cut.fcpxml").unwrap());
+
+        // Checkout restores committed content and forgets relinks
+        repo.relink("cut.fcpxml", vec![mapping]).unwrap();
+        repo.checkout(&commit.hash).unwrap();
+        assert_eq!(fs::read_to_string(&project).unwrap(), original);
+        assert!(repo.relinks().list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_checkout_forgets_only_relinks_it_rewrote() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        fs::write(temp.path().join("notes.txt"), "notes").unwrap();
+        repo.add("notes.txt").unwrap();
+        let without_project = repo.commit("notes").unwrap();
+        let project = temp.path().join("cut.fcpxml");
+        fs::write(&project, r#"<fcpxml><asset src="file:///Volumes/RAID/A001.mov"/></fcpxml>"#).unwrap();
+        repo.add("cut.fcpxml").unwrap();
+        let with_project = repo.commit("cut").unwrap();
+        let mapping = RelinkMapping::new("/Volumes/RAID/A001.mov", "/work/footage/A001.mov");
+
+        // A relinked project is removed like an unmodified one
+        repo.relink("cut.fcpxml", vec![mapping.clone()]).unwrap();
+        repo.checkout(&without_project.hash).unwrap();
+        assert!(!project.exists());
+        assert!(repo.relinks().list().unwrap().is_empty());
+
+        // One edited since it was relinked is left in place, still relinked
+        repo.checkout(&with_project.hash).unwrap();
+        repo.relink("cut.fcpxml", vec![mapping]).unwrap();
+        fs::write(&project, r#"<fcpxml><asset src="file:///work/footage/A001.mov"/><title/></fcpxml>"#).unwrap();
+        repo.checkout(&without_project.hash).unwrap();
+        assert!(project.exists());
+        assert_eq!(repo.relinks().list().unwrap().len(), 1);
+    }
 }
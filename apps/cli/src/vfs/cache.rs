@@ -7,12 +7,22 @@
 //!
 //! Reads check each tier in order. Cache misses trigger fetches
 //! from the next tier and populate the faster tiers.
+//!
+//! The L2 cache is bounded: once it grows past `l2_max_bytes`, the least
+//! recently used chunks are deleted. Chunks downloaded from a remote are
+//! only kept in L1 and L2, so a lazy mount never grows the object store by
+//! more than the MP4 blobs it reads.
 
+use super::remote::ObjectSource;
+use super::VfsError;
 use crate::core::Hash;
-use crate::store::ObjectStore;
+use crate::store::reachability::{ObjectId, ObjectKind};
+use crate::store::{ObjectError, ObjectStore, ObjectType};
 use moka::future::Cache;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::fs;
 use tokio::sync::RwLock;
 
@@ -29,6 +39,10 @@ pub struct CacheConfig {
     pub prefetch_enabled: bool,
     /// Number of chunks to prefetch ahead.
     pub prefetch_count: usize,
+    /// Remote that chunks and blobs missing locally are downloaded from.
+    pub remote: Option<Arc<dyn ObjectSource>>,
+    /// How long a read waits for the remote before failing.
+    pub remote_timeout: Duration,
 }
 
 impl Default for CacheConfig {
@@ -39,6 +53,8 @@ impl Default for CacheConfig {
             l2_path: PathBuf::from(".dits/cache"),
             prefetch_enabled: true,
             prefetch_count: 4,
+            remote: None,
+            remote_timeout: Duration::from_secs(30),
         }
     }
 }
@@ -75,8 +91,66 @@ pub struct ChunkCache {
     object_store: Arc<ObjectStore>,
     /// Statistics.
     stats: Arc<RwLock<CacheStats>>,
-    /// L2 cache current size in bytes.
-    l2_size: Arc<RwLock<u64>>,
+    /// Chunks in the L2 cache, for eviction.
+    l2_index: Arc<Mutex<L2Index>>,
+}
+
+/// Least-recently-used index of the L2 disk cache.
+#[derive(Debug, Default)]
+struct L2Index {
+    /// Size and last use of each cached chunk.
+    entries: HashMap<Hash, (u64, u64)>,
+    /// Cached chunks by last use, oldest first.
+    order: BTreeMap<u64, Hash>,
+    /// Total size of the cached chunks in bytes.
+    size: u64,
+    /// Use counter.
+    tick: u64,
+}
+
+impl L2Index {
+    /// Mark a chunk as just used. Returns false if it is not cached.
+    fn touch(&mut self, hash: &Hash) -> bool {
+        let Some((_, last_use)) = self.entries.get_mut(hash) else {
+            return false;
+        };
+        self.order.remove(last_use);
+        self.tick += 1;
+        *last_use = self.tick;
+        self.order.insert(self.tick, *hash);
+        true
+    }
+
+    /// Record a cached chunk, returning the chunks to evict to stay within
+    /// `max_bytes`.
+    fn insert(&mut self, hash: Hash, size: u64, max_bytes: u64) -> Vec<Hash> {
+        if !self.touch(&hash) {
+            self.tick += 1;
+            self.entries.insert(hash, (size, self.tick));
+            self.order.insert(self.tick, hash);
+            self.size += size;
+        }
+
+        let mut evicted = Vec::new();
+        while self.size > max_bytes {
+            let Some((_, oldest)) = self.order.pop_first() else {
+                break;
+            };
+            if let Some((size, _)) = self.entries.remove(&oldest) {
+                self.size -= size;
+            }
+            evicted.push(oldest);
+        }
+        evicted
+    }
+
+    /// Forget a chunk whose file is gone.
+    fn remove(&mut self, hash: &Hash) {
+        if let Some((size, last_use)) = self.entries.remove(hash) {
+            self.order.remove(&last_use);
+            self.size -= size;
+        }
+    }
 }
 
 /// Cache statistics.
@@ -88,18 +162,20 @@ pub struct CacheStats {
     pub l2_hits: u64,
     /// L3 (object store) hits.
     pub l3_hits: u64,
+    /// Chunks downloaded from the remote.
+    pub remote_hits: u64,
     /// Total cache misses (chunk not found anywhere).
     pub misses: u64,
     /// Total bytes read from cache.
     pub bytes_read: u64,
-    /// Total bytes fetched from object store.
+    /// Total bytes fetched from the object store or the remote.
     pub bytes_fetched: u64,
 }
 
 impl CacheStats {
     /// Calculate L1 hit rate.
     pub fn l1_hit_rate(&self) -> f64 {
-        let total = self.l1_hits + self.l2_hits + self.l3_hits + self.misses;
+        let total = self.l1_hits + self.l2_hits + self.l3_hits + self.remote_hits + self.misses;
         if total == 0 {
             0.0
         } else {
@@ -109,11 +185,12 @@ impl CacheStats {
 
     /// Calculate overall hit rate (any tier).
     pub fn overall_hit_rate(&self) -> f64 {
-        let total = self.l1_hits + self.l2_hits + self.l3_hits + self.misses;
+        let hits = self.l1_hits + self.l2_hits + self.l3_hits + self.remote_hits;
+        let total = hits + self.misses;
         if total == 0 {
             0.0
         } else {
-            (self.l1_hits + self.l2_hits + self.l3_hits) as f64 / total as f64
+            hits as f64 / total as f64
         }
     }
 }
@@ -139,89 +216,179 @@ impl ChunkCache {
             l1,
             object_store,
             stats: Arc::new(RwLock::new(CacheStats::default())),
-            l2_size: Arc::new(RwLock::new(0)),
+            l2_index: Arc::new(Mutex::new(L2Index::default())),
         }
     }
 
     /// Initialize the cache (create directories).
+    ///
+    /// Chunks left in the L2 cache by earlier runs are indexed oldest
+    /// first, and evicted if they exceed the configured size.
     pub async fn init(&self) -> std::io::Result<()> {
         fs::create_dir_all(&self.l2_path).await?;
+
+        let cached = scan_l2(&self.l2_path)?;
+        let evicted: Vec<Hash> = {
+            let mut index = self.l2_index.lock().expect("L2 index lock poisoned");
+            cached
+                .into_iter()
+                .flat_map(|(hash, size)| index.insert(hash, size, self.config.l2_max_bytes))
+                .collect()
+        };
+        for hash in evicted {
+            let _ = fs::remove_file(self.l2_chunk_path(&hash)).await;
+        }
         Ok(())
     }
 
     /// Get a chunk by hash, checking all cache tiers.
     pub async fn get(&self, hash: &Hash) -> Option<Arc<Vec<u8>>> {
+        self.fetch(hash).await.ok()
+    }
+
+    /// Get a chunk by hash, checking all cache tiers and then the remote.
+    ///
+    /// Concurrent requests for the same chunk share one load, so a read
+    /// never downloads a chunk that read-ahead is already fetching.
+    pub async fn fetch(&self, hash: &Hash) -> Result<Arc<Vec<u8>>, VfsError> {
         // L1: RAM cache
         if let Some(data) = self.l1.get(hash).await {
             let mut stats = self.stats.write().await;
             stats.l1_hits += 1;
             stats.bytes_read += data.len() as u64;
-            return Some(data);
+            return Ok(data);
         }
 
+        let data = self
+            .l1
+            .try_get_with(*hash, self.load(hash))
+            .await
+            .map_err(|e| unshare(&e))?;
+        self.stats.write().await.bytes_read += data.len() as u64;
+        Ok(data)
+    }
+
+    /// Load a chunk missing from L1 from the slower tiers.
+    async fn load(&self, hash: &Hash) -> Result<Arc<Vec<u8>>, VfsError> {
         // L2: Disk cache
         if let Some(data) = self.get_l2(hash).await {
-            let data = Arc::new(data);
-            // Promote to L1
-            self.l1.insert(*hash, data.clone()).await;
-            let mut stats = self.stats.write().await;
-            stats.l2_hits += 1;
-            stats.bytes_read += data.len() as u64;
-            return Some(data);
+            self.stats.write().await.l2_hits += 1;
+            return Ok(Arc::new(data));
         }
 
         // L3: Object store
-        match self.object_store.load_chunk(hash) {
-            Ok(chunk) => {
-                let data = Arc::new(chunk.data);
-                // Populate L1 and L2
-                self.l1.insert(*hash, data.clone()).await;
-                let _ = self.put_l2(hash, &data).await;
-                let mut stats = self.stats.write().await;
-                stats.l3_hits += 1;
-                stats.bytes_read += data.len() as u64;
-                stats.bytes_fetched += data.len() as u64;
-                return Some(data);
+        if let Ok(chunk) = self.object_store.load_chunk(hash) {
+            let data = Arc::new(chunk.data);
+            let _ = self.put_l2(hash, &data).await;
+            let mut stats = self.stats.write().await;
+            stats.l3_hits += 1;
+            stats.bytes_fetched += data.len() as u64;
+            return Ok(data);
+        }
+
+        // Remote
+        let downloaded = match &self.config.remote {
+            Some(remote) => self.download(remote.as_ref(), &ObjectId::new(ObjectKind::Chunk, hash)).await,
+            None => Ok(None),
+        };
+        let data = match downloaded {
+            Ok(Some(data)) => data,
+            Ok(None) => {
+                self.stats.write().await.misses += 1;
+                return Err(VfsError::ChunkNotFound(hash.to_hex()));
             }
             Err(e) => {
-                eprintln!("L3 miss for {}: {:?}", hash.to_hex(), e);
+                self.stats.write().await.misses += 1;
+                return Err(e);
             }
-        }
+        };
+        ObjectStore::verify_object(ObjectType::Chunk, hash, &data)
+            .map_err(|e| VfsError::Cache(format!("Remote sent a corrupt chunk: {}", e)))?;
 
-        // Not found anywhere
+        let data = Arc::new(data);
+        let _ = self.put_l2(hash, &data).await;
         let mut stats = self.stats.write().await;
-        stats.misses += 1;
-        None
+        stats.remote_hits += 1;
+        stats.bytes_fetched += data.len() as u64;
+        Ok(data)
+    }
+
+    /// Load an MP4 blob (ftyp or moov), downloading it if it is missing.
+    ///
+    /// Downloaded blobs are written to the object store: they are small and
+    /// every read of the start of the file needs them.
+    pub async fn fetch_blob(&self, hash: &Hash) -> Result<Vec<u8>, VfsError> {
+        let remote = match self.object_store.load_blob(hash) {
+            Ok(data) => return Ok(data),
+            Err(ObjectError::NotFound(_)) => match &self.config.remote {
+                Some(remote) => remote,
+                None => return Err(VfsError::NotFound(format!("blob {}", hash.to_hex()))),
+            },
+            Err(e) => return Err(VfsError::Cache(e.to_string())),
+        };
+
+        let data = self
+            .download(remote.as_ref(), &ObjectId::new(ObjectKind::Blob, hash))
+            .await?
+            .ok_or_else(|| VfsError::NotFound(format!("blob {}", hash.to_hex())))?;
+        self.object_store
+            .write_object(ObjectType::Blob, hash, &data)
+            .map_err(|e| VfsError::Cache(format!("Remote sent a bad blob: {}", e)))?;
+        Ok(data)
+    }
+
+    /// Download an object, giving up after the configured timeout.
+    async fn download(&self, remote: &dyn ObjectSource, id: &ObjectId) -> Result<Option<Vec<u8>>, VfsError> {
+        match tokio::time::timeout(self.config.remote_timeout, remote.download(id)).await {
+            Ok(Ok(data)) => Ok(data),
+            Ok(Err(reason)) => Err(VfsError::RemoteUnavailable(format!("{}: {}", remote.location(), reason))),
+            Err(_) => Err(VfsError::RemoteUnavailable(format!(
+                "{}: no response after {}s",
+                remote.location(),
+                self.config.remote_timeout.as_secs()
+            ))),
+        }
     }
 
     /// Get chunk from L2 disk cache.
     async fn get_l2(&self, hash: &Hash) -> Option<Vec<u8>> {
-        let path = self.l2_chunk_path(hash);
-        fs::read(&path).await.ok()
+        if !self.l2_index.lock().expect("L2 index lock poisoned").touch(hash) {
+            return None;
+        }
+        match fs::read(self.l2_chunk_path(hash)).await {
+            Ok(data) => Some(data),
+            Err(_) => {
+                self.l2_index.lock().expect("L2 index lock poisoned").remove(hash);
+                None
+            }
+        }
     }
 
-    /// Put chunk into L2 disk cache with size limiting.
+    /// Put chunk into L2 disk cache, evicting the least recently used
+    /// chunks if it grows past its limit.
     async fn put_l2(&self, hash: &Hash, data: &[u8]) -> std::io::Result<()> {
-        let path = self.l2_chunk_path(hash);
-
-        // Check if we have room in L2 cache
         let data_size = data.len() as u64;
-        {
-            let current_size = *self.l2_size.read().await;
-            if current_size + data_size > self.config.l2_max_bytes {
-                // L2 cache is full, skip caching (LRU eviction would be more complex)
-                // In production, we'd implement proper eviction based on access time
-                return Ok(());
-            }
+        if data_size > self.config.l2_max_bytes {
+            return Ok(());
         }
 
+        // Write through a temporary file so an interrupted write is never indexed
+        let path = self.l2_chunk_path(hash);
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent).await?;
         }
-        fs::write(&path, data).await?;
-
-        // Update size tracking
-        *self.l2_size.write().await += data_size;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data).await?;
+        fs::rename(&tmp, &path).await?;
+
+        let evicted = self
+            .l2_index
+            .lock()
+            .expect("L2 index lock poisoned")
+            .insert(*hash, data_size, self.config.l2_max_bytes);
+        for hash in evicted {
+            let _ = fs::remove_file(self.l2_chunk_path(&hash)).await;
+        }
         Ok(())
     }
 
@@ -260,7 +427,7 @@ impl ChunkCache {
             l2_path: self.l2_path.clone(),
             object_store: self.object_store.clone(),
             stats: self.stats.clone(),
-            l2_size: self.l2_size.clone(),
+            l2_index: self.l2_index.clone(),
         }
     }
 
@@ -276,6 +443,7 @@ impl ChunkCache {
             fs::remove_dir_all(&self.l2_path).await?;
             fs::create_dir_all(&self.l2_path).await?;
         }
+        *self.l2_index.lock().expect("L2 index lock poisoned") = L2Index::default();
         *self.stats.write().await = CacheStats::default();
         Ok(())
     }
@@ -289,12 +457,55 @@ impl ChunkCache {
     pub fn l1_weighted_size(&self) -> u64 {
         self.l1.weighted_size()
     }
+
+    /// Get the L2 cache size in bytes.
+    pub fn l2_size(&self) -> u64 {
+        self.l2_index.lock().expect("L2 index lock poisoned").size
+    }
+}
+
+/// Copy an error shared by coalesced loads.
+fn unshare(e: &VfsError) -> VfsError {
+    match e {
+        VfsError::ChunkNotFound(hash) => VfsError::ChunkNotFound(hash.clone()),
+        VfsError::RemoteUnavailable(reason) => VfsError::RemoteUnavailable(reason.clone()),
+        other => VfsError::Cache(other.to_string()),
+    }
+}
+
+/// Chunks in an L2 cache directory, least recently written first.
+fn scan_l2(l2_path: &Path) -> std::io::Result<Vec<(Hash, u64)>> {
+    let mut cached = Vec::new();
+    for dir in std::fs::read_dir(l2_path)? {
+        let dir = dir?;
+        if !dir.file_type()?.is_dir() {
+            continue;
+        }
+        let prefix = dir.file_name().to_string_lossy().to_string();
+        for file in std::fs::read_dir(dir.path())? {
+            let file = file?;
+            let name = format!("{}{}", prefix, file.file_name().to_string_lossy());
+            // Leftover temporary files do not parse as hashes
+            let Ok(hash) = Hash::from_hex(&name) else {
+                continue;
+            };
+            let metadata = file.metadata()?;
+            cached.push((metadata.modified()?, hash, metadata.len()));
+        }
+    }
+    cached.sort_by_key(|(modified, ..)| *modified);
+    Ok(cached.into_iter().map(|(_, hash, size)| (hash, size)).collect())
 }
 
 /// Synchronous wrapper for use in FUSE handlers.
+///
+/// Calls block on the wrapper's own runtime, so they must not be made from
+/// async code; front ends that are async themselves call it from
+/// `spawn_blocking`. The wrapper may be dropped anywhere.
 pub struct SyncChunkCache {
     inner: ChunkCache,
-    runtime: tokio::runtime::Runtime,
+    /// Always `Some` until dropped.
+    runtime: Option<tokio::runtime::Runtime>,
 }
 
 impl SyncChunkCache {
@@ -310,27 +521,45 @@ impl SyncChunkCache {
         // Initialize cache directories
         runtime.block_on(inner.init())?;
 
-        Ok(Self { inner, runtime })
+        Ok(Self { inner, runtime: Some(runtime) })
+    }
+
+    fn runtime(&self) -> &tokio::runtime::Runtime {
+        self.runtime.as_ref().expect("runtime is only taken on drop")
+    }
+
+    /// Get a chunk synchronously, reporting why it is unavailable.
+    pub fn fetch(&self, hash: &Hash) -> Result<Arc<Vec<u8>>, VfsError> {
+        self.runtime().block_on(self.inner.fetch(hash))
     }
 
-    /// Get a chunk synchronously.
-    pub fn get(&self, hash: &Hash) -> Option<Arc<Vec<u8>>> {
-        self.runtime.block_on(self.inner.get(hash))
+    /// Load an MP4 blob synchronously.
+    pub fn fetch_blob(&self, hash: &Hash) -> Result<Vec<u8>, VfsError> {
+        self.runtime().block_on(self.inner.fetch_blob(hash))
     }
 
     /// Prefetch chunks synchronously.
     pub fn prefetch(&self, hashes: &[Hash]) {
-        self.runtime.block_on(self.inner.prefetch(hashes))
+        self.runtime().block_on(self.inner.prefetch(hashes))
     }
 
     /// Get statistics.
     pub fn stats(&self) -> CacheStats {
-        self.runtime.block_on(self.inner.stats())
+        self.runtime().block_on(self.inner.stats())
     }
 
     /// Clear cache.
     pub fn clear(&self) -> std::io::Result<()> {
-        self.runtime.block_on(self.inner.clear())
+        self.runtime().block_on(self.inner.clear())
+    }
+}
+
+impl Drop for SyncChunkCache {
+    fn drop(&mut self) {
+        // Dropping a runtime blocks, which panics inside async code
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
     }
 }
 
@@ -393,4 +622,105 @@ mod tests {
         let stats = cache.stats().await;
         assert_eq!(stats.misses, 1);
     }
+
+    /// Serves chunks from memory, or fails as if the network were down.
+    #[derive(Debug, Default)]
+    struct FakeRemote {
+        chunks: HashMap<String, Vec<u8>>,
+        offline: bool,
+    }
+
+    impl ObjectSource for FakeRemote {
+        fn location(&self) -> String {
+            "http://remote".to_string()
+        }
+
+        fn download<'a>(
+            &'a self,
+            id: &'a ObjectId,
+        ) -> futures_util::future::BoxFuture<'a, Result<Option<Vec<u8>>, String>> {
+            Box::pin(async move {
+                if self.offline {
+                    return Err("connection refused".to_string());
+                }
+                Ok(self.chunks.get(&id.id).cloned())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_fallback() {
+        let temp = tempdir().unwrap();
+        let store = Arc::new(ObjectStore::new(temp.path()));
+        store.init().unwrap();
+
+        let chunk = Chunk::new(b"remote chunk".to_vec());
+        let mut remote = FakeRemote::default();
+        remote.chunks.insert(chunk.hash.to_hex(), chunk.data.clone());
+        let config = CacheConfig {
+            l2_path: temp.path().join("cache"),
+            remote: Some(Arc::new(remote)),
+            ..Default::default()
+        };
+        let cache = ChunkCache::new(config, store.clone());
+        cache.init().await.unwrap();
+
+        assert_eq!(*cache.fetch(&chunk.hash).await.unwrap(), chunk.data);
+        assert!(matches!(cache.fetch(&Hash::ZERO).await, Err(VfsError::ChunkNotFound(_))));
+        let stats = cache.stats().await;
+        assert_eq!(stats.remote_hits, 1);
+        assert_eq!(stats.misses, 1);
+        // Downloaded chunks are cached, not added to the object store
+        assert!(!store.has_object(ObjectType::Chunk, &chunk.hash));
+        assert_eq!(cache.l2_size(), chunk.data.len() as u64);
+
+        let offline = CacheConfig {
+            l2_path: temp.path().join("offline"),
+            remote: Some(Arc::new(FakeRemote { offline: true, ..Default::default() })),
+            ..Default::default()
+        };
+        let cache = ChunkCache::new(offline, store);
+        cache.init().await.unwrap();
+        match cache.fetch(&chunk.hash).await {
+            Err(VfsError::RemoteUnavailable(reason)) => assert!(reason.contains("connection refused")),
+            other => panic!("expected the remote to be unavailable, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_l2_evicts_least_recently_used() {
+        let temp = tempdir().unwrap();
+        let store = Arc::new(ObjectStore::new(temp.path()));
+        store.init().unwrap();
+
+        let chunks: Vec<Chunk> = (0..3u8).map(|i| Chunk::new(vec![i; 100])).collect();
+        for chunk in &chunks {
+            store.store_chunk(chunk).unwrap();
+        }
+        let config = CacheConfig {
+            l2_max_bytes: 250,
+            l2_path: temp.path().join("cache"),
+            ..Default::default()
+        };
+        let cache = ChunkCache::new(config.clone(), store.clone());
+        cache.init().await.unwrap();
+
+        cache.fetch(&chunks[0].hash).await.unwrap();
+        cache.fetch(&chunks[1].hash).await.unwrap();
+        // Use the first chunk again so the second is the oldest
+        cache.l1.invalidate_all();
+        cache.fetch(&chunks[0].hash).await.unwrap();
+        cache.fetch(&chunks[2].hash).await.unwrap();
+
+        assert_eq!(cache.l2_size(), 200);
+        assert!(cache.l2_chunk_path(&chunks[0].hash).exists());
+        assert!(!cache.l2_chunk_path(&chunks[1].hash).exists());
+        assert!(cache.l2_chunk_path(&chunks[2].hash).exists());
+
+        // A smaller limit on the next run evicts down to it
+        let smaller = CacheConfig { l2_max_bytes: 100, ..config };
+        let cache = ChunkCache::new(smaller, store);
+        cache.init().await.unwrap();
+        assert_eq!(cache.l2_size(), 100);
+    }
 }
@@ -121,6 +121,15 @@ impl VfsEntry {
         self.children.insert(name, inode);
     }
 
+    /// Replace a file's content with that of a manifest entry.
+    pub fn set_content(&mut self, manifest_entry: &ManifestEntry) {
+        self.size = manifest_entry.size;
+        self.chunks = manifest_entry.chunks.clone();
+        self.content_hash = Some(manifest_entry.content_hash);
+        self.mp4_metadata = manifest_entry.mp4_metadata.clone();
+        self.mtime = SystemTime::now();
+    }
+
     /// Find chunk(s) covering a byte range.
     pub fn chunks_for_range(&self, offset: u64, size: u64) -> Vec<(usize, &ChunkRef, u64, u64)> {
         let mut result = Vec::new();
@@ -186,14 +195,28 @@ impl VfsTree {
         tree
     }
 
+    /// Add every file of a manifest below the directory `dir_inode`.
+    #[cfg(feature = "fuser")]
+    pub fn graft(&mut self, dir_inode: u64, manifest: &Manifest) {
+        for (path, entry) in manifest.iter() {
+            self.add_file_under(dir_inode, path, entry);
+        }
+    }
+
     /// Add a file to the tree, creating parent directories as needed.
     fn add_file(&mut self, path: &str, manifest_entry: &ManifestEntry) {
+        self.add_file_under(self.root_inode, path, manifest_entry);
+    }
+
+    /// Add a file below the directory `dir_inode`, creating parent
+    /// directories as needed.
+    fn add_file_under(&mut self, dir_inode: u64, path: &str, manifest_entry: &ManifestEntry) {
         let components: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
-        if components.is_empty() {
+        if components.is_empty() || !self.entries.contains_key(&dir_inode) {
             return;
         }
 
-        let mut current_inode = self.root_inode;
+        let mut current_inode = dir_inode;
 
         // Create/traverse directories
         for (i, component) in components.iter().enumerate() {
@@ -284,6 +307,105 @@ impl VfsTree {
         Some(result)
     }
 
+    /// Insert an entry into a directory, replacing any child of the same
+    /// name. Returns the inode assigned to the entry.
+    pub fn insert(&mut self, parent_inode: u64, mut entry: VfsEntry) -> Option<u64> {
+        if !self.entries.get(&parent_inode)?.is_dir() {
+            return None;
+        }
+        if let Some(existing) = self.lookup_child(parent_inode, &entry.name).map(|e| e.inode) {
+            self.remove(existing);
+        }
+
+        let inode = self.next_inode;
+        self.next_inode += 1;
+        entry.inode = inode;
+        entry.parent_inode = parent_inode;
+        self.entries.get_mut(&parent_inode)?.add_child(entry.name.clone(), inode);
+        self.entries.insert(inode, entry);
+        Some(inode)
+    }
+
+    /// Remove an entry and everything below it.
+    pub fn remove(&mut self, inode: u64) -> Option<VfsEntry> {
+        if inode == self.root_inode {
+            return None;
+        }
+        let entry = self.entries.remove(&inode)?;
+        if let Some(parent) = self.entries.get_mut(&entry.parent_inode) {
+            parent.children.remove(&entry.name);
+        }
+
+        let mut pending: Vec<u64> = entry.children.values().copied().collect();
+        while let Some(child) = pending.pop() {
+            if let Some(removed) = self.entries.remove(&child) {
+                pending.extend(removed.children.values());
+            }
+        }
+        Some(entry)
+    }
+
+    /// Unlink an entry from its directory. The entry and everything below it
+    /// stay readable by inode, so files already open are not cut off.
+    #[cfg(feature = "fuser")]
+    pub fn detach(&mut self, inode: u64) -> bool {
+        if inode == self.root_inode {
+            return false;
+        }
+        let Some(entry) = self.entries.get(&inode) else {
+            return false;
+        };
+        let (parent, name) = (entry.parent_inode, entry.name.clone());
+        if let Some(parent) = self.entries.get_mut(&parent) {
+            if parent.children.get(&name) == Some(&inode) {
+                parent.children.remove(&name);
+            }
+        }
+        true
+    }
+
+    /// Move an entry to a new parent and name, replacing any entry already there.
+    #[cfg(feature = "fuser")]
+    pub fn rename(&mut self, inode: u64, new_parent: u64, new_name: &str) -> bool {
+        if !self.entries.get(&new_parent).is_some_and(|p| p.is_dir()) {
+            return false;
+        }
+        let Some(entry) = self.entries.get(&inode) else {
+            return false;
+        };
+        let (old_parent, old_name) = (entry.parent_inode, entry.name.clone());
+
+        if let Some(existing) = self.lookup_child(new_parent, new_name).map(|e| e.inode) {
+            if existing != inode {
+                self.remove(existing);
+            }
+        }
+        if let Some(parent) = self.entries.get_mut(&old_parent) {
+            parent.children.remove(&old_name);
+        }
+        if let Some(parent) = self.entries.get_mut(&new_parent) {
+            parent.add_child(new_name.to_string(), inode);
+        }
+        if let Some(entry) = self.entries.get_mut(&inode) {
+            entry.name = new_name.to_string();
+            entry.parent_inode = new_parent;
+        }
+        true
+    }
+
+    /// Repository path of an entry (empty for the root).
+    #[cfg(feature = "fuser")]
+    pub fn path_of(&self, inode: u64) -> Option<String> {
+        let mut components = Vec::new();
+        let mut current = self.entries.get(&inode)?;
+        while current.inode != self.root_inode {
+            components.push(current.name.as_str());
+            current = self.entries.get(&current.parent_inode)?;
+        }
+        components.reverse();
+        Some(components.join("/"))
+    }
+
     /// Get total number of entries.
     pub fn len(&self) -> usize {
         self.entries.len()
@@ -364,6 +486,49 @@ mod tests {
         assert!(entries.iter().any(|(n, _, _)| n == "dir"));
     }
 
+    #[test]
+    #[cfg(feature = "fuser")]
+    fn test_insert_rename_remove() {
+        let mut tree = VfsTree::new();
+        tree.add_file("footage/a.mov", &make_manifest_entry(100));
+        let footage = tree.lookup(Path::new("footage")).unwrap().inode;
+
+        let dir = tree.insert(1, VfsEntry::directory("renders".into(), 0, 0)).unwrap();
+        let file = tree.insert(dir, VfsEntry::file("out.mov".into(), 0, 0, &make_manifest_entry(5))).unwrap();
+        assert_eq!(tree.path_of(file).unwrap(), "renders/out.mov");
+
+        // Renaming over an existing file replaces it
+        assert!(tree.rename(file, footage, "a.mov"));
+        assert_eq!(tree.path_of(file).unwrap(), "footage/a.mov");
+        assert_eq!(tree.lookup(Path::new("footage/a.mov")).unwrap().size, 5);
+        assert!(tree.readdir(dir).unwrap().len() == 2);
+
+        // Removing a directory drops its children
+        let before = tree.len();
+        tree.remove(footage).unwrap();
+        assert_eq!(tree.len(), before - 2);
+        assert!(tree.get(file).is_none());
+        assert!(tree.lookup(Path::new("footage")).is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "fuser")]
+    fn test_graft_and_detach() {
+        let mut tree = VfsTree::new();
+        let mut manifest = Manifest::new();
+        manifest.add(ManifestEntry::new("cuts/a.mov".into(), 100, Hash::ZERO, Vec::new()));
+
+        let v1 = tree.insert(1, VfsEntry::directory("v1".into(), 0, 0)).unwrap();
+        tree.graft(v1, &manifest);
+        let file = tree.lookup(Path::new("v1/cuts/a.mov")).unwrap().inode;
+        assert_eq!(tree.path_of(file).unwrap(), "v1/cuts/a.mov");
+
+        // Detached entries leave the namespace but stay readable by inode
+        assert!(tree.detach(v1));
+        assert!(tree.lookup(Path::new("v1")).is_none());
+        assert_eq!(tree.get(file).unwrap().size, 100);
+    }
+
     #[test]
     fn test_chunks_for_range() {
         let mut entry = VfsEntry::directory("test".into(), 1, 0);
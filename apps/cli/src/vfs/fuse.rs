@@ -1,66 +1,49 @@
 //! FUSE filesystem implementation for Dits.
 //!
-//! This module provides a read-only FUSE filesystem that exposes
-//! a repository commit as a virtual directory structure.
+//! This module provides a FUSE filesystem that exposes a repository commit
+//! as a virtual directory structure. Mounts are read-only unless created
+//! with [`mount_writable`], which records edits in an [`Overlay`]: a file
+//! is copied up on first write and re-chunked into the object store when
+//! it is flushed. [`mount_namespace`] shows every branch, tag and commit
+//! through a [`RefNamespace`] instead of a single commit, and
+//! [`mount_proxied`] serves videos from their proxies as set out in a
+//! [`ProxyView`].
+//!
+//! Reads that need a chunk the lazy mount's remote cannot provide fail with
+//! `ENETUNREACH`, so applications report a network problem instead of a
+//! corrupt file.
 
-use super::cache::{CacheConfig, SyncChunkCache};
+use super::cache::{CacheConfig, CacheStats};
 use super::entry::{VfsEntry, VfsEntryType, VfsTree};
-use crate::core::{Hash, Manifest, Mp4Metadata};
-use crate::store::ObjectStore;
-use byteorder::{BigEndian, ByteOrder};
+use super::namespace::RefNamespace;
+use super::overlay::Overlay;
+use super::proxy::{ProxyView, Substitute};
+use super::reader::ContentReader;
+use super::VfsError;
+use crate::core::{FileMode, Hash, Hasher, Manifest, ManifestEntry};
+use crate::store::{ObjectStore, Repository};
 use fuser::{
-    FileAttr, FileType, Filesystem, ReplyAttr, ReplyData, ReplyDirectory, ReplyEntry,
-    Request, FUSE_ROOT_ID,
+    FileAttr, FileType, Filesystem, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEmpty,
+    ReplyEntry, ReplyWrite, Request, TimeOrNow,
 };
-use libc::{ENOENT, ENOTDIR, EISDIR};
+use libc::{EBADF, EINVAL, EIO, EISDIR, ENETUNREACH, ENOENT, ENOTDIR, ENOTEMPTY, EROFS};
+use std::collections::HashMap;
 use std::ffi::OsStr;
-use std::path::Path;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime};
 
 /// TTL for cached attributes.
 const TTL: Duration = Duration::from_secs(60);
 
-/// Patch stco/co64 offsets in moov data to denormalize them.
-///
-/// The moov is stored with offsets normalized to 0 (relative to mdat data start).
-/// When serving via FUSE, we need to add the actual mdat data position to each offset.
-fn patch_moov_offsets(moov_data: &mut [u8], meta: &Mp4Metadata, mdat_data_start: u64) {
-    if !meta.needs_offset_patching {
-        return;
-    }
-
-    // Patch stco tables (32-bit offsets)
-    for (offset_in_moov, entry_count) in &meta.stco_offsets {
-        let base = *offset_in_moov as usize;
-        for i in 0..*entry_count as usize {
-            let entry_offset = base + i * 4;
-            if entry_offset + 4 > moov_data.len() {
-                break;
-            }
-            let current = BigEndian::read_u32(&moov_data[entry_offset..entry_offset + 4]) as u64;
-            let new_value = current + mdat_data_start;
-            // Write back (assuming it fits in 32 bits)
-            if new_value <= u32::MAX as u64 {
-                BigEndian::write_u32(&mut moov_data[entry_offset..entry_offset + 4], new_value as u32);
-            }
-        }
-    }
+/// TTL for attributes in a namespace mount, where refs can move.
+const NAMESPACE_TTL: Duration = Duration::from_secs(1);
 
-    // Patch co64 tables (64-bit offsets)
-    for (offset_in_moov, entry_count) in &meta.co64_offsets {
-        let base = *offset_in_moov as usize;
-        for i in 0..*entry_count as usize {
-            let entry_offset = base + i * 8;
-            if entry_offset + 8 > moov_data.len() {
-                break;
-            }
-            let current = BigEndian::read_u64(&moov_data[entry_offset..entry_offset + 8]);
-            let new_value = current + mdat_data_start;
-            BigEndian::write_u64(&mut moov_data[entry_offset..entry_offset + 8], new_value);
-        }
-    }
-}
+/// Bytes read per step when copying a file up into the overlay.
+const COPY_UP_BLOCK: u64 = 8 * 1024 * 1024;
 
 /// Convert VfsEntry to FUSE FileAttr.
 fn entry_to_attr(entry: &VfsEntry) -> FileAttr {
@@ -95,217 +78,294 @@ fn entry_to_attr(entry: &VfsEntry) -> FileAttr {
     }
 }
 
+/// Map an I/O error to an errno for the kernel.
+fn errno(e: &std::io::Error) -> i32 {
+    e.raw_os_error().unwrap_or(EIO)
+}
+
+/// Map a failed content read to an errno, logging why it failed.
+fn read_errno(e: &VfsError) -> i32 {
+    eprintln!("Read failed: {}", e);
+    match e {
+        VfsError::RemoteUnavailable(_) => ENETUNREACH,
+        _ => EIO,
+    }
+}
+
+/// A file copied up into the overlay.
+struct UpperFile {
+    /// Open copy under the overlay's `upper/` directory.
+    file: File,
+    /// Path of the copy.
+    path: PathBuf,
+    /// Whether the copy has changes not yet stored.
+    dirty: bool,
+    /// Open write handles.
+    handles: usize,
+}
+
+/// State of a writable mount.
+struct WriteState {
+    /// Repository new content is stored in.
+    repo: Repository,
+    /// Where changes are recorded.
+    overlay: Overlay,
+    /// Files as mounted: the base manifest with the overlay applied.
+    view: Manifest,
+    /// Copied-up files by inode.
+    upper: HashMap<u64, UpperFile>,
+    /// Inode of each open write handle.
+    handles: HashMap<u64, u64>,
+    /// Next write handle number (0 is used for read-only opens).
+    next_fh: u64,
+}
+
 /// Dits FUSE filesystem handler.
 pub struct DitsFS {
     /// Virtual filesystem tree.
     tree: VfsTree,
-    /// Chunk cache.
-    cache: SyncChunkCache,
-    /// Object store for blob data (ftyp, moov).
-    object_store: Arc<ObjectStore>,
-    /// Read buffer size.
-    read_buffer_size: usize,
+    /// Reads file content through the chunk cache.
+    reader: ContentReader,
+    /// Present for writable mounts.
+    writable: Option<WriteState>,
+    /// Present for namespace mounts.
+    namespace: Option<RefNamespace>,
+    /// Content served instead of chunks, by inode.
+    substitutes: HashMap<u64, Substitute>,
 }
 
 impl DitsFS {
-    /// Create a new FUSE filesystem from a manifest.
+    /// Create a new read-only FUSE filesystem from a manifest.
     pub fn new(
         manifest: &Manifest,
         object_store: Arc<ObjectStore>,
         cache_config: CacheConfig,
     ) -> std::io::Result<Self> {
-        let tree = VfsTree::from_manifest(manifest);
-        let cache = SyncChunkCache::new(cache_config, object_store.clone())?;
+        Ok(Self {
+            tree: VfsTree::from_manifest(manifest),
+            reader: ContentReader::new(object_store, cache_config)?,
+            writable: None,
+            namespace: None,
+            substitutes: HashMap::new(),
+        })
+    }
 
+    /// Create a read-only filesystem that serves videos from the proxies
+    /// chosen in `proxies`.
+    pub fn proxied(
+        manifest: &Manifest,
+        object_store: Arc<ObjectStore>,
+        cache_config: CacheConfig,
+        proxies: &ProxyView,
+    ) -> std::io::Result<Self> {
+        let mut fs = Self::new(manifest, object_store, cache_config)?;
+        fs.substitutes = proxies.apply(&mut fs.tree)?;
+        Ok(fs)
+    }
+
+    /// Create a read-only filesystem showing every branch, tag and commit of
+    /// `repo`, all read through one chunk cache.
+    pub fn namespace(
+        repo: Repository,
+        object_store: Arc<ObjectStore>,
+        cache_config: CacheConfig,
+    ) -> std::io::Result<Self> {
+        let mut tree = VfsTree::new();
+        let namespace = RefNamespace::new(repo, &mut tree);
         Ok(Self {
             tree,
-            cache,
-            object_store,
-            read_buffer_size: 1024 * 1024, // 1MB read buffer
+            reader: ContentReader::new(object_store, cache_config)?,
+            writable: None,
+            namespace: Some(namespace),
+            substitutes: HashMap::new(),
         })
     }
 
-    /// Get file contents for a range.
-    fn read_file(&self, entry: &VfsEntry, offset: u64, size: u32) -> Option<Vec<u8>> {
-        if offset >= entry.size {
-            return Some(Vec::new());
-        }
+    /// Create a writable filesystem presenting `base` with `overlay` applied.
+    pub fn writable(
+        repo: Repository,
+        object_store: Arc<ObjectStore>,
+        overlay: Overlay,
+        base: &Manifest,
+        cache_config: CacheConfig,
+    ) -> std::io::Result<Self> {
+        let view = overlay.apply(base);
+        let mut fs = Self::new(&view, object_store, cache_config)?;
+        fs.writable = Some(WriteState {
+            repo,
+            overlay,
+            view,
+            upper: HashMap::new(),
+            handles: HashMap::new(),
+            next_fh: 1,
+        });
+        Ok(fs)
+    }
 
-        let actual_size = std::cmp::min(size as u64, entry.size - offset) as usize;
-        let mut result = Vec::with_capacity(actual_size);
+    /// Get cache statistics.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.reader.cache_stats()
+    }
 
-        // Find chunks that cover this range
-        let ranges = entry.chunks_for_range(offset, actual_size as u64);
+    /// How long the kernel may cache attributes.
+    fn ttl(&self) -> &'static Duration {
+        if self.namespace.is_some() {
+            &NAMESPACE_TTL
+        } else {
+            &TTL
+        }
+    }
 
-        // Prefetch upcoming chunks
-        if ranges.len() > 0 {
-            let chunk_idx = ranges.last().unwrap().0;
-            let prefetch_hashes: Vec<Hash> = entry.chunks
-                .iter()
-                .skip(chunk_idx + 1)
-                .take(4)
-                .map(|c| c.hash)
-                .collect();
-            self.cache.prefetch(&prefetch_hashes);
+    /// Copy a file into the overlay so it can be written, emptying it if
+    /// `truncate` is set.
+    fn copy_up(&mut self, ino: u64, truncate: bool) -> Result<(), i32> {
+        let Self { tree, reader, writable } = self;
+        let state = writable.as_mut().ok_or(EROFS)?;
+        let entry = tree.get_mut(ino).ok_or(ENOENT)?;
+        if !entry.is_file() {
+            return Err(EISDIR);
         }
 
-        for (_idx, chunk_ref, chunk_offset, read_len) in ranges {
-            // Get chunk data
-            let chunk_data = match self.cache.get(&chunk_ref.hash) {
-                Some(data) => data,
-                None => {
-                    eprintln!("Chunk not found: {} (expected size {})",
-                             chunk_ref.hash.to_hex(), chunk_ref.size);
-                    return None;
-                }
-            };
+        if let Some(upper) = state.upper.get_mut(&ino) {
+            if truncate {
+                upper.file.set_len(0).map_err(|e| errno(&e))?;
+                upper.dirty = true;
+                entry.size = 0;
+            }
+            return Ok(());
+        }
 
-            // Extract the needed portion
-            let start = chunk_offset as usize;
-            let end = start + read_len as usize;
-            if end <= chunk_data.len() {
-                result.extend_from_slice(&chunk_data[start..end]);
-            } else {
-                eprintln!(
-                    "Chunk data too short: {} vs {}..{} (hash: {}, expected: {})",
-                    chunk_data.len(),
-                    start,
-                    end,
-                    chunk_ref.hash.to_hex(),
-                    chunk_ref.size
-                );
-                return None;
+        let path = state.overlay.upper_dir().join(ino.to_string());
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .map_err(|e| errno(&e))?;
+        if truncate {
+            entry.size = 0;
+        } else {
+            let mut offset = 0;
+            while offset < entry.size {
+                let data = reader.read(entry, offset, COPY_UP_BLOCK).map_err(|e| read_errno(&e))?;
+                if data.is_empty() {
+                    break;
+                }
+                file.write_all(&data).map_err(|e| errno(&e))?;
+                offset += data.len() as u64;
             }
         }
 
-        Some(result)
+        state.upper.insert(ino, UpperFile { file, path, dirty: truncate, handles: 0 });
+        Ok(())
     }
 
-    /// Read MP4 file data, reconstructing the full structure from ftyp + moov + mdat.
-    fn read_mp4_file(&self, entry: &VfsEntry, offset: u64, size: u32) -> Option<Vec<u8>> {
-        let meta = entry.mp4_metadata.as_ref()?;
-
-        if offset >= entry.size {
-            return Some(Vec::new());
+    /// Copy a file up and register a write handle for it.
+    fn open_for_write(&mut self, ino: u64, truncate: bool) -> Result<u64, i32> {
+        self.copy_up(ino, truncate)?;
+        let state = self.writable.as_mut().ok_or(EROFS)?;
+        let fh = state.next_fh;
+        state.next_fh += 1;
+        state.handles.insert(fh, ino);
+        if let Some(upper) = state.upper.get_mut(&ino) {
+            upper.handles += 1;
         }
+        Ok(fh)
+    }
 
-        let actual_size = std::cmp::min(size as u64, entry.size - offset) as usize;
-        let mut result = Vec::with_capacity(actual_size);
-        let mut current_offset = offset;
-        let mut remaining = actual_size;
-
-        // Structure: ftyp (32 bytes) + moov + mdat header (8 bytes) + mdat data (chunks)
-        let ftyp_size: u64 = 32;
-        let moov_end = ftyp_size + meta.moov_size;
-        let mdat_header_end = moov_end + 8;
-
-        // Region 1: ftyp (0..32)
-        if current_offset < ftyp_size && remaining > 0 {
-            if let Some(ftyp_hash) = &meta.ftyp_hash {
-                if let Ok(ftyp_data) = self.object_store.load_blob(ftyp_hash) {
-                    let start = current_offset as usize;
-                    let end = std::cmp::min(ftyp_size as usize, start + remaining);
-                    if end <= ftyp_data.len() {
-                        result.extend_from_slice(&ftyp_data[start..end]);
-                        remaining -= end - start;
-                        current_offset = ftyp_size;
-                    }
-                } else {
-                    eprintln!("Failed to load ftyp blob");
-                    return None;
-                }
-            }
+    /// Re-chunk a dirty copied-up file into the object store and record it
+    /// in the overlay.
+    fn persist(&mut self, ino: u64) -> Result<(), i32> {
+        let Self { tree, writable, .. } = self;
+        let Some(state) = writable.as_mut() else {
+            return Ok(());
+        };
+        let Some(upper) = state.upper.get_mut(&ino) else {
+            return Ok(());
+        };
+        if !upper.dirty {
+            return Ok(());
+        }
+        // Unlinked while open: nothing left to record
+        let Some(path) = tree.path_of(ino) else {
+            upper.dirty = false;
+            return Ok(());
+        };
+
+        let len = upper.file.metadata().map_err(|e| errno(&e))?.len();
+        // Empty files cannot be mapped
+        let map = if len > 0 {
+            Some(unsafe { memmap2::Mmap::map(&upper.file) }.map_err(|e| errno(&e))?)
+        } else {
+            None
+        };
+        let data: &[u8] = map.as_deref().unwrap_or_default();
+
+        let template = state
+            .view
+            .get(&path)
+            .cloned()
+            .unwrap_or_else(|| ManifestEntry::new(path.clone(), 0, Hash::ZERO, Vec::new()));
+        let stored = state.repo.store_entry(&template, data).map_err(|e| {
+            eprintln!("Failed to store {}: {}", path, e);
+            EIO
+        })?;
+
+        if let Some(entry) = tree.get_mut(ino) {
+            entry.set_content(&stored);
         }
+        state.view.add(stored.clone());
+        state.overlay.put(stored);
+        state.overlay.save().map_err(|e| errno(&e))?;
+        upper.dirty = false;
+        Ok(())
+    }
 
-        // Region 2: moov (32..32+moov_size)
-        if current_offset < moov_end && remaining > 0 {
-            if let Some(moov_hash) = &meta.moov_hash {
-                if let Ok(moov_data) = self.object_store.load_blob(moov_hash) {
-                    // Clone and patch the moov data to denormalize offsets
-                    // The mdat data starts right after: ftyp + moov + mdat_header(8)
-                    let mdat_data_start = ftyp_size + meta.moov_size + 8;
-                    let mut patched_moov = moov_data.clone();
-                    patch_moov_offsets(&mut patched_moov, meta, mdat_data_start);
-
-                    let moov_offset = current_offset.saturating_sub(ftyp_size) as usize;
-                    let moov_remaining = (moov_end - current_offset) as usize;
-                    let to_read = std::cmp::min(moov_remaining, remaining);
-                    let end = moov_offset + to_read;
-                    if end <= patched_moov.len() {
-                        result.extend_from_slice(&patched_moov[moov_offset..end]);
-                        remaining -= to_read;
-                        current_offset += to_read as u64;
-                    } else {
-                        eprintln!("moov data too short: {} vs {}..{}", patched_moov.len(), moov_offset, end);
-                        return None;
-                    }
-                } else {
-                    eprintln!("Failed to load moov blob");
-                    return None;
+    /// Store a file and drop its overlay copy once no handle has it open.
+    fn close_upper(&mut self, ino: u64) -> Result<(), i32> {
+        let result = self.persist(ino);
+        if let Some(state) = self.writable.as_mut() {
+            if state.upper.get(&ino).is_some_and(|u| u.handles == 0 && !u.dirty) {
+                if let Some(upper) = state.upper.remove(&ino) {
+                    let _ = std::fs::remove_file(&upper.path);
                 }
             }
         }
+        result
+    }
 
-        // Region 3: mdat header (moov_end..moov_end+8 or moov_end+16 for extended size)
-        if current_offset < mdat_header_end && remaining > 0 {
-            let mdat_total_size = meta.mdat_size + 8;
-
-            // For files > 4GB, use extended size (64-bit) header
-            let mdat_header: Vec<u8> = if mdat_total_size > u32::MAX as u64 {
-                // Extended size: size=1 (4 bytes) + "mdat" (4 bytes) + actual_size (8 bytes)
-                let mut header = Vec::with_capacity(16);
-                header.extend_from_slice(&1u32.to_be_bytes()); // size = 1 means extended
-                header.extend_from_slice(b"mdat");
-                header.extend_from_slice(&(mdat_total_size + 8).to_be_bytes()); // +8 for extended header
-                header
-            } else {
-                // Standard header: size (4 bytes) + "mdat" (4 bytes)
-                let mut header = Vec::with_capacity(8);
-                header.extend_from_slice(&(mdat_total_size as u32).to_be_bytes());
-                header.extend_from_slice(b"mdat");
-                header
-            };
-
-            let header_offset = (current_offset - moov_end) as usize;
-            let to_read = std::cmp::min(mdat_header.len() - header_offset, remaining);
-            if header_offset < mdat_header.len() {
-                result.extend_from_slice(&mdat_header[header_offset..header_offset + to_read]);
-                remaining -= to_read;
-                current_offset += to_read as u64;
-            }
+    /// Record a mode change; only the executable bit is tracked.
+    fn set_mode(&mut self, ino: u64, mode: u32) -> Result<(), i32> {
+        let Self { tree, writable, .. } = self;
+        let state = writable.as_mut().ok_or(EROFS)?;
+        let entry = tree.get_mut(ino).ok_or(ENOENT)?;
+        entry.mode = mode & 0o777;
+        if !entry.is_file() {
+            return Ok(());
         }
 
-        // Region 4: mdat data (chunks)
-        if current_offset >= mdat_header_end && remaining > 0 {
-            let chunk_offset = current_offset - mdat_header_end;
-            let ranges = entry.chunks_for_range(chunk_offset, remaining as u64);
-
-            for (_idx, chunk_ref, chunk_off, read_len) in ranges {
-                let chunk_data = match self.cache.get(&chunk_ref.hash) {
-                    Some(data) => data,
-                    None => {
-                        eprintln!("MP4 chunk not found: {}", chunk_ref.hash.to_hex());
-                        return None;
-                    }
-                };
-
-                let start = chunk_off as usize;
-                let end = start + read_len as usize;
-                if end <= chunk_data.len() {
-                    result.extend_from_slice(&chunk_data[start..end]);
-                } else {
-                    eprintln!("MP4 chunk data too short: {} vs {}..{}", chunk_data.len(), start, end);
-                    return None;
-                }
+        let path = tree.path_of(ino).ok_or(ENOENT)?;
+        if let Some(mut manifest_entry) = state.view.get(&path).cloned() {
+            let mode = if mode & 0o111 != 0 { FileMode::Executable } else { FileMode::Regular };
+            if manifest_entry.mode != mode && manifest_entry.mode != FileMode::Symlink {
+                manifest_entry.mode = mode;
+                state.view.add(manifest_entry.clone());
+                state.overlay.put(manifest_entry);
+                state.overlay.save().map_err(|e| errno(&e))?;
             }
         }
-
-        Some(result)
+        Ok(())
     }
 
-    /// Get cache statistics.
-    pub fn cache_stats(&self) -> super::cache::CacheStats {
-        self.cache.stats()
+    /// Repository path of `name` inside directory `parent`.
+    fn child_path(&self, parent: u64, name: &str) -> Option<String> {
+        let parent_path = self.tree.path_of(parent)?;
+        Some(if parent_path.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", parent_path, name)
+        })
     }
 }
 
@@ -314,9 +374,16 @@ impl Filesystem for DitsFS {
     fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
         let name_str = name.to_string_lossy();
 
+        if let Some(namespace) = self.namespace.as_mut() {
+            if let Err(e) = namespace.prepare_lookup(&mut self.tree, parent, &name_str) {
+                reply.error(read_errno(&e));
+                return;
+            }
+        }
+
         if let Some(entry) = self.tree.lookup_child(parent, &name_str) {
             let attr = entry_to_attr(entry);
-            reply.entry(&TTL, &attr, 0);
+            reply.entry(self.ttl(), &attr, 0);
         } else {
             reply.error(ENOENT);
         }
@@ -326,12 +393,66 @@ impl Filesystem for DitsFS {
     fn getattr(&mut self, _req: &Request, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
         if let Some(entry) = self.tree.get(ino) {
             let attr = entry_to_attr(entry);
-            reply.attr(&TTL, &attr);
+            reply.attr(self.ttl(), &attr);
         } else {
             reply.error(ENOENT);
         }
     }
 
+    /// Change file attributes (truncate and mode).
+    fn setattr(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        mode: Option<u32>,
+        _uid: Option<u32>,
+        _gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        _fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        if let Some(size) = size {
+            if let Err(e) = self.copy_up(ino, size == 0) {
+                reply.error(e);
+                return;
+            }
+            if let Some(upper) = self.writable.as_mut().and_then(|s| s.upper.get_mut(&ino)) {
+                if let Err(e) = upper.file.set_len(size) {
+                    reply.error(errno(&e));
+                    return;
+                }
+                upper.dirty = true;
+            }
+            if let Some(entry) = self.tree.get_mut(ino) {
+                entry.size = size;
+            }
+            // A truncate outside an open handle has no release to store it
+            if let Err(e) = self.close_upper(ino) {
+                reply.error(e);
+                return;
+            }
+        }
+
+        if let Some(mode) = mode {
+            if let Err(e) = self.set_mode(ino, mode) {
+                reply.error(e);
+                return;
+            }
+        }
+
+        match self.tree.get(ino) {
+            Some(entry) => reply.attr(&TTL, &entry_to_attr(entry)),
+            None => reply.error(ENOENT),
+        }
+    }
+
     /// Read directory contents.
     fn readdir(
         &mut self,
@@ -341,6 +462,14 @@ impl Filesystem for DitsFS {
         offset: i64,
         mut reply: ReplyDirectory,
     ) {
+        // Later pages of a listing must match the first
+        if let (Some(namespace), 0) = (self.namespace.as_mut(), offset) {
+            if let Err(e) = namespace.prepare(&mut self.tree, ino) {
+                reply.error(read_errno(&e));
+                return;
+            }
+        }
+
         if let Some(entries) = self.tree.readdir(ino) {
             for (i, (name, inode, entry_type)) in entries.iter().enumerate().skip(offset as usize) {
                 let file_type = match entry_type {
@@ -372,44 +501,298 @@ impl Filesystem for DitsFS {
         _lock_owner: Option<u64>,
         reply: ReplyData,
     ) {
+        // Files being written are read from their overlay copy
+        if let Some(upper) = self.writable.as_ref().and_then(|s| s.upper.get(&ino)) {
+            let mut data = vec![0u8; size as usize];
+            match upper.file.read_at(&mut data, offset as u64) {
+                Ok(n) => {
+                    data.truncate(n);
+                    reply.data(&data);
+                }
+                Err(e) => reply.error(errno(&e)),
+            }
+            return;
+        }
+
+        if let Some(substitute) = self.substitutes.get(&ino) {
+            match substitute.read(offset as u64, size as u64) {
+                Ok(data) => reply.data(&data),
+                Err(e) => reply.error(errno(&e)),
+            }
+            return;
+        }
+
         if let Some(entry) = self.tree.get(ino) {
             if !entry.is_file() {
                 reply.error(EISDIR);
                 return;
             }
 
-            // Use MP4-specific reader for files with MP4 metadata
-            let data = if entry.is_mp4() {
-                self.read_mp4_file(entry, offset as u64, size)
-            } else {
-                self.read_file(entry, offset as u64, size)
-            };
-
-            if let Some(data) = data {
-                reply.data(&data);
-            } else {
-                reply.error(libc::EIO);
+            match self.reader.read(entry, offset as u64, size as u64) {
+                Ok(data) => reply.data(&data),
+                Err(e) => reply.error(read_errno(&e)),
             }
         } else {
             reply.error(ENOENT);
         }
     }
 
-    /// Open a file (no-op for read-only fs).
-    fn open(&mut self, _req: &Request, ino: u64, _flags: i32, reply: fuser::ReplyOpen) {
-        if let Some(entry) = self.tree.get(ino) {
-            if entry.is_file() {
-                // Use direct_io to disable kernel caching (we do our own)
-                reply.opened(0, fuser::consts::FOPEN_DIRECT_IO);
-            } else {
-                reply.error(EISDIR);
+    /// Write file data into the file's overlay copy.
+    fn write(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        let Some(state) = self.writable.as_mut() else {
+            reply.error(EROFS);
+            return;
+        };
+        let Some(upper) = state.upper.get_mut(&ino) else {
+            reply.error(EBADF);
+            return;
+        };
+        if let Err(e) = upper.file.write_all_at(data, offset as u64) {
+            reply.error(errno(&e));
+            return;
+        }
+        upper.dirty = true;
+
+        if let Some(entry) = self.tree.get_mut(ino) {
+            entry.size = entry.size.max(offset as u64 + data.len() as u64);
+            entry.mtime = SystemTime::now();
+        }
+        reply.written(data.len() as u32);
+    }
+
+    /// Store a written file when a handle to it is closed.
+    fn flush(&mut self, _req: &Request, ino: u64, _fh: u64, _lock_owner: u64, reply: ReplyEmpty) {
+        match self.persist(ino) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    /// Store a written file.
+    fn fsync(&mut self, _req: &Request, ino: u64, _fh: u64, _datasync: bool, reply: ReplyEmpty) {
+        match self.persist(ino) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    /// Release an open handle, storing the file if it was written.
+    fn release(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        if let Some(state) = self.writable.as_mut() {
+            if state.handles.remove(&fh).is_some() {
+                if let Some(upper) = state.upper.get_mut(&ino) {
+                    upper.handles = upper.handles.saturating_sub(1);
+                }
+            }
+        }
+        match self.close_upper(ino) {
+            Ok(()) => reply.ok(),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    /// Open a file, copying it into the overlay if it is opened for writing.
+    fn open(&mut self, _req: &Request, ino: u64, flags: i32, reply: fuser::ReplyOpen) {
+        match self.tree.get(ino) {
+            Some(entry) if entry.is_file() => {}
+            Some(_) => return reply.error(EISDIR),
+            None => return reply.error(ENOENT),
+        }
+
+        // Use direct_io to disable kernel caching (we do our own)
+        if flags & libc::O_ACCMODE == libc::O_RDONLY {
+            reply.opened(0, fuser::consts::FOPEN_DIRECT_IO);
+            return;
+        }
+        match self.open_for_write(ino, flags & libc::O_TRUNC != 0) {
+            Ok(fh) => reply.opened(fh, fuser::consts::FOPEN_DIRECT_IO),
+            Err(e) => reply.error(e),
+        }
+    }
+
+    /// Create and open a new file.
+    fn create(
+        &mut self,
+        _req: &Request,
+        parent: u64,
+        name: &OsStr,
+        mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        if self.writable.is_none() {
+            reply.error(EROFS);
+            return;
+        }
+
+        let empty = ManifestEntry::new(String::new(), 0, Hasher::hash(&[]), Vec::new());
+        let mut entry = VfsEntry::file(name.to_string_lossy().into_owned(), 0, 0, &empty);
+        entry.mode = mode & 0o777;
+        let Some(ino) = self.tree.insert(parent, entry) else {
+            reply.error(ENOTDIR);
+            return;
+        };
+
+        match self.open_for_write(ino, true) {
+            Ok(fh) => {
+                let attr = self.tree.get(ino).map(entry_to_attr);
+                match attr {
+                    Some(attr) => reply.created(&TTL, &attr, 0, fh, fuser::consts::FOPEN_DIRECT_IO),
+                    None => reply.error(ENOENT),
+                }
+            }
+            Err(e) => reply.error(e),
+        }
+    }
+
+    /// Create a directory. Directories exist only while they hold files.
+    fn mkdir(
+        &mut self,
+        _req: &Request,
+        parent: u64,
+        name: &OsStr,
+        mode: u32,
+        _umask: u32,
+        reply: ReplyEntry,
+    ) {
+        if self.writable.is_none() {
+            reply.error(EROFS);
+            return;
+        }
+
+        let mut entry = VfsEntry::directory(name.to_string_lossy().into_owned(), 0, 0);
+        entry.mode = mode & 0o777;
+        match self.tree.insert(parent, entry).and_then(|ino| self.tree.get(ino)) {
+            Some(entry) => reply.entry(&TTL, &entry_to_attr(entry), 0),
+            None => reply.error(ENOTDIR),
+        }
+    }
+
+    /// Delete a file.
+    fn unlink(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        let Some(state) = self.writable.as_mut() else {
+            reply.error(EROFS);
+            return;
+        };
+        let Some(entry) = self.tree.lookup_child(parent, &name.to_string_lossy()) else {
+            reply.error(ENOENT);
+            return;
+        };
+        if entry.is_dir() {
+            reply.error(EISDIR);
+            return;
+        }
+
+        let ino = entry.inode;
+        if let Some(path) = self.tree.path_of(ino) {
+            state.overlay.remove(&mut state.view, &path);
+            if let Err(e) = state.overlay.save() {
+                reply.error(errno(&e));
+                return;
             }
+        }
+        self.tree.remove(ino);
+        reply.ok();
+    }
+
+    /// Delete an empty directory.
+    fn rmdir(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEmpty) {
+        if self.writable.is_none() {
+            reply.error(EROFS);
+            return;
+        }
+        let Some(entry) = self.tree.lookup_child(parent, &name.to_string_lossy()) else {
+            reply.error(ENOENT);
+            return;
+        };
+        if !entry.is_dir() {
+            reply.error(ENOTDIR);
+        } else if !entry.children.is_empty() {
+            reply.error(ENOTEMPTY);
         } else {
+            let ino = entry.inode;
+            self.tree.remove(ino);
+            reply.ok();
+        }
+    }
+
+    /// Move a file or directory, replacing any file at the destination.
+    fn rename(
+        &mut self,
+        _req: &Request,
+        parent: u64,
+        name: &OsStr,
+        newparent: u64,
+        newname: &OsStr,
+        flags: u32,
+        reply: ReplyEmpty,
+    ) {
+        if self.writable.is_none() {
+            reply.error(EROFS);
+            return;
+        }
+        // RENAME_EXCHANGE and RENAME_NOREPLACE are not supported
+        if flags != 0 {
+            reply.error(EINVAL);
+            return;
+        }
+
+        let newname = newname.to_string_lossy();
+        let Some(ino) = self.tree.lookup_child(parent, &name.to_string_lossy()).map(|e| e.inode) else {
             reply.error(ENOENT);
+            return;
+        };
+        if let Some(target) = self.tree.lookup_child(newparent, &newname) {
+            if target.is_dir() && !target.children.is_empty() {
+                reply.error(ENOTEMPTY);
+                return;
+            }
+        }
+        let (Some(from), Some(to)) = (self.tree.path_of(ino), self.child_path(newparent, &newname)) else {
+            reply.error(ENOENT);
+            return;
+        };
+
+        let Some(state) = self.writable.as_mut() else {
+            reply.error(EROFS);
+            return;
+        };
+        state.overlay.remove(&mut state.view, &to);
+        state.overlay.rename(&mut state.view, &from, &to);
+        if let Err(e) = state.overlay.save() {
+            reply.error(errno(&e));
+            return;
+        }
+
+        if self.tree.rename(ino, newparent, &newname) {
+            reply.ok();
+        } else {
+            reply.error(ENOTDIR);
         }
     }
 
-    /// Open a directory (no-op for read-only fs).
+    /// Open a directory.
     fn opendir(&mut self, _req: &Request, ino: u64, _flags: i32, reply: fuser::ReplyOpen) {
         if let Some(entry) = self.tree.get(ino) {
             if entry.is_dir() {
@@ -437,7 +820,7 @@ impl Filesystem for DitsFS {
     }
 }
 
-/// Mount a Dits repository as a FUSE filesystem.
+/// Mount a Dits repository as a read-only FUSE filesystem.
 ///
 /// This function blocks until the filesystem is unmounted.
 pub fn mount(
@@ -446,19 +829,70 @@ pub fn mount(
     mount_point: &Path,
     cache_config: CacheConfig,
 ) -> Result<(), super::VfsError> {
+    let fs = DitsFS::new(manifest, object_store, cache_config)
+        .map_err(|e| super::VfsError::Mount(format!("Failed to create filesystem: {}", e)))?;
+    run(fs, mount_point, fuser::MountOption::RO)
+}
+
+/// Mount a Dits repository as a read-only FUSE filesystem that serves
+/// videos from their proxies.
+///
+/// This function blocks until the filesystem is unmounted.
+pub fn mount_proxied(
+    manifest: &Manifest,
+    object_store: Arc<ObjectStore>,
+    mount_point: &Path,
+    cache_config: CacheConfig,
+    proxies: &ProxyView,
+) -> Result<(), super::VfsError> {
+    let fs = DitsFS::proxied(manifest, object_store, cache_config, proxies)
+        .map_err(|e| super::VfsError::Mount(format!("Failed to create filesystem: {}", e)))?;
+    run(fs, mount_point, fuser::MountOption::RO)
+}
+
+/// Mount every branch, tag and commit of a repository as a read-only FUSE
+/// filesystem.
+///
+/// This function blocks until the filesystem is unmounted.
+pub fn mount_namespace(
+    repo: Repository,
+    object_store: Arc<ObjectStore>,
+    mount_point: &Path,
+    cache_config: CacheConfig,
+) -> Result<(), super::VfsError> {
+    let fs = DitsFS::namespace(repo, object_store, cache_config)
+        .map_err(|e| super::VfsError::Mount(format!("Failed to create filesystem: {}", e)))?;
+    run(fs, mount_point, fuser::MountOption::RO)
+}
+
+/// Mount a branch as a writable FUSE filesystem whose changes are kept in
+/// `overlay`.
+///
+/// This function blocks until the filesystem is unmounted.
+pub fn mount_writable(
+    repo: Repository,
+    object_store: Arc<ObjectStore>,
+    overlay: Overlay,
+    base: &Manifest,
+    mount_point: &Path,
+    cache_config: CacheConfig,
+) -> Result<(), super::VfsError> {
+    let fs = DitsFS::writable(repo, object_store, overlay, base, cache_config)
+        .map_err(|e| super::VfsError::Mount(format!("Failed to create filesystem: {}", e)))?;
+    run(fs, mount_point, fuser::MountOption::RW)
+}
+
+/// Mount a filesystem and serve it until unmounted.
+fn run(fs: DitsFS, mount_point: &Path, access: fuser::MountOption) -> Result<(), super::VfsError> {
     // Ensure mount point exists
     if !mount_point.exists() {
         std::fs::create_dir_all(mount_point)
             .map_err(|e| super::VfsError::Mount(format!("Failed to create mount point: {}", e)))?;
     }
 
-    // Create filesystem
-    let fs = DitsFS::new(manifest, object_store, cache_config)
-        .map_err(|e| super::VfsError::Mount(format!("Failed to create filesystem: {}", e)))?;
-
     // Mount options
     let options = vec![
-        fuser::MountOption::RO,           // Read-only
+        access,
         fuser::MountOption::FSName("dits".to_string()),
         fuser::MountOption::AutoUnmount,  // Unmount on process exit
         fuser::MountOption::AllowOther,   // Allow other users (requires user_allow_other in fuse.conf)
@@ -526,6 +960,7 @@ pub fn unmount(mount_point: &Path) -> Result<(), super::VfsError> {
 mod tests {
     use super::*;
     use crate::core::{Chunk, ChunkRef, ManifestEntry};
+    use crate::vfs::OverlayBase;
     use tempfile::tempdir;
 
     fn create_test_manifest() -> (Manifest, ObjectStore, tempfile::TempDir) {
@@ -574,7 +1009,7 @@ mod tests {
 
     #[test]
     fn test_tree_structure() {
-        let (manifest, store, _temp) = create_test_manifest();
+        let (manifest, _store, _temp) = create_test_manifest();
         let tree = VfsTree::from_manifest(&manifest);
 
         // Check root
@@ -592,4 +1027,52 @@ mod tests {
         assert!(nested.is_file());
         assert_eq!(nested.size, 1000);
     }
+
+    #[test]
+    fn test_write_records_overlay_entry() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        std::fs::write(temp.path().join("clip.bin"), vec![7u8; 4096]).unwrap();
+        repo.add("clip.bin").unwrap();
+        let commit = repo.commit("add clip").unwrap();
+        let base = repo.load_manifest(&commit.manifest).unwrap();
+
+        let overlay = Overlay::create(
+            repo.dits_dir(),
+            &OverlayBase {
+                branch: "main".to_string(),
+                commit: commit.hash,
+                mount_point: temp.path().join("mnt"),
+            },
+        )
+        .unwrap();
+        let store = Arc::new(Repository::open(temp.path()).unwrap().into_object_store());
+        let config = CacheConfig {
+            l2_path: temp.path().join("cache"),
+            ..Default::default()
+        };
+        let mut fs = DitsFS::writable(repo, store, overlay, &base, config).unwrap();
+
+        let ino = fs.tree.lookup(Path::new("clip.bin")).unwrap().inode;
+        let fh = fs.open_for_write(ino, false).unwrap();
+        {
+            // What write() and release() do for the handle
+            let state = fs.writable.as_mut().unwrap();
+            let upper = state.upper.get_mut(&ino).unwrap();
+            upper.file.write_all_at(b"edit", 4096).unwrap();
+            upper.dirty = true;
+            upper.handles -= 1;
+            state.handles.remove(&fh);
+        }
+        fs.close_upper(ino).unwrap();
+        assert!(fs.writable.as_ref().unwrap().upper.is_empty());
+
+        let entry = fs.tree.get(ino).unwrap();
+        assert_eq!(fs.reader.read(entry, 4094, 10).unwrap(), b"\x07\x07edit");
+
+        let overlay = Overlay::open(&temp.path().join(".dits")).unwrap().unwrap();
+        let changes = overlay.changes(&base);
+        assert_eq!(changes.modified, ["clip.bin"]);
+        assert_eq!(overlay.apply(&base).get("clip.bin").unwrap().size, 4100);
+    }
 }
@@ -4,6 +4,12 @@
 //! - Mount a repository as a virtual filesystem
 //! - Files appear with full size but are fetched on-demand
 //! - Chunks are cached locally for fast repeated access
+//! - Lazy mounts download chunks missing from a partial clone from its remote
+//! - Proxy mounts serve video proxies under the originals' names
+//! - Namespace mounts show every branch, tag and commit side by side
+//! - Writable mounts keep edits in a copy-on-write overlay that `dits commit`
+//!   turns into a commit
+//! - The WebDAV server serves the same trees to clients without FUSE
 //!
 //! ## Architecture
 //!
@@ -36,15 +42,27 @@
 
 mod cache;
 mod entry;
+#[cfg(feature = "fuser")]
+mod namespace;
+mod overlay;
+#[cfg(feature = "fuser")]
+mod proxy;
+mod reader;
+mod remote;
+mod webdav;
 
 #[cfg(feature = "fuser")]
 mod fuse;
 
 #[allow(unused_imports)]
 pub use {cache::{CacheConfig, ChunkCache}, entry::{VfsEntry, VfsEntryType, VfsTree}};
+pub use overlay::{Overlay, OverlayBase};
+pub use webdav::WebDav;
 
 #[cfg(feature = "fuser")]
-pub use fuse::{DitsFS, mount, unmount};
+pub use fuse::{DitsFS, mount, mount_namespace, mount_proxied, mount_writable, unmount};
+#[cfg(feature = "fuser")]
+pub use {proxy::{ProxyFile, ProxyView}, remote::{HttpSource, ObjectSource}};
 
 /// Errors from VFS operations.
 #[derive(Debug, thiserror::Error)]
@@ -64,6 +82,12 @@ pub enum VfsError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Remote unavailable: {0}")]
+    RemoteUnavailable(String),
+
+    #[error("Repository error: {0}")]
+    Repository(String),
+
     #[error("Cache error: {0}")]
     Cache(String),
 
@@ -0,0 +1,263 @@
+//! Multi-ref namespace for mounts.
+//!
+//! A namespace mount shows every version of a repository side by side:
+//!
+//! ```text
+//! /branches/<name>/...
+//! /tags/<name>/...
+//! /commits/<short-hash>/...
+//! ```
+//!
+//! Ref directories are listed from the refs but only filled with their
+//! commit's files when first entered, so a repository with hundreds of
+//! branches mounts instantly. Any commit can be opened under `/commits` by a
+//! hash prefix of at least six characters, whether it is listed or not.
+//!
+//! The branch and tag listings are re-read whenever they are looked up in.
+//! A ref that moved gets a fresh directory; the old one is detached rather
+//! than dropped, so files opened from it keep reading the version they
+//! were opened at.
+
+use super::entry::{VfsEntry, VfsTree};
+use super::VfsError;
+use crate::core::Hash;
+use crate::store::Repository;
+use std::collections::HashMap;
+
+/// Shortest commit prefix accepted under `/commits`.
+const MIN_PREFIX_LEN: usize = 6;
+
+/// Hex digits in the names of listed commit directories.
+const SHORT_HASH_LEN: usize = 8;
+
+/// Kind of ref a directory shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RefKind {
+    Branch,
+    Tag,
+    Commit,
+}
+
+/// A directory showing one commit.
+#[derive(Debug)]
+struct RefDir {
+    /// Commit whose files the directory holds.
+    commit: Hash,
+    /// Whether the commit's files have been added yet.
+    built: bool,
+}
+
+/// Branches, tags and commits of a repository as lazily built subtrees of
+/// one [`VfsTree`].
+pub struct RefNamespace {
+    repo: Repository,
+    /// Inode of `/branches`.
+    branches: u64,
+    /// Inode of `/tags`.
+    tags: u64,
+    /// Inode of `/commits`.
+    commits: u64,
+    /// Ref directories by inode.
+    dirs: HashMap<u64, RefDir>,
+}
+
+impl RefNamespace {
+    /// Create the top-level directories in the root of `tree`.
+    pub fn new(repo: Repository, tree: &mut VfsTree) -> Self {
+        let root = tree.root_inode;
+        let mut top = |name: &str| {
+            tree.insert(root, VfsEntry::directory(name.to_string(), 0, 0))
+                .expect("root is a directory")
+        };
+        let (branches, tags, commits) = (top("branches"), top("tags"), top("commits"));
+        Self {
+            repo,
+            branches,
+            tags,
+            commits,
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// Bring directory `inode` up to date before it is listed or searched.
+    pub fn prepare(&mut self, tree: &mut VfsTree, inode: u64) -> Result<(), VfsError> {
+        if inode == self.branches {
+            self.sync(tree, RefKind::Branch)
+        } else if inode == self.tags {
+            self.sync(tree, RefKind::Tag)
+        } else if inode == self.commits {
+            self.list_tips(tree)
+        } else {
+            self.build(tree, inode)
+        }
+    }
+
+    /// Bring directory `parent` up to date before `name` is looked up in it.
+    ///
+    /// Under `/commits`, a name that is a commit prefix gets a directory
+    /// even if it was never listed.
+    pub fn prepare_lookup(&mut self, tree: &mut VfsTree, parent: u64, name: &str) -> Result<(), VfsError> {
+        if parent != self.commits {
+            return self.prepare(tree, parent);
+        }
+        if tree.lookup_child(parent, name).is_some() {
+            return Ok(());
+        }
+        if name.len() < MIN_PREFIX_LEN || !name.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f')) {
+            return Ok(());
+        }
+        if let Some(commit) = self.repo.find_commit_by_prefix(name).map_err(repo_error)? {
+            self.add_dir(tree, RefKind::Commit, name, commit);
+        }
+        Ok(())
+    }
+
+    /// Make the directory of `kind` list exactly the current refs.
+    fn sync(&mut self, tree: &mut VfsTree, kind: RefKind) -> Result<(), VfsError> {
+        let refs = self.repo.refs();
+        let names = match kind {
+            RefKind::Branch => refs.list_branches()?,
+            _ => refs.list_tags()?,
+        };
+        let mut current = HashMap::new();
+        for name in names {
+            let tip = match kind {
+                RefKind::Branch => refs.get_branch(&name)?,
+                _ => refs.get_tag(&name)?,
+            };
+            if let Some(tip) = tip {
+                current.insert(name, tip);
+            }
+        }
+
+        let parent = self.parent_of(kind);
+        let listed: Vec<(String, u64)> = tree
+            .get(parent)
+            .map(|dir| dir.children.iter().map(|(name, inode)| (name.clone(), *inode)).collect())
+            .unwrap_or_default();
+        for (name, inode) in listed {
+            let shown = self.dirs.get(&inode).map(|dir| dir.commit);
+            if shown.is_some() && current.get(&name) == shown.as_ref() {
+                current.remove(&name);
+            } else {
+                tree.detach(inode);
+            }
+        }
+        for (name, tip) in current {
+            self.add_dir(tree, kind, &name, tip);
+        }
+        Ok(())
+    }
+
+    /// List the commits at the tip of each branch and tag under `/commits`.
+    fn list_tips(&mut self, tree: &mut VfsTree) -> Result<(), VfsError> {
+        let refs = self.repo.refs();
+        let mut tips = Vec::new();
+        for name in refs.list_branches()? {
+            tips.extend(refs.get_branch(&name)?);
+        }
+        for name in refs.list_tags()? {
+            tips.extend(refs.get_tag(&name)?);
+        }
+
+        for tip in tips {
+            let name = tip.to_hex()[..SHORT_HASH_LEN].to_string();
+            if tree.lookup_child(self.commits, &name).is_none() {
+                self.add_dir(tree, RefKind::Commit, &name, tip);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add an empty directory for a commit; its files are added on first use.
+    fn add_dir(&mut self, tree: &mut VfsTree, kind: RefKind, name: &str, commit: Hash) {
+        let parent = self.parent_of(kind);
+        if let Some(inode) = tree.insert(parent, VfsEntry::directory(name.to_string(), 0, 0)) {
+            self.dirs.insert(inode, RefDir { commit, built: false });
+        }
+    }
+
+    /// Add the files of the commit a ref directory shows, if not done yet.
+    fn build(&mut self, tree: &mut VfsTree, inode: u64) -> Result<(), VfsError> {
+        let Some(dir) = self.dirs.get_mut(&inode) else {
+            return Ok(());
+        };
+        if dir.built {
+            return Ok(());
+        }
+
+        let commit = self.repo.load_commit(&dir.commit).map_err(repo_error)?;
+        let manifest = self.repo.load_manifest(&commit.manifest).map_err(repo_error)?;
+        tree.graft(inode, &manifest);
+        dir.built = true;
+        Ok(())
+    }
+
+    /// Directory holding the ref directories of `kind`.
+    fn parent_of(&self, kind: RefKind) -> u64 {
+        match kind {
+            RefKind::Branch => self.branches,
+            RefKind::Tag => self.tags,
+            RefKind::Commit => self.commits,
+        }
+    }
+}
+
+/// Report a repository failure as a VFS error.
+fn repo_error(e: impl std::fmt::Display) -> VfsError {
+    VfsError::Repository(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_refs_are_built_lazily_and_follow_moves() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        std::fs::write(temp.path().join("cut.txt"), b"first cut").unwrap();
+        repo.add("cut.txt").unwrap();
+        let first = repo.commit("first cut").unwrap();
+        repo.refs().set_tag("v1", &first.hash).unwrap();
+
+        let mut tree = VfsTree::new();
+        let mut namespace = RefNamespace::new(Repository::open(temp.path()).unwrap(), &mut tree);
+        namespace.prepare(&mut tree, namespace.branches).unwrap();
+        let main = tree.lookup(Path::new("branches/main")).unwrap().inode;
+        // Nothing is read until the directory is entered
+        assert!(tree.lookup(Path::new("branches/main/cut.txt")).is_none());
+        namespace.prepare(&mut tree, main).unwrap();
+        let old_file = tree.lookup(Path::new("branches/main/cut.txt")).unwrap().inode;
+        assert_eq!(tree.get(old_file).unwrap().size, 9);
+
+        std::fs::write(temp.path().join("cut.txt"), b"second cut, longer").unwrap();
+        repo.add("cut.txt").unwrap();
+        let second = repo.commit("second cut").unwrap();
+
+        // The branch directory follows the branch; the tag stays put
+        namespace.prepare_lookup(&mut tree, namespace.branches, "main").unwrap();
+        let moved = tree.lookup(Path::new("branches/main")).unwrap().inode;
+        assert_ne!(moved, main);
+        namespace.prepare(&mut tree, moved).unwrap();
+        assert_eq!(tree.lookup(Path::new("branches/main/cut.txt")).unwrap().size, 18);
+        assert_eq!(tree.get(old_file).unwrap().size, 9);
+
+        namespace.prepare(&mut tree, namespace.tags).unwrap();
+        let v1 = tree.lookup(Path::new("tags/v1")).unwrap().inode;
+        namespace.prepare(&mut tree, v1).unwrap();
+        assert_eq!(tree.lookup(Path::new("tags/v1/cut.txt")).unwrap().size, 9);
+
+        // Tips are listed by short hash, other commits open by prefix
+        namespace.prepare(&mut tree, namespace.commits).unwrap();
+        let short = &second.hash.to_hex()[..8];
+        assert!(tree.lookup(&Path::new("commits").join(short)).is_some());
+        let prefix = &first.hash.to_hex()[..10];
+        namespace.prepare_lookup(&mut tree, namespace.commits, prefix).unwrap();
+        assert!(tree.lookup(&Path::new("commits").join(prefix)).is_some());
+        namespace.prepare_lookup(&mut tree, namespace.commits, "zzzzzz").unwrap();
+        assert!(tree.lookup(Path::new("commits/zzzzzz")).is_none());
+    }
+}
@@ -0,0 +1,301 @@
+//! Copy-on-write overlay for writable mounts.
+//!
+//! A writable mount never touches the working directory. Changes made
+//! through it are kept under `.dits/mount/`:
+//!
+//! - `base.json`: the branch being edited and the commit the overlay
+//!   applies to. `dits commit` advances it.
+//! - `entries.json`: changed paths, each mapped to the manifest entry of
+//!   its new content, or to `null` once deleted. Only the mount writes it.
+//! - `upper/`: full copies of files open for writing. They are re-chunked
+//!   into the object store on flush and removed once closed.
+//!
+//! The mounted tree is the base manifest with the entries applied. Because
+//! the base and the entries live in separate files, `dits commit` can run
+//! while the mount is live: after the base advances, entries that match it
+//! no longer show up as changes.
+
+use crate::core::{Hash, Manifest, ManifestEntry};
+use crate::store::repository::RepoError;
+use crate::store::Repository;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What an overlay applies to.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OverlayBase {
+    /// Branch that commits from the mount update.
+    pub branch: String,
+    /// Commit the overlay entries apply to.
+    pub commit: Hash,
+    /// Where the overlay is mounted.
+    pub mount_point: PathBuf,
+}
+
+/// Paths an overlay changes relative to its base.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct OverlayChanges {
+    /// Files not in the base.
+    pub added: Vec<String>,
+    /// Files whose content or mode differs from the base.
+    pub modified: Vec<String>,
+    /// Base files removed.
+    pub deleted: Vec<String>,
+}
+
+impl OverlayChanges {
+    /// Whether the overlay matches its base.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.deleted.is_empty()
+    }
+
+    /// Number of changed paths.
+    pub fn len(&self) -> usize {
+        self.added.len() + self.modified.len() + self.deleted.len()
+    }
+}
+
+/// A writable mount's changes, stored under `.dits/mount/`.
+pub struct Overlay {
+    /// Overlay directory.
+    dir: PathBuf,
+    /// Changed paths; `None` marks a deletion.
+    entries: BTreeMap<String, Option<ManifestEntry>>,
+}
+
+impl Overlay {
+    fn dir_for(dits_dir: &Path) -> PathBuf {
+        dits_dir.join("mount")
+    }
+
+    /// Create an overlay over `base`, keeping the entries of an existing one.
+    pub fn create(dits_dir: &Path, base: &OverlayBase) -> io::Result<Self> {
+        let overlay = match Self::open(dits_dir)? {
+            Some(existing) => existing,
+            None => Self {
+                dir: Self::dir_for(dits_dir),
+                entries: BTreeMap::new(),
+            },
+        };
+        fs::create_dir_all(overlay.upper_dir())?;
+        overlay.set_base(base)?;
+        overlay.save()?;
+        Ok(overlay)
+    }
+
+    /// Open the repository's overlay, if a writable mount has created one.
+    pub fn open(dits_dir: &Path) -> io::Result<Option<Self>> {
+        let dir = Self::dir_for(dits_dir);
+        if !dir.join("base.json").exists() {
+            return Ok(None);
+        }
+        let entries_path = dir.join("entries.json");
+        let entries = if entries_path.exists() {
+            read_json(&entries_path)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Some(Self { dir, entries }))
+    }
+
+    /// What the overlay currently applies to.
+    pub fn base(&self) -> io::Result<OverlayBase> {
+        read_json(&self.dir.join("base.json"))
+    }
+
+    /// The base and its manifest.
+    pub fn load_base(&self, repo: &Repository) -> Result<(OverlayBase, Manifest), RepoError> {
+        let base = self.base()?;
+        let commit = repo.load_commit(&base.commit)?;
+        let manifest = repo.load_manifest(&commit.manifest)?;
+        Ok((base, manifest))
+    }
+
+    /// Point the overlay at a new base.
+    pub fn set_base(&self, base: &OverlayBase) -> io::Result<()> {
+        write_json(&self.dir.join("base.json"), base)
+    }
+
+    /// Directory holding copies of files open for writing.
+    pub fn upper_dir(&self) -> PathBuf {
+        self.dir.join("upper")
+    }
+
+    /// Record new content for a path.
+    pub fn put(&mut self, entry: ManifestEntry) {
+        self.entries.insert(entry.path.clone(), Some(entry));
+    }
+
+    /// Record a deleted path.
+    #[cfg(feature = "fuser")]
+    pub fn delete(&mut self, path: &str) {
+        self.entries.insert(path.to_string(), None);
+    }
+
+    /// Delete a file, or every file below a directory, from the overlay and
+    /// the mounted `view`.
+    #[cfg(feature = "fuser")]
+    pub fn remove(&mut self, view: &mut Manifest, path: &str) {
+        for removed in files_at(view, path) {
+            view.remove(&removed);
+            self.delete(&removed);
+        }
+    }
+
+    /// Move a file, or every file below a directory, from `from` to `to`,
+    /// updating both the overlay and the mounted `view`.
+    #[cfg(feature = "fuser")]
+    pub fn rename(&mut self, view: &mut Manifest, from: &str, to: &str) {
+        for old_path in files_at(view, from) {
+            let Some(mut entry) = view.remove(&old_path) else {
+                continue;
+            };
+            entry.path = format!("{}{}", to, &old_path[from.len()..]);
+            self.delete(&old_path);
+            self.put(entry.clone());
+            view.add(entry);
+        }
+    }
+
+    /// Write the entries to disk.
+    pub fn save(&self) -> io::Result<()> {
+        write_json(&self.dir.join("entries.json"), &self.entries)
+    }
+
+    /// The tree the overlay presents over `base`.
+    pub fn apply(&self, base: &Manifest) -> Manifest {
+        let mut manifest = base.clone();
+        for (path, entry) in &self.entries {
+            match entry {
+                Some(entry) => manifest.add(entry.clone()),
+                None => {
+                    manifest.remove(path);
+                }
+            }
+        }
+        manifest
+    }
+
+    /// Paths whose content differs from `base`.
+    pub fn changes(&self, base: &Manifest) -> OverlayChanges {
+        let mut changes = OverlayChanges::default();
+        for (path, entry) in &self.entries {
+            match (entry, base.get(path)) {
+                (Some(_), None) => changes.added.push(path.clone()),
+                (Some(new), Some(old)) => {
+                    if new.content_hash != old.content_hash || new.mode != old.mode {
+                        changes.modified.push(path.clone());
+                    }
+                }
+                (None, Some(_)) => changes.deleted.push(path.clone()),
+                (None, None) => {}
+            }
+        }
+        changes
+    }
+
+    /// Remove the overlay and any leftover file copies.
+    pub fn discard(self) -> io::Result<()> {
+        fs::remove_dir_all(&self.dir)
+    }
+}
+
+/// The file at `path`, or the files below it if it is a directory.
+#[cfg(feature = "fuser")]
+fn files_at(view: &Manifest, path: &str) -> Vec<String> {
+    let prefix = format!("{}/", path);
+    view.paths()
+        .filter(|p| *p == path || p.starts_with(&prefix))
+        .map(str::to_string)
+        .collect()
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> io::Result<T> {
+    let json = fs::read_to_string(path)?;
+    serde_json::from_str(&json).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Write JSON through a temporary file so readers never see a partial file.
+fn write_json<T: Serialize>(path: &Path, value: &T) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::Hasher;
+    use tempfile::tempdir;
+
+    fn entry(path: &str, content: &[u8]) -> ManifestEntry {
+        ManifestEntry::new(path.to_string(), content.len() as u64, Hasher::hash(content), Vec::new())
+    }
+
+    #[test]
+    fn test_overlay_changes_survive_rebase() {
+        let temp = tempdir().unwrap();
+        let mut base = Manifest::new();
+        base.add(entry("footage/a.mov", b"a"));
+        base.add(entry("edit.prproj", b"v1"));
+
+        let mount_base = OverlayBase {
+            branch: "main".to_string(),
+            commit: Hash::ZERO,
+            mount_point: PathBuf::from("/mnt/dits"),
+        };
+        let mut overlay = Overlay::create(temp.path(), &mount_base).unwrap();
+        overlay.put(entry("edit.prproj", b"v2"));
+        overlay.put(entry("footage/b.mov", b"b"));
+        overlay.put(entry("footage/a.mov", b"a"));
+        overlay.save().unwrap();
+
+        let overlay = Overlay::open(temp.path()).unwrap().unwrap();
+        let changes = overlay.changes(&base);
+        assert_eq!(changes.added, ["footage/b.mov"]);
+        assert_eq!(changes.modified, ["edit.prproj"]);
+        assert!(changes.deleted.is_empty());
+
+        // Once committed, the applied tree is the new base and nothing is left to commit
+        let committed = overlay.apply(&base);
+        let paths: Vec<_> = committed.paths().collect();
+        assert_eq!(paths, ["edit.prproj", "footage/a.mov", "footage/b.mov"]);
+        assert!(overlay.changes(&committed).is_empty());
+        assert_eq!(overlay.base().unwrap().branch, "main");
+
+        overlay.discard().unwrap();
+        assert!(Overlay::open(temp.path()).unwrap().is_none());
+    }
+
+    #[cfg(feature = "fuser")]
+    #[test]
+    fn test_overlay_remove_and_rename() {
+        let temp = tempdir().unwrap();
+        let mut base = Manifest::new();
+        base.add(entry("footage/a.mov", b"a"));
+        base.add(entry("footage/b.mov", b"b"));
+        base.add(entry("edit.prproj", b"v1"));
+
+        let mount_base = OverlayBase {
+            branch: "main".to_string(),
+            commit: Hash::ZERO,
+            mount_point: PathBuf::from("/mnt/dits"),
+        };
+        let mut overlay = Overlay::create(temp.path(), &mount_base).unwrap();
+        let mut view = overlay.apply(&base);
+        overlay.remove(&mut view, "footage/b.mov");
+        overlay.rename(&mut view, "footage", "media");
+        assert_eq!(view.paths().collect::<Vec<_>>(), ["edit.prproj", "media/a.mov"]);
+
+        let changes = overlay.changes(&base);
+        assert_eq!(changes.added, ["media/a.mov"]);
+        assert!(changes.modified.is_empty());
+        assert_eq!(changes.deleted, ["footage/a.mov", "footage/b.mov"]);
+        let committed = overlay.apply(&base);
+        assert_eq!(committed.paths().collect::<Vec<_>>(), ["edit.prproj", "media/a.mov"]);
+    }
+}
@@ -0,0 +1,189 @@
+//! Proxy substitution for mounts.
+//!
+//! A proxy mount serves each video that has a proxy from the proxy's data
+//! file instead of the original's chunks. Paths and file names stay the
+//! same, so NLE projects link to the proxies without relinking. Videos
+//! without a proxy are served as originals. The virtual file
+//! [`STATUS_FILE`] at the root of the mount lists which is which.
+
+use super::entry::{VfsEntry, VfsTree};
+use crate::core::{Hasher, ManifestEntry};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the virtual file listing what a proxy mount serves.
+pub const STATUS_FILE: &str = ".dits-proxy-status";
+
+/// Proxy data served in place of a video.
+#[derive(Clone, Debug)]
+pub struct ProxyFile {
+    /// Proxy data on disk.
+    pub data: PathBuf,
+    /// Size of the proxy data in bytes.
+    pub size: u64,
+    /// Kind of proxy, e.g. "720p Proxy".
+    pub variant: String,
+}
+
+/// Which videos of a mount are served as proxies.
+#[derive(Debug, Default)]
+pub struct ProxyView {
+    /// What the mount was asked for, e.g. "720p".
+    requested: String,
+    /// Each video path with the proxy served for it, if any.
+    videos: BTreeMap<String, Option<(ProxyFile, u64)>>,
+}
+
+impl ProxyView {
+    /// Create an empty view for proxies of the `requested` kind.
+    pub fn new(requested: &str) -> Self {
+        Self {
+            requested: requested.to_string(),
+            videos: BTreeMap::new(),
+        }
+    }
+
+    /// Serve `path`, whose original is `original_size` bytes, from a proxy.
+    pub fn serve_proxy(&mut self, path: &str, original_size: u64, proxy: ProxyFile) {
+        self.videos.insert(path.to_string(), Some((proxy, original_size)));
+    }
+
+    /// Serve the video at `path` as its original.
+    pub fn serve_original(&mut self, path: &str) {
+        self.videos.insert(path.to_string(), None);
+    }
+
+    /// Number of videos served as proxies.
+    pub fn proxy_count(&self) -> usize {
+        self.videos.values().filter(|proxy| proxy.is_some()).count()
+    }
+
+    /// Number of videos served as originals.
+    pub fn original_count(&self) -> usize {
+        self.videos.len() - self.proxy_count()
+    }
+
+    /// Contents of the status file.
+    pub fn status(&self) -> String {
+        let mut out = format!(
+            "# dits mount --proxy {}: {} proxy, {} original\n",
+            self.requested,
+            self.proxy_count(),
+            self.original_count()
+        );
+        for (path, proxy) in &self.videos {
+            let _ = match proxy {
+                Some((proxy, original_size)) => writeln!(
+                    out,
+                    "proxy\t{}\t{}\t{} of {} bytes",
+                    path, proxy.variant, proxy.size, original_size
+                ),
+                None => writeln!(out, "original\t{}", path),
+            };
+        }
+        out
+    }
+
+    /// Point the proxied files of `tree` at their proxy data and add the
+    /// status file to its root.
+    ///
+    /// Returns the content to serve for each substituted inode.
+    pub fn apply(&self, tree: &mut VfsTree) -> io::Result<HashMap<u64, Substitute>> {
+        let mut substitutes = HashMap::new();
+        for (path, proxy) in &self.videos {
+            let Some((proxy, _)) = proxy else {
+                continue;
+            };
+            let Some(inode) = tree.lookup(Path::new(path)).map(|entry| entry.inode) else {
+                continue;
+            };
+            let file = File::open(&proxy.data)?;
+            if let Some(entry) = tree.get_mut(inode) {
+                entry.size = proxy.size;
+                entry.chunks.clear();
+                entry.mp4_metadata = None;
+            }
+            substitutes.insert(inode, Substitute::File(Mutex::new(file)));
+        }
+
+        let status = self.status().into_bytes();
+        let template = ManifestEntry::new(String::new(), status.len() as u64, Hasher::hash(&status), Vec::new());
+        let mut entry = VfsEntry::file(STATUS_FILE.to_string(), 0, 0, &template);
+        entry.mode = 0o444;
+        if let Some(inode) = tree.insert(tree.root_inode, entry) {
+            substitutes.insert(inode, Substitute::Bytes(status));
+        }
+        Ok(substitutes)
+    }
+}
+
+/// Content served for a file instead of its chunks.
+#[derive(Debug)]
+pub enum Substitute {
+    /// An open file on disk.
+    File(Mutex<File>),
+    /// Bytes held in memory.
+    Bytes(Vec<u8>),
+}
+
+impl Substitute {
+    /// Read up to `size` bytes starting at `offset`.
+    pub fn read(&self, offset: u64, size: u64) -> io::Result<Vec<u8>> {
+        match self {
+            Substitute::File(file) => {
+                let mut file = file.lock().map_err(|_| io::Error::other("proxy file lock poisoned"))?;
+                file.seek(SeekFrom::Start(offset))?;
+                let mut data = Vec::new();
+                file.by_ref().take(size).read_to_end(&mut data)?;
+                Ok(data)
+            }
+            Substitute::Bytes(bytes) => {
+                let start = (offset as usize).min(bytes.len());
+                let end = start.saturating_add(size as usize).min(bytes.len());
+                Ok(bytes[start..end].to_vec())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Hash, Manifest};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_proxies_replace_originals_under_the_same_name() {
+        let temp = tempdir().unwrap();
+        let data = temp.path().join("proxy-data");
+        std::fs::write(&data, b"small proxy").unwrap();
+
+        let mut manifest = Manifest::new();
+        manifest.add(ManifestEntry::new("footage/a.mov".into(), 5000, Hash::ZERO, Vec::new()));
+        manifest.add(ManifestEntry::new("footage/b.mov".into(), 7000, Hash::ZERO, Vec::new()));
+        let mut tree = VfsTree::from_manifest(&manifest);
+
+        let mut view = ProxyView::new("720p");
+        let proxy = ProxyFile { data, size: 11, variant: "720p Proxy".into() };
+        view.serve_proxy("footage/a.mov", 5000, proxy);
+        view.serve_original("footage/b.mov");
+        let substitutes = view.apply(&mut tree).unwrap();
+
+        let a = tree.lookup(Path::new("footage/a.mov")).unwrap();
+        assert_eq!(a.size, 11);
+        assert_eq!(substitutes[&a.inode].read(6, 100).unwrap(), b"proxy");
+        let b = tree.lookup(Path::new("footage/b.mov")).unwrap();
+        assert_eq!(b.size, 7000);
+        assert!(!substitutes.contains_key(&b.inode));
+
+        let status = tree.lookup(Path::new(STATUS_FILE)).unwrap();
+        let text = String::from_utf8(substitutes[&status.inode].read(0, status.size).unwrap()).unwrap();
+        assert!(text.starts_with("# dits mount --proxy 720p: 1 proxy, 1 original\n"));
+        assert!(text.contains("proxy\tfootage/a.mov\t720p Proxy\t11 of 5000 bytes\n"));
+        assert!(text.contains("original\tfootage/b.mov\n"));
+    }
+}
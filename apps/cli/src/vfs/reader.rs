@@ -0,0 +1,291 @@
+//! File content reconstruction for VFS front ends.
+//!
+//! Both the FUSE filesystem and other front ends serve byte ranges of
+//! manifest entries. Plain files are read straight from their chunks; MP4
+//! files are reassembled from the stored ftyp and moov blobs, a synthesized
+//! mdat header and the mdat payload chunks.
+//!
+//! Each read also starts fetching the chunks that follow it in the entry's
+//! chunk list, so sequential playback of a lazily mounted file rarely waits
+//! on the remote.
+
+#[cfg(feature = "fuser")]
+use super::cache::CacheStats;
+use super::cache::{CacheConfig, SyncChunkCache};
+use super::entry::VfsEntry;
+use super::VfsError;
+use crate::core::{Hash, Mp4Metadata};
+use crate::store::ObjectStore;
+use byteorder::{BigEndian, ByteOrder};
+use std::sync::Arc;
+
+/// Patch stco/co64 offsets in moov data to denormalize them.
+///
+/// The moov is stored with offsets normalized to 0 (relative to mdat data start).
+/// When serving the file, we need to add the actual mdat data position to each offset.
+fn patch_moov_offsets(moov_data: &mut [u8], meta: &Mp4Metadata, mdat_data_start: u64) {
+    if !meta.needs_offset_patching {
+        return;
+    }
+
+    // Patch stco tables (32-bit offsets)
+    for (offset_in_moov, entry_count) in &meta.stco_offsets {
+        let base = *offset_in_moov as usize;
+        for i in 0..*entry_count as usize {
+            let entry_offset = base + i * 4;
+            if entry_offset + 4 > moov_data.len() {
+                break;
+            }
+            let current = BigEndian::read_u32(&moov_data[entry_offset..entry_offset + 4]) as u64;
+            let new_value = current + mdat_data_start;
+            // Write back (assuming it fits in 32 bits)
+            if new_value <= u32::MAX as u64 {
+                BigEndian::write_u32(&mut moov_data[entry_offset..entry_offset + 4], new_value as u32);
+            }
+        }
+    }
+
+    // Patch co64 tables (64-bit offsets)
+    for (offset_in_moov, entry_count) in &meta.co64_offsets {
+        let base = *offset_in_moov as usize;
+        for i in 0..*entry_count as usize {
+            let entry_offset = base + i * 8;
+            if entry_offset + 8 > moov_data.len() {
+                break;
+            }
+            let current = BigEndian::read_u64(&moov_data[entry_offset..entry_offset + 8]);
+            let new_value = current + mdat_data_start;
+            BigEndian::write_u64(&mut moov_data[entry_offset..entry_offset + 8], new_value);
+        }
+    }
+}
+
+/// Reads byte ranges of VFS entries through the chunk cache.
+pub struct ContentReader {
+    /// Chunk cache.
+    cache: SyncChunkCache,
+    /// Number of chunks to fetch ahead of each read.
+    read_ahead: usize,
+}
+
+impl ContentReader {
+    /// Create a reader backed by an object store.
+    pub fn new(object_store: Arc<ObjectStore>, cache_config: CacheConfig) -> std::io::Result<Self> {
+        let read_ahead = if cache_config.prefetch_enabled { cache_config.prefetch_count } else { 0 };
+        let cache = SyncChunkCache::new(cache_config, object_store)?;
+        Ok(Self { cache, read_ahead })
+    }
+
+    /// Read up to `size` bytes of a file starting at `offset`.
+    pub fn read(&self, entry: &VfsEntry, offset: u64, size: u64) -> Result<Vec<u8>, VfsError> {
+        if entry.is_mp4() {
+            self.read_mp4_file(entry, offset, size)
+        } else {
+            self.read_file(entry, offset, size)
+        }
+    }
+
+    /// Start fetching the chunks after `chunk_idx`.
+    fn read_ahead(&self, entry: &VfsEntry, chunk_idx: usize) {
+        let hashes: Vec<Hash> = entry.chunks
+            .iter()
+            .skip(chunk_idx + 1)
+            .take(self.read_ahead)
+            .map(|c| c.hash)
+            .collect();
+        if !hashes.is_empty() {
+            self.cache.prefetch(&hashes);
+        }
+    }
+
+    /// Copy `read_len` bytes at `chunk_offset` out of a chunk.
+    fn chunk_slice(&self, hash: &Hash, chunk_offset: u64, read_len: u64, out: &mut Vec<u8>) -> Result<(), VfsError> {
+        let chunk_data = self.cache.fetch(hash)?;
+        let start = chunk_offset as usize;
+        let end = start + read_len as usize;
+        if end > chunk_data.len() {
+            return Err(VfsError::Cache(format!(
+                "Chunk {} is {} bytes, read needs {}..{}",
+                hash.to_hex(),
+                chunk_data.len(),
+                start,
+                end
+            )));
+        }
+        out.extend_from_slice(&chunk_data[start..end]);
+        Ok(())
+    }
+
+    /// Get file contents for a range.
+    fn read_file(&self, entry: &VfsEntry, offset: u64, size: u64) -> Result<Vec<u8>, VfsError> {
+        if offset >= entry.size {
+            return Ok(Vec::new());
+        }
+
+        let actual_size = std::cmp::min(size, entry.size - offset) as usize;
+        let mut result = Vec::with_capacity(actual_size);
+
+        // Find chunks that cover this range
+        let ranges = entry.chunks_for_range(offset, actual_size as u64);
+        if let Some(&(chunk_idx, ..)) = ranges.last() {
+            self.read_ahead(entry, chunk_idx);
+        }
+
+        for (_idx, chunk_ref, chunk_offset, read_len) in ranges {
+            self.chunk_slice(&chunk_ref.hash, chunk_offset, read_len, &mut result)?;
+        }
+
+        Ok(result)
+    }
+
+    /// Read MP4 file data, reconstructing the full structure from ftyp + moov + mdat.
+    fn read_mp4_file(&self, entry: &VfsEntry, offset: u64, size: u64) -> Result<Vec<u8>, VfsError> {
+        let meta = entry
+            .mp4_metadata
+            .as_ref()
+            .ok_or_else(|| VfsError::Cache(format!("No MP4 metadata for {}", entry.name)))?;
+
+        if offset >= entry.size {
+            return Ok(Vec::new());
+        }
+
+        let actual_size = std::cmp::min(size, entry.size - offset) as usize;
+        let mut result = Vec::with_capacity(actual_size);
+        let mut current_offset = offset;
+        let mut remaining = actual_size;
+
+        // Structure: ftyp (32 bytes) + moov + mdat header (8 bytes) + mdat data (chunks)
+        let ftyp_size: u64 = 32;
+        let moov_end = ftyp_size + meta.moov_size;
+        let mdat_header_end = moov_end + 8;
+
+        // Region 1: ftyp (0..32)
+        if current_offset < ftyp_size && remaining > 0 {
+            if let Some(ftyp_hash) = &meta.ftyp_hash {
+                let ftyp_data = self.cache.fetch_blob(ftyp_hash)?;
+                let start = current_offset as usize;
+                let end = std::cmp::min(ftyp_size as usize, start + remaining);
+                if end <= ftyp_data.len() {
+                    result.extend_from_slice(&ftyp_data[start..end]);
+                    remaining -= end - start;
+                    current_offset = end as u64;
+                }
+            }
+        }
+
+        // Region 2: moov (32..32+moov_size)
+        if current_offset < moov_end && remaining > 0 {
+            if let Some(moov_hash) = &meta.moov_hash {
+                let moov_data = self.cache.fetch_blob(moov_hash)?;
+                // Patch the moov data to denormalize offsets
+                // The mdat data starts right after: ftyp + moov + mdat_header(8)
+                let mdat_data_start = ftyp_size + meta.moov_size + 8;
+                let mut patched_moov = moov_data;
+                patch_moov_offsets(&mut patched_moov, meta, mdat_data_start);
+
+                let moov_offset = current_offset.saturating_sub(ftyp_size) as usize;
+                let moov_remaining = (moov_end - current_offset) as usize;
+                let to_read = std::cmp::min(moov_remaining, remaining);
+                let end = moov_offset + to_read;
+                if end > patched_moov.len() {
+                    return Err(VfsError::Cache(format!(
+                        "moov data too short: {} vs {}..{}",
+                        patched_moov.len(),
+                        moov_offset,
+                        end
+                    )));
+                }
+                result.extend_from_slice(&patched_moov[moov_offset..end]);
+                remaining -= to_read;
+                current_offset += to_read as u64;
+            }
+        }
+
+        // Region 3: mdat header (moov_end..moov_end+8 or moov_end+16 for extended size)
+        if current_offset < mdat_header_end && remaining > 0 {
+            let mdat_total_size = meta.mdat_size + 8;
+
+            // For files > 4GB, use extended size (64-bit) header
+            let mdat_header: Vec<u8> = if mdat_total_size > u32::MAX as u64 {
+                // Extended size: size=1 (4 bytes) + "mdat" (4 bytes) + actual_size (8 bytes)
+                let mut header = Vec::with_capacity(16);
+                header.extend_from_slice(&1u32.to_be_bytes()); // size = 1 means extended
+                header.extend_from_slice(b"mdat");
+                header.extend_from_slice(&(mdat_total_size + 8).to_be_bytes()); // +8 for extended header
+                header
+            } else {
+                // Standard header: size (4 bytes) + "mdat" (4 bytes)
+                let mut header = Vec::with_capacity(8);
+                header.extend_from_slice(&(mdat_total_size as u32).to_be_bytes());
+                header.extend_from_slice(b"mdat");
+                header
+            };
+
+            let header_offset = (current_offset - moov_end) as usize;
+            if header_offset < mdat_header.len() {
+                let to_read = std::cmp::min(mdat_header.len() - header_offset, remaining);
+                result.extend_from_slice(&mdat_header[header_offset..header_offset + to_read]);
+                remaining -= to_read;
+                current_offset += to_read as u64;
+            }
+        }
+
+        // Region 4: mdat data (chunks)
+        if current_offset >= mdat_header_end && remaining > 0 {
+            let chunk_offset = current_offset - mdat_header_end;
+            let ranges = entry.chunks_for_range(chunk_offset, remaining as u64);
+            if let Some(&(chunk_idx, ..)) = ranges.last() {
+                self.read_ahead(entry, chunk_idx);
+            }
+
+            for (_idx, chunk_ref, chunk_off, read_len) in ranges {
+                self.chunk_slice(&chunk_ref.hash, chunk_off, read_len, &mut result)?;
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Get cache statistics.
+    #[cfg(feature = "fuser")]
+    pub fn cache_stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{Chunk, ChunkRef, ManifestEntry};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_read_range_across_chunks() {
+        let temp = tempdir().unwrap();
+        let store = ObjectStore::new(temp.path());
+        store.init().unwrap();
+
+        let first = Chunk::new(vec![b'a'; 100]);
+        let second = Chunk::new(vec![b'b'; 50]);
+        store.store_chunk(&first).unwrap();
+        store.store_chunk(&second).unwrap();
+        let manifest_entry = ManifestEntry::new(
+            "clip.bin".to_string(),
+            150,
+            Hash::ZERO,
+            vec![ChunkRef::new(first.hash, 0, 100), ChunkRef::new(second.hash, 100, 50)],
+        );
+        let entry = VfsEntry::file("clip.bin".to_string(), 2, 1, &manifest_entry);
+
+        let config = CacheConfig {
+            l2_path: temp.path().join("cache"),
+            ..Default::default()
+        };
+        let reader = ContentReader::new(Arc::new(store), config).unwrap();
+
+        let data = reader.read(&entry, 90, 20).unwrap();
+        assert_eq!(data, [vec![b'a'; 10], vec![b'b'; 10]].concat());
+        assert_eq!(reader.read(&entry, 140, 1 << 20).unwrap(), vec![b'b'; 10]);
+        assert!(reader.read(&entry, 150, 10).unwrap().is_empty());
+    }
+}
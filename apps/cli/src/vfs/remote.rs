@@ -0,0 +1,113 @@
+//! Remote object sources for lazy mounts.
+//!
+//! A lazy mount serves a partial clone: commits and manifests are local,
+//! but chunks and MP4 blobs may only exist on a remote. When the chunk
+//! cache misses locally it downloads the object from an [`ObjectSource`]
+//! and verifies it against its hash before caching it.
+
+use crate::store::reachability::ObjectId;
+#[cfg(feature = "fuser")]
+use crate::store::remote_client::{HttpRemote, RemoteClientError};
+use futures_util::future::BoxFuture;
+#[cfg(feature = "fuser")]
+use reqwest::StatusCode;
+use std::fmt;
+
+/// A remote that objects missing locally can be downloaded from.
+pub trait ObjectSource: fmt::Debug + Send + Sync {
+    /// Where objects come from, for error messages.
+    fn location(&self) -> String;
+
+    /// Download an object's transfer bytes.
+    ///
+    /// Returns `Ok(None)` if the remote answered but does not have the
+    /// object, and `Err` with a reason if it could not be reached.
+    fn download<'a>(&'a self, id: &'a ObjectId) -> BoxFuture<'a, Result<Option<Vec<u8>>, String>>;
+}
+
+/// Objects served by a `RepoServer` over HTTP.
+#[cfg(feature = "fuser")]
+pub struct HttpSource {
+    remote: HttpRemote,
+    url: String,
+}
+
+#[cfg(feature = "fuser")]
+impl HttpSource {
+    /// Download from the repository at `url`.
+    pub fn new(url: &str, token: Option<String>) -> Self {
+        Self {
+            remote: HttpRemote::new(url).with_token(token),
+            url: url.to_string(),
+        }
+    }
+}
+
+#[cfg(feature = "fuser")]
+impl fmt::Debug for HttpSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpSource").field("url", &self.url).finish()
+    }
+}
+
+#[cfg(feature = "fuser")]
+impl ObjectSource for HttpSource {
+    fn location(&self) -> String {
+        self.url.clone()
+    }
+
+    fn download<'a>(&'a self, id: &'a ObjectId) -> BoxFuture<'a, Result<Option<Vec<u8>>, String>> {
+        Box::pin(async move {
+            match self.remote.download(id).await {
+                Ok(data) => Ok(Some(data)),
+                Err(RemoteClientError::Status { status: StatusCode::NOT_FOUND, .. }) => Ok(None),
+                Err(e) => Err(e.to_string()),
+            }
+        })
+    }
+}
+
+#[cfg(all(test, feature = "fuser"))]
+mod tests {
+    use super::*;
+    use crate::core::Hash;
+    use crate::store::reachability::ObjectKind;
+    use crate::store::remote_server::RepoServer;
+    use crate::store::{ObjectType, Repository};
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_http_source_serves_partial_clone() {
+        let server_dir = tempdir().unwrap();
+        Repository::init(&server_dir.path().join("project")).unwrap();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/repos/project", listener.local_addr().unwrap());
+        let app = Arc::new(RepoServer::new(server_dir.path().to_path_buf())).router();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let author_dir = tempdir().unwrap();
+        let author = Repository::init(author_dir.path()).unwrap();
+        std::fs::write(author_dir.path().join("clip.bin"), vec![7u8; 300_000]).unwrap();
+        author.add("clip.bin").unwrap();
+        author.commit("add clip").unwrap();
+        HttpRemote::new(&url).push_branch(&author, "main", false).await.unwrap();
+
+        // A lazy fetch brings history but no chunks
+        let reader_dir = tempdir().unwrap();
+        let reader = Repository::init(reader_dir.path()).unwrap();
+        HttpRemote::new(&url).without_content().fetch(&reader).await.unwrap();
+        let tip = author.head().unwrap().unwrap();
+        let manifest = reader.load_manifest(&reader.load_commit(&tip).unwrap().manifest).unwrap();
+        let chunk = manifest.get("clip.bin").unwrap().chunks[0].hash;
+        assert!(!reader.objects().has_object(ObjectType::Chunk, &chunk));
+
+        let source = HttpSource::new(&url, None);
+        let data = source.download(&ObjectId::new(ObjectKind::Chunk, &chunk)).await.unwrap().unwrap();
+        assert_eq!(crate::core::Hasher::hash(&data), chunk);
+        let missing = source.download(&ObjectId::new(ObjectKind::Chunk, &Hash::ZERO)).await.unwrap();
+        assert!(missing.is_none());
+    }
+}
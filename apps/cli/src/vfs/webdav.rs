@@ -0,0 +1,707 @@
+//! WebDAV front end for repository trees.
+//!
+//! Serves a [`VfsTree`] over WebDAV class 1, so the built-in WebDAV clients
+//! of Windows, macOS and Linux can mount a repository without FUSE:
+//!
+//! - `OPTIONS` advertises WebDAV support
+//! - `PROPFIND` describes a resource and, unless `Depth: 0`, its children
+//!   (`Depth: infinity` is answered like `Depth: 1`)
+//! - `GET` and `HEAD` serve files through [`ContentReader`], including
+//!   single byte ranges, so players can seek without downloading the whole file
+//! - `PUT` and `MKCOL` store files and directories in a staging
+//!   [`Overlay`] that `dits commit` turns into a commit, when writable
+//!
+//! Other methods, including locking, are refused. Clients that need locks to
+//! write, like the macOS Finder, mount the server read-only.
+
+use super::entry::{VfsEntry, VfsTree};
+use super::overlay::Overlay;
+use super::reader::ContentReader;
+use super::{CacheConfig, VfsError};
+use crate::core::{Hash, Manifest, ManifestEntry};
+use crate::store::{ObjectStore, Repository};
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Router,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use futures_util::{stream, StreamExt};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use quick_xml::escape::escape;
+use std::fmt::Write as _;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::io::AsyncWriteExt;
+
+/// Bytes read from the chunk cache per step of a download.
+const READ_BLOCK: u64 = 4 * 1024 * 1024;
+
+/// Characters left unencoded in hrefs, besides letters and digits.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// Methods served by every server.
+const READ_METHODS: &str = "OPTIONS, GET, HEAD, PROPFIND";
+
+/// Methods served by writable servers.
+const WRITE_METHODS: &str = "OPTIONS, GET, HEAD, PROPFIND, PUT, MKCOL";
+
+/// Where a writable server records uploads.
+struct Staging {
+    /// Where changes are recorded.
+    overlay: Overlay,
+    /// Files as served: the base manifest with the overlay applied.
+    view: Manifest,
+    /// Number of uploads received, used to name their temporary files.
+    uploads: u64,
+}
+
+/// State shared by all requests.
+struct DavState {
+    /// Files and directories served.
+    tree: VfsTree,
+    /// Present for writable servers.
+    staging: Option<Staging>,
+}
+
+/// WebDAV server for one repository tree.
+pub struct WebDav {
+    state: Mutex<DavState>,
+    /// Repository uploads are stored in, for writable servers. Locked apart
+    /// from `state`, so that other requests are served while an upload is
+    /// being chunked.
+    repo: Option<Mutex<Repository>>,
+    /// Reads file content through the chunk cache.
+    reader: Arc<ContentReader>,
+}
+
+impl WebDav {
+    /// Create a read-only server for the files of `manifest`.
+    ///
+    /// The chunk cache blocks on its own runtime, so this must not be called
+    /// from async code.
+    pub fn new(manifest: &Manifest, object_store: Arc<ObjectStore>, cache_config: CacheConfig) -> io::Result<Self> {
+        Ok(Self {
+            state: Mutex::new(DavState {
+                tree: VfsTree::from_manifest(manifest),
+                staging: None,
+            }),
+            repo: None,
+            reader: Arc::new(ContentReader::new(object_store, cache_config)?),
+        })
+    }
+
+    /// Create a server presenting `base` with `overlay` applied that stores
+    /// uploads in `overlay`.
+    pub fn writable(
+        repo: Repository,
+        object_store: Arc<ObjectStore>,
+        overlay: Overlay,
+        base: &Manifest,
+        cache_config: CacheConfig,
+    ) -> io::Result<Self> {
+        let view = overlay.apply(base);
+        let mut dav = Self::new(&view, object_store, cache_config)?;
+        dav.repo = Some(Mutex::new(repo));
+        dav.lock().staging = Some(Staging {
+            overlay,
+            view,
+            uploads: 0,
+        });
+        Ok(dav)
+    }
+
+    /// Create the Axum router answering every path.
+    pub fn router(self: Arc<Self>) -> Router {
+        Router::new().fallback(Self::handle).with_state(self)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, DavState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Dispatch a request on its method.
+    async fn handle(State(dav): State<Arc<WebDav>>, request: Request) -> Response {
+        let Some(path) = decode_path(request.uri().path()) else {
+            return StatusCode::BAD_REQUEST.into_response();
+        };
+        match request.method().as_str() {
+            "OPTIONS" => dav.options(),
+            "PROPFIND" => dav.propfind(&path, request.headers()),
+            "GET" => dav.get(&path, request.headers(), true).await,
+            "HEAD" => dav.get(&path, request.headers(), false).await,
+            "PUT" => dav.put(&path, request.into_body()).await,
+            "MKCOL" => dav.mkcol(&path),
+            _ => dav.not_allowed(),
+        }
+    }
+
+    /// Methods this server answers.
+    fn allowed(&self) -> &'static str {
+        if self.lock().staging.is_some() {
+            WRITE_METHODS
+        } else {
+            READ_METHODS
+        }
+    }
+
+    fn options(&self) -> Response {
+        (
+            [
+                (header::ALLOW, self.allowed()),
+                (header::HeaderName::from_static("dav"), "1"),
+                (header::HeaderName::from_static("ms-author-via"), "DAV"),
+            ],
+            StatusCode::OK,
+        )
+            .into_response()
+    }
+
+    fn not_allowed(&self) -> Response {
+        ([(header::ALLOW, self.allowed())], StatusCode::METHOD_NOT_ALLOWED).into_response()
+    }
+
+    /// Describe `path` and, unless the request asks for depth 0, its children.
+    fn propfind(&self, path: &str, headers: &HeaderMap) -> Response {
+        let depth_zero = headers.get("depth").is_some_and(|depth| depth.as_bytes() == b"0");
+        let state = self.lock();
+        let Some(entry) = state.tree.lookup(Path::new(path)) else {
+            return StatusCode::NOT_FOUND.into_response();
+        };
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n");
+        push_response(&mut xml, path, entry);
+        if entry.is_dir() && !depth_zero {
+            for child in children(&state.tree, entry) {
+                push_response(&mut xml, &join(path, &child.name), child);
+            }
+        }
+        xml.push_str("</D:multistatus>\n");
+
+        (
+            StatusCode::MULTI_STATUS,
+            [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+            xml,
+        )
+            .into_response()
+    }
+
+    /// Serve a file, or an index page for a directory.
+    async fn get(&self, path: &str, headers: &HeaderMap, with_body: bool) -> Response {
+        let entry = {
+            let state = self.lock();
+            let Some(entry) = state.tree.lookup(Path::new(path)) else {
+                return StatusCode::NOT_FOUND.into_response();
+            };
+            if entry.is_dir() {
+                return index(&state.tree, path, entry);
+            }
+            Arc::new(entry.clone())
+        };
+
+        let size = entry.size;
+        let range = match headers.get(header::RANGE).and_then(|value| value.to_str().ok()) {
+            Some(value) => match parse_range(value, size) {
+                Some(Some(range)) => Some(range),
+                Some(None) => {
+                    return (
+                        StatusCode::RANGE_NOT_SATISFIABLE,
+                        [(header::CONTENT_RANGE, format!("bytes */{}", size))],
+                    )
+                        .into_response();
+                }
+                // Ranges we do not understand are ignored, as the RFC allows
+                None => None,
+            },
+            None => None,
+        };
+        let (start, end) = range.unwrap_or((0, size));
+
+        let mut response = Response::builder()
+            .status(if range.is_some() { StatusCode::PARTIAL_CONTENT } else { StatusCode::OK })
+            .header(header::ACCEPT_RANGES, "bytes")
+            .header(header::CONTENT_TYPE, content_type(&entry.name))
+            .header(header::CONTENT_LENGTH, end - start)
+            .header(header::LAST_MODIFIED, http_date(&entry));
+        if let Some(hash) = &entry.content_hash {
+            response = response.header(header::ETAG, format!("\"{}\"", hash.to_hex()));
+        }
+        if range.is_some() {
+            response = response.header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end - 1, size));
+        }
+        if !with_body || start == end {
+            return response.body(Body::empty()).unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+
+        // Read the first block before answering, so a failure gets a status code
+        let first = match read_block(self.reader.clone(), entry.clone(), start, end).await {
+            Ok(data) if !data.is_empty() => data,
+            Ok(_) => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            Err(e) => return read_failed(&e),
+        };
+        let reader = self.reader.clone();
+        let rest = stream::unfold(start + first.len() as u64, move |offset| {
+            let (reader, entry) = (reader.clone(), entry.clone());
+            async move {
+                if offset >= end {
+                    return None;
+                }
+                // Once the status is sent, a failure can only cut the body short
+                match read_block(reader, entry, offset, end).await {
+                    Ok(data) if data.is_empty() => Some((Err(io::Error::other("file ended early")), end)),
+                    Ok(data) => {
+                        let next = offset + data.len() as u64;
+                        Some((Ok(Bytes::from(data)), next))
+                    }
+                    Err(e) => {
+                        eprintln!("Read failed: {}", e);
+                        Some((Err(io::Error::other(e.to_string())), end))
+                    }
+                }
+            }
+        });
+        let body = stream::once(async move { Ok(Bytes::from(first)) }).chain(rest);
+        response
+            .body(Body::from_stream(body))
+            .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+    }
+
+    /// Store an uploaded file in the overlay.
+    async fn put(self: &Arc<Self>, path: &str, body: Body) -> Response {
+        let upload = {
+            let mut state = self.lock();
+            let DavState { tree, staging } = &mut *state;
+            let Some(staging) = staging.as_mut() else {
+                drop(state);
+                return self.not_allowed();
+            };
+            match parent_inode(tree, path) {
+                Ok(parent) if tree.lookup_child(parent, file_name(path)).is_some_and(|e| e.is_dir()) => {
+                    return StatusCode::METHOD_NOT_ALLOWED.into_response();
+                }
+                Ok(_) => {}
+                Err(status) => return status.into_response(),
+            }
+            staging.uploads += 1;
+            staging.overlay.upper_dir().join(format!("webdav-{}", staging.uploads))
+        };
+
+        if let Err(e) = receive(body, &upload).await {
+            eprintln!("Upload of {} failed: {}", path, e);
+            let _ = tokio::fs::remove_file(&upload).await;
+            return StatusCode::BAD_REQUEST.into_response();
+        }
+
+        let dav = self.clone();
+        let target = path.to_string();
+        let stored = tokio::task::spawn_blocking(move || {
+            let result = dav.store(&target, &upload);
+            let _ = std::fs::remove_file(&upload);
+            result
+        })
+        .await;
+        match stored {
+            Ok(Ok(status)) => status.into_response(),
+            Ok(Err(e)) => {
+                eprintln!("Failed to store {}: {}", path, e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+            Err(e) => {
+                eprintln!("Failed to store {}: {}", path, e);
+                StatusCode::INTERNAL_SERVER_ERROR.into_response()
+            }
+        }
+    }
+
+    /// Chunk the upload at `upload` into the object store as the file at
+    /// `path` and record it in the overlay.
+    ///
+    /// The server state is only locked to look up the file and to record it,
+    /// not while the upload is chunked.
+    fn store(&self, path: &str, upload: &Path) -> Result<StatusCode, VfsError> {
+        let Some(repo) = &self.repo else {
+            return Ok(StatusCode::METHOD_NOT_ALLOWED);
+        };
+        let template = {
+            let state = self.lock();
+            let Some(staging) = &state.staging else {
+                return Ok(StatusCode::METHOD_NOT_ALLOWED);
+            };
+            if let Err(status) = parent_inode(&state.tree, path) {
+                return Ok(status);
+            }
+            staging
+                .view
+                .get(path)
+                .cloned()
+                .unwrap_or_else(|| ManifestEntry::new(path.to_string(), 0, Hash::ZERO, Vec::new()))
+        };
+
+        let file = std::fs::File::open(upload)?;
+        // Empty files cannot be mapped
+        let map = if file.metadata()?.len() > 0 {
+            Some(unsafe { memmap2::Mmap::map(&file) }?)
+        } else {
+            None
+        };
+        let data: &[u8] = map.as_deref().unwrap_or_default();
+        let stored = repo
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .store_entry(&template, data)
+            .map_err(|e| VfsError::Repository(e.to_string()))?;
+
+        let mut state = self.lock();
+        let DavState { tree, staging } = &mut *state;
+        let Some(staging) = staging.as_mut() else {
+            return Ok(StatusCode::METHOD_NOT_ALLOWED);
+        };
+        let parent = match parent_inode(tree, path) {
+            Ok(parent) => parent,
+            Err(status) => return Ok(status),
+        };
+
+        let name = file_name(path);
+        let status = match tree.lookup_child(parent, name).map(|entry| entry.inode) {
+            Some(inode) => {
+                if let Some(entry) = tree.get_mut(inode) {
+                    entry.set_content(&stored);
+                }
+                StatusCode::NO_CONTENT
+            }
+            None => {
+                tree.insert(parent, VfsEntry::file(name.to_string(), 0, 0, &stored));
+                StatusCode::CREATED
+            }
+        };
+        staging.view.add(stored.clone());
+        staging.overlay.put(stored);
+        staging.overlay.save()?;
+        Ok(status)
+    }
+
+    /// Create a directory.
+    fn mkcol(&self, path: &str) -> Response {
+        let mut state = self.lock();
+        if state.staging.is_none() {
+            drop(state);
+            return self.not_allowed();
+        }
+        let parent = match parent_inode(&state.tree, path) {
+            Ok(parent) => parent,
+            Err(status) => return status.into_response(),
+        };
+        let name = file_name(path);
+        if state.tree.lookup_child(parent, name).is_some() {
+            return StatusCode::METHOD_NOT_ALLOWED.into_response();
+        }
+        state.tree.insert(parent, VfsEntry::directory(name.to_string(), 0, 0));
+        StatusCode::CREATED.into_response()
+    }
+}
+
+/// Decode a request path into a tree path without leading or trailing
+/// slashes. Paths that climb out of the tree are rejected.
+fn decode_path(path: &str) -> Option<String> {
+    let decoded = percent_decode_str(path).decode_utf8().ok()?;
+    let mut parts = Vec::new();
+    for part in decoded.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => return None,
+            _ => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Path of `name` inside the directory at `dir`.
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Last component of a tree path.
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Inode of the directory a new entry at `path` goes in.
+///
+/// The root cannot be replaced, and entries can only be added to existing
+/// directories.
+fn parent_inode(tree: &VfsTree, path: &str) -> Result<u64, StatusCode> {
+    if path.is_empty() {
+        return Err(StatusCode::METHOD_NOT_ALLOWED);
+    }
+    let parent = path.rsplit_once('/').map_or("", |(parent, _)| parent);
+    match tree.lookup(Path::new(parent)) {
+        Some(dir) if dir.is_dir() => Ok(dir.inode),
+        _ => Err(StatusCode::CONFLICT),
+    }
+}
+
+/// Children of a directory, sorted by name.
+fn children<'a>(tree: &'a VfsTree, dir: &VfsEntry) -> Vec<&'a VfsEntry> {
+    let mut children: Vec<&VfsEntry> = dir.children.values().filter_map(|inode| tree.get(*inode)).collect();
+    children.sort_by(|a, b| a.name.cmp(&b.name));
+    children
+}
+
+/// URL path of a tree path; directory hrefs end with a slash.
+fn href(path: &str, is_dir: bool) -> String {
+    let mut href = String::new();
+    for part in path.split('/').filter(|part| !part.is_empty()) {
+        href.push('/');
+        href.extend(utf8_percent_encode(part, PATH_SEGMENT));
+    }
+    if is_dir || href.is_empty() {
+        href.push('/');
+    }
+    href
+}
+
+/// Append the PROPFIND response describing one entry.
+fn push_response(xml: &mut String, path: &str, entry: &VfsEntry) {
+    let created = DateTime::<Utc>::from(entry.ctime).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let _ = write!(
+        xml,
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>\
+         <D:displayname>{}</D:displayname><D:creationdate>{}</D:creationdate>\
+         <D:getlastmodified>{}</D:getlastmodified>",
+        escape(href(path, entry.is_dir()).as_str()),
+        escape(file_name(path)),
+        created,
+        http_date(entry)
+    );
+    if entry.is_dir() {
+        xml.push_str("<D:resourcetype><D:collection/></D:resourcetype>");
+    } else {
+        let _ = write!(
+            xml,
+            "<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength>\
+             <D:getcontenttype>{}</D:getcontenttype>",
+            entry.size,
+            content_type(&entry.name)
+        );
+        if let Some(hash) = &entry.content_hash {
+            let _ = write!(xml, "<D:getetag>\"{}\"</D:getetag>", hash.to_hex());
+        }
+    }
+    xml.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\n");
+}
+
+/// HTML listing of a directory, for browsers.
+fn index(tree: &VfsTree, path: &str, dir: &VfsEntry) -> Response {
+    let title = escape(href(path, true)).into_owned();
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{0}</title></head>\n<body><h1>{0}</h1><ul>\n",
+        title
+    );
+    for child in children(tree, dir) {
+        let suffix = if child.is_dir() { "/" } else { "" };
+        let _ = writeln!(
+            html,
+            "<li><a href=\"{}\">{}{}</a></li>",
+            escape(href(&join(path, &child.name), child.is_dir()).as_str()),
+            escape(child.name.as_str()),
+            suffix
+        );
+    }
+    html.push_str("</ul></body></html>\n");
+    ([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response()
+}
+
+/// `Last-Modified` value of an entry.
+fn http_date(entry: &VfsEntry) -> String {
+    DateTime::<Utc>::from(entry.mtime).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+/// Media type served for a file name.
+fn content_type(name: &str) -> &'static str {
+    let extension = name.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default();
+    match extension.as_str() {
+        "mp4" | "m4v" => "video/mp4",
+        "mov" => "video/quicktime",
+        "mkv" => "video/x-matroska",
+        "webm" => "video/webm",
+        "mxf" => "application/mxf",
+        "wav" => "audio/wav",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Parse a `Range` header against a file of `size` bytes into the half-open
+/// byte range it asks for.
+///
+/// Returns `None` for headers that are not a single byte range, which are
+/// served as the whole file, and `Some(None)` for a range past the end.
+fn parse_range(value: &str, size: u64) -> Option<Option<(u64, u64)>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (first, last) = spec.split_once('-')?;
+    let (first, last) = (first.trim(), last.trim());
+    if first.is_empty() {
+        // Suffix range: the last N bytes
+        let suffix: u64 = last.parse().ok()?;
+        return Some((suffix > 0 && size > 0).then(|| (size - suffix.min(size), size)));
+    }
+    let first: u64 = first.parse().ok()?;
+    let last = if last.is_empty() { u64::MAX } else { last.parse().ok()? };
+    if last < first {
+        return None;
+    }
+    Some((first < size).then(|| (first, last.saturating_add(1).min(size))))
+}
+
+/// Read the next block of `entry` between `offset` and `end`.
+async fn read_block(
+    reader: Arc<ContentReader>,
+    entry: Arc<VfsEntry>,
+    offset: u64,
+    end: u64,
+) -> Result<Vec<u8>, VfsError> {
+    let size = (end - offset).min(READ_BLOCK);
+    tokio::task::spawn_blocking(move || reader.read(&entry, offset, size))
+        .await
+        .map_err(io::Error::other)?
+}
+
+/// Answer a failed content read, logging why it failed.
+fn read_failed(e: &VfsError) -> Response {
+    eprintln!("Read failed: {}", e);
+    match e {
+        VfsError::RemoteUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+    .into_response()
+}
+
+/// Write a request body to `path`.
+async fn receive(body: Body, path: &Path) -> io::Result<()> {
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut data = body.into_data_stream();
+    while let Some(bytes) = data.next().await {
+        file.write_all(&bytes.map_err(io::Error::other)?).await?;
+    }
+    file.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::OverlayBase;
+    use reqwest::{header::RANGE, Method};
+    use tempfile::tempdir;
+
+    fn binary(size: usize) -> Vec<u8> {
+        (0..size).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_browse_read_ranges_and_upload() {
+        let temp = tempdir().unwrap();
+        let repo = Repository::init(temp.path()).unwrap();
+        // Larger than one read block, so downloads are streamed in steps
+        let clip = binary(READ_BLOCK as usize + 100_000);
+        std::fs::create_dir(temp.path().join("footage")).unwrap();
+        std::fs::write(temp.path().join("footage/take one.bin"), &clip).unwrap();
+        repo.add("footage/take one.bin").unwrap();
+        let commit = repo.commit("footage").unwrap();
+        let manifest = repo.load_manifest(&commit.manifest).unwrap();
+
+        let base = OverlayBase {
+            branch: "main".into(),
+            commit: commit.hash,
+            mount_point: "http://127.0.0.1/".into(),
+        };
+        let overlay = Overlay::create(repo.dits_dir(), &base).unwrap();
+        let object_store = Arc::new(Repository::open(temp.path()).unwrap().into_object_store());
+        let config = CacheConfig {
+            l2_path: temp.path().join("cache"),
+            ..Default::default()
+        };
+        let base_manifest = manifest.clone();
+        let dav = tokio::task::spawn_blocking(move || {
+            WebDav::writable(repo, object_store, overlay, &base_manifest, config)
+        })
+        .await
+        .unwrap()
+        .unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let app = Arc::new(dav).router();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        let client = reqwest::Client::new();
+        let propfind = Method::from_bytes(b"PROPFIND").unwrap();
+
+        let response = client.request(propfind.clone(), format!("{}/", url)).send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::MULTI_STATUS);
+        let listing = response.text().await.unwrap();
+        assert!(listing.contains("<D:href>/footage/</D:href>"));
+        assert!(!listing.contains("take%20one.bin"));
+
+        let response = client
+            .request(propfind, format!("{}/footage/take%20one.bin", url))
+            .header("Depth", "0")
+            .send()
+            .await
+            .unwrap();
+        let listing = response.text().await.unwrap();
+        assert!(listing.contains("<D:href>/footage/take%20one.bin</D:href>"));
+        assert!(listing.contains(&format!("<D:getcontentlength>{}</D:getcontentlength>", clip.len())));
+
+        let file_url = format!("{}/footage/take%20one.bin", url);
+        let response = client.get(&file_url).header(RANGE, "bytes=1000-1999").send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        let content_range = format!("bytes 1000-1999/{}", clip.len());
+        assert_eq!(response.headers()[header::CONTENT_RANGE], content_range.as_str());
+        assert_eq!(response.bytes().await.unwrap(), clip[1000..2000]);
+        let response = client.get(&file_url).send().await.unwrap();
+        assert_eq!(response.bytes().await.unwrap(), clip);
+        let response = client.get(&file_url).header(RANGE, "bytes=9000000-").send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        let new_url = format!("{}/footage/notes.bin", url);
+        let response = client.put(&new_url).body("first").send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let response = client.put(&new_url).body("second").send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(client.get(&new_url).send().await.unwrap().text().await.unwrap(), "second");
+        let response = client.put(format!("{}/missing/notes.bin", url)).body("x").send().await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+
+        let repo = Repository::open(temp.path()).unwrap();
+        let overlay = Overlay::open(repo.dits_dir()).unwrap().unwrap();
+        assert_eq!(overlay.changes(&manifest).added, vec!["footage/notes.bin".to_string()]);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Some((0, 100))));
+        assert_eq!(parse_range("bytes=900-", 1000), Some(Some((900, 1000))));
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Some((900, 1000))));
+        assert_eq!(parse_range("bytes=500-5000", 1000), Some(Some((500, 1000))));
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(None));
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), None);
+        assert_eq!(parse_range("items=0-1", 1000), None);
+    }
+}